@@ -1,22 +1,68 @@
 //! CXP CLI - Build and query CXP files
 //!
 //! Usage:
-//!   cxp build <source-dir> <output.cxp> [--embeddings | --images] [--model <path>]
-//!   cxp info <file.cxp>
-//!   cxp list <file.cxp>
-//!   cxp extract <file.cxp> <file-path> [output]
+//!   cxp build <source-dir> <output.cxp> [--embeddings | --images] [--model <path|name>] [--model-type minilm|bge-small|bge-base|e5-small|e5-base|nomic-embed|embeddinggemma] [--meta KEY=VALUE]... [--chunker gear|buzhash|fixed:<size>] [--dictionary] [--compression zstd:<level>|lz4] [--int8 all|hot|none] [--precision binary|int8|f16|f32] [--dedup-embeddings <bits>] [--batch-size <N>] [--index hnsw|ivf-flat] [--hnsw-m <M>] [--hnsw-ef <N>] [--hnsw-ef-search <N>] [--ivf-nlist <N>] [--ivf-nprobe <N>] [--min-reader-version <x.y.z>] [--git-rev <rev>|<from>..<to>] [--git-history [N] [--git-authors]] [--summaries] [--summarizer-command <cmd>] [--extract-keywords] [--graph] [--symbols] [--no-redact] [--provenance] [--scrub email,phone,iban] [--scrub-name <name>]... [--scrub-allow KIND=VALUE]... [--include <glob>]... [--exclude <glob>]... [--max-file-size <MB>] [--hidden] [--profile <profile> [--tier hot,warm,cold] [--split-tiers]] [--checkpoint <dir>] [--atomic] [--snapshot <label>] [--journal] [--device cpu|cuda[:N]|coreml|directml]
+//!   cxp import <data.jsonl | -> <output.cxp> [--text-field text] [--id-field <field>] [--prefix records] [--extension txt] [--no-redact]
+//!   cxp info <file.cxp> [--tokens] [--tokenizer cl100k|o200k|llama] [--tokenizer-path <path>]
+//!   cxp list <file.cxp> [--long] [--tag <tag>] [--provenance | --summaries | --snapshot <label>]
+//!   cxp snapshots <file.cxp>
+//!   cxp tag <file.cxp> <file-path> [--add <tag>]... [--remove <tag>]... [--note KEY=VALUE]...
+//!   cxp stats <file.cxp> [--json] [--dedup [--min-shared <percent>] [--threshold <percent>] [--min-size <bytes>]]
+//!   cxp usage <file.cxp> [--json] [--top N] [--reset]
+//!   cxp conformance <file.cxp> [--json]
+//!   cxp lint <file.cxp> --policy <policy.toml> [--json]
+//!   cxp publish <file.cxp> --out <bundle-dir> [--title <title>] [--sign-key <key-file>]
+//!   cxp verify-bundle <bundle-dir> [--sign-key <key-file>]
+//!   cxp extract <file.cxp> (<file-path> [output] | --glob <glob> [--dest <dir>] | --regex <re> [--dest <dir>]) [--snapshot <label>]
+//!   cxp export <file.cxp> [--format jsonl|parquet] [--per chunk|file] [--embeddings] [-o <output>]
+//!   cxp unpack <file.cxp> <dest-dir> [--overwrite fail|skip|overwrite|newer] [--no-mtime] [--no-verify] [--snapshot <label>]
+//!   cxp delta <old.cxp> <new.cxp> <patch.cxpd>
+//!   cxp apply <base.cxp> <patch.cxpd> [--output <file.cxp>]
+//!   cxp merge <a.cxp> <b.cxp>... -o <combined.cxp> [--on-conflict first|last|fail|prefix]
+//!   cxp split <big.cxp> -o <parent.cxp> [--by-dir | --by-tier]
+//!   cxp reindex <root.cxp>
+//!   cxp optimize <file.cxp> [-o <output.cxp>] [--keep-dictionary]
+//!   cxp gc <file.cxp> [--keep-last N]
 //!   cxp query <file.cxp> <search-term> [--top-k N]
-//!   cxp search <file.cxp> [<query> | --image <path>] [--top-k N] [--result-type text|image|all] --model <path>
+//!   cxp search <file.cxp> [<query> | --image <path>] [--top-k N] [--result-type text|image|all] [--expand none|synonyms|hyde] [--hyde-command <cmd>] [--mmap] [--max-memory-mb N] [--cluster <id>] --model <path>
+//!   cxp cluster <file.cxp> [-k 20] [--max-iterations N] [--json]
+//!   cxp eval-recall <file.cxp> [--top-k N] [--samples N] [--query <text>... --model <path>]
+//!   cxp graph <file.cxp> <file-path> [--hops N]
+//!   cxp symbols <file.cxp> [--find <name> | --search <text> | --file <path>] [--top-k N]
+//!   cxp history <file.cxp> [<query>] [--file <path>] [--top-k N] [--model <path>] (requires git feature)
+//!   cxp search-all <a.cxp> <b.cxp>... <query> [--top-k N] [--memory-mb 500] [--model <path>] [--keyword] [--json]
 //!   cxp embed-image <image-path> --model <path> [--show-dims N]  (requires multimodal feature)
+//!   cxp models pull <model> [--force] | list | rm <model>  (requires models feature)
+//!   cxp verify-model --model <path> [--engines ort,tract] [--threshold 0.999]
 //!   cxp migrate <sqlite.db> <output.cxp> [--files <source-dir>]
+//!   cxp serve <file.cxp> [--port 8080] [--host 127.0.0.1] [--model <path>] (requires server feature)
+//!   cxp watch <source-dir> <output.cxp> [--embeddings --model <path|name> [--model-type <type>] [--device <device>]] [--include <glob>...] [--exclude <glob>...] [--max-file-size <MB>] [--hidden] [--debounce-ms 500] [--low-priority] [--journal <N>] (requires watch feature)
+//!   cxp push <file.cxp> <s3://bucket/key | gs://bucket/key> [--part-size-mb 8] [--restart] (requires cloud feature)
+//!   cxp pull <s3://bucket/key | gs://bucket/key> <file.cxp> (requires cloud feature)
+//!   cxp self-update [--check] [--to <x.y.z>] [--repo <owner/name>] [--no-verify] (requires self-update feature)
 //!   cxp detect-profile [paths...] (requires scanner feature)
 //!   cxp smart-scan <paths...> [--profile <profile>] (requires scanner feature)
+//!
+//! Global options:
+//!   --temp-dir <dir> | --temp-in-memory   where embedded child archives are staged
+//!   -q, --quiet                            no progress bars, only warnings and errors in the log
+//!   --track-usage                          record anonymous query/read counts next to the archive (opt-in, never sent)
 
 mod migrate;
+mod progress;
+#[cfg(feature = "models")]
+mod models;
+#[cfg(feature = "self-update")]
+mod self_update;
+#[cfg(feature = "server")]
+mod serve;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use cxp_core::{CxpBuilder, CxpReader};
+use cxp_core::{
+    ChunkingAlgorithm, Codec, CxpBuilder, CxpReader, DuplicateOptions, EmbeddingPrecision, IndexBackend, IndexParams, Int8Storage,
+    UsageRecorder,
+};
 use std::io::Write;
 use std::path::PathBuf;
 use std::time::Instant;
@@ -33,9 +79,26 @@ struct Cli {
     /// Verbose output
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// No progress bars; only log warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Directory for temp files of embedded child archives (default: system temp directory)
+    #[arg(long, global = true, value_name = "DIR")]
+    temp_dir: Option<PathBuf>,
+
+    /// Keep temp files of embedded child archives in RAM (/dev/shm) when available
+    #[arg(long, global = true, conflicts_with = "temp_dir")]
+    temp_in_memory: bool,
+
+    /// Record anonymous query and read counts in a local sidecar (never transmitted)
+    #[arg(long, global = true)]
+    track_usage: bool,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Build a CXP file from a directory
     Build {
@@ -53,17 +116,384 @@ enum Commands {
         #[arg(long)]
         images: bool,
 
-        /// Path to embedding model directory (ONNX)
+        /// Path to embedding model directory (ONNX), or the name of a pulled model (`cxp models pull`)
         /// For text: model.onnx + tokenizer.json
         /// For multimodal: image_encoder.onnx + text_encoder.onnx + tokenizer.json
         #[arg(long)]
         model: Option<PathBuf>,
+
+        /// Text embedding model in --model: minilm, bge-small, bge-base, e5-small, e5-base, nomic-embed or embeddinggemma
+        /// (default: the pulled model named by --model, else minilm)
+        #[arg(long)]
+        model_type: Option<String>,
+
+        /// Device the embedding model runs on: cpu, cuda[:N], coreml or directml (falls back to cpu)
+        #[arg(long, default_value = "cpu")]
+        device: String,
+
+        /// Custom manifest metadata (repeatable)
+        #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+        metadata: Vec<(String, String)>,
+
+        /// Chunking algorithm: gear (FastCDC), buzhash or fixed:<size>
+        #[arg(long, default_value = "gear")]
+        chunker: String,
+
+        /// Train a zstd dictionary over the chunks (helps with many small, similar files)
+        #[arg(long)]
+        dictionary: bool,
+
+        /// Chunk compression: zstd, zstd:<level> (1-22) or lz4 (requires lz4 feature)
+        #[arg(long, default_value = "zstd")]
+        compression: String,
+
+        /// Int8 embeddings to store: all, hot (hot-tier files only) or none (binary only)
+        #[arg(long, default_value = "all")]
+        int8: String,
+
+        /// Rescoring vector format: binary (none), int8, f16 or f32 (unquantized)
+        #[arg(long, default_value = "int8")]
+        precision: String,
+
+        /// Share one embedding between chunks within this Hamming distance (semantic dedup)
+        #[arg(long, value_name = "BITS")]
+        dedup_embeddings: Option<u32>,
+
+        /// Texts per embedding batch to start with; halved on out-of-memory, raised while batches succeed (default 32)
+        #[arg(long, value_name = "N")]
+        batch_size: Option<usize>,
+
+        /// Vector index for text embeddings: hnsw, or ivf-flat (much less RAM, slower queries)
+        #[arg(long, default_value = "hnsw")]
+        index: String,
+
+        /// HNSW connections per node (default 16); higher improves recall and costs memory
+        #[arg(long, value_name = "M")]
+        hnsw_m: Option<usize>,
+
+        /// HNSW candidate list size while building (ef_construction, default 128)
+        #[arg(long, value_name = "N")]
+        hnsw_ef: Option<usize>,
+
+        /// HNSW candidate list size while searching (ef_search, default 64)
+        #[arg(long, value_name = "N")]
+        hnsw_ef_search: Option<usize>,
+
+        /// IVF-Flat clusters (default 1024, capped at the number of embeddings)
+        #[arg(long, value_name = "N")]
+        ivf_nlist: Option<usize>,
+
+        /// IVF-Flat clusters scanned per query (default 16); higher improves recall, slows queries
+        #[arg(long, value_name = "N")]
+        ivf_nprobe: Option<usize>,
+
+        /// Refuse to open the archive with CXP readers older than this version
+        #[arg(long, value_name = "VERSION")]
+        min_reader_version: Option<String>,
+
+        /// Keep API keys, tokens and private keys instead of replacing them with placeholders
+        #[arg(long)]
+        no_redact: bool,
+
+        /// Pack a git revision (e.g. v1.0) or only the files touched in a range (e.g. HEAD~5..HEAD)
+        /// instead of the working tree (requires git feature)
+        #[arg(long, value_name = "REV")]
+        git_rev: Option<String>,
+
+        /// Store the last N commits (default 200) with authorship per file, searchable with
+        /// `cxp history` (requires git feature)
+        #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "200")]
+        git_history: Option<usize>,
+
+        /// Keep author names and emails in the stored history (replaced with pseudonyms by default)
+        #[arg(long, requires = "git_history")]
+        git_authors: bool,
+
+        /// Store a short summary of every text file and directory (see `cxp list --summaries`)
+        #[arg(long)]
+        summaries: bool,
+
+        /// Write the summaries with an external command reading a prompt on stdin,
+        /// e.g. "ollama run llama3", split with shell quoting (implies --summaries)
+        #[arg(long, value_name = "CMD")]
+        summarizer_command: Option<String>,
+
+        /// Extract keywords per file and topics of the archive (shown by `cxp info`,
+        /// used by the global index)
+        #[arg(long)]
+        extract_keywords: bool,
+
+        /// Store a knowledge graph of imports and function references between files
+        /// (see `cxp graph`)
+        #[arg(long)]
+        graph: bool,
+
+        /// Index where functions, types and constants are defined (see `cxp symbols`)
+        #[arg(long)]
+        symbols: bool,
+
+        /// Record origin path, git commit and license of every file (see `cxp list --provenance`)
+        #[arg(long)]
+        provenance: bool,
+
+        /// Replace personal data: email, phone and/or iban (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "DETECTORS")]
+        scrub: Vec<String>,
+
+        /// Replace this name wherever it appears (repeatable)
+        #[arg(long = "scrub-name", value_name = "NAME")]
+        scrub_names: Vec<String>,
+
+        /// Keep a value a PII detector would replace, e.g. email=security@acme.dev (repeatable)
+        #[arg(long = "scrub-allow", value_name = "KIND=VALUE", value_parser = parse_key_value)]
+        scrub_allow: Vec<(String, String)>,
+
+        /// Only pack files matching this glob, e.g. 'src/**' (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+
+        /// Skip files and directories matching this glob, e.g. 'target' or '*.lock' (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Skip files larger than this many megabytes
+        #[arg(long, value_name = "MB")]
+        max_file_size: Option<f64>,
+
+        /// Pack hidden files and directories (names starting with '.')
+        #[arg(long)]
+        hidden: bool,
+
+        /// Pack the files a smart scan selects for this profile: developer, writer, ... (requires scanner feature)
+        #[arg(long, conflicts_with = "git_rev")]
+        profile: Option<String>,
+
+        /// Only pack files in these smart-scan tiers: hot, warm and/or cold (comma-separated)
+        #[arg(long = "tier", value_delimiter = ',', requires = "profile")]
+        tiers: Vec<String>,
+
+        /// Store each smart-scan tier as its own child CXP inside the output
+        #[arg(long, requires = "profile")]
+        split_tiers: bool,
+
+        /// Journal progress to this directory; rerunning an interrupted build resumes from it
+        #[arg(long, value_name = "DIR")]
+        checkpoint: Option<PathBuf>,
+
+        /// Write to a temp file and rename it over the output, so a crash never leaves a partial archive
+        #[arg(long)]
+        atomic: bool,
+
+        /// Record the built tree as a snapshot with this label (snapshots already in the output are kept)
+        #[arg(long, value_name = "LABEL", conflicts_with = "split_tiers")]
+        snapshot: Option<String>,
+
+        /// Append what changed since the output was written to its journal instead of rewriting it
+        #[arg(long, conflicts_with_all = ["split_tiers", "snapshot", "atomic"])]
+        journal: bool,
     },
 
     /// Show information about a CXP file
     Info {
         /// CXP file to inspect
         file: PathBuf,
+
+        /// Report token counts and savings
+        #[arg(long)]
+        tokens: bool,
+
+        /// Tokenizer vocabulary for --tokens (cl100k, o200k, llama)
+        #[arg(long, default_value = "cl100k")]
+        tokenizer: String,
+
+        /// tokenizer.json file or directory of vocabularies (requires tokenizer feature)
+        #[arg(long)]
+        tokenizer_path: Option<PathBuf>,
+    },
+
+    /// Show recorded usage metrics (see --track-usage)
+    Usage {
+        /// CXP file whose usage to show
+        file: PathBuf,
+
+        /// Output the metrics as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Number of most used files to list
+        #[arg(long, default_value = "20")]
+        top: usize,
+
+        /// Delete the recorded metrics
+        #[arg(long)]
+        reset: bool,
+    },
+
+    /// Show storage statistics (chunk sizes, dedup, compression, embeddings)
+    Stats {
+        /// CXP file to analyze
+        file: PathBuf,
+
+        /// Output the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Report which files share chunks with which
+        #[arg(long)]
+        dedup: bool,
+
+        /// Minimum share of the smaller file two files must have in common (with --dedup)
+        #[arg(long, default_value = "50")]
+        min_shared: f64,
+
+        /// Minimum similarity of two files' chunk sets in percent to group them as
+        /// near-duplicates (with --dedup)
+        #[arg(long, default_value = "80")]
+        threshold: f64,
+
+        /// Leave files smaller than this many bytes out of near-duplicate groups (with --dedup)
+        #[arg(long, default_value = "1")]
+        min_size: u64,
+    },
+
+    /// Check a CXP file against the format specification
+    Conformance {
+        /// CXP file to check
+        file: PathBuf,
+
+        /// Output the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check a CXP file against an organizational policy (non-zero exit on violations)
+    Lint {
+        /// CXP file to check
+        file: PathBuf,
+
+        /// Policy file (TOML)
+        #[arg(long)]
+        policy: PathBuf,
+
+        /// Output the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Package an archive with a generated README and checksums for hand-off
+    Publish {
+        /// CXP file to publish
+        file: PathBuf,
+
+        /// Bundle directory (created if missing)
+        #[arg(short, long)]
+        out: PathBuf,
+
+        /// README title (default: the archive's file name)
+        #[arg(long)]
+        title: Option<String>,
+
+        /// File with a key shared with recipients for signing the checksums
+        #[arg(long, value_name = "FILE")]
+        sign_key: Option<PathBuf>,
+    },
+
+    /// Check a published bundle's checksums (and signature with --sign-key)
+    VerifyBundle {
+        /// Bundle directory
+        dir: PathBuf,
+
+        /// File with the key the bundle was signed with
+        #[arg(long, value_name = "FILE")]
+        sign_key: Option<PathBuf>,
+    },
+
+    /// Create a delta patch containing only the changes from one archive to another
+    Delta {
+        /// Base (old) CXP file
+        old: PathBuf,
+
+        /// Target (new) CXP file
+        new: PathBuf,
+
+        /// Patch file to write
+        patch: PathBuf,
+    },
+
+    /// Apply a delta patch to a base archive
+    Apply {
+        /// Base CXP file the patch was created against
+        base: PathBuf,
+
+        /// Patch file
+        patch: PathBuf,
+
+        /// Write the result here instead of replacing the base
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Merge several CXP archives into one (chunks are deduplicated across inputs)
+    Merge {
+        /// Archives to merge (in priority order)
+        #[arg(required = true, num_args = 2..)]
+        inputs: Vec<PathBuf>,
+
+        /// Output CXP file path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// How to resolve paths present in several archives: first, last, fail, prefix
+        #[arg(long, default_value = "first")]
+        on_conflict: String,
+    },
+
+    /// Split a CXP archive into a parent pack with embedded child CXPs
+    Split {
+        /// CXP file to split
+        file: PathBuf,
+
+        /// Output parent CXP file path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// One child per top-level directory (default)
+        #[arg(long, conflicts_with = "by_tier")]
+        by_dir: bool,
+
+        /// One child per tier (hot, warm, cold) by file modification time
+        #[arg(long)]
+        by_tier: bool,
+    },
+
+    /// Rebuild the global index of a root CXP from its children
+    Reindex {
+        /// Root CXP file (e.g. master.cxp)
+        file: PathBuf,
+    },
+
+    /// Rewrite an archive to reclaim space left behind by incremental updates
+    Optimize {
+        /// CXP file to optimize
+        file: PathBuf,
+
+        /// Write the optimized archive here instead of replacing the input
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Copy chunks as stored instead of trying a freshly trained dictionary
+        #[arg(long)]
+        keep_dictionary: bool,
+    },
+
+    /// Remove chunks no file map references any more (rewrites the archive)
+    Gc {
+        /// CXP file to collect
+        file: PathBuf,
+
+        /// Keep only the newest N snapshots, dropping older ones first
+        #[arg(long)]
+        keep_last: Option<usize>,
     },
 
     /// List files in a CXP archive
@@ -74,6 +504,71 @@ enum Commands {
         /// Show detailed information
         #[arg(short, long)]
         long: bool,
+
+        /// Only list files carrying this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Show origin path, git commit, modification time and license of each file (built with --provenance)
+        #[arg(long)]
+        provenance: bool,
+
+        /// Show the summary of each file and directory (built with --summaries)
+        #[arg(long, conflicts_with = "provenance")]
+        summaries: bool,
+
+        /// List the files of a snapshot instead of the latest version
+        #[arg(long, conflicts_with_all = ["provenance", "summaries"])]
+        snapshot: Option<String>,
+
+        /// Only list files matching this glob (e.g. 'src/**/*.rs')
+        #[arg(long, conflicts_with = "regex")]
+        glob: Option<String>,
+
+        /// Only list files whose path matches this regular expression
+        #[arg(long)]
+        regex: Option<String>,
+    },
+
+    /// Show the files as a directory tree with per-directory file counts
+    Tree {
+        /// CXP file to show
+        file: PathBuf,
+
+        /// Only descend this many directory levels (deeper ones are summarized)
+        #[arg(long)]
+        depth: Option<usize>,
+
+        /// Show file sizes and aggregate directory sizes
+        #[arg(long)]
+        sizes: bool,
+    },
+
+    /// List the snapshots (earlier versions of the tree) stored in an archive
+    Snapshots {
+        /// CXP file to inspect
+        file: PathBuf,
+    },
+
+    /// Show or change the tags and notes of a file (rewrites the archive's annotations)
+    Tag {
+        /// CXP file to annotate
+        file: PathBuf,
+
+        /// File path inside the archive
+        path: String,
+
+        /// Tag to add (repeatable)
+        #[arg(long)]
+        add: Vec<String>,
+
+        /// Tag to remove (repeatable)
+        #[arg(long)]
+        remove: Vec<String>,
+
+        /// Note to set, an empty value removes it (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_key_value)]
+        note: Vec<(String, String)>,
     },
 
     /// Extract a file from a CXP archive
@@ -82,12 +577,77 @@ enum Commands {
         file: PathBuf,
 
         /// Path of file to extract (within the CXP)
-        path: String,
+        #[arg(required_unless_present_any = ["glob", "regex"])]
+        path: Option<String>,
 
         /// Output path (default: stdout)
+        #[arg(conflicts_with_all = ["glob", "regex"])]
+        output: Option<PathBuf>,
+
+        /// Extract the file as it was in this snapshot
+        #[arg(long)]
+        snapshot: Option<String>,
+
+        /// Extract every file matching this glob (e.g. 'src/**/*.rs') into --dest
+        #[arg(long, conflicts_with_all = ["path", "regex"])]
+        glob: Option<String>,
+
+        /// Extract every file whose path matches this regular expression into --dest
+        #[arg(long, conflicts_with = "path")]
+        regex: Option<String>,
+
+        /// Destination directory for --glob/--regex (created if missing, default: .)
+        #[arg(long, conflicts_with = "path")]
+        dest: Option<PathBuf>,
+    },
+
+    /// Export chunks or files as records for data pipelines (JSON Lines or Parquet)
+    Export {
+        /// CXP file
+        file: PathBuf,
+
+        /// Output format: jsonl or parquet (requires arrow feature)
+        #[arg(long, default_value = "jsonl")]
+        format: String,
+
+        /// One record per chunk or per file
+        #[arg(long, default_value = "chunk")]
+        per: String,
+
+        /// Include embeddings (requires embeddings and search features)
+        #[arg(long)]
+        embeddings: bool,
+
+        /// Output file (default: stdout, jsonl only)
+        #[arg(short, long)]
         output: Option<PathBuf>,
     },
 
+    /// Restore all files of a CXP archive into a directory
+    Unpack {
+        /// CXP file
+        file: PathBuf,
+
+        /// Destination directory (created if missing)
+        dest: PathBuf,
+
+        /// Existing files: fail, skip, overwrite or newer (only if the archived copy is newer)
+        #[arg(long, default_value = "fail")]
+        overwrite: String,
+
+        /// Do not restore modification times
+        #[arg(long)]
+        no_mtime: bool,
+
+        /// Skip checking chunks against their hashes
+        #[arg(long)]
+        no_verify: bool,
+
+        /// Unpack the files of this snapshot instead of the latest version
+        #[arg(long)]
+        snapshot: Option<String>,
+    },
+
     /// Query files in a CXP archive (keyword search)
     Query {
         /// CXP file to query
@@ -103,6 +663,14 @@ enum Commands {
         /// Case insensitive search
         #[arg(short = 'i', long)]
         ignore_case: bool,
+
+        /// Only search files matching this glob (e.g. 'src/**/*.rs')
+        #[arg(long, conflicts_with = "regex")]
+        glob: Option<String>,
+
+        /// Only search files whose path matches this regular expression
+        #[arg(long)]
+        regex: Option<String>,
     },
 
     /// Semantic search in a CXP archive (requires embeddings)
@@ -130,87 +698,574 @@ enum Commands {
         /// Use an image as the search query (requires multimodal feature)
         #[arg(long)]
         image: Option<PathBuf>,
-    },
-
-    /// Migrate a SQLite database to CXP format
-    Migrate {
-        /// SQLite database file to migrate
-        sqlite: PathBuf,
 
-        /// Output CXP file path
-        output: PathBuf,
+        /// Expand the query before embedding (none, synonyms, hyde)
+        #[arg(long, default_value = "none")]
+        expand: String,
 
-        /// Optional source files directory to include
+        /// Command generating HyDE documents from a prompt on stdin
+        /// (falls back to CXP_HYDE_COMMAND, then a built-in template)
         #[arg(long)]
-        files: Option<PathBuf>,
-    },
+        hyde_command: Option<String>,
 
-    /// Generate and display embedding for an image (debugging)
-    #[cfg(all(feature = "multimodal", feature = "search"))]
-    EmbedImage {
-        /// Path to the image file
-        image: PathBuf,
+        /// Memory-map binary embeddings and read rescoring vectors from disk
+        #[arg(long)]
+        mmap: bool,
 
-        /// Path to SigLIP 2 model directory
+        /// RAM limit for embedding vectors (MB); larger parts are mapped or read from disk
         #[arg(long)]
-        model: PathBuf,
+        max_memory_mb: Option<usize>,
 
-        /// Show first N dimensions (default: 10)
-        #[arg(long, default_value = "10")]
-        show_dims: usize,
+        /// Only search the chunks of this cluster (see `cxp cluster`)
+        #[arg(long, conflicts_with = "image")]
+        cluster: Option<usize>,
     },
 
-    /// Detect user profile based on file types (Developer, Photographer, Designer, etc.)
-    #[cfg(feature = "scanner")]
-    DetectProfile {
-        /// Paths to scan (default: ~/Documents, ~/Desktop, ~/Downloads)
-        paths: Vec<PathBuf>,
-    },
+    /// Cluster chunk embeddings by topic and store the topic map in the archive
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    Cluster {
+        /// CXP file to cluster (rewritten in place)
+        file: PathBuf,
 
-    /// Smart scan directories with profile-based filtering
-    #[cfg(feature = "scanner")]
-    SmartScan {
-        /// Paths to scan
-        paths: Vec<PathBuf>,
+        /// Number of clusters
+        #[arg(short = 'k', long, default_value = "20")]
+        k: usize,
 
-        /// User profile to use (developer, photographer, designer, writer, student, business)
-        #[arg(long, short)]
-        profile: Option<String>,
+        /// Maximum k-means iterations
+        #[arg(long, default_value = "50")]
+        max_iterations: usize,
 
-        /// Output detailed information
+        /// Output the topic map as JSON
         #[arg(long)]
-        detailed: bool,
+        json: bool,
     },
-}
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+    /// Measure how many exact nearest neighbours the HNSW search finds (recall@k)
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    EvalRecall {
+        /// CXP file to evaluate
+        file: PathBuf,
 
-    // Setup logging
+        /// Results compared per query
+        #[arg(short = 'k', long, default_value = "10")]
+        top_k: usize,
+
+        /// Stored embeddings to use as queries when no --query is given
+        #[arg(long, default_value = "100")]
+        samples: usize,
+
+        /// Query text to evaluate (repeatable, requires --model)
+        #[arg(long = "query", value_name = "TEXT")]
+        queries: Vec<String>,
+
+        /// Embedding model directory or name for --query
+        #[arg(long)]
+        model: Option<PathBuf>,
+    },
+
+    /// Show the files and functions linked to a file in the knowledge graph
+    /// (built with `cxp build --graph`)
+    Graph {
+        /// CXP file to inspect
+        file: PathBuf,
+
+        /// File path inside the archive
+        path: String,
+
+        /// Number of edges to follow from the file
+        #[arg(long, default_value = "1")]
+        hops: usize,
+    },
+
+    /// Find where functions and types are defined (built with `cxp build --symbols`)
+    Symbols {
+        /// CXP file to inspect
+        file: PathBuf,
+
+        /// Exact symbol name (e.g. ChunkStore)
+        #[arg(long, conflicts_with_all = ["search", "file_path"])]
+        find: Option<String>,
+
+        /// Symbols whose name contains this text (case-insensitive)
+        #[arg(long, conflicts_with = "file_path")]
+        search: Option<String>,
+
+        /// List the symbols defined in one file
+        #[arg(long = "file", value_name = "PATH")]
+        file_path: Option<String>,
+
+        /// Number of symbols to show
+        #[arg(short = 'k', long, default_value = "50")]
+        top_k: usize,
+    },
+
+    /// Show or search the git history stored with `cxp build --git-history`
+    #[cfg(feature = "git")]
+    History {
+        /// CXP file to inspect
+        file: PathBuf,
+
+        /// Search commit messages (e.g. "why was the retry logic changed")
+        query: Option<String>,
+
+        /// Show who changed this file and the commits touching it
+        #[arg(long = "file", value_name = "PATH")]
+        file_path: Option<String>,
+
+        /// Number of commits to show
+        #[arg(short = 'k', long, default_value = "10")]
+        top_k: usize,
+
+        /// Embedding model directory for semantic search over commit messages
+        #[arg(long)]
+        model: Option<PathBuf>,
+    },
+
+    /// Search several CXP archives at once and merge the ranked results
+    SearchAll {
+        /// Archives followed by the query (e.g. ~/contexts/*.cxp "where is the retry logic")
+        #[arg(required = true, num_args = 2.., value_name = "ARCHIVES... QUERY")]
+        args: Vec<String>,
+
+        /// Number of results
+        #[arg(short = 'k', long, default_value = "10")]
+        top_k: usize,
+
+        /// Memory limit for archives open at the same time (MB)
+        #[arg(long, default_value = "500")]
+        memory_mb: u64,
+
+        /// Embedding model directory for semantic search (default: $CXP_MODEL_DIR)
+        #[arg(long)]
+        model: Option<PathBuf>,
+
+        /// Use keyword search even if archives have embeddings
+        #[arg(long)]
+        keyword: bool,
+
+        /// Output the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Build a CXP file from JSON Lines text records (one object per line)
+    Import {
+        /// JSON Lines file (`-` reads stdin)
+        input: PathBuf,
+
+        /// Output CXP file path
+        output: PathBuf,
+
+        /// Field holding the text of each record
+        #[arg(long, default_value = "text")]
+        text_field: String,
+
+        /// Field naming each record's file (default: line number)
+        #[arg(long)]
+        id_field: Option<String>,
+
+        /// Directory records are stored under
+        #[arg(long, default_value = "records")]
+        prefix: String,
+
+        /// File extension of records
+        #[arg(long, default_value = "txt")]
+        extension: String,
+
+        /// Keep secrets instead of redacting them
+        #[arg(long)]
+        no_redact: bool,
+    },
+
+    /// Migrate a SQLite database to CXP format
+    Migrate {
+        /// SQLite database file to migrate
+        sqlite: PathBuf,
+
+        /// Output CXP file path
+        output: PathBuf,
+
+        /// Optional source files directory to include
+        #[arg(long)]
+        files: Option<PathBuf>,
+    },
+
+    /// Generate and display embedding for an image (debugging)
+    #[cfg(all(feature = "multimodal", feature = "search"))]
+    EmbedImage {
+        /// Path to the image file
+        image: PathBuf,
+
+        /// Path to SigLIP 2 model directory
+        #[arg(long)]
+        model: PathBuf,
+
+        /// Show first N dimensions (default: 10)
+        #[arg(long, default_value = "10")]
+        show_dims: usize,
+    },
+
+    /// Serve a CXP archive over a local HTTP API (requires server feature)
+    #[cfg(feature = "server")]
+    Serve {
+        /// CXP file to serve
+        file: PathBuf,
+
+        /// Port to listen on
+        #[arg(long, default_value = "8080")]
+        port: u16,
+
+        /// Address to bind
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Embedding model directory for /search (requires embeddings and search features)
+        #[arg(long)]
+        model: Option<PathBuf>,
+    },
+
+    /// Keep a CXP file in sync with a directory, rebuilding incrementally on change
+    #[cfg(feature = "watch")]
+    Watch {
+        /// Source directory to watch
+        source: PathBuf,
+
+        /// Output CXP file path
+        output: PathBuf,
+
+        /// Generate embeddings (only new chunks are embedded on each update)
+        #[arg(long)]
+        embeddings: bool,
+
+        /// Path to embedding model directory (ONNX), or the name of a pulled model (`cxp models pull`)
+        #[arg(long)]
+        model: Option<PathBuf>,
+
+        /// Text embedding model in --model: minilm, bge-small, bge-base, e5-small, e5-base, nomic-embed or embeddinggemma
+        /// (default: the pulled model named by --model, else minilm)
+        #[arg(long)]
+        model_type: Option<String>,
+
+        /// Device the embedding model runs on: cpu, cuda[:N], coreml or directml (falls back to cpu)
+        #[arg(long, default_value = "cpu")]
+        device: String,
+
+        /// Only pack files matching this glob, e.g. 'src/**' (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+
+        /// Skip files and directories matching this glob, e.g. 'target' or '*.lock' (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Skip files larger than this many megabytes
+        #[arg(long, value_name = "MB")]
+        max_file_size: Option<f64>,
+
+        /// Pack hidden files and directories (names starting with '.')
+        #[arg(long)]
+        hidden: bool,
+
+        /// Quiet period after the last change before rebuilding (milliseconds)
+        #[arg(long, default_value = "500")]
+        debounce_ms: u64,
+
+        /// Rebuild at idle CPU/IO priority and pause while on battery
+        #[arg(long)]
+        low_priority: bool,
+
+        /// Append updates to the archive's journal, rewriting it after N journaled updates
+        #[arg(long, value_name = "N")]
+        journal: Option<u64>,
+    },
+
+    /// Upload a CXP file to S3 or GCS (interrupted uploads resume)
+    #[cfg(feature = "cloud")]
+    Push {
+        /// CXP file to upload
+        file: PathBuf,
+
+        /// Destination URL (s3://bucket/key or gs://bucket/key)
+        url: String,
+
+        /// Multipart upload part size in MiB (at least 5 for S3)
+        #[arg(long, default_value = "8")]
+        part_size_mb: usize,
+
+        /// Discard an interrupted upload instead of resuming it
+        #[arg(long)]
+        restart: bool,
+    },
+
+    /// Download a CXP file from S3 or GCS
+    #[cfg(feature = "cloud")]
+    Pull {
+        /// Source URL (s3://bucket/key or gs://bucket/key)
+        url: String,
+
+        /// Output CXP file path
+        output: PathBuf,
+    },
+
+    /// Update this binary to the latest (or a pinned) release
+    #[cfg(feature = "self-update")]
+    SelfUpdate {
+        /// Only report whether an update is available
+        #[arg(long)]
+        check: bool,
+
+        /// Install this release instead of the latest (allows downgrades)
+        #[arg(long, value_name = "VERSION")]
+        to: Option<String>,
+
+        /// GitHub repository to update from (default: the one this binary was built from)
+        #[arg(long, value_name = "OWNER/NAME")]
+        repo: Option<String>,
+
+        /// Install a release that publishes no checksum for this platform
+        #[arg(long)]
+        no_verify: bool,
+    },
+
+    /// Download and manage embedding models from Hugging Face
+    #[cfg(feature = "models")]
+    Models {
+        #[command(subcommand)]
+        command: ModelsCommand,
+    },
+
+    /// Compare embedding engines on a fixed probe set (detects preprocessing drift)
+    #[cfg(any(feature = "embeddings", feature = "embeddings-wasm"))]
+    VerifyModel {
+        /// Path to embedding model directory (model.onnx + tokenizer.json)
+        #[arg(long)]
+        model: PathBuf,
+
+        /// Engines to compare (comma-separated: ort, tract)
+        #[arg(long, value_delimiter = ',', default_value = "ort,tract")]
+        engines: Vec<String>,
+
+        /// Minimum cosine similarity required between engines
+        #[arg(long, default_value = "0.999")]
+        threshold: f32,
+    },
+
+    /// Detect user profile based on file types (Developer, Photographer, Designer, etc.)
+    #[cfg(feature = "scanner")]
+    DetectProfile {
+        /// Paths to scan (default: ~/Documents, ~/Desktop, ~/Downloads)
+        paths: Vec<PathBuf>,
+    },
+
+    /// Smart scan directories with profile-based filtering
+    #[cfg(feature = "scanner")]
+    SmartScan {
+        /// Paths to scan
+        paths: Vec<PathBuf>,
+
+        /// User profile to use (developer, photographer, designer, writer, student, business)
+        #[arg(long, short)]
+        profile: Option<String>,
+
+        /// Output detailed information
+        #[arg(long)]
+        detailed: bool,
+    },
+}
+
+/// `cxp models` subcommands
+#[cfg(feature = "models")]
+#[derive(Subcommand)]
+enum ModelsCommand {
+    /// Download a model (minilm, bge-small, bge-base, e5-small, e5-base, nomic-embed)
+    Pull {
+        /// Model name
+        model: String,
+
+        /// Download again even if the model is installed and intact
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// List registered models and which are installed
+    List,
+
+    /// Delete a downloaded model
+    Rm {
+        /// Model name
+        model: String,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    // Setup logging
     let filter = if cli.verbose {
         EnvFilter::new("debug")
+    } else if cli.quiet {
+        EnvFilter::new("warn")
     } else {
         EnvFilter::new("info")
     };
 
+    // Logs go to stderr so `extract` and `export` can stream data to stdout
     tracing_subscriber::fmt()
         .with_env_filter(filter)
         .with_target(false)
+        .with_writer(|| progress::LogWriter)
         .init();
 
+    #[cfg(any(feature = "server", all(feature = "embeddings", feature = "search")))]
+    let temp_policy = cxp_core::TempPolicy::from_options(cli.temp_dir, cli.temp_in_memory);
+    let track_usage = cli.track_usage;
+    let show_progress = !cli.quiet;
+
     match cli.command {
-        Commands::Build { source, output, embeddings, images, model } => {
-            build_cxp(&source, &output, embeddings, images, model.as_deref())
+        Commands::Build { source, output, embeddings, images, model, model_type, device, metadata, chunker, dictionary, compression, int8, precision, dedup_embeddings, batch_size, index, hnsw_m, hnsw_ef, hnsw_ef_search, ivf_nlist, ivf_nprobe, min_reader_version, git_rev, git_history, git_authors, summaries, summarizer_command, extract_keywords, graph, symbols, no_redact, provenance, scrub, scrub_names, scrub_allow, include, exclude, max_file_size, hidden, profile, tiers, split_tiers, checkpoint, atomic, snapshot, journal } => {
+            let scrub = ScrubArgs { detectors: scrub, names: scrub_names, allow: scrub_allow };
+            let plan = PlanArgs { profile, tiers, split: split_tiers };
+            let mut filter = cxp_core::ScanFilter::new()
+                .with_include(include)
+                .with_exclude(exclude)
+                .with_skip_hidden(!hidden);
+            if let Some(mb) = max_file_size {
+                filter = filter.with_max_file_size((mb * 1024.0 * 1024.0) as u64);
+            }
+            let model_type = model_type.unwrap_or_else(|| default_model_type(model.as_deref()));
+            let model = model.map(model_dir).transpose()?;
+            let index_params = (hnsw_m.is_some() || hnsw_ef.is_some() || hnsw_ef_search.is_some()).then(|| {
+                let defaults = IndexParams::default();
+                IndexParams {
+                    m: hnsw_m.unwrap_or(defaults.m),
+                    ef_construction: hnsw_ef.unwrap_or(defaults.ef_construction),
+                    ef_search: hnsw_ef_search.unwrap_or(defaults.ef_search),
+                }
+            });
+            let index_backend = match index.parse()? {
+                IndexBackend::IvfFlat { nlist, nprobe } => IndexBackend::IvfFlat {
+                    nlist: ivf_nlist.unwrap_or(nlist),
+                    nprobe: ivf_nprobe.unwrap_or(nprobe),
+                },
+                IndexBackend::Hnsw if ivf_nlist.is_some() || ivf_nprobe.is_some() => {
+                    return Err(anyhow::anyhow!("--ivf-nlist and --ivf-nprobe need --index ivf-flat"));
+                }
+                backend => backend,
+            };
+            build_cxp(&source, &output, embeddings, images, model.as_deref(), &model_type, &device, &metadata, &chunker, dictionary, &compression, &int8, &precision, dedup_embeddings, batch_size, index_backend, index_params, min_reader_version.as_deref(), git_rev.as_deref(), git_history, git_authors, summaries || summarizer_command.is_some(), summarizer_command.as_deref(), extract_keywords, graph, symbols, !no_redact, provenance, &scrub, filter, &plan, checkpoint.as_deref(), atomic, snapshot.as_deref(), journal, show_progress)
         }
-        Commands::Info { file } => show_info(&file),
-        Commands::List { file, long } => list_files(&file, long),
-        Commands::Extract { file, path, output } => extract_file(&file, &path, output.as_deref()),
-        Commands::Query { file, query, top_k, ignore_case } => {
-            query_files(&file, &query, top_k, ignore_case)
+        Commands::Info { file, tokens, tokenizer, tokenizer_path } => {
+            show_info(&file)?;
+            if tokens {
+                show_token_info(&file, &tokenizer, tokenizer_path.as_deref())?;
+            }
+            Ok(())
+        }
+        Commands::Stats { file, json, dedup, min_shared, threshold, min_size } => {
+            if dedup {
+                show_duplicates(&file, json, min_shared, threshold, min_size)
+            } else {
+                show_stats(&file, json)
+            }
+        }
+        Commands::Conformance { file, json } => conformance_command(&file, json),
+        Commands::Lint { file, policy, json } => lint_command(&file, &policy, json),
+        Commands::Publish { file, out, title, sign_key } => {
+            publish_command(&file, &out, title, sign_key.as_deref())
+        }
+        Commands::VerifyBundle { dir, sign_key } => verify_bundle_command(&dir, sign_key.as_deref()),
+        Commands::Delta { old, new, patch } => delta_command(&old, &new, &patch),
+        Commands::Apply { base, patch, output } => apply_command(&base, &patch, output.as_deref()),
+        Commands::Merge { inputs, output, on_conflict } => merge_command(&inputs, &output, &on_conflict),
+        Commands::Split { file, output, by_dir: _, by_tier } => split_command(&file, &output, by_tier),
+        Commands::Reindex { file } => reindex_command(&file),
+        Commands::Optimize { file, output, keep_dictionary } => {
+            optimize_command(&file, output.as_deref(), keep_dictionary)
+        }
+        Commands::Gc { file, keep_last } => gc_command(&file, keep_last),
+        Commands::List { file, long, tag, provenance, summaries, snapshot, glob, regex } => {
+            let pattern = path_pattern(glob.as_deref(), regex.as_deref())?;
+            if provenance {
+                list_provenance(&file, tag.as_deref(), pattern.as_ref())
+            } else if summaries {
+                list_summaries(&file, tag.as_deref(), pattern.as_ref())
+            } else {
+                list_files(&file, long, tag.as_deref(), snapshot.as_deref(), pattern.as_ref())
+            }
+        }
+        Commands::Tree { file, depth, sizes } => tree_command(&file, depth, sizes),
+        Commands::Snapshots { file } => snapshots_command(&file),
+        Commands::Tag { file, path, add, remove, note } => tag_command(&file, &path, &add, &remove, &note),
+        Commands::Extract { file, path, output, snapshot, glob, regex, dest } => {
+            match (path, path_pattern(glob.as_deref(), regex.as_deref())?) {
+                (_, Some(pattern)) => {
+                    let dest = dest.unwrap_or_else(|| PathBuf::from("."));
+                    extract_matching(&file, pattern, &dest, snapshot.as_deref())
+                }
+                (Some(path), None) => extract_file(&file, &path, output.as_deref(), snapshot.as_deref(), track_usage),
+                (None, None) => Err(anyhow::anyhow!("Give a path to extract, or --glob/--regex")),
+            }
+        }
+        Commands::Export { file, format, per, embeddings, output } => {
+            export_command(&file, &format, &per, embeddings, output.as_deref())
+        }
+        Commands::Unpack { file, dest, overwrite, no_mtime, no_verify, snapshot } => {
+            unpack_command(&file, &dest, &overwrite, !no_mtime, !no_verify, snapshot.as_deref())
+        }
+        Commands::Query { file, query, top_k, ignore_case, glob, regex } => {
+            let pattern = path_pattern(glob.as_deref(), regex.as_deref())?;
+            query_files(&file, &query, top_k, ignore_case, pattern.as_ref(), track_usage)
+        }
+        Commands::Usage { file, json, top, reset } => usage_command(&file, json, top, reset),
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        Commands::Search { file, query, top_k, model, result_type, image, expand, hyde_command, mmap, max_memory_mb, cluster } => {
+            let model = model.map(model_dir).transpose()?;
+            let mut load_options = cxp_core::LoadOptions::new().with_mmap(mmap).with_int8_lazy(mmap);
+            if let Some(mb) = max_memory_mb {
+                load_options = load_options.with_max_memory(mb * 1024 * 1024);
+            }
+            search_semantic(
+                &file,
+                query.as_deref(),
+                top_k,
+                model.as_deref(),
+                &result_type,
+                image.as_deref(),
+                &expand,
+                hyde_command.as_deref(),
+                cluster,
+                load_options,
+                &temp_policy,
+                track_usage,
+            )
         }
         #[cfg(all(feature = "embeddings", feature = "search"))]
-        Commands::Search { file, query, top_k, model, result_type, image } => {
-            search_semantic(&file, query.as_deref(), top_k, model.as_deref(), &result_type, image.as_deref())
+        Commands::Cluster { file, k, max_iterations, json } => cluster_command(&file, k, max_iterations, json),
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        Commands::EvalRecall { file, top_k, samples, queries, model } => {
+            eval_recall_command(&file, top_k, samples, &queries, model.map(model_dir).transpose()?.as_deref())
+        }
+        Commands::Graph { file, path, hops } => graph_command(&file, &path, hops),
+        Commands::Symbols { file, find, search, file_path, top_k } => {
+            symbols_command(&file, find.as_deref(), search.as_deref(), file_path.as_deref(), top_k)
+        }
+        #[cfg(feature = "git")]
+        Commands::History { file, query, file_path, top_k, model } => {
+            history_command(&file, query.as_deref(), file_path.as_deref(), top_k, model.map(model_dir).transpose()?.as_deref())
+        }
+        Commands::SearchAll { args, top_k, memory_mb, model, keyword, json } => {
+            search_all_command(&args, top_k, memory_mb, model.map(model_dir).transpose()?, keyword, json)
+        }
+        Commands::Import { input, output, text_field, id_field, prefix, extension, no_redact } => {
+            let options = cxp_core::ImportOptions::new()
+                .with_text_field(text_field)
+                .with_prefix(prefix)
+                .with_extension(extension);
+            let options = match id_field {
+                Some(field) => options.with_id_field(field),
+                None => options,
+            };
+            import_command(&input, &output, options, !no_redact, show_progress)
         }
         Commands::Migrate { sqlite, output, files } => {
             migrate::migrate_sqlite_to_cxp(&sqlite, &output, files.as_deref())
@@ -219,6 +1274,41 @@ fn main() -> Result<()> {
         Commands::EmbedImage { image, model, show_dims } => {
             embed_image_command(&image, &model, show_dims)
         }
+        #[cfg(feature = "server")]
+        Commands::Serve { file, port, host, model } => {
+            serve::serve(&file, &host, port, model.map(model_dir).transpose()?.as_deref(), &temp_policy)
+        }
+        #[cfg(feature = "watch")]
+        Commands::Watch { source, output, embeddings, model, model_type, device, include, exclude, max_file_size, hidden, debounce_ms, low_priority, journal } => {
+            let mut filter = cxp_core::ScanFilter::new()
+                .with_include(include)
+                .with_exclude(exclude)
+                .with_skip_hidden(!hidden);
+            if let Some(mb) = max_file_size {
+                filter = filter.with_max_file_size((mb * 1024.0 * 1024.0) as u64);
+            }
+            let model_type = model_type.unwrap_or_else(|| default_model_type(model.as_deref()));
+            let model = model.map(model_dir).transpose()?;
+            watch_command(&source, &output, embeddings, model.as_deref(), &model_type, &device, filter, debounce_ms, low_priority, journal)
+        }
+        #[cfg(feature = "cloud")]
+        Commands::Push { file, url, part_size_mb, restart } => push_command(&file, &url, part_size_mb, restart),
+        #[cfg(feature = "cloud")]
+        Commands::Pull { url, output } => pull_command(&url, &output),
+        #[cfg(feature = "self-update")]
+        Commands::SelfUpdate { check, to, repo, no_verify } => {
+            self_update::self_update(repo.as_deref(), to.as_deref(), check, no_verify)
+        }
+        #[cfg(feature = "models")]
+        Commands::Models { command } => match command {
+            ModelsCommand::Pull { model, force } => models::pull(&model, force),
+            ModelsCommand::List => models::list(),
+            ModelsCommand::Rm { model } => models::remove(&model),
+        },
+        #[cfg(any(feature = "embeddings", feature = "embeddings-wasm"))]
+        Commands::VerifyModel { model, engines, threshold } => {
+            verify_model_command(&model_dir(model)?, &engines, threshold)
+        }
         #[cfg(feature = "scanner")]
         Commands::DetectProfile { paths } => {
             detect_profile_command(paths)
@@ -230,6 +1320,102 @@ fn main() -> Result<()> {
     }
 }
 
+/// Profile-aware (smart-scan) options of `cxp build`
+struct PlanArgs {
+    profile: Option<String>,
+    #[cfg_attr(not(feature = "scanner"), allow(dead_code))]
+    tiers: Vec<String>,
+    split: bool,
+}
+
+/// PII scrubbing options of `cxp build`
+struct ScrubArgs {
+    detectors: Vec<String>,
+    names: Vec<String>,
+    allow: Vec<(String, String)>,
+}
+
+impl ScrubArgs {
+    #[cfg(not(feature = "redact"))]
+    fn is_empty(&self) -> bool {
+        self.detectors.is_empty() && self.names.is_empty() && self.allow.is_empty()
+    }
+
+    /// Detectors with their allowlists applied
+    #[cfg(feature = "redact")]
+    fn scrubbers(&self) -> Result<Vec<cxp_core::RegexScrubber>> {
+        use cxp_core::RegexScrubber;
+
+        let mut scrubbers = self
+            .detectors
+            .iter()
+            .filter(|name| !name.trim().is_empty())
+            .map(|name| RegexScrubber::preset(name))
+            .collect::<cxp_core::Result<Vec<_>>>()?;
+        if !self.names.is_empty() {
+            scrubbers.push(RegexScrubber::names(&self.names)?);
+        }
+
+        if let Some((kind, _)) = self.allow.iter().find(|(kind, _)| !scrubbers.iter().any(|s| s.kind() == kind)) {
+            return Err(anyhow::anyhow!("--scrub-allow {}=...: no '{}' detector is enabled", kind, kind));
+        }
+        let scrubbers = scrubbers
+            .into_iter()
+            .map(|scrubber| {
+                let kind = scrubber.kind().to_string();
+                self.allow
+                    .iter()
+                    .filter(|(allow_kind, _)| *allow_kind == kind)
+                    .fold(scrubber, |scrubber, (_, value)| scrubber.with_allowed(value))
+            })
+            .collect();
+        Ok(scrubbers)
+    }
+}
+
+/// `--model` as a directory: a path, or the name of a model pulled with `cxp models pull`
+fn model_dir(model: PathBuf) -> Result<PathBuf> {
+    #[cfg(feature = "models")]
+    return models::resolve(&model);
+    #[cfg(not(feature = "models"))]
+    Ok(model)
+}
+
+/// Model type for a build without `--model-type`: the pulled model `--model` names, else MiniLM
+#[allow(unused_variables)]
+fn default_model_type(model: Option<&std::path::Path>) -> String {
+    #[cfg(feature = "models")]
+    if let Some(key) = model.and_then(models::registered_key) {
+        return key.to_string();
+    }
+    "minilm".to_string()
+}
+
+/// Load the `--model` embedding engine for `--model-type` on `--device`
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn load_embedding_engine(
+    model: Option<&std::path::Path>,
+    model_type: &str,
+    device: &str,
+) -> Result<cxp_core::EmbeddingEngine> {
+    use cxp_core::{Device, EmbeddingEngine, EmbeddingModel};
+
+    let model_path = model.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Model path is required for embeddings. Use --model <path> to specify the model directory."
+        )
+    })?;
+
+    let model_type: EmbeddingModel = model_type.parse().map_err(anyhow::Error::msg)?;
+    let device: Device = device.parse()?;
+    let engine = EmbeddingEngine::load_with(model_path, model_type, device)
+        .context("Failed to initialize embeddings")?;
+    println!("  Embedding model: {} ({})", model_type.name(), model_type.spec().id);
+    println!("  Embedding device: {}", engine.device());
+    Ok(engine)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn build_cxp(
     source: &PathBuf,
     output: &PathBuf,
@@ -238,219 +1424,1903 @@ fn build_cxp(
     images: bool,
     #[allow(unused_variables)]
     model: Option<&std::path::Path>,
+    #[allow(unused_variables)]
+    model_type: &str,
+    #[allow(unused_variables)]
+    device: &str,
+    metadata: &[(String, String)],
+    chunker: &str,
+    dictionary: bool,
+    compression: &str,
+    int8: &str,
+    precision: &str,
+    dedup_embeddings: Option<u32>,
+    batch_size: Option<usize>,
+    index_backend: IndexBackend,
+    index_params: Option<IndexParams>,
+    min_reader_version: Option<&str>,
+    git_rev: Option<&str>,
+    git_history: Option<usize>,
+    #[allow(unused_variables)]
+    git_authors: bool,
+    summaries: bool,
+    summarizer_command: Option<&str>,
+    extract_keywords: bool,
+    graph: bool,
+    symbols: bool,
+    #[allow(unused_variables)]
+    redact: bool,
+    provenance: bool,
+    scrub: &ScrubArgs,
+    filter: cxp_core::ScanFilter,
+    plan: &PlanArgs,
+    checkpoint: Option<&std::path::Path>,
+    atomic: bool,
+    snapshot: Option<&str>,
+    journal: bool,
+    show_progress: bool,
+) -> Result<()> {
+    let chunking: ChunkingAlgorithm = chunker.parse()?;
+    let codec: Codec = compression.parse()?;
+    let int8_storage: Int8Storage = int8.parse()?;
+    let precision: EmbeddingPrecision = precision.parse()?;
+    #[cfg(feature = "redact")]
+    let scrubbers = scrub.scrubbers()?;
+    #[cfg(not(feature = "redact"))]
+    if !scrub.is_empty() {
+        return Err(anyhow::anyhow!(
+            "PII scrubbing is not enabled. Rebuild cxp-cli with --features redact"
+        ));
+    }
+
+    println!("Building CXP file...");
+    println!("  Source: {}", source.display());
+    if let Some(rev) = git_rev {
+        println!("  Git: {}", rev);
+    }
+    if let Some(depth) = git_history {
+        println!("  Git history: last {} commits{}", depth, if git_authors { " with authors" } else { "" });
+    }
+    if summaries {
+        println!("  Summaries: {}", summarizer_command.unwrap_or("heuristic"));
+    }
+    if extract_keywords {
+        println!("  Keywords: per file, topics per archive");
+    }
+    if graph {
+        println!("  Graph: imports, symbols");
+    }
+    if symbols {
+        println!("  Symbols: definitions per file");
+    }
+    println!("  Output: {}", output.display());
+    println!("  Chunker: {}", chunking);
+    println!("  Compression: {}", codec);
+    #[cfg(feature = "redact")]
+    println!("  Redaction: {}", if redact { "enabled" } else { "disabled" });
+    #[cfg(feature = "redact")]
+    if !scrubbers.is_empty() {
+        let kinds: Vec<&str> = scrubbers.iter().map(|s| s.kind()).collect();
+        println!("  PII scrubbing: {}", kinds.join(", "));
+    }
+
+    // Check for incompatible feature combinations
+    if images && embeddings {
+        return Err(anyhow::anyhow!(
+            "Cannot use both --embeddings and --images. Use --images with a multimodal model instead."
+        ));
+    }
+
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    if embeddings {
+        println!("  Embeddings: enabled (text only, {:?} precision, rescoring vectors: {:?}, {} index)", precision, int8_storage, index_backend);
+        if let Some(bits) = dedup_embeddings {
+            println!("  Embedding dedup: within {} bits", bits);
+        }
+        if let Some(model_path) = model {
+            println!("  Model: {}", model_path.display());
+        }
+    }
+
+    #[cfg(feature = "multimodal")]
+    if images {
+        println!("  Images: enabled (multimodal)");
+        if let Some(model_path) = model {
+            println!("  Model: {}", model_path.display());
+        }
+    }
+
+    println!();
+
+    let start = Instant::now();
+
+    let mut builder = match git_rev {
+        #[cfg(feature = "git")]
+        Some(rev) => CxpBuilder::from_git(source, rev).context("Failed to read git revision")?,
+        #[cfg(not(feature = "git"))]
+        Some(_) => {
+            return Err(anyhow::anyhow!(
+                "Git support is not enabled. Rebuild cxp-cli with --features git"
+            ));
+        }
+        None => CxpBuilder::new(source),
+    };
+    builder.with_scan_filter(filter);
+    if let Some(dir) = checkpoint {
+        builder.with_checkpoint(dir).context("Failed to open checkpoint")?;
+    }
+    builder.with_atomic_write(atomic);
+    // Journaling leaves the archive as it is
+    let journal = journal && output.exists();
+    // Rebuilding over an archive keeps its snapshots
+    if output.exists() && !plan.split && !journal {
+        match builder.with_snapshots_from(output) {
+            Ok(_) => {}
+            Err(e) if snapshot.is_none() => {
+                println!("  Warning: snapshots of {} are not kept: {}", output.display(), e);
+            }
+            Err(e) => return Err(e).context("Failed to read the snapshots of the existing output"),
+        }
+    }
+    if show_progress {
+        builder.with_progress(std::sync::Arc::new(progress::BuildProgress::default()));
+    }
+    if let Some(profile) = &plan.profile {
+        #[cfg(feature = "scanner")]
+        {
+            use cxp_core::scanner::{ScanPlan, Tier, UserProfile};
+
+            let profile: UserProfile = profile.parse()?;
+            let tiers = plan.tiers.iter().map(|t| t.parse()).collect::<Result<Vec<Tier>>>()?;
+            let scan_plan = ScanPlan::scan(&[source], profile)
+                .context("Failed to scan")?
+                .with_tiers(&tiers);
+            println!(
+                "Profile {}: {} files selected (HOT {}, WARM {}, COLD {})",
+                profile.name(),
+                scan_plan.files.len(),
+                scan_plan.files_in(Tier::Hot).count(),
+                scan_plan.files_in(Tier::Warm).count(),
+                scan_plan.files_in(Tier::Cold).count()
+            );
+            builder.with_scan_plan(scan_plan);
+        }
+        #[cfg(not(feature = "scanner"))]
+        {
+            let _ = profile;
+            return Err(anyhow::anyhow!(
+                "Profile-aware builds are not enabled. Rebuild cxp-cli with --features scanner"
+            ));
+        }
+    }
+    for (key, value) in metadata {
+        builder.with_metadata(key, value);
+    }
+    match git_history {
+        #[cfg(feature = "git")]
+        Some(depth) => {
+            builder.with_git_history(depth).context("Failed to read git history")?.with_git_authors(git_authors);
+        }
+        #[cfg(not(feature = "git"))]
+        Some(_) => {
+            return Err(anyhow::anyhow!(
+                "Git support is not enabled. Rebuild cxp-cli with --features git"
+            ));
+        }
+        None => {}
+    }
+    match summarizer_command {
+        Some(command) => {
+            builder.with_summarizer(cxp_core::CommandSummarizer::new(command_words(command)?)?);
+        }
+        None if summaries => {
+            builder.with_summarizer(cxp_core::HeuristicSummarizer::new());
+        }
+        None => {}
+    }
+    if extract_keywords {
+        builder.with_keywords(cxp_core::KeywordExtractor::new());
+    }
+    if graph {
+        builder.with_graph_plugin(cxp_core::ImportGraph);
+        builder.with_graph_plugin(cxp_core::SymbolGraph);
+    }
+    if symbols {
+        builder.with_symbols(cxp_core::LineSymbolParser);
+    }
+    builder.with_chunking(chunking);
+    builder.with_compression(codec);
+    builder.with_int8_storage(int8_storage);
+    builder.with_embedding_precision(precision);
+    if let Some(bits) = dedup_embeddings {
+        builder.with_embedding_dedup(bits);
+    }
+    if let Some(size) = batch_size {
+        builder.with_embedding_batch_size(size);
+    }
+    builder.with_index_backend(index_backend)?;
+    if let Some(params) = index_params {
+        builder.with_index_params(params)?;
+    }
+    if let Some(version) = min_reader_version {
+        builder.with_min_reader_version(version)?;
+    }
+    if dictionary {
+        builder.with_trained_dictionary();
+    }
+    builder.with_provenance(provenance);
+    #[cfg(feature = "redact")]
+    builder.with_redaction(redact);
+    #[cfg(feature = "redact")]
+    for scrubber in scrubbers {
+        builder.with_scrubber(scrubber);
+    }
+
+    // Enable images if requested
+    #[cfg(feature = "multimodal")]
+    if images {
+        builder.with_images();
+    }
+
+    #[cfg(not(feature = "multimodal"))]
+    if images {
+        return Err(anyhow::anyhow!(
+            "Image processing is not enabled. Rebuild cxp-cli with --features multimodal,search"
+        ));
+    }
+
+    builder
+        .scan()
+        .context("Failed to scan directory")?
+        .process()
+        .context("Failed to process files")?;
+    if let Some(label) = snapshot {
+        builder.snapshot(label).context("Failed to take snapshot")?;
+    }
+    if journal {
+        builder.append_journal(output).context("Failed to journal the update")?;
+        println!();
+        println!(
+            "Journaled in {:.2}s: {} records (embeddings and indices catch up with the next full build)",
+            start.elapsed().as_secs_f64(),
+            builder.journal_records()
+        );
+        println!();
+        show_info(output)?;
+        return Ok(());
+    }
+
+    // Generate embeddings if requested
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    if embeddings {
+        builder.with_embedding_engine(load_embedding_engine(model, model_type, device)?);
+    }
+
+    #[cfg(not(all(feature = "embeddings", feature = "search")))]
+    if embeddings {
+        return Err(anyhow::anyhow!(
+            "Embeddings feature is not enabled. Rebuild cxp-cli with --features embeddings,search"
+        ));
+    }
+
+    // Generate multimodal embeddings if images are enabled
+    #[cfg(all(feature = "multimodal", feature = "search"))]
+    if images {
+        let model_path = model.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Model path is required for multimodal embeddings. Use --model <path> to specify the SigLIP 2 model directory."
+            )
+        })?;
+
+        let device: cxp_core::Device = device.parse()?;
+        let engine = cxp_core::MultimodalEngine::load_with(model_path, device)
+            .context("Failed to initialize multimodal embeddings")?;
+        println!("  Embedding device: {}", engine.device());
+        builder.with_multimodal_engine(engine);
+    }
+
+    if plan.split {
+        // Build flat, then move each tier into its own child
+        let mut flat = output.as_os_str().to_os_string();
+        flat.push(".flat");
+        let flat = PathBuf::from(flat);
+        builder
+            .build(&flat)
+            .context("Failed to build CXP file")?;
+        let split = cxp_core::CxpSplitter::new(cxp_core::SplitMode::ByTier).split(&flat, output);
+        std::fs::remove_file(&flat)?;
+        let stats = split.context("Failed to split tiers")?;
+        for child in &stats.children {
+            println!("  {} {:<6} {:>6} files", child.tier.emoji(), child.name, child.meta.total_files);
+        }
+    } else {
+        builder
+            .build(output)
+            .context("Failed to build CXP file")?;
+    }
+
+    let duration = start.elapsed();
+
+    println!();
+    println!("Done in {:.2}s", duration.as_secs_f64());
+    println!();
+
+    // Show summary
+    show_info(output)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "watch")]
+#[allow(clippy::too_many_arguments)]
+fn watch_command(
+    source: &std::path::Path,
+    output: &std::path::Path,
+    embeddings: bool,
+    #[allow(unused_variables)]
+    model: Option<&std::path::Path>,
+    #[allow(unused_variables)]
+    model_type: &str,
+    #[allow(unused_variables)]
+    device: &str,
+    filter: cxp_core::ScanFilter,
+    debounce_ms: u64,
+    low_priority: bool,
+    journal: Option<u64>,
+) -> Result<()> {
+    use cxp_core::{WatchConfig, WatchService};
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    println!("Watching for changes...");
+    println!("  Source:   {}", source.display());
+    println!("  Output:   {}", output.display());
+    println!("  Debounce: {} ms", debounce_ms);
+    if low_priority {
+        println!("  Priority: idle (paused on battery)");
+        // Before the embedding session starts its threads, so they inherit it
+        if let Err(e) = cxp_core::priority::lower_current_thread() {
+            println!("  Warning:  {}", e);
+        }
+    }
+    if let Some(max_records) = journal {
+        println!("  Journal:  rewrite after {} updates", max_records);
+    }
+    println!();
+
+    let mut builder = CxpBuilder::new(source);
+    builder.with_scan_filter(filter);
+
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    if embeddings {
+        builder.with_embedding_engine(load_embedding_engine(model, model_type, device)?);
+    }
+
+    #[cfg(not(all(feature = "embeddings", feature = "search")))]
+    if embeddings {
+        return Err(anyhow::anyhow!(
+            "Embeddings feature is not enabled. Rebuild cxp-cli with --features embeddings,search"
+        ));
+    }
+
+    let mut config = WatchConfig::new()
+        .with_debounce(Duration::from_millis(debounce_ms))
+        .with_low_priority(low_priority)
+        .with_pause_on_battery(low_priority);
+    if let Some(max_records) = journal {
+        config = config.with_journal(max_records);
+    }
+    let mut service = WatchService::new(builder, output).with_config(config);
+
+    // Archives are replaced atomically and journal records are rolled back
+    // when incomplete, so stopping with Ctrl+C is safe at any time
+    let stop = AtomicBool::new(false);
+    service
+        .run(&stop, |update| {
+            let time = chrono::Local::now().format("%H:%M:%S");
+            if update.changed_paths == 0 {
+                println!(
+                    "[{}] Built {} files, {} unique chunks in {:.2}s",
+                    time,
+                    update.total_files,
+                    update.unique_chunks,
+                    update.duration.as_secs_f64()
+                );
+            } else {
+                println!(
+                    "[{}] {} changed paths -> {} files, {} unique chunks in {:.2}s",
+                    time,
+                    update.changed_paths,
+                    update.total_files,
+                    update.unique_chunks,
+                    update.duration.as_secs_f64()
+                );
+            }
+        })
+        .context("Watch failed")?;
+
+    Ok(())
+}
+
+fn show_info(file: &PathBuf) -> Result<()> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    let manifest = reader.manifest();
+
+    println!("CXP File Information");
+    println!("====================");
+    println!();
+    println!("Version:        {}", manifest.version);
+    if let Some(ref version) = manifest.min_reader_version {
+        println!("Min reader:     {}", version);
+    }
+    println!("Created:        {}", manifest.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    if reader.journal_records() > 0 {
+        println!("Journal:        {} updates pending (fold in with cxp optimize)", reader.journal_records());
+    }
+    println!();
+    println!("Statistics:");
+    println!("  Total files:  {}", manifest.stats.total_files);
+    println!("  Unique chunks:{}", manifest.stats.unique_chunks);
+    println!(
+        "  Original size:{:.2} MB",
+        manifest.stats.original_size_bytes as f64 / 1024.0 / 1024.0
+    );
+    println!(
+        "  CXP size:     {:.2} MB",
+        manifest.stats.cxp_size_bytes as f64 / 1024.0 / 1024.0
+    );
+    println!(
+        "  Compression:  {:.1}%",
+        manifest.stats.compression_ratio * 100.0
+    );
+    println!(
+        "  Dedup savings:{:.1}%",
+        manifest.stats.dedup_savings_percent
+    );
+    if let Some(ref chunker) = manifest.chunker {
+        println!("  Chunker:      {}", chunker);
+    }
+    if let Some(ref compression) = manifest.compression {
+        println!("  Codec:        {}", compression);
+    }
+    if let Some(dictionary) = reader.compression_dictionary() {
+        println!("  Dictionary:   {:.2} KB", dictionary.len() as f64 / 1024.0);
+    }
+    println!();
+
+    if !manifest.file_types.is_empty() {
+        println!("File Types:");
+        let mut types: Vec<_> = manifest.file_types.iter().collect();
+        types.sort_by_key(|(_, info)| std::cmp::Reverse(info.count));
+
+        for (ext, info) in types.iter().take(10) {
+            println!(
+                "  .{:<10} {:>5} files ({:.2} KB)",
+                ext,
+                info.count,
+                info.total_bytes as f64 / 1024.0
+            );
+        }
+
+        // Worst-compressing types first
+        let mut ratios: Vec<_> = manifest
+            .file_types
+            .iter()
+            .filter_map(|(ext, info)| info.compression_ratio().map(|ratio| (ext, info, ratio)))
+            .collect();
+        ratios.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        if !ratios.is_empty() {
+            println!();
+            println!("Compression by Type:");
+            for (ext, info, ratio) in ratios.iter().take(10) {
+                println!(
+                    "  .{:<10} {:>10.2} KB -> {:>10.2} KB ({:>5.1}%)",
+                    ext,
+                    info.chunk_bytes as f64 / 1024.0,
+                    info.compressed_bytes as f64 / 1024.0,
+                    ratio * 100.0
+                );
+            }
+        }
+    }
+
+    if !manifest.topics.is_empty() {
+        println!();
+        println!("Topics: {}", manifest.topics.join(", "));
+    }
+
+    if let Some(clusters) = reader.clusters()? {
+        println!();
+        println!("Clusters: {}", clusters.clusters.len());
+        for cluster in &clusters.clusters {
+            println!("  [{:>2}] {:<48} {:>6} chunks", cluster.id, cluster.label_text(), cluster.chunks);
+        }
+    }
+
+    if !manifest.extensions.is_empty() {
+        println!();
+        println!("Extensions: {}", manifest.extensions.join(", "));
+    }
+
+    if let Some(ref report) = manifest.redactions {
+        println!();
+        if report.total == 0 {
+            println!("Redactions: none");
+        } else {
+            println!("Redactions: {} values in {} files", report.total, report.files.len());
+            for (kind, count) in &report.by_kind {
+                println!("  {:<16} {:>5}", kind, count);
+            }
+            for (path, redactions) in report.files.iter().take(10) {
+                let lines: Vec<String> = redactions.iter().map(|r| r.line.to_string()).collect();
+                println!("  {} (lines {})", path, lines.join(", "));
+            }
+            if report.files.len() > 10 {
+                println!("  ... and {} more files", report.files.len() - 10);
+            }
+        }
+    }
+
+    // Show embedding info if present
+    if let Some(ref model) = manifest.embedding_model {
+        println!();
+        println!("Embeddings:");
+        println!("  Model:      {}", model);
+        if let Some(ref id) = manifest.embedding_model_id {
+            match manifest.embedding_model_revision {
+                Some(ref revision) => println!("  Model ID:   {} (revision {})", id, revision),
+                None => println!("  Model ID:   {}", id),
+            }
+        }
+        if let Some(dim) = manifest.embedding_dim {
+            println!("  Dimensions: {}", dim);
+        }
+        let int8 = match manifest.int8_embeddings {
+            Int8Storage::All => "all chunks",
+            Int8Storage::HotOnly => "hot-tier chunks",
+            Int8Storage::None => "none (binary only)",
+        };
+        if manifest.embedding_precision.is_float() {
+            println!("  Precision:  {:?} (float rescoring vectors)", manifest.embedding_precision);
+        } else {
+            println!("  Int8:       {}", int8);
+        }
+        match (manifest.index_backend, manifest.index_params) {
+            (IndexBackend::Hnsw, Some(params)) => {
+                println!("  Index:      HNSW (M {}, ef_construction {}, ef_search {})", params.m, params.ef_construction, params.ef_search);
+            }
+            (backend, _) => println!("  Index:      {}", backend),
+        }
+        if manifest.embedding_tombstones > 0 {
+            println!("  Tombstones: {} rows of removed chunks (reclaim with cxp optimize)", manifest.embedding_tombstones);
+        }
+    }
+
+    if let Some(info) = reader.build_info()? {
+        println!();
+        println!("Build:");
+        println!("  Builder:    {} ({}/{})", info.builder_version, info.host_os, info.host_arch);
+        if !info.features.is_empty() {
+            println!("  Features:   {}", info.features.join(", "));
+        }
+        let phases: Vec<String> = info
+            .phases_ms
+            .iter()
+            .map(|(phase, ms)| format!("{} {}ms", phase, ms))
+            .collect();
+        println!("  Phases:     {}", phases.join(", "));
+    }
+
+    if manifest.has_children() {
+        println!();
+        println!("Children:");
+        for child in manifest.children.iter() {
+            let storage = if child.is_embedded() { "embedded" } else { "external" };
+            println!(
+                "  {} {:<20} {:>6} files  {:>10}  {}",
+                child.tier.emoji(),
+                child.id,
+                child.meta.total_files,
+                child.meta.size_display(),
+                storage
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Show token counts and savings, tokenizer-accurate when a vocabulary is available
+#[allow(unused_variables)]
+fn show_token_info(
+    file: &PathBuf,
+    tokenizer: &str,
+    tokenizer_path: Option<&std::path::Path>,
+) -> Result<()> {
+    use cxp_core::{calculate_savings, format_tokens, Tokenizer};
+
+    let tokenizer: Tokenizer = tokenizer.parse()?;
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+
+    println!();
+    println!("Tokens:");
+
+    #[cfg(feature = "tokenizer")]
+    if let Some(path) = tokenizer_path {
+        use cxp_core::TokenCounter;
+
+        let counter = if path.is_dir() {
+            TokenCounter::from_dir(path)?
+        } else {
+            let mut counter = TokenCounter::new();
+            counter.load(tokenizer, path)?;
+            counter
+        };
+
+        if counter.has(tokenizer) {
+            let savings = counter.token_savings(&reader, tokenizer)?;
+            println!("  Tokenizer:    {} (exact)", tokenizer);
+            println!("  Original:     {}", format_tokens(savings.original_tokens));
+            println!("  Deduplicated: {}", format_tokens(savings.cxp_tokens));
+            println!("  Savings:      {:.1}%", savings.savings_percent);
+            return Ok(());
+        }
+
+        println!(
+            "  No {} vocabulary found at {} (expected {}), falling back to estimate",
+            tokenizer,
+            path.display(),
+            tokenizer.file_name()
+        );
+    }
+
+    let stats = &reader.manifest().stats;
+    let savings = calculate_savings(stats.original_size_bytes, stats.cxp_size_bytes);
+    println!("  Tokenizer:    estimate (bytes / 4)");
+    println!("  Original:     {}", format_tokens(savings.original_tokens));
+    println!("  CXP:          {}", format_tokens(savings.cxp_tokens));
+    println!("  Savings:      {:.1}%", savings.savings_percent);
+
+    Ok(())
+}
+
+fn delta_command(old: &std::path::Path, new: &std::path::Path, patch: &std::path::Path) -> Result<()> {
+    let delta = cxp_core::CxpDelta::create(old, new, patch).context("Failed to create delta")?;
+
+    let new_size = std::fs::metadata(new)?.len();
+    let patch_size = std::fs::metadata(patch)?.len();
+
+    println!("CXP Delta");
+    println!("=========");
+    println!();
+    println!("Changed files:   {}", delta.upserted_files.len());
+    println!("Removed files:   {}", delta.removed_files.len());
+    println!("New chunks:      {}", delta.new_chunks.len());
+    println!("Changed entries: {}", delta.changed_entries.len());
+    println!();
+    println!(
+        "Patch size:      {} ({:.1}% of {})",
+        format_size(patch_size),
+        patch_size as f64 / new_size.max(1) as f64 * 100.0,
+        format_size(new_size)
+    );
+    println!("Written to:      {}", patch.display());
+
+    Ok(())
+}
+
+fn apply_command(base: &std::path::Path, patch: &std::path::Path, output: Option<&std::path::Path>) -> Result<()> {
+    let output = output.unwrap_or(base);
+    let delta = cxp_core::CxpDelta::apply(base, patch, output).context("Failed to apply delta")?;
+
+    println!(
+        "Applied patch: {} changed, {} removed files, {} new chunks",
+        delta.upserted_files.len(),
+        delta.removed_files.len(),
+        delta.new_chunks.len()
+    );
+    println!("Written to: {}", output.display());
+
+    Ok(())
+}
+
+fn merge_command(
+    inputs: &[PathBuf],
+    output: &std::path::Path,
+    on_conflict: &str,
+) -> Result<()> {
+    let policy: cxp_core::ConflictPolicy = on_conflict.parse()?;
+
+    println!("Merging {} archives into {}...", inputs.len(), output.display());
+    let start = Instant::now();
+
+    let stats = cxp_core::CxpMerger::new()
+        .with_policy(policy)
+        .merge(inputs, output)
+        .context("Failed to merge archives")?;
+
+    println!();
+    println!("CXP Merge");
+    println!("=========");
+    println!();
+    println!("Archives:       {}", stats.archives);
+    println!("Files:          {}", stats.files);
+    println!("Conflicts:      {} ({})", stats.conflicts, on_conflict);
+    println!("Unique chunks:  {}", stats.unique_chunks);
+    println!("Shared chunks:  {}", stats.shared_chunks);
+    println!("Embeddings:     {}", if stats.embeddings { "merged" } else { "none" });
+    println!(
+        "Archive size:   {}",
+        format_size(std::fs::metadata(output)?.len())
+    );
+    println!("Done in {:.2}s", start.elapsed().as_secs_f64());
+
+    Ok(())
+}
+
+fn optimize_command(
+    file: &std::path::Path,
+    output: Option<&std::path::Path>,
+    keep_dictionary: bool,
+) -> Result<()> {
+    println!("Optimizing {}...", file.display());
+    let optimizer = cxp_core::CxpOptimizer::new().with_retrain_dictionary(!keep_dictionary);
+    // In-place runs also fold the journal into the archive and delete it
+    let stats = match output {
+        Some(output) => optimizer.optimize(file, output),
+        None => cxp_core::journal::compact(file, &optimizer),
+    }
+    .context("Failed to optimize archive")?;
+
+    println!();
+    println!("CXP Optimize");
+    println!("============");
+    println!();
+    println!("Chunks:         {} ({} orphaned dropped)", stats.chunks, stats.orphaned_chunks);
+    println!(
+        "Chunk data:     {} -> {}{}",
+        format_size(stats.chunk_bytes_before),
+        format_size(stats.chunk_bytes_after),
+        if stats.recompressed { " (recompressed)" } else { " (kept as stored)" }
+    );
+    match stats.dictionary_bytes {
+        Some(bytes) => println!("Dictionary:     {}", format_size(bytes as u64)),
+        None => println!("Dictionary:     none"),
+    }
+    if stats.index_rebuilt {
+        println!("Embeddings:     {} rows dropped, {} kept, index rebuilt", stats.embeddings_dropped, stats.embeddings_kept);
+    }
+    println!(
+        "Archive size:   {} -> {} ({} saved)",
+        format_size(stats.bytes_before),
+        format_size(stats.bytes_after),
+        format_size(stats.bytes_saved().max(0) as u64)
+    );
+    println!("Written to:     {}", output.unwrap_or(file).display());
+    println!("Done in {:.2}s", stats.elapsed.as_secs_f64());
+
+    Ok(())
+}
+
+fn gc_command(file: &std::path::Path, keep_last: Option<usize>) -> Result<()> {
+    let mut options = cxp_core::GcOptions::default();
+    if let Some(count) = keep_last {
+        options = options.with_keep_last(count);
+    }
+
+    println!("Collecting garbage in {}...", file.display());
+    let stats = cxp_core::CxpFile::gc(file, &options).context("Failed to collect garbage")?;
+
+    println!();
+    println!("CXP GC");
+    println!("======");
+    println!();
+    if stats.snapshots_dropped.is_empty() {
+        println!("Snapshots:      {} kept", stats.snapshots_kept);
+    } else {
+        println!(
+            "Snapshots:      {} kept, {} dropped ({})",
+            stats.snapshots_kept,
+            stats.snapshots_dropped.len(),
+            stats.snapshots_dropped.join(", ")
+        );
+    }
+    println!("Chunks:         {} kept, {} removed", stats.chunks_kept, stats.chunks_removed);
+    println!("Reclaimed:      {}", format_size(stats.bytes_reclaimed));
+    println!(
+        "Archive size:   {} -> {}",
+        format_size(stats.bytes_before),
+        format_size(stats.bytes_after)
+    );
+
+    Ok(())
+}
+
+fn split_command(file: &std::path::Path, output: &std::path::Path, by_tier: bool) -> Result<()> {
+    let mode = if by_tier { cxp_core::SplitMode::ByTier } else { cxp_core::SplitMode::ByDir };
+
+    println!("Splitting {} into {}...", file.display(), output.display());
+    let start = Instant::now();
+
+    let stats = cxp_core::CxpSplitter::new(mode)
+        .split(file, output)
+        .context("Failed to split archive")?;
+
+    println!();
+    println!("CXP Split");
+    println!("=========");
+    println!();
+    println!("Files:          {}", stats.files);
+    println!("In parent:      {}", stats.root_files);
+    println!("Children:       {}", stats.children.len());
+    for child in &stats.children {
+        println!(
+            "  {} {:<20} {:>6} files  {}",
+            child.tier.emoji(),
+            child.name,
+            child.meta.total_files,
+            child.meta.size_display()
+        );
+    }
+    println!(
+        "Archive size:   {}",
+        format_size(std::fs::metadata(output)?.len())
+    );
+    println!("Done in {:.2}s", start.elapsed().as_secs_f64());
+
+    Ok(())
+}
+
+fn reindex_command(file: &std::path::Path) -> Result<()> {
+    println!("Reindexing {}...", file.display());
+    let start = Instant::now();
+
+    let index = cxp_core::CxpManager::reindex_archive(file).context("Failed to rebuild global index")?;
+
+    println!();
+    println!("Global Index");
+    println!("============");
+    println!();
+    println!("CXPs indexed:   {}", index.stats.cxp_count);
+    println!("Files indexed:  {}", index.stats.total_entries);
+    for (cxp_path, range) in &index.cxp_ranges {
+        println!("  {:<30} {:>6} files", cxp_path, range.end - range.start);
+    }
+    println!("Done in {:.2}s", start.elapsed().as_secs_f64());
+
+    Ok(())
+}
+
+fn conformance_command(file: &std::path::Path, json: bool) -> Result<()> {
+    use cxp_core::CheckStatus;
+
+    let report = cxp_core::format_spec::check_file(file).context("Failed to open CXP file")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("CXP Conformance");
+        println!("===============");
+        println!();
+        println!("Spec version:     {}", report.spec_version);
+        println!("Archive version:  {}", report.archive_version.as_deref().unwrap_or("unknown"));
+        println!();
+        for check in &report.checks {
+            let status = match check.status {
+                CheckStatus::Pass => "PASS",
+                CheckStatus::Warn => "WARN",
+                CheckStatus::Fail => "FAIL",
+            };
+            println!("  [{}] {:<16} {}", status, check.name, check.detail);
+        }
+        println!();
+    }
+
+    let failures = report.failures().len();
+    if failures > 0 {
+        return Err(anyhow::anyhow!(
+            "{} is not conformant ({} failed checks)",
+            file.display(),
+            failures
+        ));
+    }
+    if !json {
+        println!("{} conforms to CXP {}", file.display(), report.spec_version);
+    }
+
+    Ok(())
+}
+
+fn lint_command(file: &std::path::Path, policy_path: &std::path::Path, json: bool) -> Result<()> {
+    let policy_text = std::fs::read_to_string(policy_path)
+        .with_context(|| format!("Failed to read policy {}", policy_path.display()))?;
+    let policy: cxp_core::LintPolicy = toml::from_str(&policy_text)
+        .with_context(|| format!("Invalid policy {}", policy_path.display()))?;
+    let report = policy.check_file(file).context("Failed to lint CXP file")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("CXP Lint");
+        println!("========");
+        println!();
+        println!("Policy:      {}", policy_path.display());
+        println!("Rules:       {}", report.rules_checked);
+        println!("Violations:  {}", report.violations.len());
+        if !report.is_clean() {
+            println!();
+            for violation in &report.violations {
+                println!("  [{}] {}", violation.rule, violation.message);
+            }
+        }
+        println!();
+    }
+
+    if !report.is_clean() {
+        return Err(anyhow::anyhow!(
+            "{} violates {} ({} violations)",
+            file.display(),
+            policy_path.display(),
+            report.violations.len()
+        ));
+    }
+    if !json {
+        println!("{} passes all policy rules", file.display());
+    }
+
+    Ok(())
+}
+
+fn publish_command(
+    file: &std::path::Path,
+    out: &std::path::Path,
+    title: Option<String>,
+    sign_key: Option<&std::path::Path>,
+) -> Result<()> {
+    let mut options = cxp_core::PublishOptions::default();
+    if let Some(title) = title {
+        options = options.with_title(title);
+    }
+    if let Some(path) = sign_key {
+        options = options.with_signing_key(read_signing_key(path)?);
+    }
+
+    let bundle = cxp_core::publish(file, out, &options).context("Failed to publish CXP file")?;
+
+    println!("Published {}", file.display());
+    println!("  Archive:   {}", bundle.archive.display());
+    println!("  README:    {}", bundle.readme.display());
+    println!("  Checksums: {}", bundle.checksums.display());
+    if let Some(ref signature) = bundle.signature {
+        println!("  Signature: {}", signature.display());
+    }
+    println!("  SHA-256:   {}", bundle.archive_sha256);
+
+    Ok(())
+}
+
+fn verify_bundle_command(dir: &std::path::Path, sign_key: Option<&std::path::Path>) -> Result<()> {
+    let key = sign_key.map(read_signing_key).transpose()?;
+    cxp_core::verify_bundle(dir, key.as_deref())
+        .with_context(|| format!("Bundle {} failed verification", dir.display()))?;
+
+    if key.is_some() {
+        println!("{}: checksums and signature OK", dir.display());
+    } else {
+        println!("{}: checksums OK (signature not checked)", dir.display());
+    }
+    Ok(())
+}
+
+/// Read a signing key file (surrounding whitespace is ignored)
+fn read_signing_key(path: &std::path::Path) -> Result<Vec<u8>> {
+    let key = std::fs::read(path).with_context(|| format!("Failed to read key {}", path.display()))?;
+    Ok(key.trim_ascii().to_vec())
+}
+
+/// Split a command line into program and arguments, honoring shell quoting
+fn command_words(command: &str) -> Result<Vec<String>> {
+    shlex::split(command).ok_or_else(|| anyhow::anyhow!("Unbalanced quotes in command: {}", command))
+}
+
+/// Parse a `KEY=VALUE` argument
+fn parse_key_value(arg: &str) -> std::result::Result<(String, String), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", arg))?;
+    if key.is_empty() {
+        return Err(format!("empty key in '{}'", arg));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+fn usage_command(file: &std::path::Path, json: bool, top: usize, reset: bool) -> Result<()> {
+    let recorder = UsageRecorder::for_archive(file);
+
+    if reset {
+        recorder.reset().context("Failed to reset usage metrics")?;
+        println!("Usage metrics reset ({})", recorder.path().display());
+        return Ok(());
+    }
+
+    let metrics = recorder.metrics().context("Failed to read usage metrics")?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&metrics)?);
+        return Ok(());
+    }
+
+    if metrics.is_empty() {
+        println!("No usage recorded for {}", file.display());
+        println!("Run queries with --track-usage to record anonymous usage metrics locally.");
+        return Ok(());
+    }
+
+    println!("CXP Usage Metrics");
+    println!("=================");
+    println!();
+    if let (Some(since), Some(until)) = (metrics.since, metrics.until) {
+        println!("Period:    {} - {}", since.format("%Y-%m-%d %H:%M"), until.format("%Y-%m-%d %H:%M"));
+    }
+    println!("Queries:   {}", metrics.queries);
+    println!(
+        "Hit rate:  {:.1}% ({} with results)",
+        metrics.hit_rate() * 100.0,
+        metrics.queries_with_results
+    );
+    println!("Files used: {}", metrics.top_files(usize::MAX).len());
+    println!();
+
+    println!("Most Used Files:");
+    println!("  {:>8} {:>8}  PATH", "RESULTS", "READS");
+    for entry in metrics.top_files(top) {
+        println!("  {:>8} {:>8}  {}", entry.results, entry.reads, entry.path);
+    }
+
+    Ok(())
+}
+
+fn show_duplicates(file: &std::path::Path, json: bool, min_shared: f64, threshold: f64, min_size: u64) -> Result<()> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    let options = DuplicateOptions::default()
+        .with_min_shared_fraction(min_shared / 100.0)
+        .with_similarity_threshold(threshold / 100.0)
+        .with_min_size(min_size);
+    let report = reader.duplicate_report_with(&options).context("Failed to collect duplicates")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("CXP Duplicate Report");
+    println!("====================");
+    println!();
+    println!("Clusters:         {}", report.clusters.len());
+    println!("Files involved:   {}", report.files_with_duplicates);
+    println!("Saved by sharing: {}", format_size(report.duplicate_bytes));
+
+    for (i, cluster) in report.clusters.iter().enumerate() {
+        println!();
+        println!(
+            "Cluster {} ({} files, {} shared, {} saved):",
+            i + 1,
+            cluster.files.len(),
+            format_size(cluster.shared_bytes),
+            format_size(cluster.saved_bytes)
+        );
+        for entry in &cluster.files {
+            println!(
+                "  {:>10}  {:>5.1}% shared  {}",
+                format_size(entry.size),
+                entry.shared_percent,
+                entry.path
+            );
+        }
+        for pair in &cluster.pairs {
+            println!(
+                "    {} <-> {}: {} ({:.1}%)",
+                pair.a,
+                pair.b,
+                format_size(pair.shared_bytes),
+                pair.shared_percent
+            );
+        }
+    }
+
+    println!();
+    println!("Near-duplicate groups: {}", report.groups.len());
+    println!("Redundant:             {}", format_size(report.redundant_bytes));
+    for (i, group) in report.groups.iter().enumerate() {
+        println!();
+        println!(
+            "Group {} ({} files, {}, {} redundant):",
+            i + 1,
+            group.files.len(),
+            if group.identical { "identical" } else { "near-identical" },
+            format_size(group.redundant_bytes)
+        );
+        for (j, entry) in group.files.iter().enumerate() {
+            let role = if j == 0 { "keep".to_string() } else { format!("{:.1}%", entry.similarity * 100.0) };
+            println!("  {:>10}  {:>6}  {}", format_size(entry.size), role, entry.path);
+        }
+    }
+
+    Ok(())
+}
+
+fn show_stats(file: &PathBuf, json: bool) -> Result<()> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    let stats = reader.statistics().context("Failed to collect statistics")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!("CXP Statistics");
+    println!("==============");
+    println!();
+    println!("Files:          {}", stats.total_files);
+    println!("Original size:  {}", format_size(stats.original_bytes));
+    println!("Archive size:   {}", format_size(stats.archive_bytes));
+    println!(
+        "Chunks:         {} unique / {} refs ({:.1}% dedup hits)",
+        stats.unique_chunks,
+        stats.chunk_refs,
+        stats.dedup_ratio() * 100.0
+    );
+    println!(
+        "Compression:    {} -> {} ({:.1}%)",
+        format_size(stats.unique_chunk_bytes),
+        format_size(stats.compressed_chunk_bytes),
+        stats.compression_ratio() * 100.0
+    );
+    println!();
+
+    println!("Chunk Sizes:");
+    let max_count = stats.chunk_size_histogram.iter().map(|b| b.count).max().unwrap_or(0);
+    let mut lower = 0;
+    for bucket in &stats.chunk_size_histogram {
+        let label = match bucket.max_bytes {
+            Some(max) => {
+                let label = format!("{}-{} KB", lower / 1024, max / 1024);
+                lower = max;
+                label
+            }
+            None => format!(">{} KB", lower / 1024),
+        };
+        let bar = (bucket.count * 40).checked_div(max_count).unwrap_or(0);
+        println!("  {:<10} {:>7} {}", label, bucket.count, "#".repeat(bar));
+    }
+    println!();
+
+    println!("By Extension:");
+    println!(
+        "  {:<12} {:>6} {:>10} {:>10} {:>8} {:>8}",
+        "EXT", "FILES", "ORIGINAL", "STORED", "DEDUP", "RATIO"
+    );
+    for ext in &stats.extensions {
+        let name = if ext.extension.is_empty() { "(none)".to_string() } else { format!(".{}", ext.extension) };
+        println!(
+            "  {:<12} {:>6} {:>10} {:>10} {:>7.1}% {:>7.1}%",
+            name,
+            ext.files,
+            format_size(ext.original_bytes),
+            format_size(ext.compressed_bytes),
+            ext.dedup_ratio() * 100.0,
+            ext.compression_ratio() * 100.0
+        );
+    }
+    println!();
+
+    println!("By Directory:");
+    for dir in &stats.directories {
+        println!(
+            "  {:<30} {:>6} files {:>10} -> {:>10}",
+            dir.directory,
+            dir.files,
+            format_size(dir.original_bytes),
+            format_size(dir.compressed_bytes)
+        );
+    }
+    println!();
+
+    println!("Largest Files:");
+    for entry in &stats.largest_files {
+        println!("  {:>10}  {:>4} chunks  {}", format_size(entry.size), entry.chunks, entry.path);
+    }
+    println!();
+
+    let embeddings = &stats.embeddings;
+    println!("Embeddings:");
+    if embeddings.total_bytes() == 0 {
+        println!("  (none)");
+    } else {
+        println!("  Binary:   {}", format_size(embeddings.binary_bytes));
+        println!("  Int8:     {}", format_size(embeddings.int8_bytes));
+        if embeddings.float_bytes > 0 {
+            println!("  Float:    {}", format_size(embeddings.float_bytes));
+        }
+        println!("  Index:    {}", format_size(embeddings.index_bytes));
+        println!("  Other:    {}", format_size(embeddings.other_bytes));
+        println!(
+            "  Total:    {} ({:.1}% of archive)",
+            format_size(embeddings.total_bytes()),
+            embeddings.total_bytes() as f64 / stats.archive_bytes.max(1) as f64 * 100.0
+        );
+    }
+
+    Ok(())
+}
+
+/// Open an archive, or one of its snapshots
+fn open_at_snapshot(file: &std::path::Path, snapshot: Option<&str>) -> Result<CxpReader> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    match snapshot {
+        Some(label) => reader.open_snapshot(label).with_context(|| format!("Failed to open snapshot '{}'", label)),
+        None => Ok(reader),
+    }
+}
+
+fn snapshots_command(file: &std::path::Path) -> Result<()> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    if reader.snapshots().is_empty() {
+        println!("No snapshots in {}", file.display());
+        return Ok(());
+    }
+
+    println!("{:<24} {:>4} {:<20} {:>8} {:>10}", "LABEL", "GEN", "CREATED", "FILES", "SIZE");
+    println!("{}", "-".repeat(70));
+    for snapshot in reader.snapshots() {
+        println!(
+            "{:<24} {:>4} {:<20} {:>8} {:>10}",
+            snapshot.label,
+            snapshot.generation,
+            snapshot.created_at.format("%Y-%m-%d %H:%M:%S"),
+            snapshot.files,
+            format_size(snapshot.size)
+        );
+    }
+    println!();
+    println!(
+        "Latest: {} files; open a snapshot with `cxp list --snapshot <label>`",
+        reader.manifest().stats.total_files
+    );
+
+    Ok(())
+}
+
+/// Compile the --glob or --regex option of a command, if given
+fn path_pattern(glob: Option<&str>, regex: Option<&str>) -> Result<Option<cxp_core::PathPattern>> {
+    Ok(match (glob, regex) {
+        (Some(glob), _) => Some(cxp_core::PathPattern::glob(glob)?),
+        #[cfg(feature = "regex")]
+        (None, Some(regex)) => Some(cxp_core::PathPattern::regex(regex)?),
+        #[cfg(not(feature = "regex"))]
+        (None, Some(_)) => {
+            return Err(anyhow::anyhow!("--regex requires the regex feature. Rebuild cxp-cli with --features regex"));
+        }
+        (None, None) => None,
+    })
+}
+
+/// Files carrying `tag` (all files if None) that match `pattern`, sorted
+fn selected_paths<'a>(
+    reader: &'a CxpReader,
+    tag: Option<&str>,
+    pattern: Option<&cxp_core::PathPattern>,
+) -> Vec<&'a str> {
+    let mut paths: Vec<_> = match tag {
+        Some(tag) => reader.files_with_tag(tag),
+        None => reader.file_paths(),
+    };
+    if let Some(pattern) = pattern {
+        paths.retain(|path| pattern.is_match(path));
+    }
+    paths.sort();
+    paths
+}
+
+fn list_files(
+    file: &std::path::Path,
+    long: bool,
+    tag: Option<&str>,
+    snapshot: Option<&str>,
+    pattern: Option<&cxp_core::PathPattern>,
+) -> Result<()> {
+    let reader = open_at_snapshot(file, snapshot)?;
+    let paths = selected_paths(&reader, tag, pattern);
+
+    if long {
+        println!("{:<60} {:>10} {:>6}  TAGS", "PATH", "SIZE", "CHUNKS");
+        println!("{}", "-".repeat(80));
+
+        for path in paths {
+            if let Some(entry) = reader.file_entry(path)? {
+                println!(
+                    "{:<60} {:>10} {:>6}  {}",
+                    path,
+                    format_size(entry.size),
+                    entry.chunks.len(),
+                    reader.file_tags(path).join(", ")
+                );
+            }
+        }
+    } else {
+        for path in paths {
+            println!("{}", path);
+        }
+    }
+
+    Ok(())
+}
+
+fn tree_command(file: &std::path::Path, depth: Option<usize>, sizes: bool) -> Result<()> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    let tree = reader.tree().context("Failed to read the file map")?;
+
+    println!("{}  {}", file.display(), dir_rollup(&tree, sizes));
+    print_tree(&tree, "", depth, sizes);
+    println!();
+    println!("{} directories, {} files", tree.dir_count(), tree.file_count);
+
+    Ok(())
+}
+
+/// Print the entries of `dir` below `prefix`, directories first
+fn print_tree(dir: &cxp_core::DirTree, prefix: &str, depth: Option<usize>, sizes: bool) {
+    if depth == Some(0) {
+        return;
+    }
+    let depth = depth.map(|d| d - 1);
+    let count = dir.dirs.len() + dir.files.len();
+
+    for (i, sub) in dir.dirs.iter().enumerate() {
+        let last = i + 1 == count;
+        println!("{}{}{}/  {}", prefix, if last { "└── " } else { "├── " }, sub.name, dir_rollup(sub, sizes));
+        print_tree(sub, &format!("{}{}", prefix, if last { "    " } else { "│   " }), depth, sizes);
+    }
+    for (i, entry) in dir.files.iter().enumerate() {
+        let connector = if dir.dirs.len() + i + 1 == count { "└── " } else { "├── " };
+        if sizes {
+            println!("{}{}{}  ({})", prefix, connector, entry.name, format_size(entry.size));
+        } else {
+            println!("{}{}{}", prefix, connector, entry.name);
+        }
+    }
+}
+
+/// File count (and size) of a directory and everything below it
+fn dir_rollup(dir: &cxp_core::DirTree, sizes: bool) -> String {
+    let files = if dir.file_count == 1 { "1 file".to_string() } else { format!("{} files", dir.file_count) };
+    if sizes {
+        format!("({}, {})", files, format_size(dir.total_size))
+    } else {
+        format!("({})", files)
+    }
+}
+
+fn list_summaries(file: &PathBuf, tag: Option<&str>, pattern: Option<&cxp_core::PathPattern>) -> Result<()> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    let summaries = reader.summaries()?.ok_or_else(|| {
+        anyhow::anyhow!("This CXP file has no summaries. Use 'cxp build --summaries' to create one.")
+    })?;
+
+    let paths = selected_paths(&reader, tag, pattern);
+
+    // Each directory's summary comes right before its first file
+    let mut shown_dirs = std::collections::BTreeSet::new();
+    for path in paths {
+        for (end, _) in path.match_indices('/') {
+            let dir = &path[..end];
+            if shown_dirs.insert(dir) {
+                let depth = dir.matches('/').count();
+                println!("{}{}/  {}", "  ".repeat(depth), dir, summaries.directory(dir).unwrap_or("-"));
+            }
+        }
+        let depth = path.matches('/').count();
+        println!("{}{}  {}", "  ".repeat(depth), path, summaries.file(path).unwrap_or("-"));
+    }
+
+    Ok(())
+}
+
+fn list_provenance(file: &PathBuf, tag: Option<&str>, pattern: Option<&cxp_core::PathPattern>) -> Result<()> {
+    use cxp_core::provenance::license_of;
+
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    let paths = selected_paths(&reader, tag, pattern);
+
+    println!("{:<50} {:<20} {:<12} {:<20} ORIGIN", "PATH", "LICENSE", "COMMIT", "MODIFIED");
+    println!("{}", "-".repeat(120));
+
+    let mut licenses: std::collections::BTreeMap<String, usize> = Default::default();
+    for path in paths {
+        let Some(entry) = reader.file_entry(path)? else {
+            continue;
+        };
+        let license = license_of(entry);
+        *licenses.entry(license.to_string()).or_insert(0) += 1;
+
+        let provenance = entry.provenance.as_ref();
+        let commit = provenance
+            .and_then(|p| p.git_commit.as_deref())
+            .map(|c| &c[..c.len().min(12)])
+            .unwrap_or("-");
+        let modified = entry
+            .modified
+            .map(|m| m.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<50} {:<20} {:<12} {:<20} {}",
+            path,
+            license,
+            commit,
+            modified,
+            provenance.map(|p| p.origin.as_str()).unwrap_or("-")
+        );
+    }
+
+    println!();
+    println!("Licenses:");
+    for (license, count) in &licenses {
+        println!("  {:<20} {:>6} files", license, count);
+    }
+
+    Ok(())
+}
+
+fn symbols_command(file: &PathBuf, find: Option<&str>, search: Option<&str>, file_path: Option<&str>, top_k: usize) -> Result<()> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    let index = reader.symbols()?.ok_or_else(|| {
+        anyhow::anyhow!("This CXP file has no symbol index. Use 'cxp build --symbols' to create one.")
+    })?;
+
+    let symbols: Vec<&cxp_core::Symbol> = match (find, search, file_path) {
+        (Some(name), _, _) => index.find(name).iter().collect(),
+        (_, Some(text), _) => index.search(text, top_k),
+        (_, _, Some(path)) => index.in_file(path),
+        _ => index.symbols().iter().collect(),
+    };
+    if symbols.is_empty() {
+        println!("No symbols found.");
+        return Ok(());
+    }
+
+    for symbol in symbols.iter().take(top_k) {
+        println!("{:<32} {:<10} {}:{}", symbol.name, symbol.kind, symbol.path, symbol.line);
+    }
+    if symbols.len() > top_k {
+        println!("... and {} more (use --top-k)", symbols.len() - top_k);
+    }
+    Ok(())
+}
+
+fn graph_command(file: &PathBuf, path: &str, hops: usize) -> Result<()> {
+    use cxp_core::{EdgeKind, Node};
+
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    let graph = reader.graph()?.ok_or_else(|| {
+        anyhow::anyhow!("This CXP file has no knowledge graph. Use 'cxp build --graph' to create one.")
+    })?;
+    let id = Node::file_id(path);
+    if graph.node(&id).is_none() {
+        return Err(anyhow::anyhow!("File not found in the graph: {}", path));
+    }
+
+    println!("{}", path);
+    for edge in graph.edges_of(&id) {
+        let outgoing = edge.from == id;
+        let relation = match (edge.kind, outgoing) {
+            (EdgeKind::Imports, true) => "imports",
+            (EdgeKind::Imports, false) => "imported by",
+            (EdgeKind::Defines, true) => "defines",
+            (EdgeKind::Defines, false) => "defined in",
+            (EdgeKind::References, true) => "references",
+            (EdgeKind::References, false) => "referenced by",
+            (EdgeKind::Mentions, true) => "mentions",
+            (EdgeKind::Mentions, false) => "mentioned by",
+            (EdgeKind::Contains, true) => "contains",
+            (EdgeKind::Contains, false) => "contained in",
+        };
+        let other = if outgoing { &edge.to } else { &edge.from };
+        let Some(node) = graph.node(other) else { continue };
+        match (&node.file, node.line) {
+            (Some(file), Some(line)) if node.label != *file => {
+                println!("  {:<14} {} ({}:{})", relation, node.label, file, line)
+            }
+            _ => println!("  {:<14} {}", relation, node.label),
+        }
+    }
+
+    if hops > 1 {
+        let neighborhood = graph.around_files(&[path], hops);
+        let files = neighborhood.files();
+        if !files.is_empty() {
+            println!();
+            println!("Files within {} hops:", hops);
+            for file in files {
+                println!("  {}", file);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "git")]
+fn history_command(
+    file: &std::path::Path,
+    query: Option<&str>,
+    file_path: Option<&str>,
+    top_k: usize,
+    #[allow(unused_variables)]
+    model: Option<&std::path::Path>,
 ) -> Result<()> {
-    println!("Building CXP file...");
-    println!("  Source: {}", source.display());
-    println!("  Output: {}", output.display());
+    use cxp_core::CommitInfo;
 
-    // Check for incompatible feature combinations
-    if images && embeddings {
-        return Err(anyhow::anyhow!(
-            "Cannot use both --embeddings and --images. Use --images with a multimodal model instead."
-        ));
-    }
+    let print_commit = |commit: &CommitInfo| {
+        let date = chrono::DateTime::from_timestamp(commit.time, 0)
+            .map(|t| t.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        println!("{}  {}  {:<20}  {}", commit.short_id(), date, commit.author, commit.summary());
+    };
 
-    #[cfg(all(feature = "embeddings", feature = "search"))]
-    if embeddings {
-        println!("  Embeddings: enabled (text only)");
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    let history = reader.git_history()?.ok_or_else(|| {
+        anyhow::anyhow!("This CXP file has no git history. Use 'cxp build --git-history' to create one.")
+    })?;
+
+    if let Some(query) = query {
+        #[cfg(all(feature = "embeddings", feature = "search"))]
         if let Some(model_path) = model {
-            println!("  Model: {}", model_path.display());
+            let model_name = history.embedding_model.clone().unwrap_or_default();
+            let model = cxp_core::EmbeddingModel::from_name(&model_name)
+                .ok_or_else(|| anyhow::anyhow!("Commit messages have no embeddings (built without --embeddings)"))?;
+            reader.load_query_model(model_path, model)?;
         }
+        #[cfg(not(all(feature = "embeddings", feature = "search")))]
+        if model.is_some() {
+            return Err(anyhow::anyhow!(
+                "Semantic search is not enabled. Rebuild cxp-cli with --features embeddings,search"
+            ));
+        }
+
+        println!("Commits matching \"{}\":", query);
+        println!();
+        let hits = reader.search_history(query, top_k)?;
+        if hits.is_empty() {
+            println!("No matching commits.");
+        }
+        for hit in &hits {
+            print!("[{:.3}] ", hit.score);
+            print_commit(&hit.commit);
+        }
+        return Ok(());
     }
 
-    #[cfg(feature = "multimodal")]
-    if images {
-        println!("  Images: enabled (multimodal)");
-        if let Some(model_path) = model {
-            println!("  Model: {}", model_path.display());
+    if let Some(path) = file_path {
+        let summary = history
+            .file(path)
+            .ok_or_else(|| anyhow::anyhow!("No commits touching '{}' in the stored history", path))?;
+        println!("File: {}", path);
+        println!("  Last change: {} by {}", &summary.last_commit[..summary.last_commit.len().min(8)], summary.last_author);
+        println!("  Commits: {}", summary.commits);
+        println!("  Authors:");
+        for (author, count) in &summary.authors {
+            println!("    {:<30} {:>4} commits", author, count);
+        }
+        println!();
+        for commit in history.commits_for(path).into_iter().take(top_k) {
+            print_commit(commit);
         }
+        return Ok(());
     }
 
+    println!("Git history: {} commits from {}", history.commits.len(), &history.head[..history.head.len().min(8)]);
     println!();
-
-    let start = Instant::now();
-
-    let mut builder = CxpBuilder::new(source);
-
-    // Enable images if requested
-    #[cfg(feature = "multimodal")]
-    if images {
-        builder.with_images();
+    for commit in history.commits.iter().take(top_k) {
+        print_commit(commit);
     }
-
-    #[cfg(not(feature = "multimodal"))]
-    if images {
-        return Err(anyhow::anyhow!(
-            "Image processing is not enabled. Rebuild cxp-cli with --features multimodal,search"
-        ));
+    println!();
+    println!("Authors:");
+    for (author, count) in history.authors() {
+        println!("  {:<30} {:>4} commits", author, count);
     }
 
-    builder
-        .scan()
-        .context("Failed to scan directory")?
-        .process()
-        .context("Failed to process files")?;
+    Ok(())
+}
 
-    // Generate embeddings if requested
-    #[cfg(all(feature = "embeddings", feature = "search"))]
-    if embeddings {
-        use cxp_core::EmbeddingModel;
+fn tag_command(
+    file: &std::path::Path,
+    path: &str,
+    add: &[String],
+    remove: &[String],
+    notes: &[(String, String)],
+) -> Result<()> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    if reader.file_entry(path)?.is_none() {
+        anyhow::bail!("{} is not in {}", path, file.display());
+    }
 
-        let model_path = model.ok_or_else(|| {
-            anyhow::anyhow!(
-                "Model path is required for embeddings. Use --model <path> to specify the model directory."
-            )
-        })?;
+    let mut annotations = reader.annotations().clone();
+    drop(reader);
 
-        builder
-            .with_embeddings(model_path, EmbeddingModel::MiniLM)
-            .context("Failed to initialize embeddings")?;
+    if !add.is_empty() || !remove.is_empty() || !notes.is_empty() {
+        annotations.tag(path, add.iter().cloned());
+        annotations.untag(path, remove);
+        for (key, value) in notes {
+            annotations.annotate(path, key.clone(), value.clone());
+        }
+        annotations.write_to(file).context("Failed to write annotations")?;
     }
 
-    #[cfg(not(all(feature = "embeddings", feature = "search")))]
-    if embeddings {
-        return Err(anyhow::anyhow!(
-            "Embeddings feature is not enabled. Rebuild cxp-cli with --features embeddings,search"
-        ));
+    let annotation = annotations.get(path).cloned().unwrap_or_default();
+    println!("{}", path);
+    if annotation.tags.is_empty() {
+        println!("  Tags: (none)");
+    } else {
+        println!("  Tags: {}", annotation.tags.iter().cloned().collect::<Vec<_>>().join(", "));
     }
-
-    // Generate multimodal embeddings if images are enabled
-    #[cfg(all(feature = "multimodal", feature = "search"))]
-    if images {
-        let model_path = model.ok_or_else(|| {
-            anyhow::anyhow!(
-                "Model path is required for multimodal embeddings. Use --model <path> to specify the SigLIP 2 model directory."
-            )
-        })?;
-
-        builder
-            .with_multimodal_embeddings(model_path)
-            .context("Failed to initialize multimodal embeddings")?;
+    for (key, value) in &annotation.notes {
+        println!("  {}: {}", key, value);
     }
 
-    builder
-        .build(output)
-        .context("Failed to build CXP file")?;
+    Ok(())
+}
 
-    let duration = start.elapsed();
+fn extract_file(
+    file: &std::path::Path,
+    path: &str,
+    output: Option<&std::path::Path>,
+    snapshot: Option<&str>,
+    track_usage: bool,
+) -> Result<()> {
+    let reader = open_at_snapshot(file, snapshot)?.with_usage_metrics(track_usage);
 
-    println!();
-    println!("Done in {:.2}s", duration.as_secs_f64());
-    println!();
+    let content = reader.read_file(path).context("Failed to read file from CXP")?;
+    if let Some(usage) = reader.usage_recorder() {
+        usage.record_read(path);
+    }
 
-    // Show summary
-    show_info(output)?;
+    match output {
+        Some(output_path) => {
+            std::fs::write(output_path, &content)?;
+            println!("Extracted {} bytes to {}", content.len(), output_path.display());
+        }
+        None => {
+            // Write to stdout
+            std::io::stdout().write_all(&content)?;
+        }
+    }
 
     Ok(())
 }
 
-fn show_info(file: &PathBuf) -> Result<()> {
-    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
-    let manifest = reader.manifest();
+fn extract_matching(
+    file: &std::path::Path,
+    pattern: cxp_core::PathPattern,
+    dest: &std::path::Path,
+    snapshot: Option<&str>,
+) -> Result<()> {
+    let reader = open_at_snapshot(file, snapshot)?;
+    let count = reader.files_matching(&pattern).len();
+    if count == 0 {
+        return Err(anyhow::anyhow!("No files in {} match the pattern", file.display()));
+    }
 
-    println!("CXP File Information");
-    println!("====================");
-    println!();
-    println!("Version:        {}", manifest.version);
-    println!("Created:        {}", manifest.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
-    println!();
-    println!("Statistics:");
-    println!("  Total files:  {}", manifest.stats.total_files);
-    println!("  Unique chunks:{}", manifest.stats.unique_chunks);
-    println!(
-        "  Original size:{:.2} MB",
-        manifest.stats.original_size_bytes as f64 / 1024.0 / 1024.0
-    );
-    println!(
-        "  CXP size:     {:.2} MB",
-        manifest.stats.cxp_size_bytes as f64 / 1024.0 / 1024.0
-    );
-    println!(
-        "  Compression:  {:.1}%",
-        manifest.stats.compression_ratio * 100.0
-    );
+    let options = cxp_core::ExtractOptions::new().with_pattern(pattern);
+    let stats = reader.extract_all_with(dest, &options).context("Failed to extract files")?;
     println!(
-        "  Dedup savings:{:.1}%",
-        manifest.stats.dedup_savings_percent
+        "Extracted {} of {} matching files ({}) to {}",
+        stats.files_written,
+        count,
+        format_size(stats.bytes_written),
+        dest.display()
     );
-    println!();
 
-    if !manifest.file_types.is_empty() {
-        println!("File Types:");
-        let mut types: Vec<_> = manifest.file_types.iter().collect();
-        types.sort_by(|a, b| b.1.count.cmp(&a.1.count));
+    Ok(())
+}
 
-        for (ext, info) in types.iter().take(10) {
-            println!(
-                "  .{:<10} {:>5} files ({:.2} KB)",
-                ext,
-                info.count,
-                info.total_bytes as f64 / 1024.0
-            );
+fn export_command(
+    file: &PathBuf,
+    format: &str,
+    per: &str,
+    embeddings: bool,
+    output: Option<&std::path::Path>,
+) -> Result<()> {
+    use cxp_core::export::{write_jsonl, ExportFormat};
+
+    let format: ExportFormat = format.parse()?;
+    let options = cxp_core::ExportOptions::new()
+        .with_granularity(per.parse()?)
+        .with_embeddings(embeddings);
+
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    if embeddings {
+        if !reader.has_embeddings() {
+            return Err(anyhow::anyhow!(
+                "This CXP file has no embeddings. Use 'cxp build --embeddings --model <path>' to create one."
+            ));
         }
+        reader.load_embeddings()?;
     }
 
-    if !manifest.extensions.is_empty() {
-        println!();
-        println!("Extensions: {}", manifest.extensions.join(", "));
+    let stats = match (format, output) {
+        (ExportFormat::Jsonl, Some(path)) => {
+            let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            write_jsonl(&reader, file, &options)?
+        }
+        (ExportFormat::Jsonl, None) => {
+            let stdout = std::io::stdout();
+            return write_jsonl(&reader, stdout.lock(), &options).map(|_| ()).map_err(Into::into);
+        }
+        #[cfg(feature = "arrow")]
+        (ExportFormat::Parquet, Some(path)) => {
+            cxp_core::export::write_parquet(&reader, std::fs::File::create(path)?, &options)?
+        }
+        #[cfg(feature = "arrow")]
+        (ExportFormat::Parquet, None) => {
+            return Err(anyhow::anyhow!("Parquet output needs a file. Use -o <output.parquet>"));
+        }
+        #[cfg(not(feature = "arrow"))]
+        (ExportFormat::Parquet, _) => {
+            return Err(anyhow::anyhow!(
+                "Parquet export is not enabled. Rebuild cxp-cli with --features arrow"
+            ));
+        }
+    };
+
+    println!("Exported {} records from {} files", stats.records, stats.files);
+    if embeddings {
+        println!("  With embeddings: {}", stats.embedded);
+    }
+    if let Some(path) = output {
+        println!("  Output: {}", path.display());
     }
+    Ok(())
+}
 
-    // Show embedding info if present
-    if let Some(ref model) = manifest.embedding_model {
-        println!();
-        println!("Embeddings:");
-        println!("  Model:      {}", model);
-        if let Some(dim) = manifest.embedding_dim {
-            println!("  Dimensions: {}", dim);
-        }
+fn unpack_command(
+    file: &std::path::Path,
+    dest: &std::path::Path,
+    overwrite: &str,
+    mtime: bool,
+    verify: bool,
+    snapshot: Option<&str>,
+) -> Result<()> {
+    let options = cxp_core::ExtractOptions::new()
+        .with_overwrite(overwrite.parse()?)
+        .with_mtime(mtime)
+        .with_verify(verify);
+
+    let reader = open_at_snapshot(file, snapshot)?;
+    let start = Instant::now();
+    let stats = reader.extract_all_with(dest, &options).context("Failed to unpack CXP file")?;
+
+    println!("Unpacked {} into {}", file.display(), dest.display());
+    println!("  Files written:   {}", stats.files_written);
+    if stats.files_skipped > 0 {
+        println!("  Files skipped:   {} (already present)", stats.files_skipped);
     }
+    println!("  Bytes written:   {}", cxp_core::format_bytes(stats.bytes_written));
+    if verify {
+        println!("  Chunks verified: {}", stats.chunks_verified);
+    }
+    println!("  Time:            {:.2}s", start.elapsed().as_secs_f64());
 
     Ok(())
 }
 
-fn list_files(file: &PathBuf, long: bool) -> Result<()> {
-    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+fn import_command(
+    input: &PathBuf,
+    output: &PathBuf,
+    options: cxp_core::ImportOptions,
+    redact: bool,
+    show_progress: bool,
+) -> Result<()> {
+    use cxp_core::JsonlRecords;
+    use std::io::BufRead;
 
-    let mut paths: Vec<_> = reader.file_paths();
-    paths.sort();
+    let start = Instant::now();
+    let reader: Box<dyn BufRead> = if input.as_os_str() == "-" {
+        Box::new(std::io::stdin().lock())
+    } else {
+        let file = std::fs::File::open(input).with_context(|| format!("Failed to open {}", input.display()))?;
+        Box::new(std::io::BufReader::new(file))
+    };
+    let records = JsonlRecords::new(reader, options)
+        .collect::<cxp_core::Result<Vec<_>>>()
+        .context("Failed to read records")?;
 
-    if long {
-        println!("{:<60} {:>10} {:>6}", "PATH", "SIZE", "CHUNKS");
-        println!("{}", "-".repeat(80));
+    println!("Importing {} records into {}", records.len(), output.display());
 
-        for path in paths {
-            if let Some(entry) = reader.file_map.files.get(path) {
-                println!(
-                    "{:<60} {:>10} {:>6}",
-                    path,
-                    format_size(entry.size),
-                    entry.chunks.len()
-                );
-            }
-        }
-    } else {
-        for path in paths {
-            println!("{}", path);
-        }
+    let mut builder = CxpBuilder::new(".");
+    if show_progress {
+        builder.with_progress(std::sync::Arc::new(progress::BuildProgress::default()));
     }
+    #[cfg(feature = "redact")]
+    builder.with_redaction(redact);
+    #[cfg(not(feature = "redact"))]
+    let _ = redact;
+    builder.add_records(records)?;
+    builder
+        .process()
+        .context("Failed to process records")?
+        .build(output)
+        .context("Failed to build CXP file")?;
+
+    println!();
+    println!("Done in {:.2}s", start.elapsed().as_secs_f64());
+    println!();
+    show_info(output)?;
 
     Ok(())
 }
 
-fn extract_file(file: &PathBuf, path: &str, output: Option<&std::path::Path>) -> Result<()> {
-    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+fn search_all_command(
+    args: &[String],
+    top_k: usize,
+    memory_mb: u64,
+    model: Option<PathBuf>,
+    keyword: bool,
+    json: bool,
+) -> Result<()> {
+    let (query, archives) = args.split_last().context("Missing query")?;
 
-    let content = reader.read_file(path).context("Failed to read file from CXP")?;
+    let mut options = cxp_core::quick::Options::new().with_embeddings(!keyword);
+    if let Some(model) = model {
+        options = options.with_model_dir(model);
+    }
+    let search = cxp_core::FederatedSearch::new(archives)
+        .with_memory_limit(memory_mb * 1024 * 1024)
+        .with_options(options);
 
-    match output {
-        Some(output_path) => {
-            std::fs::write(output_path, &content)?;
-            println!("Extracted {} bytes to {}", content.len(), output_path.display());
+    let start = Instant::now();
+    let results = search.search(query, top_k);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        println!("Federated Search: \"{}\"", query);
+        println!("==================");
+        println!();
+        println!(
+            "Archives: {} searched, {} failed ({} batches, {:.2}s)",
+            results.archives_searched,
+            results.failures.len(),
+            results.batches,
+            start.elapsed().as_secs_f64()
+        );
+        for failure in &results.failures {
+            println!("  {} - {}", failure.archive.display(), failure.error);
         }
-        None => {
-            // Write to stdout
-            std::io::stdout().write_all(&content)?;
+        println!();
+
+        if results.hits.is_empty() {
+            println!("No results found.");
         }
+        for (i, hit) in results.hits.iter().enumerate() {
+            let archive = hit.archive.file_name().unwrap_or(hit.archive.as_os_str()).to_string_lossy();
+            println!(
+                "{}. [{}] {} (chunk {}, score {:.2}, rank {})",
+                i + 1,
+                archive,
+                hit.hit.file_path,
+                hit.hit.chunk_index,
+                hit.hit.score,
+                hit.rank
+            );
+            let preview: String = hit.hit.text.lines().take(3).collect::<Vec<_>>().join("\n   ");
+            println!("   {}", preview.chars().take(240).collect::<String>());
+            println!();
+        }
+    }
+
+    if results.archives_searched == 0 {
+        return Err(anyhow::anyhow!("None of the {} archives could be searched", archives.len()));
     }
 
     Ok(())
@@ -475,12 +3345,32 @@ struct SearchMatch {
     line_numbers: Vec<usize>,
 }
 
-fn query_files(file: &PathBuf, query: &str, top_k: usize, ignore_case: bool) -> Result<()> {
-    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+fn query_files(
+    file: &PathBuf,
+    query: &str,
+    top_k: usize,
+    ignore_case: bool,
+    pattern: Option<&cxp_core::PathPattern>,
+    track_usage: bool,
+) -> Result<()> {
+    let reader = CxpReader::open(file)
+        .context("Failed to open CXP file")?
+        .with_usage_metrics(track_usage);
 
     println!("Searching for: \"{}\"", query);
     println!();
 
+    // Summaries (built with --summaries) point at the right file or directory first
+    let entry_points = reader.search_summaries(query, 3)?;
+    if !entry_points.is_empty() {
+        println!("Entry points:");
+        for hit in &entry_points {
+            let suffix = if hit.kind == cxp_core::SummaryKind::Directory { "/" } else { "" };
+            println!("  {}{}  {}", hit.path, suffix, hit.summary);
+        }
+        println!();
+    }
+
     let search_term = if ignore_case {
         query.to_lowercase()
     } else {
@@ -489,8 +3379,12 @@ fn query_files(file: &PathBuf, query: &str, top_k: usize, ignore_case: bool) ->
 
     let mut results: Vec<SearchMatch> = Vec::new();
 
-    // Search through all files
-    for path in reader.file_paths() {
+    // Search through all files (or those matching --glob/--regex)
+    let paths = match pattern {
+        Some(pattern) => reader.files_matching(pattern),
+        None => reader.file_paths(),
+    };
+    for path in paths {
         if let Ok(content) = reader.read_file(path) {
             // Convert to string (skip binary content)
             if let Ok(text) = String::from_utf8(content) {
@@ -545,11 +3439,16 @@ fn query_files(file: &PathBuf, query: &str, top_k: usize, ignore_case: bool) ->
     }
 
     // Sort by number of matches (descending)
-    results.sort_by(|a, b| b.matches.cmp(&a.matches));
+    results.sort_by_key(|r| std::cmp::Reverse(r.matches));
 
     // Show top-k results
     let display_count = results.len().min(top_k);
 
+    if let Some(usage) = reader.usage_recorder() {
+        let paths: Vec<&str> = results.iter().take(display_count).map(|r| r.path.as_str()).collect();
+        usage.record_query(&paths);
+    }
+
     if results.is_empty() {
         println!("No matches found.");
         return Ok(());
@@ -577,11 +3476,26 @@ fn query_files(file: &PathBuf, query: &str, top_k: usize, ignore_case: bool) ->
         println!();
     }
 
+    // The knowledge graph (built with --graph) adds files linked to the hits
+    if let Some(graph) = reader.graph()? {
+        let hits: Vec<&str> = results.iter().take(display_count).map(|r| r.path.as_str()).collect();
+        let neighborhood = graph.around_files(&hits, 1);
+        let related: Vec<&str> = neighborhood.files().into_iter().filter(|path| !hits.contains(path)).take(5).collect();
+        if !related.is_empty() {
+            println!("Related files:");
+            for path in related {
+                println!("  {}", path);
+            }
+            println!();
+        }
+    }
+
     Ok(())
 }
 
 /// Perform semantic search using embeddings
 #[cfg(all(feature = "embeddings", feature = "search"))]
+#[allow(clippy::too_many_arguments)]
 fn search_semantic(
     file: &PathBuf,
     query: Option<&str>,
@@ -591,8 +3505,16 @@ fn search_semantic(
     result_type: &str,
     #[allow(unused_variables)]
     image_query: Option<&std::path::Path>,
+    expand: &str,
+    hyde_command: Option<&str>,
+    cluster: Option<usize>,
+    load_options: cxp_core::LoadOptions,
+    temp_policy: &cxp_core::TempPolicy,
+    track_usage: bool,
 ) -> Result<()> {
-    use cxp_core::{EmbeddingEngine, EmbeddingModel};
+    use cxp_core::{EmbeddingEngine, ExpansionKind};
+
+    let expansion: ExpansionKind = expand.parse()?;
 
     // Determine query type
     let is_image_query = image_query.is_some();
@@ -616,7 +3538,10 @@ fn search_semantic(
     println!();
 
     // Open CXP file
-    let mut reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    let reader = CxpReader::open(file)
+        .context("Failed to open CXP file")?
+        .with_temp_policy(temp_policy.clone())
+        .with_usage_metrics(track_usage);
 
     // Check if file has embeddings
     if !reader.has_embeddings() {
@@ -625,9 +3550,6 @@ fn search_semantic(
         ));
     }
 
-    println!("Loading embeddings...");
-    reader.load_embeddings().context("Failed to load embeddings")?;
-
     // Load embedding model and generate query embedding
     let model_path = model.ok_or_else(|| {
         anyhow::anyhow!(
@@ -635,37 +3557,54 @@ fn search_semantic(
         )
     })?;
 
-    let query_embedding = if is_image_query {
-        #[cfg(feature = "multimodal")]
-        {
-            use cxp_core::MultimodalEngine;
+    // Image queries go through the multimodal UnifiedIndex
+    #[cfg(feature = "multimodal")]
+    if let Some(image_path) = image_query {
+        return search_image(&reader, image_path, model_path, top_k, result_type);
+    }
 
-            println!("Loading multimodal model...");
-            let mut engine = MultimodalEngine::load(model_path)
-                .context("Failed to load multimodal model")?;
+    println!("Loading embeddings...");
+    reader.load_embeddings_with(load_options).context("Failed to load embeddings")?;
 
-            println!("Encoding image...");
-            engine.embed_image(image_query.unwrap())
-                .context("Failed to encode image")?
-        }
-        #[cfg(not(feature = "multimodal"))]
-        {
-            unreachable!() // Already checked above
+    if expansion != ExpansionKind::None {
+        if cluster.is_some() {
+            return Err(anyhow::anyhow!("--cluster cannot be combined with --expand"));
         }
-    } else {
-        println!("Loading embedding model...");
-        let engine = EmbeddingEngine::load(model_path, EmbeddingModel::MiniLM)
-            .context("Failed to load embedding model")?;
+        return search_expanded(&reader, query.unwrap(), model_path, expansion, hyde_command, top_k);
+    }
 
-        println!("Encoding query...");
-        engine.embed(query.unwrap()).context("Failed to encode query")?
-    };
+    println!("Loading embedding model...");
+    let mut engine = EmbeddingEngine::load(model_path, archive_model(&reader))
+        .context("Failed to load embedding model")?;
+
+    println!("Encoding query...");
+    let query_embedding = engine.embed_query(query.unwrap()).context("Failed to encode query")?;
 
     // Search
-    println!("Searching...");
-    let results = reader
-        .search_semantic(&query_embedding, top_k)
-        .context("Search failed")?;
+    let results = match cluster {
+        Some(cluster) => {
+            println!("Searching cluster {}...", cluster);
+            reader.search_cluster(&query_embedding, cluster, top_k)
+        }
+        None => {
+            println!("Searching...");
+            reader.search_semantic(&query_embedding, top_k)
+        }
+    }
+    .context("Search failed")?;
+
+    if let Some(usage) = reader.usage_recorder() {
+        let hashes: std::collections::HashSet<&str> =
+            results.iter().flat_map(|r| reader.embedding_chunk_hashes(r.id)).collect();
+        let paths: Vec<&str> = reader
+            .file_map
+            .files
+            .iter()
+            .filter(|(_, entry)| entry.chunks.iter().any(|c| hashes.contains(c.hash.as_str())))
+            .map(|(path, _)| path.as_str())
+            .collect();
+        usage.record_query(&paths);
+    }
 
     if results.is_empty() {
         println!();
@@ -709,6 +3648,234 @@ fn search_semantic(
     Ok(())
 }
 
+/// Search the multimodal UnifiedIndex with an image query
+#[cfg(all(feature = "multimodal", feature = "search"))]
+fn search_image(
+    reader: &CxpReader,
+    image_path: &std::path::Path,
+    model_path: &std::path::Path,
+    top_k: usize,
+    result_type: &str,
+) -> Result<()> {
+    use cxp_core::{EntryType, MultimodalEngine};
+
+    if !matches!(result_type.to_lowercase().as_str(), "text" | "image" | "all") {
+        return Err(anyhow::anyhow!("Unknown result type '{}' (expected text, image or all)", result_type));
+    }
+
+    println!("Loading multimodal index...");
+    reader.load_unified_index().context("Failed to load multimodal index")?;
+
+    println!("Loading multimodal model...");
+    let mut engine = MultimodalEngine::load(model_path).context("Failed to load multimodal model")?;
+
+    println!("Searching...");
+    let results = reader
+        .search_with_image(image_path, &mut engine, top_k, result_type)
+        .context("Search failed")?;
+
+    if results.is_empty() {
+        println!();
+        println!("No results found.");
+        return Ok(());
+    }
+
+    println!();
+    println!("Found {} results:", results.len());
+    println!();
+
+    for (i, result) in results.iter().enumerate() {
+        match &result.entry_type {
+            EntryType::Image { file_path } => {
+                println!("{}. [image] {} (similarity: {:.4})", i + 1, file_path, result.similarity());
+            }
+            EntryType::Text { chunk_id, file_path } => {
+                println!("{}. [text] {} (similarity: {:.4})", i + 1, file_path, result.similarity());
+                if let Ok(text) = reader.get_chunk_text(*chunk_id) {
+                    for line in text.lines().take(3) {
+                        println!("    {}", line);
+                    }
+                }
+            }
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Compare HNSW search with exact search and print recall@k
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn eval_recall_command(
+    file: &PathBuf,
+    top_k: usize,
+    samples: usize,
+    queries: &[String],
+    model: Option<&std::path::Path>,
+) -> Result<()> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    if !reader.has_embeddings() {
+        return Err(anyhow::anyhow!("This CXP file has no embeddings"));
+    }
+    reader.load_embeddings().context("Failed to load embeddings")?;
+
+    let (embeddings, source) = if queries.is_empty() {
+        (reader.sample_query_embeddings(samples)?, "sampled from stored embeddings")
+    } else {
+        let model_path = model.ok_or_else(|| anyhow::anyhow!("--query requires --model <path>"))?;
+        let mut engine = cxp_core::EmbeddingEngine::load(model_path, archive_model(&reader))
+            .context("Failed to load embedding model")?;
+        let texts: Vec<&str> = queries.iter().map(String::as_str).collect();
+        let embeddings = engine.embed_queries(&texts).context("Failed to encode queries")?;
+        (embeddings.into_iter().map(cxp_core::RecallQuery::from).collect(), "from --query")
+    };
+    if embeddings.is_empty() {
+        return Err(anyhow::anyhow!("No queries to evaluate"));
+    }
+
+    let report = reader.evaluate_recall(&embeddings, top_k)?;
+    let per_query = |time: std::time::Duration| time.as_secs_f64() * 1000.0 / report.queries as f64;
+
+    println!("Recall evaluation: {}", file.display());
+    println!("  Queries:     {} ({})", report.queries, source);
+    println!("  Recall@{}:   {:.3} (min {:.3})", report.k, report.mean_recall, report.min_recall);
+    println!("  HNSW:        {:.3} ms/query", per_query(report.hnsw_time));
+    println!("  Exact:       {:.3} ms/query", per_query(report.exact_time));
+    Ok(())
+}
+
+/// Cluster the chunk embeddings of an archive and store the topic map in it
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn cluster_command(file: &std::path::Path, k: usize, max_iterations: usize, json: bool) -> Result<()> {
+    let reader = CxpReader::open(file).context("Failed to open CXP file")?;
+    if !reader.has_embeddings() {
+        return Err(anyhow::anyhow!(
+            "This CXP file has no embeddings. Use 'cxp build --embeddings --model <path>' to create one."
+        ));
+    }
+    reader.load_embeddings().context("Failed to load embeddings")?;
+
+    let start = Instant::now();
+    let options = cxp_core::ClusterOptions::default().with_k(k).with_max_iterations(max_iterations);
+    let map = reader.cluster(&options).context("Failed to cluster embeddings")?;
+    drop(reader);
+    map.write_to(file).context("Failed to write clusters")?;
+
+    if json {
+        let clusters: Vec<serde_json::Value> = map
+            .clusters
+            .iter()
+            .map(|c| serde_json::json!({ "id": c.id, "label": c.label, "chunks": c.chunks, "files": c.files }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&clusters)?);
+        return Ok(());
+    }
+
+    println!("Topic Map");
+    println!("=========");
+    println!();
+    println!("Clusters:  {} ({} chunks)", map.clusters.len(), map.assignments.len());
+    for cluster in &map.clusters {
+        println!();
+        println!("[{}] {} ({} chunks)", cluster.id, cluster.label_text(), cluster.chunks);
+        for path in cluster.files.iter().take(5) {
+            println!("    {}", path);
+        }
+        if cluster.files.len() > 5 {
+            println!("    ... ({} more files)", cluster.files.len() - 5);
+        }
+    }
+    println!();
+    println!("Done in {:.2}s; search one cluster with `cxp search --cluster <id>`", start.elapsed().as_secs_f64());
+
+    Ok(())
+}
+
+/// Text model an archive was embedded with (MiniLM for archives that do not record it)
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn archive_model(reader: &CxpReader) -> cxp_core::EmbeddingModel {
+    reader
+        .manifest()
+        .embedding_model
+        .as_deref()
+        .and_then(cxp_core::EmbeddingModel::from_name)
+        .unwrap_or(cxp_core::EmbeddingModel::MiniLM)
+}
+
+/// Semantic search over the variants of an expanded query
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn search_expanded(
+    reader: &CxpReader,
+    query: &str,
+    model_path: &std::path::Path,
+    kind: cxp_core::ExpansionKind,
+    hyde_command: Option<&str>,
+    top_k: usize,
+) -> Result<()> {
+    use cxp_core::{ExpansionKind, Fusion, HydeExpansion, NoExpansion, QueryExpansion, SynonymExpansion};
+
+    let hyde_command = hyde_command.map(str::to_string).or_else(|| std::env::var("CXP_HYDE_COMMAND").ok());
+    let expansion: Box<dyn QueryExpansion> = match (kind, hyde_command.as_deref()) {
+        (ExpansionKind::None, _) => Box::new(NoExpansion),
+        (ExpansionKind::Synonyms, _) => Box::new(SynonymExpansion::new()),
+        (ExpansionKind::Hyde, Some(command)) => Box::new(HydeExpansion::command(command_words(command)?)?),
+        (ExpansionKind::Hyde, None) => Box::new(HydeExpansion::template()),
+    };
+
+    println!("Loading embedding model...");
+    reader
+        .load_query_model(model_path, archive_model(reader))
+        .context("Failed to load embedding model")?;
+
+    println!("Expanding query...");
+    let variants = expansion.expand(query).context("Query expansion failed")?;
+    for variant in &variants {
+        let first_line = variant.lines().next().unwrap_or("");
+        println!("    - {}", first_line);
+    }
+
+    println!("Searching...");
+    let queries: Vec<&str> = variants.iter().map(String::as_str).collect();
+    let results = reader.search_multi(&queries, top_k, Fusion::rrf()).context("Search failed")?;
+
+    if results.is_empty() {
+        println!();
+        println!("No results found.");
+        return Ok(());
+    }
+
+    println!();
+    println!("Found {} results:", results.len());
+    println!();
+
+    for (i, result) in results.iter().enumerate() {
+        println!(
+            "{}. {} (score: {:.4}, {}/{} variants)",
+            i + 1,
+            result.file_path.as_deref().unwrap_or("[unknown file]"),
+            result.score,
+            result.hits,
+            variants.len()
+        );
+        match reader.get_chunk_text(result.id) {
+            Ok(text) => {
+                for line in text.lines().take(5) {
+                    let truncated = if line.len() > 100 {
+                        format!("{}...", &line[..97])
+                    } else {
+                        line.to_string()
+                    };
+                    println!("    {}", truncated);
+                }
+            }
+            Err(_) => println!("    [Could not retrieve chunk content]"),
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
 /// Generate and display embedding for an image (debugging tool)
 #[cfg(all(feature = "multimodal", feature = "search"))]
 fn embed_image_command(
@@ -763,6 +3930,196 @@ fn embed_image_command(
     Ok(())
 }
 
+/// Upload an archive to object storage, resuming an interrupted upload
+#[cfg(feature = "cloud")]
+fn push_command(file: &std::path::Path, url: &str, part_size_mb: usize, restart: bool) -> Result<()> {
+    use cxp_core::{CxpStorageBackend, ObjectStoreBackend};
+
+    if !file.exists() {
+        return Err(anyhow::anyhow!("File not found: {}", file.display()));
+    }
+
+    let backend = ObjectStoreBackend::from_url(url)?.with_part_size(part_size_mb * 1024 * 1024);
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start async runtime")?;
+
+    println!("Pushing {} -> {}", file.display(), url);
+    let start = Instant::now();
+    let stats = runtime.block_on(async {
+        if restart {
+            backend.discard_upload(file).await?;
+        }
+        backend.push(file).await
+    })?;
+
+    if stats.resumed_parts > 0 {
+        println!("Resumed after {} parts", stats.resumed_parts);
+    }
+    println!(
+        "Uploaded {} in {} request(s) ({:.1}s)",
+        format_size(stats.bytes),
+        stats.parts,
+        start.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
+
+/// Download an archive from object storage
+#[cfg(feature = "cloud")]
+fn pull_command(url: &str, output: &std::path::Path) -> Result<()> {
+    use cxp_core::{CxpStorageBackend, ObjectStoreBackend};
+
+    let backend = ObjectStoreBackend::from_url(url)?;
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start async runtime")?;
+
+    println!("Pulling {} -> {}", url, output.display());
+    let start = Instant::now();
+    let stats = runtime.block_on(backend.pull(output))?;
+
+    // Make sure what arrived is a readable archive
+    let reader = CxpReader::open(output).context("Downloaded file is not a valid CXP archive")?;
+    println!(
+        "Downloaded {} ({} files, {:.1}s)",
+        format_size(stats.bytes),
+        reader.manifest().stats.total_files,
+        start.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
+
+/// Embed the probe set with every requested engine and report cosine deviations
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm"))]
+fn verify_model_command(model_path: &std::path::Path, engines: &[String], threshold: f32) -> Result<()> {
+    use cxp_core::{compare_embeddings, PROBE_TEXTS};
+
+    println!("Verifying model: {}", model_path.display());
+    println!("Probe set: {} texts", PROBE_TEXTS.len());
+    println!();
+
+    let mut outputs: Vec<(String, Vec<Vec<f32>>)> = Vec::new();
+    let mut failed = false;
+
+    for engine in engines {
+        let name = engine.trim().to_lowercase();
+        let result = match name.as_str() {
+            "ort" => embed_probes_ort(model_path)?,
+            "tract" => embed_probes_tract(model_path)?,
+            other => {
+                return Err(anyhow::anyhow!("Unknown engine '{}'. Use: ort, tract", other));
+            }
+        };
+
+        let Some((batch, single)) = result else {
+            println!("  {}: skipped (not enabled in this build)", name);
+            continue;
+        };
+
+        // Batched and single-text embeddings must agree (padding must not leak into pooling)
+        let consistency = compare_embeddings(&batch, &single)?;
+        let ok = consistency.min_cosine >= threshold;
+        failed |= !ok;
+        println!(
+            "  {}: dims={}, batch vs single min cosine {:.6} {}",
+            name,
+            batch.first().map(|e| e.len()).unwrap_or(0),
+            consistency.min_cosine,
+            if ok { "OK" } else { "FAIL" }
+        );
+
+        outputs.push((name, batch));
+    }
+
+    if outputs.len() < 2 {
+        println!();
+        println!("Need at least two engines to compare across engines.");
+    }
+
+    for i in 0..outputs.len() {
+        for j in (i + 1)..outputs.len() {
+            let (name_a, a) = &outputs[i];
+            let (name_b, b) = &outputs[j];
+            let deviation = compare_embeddings(a, b)?;
+
+            println!();
+            println!("{} vs {}", name_a, name_b);
+            println!("{}", "=".repeat(name_a.len() + name_b.len() + 4));
+
+            for (text, cosine) in PROBE_TEXTS.iter().zip(&deviation.cosines) {
+                let preview: String = text.chars().take(40).collect::<String>().replace('\n', " ");
+                println!(
+                    "  {:.6}  {}{}",
+                    cosine,
+                    preview,
+                    if *cosine < threshold { "  <-- below threshold" } else { "" }
+                );
+            }
+
+            println!();
+            println!("  Min cosine:   {:.6}", deviation.min_cosine);
+            println!("  Mean cosine:  {:.6}", deviation.mean_cosine);
+            println!("  Max abs diff: {:.6}", deviation.max_abs_diff);
+
+            failed |= deviation.min_cosine < threshold;
+        }
+    }
+
+    println!();
+    if failed {
+        return Err(anyhow::anyhow!(
+            "Embedding deviation exceeds threshold (min cosine < {})",
+            threshold
+        ));
+    }
+
+    println!("All engines agree (min cosine >= {})", threshold);
+    Ok(())
+}
+
+/// Embed the probe set with the ort engine as a batch and one text at a time
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm"))]
+#[allow(clippy::type_complexity, unused_variables)]
+fn embed_probes_ort(model_path: &std::path::Path) -> Result<Option<(Vec<Vec<f32>>, Vec<Vec<f32>>)>> {
+    #[cfg(feature = "embeddings")]
+    {
+        use cxp_core::{EmbeddingEngine, EmbeddingModel, PROBE_TEXTS};
+
+        let mut engine = EmbeddingEngine::load(model_path, EmbeddingModel::MiniLM)
+            .context("Failed to load model with ort")?;
+        let batch = engine.embed_batch(PROBE_TEXTS)?;
+        let single = PROBE_TEXTS
+            .iter()
+            .map(|text| engine.embed(text))
+            .collect::<cxp_core::Result<Vec<_>>>()?;
+        Ok(Some((batch, single)))
+    }
+
+    #[cfg(not(feature = "embeddings"))]
+    Ok(None)
+}
+
+/// Embed the probe set with the tract engine as a batch and one text at a time
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm"))]
+#[allow(clippy::type_complexity, unused_variables)]
+fn embed_probes_tract(model_path: &std::path::Path) -> Result<Option<(Vec<Vec<f32>>, Vec<Vec<f32>>)>> {
+    #[cfg(feature = "embeddings-wasm")]
+    {
+        use cxp_core::{EmbeddingModel, TractEmbeddingEngine, PROBE_TEXTS};
+
+        let engine = TractEmbeddingEngine::load(model_path, EmbeddingModel::MiniLM)
+            .context("Failed to load model with tract")?;
+        let batch = engine.embed_batch(PROBE_TEXTS)?;
+        let single = PROBE_TEXTS
+            .iter()
+            .map(|text| engine.embed(text))
+            .collect::<cxp_core::Result<Vec<_>>>()?;
+        Ok(Some((batch, single)))
+    }
+
+    #[cfg(not(feature = "embeddings-wasm"))]
+    Ok(None)
+}
+
 /// Detect user profile based on file types
 #[cfg(feature = "scanner")]
 fn detect_profile_command(paths: Vec<PathBuf>) -> Result<()> {
@@ -886,10 +4243,7 @@ fn detect_profile_command(paths: Vec<PathBuf>) -> Result<()> {
 /// Smart scan directories with profile-based filtering
 #[cfg(feature = "scanner")]
 fn smart_scan_command(paths: Vec<PathBuf>, profile_str: Option<String>, detailed: bool) -> Result<()> {
-    use cxp_core::scanner::{
-        ProfileDetector, QuickScanner, UserProfile, RelevanceScorer, Tier, TierManager,
-        IgnoreConfig, FileMetadata,
-    };
+    use cxp_core::scanner::{ProfileDetector, QuickScanner, ScanPlan, Tier, UserProfile};
 
     println!("Smart Scan");
     println!("==========");
@@ -914,21 +4268,7 @@ fn smart_scan_command(paths: Vec<PathBuf>, profile_str: Option<String>, detailed
 
     // Determine profile
     let profile = if let Some(profile_name) = profile_str {
-        match profile_name.to_lowercase().as_str() {
-            "developer" | "dev" => UserProfile::Developer,
-            "photographer" | "photo" => UserProfile::Photographer,
-            "designer" | "design" => UserProfile::Designer,
-            "writer" | "write" => UserProfile::Writer,
-            "student" => UserProfile::Student,
-            "business" | "biz" => UserProfile::Business,
-            "custom" => UserProfile::Custom,
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "Unknown profile: {}. Valid options: developer, photographer, designer, writer, student, business, custom",
-                    profile_name
-                ));
-            }
-        }
+        profile_name.parse::<UserProfile>()?
     } else {
         // Auto-detect profile
         println!("Auto-detecting profile...");
@@ -955,7 +4295,6 @@ fn smart_scan_command(paths: Vec<PathBuf>, profile_str: Option<String>, detailed
 
     // Get profile-specific config
     let scan_config = profile.default_config();
-    let ignore_config = IgnoreConfig::default();
 
     println!("Profile Settings:");
     println!("  Max file size:  {} MB", scan_config.max_file_size / 1024 / 1024);
@@ -967,86 +4306,20 @@ fn smart_scan_command(paths: Vec<PathBuf>, profile_str: Option<String>, detailed
     println!("Scanning files...");
     let start = Instant::now();
 
-    use walkdir::WalkDir;
-    let mut files_by_tier: Vec<(PathBuf, f64, Tier)> = Vec::new();
-    let mut total_scanned = 0;
-    let mut total_ignored = 0;
-
-    let scorer = RelevanceScorer::new(profile.clone());
-
-    for base_path in &paths {
-        for entry in WalkDir::new(base_path)
-            .follow_links(false)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-
-            // Skip directories
-            if !entry.file_type().is_file() {
-                continue;
-            }
-
-            total_scanned += 1;
-
-            // Check ignore patterns (use path string)
-            let path_str = path.to_string_lossy();
-            if ignore_config.should_ignore(&path_str).unwrap_or(false) {
-                total_ignored += 1;
-                continue;
-            }
-
-            // Check extension
-            if let Some(ext) = path.extension() {
-                let ext_str = ext.to_string_lossy().to_lowercase();
-                if !scan_config.file_extensions.is_empty()
-                    && !scan_config.file_extensions.iter().any(|e| e.to_lowercase() == ext_str)
-                {
-                    total_ignored += 1;
-                    continue;
-                }
-            } else {
-                total_ignored += 1;
-                continue;
-            }
-
-            // Check file size
-            if let Ok(metadata) = path.metadata() {
-                if metadata.len() > scan_config.max_file_size {
-                    total_ignored += 1;
-                    continue;
-                }
-            }
-
-            // Calculate relevance score
-            let score = if let Ok(file_meta) = FileMetadata::from_path(path) {
-                scorer.score_file(&file_meta)
-            } else {
-                0.5
-            };
-
-            let tier = Tier::from_score(score);
-            files_by_tier.push((path.to_path_buf(), score, tier));
-        }
-    }
-
+    let plan = ScanPlan::scan(&paths, profile).context("Failed to scan")?;
     let scan_duration = start.elapsed();
 
     // Categorize by tier
-    let mut tier_manager = TierManager::new();
-    for (path, score, _) in &files_by_tier {
-        tier_manager.add_file_with_score(path.to_string_lossy().to_string(), *score);
-    }
-
+    let tier_manager = plan.tier_manager();
     let stats = tier_manager.stats();
 
     println!();
     println!("Scan Results");
     println!("============");
     println!("  Duration:      {:.2}s", scan_duration.as_secs_f64());
-    println!("  Total scanned: {}", total_scanned);
-    println!("  Total ignored: {}", total_ignored);
-    println!("  Included:      {}", files_by_tier.len());
+    println!("  Total scanned: {}", plan.scanned);
+    println!("  Total ignored: {}", plan.ignored);
+    println!("  Included:      {}", plan.files.len());
     println!();
 
     println!("Tier Distribution:");
@@ -1056,19 +4329,9 @@ fn smart_scan_command(paths: Vec<PathBuf>, profile_str: Option<String>, detailed
     println!();
 
     // Calculate estimated sizes
-    let mut hot_size: u64 = 0;
-    let mut warm_size: u64 = 0;
-    let mut cold_size: u64 = 0;
-
-    for (path, _, tier) in &files_by_tier {
-        if let Ok(metadata) = path.metadata() {
-            match tier {
-                Tier::Hot => hot_size += metadata.len(),
-                Tier::Warm => warm_size += metadata.len(),
-                Tier::Cold => cold_size += metadata.len(),
-            }
-        }
-    }
+    let hot_size = plan.size_of(Tier::Hot);
+    let warm_size = plan.size_of(Tier::Warm);
+    let cold_size = plan.size_of(Tier::Cold);
 
     println!("Estimated Sizes:");
     println!("  🔥 HOT:   {}", format_size(hot_size));
@@ -1092,8 +4355,24 @@ fn smart_scan_command(paths: Vec<PathBuf>, profile_str: Option<String>, detailed
     }
 
     println!("Next steps:");
-    println!("  cxp build <path> output.cxp --embeddings --model <model-path>");
+    println!("  cxp build <path> output.cxp --profile {} --tier hot", profile.name().to_lowercase());
+    println!("  cxp build <path> output.cxp --profile {} --split-tiers", profile.name().to_lowercase());
     println!("  (Use HOT files for active context, WARM for on-demand loading)");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_journal_conflicts_with_snapshot() {
+        let parse = |args: &[&str]| Cli::try_parse_from([&["cxp", "build", "src", "out.cxp"], args].concat());
+
+        assert!(parse(&["--journal"]).is_ok());
+        assert!(parse(&["--snapshot", "v1"]).is_ok());
+        let err = parse(&["--journal", "--snapshot", "v1"]).err().expect("--journal with --snapshot");
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+}
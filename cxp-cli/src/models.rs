@@ -0,0 +1,270 @@
+//! Managed embedding models
+//!
+//! `cxp models pull <model>` downloads the ONNX export and tokenizer of a
+//! registered model (see `EmbeddingModel::spec`) from Hugging Face into the
+//! model cache, `list` shows what is installed and `rm` deletes a model.
+//! Every `--model <path>` option also accepts the name of a pulled model.
+//!
+//! The cache lives in `$CXP_MODEL_CACHE`, or `<cache dir>/cxp/models` (e.g.
+//! `~/.cache/cxp/models`). Each model directory has a `model.json` recording
+//! the repository, the commit the files were downloaded from and their SHA-256.
+
+use anyhow::{bail, Context, Result};
+use cxp_core::EmbeddingModel;
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// User agent sent to Hugging Face
+const USER_AGENT: &str = concat!("cxp/", env!("CARGO_PKG_VERSION"));
+
+/// Hugging Face Hub
+const HUB: &str = "https://huggingface.co";
+
+/// Files of a model directory: (path in the repository, local name)
+const FILES: &[(&str, &str)] = &[("onnx/model.onnx", "model.onnx"), ("tokenizer.json", "tokenizer.json")];
+
+/// Name of the metadata file in each model directory
+const METADATA: &str = "model.json";
+
+/// Directory pulled models are stored in
+pub fn cache_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("CXP_MODEL_CACHE") {
+        return Ok(PathBuf::from(dir));
+    }
+    let base = dirs::cache_dir().context("No cache directory on this platform; set CXP_MODEL_CACHE")?;
+    Ok(base.join("cxp").join("models"))
+}
+
+/// Directory for `--model`: an existing path as is, or a pulled model by name
+pub fn resolve(model: &Path) -> Result<PathBuf> {
+    let Some(key) = registered_key(model) else {
+        return Ok(model.to_path_buf());
+    };
+    let dir = cache_dir()?.join(key);
+    if !dir.join(METADATA).exists() {
+        bail!("Model '{}' is not installed. Run `cxp models pull {}` first", model.display(), key);
+    }
+    Ok(dir)
+}
+
+/// Registry key of a `--model` that `resolve` maps into the cache
+pub fn registered_key(model: &Path) -> Option<&'static str> {
+    if model.exists() {
+        return None;
+    }
+    let model: EmbeddingModel = model.to_str()?.parse().ok()?;
+    Some(model.spec().key)
+}
+
+/// Download `name` into the cache (skipped if installed and intact, unless `force`)
+pub fn pull(name: &str, force: bool) -> Result<()> {
+    let model: EmbeddingModel = name.parse().map_err(anyhow::Error::msg)?;
+    let spec = model.spec();
+    let repo = spec.onnx_repo.with_context(|| {
+        format!("{} cannot be downloaded; prepare a model directory and pass --model <path>", spec.name)
+    })?;
+    let dir = cache_dir()?.join(spec.key);
+
+    if !force && dir.join(METADATA).exists() {
+        match verify(&dir) {
+            Ok(()) => {
+                println!("{} is already installed at {}", spec.name, dir.display());
+                return Ok(());
+            }
+            Err(e) => println!("Reinstalling {}: {}", spec.name, e),
+        }
+    }
+
+    let client = Client::builder().user_agent(USER_AGENT).build()?;
+
+    // Pin every file to the commit `main` points at now
+    let info: Value = client
+        .get(format!("{}/api/models/{}/revision/main", HUB, repo))
+        .send()?
+        .error_for_status()
+        .with_context(|| format!("Failed to look up {} on Hugging Face", repo))?
+        .json()?;
+    let revision = info["sha"].as_str().context("Hugging Face did not report a revision")?.to_string();
+    println!("Pulling {} ({}@{})", spec.name, repo, &revision[..revision.len().min(12)]);
+
+    let staging = dir.with_extension("partial");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+
+    let mut files = serde_json::Map::new();
+    for (remote, local) in FILES {
+        let expected = lfs_checksum(&client, repo, &revision, remote)?;
+        let url = format!("{}/{}/resolve/{}/{}", HUB, repo, revision, remote);
+        let (size, sha256) = download(&client, &url, &staging.join(local))
+            .with_context(|| format!("Failed to download {}", remote))?;
+        if let Some(expected) = expected {
+            if !sha256.eq_ignore_ascii_case(&expected) {
+                bail!("Checksum mismatch for {}: expected {}, downloaded {}", remote, expected, sha256);
+            }
+        }
+        files.insert(local.to_string(), json!({ "size": size, "sha256": sha256 }));
+    }
+
+    let metadata = json!({
+        "model": spec.key,
+        "name": spec.name,
+        "repo": repo,
+        "revision": revision,
+        "files": files,
+    });
+    std::fs::write(staging.join(METADATA), serde_json::to_vec_pretty(&metadata)?)?;
+
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::rename(&staging, &dir)?;
+
+    println!("Installed {} at {}", spec.name, dir.display());
+    println!("  Use it with --model {}", spec.key);
+    Ok(())
+}
+
+/// Print every registered model and whether it is installed
+pub fn list() -> Result<()> {
+    let cache = cache_dir()?;
+    println!("Models in {}:", cache.display());
+    println!();
+    println!("  {:<16} {:<24} {:>5}  STATUS", "MODEL", "NAME", "DIMS");
+    for model in EmbeddingModel::ALL {
+        let spec = model.spec();
+        let dir = cache.join(spec.key);
+        let status = match read_metadata(&dir) {
+            Ok(metadata) => {
+                let size: u64 = metadata["files"]
+                    .as_object()
+                    .map(|files| files.values().filter_map(|f| f["size"].as_u64()).sum())
+                    .unwrap_or(0);
+                format!("installed ({:.1} MB)", size as f64 / (1024.0 * 1024.0))
+            }
+            Err(_) if spec.onnx_repo.is_none() => "manual download only".to_string(),
+            Err(_) => "-".to_string(),
+        };
+        println!("  {:<16} {:<24} {:>5}  {}", spec.key, spec.name, spec.dimensions, status);
+    }
+    Ok(())
+}
+
+/// Delete a pulled model
+pub fn remove(name: &str) -> Result<()> {
+    let model: EmbeddingModel = name.parse().map_err(anyhow::Error::msg)?;
+    let dir = cache_dir()?.join(model.spec().key);
+    if !dir.exists() {
+        bail!("Model '{}' is not installed", model.spec().key);
+    }
+    std::fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+    println!("Removed {}", model.spec().name);
+    Ok(())
+}
+
+/// `model.json` of an installed model
+fn read_metadata(dir: &Path) -> Result<Value> {
+    let data = std::fs::read(dir.join(METADATA))?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Check the files of an installed model against the checksums in its `model.json`
+fn verify(dir: &Path) -> Result<()> {
+    let metadata = read_metadata(dir)?;
+    for (_, local) in FILES {
+        let expected = metadata["files"][local]["sha256"].as_str().unwrap_or_default();
+        let mut file = std::fs::File::open(dir.join(local)).with_context(|| format!("{} is missing", local))?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)?;
+        if format!("{:x}", hasher.finalize()) != expected {
+            bail!("{} does not match its checksum", local);
+        }
+    }
+    Ok(())
+}
+
+/// SHA-256 Hugging Face stores for a file (None for files not kept in LFS)
+fn lfs_checksum(client: &Client, repo: &str, revision: &str, path: &str) -> Result<Option<String>> {
+    let parent = path.rsplit_once('/').map(|(parent, _)| parent).unwrap_or("");
+    let entries: Value = client
+        .get(format!("{}/api/models/{}/tree/{}/{}", HUB, repo, revision, parent))
+        .send()?
+        .error_for_status()?
+        .json()?;
+    let entry = entries
+        .as_array()
+        .and_then(|entries| entries.iter().find(|entry| entry["path"].as_str() == Some(path)))
+        .with_context(|| format!("{} has no file {}", repo, path))?;
+    Ok(entry["lfs"]["oid"].as_str().map(str::to_string))
+}
+
+/// Stream `url` into `dest`, returning its size and SHA-256
+fn download(client: &Client, url: &str, dest: &Path) -> Result<(u64, String)> {
+    let mut response = client.get(url).send()?.error_for_status()?;
+    let bar = ProgressBar::new(response.content_length().unwrap_or(0));
+    bar.set_style(
+        ProgressStyle::with_template("  {msg:<16} [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    bar.set_message(dest.file_name().unwrap_or_default().to_string_lossy().to_string());
+
+    let mut file = std::fs::File::create(dest)?;
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = response.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n])?;
+        hasher.update(&buf[..n]);
+        size += n as u64;
+        bar.set_position(size);
+    }
+    file.flush()?;
+    bar.finish();
+
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_and_verify() {
+        let cache = tempfile::TempDir::new().unwrap();
+        std::env::set_var("CXP_MODEL_CACHE", cache.path());
+
+        // Paths that exist and names that are not models pass through
+        assert_eq!(resolve(cache.path()).unwrap(), cache.path());
+        assert_eq!(resolve(Path::new("./my-model")).unwrap(), Path::new("./my-model"));
+        assert!(resolve(Path::new("bge-small")).unwrap_err().to_string().contains("cxp models pull bge-small"));
+
+        let dir = cache.path().join("bge-small");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut files = serde_json::Map::new();
+        for (_, local) in FILES {
+            std::fs::write(dir.join(local), local.as_bytes()).unwrap();
+            let sha256 = format!("{:x}", Sha256::digest(local.as_bytes()));
+            files.insert(local.to_string(), json!({ "size": local.len(), "sha256": sha256 }));
+        }
+        std::fs::write(dir.join(METADATA), json!({ "model": "bge-small", "files": files }).to_string()).unwrap();
+
+        assert_eq!(resolve(Path::new("bge-small-en-v1.5")).unwrap(), dir);
+        assert!(verify(&dir).is_ok());
+        std::fs::write(dir.join("tokenizer.json"), b"tampered").unwrap();
+        assert!(verify(&dir).is_err());
+
+        remove("bge-small").unwrap();
+        assert!(!dir.exists());
+        std::env::remove_var("CXP_MODEL_CACHE");
+    }
+}
@@ -0,0 +1,79 @@
+//! Progress bars for builds
+//!
+//! `BuildProgress` turns `CxpBuilder` progress events into one bar per phase
+//! (chunking, embedding, writing) on stderr. Log lines are written through
+//! `LogWriter`, which clears the bars while printing so both stay readable.
+//! Bars are hidden when stderr is not a terminal.
+
+use cxp_core::ProgressReporter;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+
+/// Bars shared by all progress reporters and the log writer
+fn bars() -> &'static MultiProgress {
+    static BARS: OnceLock<MultiProgress> = OnceLock::new();
+    BARS.get_or_init(MultiProgress::new)
+}
+
+/// stderr writer for logs that keeps them above the progress bars
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        bars().suspend(|| std::io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stderr().flush()
+    }
+}
+
+/// Progress bars for `CxpBuilder`
+#[derive(Default)]
+pub struct BuildProgress {
+    /// Phase and bar currently shown
+    current: Mutex<Option<(&'static str, ProgressBar)>>,
+}
+
+impl BuildProgress {
+    fn update(&self, phase: &'static str, done: usize, total: usize) {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let bar = match current.as_ref() {
+            Some((shown, bar)) if *shown == phase => bar.clone(),
+            _ => {
+                if let Some((_, previous)) = current.take() {
+                    previous.finish();
+                }
+                let bar = bars().add(ProgressBar::new(total as u64));
+                bar.set_style(
+                    ProgressStyle::with_template("{msg:<10} [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
+                        .expect("valid progress template")
+                        .progress_chars("=> "),
+                );
+                bar.set_message(phase);
+                *current = Some((phase, bar.clone()));
+                bar
+            }
+        };
+        bar.set_length(total as u64);
+        bar.set_position(done as u64);
+        if done >= total {
+            bar.finish();
+        }
+    }
+}
+
+impl ProgressReporter for BuildProgress {
+    fn chunked(&self, _path: &str, done: usize, total: usize) {
+        self.update("Chunking", done, total);
+    }
+
+    fn embedded(&self, done: usize, total: usize) {
+        self.update("Embedding", done, total);
+    }
+
+    fn written(&self, done: usize, total: usize) {
+        self.update("Writing", done, total);
+    }
+}
@@ -0,0 +1,191 @@
+//! Self-update from GitHub releases
+//!
+//! `cxp self-update` looks up the latest release (or the one pinned with
+//! `--to`) of the repository this binary was built from and replaces the
+//! running executable with the release asset for this platform.
+//!
+//! Release assets are named `cxp-<arch>-<os>[.exe]` (e.g. `cxp-x86_64-linux`,
+//! `cxp-aarch64-macos`). The `<asset>.sha256` file next to it is verified; a
+//! release without one is only installed with `--no-verify`.
+
+use anyhow::{bail, Context, Result};
+use reqwest::blocking::Client;
+use semver::Version;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// User agent sent to the GitHub API (required by GitHub)
+const USER_AGENT: &str = concat!("cxp/", env!("CARGO_PKG_VERSION"));
+
+/// Check for and install a release of the CLI
+pub fn self_update(repo: Option<&str>, pinned: Option<&str>, check: bool, no_verify: bool) -> Result<()> {
+    let repo = match repo {
+        Some(repo) => repo.to_string(),
+        None => github_repo(env!("CARGO_PKG_REPOSITORY")).with_context(|| {
+            format!(
+                "{} is not a GitHub repository; pass --repo <owner/name>",
+                env!("CARGO_PKG_REPOSITORY")
+            )
+        })?,
+    };
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))?;
+
+    let client = Client::builder().user_agent(USER_AGENT).build()?;
+    let release_url = match pinned {
+        Some(version) => format!(
+            "https://api.github.com/repos/{}/releases/tags/v{}",
+            repo,
+            version.trim_start_matches('v')
+        ),
+        None => format!("https://api.github.com/repos/{}/releases/latest", repo),
+    };
+    let release: Value = client
+        .get(&release_url)
+        .send()?
+        .error_for_status()
+        .with_context(|| format!("Failed to look up release at {}", release_url))?
+        .json()?;
+
+    let tag = release["tag_name"].as_str().context("Release has no tag")?;
+    let target = Version::parse(tag.trim_start_matches('v'))
+        .with_context(|| format!("Release tag '{}' is not a version", tag))?;
+
+    println!("Current version: {}", current);
+    println!("Release:         {} ({})", target, repo);
+
+    if pinned.is_none() && target <= current {
+        println!("Already up to date");
+        return Ok(());
+    }
+    if target == current {
+        println!("Version {} is already installed", current);
+        return Ok(());
+    }
+    if check {
+        println!("Update available: {} -> {} (run `cxp self-update` to install)", current, target);
+        return Ok(());
+    }
+
+    let asset = asset_name();
+    let assets = release["assets"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let download_url = |name: &str| {
+        assets
+            .iter()
+            .find(|a| a["name"].as_str() == Some(name))
+            .and_then(|a| a["browser_download_url"].as_str())
+            .map(str::to_string)
+    };
+    let binary_url = download_url(&asset)
+        .with_context(|| format!("Release {} has no build for this platform ({})", tag, asset))?;
+    let checksum_url = download_url(&format!("{}.sha256", asset));
+    if checksum_url.is_none() && !no_verify {
+        bail!("Release {} publishes no checksum for {}; pass --no-verify to install it unverified", tag, asset);
+    }
+
+    println!("Downloading {}...", asset);
+    let binary = client.get(&binary_url).send()?.error_for_status()?.bytes()?;
+
+    match checksum_url {
+        Some(checksum_url) => {
+            let expected = client.get(&checksum_url).send()?.error_for_status()?.text()?;
+            verify_checksum(&binary, &expected)?;
+            println!("  Checksum verified");
+        }
+        None => println!("  No checksum published; installing unverified (--no-verify)"),
+    }
+
+    let executable = std::env::current_exe().context("Cannot locate the running executable")?;
+    replace_executable(&executable, &binary)?;
+
+    println!("Updated cxp {} -> {}", current, target);
+    Ok(())
+}
+
+/// `owner/name` of a `https://github.com/owner/name` URL
+fn github_repo(url: &str) -> Option<String> {
+    let path = url.strip_prefix("https://github.com/")?;
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    let mut parts = path.split('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(owner), Some(name), None) if !owner.is_empty() && !name.is_empty() => Some(path.to_string()),
+        _ => None,
+    }
+}
+
+/// Release asset name for the platform this binary was built for
+fn asset_name() -> String {
+    format!(
+        "cxp-{}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+/// Compare `data` against a `sha256sum`-style checksum file
+fn verify_checksum(data: &[u8], checksum_file: &str) -> Result<()> {
+    let expected = checksum_file.split_whitespace().next().unwrap_or_default();
+    let actual = format!("{:x}", Sha256::digest(data));
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!("Checksum mismatch: expected {}, downloaded {}", expected, actual);
+    }
+    Ok(())
+}
+
+/// Stage `binary` next to `executable` and swap it in
+fn replace_executable(executable: &Path, binary: &[u8]) -> Result<()> {
+    let staged = executable.with_extension("new");
+    std::fs::write(&staged, binary)
+        .with_context(|| format!("Cannot write {} (missing permissions?)", staged.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    // Windows cannot overwrite a running executable, but it can rename it
+    #[cfg(windows)]
+    {
+        let old = executable.with_extension("old");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(executable, &old)?;
+    }
+
+    std::fs::rename(&staged, executable)
+        .with_context(|| format!("Cannot replace {}", executable.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_repo() {
+        assert_eq!(github_repo("https://github.com/owner/cxp").as_deref(), Some("owner/cxp"));
+        assert_eq!(github_repo("https://github.com/owner/cxp.git/").as_deref(), Some("owner/cxp"));
+        assert_eq!(github_repo("https://github.com/owner"), None);
+        assert_eq!(github_repo("https://gitlab.com/owner/cxp"), None);
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let digest = format!("{:x}", Sha256::digest(b"cxp"));
+        assert!(verify_checksum(b"cxp", &format!("{}  cxp-x86_64-linux\n", digest)).is_ok());
+        assert!(verify_checksum(b"cxp", &digest.to_uppercase()).is_ok());
+        assert!(verify_checksum(b"other", &digest).is_err());
+    }
+
+    #[test]
+    fn test_replace_executable() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let executable = dir.path().join("cxp");
+        std::fs::write(&executable, b"old").unwrap();
+
+        replace_executable(&executable, b"new").unwrap();
+        assert_eq!(std::fs::read(&executable).unwrap(), b"new");
+        assert!(!executable.with_extension("new").exists());
+    }
+}
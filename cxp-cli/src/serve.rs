@@ -0,0 +1,450 @@
+//! Local HTTP API over a CXP archive
+//!
+//! Endpoints (all responses are JSON except raw file content):
+//!   GET /health                        liveness check
+//!   GET /info                          manifest summary
+//!   GET /files?prefix=src/             list files (optionally under a prefix)
+//!   GET /files/{path}                  raw file content (streamed chunk by chunk, supports Range)
+//!   GET /query?q=<terms>&top_k=10      keyword query
+//!   GET /search?q=<text>&top_k=10      semantic search (embeddings + search features, --model)
+
+use anyhow::{Context, Result};
+use axum::body::{Body, Bytes};
+use axum::extract::{Path as UrlPath, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use cxp_core::{ContextAssembler, CxpError, CxpReader, TempPolicy};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Default number of results for query endpoints
+const DEFAULT_TOP_K: usize = 10;
+
+/// Read size when streaming file content
+const STREAM_READ_SIZE: usize = 64 * 1024;
+
+/// Reads buffered ahead of the client when streaming
+const STREAM_BUFFER_READS: usize = 4;
+
+/// Shared server state
+struct AppState {
+    /// Archive reader shared by all requests (embeddings are loaded once at startup)
+    reader: CxpReader,
+    /// Whether /search is available
+    semantic: bool,
+}
+
+type SharedState = Arc<AppState>;
+
+/// Error returned by handlers, rendered as `{"error": "..."}`
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<CxpError> for ApiError {
+    fn from(e: CxpError) -> Self {
+        let status = match e {
+            CxpError::FileNotFound(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, e.to_string())
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// Start the server and block until it is stopped
+pub fn serve(file: &Path, host: &str, port: u16, model: Option<&Path>, temp_policy: &TempPolicy) -> Result<()> {
+    let reader = CxpReader::open(file)
+        .context("Failed to open CXP file")?
+        .with_temp_policy(temp_policy.clone());
+
+    #[allow(unused_mut)]
+    let mut semantic = false;
+
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    if let Some(model_path) = model {
+        if !reader.has_embeddings() {
+            return Err(anyhow::anyhow!(
+                "This CXP file has no embeddings. Use 'cxp build --embeddings --model <path>' to create one."
+            ));
+        }
+
+        println!("Loading embeddings...");
+        reader.load_embeddings().context("Failed to load embeddings")?;
+        reader
+            .load_query_model(model_path, crate::archive_model(&reader))
+            .context("Failed to load embedding model")?;
+        semantic = true;
+    }
+
+    #[cfg(not(all(feature = "embeddings", feature = "search")))]
+    if model.is_some() {
+        return Err(anyhow::anyhow!(
+            "Semantic search requires embeddings and search features. Rebuild with --features server,embeddings,search"
+        ));
+    }
+
+    let state = Arc::new(AppState {
+        reader,
+        semantic,
+    });
+
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/info", get(info))
+        .route("/files", get(list_files))
+        .route("/files/{*path}", get(extract_file))
+        .route("/query", get(keyword_query))
+        .route("/search", get(semantic_search))
+        .with_state(state);
+
+    let addr = format!("{}:{}", host, port);
+    println!("Serving {} on http://{}", file.display(), addr);
+    println!("  GET /health");
+    println!("  GET /info");
+    println!("  GET /files?prefix=<prefix>");
+    println!("  GET /files/<path>");
+    println!("  GET /query?q=<terms>&top_k=<n>");
+    if semantic {
+        println!("  GET /search?q=<text>&top_k=<n>");
+    }
+
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start async runtime")?;
+    runtime.block_on(async move {
+        let listener = tokio::net::TcpListener::bind(&addr)
+            .await
+            .with_context(|| format!("Failed to bind {}", addr))?;
+        axum::serve(listener, app).await.context("Server error")
+    })
+}
+
+/// Run blocking archive work on the blocking thread pool
+async fn with_reader<T, F>(state: SharedState, f: F) -> ApiResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&CxpReader) -> ApiResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || f(&state.reader))
+    .await
+    .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+}
+
+fn top_k(params: &HashMap<String, String>) -> ApiResult<usize> {
+    match params.get("top_k") {
+        Some(value) => value
+            .parse()
+            .map_err(|_| ApiError(StatusCode::BAD_REQUEST, format!("Invalid top_k: {}", value))),
+        None => Ok(DEFAULT_TOP_K),
+    }
+}
+
+fn query_param(params: &HashMap<String, String>) -> ApiResult<String> {
+    params
+        .get("q")
+        .filter(|q| !q.trim().is_empty())
+        .cloned()
+        .ok_or_else(|| ApiError(StatusCode::BAD_REQUEST, "Missing query parameter 'q'".to_string()))
+}
+
+async fn health() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+async fn info(State(state): State<SharedState>) -> ApiResult<Json<Value>> {
+    with_reader(state.clone(), move |reader| {
+        let manifest = reader.manifest();
+        Ok(Json(json!({
+            "version": manifest.version,
+            "created_at": manifest.created_at,
+            "stats": {
+                "total_files": manifest.stats.total_files,
+                "unique_chunks": manifest.stats.unique_chunks,
+                "original_size_bytes": manifest.stats.original_size_bytes,
+                "cxp_size_bytes": manifest.stats.cxp_size_bytes,
+            },
+            "extensions": manifest.extensions,
+            "embedding_model": manifest.embedding_model,
+            "semantic_search": state.semantic,
+        })))
+    })
+    .await
+}
+
+async fn list_files(
+    State(state): State<SharedState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> ApiResult<Json<Value>> {
+    let prefix = params.get("prefix").cloned().unwrap_or_default();
+
+    with_reader(state, move |reader| {
+        let mut files = Vec::new();
+        for path in reader.list_prefix(&prefix)? {
+            if let Some(entry) = reader.file_entry(path)? {
+                files.push(json!({
+                    "path": entry.path,
+                    "size": entry.size,
+                    "chunks": entry.chunks.len(),
+                }));
+            }
+        }
+        Ok(Json(json!({ "count": files.len(), "files": files })))
+    })
+    .await
+}
+
+async fn extract_file(
+    State(state): State<SharedState>,
+    UrlPath(path): UrlPath<String>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    // Resolve the range and open a stream over only the chunks it needs
+    let opened = with_reader(state, move |reader| {
+        let entry = reader
+            .file_entry(&path)?
+            .ok_or_else(|| CxpError::FileNotFound(path.clone()))?;
+        let size = entry.size;
+        let content_type = if entry.is_image {
+            image_content_type(&entry.extension)
+        } else {
+            let head = reader.read_file_chunks(&path, 0..1)?;
+            if looks_like_text(&head) {
+                "text/plain; charset=utf-8"
+            } else {
+                "application/octet-stream"
+            }
+        };
+
+        let range = match range.as_deref().map(|r| parse_range(r, size)) {
+            Some(ByteRange::Unsatisfiable) => return Ok(Err(size)),
+            Some(ByteRange::Range(start, end)) => Some((start, end)),
+            Some(ByteRange::Ignored) | None => None,
+        };
+        let (start, end) = range.unwrap_or((0, size));
+
+        let stream = reader.open_file_range(&path, start..end)?;
+        Ok(Ok((stream, content_type, size, range)))
+    })
+    .await?;
+
+    let (mut stream, content_type, size, range) = match opened {
+        Ok(opened) => opened,
+        Err(size) => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+            )
+                .into_response());
+        }
+    };
+    let length = stream.remaining();
+
+    // Decompress chunks on the blocking pool as the client reads
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Bytes>>(STREAM_BUFFER_READS);
+    tokio::task::spawn_blocking(move || {
+        let mut buf = vec![0u8; STREAM_READ_SIZE];
+        loop {
+            let item = match stream.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => Ok(Bytes::copy_from_slice(&buf[..n])),
+                Err(e) => Err(e),
+            };
+            let failed = item.is_err();
+            if tx.blocking_send(item).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    let body = Body::from_stream(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    }));
+
+    let mut response = (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_LENGTH, length.to_string()),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+        ],
+        body,
+    )
+        .into_response();
+
+    if let Some((start, end)) = range {
+        *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+        let content_range = format!("bytes {}-{}/{}", start, end - 1, size);
+        if let Ok(value) = content_range.parse() {
+            response.headers_mut().insert(header::CONTENT_RANGE, value);
+        }
+    }
+
+    Ok(response)
+}
+
+/// Result of parsing a `Range` header against a file size
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// Half-open byte range `[start, end)`
+    Range(u64, u64),
+    /// Syntactically valid but outside the file (416)
+    Unsatisfiable,
+    /// Unsupported or malformed (multiple ranges, other units) - serve the full file
+    Ignored,
+}
+
+/// Parse a single `bytes=` range (`a-b`, `a-` or `-n`)
+fn parse_range(value: &str, size: u64) -> ByteRange {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return ByteRange::Ignored;
+    };
+    if spec.contains(',') {
+        return ByteRange::Ignored;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Ignored;
+    };
+
+    let (start, end) = match (first.trim(), last.trim()) {
+        ("", "") => return ByteRange::Ignored,
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(n) => (size.saturating_sub(n), size),
+            Err(_) => return ByteRange::Ignored,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => (start, size),
+            Err(_) => return ByteRange::Ignored,
+        },
+        (start, last) => match (start.parse::<u64>(), last.parse::<u64>()) {
+            (Ok(start), Ok(last)) if start <= last => (start, last.saturating_add(1).min(size)),
+            _ => return ByteRange::Ignored,
+        },
+    };
+
+    if start >= size {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Range(start, end)
+    }
+}
+
+/// Content type for image files stored in multimodal archives
+fn image_content_type(extension: &str) -> &'static str {
+    match extension {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// UTF-8 check that tolerates a character cut off at the end of the sample
+fn looks_like_text(sample: &[u8]) -> bool {
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+async fn keyword_query(
+    State(state): State<SharedState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> ApiResult<Json<Value>> {
+    let query = query_param(&params)?;
+    let top_k = top_k(&params)?;
+
+    with_reader(state, move |reader| {
+        let hits = ContextAssembler::new(reader, u64::MAX).keyword_hits(&query, top_k)?;
+        let results: Vec<Value> = hits
+            .iter()
+            .map(|hit| {
+                json!({
+                    "path": hit.file_path,
+                    "chunk_index": hit.chunk_index,
+                    "score": hit.score,
+                })
+            })
+            .collect();
+        Ok(Json(json!({ "query": query, "results": results })))
+    })
+    .await
+}
+
+async fn semantic_search(
+    State(state): State<SharedState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> ApiResult<Json<Value>> {
+    if !state.semantic {
+        return Err(ApiError(
+            StatusCode::NOT_IMPLEMENTED,
+            "Semantic search is not enabled. Start the server with --model <path>".to_string(),
+        ));
+    }
+
+    let query = query_param(&params)?;
+    let top_k = top_k(&params)?;
+
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    {
+        with_reader(state, move |reader| {
+            let fused = reader.search_multi(&[query.as_str()], top_k, cxp_core::Fusion::rrf())?;
+            let mut results = Vec::with_capacity(fused.len());
+            for result in fused.iter().take(top_k) {
+                let text = match result.chunk_hash {
+                    Some(ref hash) => Some(String::from_utf8_lossy(&reader.read_chunk(hash)?).into_owned()),
+                    None => None,
+                };
+                results.push(json!({
+                    "id": result.id,
+                    "score": result.score,
+                    "path": result.file_path,
+                    "chunk_hash": result.chunk_hash,
+                    "text": text,
+                }));
+            }
+            Ok(Json(json!({ "query": query, "results": results })))
+        })
+        .await
+    }
+
+    #[cfg(not(all(feature = "embeddings", feature = "search")))]
+    {
+        let _ = (query, top_k);
+        unreachable!("semantic search is only enabled with embeddings and search features")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), ByteRange::Range(0, 100));
+        assert_eq!(parse_range("bytes=900-", 1000), ByteRange::Range(900, 1000));
+        assert_eq!(parse_range("bytes=-100", 1000), ByteRange::Range(900, 1000));
+        assert_eq!(parse_range("bytes=500-5000", 1000), ByteRange::Range(500, 1000));
+        assert_eq!(parse_range("bytes=-5000", 1000), ByteRange::Range(0, 1000));
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), ByteRange::Ignored);
+        assert_eq!(parse_range("bytes=9-1", 1000), ByteRange::Ignored);
+        assert_eq!(parse_range("items=0-1", 1000), ByteRange::Ignored);
+    }
+}
@@ -0,0 +1,125 @@
+/* CXP C API - generated from cxp-core/src/ffi.rs, do not edit */
+
+#ifndef CXP_H
+#define CXP_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Success
+#define CXP_OK 0
+
+// A required pointer argument was null or a string was not UTF-8
+#define CXP_ERR_INVALID_ARGUMENT -1
+
+// The operation failed (see `cxp_last_error`)
+#define CXP_ERR_FAILED -2
+
+// The library panicked; the handle should not be used further
+#define CXP_ERR_PANIC -3
+
+// Stable numeric error codes (0 means no error)
+//
+// Codes are never reused or renumbered; new kinds get new numbers.
+enum CxpErrorCode {
+  CXP_ERROR_CODE_IO = 1,
+  CXP_ERROR_CODE_ZIP = 2,
+  CXP_ERROR_CODE_SERIALIZATION = 3,
+  CXP_ERROR_CODE_INVALID_FORMAT = 4,
+  CXP_ERROR_CODE_CHUNK = 5,
+  CXP_ERROR_CODE_CHUNK_MISSING = 6,
+  CXP_ERROR_CODE_CHUNK_HASH_MISMATCH = 7,
+  CXP_ERROR_CODE_MANIFEST = 8,
+  CXP_ERROR_CODE_FILE_NOT_FOUND = 9,
+  CXP_ERROR_CODE_UNSUPPORTED_FILE_TYPE = 10,
+  CXP_ERROR_CODE_COMPRESSION = 11,
+  CXP_ERROR_CODE_EMBEDDING = 12,
+  CXP_ERROR_CODE_INDEX = 13,
+  CXP_ERROR_CODE_INDEX_DIMENSION_MISMATCH = 14,
+  CXP_ERROR_CODE_SEARCH = 15,
+  CXP_ERROR_CODE_TOKENIZER = 16,
+  CXP_ERROR_CODE_WATCH = 17,
+  CXP_ERROR_CODE_CANCELLED = 18,
+  CXP_ERROR_CODE_TIMEOUT = 19,
+  CXP_ERROR_CODE_STORAGE = 20,
+  CXP_ERROR_CODE_UNSUPPORTED_FORMAT_VERSION = 21,
+  CXP_ERROR_CODE_GIT = 22,
+};
+typedef int32_t CxpErrorCode;
+
+// Opaque handle to an open archive
+typedef struct CxpArchive CxpArchive;
+
+// Bytes returned by the library, freed with `cxp_free`
+typedef struct CxpBuffer {
+  // Start of the data (null for an empty buffer)
+  uint8_t *data;
+  // Length in bytes
+  size_t len;
+} CxpBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Version of the library as a static NUL-terminated string
+const char *cxp_version(void);
+
+// Message of the last failed call on this thread, or null
+//
+// The string stays valid until the next failing call on the same thread.
+const char *cxp_last_error(void);
+
+// `CxpErrorCode` of the last failed call on this thread
+//
+// 0 if there was none, or if it failed with `CXP_ERR_INVALID_ARGUMENT` or
+// `CXP_ERR_PANIC`.
+int32_t cxp_last_error_code(void);
+
+// Open an archive, returning null on failure
+//
+// # Safety
+// `path` must be null or a NUL-terminated string.
+CxpArchive *cxp_open(const char *path);
+
+// Close an archive opened with `cxp_open` (null is ignored)
+//
+// # Safety
+// `archive` must be null or a handle from `cxp_open` that has not been closed.
+void cxp_close(CxpArchive *archive);
+
+// Read a file's content into `out`
+//
+// # Safety
+// `archive` must be a live handle, `path` a NUL-terminated string and `out`
+// valid for writes.
+int32_t cxp_read_file(CxpArchive *archive, const char *path, CxpBuffer *out);
+
+// List the archive's file paths into `out` as a JSON array of strings
+//
+// # Safety
+// `archive` must be a live handle and `out` valid for writes.
+int32_t cxp_list_files(CxpArchive *archive, CxpBuffer *out);
+
+// Search the archive and write the hits to `out` as a JSON array
+//
+// Each hit is `{"file_path", "chunk_index", "score", "text"}`. Search is
+// semantic when the archive has embeddings and `CXP_MODEL_DIR` points to the
+// model, and by keywords otherwise.
+//
+// # Safety
+// `archive` must be a live handle, `query` a NUL-terminated string and `out`
+// valid for writes.
+int32_t cxp_search(CxpArchive *archive, const char *query, size_t top_k, CxpBuffer *out);
+
+// Free a buffer returned by the library (empty buffers are ignored)
+//
+// # Safety
+// `buffer` must come from this library and not have been freed before.
+void cxp_free(CxpBuffer buffer);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CXP_H */
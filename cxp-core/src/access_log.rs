@@ -0,0 +1,245 @@
+//! Access Tracking
+//!
+//! Records reads of an archive and its children in an append-only sidecar
+//! file next to the archive, so tiering and "recently used" features survive
+//! process restarts without rewriting the archive itself.
+//!
+//! ```text
+//! project.cxp
+//! project.access_log.msgpack   # concatenated MessagePack AccessRecords
+//! ```
+//!
+//! Each record is appended as a standalone MessagePack value. A record cut
+//! short by a crash is ignored when reading; `compact()` rewrites the log
+//! keeping only the latest access per file.
+
+use crate::temp::replace_file;
+use crate::{CxpError, Result};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Cursor, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Extension of the access log sidecar (replaces the archive's `.cxp`)
+pub const ACCESS_LOG_EXTENSION: &str = "access_log.msgpack";
+
+/// One recorded access
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessRecord {
+    /// Path of the accessed child CXP (empty for the archive itself)
+    pub cxp_path: Vec<String>,
+    /// File read inside that CXP (None when the CXP as a whole was opened)
+    pub file_path: Option<String>,
+    /// When the access happened
+    pub accessed_at: DateTime<Utc>,
+}
+
+/// A file read recorded in the access log
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentFile {
+    /// Path of the CXP containing the file (empty for the archive itself)
+    pub cxp_path: Vec<String>,
+    /// File path inside that CXP
+    pub file_path: String,
+    /// Latest read of the file
+    pub accessed_at: DateTime<Utc>,
+}
+
+/// (CXP path, file path) key of per-file summaries
+type AccessKey = (Vec<String>, Option<String>);
+
+/// Append-only access log of an archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessLog {
+    /// Sidecar file
+    path: PathBuf,
+    /// Child CXP this handle records accesses for (empty for the archive itself)
+    cxp_path: Vec<String>,
+}
+
+impl AccessLog {
+    /// Access log stored next to `archive` (`<name>.access_log.msgpack`)
+    pub fn for_archive<P: AsRef<Path>>(archive: P) -> Self {
+        Self::at(archive.as_ref().with_extension(ACCESS_LOG_EXTENSION))
+    }
+
+    /// Access log stored at an explicit path
+    pub fn at<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            cxp_path: Vec::new(),
+        }
+    }
+
+    /// Handle that records accesses of child `id` into the same log
+    pub fn child(&self, id: &str) -> Self {
+        let mut cxp_path = self.cxp_path.clone();
+        cxp_path.push(id.to_string());
+        Self {
+            path: self.path.clone(),
+            cxp_path,
+        }
+    }
+
+    /// Sidecar file path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record an access now (`file_path` None for the CXP as a whole)
+    pub fn record(&self, file_path: Option<&str>) -> Result<()> {
+        self.append(&AccessRecord {
+            cxp_path: self.cxp_path.clone(),
+            file_path: file_path.map(str::to_string),
+            accessed_at: Utc::now(),
+        })
+    }
+
+    /// Append a record to the log
+    pub fn append(&self, record: &AccessRecord) -> Result<()> {
+        append_record(&self.path, record)
+    }
+
+    /// All records in append order (empty if the log does not exist)
+    pub fn read(&self) -> Result<Vec<AccessRecord>> {
+        read_records(&self.path)
+    }
+
+    /// Latest access per CXP path (`"a/b"`, `""` for the archive itself)
+    pub fn last_accessed(&self) -> Result<HashMap<String, DateTime<Utc>>> {
+        let mut latest: HashMap<String, DateTime<Utc>> = HashMap::new();
+        for record in self.read()? {
+            let at = latest.entry(record.cxp_path.join("/")).or_insert(record.accessed_at);
+            *at = (*at).max(record.accessed_at);
+        }
+        Ok(latest)
+    }
+
+    /// Most recently read files, newest first
+    pub fn recent_files(&self, limit: usize) -> Result<Vec<RecentFile>> {
+        let mut recent: Vec<RecentFile> = self.latest_per_file()?
+            .into_iter()
+            .filter_map(|((cxp_path, file_path), accessed_at)| {
+                file_path.map(|file_path| RecentFile { cxp_path, file_path, accessed_at })
+            })
+            .collect();
+        recent.sort_by_key(|r| std::cmp::Reverse(r.accessed_at));
+        recent.truncate(limit);
+        Ok(recent)
+    }
+
+    /// Rewrite the log keeping only the latest access per CXP and file
+    pub fn compact(&self) -> Result<usize> {
+        let latest = self.latest_per_file()?;
+        let mut records: Vec<AccessRecord> = latest
+            .into_iter()
+            .map(|((cxp_path, file_path), accessed_at)| AccessRecord { cxp_path, file_path, accessed_at })
+            .collect();
+        records.sort_by_key(|r| r.accessed_at);
+
+        rewrite_records(&self.path, &records)?;
+        Ok(records.len())
+    }
+
+    /// Latest access per (CXP path, file path)
+    fn latest_per_file(&self) -> Result<BTreeMap<AccessKey, DateTime<Utc>>> {
+        let mut latest = BTreeMap::new();
+        for record in self.read()? {
+            let at = latest.entry((record.cxp_path, record.file_path)).or_insert(record.accessed_at);
+            *at = (*at).max(record.accessed_at);
+        }
+        Ok(latest)
+    }
+}
+
+/// Append one MessagePack record to an append-only sidecar
+///
+/// Each record is a single `O_APPEND` write, so concurrent writers never
+/// overwrite each other's records.
+pub(crate) fn append_record<T: Serialize>(path: &Path, record: &T) -> Result<()> {
+    let data = rmp_serde::to_vec(record).map_err(|e| CxpError::Serialization(e.to_string()))?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(&data)?;
+    Ok(())
+}
+
+/// All records of an append-only sidecar (empty if it does not exist)
+///
+/// A record cut short by a crash ends the log and is ignored.
+pub(crate) fn read_records<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut cursor = Cursor::new(data.as_slice());
+    let mut records = Vec::new();
+    while (cursor.position() as usize) < data.len() {
+        match rmp_serde::from_read::<_, T>(&mut cursor) {
+            Ok(record) => records.push(record),
+            Err(e) => {
+                tracing::warn!("Ignoring truncated record at the end of {:?}: {}", path, e);
+                break;
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+/// Replace an append-only sidecar with `records`
+pub(crate) fn rewrite_records<T: Serialize>(path: &Path, records: &[T]) -> Result<()> {
+    replace_file(path, |temp| {
+        let mut writer = BufWriter::new(std::fs::File::create(temp)?);
+        for record in records {
+            let data = rmp_serde::to_vec(record).map_err(|e| CxpError::Serialization(e.to_string()))?;
+            writer.write_all(&data)?;
+        }
+        writer.flush()?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_access_log_append_and_compact() {
+        let dir = TempDir::new().unwrap();
+        let log = AccessLog::for_archive(dir.path().join("project.cxp"));
+        assert_eq!(log.path(), dir.path().join("project.access_log.msgpack"));
+        assert!(log.read().unwrap().is_empty());
+
+        log.record(None).unwrap();
+        log.record(Some("src/main.rs")).unwrap();
+        log.child("docs").record(Some("guide.md")).unwrap();
+        log.record(Some("src/main.rs")).unwrap();
+
+        let records = log.read().unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[2].cxp_path, vec!["docs"]);
+
+        let last = log.last_accessed().unwrap();
+        assert_eq!(last.len(), 2);
+        assert_eq!(last["docs"], records[2].accessed_at);
+
+        let recent = log.recent_files(10).unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].file_path, "src/main.rs");
+
+        // A torn write at the end is ignored
+        let mut file = OpenOptions::new().append(true).open(log.path()).unwrap();
+        file.write_all(&[0x93, 0x91]).unwrap();
+        assert_eq!(log.read().unwrap().len(), 4);
+
+        assert_eq!(log.compact().unwrap(), 3);
+        assert_eq!(log.read().unwrap().len(), 3);
+        assert_eq!(log.last_accessed().unwrap(), last);
+    }
+}
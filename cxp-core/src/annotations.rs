@@ -0,0 +1,278 @@
+//! File Annotations
+//!
+//! User-defined tags ("entry point", "generated", "secret", ...) and
+//! key/value notes on files inside an archive, stored at
+//! [`ANNOTATIONS_PATH`]. Tags are set while building
+//! (`CxpBuilder::tag_file`) or later with [`Annotations::write_to`], which
+//! replaces only the annotations entry and leaves chunks untouched.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::path::Path;
+
+use crate::format::{ArchiveWriter, FileMap};
+use crate::packs::PackIndex;
+use crate::temp::replace_file;
+use crate::toc::TOC_PATH;
+use crate::{CxpError, Result};
+
+/// Path of the annotations inside the archive
+pub const ANNOTATIONS_PATH: &str = "annotations.msgpack";
+
+/// Tags and notes of one file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileAnnotation {
+    /// Tags (sorted, unique)
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    /// Free-form key/value notes
+    #[serde(default)]
+    pub notes: BTreeMap<String, String>,
+}
+
+impl FileAnnotation {
+    /// Whether the file has neither tags nor notes
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.notes.is_empty()
+    }
+}
+
+/// Annotations of all files in an archive
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotations {
+    /// Annotations by file path
+    pub files: BTreeMap<String, FileAnnotation>,
+}
+
+impl Annotations {
+    /// Create empty annotations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether no file is annotated
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Add tags to a file
+    pub fn tag<I, S>(&mut self, path: &str, tags: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let tags: Vec<String> = tags
+            .into_iter()
+            .map(Into::into)
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        if !tags.is_empty() {
+            self.files.entry(path.to_string()).or_default().tags.extend(tags);
+        }
+    }
+
+    /// Remove tags from a file
+    pub fn untag<I, S>(&mut self, path: &str, tags: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        if let Some(annotation) = self.files.get_mut(path) {
+            for tag in tags {
+                annotation.tags.remove(tag.as_ref().trim());
+            }
+            if annotation.is_empty() {
+                self.files.remove(path);
+            }
+        }
+    }
+
+    /// Set a note on a file (an empty value removes it)
+    pub fn annotate(&mut self, path: &str, key: impl Into<String>, value: impl Into<String>) {
+        let (key, value) = (key.into(), value.into());
+        if value.is_empty() {
+            if let Some(annotation) = self.files.get_mut(path) {
+                annotation.notes.remove(&key);
+                if annotation.is_empty() {
+                    self.files.remove(path);
+                }
+            }
+        } else {
+            self.files.entry(path.to_string()).or_default().notes.insert(key, value);
+        }
+    }
+
+    /// Annotation of a file
+    pub fn get(&self, path: &str) -> Option<&FileAnnotation> {
+        self.files.get(path)
+    }
+
+    /// Tags of a file (sorted)
+    pub fn tags(&self, path: &str) -> Vec<&str> {
+        self.files
+            .get(path)
+            .map(|a| a.tags.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Files carrying `tag` (path order)
+    pub fn files_with_tag(&self, tag: &str) -> Vec<&str> {
+        self.files
+            .iter()
+            .filter(|(_, annotation)| annotation.tags.contains(tag))
+            .map(|(path, _)| path.as_str())
+            .collect()
+    }
+
+    /// All tags with the number of files carrying them
+    pub fn tag_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for tag in self.files.values().flat_map(|a| &a.tags) {
+            *counts.entry(tag.as_str()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Annotations restricted to the files of `file_map`
+    pub fn for_files(&self, file_map: &FileMap) -> Self {
+        Self {
+            files: self
+                .files
+                .iter()
+                .filter(|(path, _)| file_map.files.contains_key(*path))
+                .map(|(path, annotation)| (path.clone(), annotation.clone()))
+                .collect(),
+        }
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Read the annotations of an archive (empty if it has none)
+    pub fn read_from<P: AsRef<Path>>(archive_path: P) -> Result<Self> {
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(archive_path.as_ref())?)?;
+        Self::read_from_archive(&mut archive)
+    }
+
+    /// Read the annotations from an open archive (empty if it has none)
+    pub fn read_from_archive<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>) -> Result<Self> {
+        let mut entry = match archive.by_name(ANNOTATIONS_PATH) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        Self::from_msgpack(&data)
+    }
+
+    /// Store the annotations in an archive, replacing any previous ones
+    ///
+    /// The archive is rewritten next to the original and moved into place
+    /// once complete. Empty annotations remove the entry.
+    pub fn write_to<P: AsRef<Path>>(&self, archive_path: P) -> Result<()> {
+        let archive_path = archive_path.as_ref();
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(archive_path)?)?;
+        let packs = PackIndex::read_from_archive(&mut archive)?;
+
+        replace_file(archive_path, |temp_path| {
+            let mut writer = ArchiveWriter::create(temp_path)?;
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i)?;
+                let name = entry.name().to_string();
+                if name == ANNOTATIONS_PATH || name == TOC_PATH {
+                    continue;
+                }
+                let mut data = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut data)?;
+                writer.copy(&name, &data, &packs)?;
+            }
+            if !self.is_empty() {
+                writer.write(ANNOTATIONS_PATH, &self.to_msgpack()?)?;
+            }
+            writer.finish()?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_and_notes() {
+        let mut annotations = Annotations::new();
+        annotations.tag("src/main.rs", ["entry point", "critical"]);
+        annotations.tag("src/gen.rs", ["generated", " "]);
+        annotations.tag("src/main.rs", ["critical"]);
+        annotations.annotate("src/main.rs", "owner", "core-team");
+
+        assert_eq!(annotations.tags("src/main.rs"), vec!["critical", "entry point"]);
+        assert_eq!(annotations.tags("src/gen.rs"), vec!["generated"]);
+        assert_eq!(annotations.files_with_tag("critical"), vec!["src/main.rs"]);
+        assert_eq!(annotations.tag_counts()["generated"], 1);
+        assert_eq!(annotations.get("src/main.rs").unwrap().notes["owner"], "core-team");
+
+        annotations.untag("src/gen.rs", ["generated"]);
+        assert!(annotations.get("src/gen.rs").is_none());
+        annotations.untag("src/main.rs", ["critical", "entry point"]);
+        annotations.annotate("src/main.rs", "owner", "");
+        assert!(annotations.is_empty());
+
+        annotations.tag("README.md", ["docs"]);
+        let restored = Annotations::from_msgpack(&annotations.to_msgpack().unwrap()).unwrap();
+        assert_eq!(restored, annotations);
+    }
+
+    #[test]
+    fn test_annotated_archive() {
+        use crate::{CxpBuilder, CxpMerger, CxpReader};
+        use tempfile::TempDir;
+
+        let source = TempDir::new().unwrap();
+        std::fs::create_dir_all(source.path().join("src")).unwrap();
+        std::fs::write(source.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(source.path().join("src/schema.rs"), "// @generated\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("tagged.cxp");
+        let mut builder = CxpBuilder::new(source.path());
+        builder.scan().unwrap().process().unwrap();
+        builder
+            .tag_file("src/main.rs", ["entry point"])
+            .tag_file("src/schema.rs", ["generated"])
+            .tag_file("src/missing.rs", ["generated"])
+            .annotate_file("src/main.rs", "owner", "core");
+        builder.build(&cxp_path).unwrap();
+
+        let reader = CxpReader::open(&cxp_path).unwrap();
+        assert_eq!(reader.files_with_tag("generated"), vec!["src/schema.rs"]);
+        assert_eq!(reader.file_tags("src/main.rs"), vec!["entry point"]);
+        assert_eq!(reader.annotations().get("src/main.rs").unwrap().notes["owner"], "core");
+
+        // Mutate in place; content stays readable
+        let mut annotations = reader.annotations().clone();
+        drop(reader);
+        annotations.tag("src/main.rs", ["critical"]);
+        annotations.write_to(&cxp_path).unwrap();
+
+        let reader = CxpReader::open(&cxp_path).unwrap();
+        assert_eq!(reader.file_tags("src/main.rs"), vec!["critical", "entry point"]);
+        assert_eq!(reader.read_file("src/main.rs").unwrap(), b"fn main() {}\n");
+
+        // Merging keeps the tags of the files it keeps
+        let merged = output.path().join("merged.cxp");
+        CxpMerger::new().merge(&[&cxp_path], &merged).unwrap();
+        assert_eq!(Annotations::read_from(&merged).unwrap(), annotations);
+    }
+}
@@ -0,0 +1,204 @@
+//! Async Reader
+//!
+//! [`AsyncCxpReader`] wraps a shared [`CxpReader`] for tokio services (axum,
+//! tonic, ...). Every call runs on tokio's blocking thread pool, so handlers
+//! can `.await` reads and searches directly instead of wrapping each one in
+//! `spawn_blocking`.
+//!
+//! Decompressing chunks is the expensive part of a read. `read_file()` splits
+//! a file into batches of chunks that are decompressed in parallel, and one
+//! semaphore shared by all calls caps how many blocking tasks run at once
+//! (see [`AsyncCxpReader::with_max_concurrency`]). A burst of large reads
+//! therefore cannot take over the whole blocking pool.
+//!
+//! # Example
+//! ```ignore
+//! let reader = AsyncCxpReader::open("project.cxp").await?.with_max_concurrency(4);
+//! let content = reader.read_file("src/main.rs").await?;
+//! ```
+
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::backend::AsyncArchiveBackend;
+use crate::{CxpError, CxpReader, Result};
+
+/// Chunks decompressed by one blocking task of `read_file()`
+const CHUNKS_PER_TASK: usize = 16;
+
+/// Async front end of a [`CxpReader`]
+///
+/// Cheap to clone; clones share the reader and the concurrency limit.
+#[derive(Clone)]
+pub struct AsyncCxpReader {
+    reader: Arc<CxpReader>,
+    permits: Arc<Semaphore>,
+}
+
+impl AsyncCxpReader {
+    /// Wrap an opened reader (concurrency limited to the number of CPUs)
+    pub fn new(reader: CxpReader) -> Self {
+        Self::from_shared(Arc::new(reader))
+    }
+
+    /// Wrap a reader that is also used elsewhere
+    pub fn from_shared(reader: Arc<CxpReader>) -> Self {
+        let cpus = std::thread::available_parallelism().map_or(4, |n| n.get());
+        Self {
+            reader,
+            permits: Arc::new(Semaphore::new(cpus)),
+        }
+    }
+
+    /// Open an archive on disk without blocking the runtime
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let reader = tokio::task::spawn_blocking(move || CxpReader::open(path))
+            .await
+            .map_err(|e| CxpError::io(format!("Open task failed: {}", e)))??;
+        Ok(Self::new(reader))
+    }
+
+    /// Open an archive from an async backend (S3, HTTP)
+    pub async fn open_backend<B: AsyncArchiveBackend>(backend: B) -> Result<Self> {
+        Ok(Self::new(CxpReader::open_async(backend).await?))
+    }
+
+    /// Limit how many blocking tasks (reads, searches) run at once (at least 1)
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.permits = Arc::new(Semaphore::new(max.max(1)));
+        self
+    }
+
+    /// The wrapped reader, for calls that do no I/O (manifest, file list)
+    pub fn reader(&self) -> &Arc<CxpReader> {
+        &self.reader
+    }
+
+    /// Run `f` on the blocking pool once a concurrency slot is free
+    ///
+    /// The building block of the other methods; use it for reader methods
+    /// that have no async counterpart.
+    pub async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&CxpReader) -> Result<T> + Send + 'static,
+    {
+        let permit = self.permit().await?;
+        let reader = Arc::clone(&self.reader);
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            f(&reader)
+        })
+        .await
+        .map_err(|e| CxpError::io(format!("Read task failed: {}", e)))?
+    }
+
+    /// Read a file's content, decompressing its chunks in parallel
+    pub async fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        let chunk_count = {
+            let path = path.to_string();
+            self.run(move |reader| {
+                let entry = reader.file_entry(&path)?.ok_or(CxpError::FileNotFound(path))?;
+                Ok(entry.chunks.len())
+            })
+            .await?
+        };
+
+        // Spawn batches as slots free up; they finish in any order
+        let mut tasks = Vec::with_capacity(chunk_count.div_ceil(CHUNKS_PER_TASK));
+        for start in (0..chunk_count).step_by(CHUNKS_PER_TASK) {
+            let permit = self.permit().await?;
+            let reader = Arc::clone(&self.reader);
+            let path = path.to_string();
+            tasks.push(tokio::task::spawn_blocking(move || {
+                let _permit = permit;
+                reader.read_file_chunks(&path, start..start + CHUNKS_PER_TASK)
+            }));
+        }
+
+        let mut content = Vec::new();
+        for task in tasks {
+            let batch = task
+                .await
+                .map_err(|e| CxpError::io(format!("Read task failed: {}", e)))??;
+            content.extend_from_slice(&batch);
+        }
+
+        let path = path.to_string();
+        self.run(move |reader| {
+            reader.record_access(&path);
+            Ok(())
+        })
+        .await?;
+        Ok(content)
+    }
+
+    /// Read a single chunk by its hash
+    pub async fn read_chunk(&self, hash: &str) -> Result<Vec<u8>> {
+        let hash = hash.to_string();
+        self.run(move |reader| reader.read_chunk(&hash)).await
+    }
+
+    /// Load the embeddings and search index (see `CxpReader::load_embeddings`)
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub async fn load_embeddings(&self) -> Result<()> {
+        self.run(CxpReader::load_embeddings).await
+    }
+
+    /// Semantic search with a query embedding (see `CxpReader::search_semantic`)
+    ///
+    /// You must call `load_embeddings()` first.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub async fn search_semantic(&self, query_embedding: &[f32], top_k: usize) -> Result<Vec<crate::SearchResult>> {
+        let query_embedding = query_embedding.to_vec();
+        self.run(move |reader| reader.search_semantic(&query_embedding, top_k)).await
+    }
+
+    /// Wait for a free concurrency slot
+    async fn permit(&self) -> Result<OwnedSemaphorePermit> {
+        Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .map_err(|e| CxpError::io(format!("Reader closed: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CxpBuilder;
+
+    #[test]
+    fn test_read_file_in_parallel_batches() {
+        let source = tempfile::TempDir::new().unwrap();
+        // Pseudo-random lines so the file spans several read batches
+        let mut state = 0x2545_f491_u32;
+        let large: String = (0..200_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                format!("{:08x}\n", state)
+            })
+            .collect();
+        std::fs::write(source.path().join("large.txt"), &large).unwrap();
+        std::fs::write(source.path().join("empty.txt"), "").unwrap();
+        let output = tempfile::TempDir::new().unwrap();
+        let cxp_path = output.path().join("async.cxp");
+        CxpBuilder::new(source.path()).scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let reader = AsyncCxpReader::open(&cxp_path).await.unwrap().with_max_concurrency(2);
+            let chunks = reader.reader().file_entry("large.txt").unwrap().unwrap().chunks.len();
+            assert!(chunks > CHUNKS_PER_TASK);
+
+            assert_eq!(reader.read_file("large.txt").await.unwrap(), large.as_bytes());
+            assert!(reader.read_file("empty.txt").await.unwrap().is_empty());
+            assert!(matches!(reader.read_file("missing.rs").await, Err(CxpError::FileNotFound(_))));
+        });
+    }
+}
@@ -0,0 +1,518 @@
+//! Archive Backends
+//!
+//! Where a [`CxpReader`](crate::CxpReader) reads its archive from. A backend
+//! hands out independent `Read + Seek` handles; the reader keeps a few open
+//! in a pool and gives each operation its own, so backends must allow
+//! several handles at the same time.
+//!
+//! - [`FileBackend`]: a file on disk
+//! - [`MemoryBackend`]: archive bytes in memory (e.g. fetched over HTTP in a
+//!   browser); never touches the filesystem, so it works on
+//!   `wasm32-unknown-unknown`
+//! - [`SharedReaderBackend`]: any single `Read + Seek` (handles share it
+//!   behind a lock and keep their own positions)
+//!
+//! With the `tokio` feature, [`AsyncArchiveBackend`] describes remote storage
+//! that is read by byte ranges (S3, HTTP); [`BlockingBackend`] adapts it for
+//! the reader, and [`CxpReader::open_async`](crate::CxpReader::open_async)
+//! opens it without blocking the runtime.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use zip::ZipArchive;
+
+use crate::Result;
+
+/// A `Read + Seek` handle on an archive
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Storage an archive is read from
+pub trait ArchiveBackend: Send + Sync + fmt::Debug {
+    /// Open a new handle positioned at the start of the archive
+    fn open(&self) -> Result<Box<dyn ReadSeek>>;
+
+    /// Size of the archive in bytes
+    fn size(&self) -> Result<u64>;
+
+    /// Path of the archive on the local filesystem, if it has one
+    ///
+    /// Used for the access log sidecar and to resolve relative external children.
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// ZIP handle on an archive backend
+pub(crate) type ZipHandle = ZipArchive<Box<dyn ReadSeek>>;
+
+/// Open a ZIP handle on a backend's archive
+pub(crate) fn open_zip(backend: &dyn ArchiveBackend) -> Result<ZipHandle> {
+    Ok(ZipArchive::new(backend.open()?)?)
+}
+
+/// Idle ZIP handles kept per reader
+const MAX_IDLE_HANDLES: usize = 8;
+
+/// ZIP handles of a reader, reused across operations
+///
+/// Parsing the central directory on every read is what made reopening the
+/// archive per call expensive. Operations take an idle handle (or open a new
+/// one when all are busy) and put it back when done, so concurrent reads on
+/// a shared reader each get their own.
+#[derive(Default, Clone)]
+pub(crate) struct ArchivePool {
+    idle: Arc<parking_lot::Mutex<Vec<ZipHandle>>>,
+}
+
+impl ArchivePool {
+    /// Take an idle handle, or open a new one on `backend`
+    pub(crate) fn get(&self, backend: &dyn ArchiveBackend) -> Result<PooledArchive> {
+        let archive = match self.idle.lock().pop() {
+            Some(archive) => archive,
+            None => open_zip(backend)?,
+        };
+        Ok(PooledArchive { archive: Some(archive), pool: self.clone() })
+    }
+
+    /// Keep `archive` for later operations
+    pub(crate) fn put(&self, archive: ZipHandle) {
+        let mut idle = self.idle.lock();
+        if idle.len() < MAX_IDLE_HANDLES {
+            idle.push(archive);
+        }
+    }
+}
+
+impl fmt::Debug for ArchivePool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArchivePool").field("idle", &self.idle.lock().len()).finish()
+    }
+}
+
+/// A ZIP handle borrowed from an [`ArchivePool`], returned to it on drop
+pub(crate) struct PooledArchive {
+    archive: Option<ZipHandle>,
+    pool: ArchivePool,
+}
+
+impl Deref for PooledArchive {
+    type Target = ZipHandle;
+
+    fn deref(&self) -> &Self::Target {
+        self.archive.as_ref().expect("archive taken before drop")
+    }
+}
+
+impl DerefMut for PooledArchive {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.archive.as_mut().expect("archive taken before drop")
+    }
+}
+
+impl Drop for PooledArchive {
+    fn drop(&mut self) {
+        if let Some(archive) = self.archive.take() {
+            self.pool.put(archive);
+        }
+    }
+}
+
+/// Archive file on disk, reopened per handle
+#[derive(Debug, Clone)]
+pub struct FileBackend {
+    path: PathBuf,
+}
+
+impl FileBackend {
+    /// Backend for the archive at `path`
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl ArchiveBackend for FileBackend {
+    fn open(&self) -> Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(File::open(&self.path)?))
+    }
+
+    fn size(&self) -> Result<u64> {
+        Ok(std::fs::metadata(&self.path)?.len())
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+}
+
+/// Archive bytes in memory, shared between handles
+#[derive(Clone)]
+pub struct MemoryBackend {
+    data: Arc<[u8]>,
+}
+
+impl MemoryBackend {
+    /// Backend over archive bytes
+    pub fn new<D: Into<Arc<[u8]>>>(data: D) -> Self {
+        Self { data: data.into() }
+    }
+}
+
+impl fmt::Debug for MemoryBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryBackend").field("len", &self.data.len()).finish()
+    }
+}
+
+impl ArchiveBackend for MemoryBackend {
+    fn open(&self) -> Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(Cursor::new(self.data.clone())))
+    }
+
+    fn size(&self) -> Result<u64> {
+        Ok(self.data.len() as u64)
+    }
+}
+
+/// A single `Read + Seek` shared by all handles
+///
+/// Each handle keeps its own position and seeks the shared reader under a
+/// lock before every read, so handles can be used concurrently.
+pub struct SharedReaderBackend<R> {
+    inner: Arc<Mutex<R>>,
+    size: u64,
+}
+
+impl<R: Read + Seek + Send + 'static> SharedReaderBackend<R> {
+    /// Share `reader` between handles
+    pub fn new(mut reader: R) -> Result<Self> {
+        let size = reader.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(reader)),
+            size,
+        })
+    }
+}
+
+impl<R> fmt::Debug for SharedReaderBackend<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedReaderBackend").field("size", &self.size).finish()
+    }
+}
+
+impl<R: Read + Seek + Send + 'static> ArchiveBackend for SharedReaderBackend<R> {
+    fn open(&self) -> Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(SharedHandle {
+            inner: self.inner.clone(),
+            position: 0,
+            size: self.size,
+        }))
+    }
+
+    fn size(&self) -> Result<u64> {
+        Ok(self.size)
+    }
+}
+
+/// Handle of a [`SharedReaderBackend`]
+struct SharedHandle<R> {
+    inner: Arc<Mutex<R>>,
+    position: u64,
+    size: u64,
+}
+
+impl<R: Read + Seek> Read for SharedHandle<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut inner = self
+            .inner
+            .lock()
+            .map_err(|_| std::io::Error::other("Lock poisoned"))?;
+        inner.seek(SeekFrom::Start(self.position))?;
+        let n = inner.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R> Seek for SharedHandle<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = seek_position(self.position, self.size, pos)?;
+        Ok(self.position)
+    }
+}
+
+/// New position after seeking from `position` in a stream of `size` bytes
+fn seek_position(position: u64, size: u64, pos: SeekFrom) -> std::io::Result<u64> {
+    let target = match pos {
+        SeekFrom::Start(offset) => Some(offset),
+        SeekFrom::End(delta) => size.checked_add_signed(delta),
+        SeekFrom::Current(delta) => position.checked_add_signed(delta),
+    };
+    target.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Seek before start of archive"))
+}
+
+/// Remote storage read by byte ranges (S3, GCS, HTTP range requests)
+#[cfg(feature = "tokio")]
+pub trait AsyncArchiveBackend: Send + Sync + 'static {
+    /// Size of the archive in bytes
+    fn size(&self) -> impl std::future::Future<Output = Result<u64>> + Send;
+
+    /// Read up to `len` bytes starting at `offset` (fewer only at the end)
+    fn read_range(&self, offset: u64, len: usize) -> impl std::future::Future<Output = Result<Vec<u8>>> + Send;
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncArchiveBackend for MemoryBackend {
+    async fn size(&self) -> Result<u64> {
+        Ok(self.data.len() as u64)
+    }
+
+    async fn read_range(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let start = (offset as usize).min(self.data.len());
+        let end = start.saturating_add(len).min(self.data.len());
+        Ok(self.data[start..end].to_vec())
+    }
+}
+
+/// Adapts an [`AsyncArchiveBackend`] to the blocking reader
+///
+/// Reads block on the runtime the adapter was created on, in blocks of
+/// [`DEFAULT_BLOCK_SIZE`](Self::DEFAULT_BLOCK_SIZE) to keep the number of range
+/// requests low. Use the reader from blocking threads only (e.g.
+/// `tokio::task::spawn_blocking`), never directly on the runtime.
+#[cfg(feature = "tokio")]
+pub struct BlockingBackend<B> {
+    backend: Arc<B>,
+    runtime: tokio::runtime::Handle,
+    size: u64,
+    block_size: usize,
+}
+
+#[cfg(feature = "tokio")]
+impl<B: AsyncArchiveBackend> BlockingBackend<B> {
+    /// Bytes fetched per range request
+    pub const DEFAULT_BLOCK_SIZE: usize = 256 * 1024;
+
+    /// Wrap `backend`, fetching its size
+    pub async fn new(backend: B) -> Result<Self> {
+        let size = backend.size().await?;
+        Ok(Self {
+            backend: Arc::new(backend),
+            runtime: tokio::runtime::Handle::current(),
+            size,
+            block_size: Self::DEFAULT_BLOCK_SIZE,
+        })
+    }
+
+    /// Set the number of bytes fetched per range request
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size.max(1);
+        self
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<B> fmt::Debug for BlockingBackend<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingBackend")
+            .field("size", &self.size)
+            .field("block_size", &self.block_size)
+            .finish()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<B: AsyncArchiveBackend> ArchiveBackend for BlockingBackend<B> {
+    fn open(&self) -> Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(BlockingHandle {
+            backend: self.backend.clone(),
+            runtime: self.runtime.clone(),
+            size: self.size,
+            block_size: self.block_size,
+            position: 0,
+            block_start: 0,
+            block: Vec::new(),
+        }))
+    }
+
+    fn size(&self) -> Result<u64> {
+        Ok(self.size)
+    }
+}
+
+/// Handle of a [`BlockingBackend`] with a one-block read cache
+#[cfg(feature = "tokio")]
+struct BlockingHandle<B> {
+    backend: Arc<B>,
+    runtime: tokio::runtime::Handle,
+    size: u64,
+    block_size: usize,
+    position: u64,
+    block_start: u64,
+    block: Vec<u8>,
+}
+
+#[cfg(feature = "tokio")]
+impl<B: AsyncArchiveBackend> Read for BlockingHandle<B> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || self.position >= self.size {
+            return Ok(0);
+        }
+
+        let cached = self.position >= self.block_start
+            && self.position < self.block_start + self.block.len() as u64;
+        if !cached {
+            // Large reads bypass the cache with a single request
+            let len = buf.len().max(self.block_size);
+            let data = self
+                .runtime
+                .block_on(self.backend.read_range(self.position, len))
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            if data.is_empty() {
+                return Ok(0);
+            }
+            self.block_start = self.position;
+            self.block = data;
+        }
+
+        let offset = (self.position - self.block_start) as usize;
+        let n = buf.len().min(self.block.len() - offset);
+        buf[..n].copy_from_slice(&self.block[offset..offset + n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<B> Seek for BlockingHandle<B> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = seek_position(self.position, self.size, pos)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(handle: &mut Box<dyn ReadSeek>) -> Vec<u8> {
+        let mut data = Vec::new();
+        handle.read_to_end(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_shared_reader_handles_are_independent() {
+        let data: Vec<u8> = (0..=255).collect();
+        let backend = SharedReaderBackend::new(Cursor::new(data.clone())).unwrap();
+        assert_eq!(backend.size().unwrap(), 256);
+
+        let mut a = backend.open().unwrap();
+        let mut b = backend.open().unwrap();
+        let mut buf = [0u8; 4];
+        a.seek(SeekFrom::Start(100)).unwrap();
+        a.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [100, 101, 102, 103]);
+
+        // b starts at 0 even though a moved the shared reader
+        b.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 1, 2, 3]);
+        a.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [104, 105, 106, 107]);
+
+        b.seek(SeekFrom::End(-2)).unwrap();
+        assert_eq!(read_all(&mut b), vec![254, 255]);
+        assert!(b.seek(SeekFrom::Current(-300)).is_err());
+    }
+
+    #[test]
+    fn test_reader_is_shared_across_threads() {
+        use crate::{CxpBuilder, CxpReader};
+
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CxpReader>();
+
+        let source = tempfile::TempDir::new().unwrap();
+        std::fs::write(source.path().join("main.rs"), "fn main() {}\n").unwrap();
+        let output = tempfile::TempDir::new().unwrap();
+        let cxp_path = output.path().join("shared.cxp");
+        CxpBuilder::new(source.path()).scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+
+        let reader = Arc::new(CxpReader::open(&cxp_path).unwrap());
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = Arc::clone(&reader);
+                std::thread::spawn(move || reader.read_file("main.rs").unwrap())
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), b"fn main() {}\n");
+        }
+    }
+
+    #[test]
+    fn test_pool_reuses_returned_handles() {
+        let data = {
+            let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+            zip.start_file("a.txt", zip::write::FileOptions::<()>::default()).unwrap();
+            zip.finish().unwrap().into_inner()
+        };
+        let backend = MemoryBackend::new(data);
+        let pool = ArchivePool::default();
+
+        let first = pool.get(&backend).unwrap();
+        let second = pool.get(&backend).unwrap();
+        assert_eq!(pool.idle.lock().len(), 0);
+        drop(first);
+        drop(second);
+        assert_eq!(pool.idle.lock().len(), 2);
+
+        let mut reused = pool.get(&backend).unwrap();
+        assert_eq!(pool.idle.lock().len(), 1);
+        assert!(reused.by_name("a.txt").is_ok());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_blocking_backend_reads_in_blocks() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let backend = runtime
+            .block_on(BlockingBackend::new(MemoryBackend::new(data.clone())))
+            .unwrap()
+            .with_block_size(1000);
+        assert_eq!(backend.size().unwrap(), 10_000);
+
+        let mut handle = backend.open().unwrap();
+        assert_eq!(read_all(&mut handle), data);
+
+        handle.seek(SeekFrom::Start(9_998)).unwrap();
+        assert_eq!(read_all(&mut handle), data[9_998..]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_open_async() {
+        use crate::{CxpBuilder, CxpReader};
+
+        let source = tempfile::TempDir::new().unwrap();
+        std::fs::write(source.path().join("main.rs"), "fn main() {}\n").unwrap();
+        let output = tempfile::TempDir::new().unwrap();
+        let cxp_path = output.path().join("async.cxp");
+        CxpBuilder::new(source.path()).scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+        let backend = MemoryBackend::new(std::fs::read(&cxp_path).unwrap());
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let content = runtime.block_on(async {
+            let reader = CxpReader::open_async(backend).await.unwrap();
+            tokio::task::spawn_blocking(move || reader.read_file("main.rs")).await.unwrap()
+        });
+        assert_eq!(content.unwrap(), b"fn main() {}\n");
+    }
+}
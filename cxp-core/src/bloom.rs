@@ -0,0 +1,181 @@
+//! Bloom Filters for Negative Lookups
+//!
+//! Archives store a bloom filter over all file paths and one over all chunk
+//! hashes. A negative answer is definite, so `contains_file()` and chunk
+//! existence checks can reject most misses without loading the file map or
+//! opening the chunk directory - useful when probing many archives.
+//!
+//! Binary layout (little-endian):
+//! ```text
+//! u64 num_bits | u32 num_hashes | u64 words[num_bits / 64]
+//! ```
+
+use crate::{CxpError, Result};
+
+/// Path of the file path filter inside the archive
+pub const PATH_FILTER_PATH: &str = "filters/paths.bloom";
+
+/// Path of the chunk hash filter inside the archive
+pub const CHUNK_FILTER_PATH: &str = "filters/chunks.bloom";
+
+/// Default false positive rate (1%)
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Header size of the serialized filter
+const HEADER_SIZE: usize = 12;
+
+/// A bloom filter over byte strings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    /// Number of bits (multiple of 64)
+    num_bits: u64,
+    /// Number of hash functions
+    num_hashes: u32,
+    /// Bit array
+    words: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Create an empty filter sized for `expected_items` at the given false positive rate
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let p = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        let bits = (-(n * p.ln()) / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_bits = bits.div_ceil(64) * 64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().clamp(1.0, 16.0) as u32;
+
+        Self {
+            num_bits,
+            num_hashes,
+            words: vec![0; (num_bits / 64) as usize],
+        }
+    }
+
+    /// Build a filter containing every item
+    pub fn from_items<I, S>(items: I, false_positive_rate: f64) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        I::IntoIter: ExactSizeIterator,
+        S: AsRef<[u8]>,
+    {
+        let items = items.into_iter();
+        let mut filter = Self::new(items.len(), false_positive_rate);
+        for item in items {
+            filter.insert(item.as_ref());
+        }
+        filter
+    }
+
+    /// Add an item
+    pub fn insert(&mut self, item: &[u8]) {
+        let (h1, h2) = hash_pair(item);
+        for i in 0..self.num_hashes as u64 {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits;
+            self.words[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Check an item - `false` means definitely absent
+    pub fn contains(&self, item: &[u8]) -> bool {
+        let (h1, h2) = hash_pair(item);
+        (0..self.num_hashes as u64).all(|i| {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits;
+            self.words[(bit / 64) as usize] & (1 << (bit % 64)) != 0
+        })
+    }
+
+    /// Size of the serialized filter in bytes
+    pub fn size_bytes(&self) -> usize {
+        HEADER_SIZE + self.words.len() * 8
+    }
+
+    /// Serialize to the binary layout
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.size_bytes());
+        data.extend_from_slice(&self.num_bits.to_le_bytes());
+        data.extend_from_slice(&self.num_hashes.to_le_bytes());
+        for word in &self.words {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data
+    }
+
+    /// Deserialize from the binary layout
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(CxpError::InvalidFormat("Bloom filter too short".to_string()));
+        }
+
+        let num_bits = u64::from_le_bytes(data[0..8].try_into().unwrap());
+        let num_hashes = u32::from_le_bytes(data[8..12].try_into().unwrap());
+        let body = &data[HEADER_SIZE..];
+
+        if num_bits == 0 || num_bits % 64 != 0 || body.len() as u64 != num_bits / 8 || num_hashes == 0 {
+            return Err(CxpError::InvalidFormat("Corrupt bloom filter".to_string()));
+        }
+
+        let words = body
+            .chunks_exact(8)
+            .map(|w| u64::from_le_bytes(w.try_into().unwrap()))
+            .collect();
+
+        Ok(Self {
+            num_bits,
+            num_hashes,
+            words,
+        })
+    }
+}
+
+/// Two independent 64-bit hashes for double hashing (FNV-1a + SplitMix64 finalizer)
+fn hash_pair(data: &[u8]) -> (u64, u64) {
+    let mut h1: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in data {
+        h1 ^= byte as u64;
+        h1 = h1.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    let mut h2 = h1 ^ (data.len() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    h2 = (h2 ^ (h2 >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h2 = (h2 ^ (h2 >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h2 ^= h2 >> 31;
+
+    (h1, h2 | 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_no_false_negatives() {
+        let items: Vec<String> = (0..1000).map(|i| format!("src/file_{}.rs", i)).collect();
+        let filter = BloomFilter::from_items(&items, DEFAULT_FALSE_POSITIVE_RATE);
+
+        assert!(items.iter().all(|item| filter.contains(item.as_bytes())));
+    }
+
+    #[test]
+    fn test_bloom_false_positive_rate() {
+        let items: Vec<String> = (0..1000).map(|i| format!("src/file_{}.rs", i)).collect();
+        let filter = BloomFilter::from_items(&items, DEFAULT_FALSE_POSITIVE_RATE);
+
+        let false_positives = (0..10_000)
+            .filter(|i| filter.contains(format!("docs/other_{}.md", i).as_bytes()))
+            .count();
+        assert!(false_positives < 300, "too many false positives: {}", false_positives);
+    }
+
+    #[test]
+    fn test_bloom_roundtrip() {
+        let filter = BloomFilter::from_items(["a", "b", "c"], DEFAULT_FALSE_POSITIVE_RATE);
+        let bytes = filter.to_bytes();
+
+        assert_eq!(bytes.len(), filter.size_bytes());
+        assert_eq!(BloomFilter::from_bytes(&bytes).unwrap(), filter);
+        assert!(BloomFilter::from_bytes(&bytes[..10]).is_err());
+        assert!(BloomFilter::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Build Telemetry
+//!
+//! `CxpBuilder` records how an archive was produced - builder version, enabled
+//! features, host, time spent per phase and the parameters in effect - and
+//! stores it as an extension so support and reproducibility questions can be
+//! answered from the file itself.
+//!
+//! Structure:
+//! ```text
+//! extensions/build_info/
+//! ├── manifest.msgpack     # extension metadata
+//! └── build_info.msgpack   # BuildInfo
+//! ```
+
+use crate::extensions::Extension;
+use crate::{CxpError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Extension namespace of the build telemetry
+pub const BUILD_INFO_NAMESPACE: &str = "build_info";
+
+/// Data key of the build telemetry within its namespace
+pub const BUILD_INFO_KEY: &str = "build_info.msgpack";
+
+/// Version of the build telemetry layout
+pub const BUILD_INFO_VERSION: &str = "1.0.0";
+
+/// How and where an archive was built
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// Version of cxp-core that wrote the archive
+    pub builder_version: String,
+    /// Cargo features cxp-core was compiled with
+    pub features: Vec<String>,
+    /// Host operating system (e.g. "linux")
+    pub host_os: String,
+    /// Host CPU architecture (e.g. "x86_64")
+    pub host_arch: String,
+    /// When the builder was created
+    pub started_at: DateTime<Utc>,
+    /// Time spent per phase in milliseconds (scan, process, embeddings, write, ...)
+    pub phases_ms: BTreeMap<String, u64>,
+    /// Parameters in effect (shard size, chunk sizes, embedding model, ...)
+    pub parameters: BTreeMap<String, String>,
+}
+
+impl Default for BuildInfo {
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+impl BuildInfo {
+    /// Build info for this binary and host, with no phases recorded yet
+    pub fn current() -> Self {
+        Self {
+            builder_version: env!("CARGO_PKG_VERSION").to_string(),
+            features: enabled_features(),
+            host_os: std::env::consts::OS.to_string(),
+            host_arch: std::env::consts::ARCH.to_string(),
+            started_at: Utc::now(),
+            phases_ms: BTreeMap::new(),
+            parameters: BTreeMap::new(),
+        }
+    }
+
+    /// Add time spent in a phase (repeated phases accumulate)
+    pub fn record_phase(&mut self, phase: &str, elapsed: Duration) {
+        *self.phases_ms.entry(phase.to_string()).or_insert(0) += elapsed.as_millis() as u64;
+    }
+
+    /// Set a build parameter
+    pub fn set_parameter(&mut self, key: &str, value: impl ToString) {
+        self.parameters.insert(key.to_string(), value.to_string());
+    }
+
+    /// Total time across all recorded phases
+    pub fn total_duration(&self) -> Duration {
+        Duration::from_millis(self.phases_ms.values().sum())
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+}
+
+/// Extension marker used to register the build_info namespace
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuildInfoExtension;
+
+impl Extension for BuildInfoExtension {
+    fn namespace(&self) -> &str {
+        BUILD_INFO_NAMESPACE
+    }
+
+    fn version(&self) -> &str {
+        BUILD_INFO_VERSION
+    }
+}
+
+/// Cargo features enabled at compile time
+fn enabled_features() -> Vec<String> {
+    let features = [
+        ("embeddings", cfg!(feature = "embeddings")),
+        ("embeddings-wasm", cfg!(feature = "embeddings-wasm")),
+        ("multimodal", cfg!(feature = "multimodal")),
+        ("search", cfg!(feature = "search")),
+        ("contextai", cfg!(feature = "contextai")),
+        ("scanner", cfg!(feature = "scanner")),
+        ("tokenizer", cfg!(feature = "tokenizer")),
+        ("watch", cfg!(feature = "watch")),
+        ("ffi", cfg!(feature = "ffi")),
+        ("tokio", cfg!(feature = "tokio")),
+        ("cloud", cfg!(feature = "cloud")),
+        ("lz4", cfg!(feature = "lz4")),
+        ("redact", cfg!(feature = "redact")),
+        ("regex", cfg!(feature = "regex")),
+        ("git", cfg!(feature = "git")),
+        ("arrow", cfg!(feature = "arrow")),
+    ];
+    features
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info_roundtrip() {
+        let mut info = BuildInfo::current();
+        info.record_phase("scan", Duration::from_millis(5));
+        info.record_phase("scan", Duration::from_millis(7));
+        info.record_phase("write", Duration::from_millis(3));
+        info.set_parameter("shard_size", 1000);
+
+        assert_eq!(info.phases_ms["scan"], 12);
+        assert_eq!(info.total_duration(), Duration::from_millis(15));
+        assert_eq!(info.host_os, std::env::consts::OS);
+        assert_eq!(info.features.iter().any(|f| f == "regex"), cfg!(feature = "regex"));
+        assert_eq!(info.features.iter().any(|f| f == "arrow"), cfg!(feature = "arrow"));
+
+        let restored = BuildInfo::from_msgpack(&info.to_msgpack().unwrap()).unwrap();
+        assert_eq!(restored, info);
+    }
+}
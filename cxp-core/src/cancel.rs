@@ -0,0 +1,97 @@
+//! Cooperative Cancellation
+//!
+//! A `CancellationToken` is a cheap, clonable flag shared between a host
+//! application (GUI, server) and long-running work. Builders and readers check
+//! it between units of work - files, embedding batches, chunks, queries - and
+//! stop with [`CxpError::Cancelled`] once it is set. Search timeouts use a
+//! deadline checked between query variants and child CXPs.
+//!
+//! # Example
+//! ```ignore
+//! let token = CancellationToken::new();
+//! let mut builder = CxpBuilder::new("./project");
+//! builder.with_cancellation(token.clone());
+//!
+//! // From another thread (e.g. a "Cancel" button)
+//! token.cancel();
+//! ```
+
+use crate::{CxpError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Shared cancellation flag
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation (affects every clone of this token)
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Return `CxpError::Cancelled` naming `operation` if cancellation was requested
+    pub fn check(&self, operation: &str) -> Result<()> {
+        if self.is_cancelled() {
+            Err(CxpError::Cancelled(operation.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Point in time after which an operation should stop (none = no timeout)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// Deadline `timeout` from now
+    pub(crate) fn after(timeout: Option<Duration>) -> Self {
+        Self(timeout.map(|t| Instant::now() + t))
+    }
+
+    /// Whether the deadline has passed
+    pub(crate) fn expired(&self) -> bool {
+        self.0.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        assert!(clone.check("scan").is_ok());
+
+        token.cancel();
+        assert!(clone.is_cancelled());
+        match clone.check("scan") {
+            Err(CxpError::Cancelled(operation)) => assert_eq!(operation, "scan"),
+            other => panic!("expected Cancelled, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_deadline() {
+        assert!(!Deadline::after(None).expired());
+        assert!(!Deadline::after(Some(Duration::from_secs(60))).expired());
+        assert!(Deadline::after(Some(Duration::ZERO)).expired());
+    }
+}
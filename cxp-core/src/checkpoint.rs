@@ -0,0 +1,391 @@
+//! Build Checkpoints
+//!
+//! `CxpBuilder::with_checkpoint(dir)` journals build work to `dir` so a build
+//! that crashes or is cancelled can be rerun without starting over:
+//!
+//! - `files.jsonl`: processed files (entry, source size, redactions)
+//! - `chunks/<hash>`: content of the journaled chunks
+//! - `embeddings.bin`: generated chunk embeddings (length-prefixed MessagePack)
+//! - `checkpoint.json`: the settings the journal was written with
+//!
+//! On rerun, files whose size and modification time are unchanged come from
+//! the journal instead of being read, redacted and chunked again, and chunks
+//! with a journaled embedding are not embedded again. Records are flushed as
+//! work completes; a record cut short by a crash is dropped. The journal is
+//! discarded when the chunker, redaction or embedding model change, and the
+//! directory is removed once `build()` succeeds.
+//!
+//! Files added from memory and images are not journaled.
+//!
+//! # Example
+//! ```ignore
+//! let mut builder = CxpBuilder::new("./project");
+//! builder.with_checkpoint(".cxp-checkpoint")?;
+//! builder.scan()?.process()?.build("project.cxp")?;
+//! ```
+
+use crate::chunker::{Chunk, ChunkRef};
+use crate::format::FileEntry;
+use crate::manifest::Redaction;
+use crate::{CxpError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::embeddings::{BinaryEmbedding, Int8Embedding, QuantizedEmbeddings};
+
+const SETTINGS_FILE: &str = "checkpoint.json";
+const FILES_JOURNAL: &str = "files.jsonl";
+const CHUNKS_DIR: &str = "chunks";
+#[cfg(all(feature = "embeddings", feature = "search"))]
+const EMBEDDINGS_JOURNAL: &str = "embeddings.bin";
+
+/// Binary, int8 and (for f16/f32 precision) float embedding of one chunk
+#[cfg(all(feature = "embeddings", feature = "search"))]
+type EmbeddingRow = (BinaryEmbedding, Int8Embedding, Option<Vec<f32>>);
+
+/// Settings a journal is only valid for
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    /// Chunker, redaction and provenance settings of the journaled files
+    files: Option<String>,
+    /// Model of the journaled embeddings
+    model: Option<String>,
+}
+
+/// A processed file as journaled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournaledFile {
+    /// Entry with its chunk refs
+    entry: FileEntry,
+    /// Size of the source file (the entry holds the redacted size)
+    source_size: u64,
+    /// Values replaced in the file
+    redactions: Vec<Redaction>,
+}
+
+/// A chunk embedding as journaled
+#[cfg(all(feature = "embeddings", feature = "search"))]
+#[derive(Debug, Serialize, Deserialize)]
+struct JournaledEmbedding {
+    hash: String,
+    binary: BinaryEmbedding,
+    int8: Int8Embedding,
+    #[serde(default)]
+    float: Option<Vec<f32>>,
+}
+
+/// On-disk journal of a build
+pub struct Checkpoint {
+    dir: PathBuf,
+    settings: Settings,
+    files: HashMap<String, JournaledFile>,
+    files_log: Mutex<File>,
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    embeddings: HashMap<String, EmbeddingRow>,
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    embeddings_log: Mutex<File>,
+}
+
+impl Checkpoint {
+    /// Open the journal in `dir`, creating it if needed
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(dir.join(CHUNKS_DIR))?;
+
+        let settings = match std::fs::read(dir.join(SETTINGS_FILE)) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_default(),
+            Err(_) => Settings::default(),
+        };
+
+        let (files, files_log) = load_files(&dir.join(FILES_JOURNAL))?;
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        let (embeddings, embeddings_log) = load_embeddings(&dir.join(EMBEDDINGS_JOURNAL))?;
+
+        let checkpoint = Self {
+            dir,
+            settings,
+            files,
+            files_log: Mutex::new(files_log),
+            #[cfg(all(feature = "embeddings", feature = "search"))]
+            embeddings,
+            #[cfg(all(feature = "embeddings", feature = "search"))]
+            embeddings_log: Mutex::new(embeddings_log),
+        };
+        if !checkpoint.is_empty() {
+            tracing::info!(
+                "Resuming from checkpoint {:?}: {} files, {} embeddings",
+                checkpoint.dir,
+                checkpoint.file_count(),
+                checkpoint.embedding_count()
+            );
+        }
+        Ok(checkpoint)
+    }
+
+    /// Journal directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Number of journaled files
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Number of journaled embeddings
+    pub fn embedding_count(&self) -> usize {
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        return self.embeddings.len();
+        #[cfg(not(all(feature = "embeddings", feature = "search")))]
+        0
+    }
+
+    /// Whether nothing is journaled yet
+    pub fn is_empty(&self) -> bool {
+        self.file_count() == 0 && self.embedding_count() == 0
+    }
+
+    /// Start journaling files processed with `settings`, dropping files
+    /// journaled with other settings
+    pub(crate) fn begin_files(&mut self, settings: &str) -> Result<()> {
+        if self.settings.files.as_deref() == Some(settings) {
+            return Ok(());
+        }
+        if !self.files.is_empty() {
+            tracing::info!("Build settings changed, discarding {} journaled files", self.files.len());
+        }
+        self.files.clear();
+        self.files_log.get_mut().unwrap_or_else(|e| e.into_inner()).set_len(0)?;
+        std::fs::remove_dir_all(self.dir.join(CHUNKS_DIR))?;
+        std::fs::create_dir_all(self.dir.join(CHUNKS_DIR))?;
+        self.settings.files = Some(settings.to_string());
+        self.save_settings()
+    }
+
+    /// Journaled result for the file at `path`, if its source is unchanged
+    pub(crate) fn file(
+        &self,
+        path: &str,
+        source_size: u64,
+        modified: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Option<(FileEntry, Vec<Chunk>, Vec<Redaction>)> {
+        let journaled = self.files.get(path)?;
+        if journaled.source_size != source_size || journaled.entry.modified != modified {
+            return None;
+        }
+        let mut chunks = Vec::with_capacity(journaled.entry.chunks.len());
+        for chunk_ref in &journaled.entry.chunks {
+            let data = std::fs::read(self.chunk_path(&chunk_ref.hash)).ok()?;
+            let chunk = Chunk::new(data, chunk_ref.offset);
+            if chunk.hash != chunk_ref.hash {
+                return None;
+            }
+            chunks.push(chunk);
+        }
+        let entry = FileEntry {
+            chunks: Vec::new(),
+            ..journaled.entry.clone()
+        };
+        Some((entry, chunks, journaled.redactions.clone()))
+    }
+
+    /// Journal a processed file (chunks first, so every entry has its chunks)
+    pub(crate) fn record_file(
+        &self,
+        source_size: u64,
+        entry: &FileEntry,
+        chunks: &[Chunk],
+        redactions: &[Redaction],
+    ) -> Result<()> {
+        for chunk in chunks {
+            let path = self.chunk_path(&chunk.hash);
+            if !path.exists() {
+                std::fs::write(path, &chunk.data)?;
+            }
+        }
+        let record = JournaledFile {
+            entry: FileEntry {
+                chunks: chunks.iter().map(ChunkRef::from).collect(),
+                ..entry.clone()
+            },
+            source_size,
+            redactions: redactions.to_vec(),
+        };
+        let mut line = serde_json::to_vec(&record).map_err(|e| CxpError::Serialization(e.to_string()))?;
+        line.push(b'\n');
+        let mut log = self.files_log.lock().unwrap_or_else(|e| e.into_inner());
+        log.write_all(&line)?;
+        log.flush()?;
+        Ok(())
+    }
+
+    /// Start journaling embeddings of `model`, dropping embeddings of other models
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub(crate) fn begin_embeddings(&mut self, model: &str) -> Result<()> {
+        if self.settings.model.as_deref() == Some(model) {
+            return Ok(());
+        }
+        if !self.embeddings.is_empty() {
+            tracing::info!("Embedding model changed, discarding {} journaled embeddings", self.embeddings.len());
+        }
+        self.embeddings.clear();
+        self.embeddings_log.get_mut().unwrap_or_else(|e| e.into_inner()).set_len(0)?;
+        self.settings.model = Some(model.to_string());
+        self.save_settings()
+    }
+
+    /// Move the journaled embeddings out (chunk hash -> embedding)
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub(crate) fn take_embeddings(&mut self) -> HashMap<String, EmbeddingRow> {
+        std::mem::take(&mut self.embeddings)
+    }
+
+    /// Journal the embeddings of the chunks `hashes`, in order
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub(crate) fn record_embeddings(&self, hashes: &[&str], embeddings: &QuantizedEmbeddings) -> Result<()> {
+        let mut data = Vec::new();
+        for (row, ((hash, binary), int8)) in hashes.iter().zip(&embeddings.binary).zip(&embeddings.int8).enumerate() {
+            let record = JournaledEmbedding {
+                hash: hash.to_string(),
+                binary: binary.clone(),
+                int8: int8.clone(),
+                float: embeddings.float.get(row).cloned(),
+            };
+            let bytes = rmp_serde::to_vec(&record).map_err(|e| CxpError::Serialization(e.to_string()))?;
+            data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            data.extend_from_slice(&bytes);
+        }
+        let mut log = self.embeddings_log.lock().unwrap_or_else(|e| e.into_inner());
+        log.write_all(&data)?;
+        log.flush()?;
+        Ok(())
+    }
+
+    /// Delete the journal (after a successful build)
+    pub fn remove(self) -> Result<()> {
+        let dir = self.dir.clone();
+        drop(self);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    fn chunk_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(CHUNKS_DIR).join(hash)
+    }
+
+    fn save_settings(&self) -> Result<()> {
+        let data = serde_json::to_vec_pretty(&self.settings).map_err(|e| CxpError::Serialization(e.to_string()))?;
+        std::fs::write(self.dir.join(SETTINGS_FILE), data)?;
+        Ok(())
+    }
+}
+
+/// Open a journal for appending, truncated to `len` valid bytes
+fn append_log(path: &Path, len: u64) -> Result<File> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.set_len(len)?;
+    Ok(file)
+}
+
+/// Read the files journal up to the first incomplete or corrupt line
+fn load_files(path: &Path) -> Result<(HashMap<String, JournaledFile>, File)> {
+    let mut files = HashMap::new();
+    let mut valid = 0u64;
+    if let Ok(file) = File::open(path) {
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            if !line.ends_with('\n') {
+                break;
+            }
+            let Ok(record) = serde_json::from_str::<JournaledFile>(&line) else {
+                break;
+            };
+            valid += line.len() as u64;
+            files.insert(record.entry.path.clone(), record);
+            line.clear();
+        }
+    }
+    Ok((files, append_log(path, valid)?))
+}
+
+/// Read the embeddings journal up to the first incomplete or corrupt record
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn load_embeddings(path: &Path) -> Result<(HashMap<String, EmbeddingRow>, File)> {
+    let mut embeddings = HashMap::new();
+    let data = std::fs::read(path).unwrap_or_default();
+    let mut offset = 0;
+    while let Some(len) = data.get(offset..offset + 4) {
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        let Some(bytes) = data.get(offset + 4..offset + 4 + len) else {
+            break;
+        };
+        let Ok(record) = rmp_serde::from_slice::<JournaledEmbedding>(bytes) else {
+            break;
+        };
+        embeddings.insert(record.hash, (record.binary, record.int8, record.float));
+        offset += 4 + len;
+    }
+    Ok((embeddings, append_log(path, offset as u64)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CancellationToken, CxpBuilder, CxpReader};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let source = TempDir::new().unwrap();
+        std::fs::write(source.path().join("a.txt"), "alpha\n").unwrap();
+        std::fs::write(source.path().join("b.txt"), "beta\n").unwrap();
+        let work = TempDir::new().unwrap();
+        let journal = work.path().join("checkpoint");
+        let cxp_path = work.path().join("out.cxp");
+
+        // Cancel the first build after one file
+        struct CancelAfterFirst(CancellationToken);
+        impl crate::ProgressReporter for CancelAfterFirst {
+            fn chunked(&self, _path: &str, _done: usize, _total: usize) {
+                self.0.cancel();
+            }
+        }
+        let token = CancellationToken::new();
+        let mut builder = CxpBuilder::new(source.path());
+        builder
+            .with_cancellation(token.clone())
+            .with_progress(Arc::new(CancelAfterFirst(token)))
+            .with_checkpoint(&journal)
+            .unwrap();
+        assert!(builder.scan().unwrap().process().is_err());
+        assert_eq!(Checkpoint::open(&journal).unwrap().file_count(), 1);
+
+        // A torn record at the end is dropped
+        let mut log = OpenOptions::new().append(true).open(journal.join(FILES_JOURNAL)).unwrap();
+        log.write_all(b"{\"entry\":").unwrap();
+        let checkpoint = Checkpoint::open(&journal).unwrap();
+        assert_eq!(checkpoint.file_count(), 1);
+        let (path, journaled) = checkpoint.files.iter().next().unwrap();
+        let (entry, chunks, _) = checkpoint.file(path, journaled.source_size, journaled.entry.modified).unwrap();
+        assert_eq!(entry.path, *path);
+        assert_eq!(chunks.len(), journaled.entry.chunks.len());
+        assert!(checkpoint.file(path, journaled.source_size + 1, journaled.entry.modified).is_none());
+        drop(checkpoint);
+
+        // The rerun completes and removes the journal
+        let mut builder = CxpBuilder::new(source.path());
+        builder.with_checkpoint(&journal).unwrap();
+        builder.scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+        let reader = CxpReader::open(&cxp_path).unwrap();
+        assert_eq!(reader.read_file("a.txt").unwrap(), b"alpha\n");
+        assert_eq!(reader.read_file("b.txt").unwrap(), b"beta\n");
+        assert!(!journal.exists());
+    }
+}
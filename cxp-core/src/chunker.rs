@@ -2,11 +2,25 @@
 //!
 //! Splits files into variable-sized chunks based on content boundaries,
 //! which enables efficient deduplication.
+//!
+//! Builds pick a [`Chunker`] through [`ChunkingAlgorithm`]:
+//!
+//! | Algorithm | Boundaries | Trade-off |
+//! |-----------|------------|-----------|
+//! | `gear` (default) | FastCDC with a Gear rolling hash | fast, good dedup |
+//! | `buzhash` | Buzhash over a 48-byte window | slower, boundaries depend only on local content |
+//! | `fixed:<size>` | every `size` bytes | fastest, dedup breaks on insertions |
+//!
+//! The chunker's name is recorded in the manifest. Archives built with
+//! different chunkers share few chunks, so incremental builds and merges dedup
+//! best when they keep the same one.
 
-use crate::{MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE};
+use crate::{CxpError, MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE};
 use fastcdc::v2020::FastCDC;
 use sha2::{Sha256, Digest};
 use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::sync::Arc;
 
 /// A content-defined chunk with its hash and data
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,21 +57,209 @@ pub fn compute_hash(data: &[u8]) -> String {
     hex::encode(hasher.finalize())
 }
 
+/// Chunk ID of a hash: its first 16 chars, or the whole hash if shorter
+///
+/// Hashes from user input or a damaged archive may not be hex; this never
+/// splits a character.
+pub(crate) fn chunk_id(hash: &str) -> &str {
+    hash.get(..16).unwrap_or(hash)
+}
+
 /// Chunk a file's content using FastCDC
 pub fn chunk_content(content: &[u8]) -> Vec<Chunk> {
-    if content.is_empty() {
-        return Vec::new();
+    GearChunker.chunk(content)
+}
+
+/// Splits file content into chunks
+///
+/// Implementations must be deterministic: the same content always yields the
+/// same boundaries, otherwise rebuilt archives stop deduplicating.
+pub trait Chunker: Send + Sync {
+    /// Name recorded in the manifest (e.g. `gear`, `fixed:4096`)
+    fn name(&self) -> String;
+
+    /// Chunk boundaries as `(offset, length)` pairs tiling `content`
+    fn cut_points(&self, content: &[u8]) -> Vec<(usize, usize)>;
+
+    /// Split `content` into hashed chunks
+    fn chunk(&self, content: &[u8]) -> Vec<Chunk> {
+        self.cut_points(content)
+            .into_iter()
+            .map(|(offset, length)| Chunk::new(content[offset..offset + length].to_vec(), offset))
+            .collect()
+    }
+}
+
+/// FastCDC (2020) with a Gear rolling hash and normalized chunk sizes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GearChunker;
+
+impl Chunker for GearChunker {
+    fn name(&self) -> String {
+        "gear".to_string()
+    }
+
+    fn cut_points(&self, content: &[u8]) -> Vec<(usize, usize)> {
+        if content.is_empty() {
+            return Vec::new();
+        }
+        FastCDC::new(content, MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE)
+            .map(|chunk| (chunk.offset, chunk.length))
+            .collect()
+    }
+}
+
+/// Bytes in the Buzhash rolling window
+const BUZHASH_WINDOW: usize = 48;
+
+/// Buzhash byte table (fixed seed: boundaries must never change between versions)
+static BUZHASH_TABLE: [u32; 256] = buzhash_table(0x6378_705f_6275_7a68);
+
+/// Fill the Buzhash table from a SplitMix64 sequence
+const fn buzhash_table(seed: u64) -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut state = seed;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = (z ^ (z >> 31)) as u32;
+        i += 1;
+    }
+    table
+}
+
+/// Content-defined chunking with a Buzhash (cyclic polynomial) rolling hash
+///
+/// A boundary is placed where the hash of the last 48 bytes matches the
+/// average-size mask, between the minimum and maximum chunk size.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuzhashChunker;
+
+impl Chunker for BuzhashChunker {
+    fn name(&self) -> String {
+        "buzhash".to_string()
+    }
+
+    fn cut_points(&self, content: &[u8]) -> Vec<(usize, usize)> {
+        let (min, max) = (MIN_CHUNK_SIZE as usize, MAX_CHUNK_SIZE as usize);
+        let mask = (AVG_CHUNK_SIZE as usize).next_power_of_two() as u32 - 1;
+        let out_rotation = (BUZHASH_WINDOW % 32) as u32;
+
+        let mut points = Vec::new();
+        let mut start = 0;
+        while start < content.len() {
+            let end = content.len().min(start + max);
+            if end - start <= min {
+                points.push((start, end - start));
+                break;
+            }
+
+            // Roll the window up to the first possible boundary, then test every byte
+            let mut cut = end;
+            let mut hash = 0u32;
+            let window_start = start + min - BUZHASH_WINDOW;
+            for i in window_start..end {
+                hash = hash.rotate_left(1) ^ BUZHASH_TABLE[content[i] as usize];
+                if i >= window_start + BUZHASH_WINDOW {
+                    hash ^= BUZHASH_TABLE[content[i - BUZHASH_WINDOW] as usize].rotate_left(out_rotation);
+                }
+                if i + 1 >= start + min && hash & mask == 0 {
+                    cut = i + 1;
+                    break;
+                }
+            }
+
+            points.push((start, cut - start));
+            start = cut;
+        }
+        points
+    }
+}
+
+/// Fixed-size chunks (no content-defined boundaries)
+#[derive(Debug, Clone, Copy)]
+pub struct FixedChunker {
+    /// Chunk size in bytes
+    pub size: usize,
+}
+
+impl Chunker for FixedChunker {
+    fn name(&self) -> String {
+        format!("fixed:{}", self.size)
     }
 
-    // Use FastCDC for content-defined chunking
-    let chunker = FastCDC::new(content, MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE);
+    fn cut_points(&self, content: &[u8]) -> Vec<(usize, usize)> {
+        let size = self.size.max(1);
+        (0..content.len())
+            .step_by(size)
+            .map(|offset| (offset, size.min(content.len() - offset)))
+            .collect()
+    }
+}
 
-    chunker
-        .map(|chunk| {
-            let data = content[chunk.offset..chunk.offset + chunk.length].to_vec();
-            Chunk::new(data, chunk.offset)
-        })
-        .collect()
+/// Built-in chunking algorithms
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChunkingAlgorithm {
+    /// FastCDC with a Gear hash ([`GearChunker`])
+    #[default]
+    Gear,
+    /// Buzhash content-defined chunking ([`BuzhashChunker`])
+    Buzhash,
+    /// Fixed-size chunks ([`FixedChunker`])
+    Fixed {
+        /// Chunk size in bytes
+        size: usize,
+    },
+}
+
+impl ChunkingAlgorithm {
+    /// Chunker implementing this algorithm
+    pub fn chunker(&self) -> Arc<dyn Chunker> {
+        match *self {
+            Self::Gear => Arc::new(GearChunker),
+            Self::Buzhash => Arc::new(BuzhashChunker),
+            Self::Fixed { size } => Arc::new(FixedChunker { size }),
+        }
+    }
+}
+
+impl fmt::Display for ChunkingAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gear => write!(f, "gear"),
+            Self::Buzhash => write!(f, "buzhash"),
+            Self::Fixed { size } => write!(f, "fixed:{}", size),
+        }
+    }
+}
+
+impl std::str::FromStr for ChunkingAlgorithm {
+    type Err = CxpError;
+
+    /// Parse `gear`, `buzhash`, `fixed` or `fixed:<size>`
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let s = s.trim().to_lowercase();
+        let (name, size) = match s.split_once(':') {
+            Some((name, size)) => (name, Some(size)),
+            None => (s.as_str(), None),
+        };
+        match (name, size) {
+            ("gear" | "fastcdc", None) => Ok(Self::Gear),
+            ("buzhash", None) => Ok(Self::Buzhash),
+            ("fixed", None) => Ok(Self::Fixed { size: AVG_CHUNK_SIZE as usize }),
+            ("fixed", Some(size)) => match size.parse::<usize>() {
+                Ok(size) if size > 0 => Ok(Self::Fixed { size }),
+                _ => Err(CxpError::Chunk(format!("Invalid fixed chunk size '{}'", size))),
+            },
+            _ => Err(CxpError::Chunk(format!(
+                "Unknown chunking algorithm '{}' (expected gear, buzhash or fixed:<size>)",
+                s
+            ))),
+        }
+    }
 }
 
 /// Chunk reference - points to a chunk by hash
@@ -113,4 +315,77 @@ mod tests {
         let chunks = chunk_content(b"");
         assert!(chunks.is_empty());
     }
+
+    /// Deterministic pseudo-random bytes
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunkers_tile_content() {
+        let content = noise(100_000, 7);
+        for algorithm in ["gear", "buzhash", "fixed:3000"] {
+            let algorithm: ChunkingAlgorithm = algorithm.parse().unwrap();
+            let chunker = algorithm.chunker();
+            assert_eq!(chunker.name(), algorithm.to_string());
+
+            let chunks = chunker.chunk(&content);
+            let mut offset = 0;
+            for (i, chunk) in chunks.iter().enumerate() {
+                assert_eq!(chunk.offset, offset);
+                assert!(chunk.length <= MAX_CHUNK_SIZE as usize);
+                let last = i + 1 == chunks.len();
+                match algorithm {
+                    ChunkingAlgorithm::Fixed { size } if !last => assert_eq!(chunk.length, size),
+                    ChunkingAlgorithm::Buzhash if !last => assert!(chunk.length >= MIN_CHUNK_SIZE as usize),
+                    _ => {}
+                }
+                offset += chunk.length;
+            }
+            assert_eq!(offset, content.len());
+            assert!(chunker.chunk(b"").is_empty());
+        }
+    }
+
+    #[test]
+    fn test_buzhash_resyncs_after_insertion() {
+        let content = noise(200_000, 11);
+        let mut edited = content[..1000].to_vec();
+        edited.extend_from_slice(b"inserted bytes");
+        edited.extend_from_slice(&content[1000..]);
+
+        let hashes = |data: &[u8]| -> std::collections::HashSet<String> {
+            BuzhashChunker.chunk(data).into_iter().map(|c| c.hash).collect()
+        };
+        let (before, after) = (hashes(&content), hashes(&edited));
+        let shared = before.intersection(&after).count();
+        assert!(shared * 10 >= before.len() * 8, "{} of {} chunks shared", shared, before.len());
+
+        // Fixed-size chunks do not recover from the shift
+        let fixed = FixedChunker { size: 4096 };
+        let fixed_before: std::collections::HashSet<_> = fixed.chunk(&content).into_iter().map(|c| c.hash).collect();
+        let fixed_after: std::collections::HashSet<_> = fixed.chunk(&edited).into_iter().map(|c| c.hash).collect();
+        assert!(fixed_before.intersection(&fixed_after).count() <= 1);
+    }
+
+    #[test]
+    fn test_parse_chunking_algorithm() {
+        assert_eq!("Gear".parse::<ChunkingAlgorithm>().unwrap(), ChunkingAlgorithm::Gear);
+        assert_eq!("buzhash".parse::<ChunkingAlgorithm>().unwrap(), ChunkingAlgorithm::Buzhash);
+        assert_eq!(
+            "fixed".parse::<ChunkingAlgorithm>().unwrap(),
+            ChunkingAlgorithm::Fixed { size: AVG_CHUNK_SIZE as usize }
+        );
+        assert_eq!("fixed:512".parse::<ChunkingAlgorithm>().unwrap().to_string(), "fixed:512");
+        assert!("fixed:0".parse::<ChunkingAlgorithm>().is_err());
+        assert!("rabin".parse::<ChunkingAlgorithm>().is_err());
+    }
 }
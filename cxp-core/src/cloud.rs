@@ -0,0 +1,422 @@
+//! Cloud Object Storage
+//!
+//! Shares archives through existing S3 or Google Cloud Storage buckets using
+//! the `object_store` crate:
+//!
+//! | URL | Store | Credentials |
+//! |-----|-------|-------------|
+//! | `s3://bucket/key.cxp` | Amazon S3 (and S3-compatible) | `AWS_*` environment variables |
+//! | `gs://bucket/key.cxp` | Google Cloud Storage | `GOOGLE_*` environment variables |
+//!
+//! [`ObjectStoreBackend`] pushes and pulls whole archives
+//! ([`CxpStorageBackend`]) and, as an
+//! [`AsyncArchiveBackend`](crate::backend::AsyncArchiveBackend), lets
+//! `CxpReader::open_async` read an archive in place with range requests.
+//!
+//! Archives larger than one part are uploaded with a multipart upload. The
+//! upload ID and finished parts are saved next to the local file
+//! (`<archive>.upload`), so an interrupted push resumes with the missing parts
+//! as long as the local file is unchanged.
+
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use futures::StreamExt;
+use object_store::aws::AmazonS3Builder;
+use object_store::gcp::GoogleCloudStorageBuilder;
+use object_store::multipart::{MultipartStore, PartId};
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectStore;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use crate::backend::AsyncArchiveBackend;
+use crate::{CxpError, Result};
+
+/// Default multipart upload part size (S3 requires at least 5 MiB)
+pub const DEFAULT_PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// Suffix of the file recording an unfinished multipart upload
+pub const UPLOAD_STATE_SUFFIX: &str = "upload";
+
+/// Suffix of a download in progress
+const PARTIAL_SUFFIX: &str = "part";
+
+/// Summary of a push or pull
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferStats {
+    /// Bytes of the archive
+    pub bytes: u64,
+    /// Requests (single upload, parts or download) sent by this transfer
+    pub parts: usize,
+    /// Parts already uploaded by an earlier, interrupted push
+    pub resumed_parts: usize,
+}
+
+/// Remote storage archives are published to and fetched from
+pub trait CxpStorageBackend: Send + Sync {
+    /// Upload the local archive, resuming an interrupted upload if possible
+    fn push(&self, local: &Path) -> impl Future<Output = Result<TransferStats>> + Send;
+
+    /// Download the archive to `local` (replaced only once complete)
+    fn pull(&self, local: &Path) -> impl Future<Output = Result<TransferStats>> + Send;
+}
+
+/// Multipart upload progress saved next to the local archive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct UploadState {
+    url: String,
+    size: u64,
+    modified_ms: u64,
+    part_size: usize,
+    upload_id: String,
+    parts: Vec<String>,
+}
+
+impl UploadState {
+    fn load(path: &Path) -> Option<Self> {
+        let data = std::fs::read(path).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+}
+
+/// An archive stored in an object store
+pub struct ObjectStoreBackend {
+    store: Arc<dyn ObjectStore>,
+    multipart: Option<Arc<dyn MultipartStore>>,
+    location: ObjectPath,
+    url: String,
+    part_size: usize,
+}
+
+impl fmt::Debug for ObjectStoreBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjectStoreBackend")
+            .field("url", &self.url)
+            .field("multipart", &self.multipart.is_some())
+            .field("part_size", &self.part_size)
+            .finish()
+    }
+}
+
+impl ObjectStoreBackend {
+    /// Archive at an `s3://` or `gs://` URL, configured from the environment
+    pub fn from_url(url: &str) -> Result<Self> {
+        let (scheme, rest) = url
+            .split_once("://")
+            .ok_or_else(|| CxpError::InvalidFormat(format!("'{}' is not a storage URL", url)))?;
+        let (bucket, key) = rest
+            .split_once('/')
+            .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+            .ok_or_else(|| CxpError::InvalidFormat(format!("'{}' has no bucket and key", url)))?;
+
+        let backend = match scheme {
+            "s3" | "s3a" => {
+                let store = AmazonS3Builder::from_env().with_bucket_name(bucket).build()?;
+                Self::with_multipart(Arc::new(store), key)?
+            }
+            "gs" => {
+                let store = GoogleCloudStorageBuilder::from_env().with_bucket_name(bucket).build()?;
+                Self::with_multipart(Arc::new(store), key)?
+            }
+            _ => {
+                return Err(CxpError::InvalidFormat(format!(
+                    "Unsupported storage scheme '{}' (expected s3 or gs)",
+                    scheme
+                )))
+            }
+        };
+        Ok(Self {
+            url: url.to_string(),
+            ..backend
+        })
+    }
+
+    /// Archive at `key` in a store without multipart support (single-request uploads)
+    pub fn new(store: Arc<dyn ObjectStore>, key: &str) -> Result<Self> {
+        Ok(Self {
+            store,
+            multipart: None,
+            location: parse_key(key)?,
+            url: key.to_string(),
+            part_size: DEFAULT_PART_SIZE,
+        })
+    }
+
+    /// Archive at `key` in a store with multipart (resumable) uploads
+    pub fn with_multipart<S: ObjectStore + MultipartStore>(store: Arc<S>, key: &str) -> Result<Self> {
+        Ok(Self {
+            store: store.clone(),
+            multipart: Some(store),
+            location: parse_key(key)?,
+            url: key.to_string(),
+            part_size: DEFAULT_PART_SIZE,
+        })
+    }
+
+    /// Set the multipart part size (all parts but the last have this size)
+    pub fn with_part_size(mut self, part_size: usize) -> Self {
+        self.part_size = part_size.max(1);
+        self
+    }
+
+    /// URL (or key) of the archive
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// File recording an unfinished upload of `local`
+    pub fn upload_state_path(local: &Path) -> PathBuf {
+        with_suffix(local, UPLOAD_STATE_SUFFIX)
+    }
+
+    /// Abort an unfinished upload of `local` so the next push starts over
+    pub async fn discard_upload(&self, local: &Path) -> Result<()> {
+        let state_path = Self::upload_state_path(local);
+        if let (Some(state), Some(multipart)) = (UploadState::load(&state_path), &self.multipart) {
+            if let Err(e) = multipart.abort_multipart(&self.location, &state.upload_id).await {
+                tracing::warn!("Failed to abort upload {}: {}", state.upload_id, e);
+            }
+        }
+        match std::fs::remove_file(&state_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Upload `local` in parts, continuing a saved upload if it matches
+    async fn push_multipart(&self, multipart: &dyn MultipartStore, local: &Path, size: u64, modified_ms: u64) -> Result<TransferStats> {
+        let state_path = Self::upload_state_path(local);
+        let mut state = match UploadState::load(&state_path) {
+            Some(state)
+                if state.url == self.url
+                    && state.size == size
+                    && state.modified_ms == modified_ms
+                    && state.part_size == self.part_size =>
+            {
+                tracing::info!("Resuming upload of {:?} after {} parts", local, state.parts.len());
+                state
+            }
+            _ => UploadState {
+                url: self.url.clone(),
+                size,
+                modified_ms,
+                part_size: self.part_size,
+                upload_id: multipart.create_multipart(&self.location).await?,
+                parts: Vec::new(),
+            },
+        };
+        state.save(&state_path)?;
+
+        let resumed_parts = state.parts.len();
+        let total_parts = size.div_ceil(self.part_size as u64) as usize;
+        let mut file = tokio::fs::File::open(local).await?;
+        file.seek(std::io::SeekFrom::Start((resumed_parts * self.part_size) as u64)).await?;
+
+        for part_idx in resumed_parts..total_parts {
+            let len = (size - (part_idx * self.part_size) as u64).min(self.part_size as u64) as usize;
+            let mut data = vec![0u8; len];
+            file.read_exact(&mut data).await?;
+
+            let part = multipart.put_part(&self.location, &state.upload_id, part_idx, data.into()).await?;
+            state.parts.push(part.content_id);
+            state.save(&state_path)?;
+            tracing::debug!("Uploaded part {}/{}", part_idx + 1, total_parts);
+        }
+
+        let parts = state.parts.iter().map(|id| PartId { content_id: id.clone() }).collect();
+        multipart.complete_multipart(&self.location, &state.upload_id, parts).await?;
+        std::fs::remove_file(&state_path)?;
+
+        Ok(TransferStats {
+            bytes: size,
+            parts: total_parts - resumed_parts,
+            resumed_parts,
+        })
+    }
+}
+
+impl CxpStorageBackend for ObjectStoreBackend {
+    async fn push(&self, local: &Path) -> Result<TransferStats> {
+        let metadata = tokio::fs::metadata(local).await?;
+        let size = metadata.len();
+
+        match &self.multipart {
+            Some(multipart) if size > self.part_size as u64 => {
+                let modified_ms = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |since| since.as_millis() as u64);
+                self.push_multipart(multipart.as_ref(), local, size, modified_ms).await
+            }
+            _ => {
+                let data = tokio::fs::read(local).await?;
+                self.store.put(&self.location, data.into()).await?;
+                Ok(TransferStats {
+                    bytes: size,
+                    parts: 1,
+                    resumed_parts: 0,
+                })
+            }
+        }
+    }
+
+    async fn pull(&self, local: &Path) -> Result<TransferStats> {
+        let partial = with_suffix(local, PARTIAL_SUFFIX);
+        let mut file = tokio::fs::File::create(&partial).await?;
+        let mut stream = self.store.get(&self.location).await?.into_stream();
+
+        let mut bytes = 0u64;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            file.write_all(&chunk).await?;
+            bytes += chunk.len() as u64;
+        }
+        file.sync_all().await?;
+        drop(file);
+        tokio::fs::rename(&partial, local).await?;
+
+        Ok(TransferStats {
+            bytes,
+            parts: 1,
+            resumed_parts: 0,
+        })
+    }
+}
+
+impl AsyncArchiveBackend for ObjectStoreBackend {
+    async fn size(&self) -> Result<u64> {
+        Ok(self.store.head(&self.location).await?.size)
+    }
+
+    async fn read_range(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let range = offset..offset.saturating_add(len as u64);
+        Ok(self.store.get_range(&self.location, range).await?.to_vec())
+    }
+}
+
+/// Object path of a key
+fn parse_key(key: &str) -> Result<ObjectPath> {
+    ObjectPath::parse(key).map_err(|e| CxpError::InvalidFormat(format!("Invalid object key '{}': {}", key, e)))
+}
+
+/// `path` with `.suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CxpBuilder, CxpReader};
+    use object_store::memory::InMemory;
+
+    fn archive(dir: &Path) -> PathBuf {
+        let source = dir.join("src");
+        std::fs::create_dir_all(&source).unwrap();
+        for i in 0..20 {
+            std::fs::write(source.join(format!("file_{}.rs", i)), format!("fn f{}() {{ /* {} */ }}\n", i, "x".repeat(i * 50))).unwrap();
+        }
+        let out = dir.join("shared.cxp");
+        CxpBuilder::new(&source).scan().unwrap().process().unwrap().build(&out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_push_pull_and_open() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let local = archive(dir.path());
+        let data = std::fs::read(&local).unwrap();
+        let store = Arc::new(InMemory::new());
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            // Single request
+            let backend = ObjectStoreBackend::with_multipart(store.clone(), "packs/whole.cxp").unwrap();
+            let stats = backend.push(&local).await.unwrap();
+            assert_eq!(stats, TransferStats { bytes: data.len() as u64, parts: 1, resumed_parts: 0 });
+
+            // Multipart
+            let backend = ObjectStoreBackend::with_multipart(store.clone(), "packs/parts.cxp").unwrap().with_part_size(1000);
+            let stats = backend.push(&local).await.unwrap();
+            assert_eq!(stats.parts, data.len().div_ceil(1000));
+            assert!(!ObjectStoreBackend::upload_state_path(&local).exists());
+
+            let pulled = dir.path().join("pulled.cxp");
+            assert_eq!(backend.pull(&pulled).await.unwrap().bytes, data.len() as u64);
+            assert_eq!(std::fs::read(&pulled).unwrap(), data);
+            assert!(!with_suffix(&pulled, PARTIAL_SUFFIX).exists());
+
+            let reader = CxpReader::open_async(backend).await.unwrap();
+            let content = tokio::task::spawn_blocking(move || reader.read_file("file_3.rs")).await.unwrap();
+            assert!(content.unwrap().starts_with(b"fn f3()"));
+        });
+    }
+
+    #[test]
+    fn test_push_resumes_upload() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let local = archive(dir.path());
+        let data = std::fs::read(&local).unwrap();
+        let store = Arc::new(InMemory::new());
+        let backend = ObjectStoreBackend::with_multipart(store.clone(), "packs/resumed.cxp").unwrap().with_part_size(1000);
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            // An earlier push uploaded two parts before it was interrupted
+            let modified_ms = std::fs::metadata(&local)
+                .unwrap()
+                .modified()
+                .unwrap()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64;
+            let location = ObjectPath::from("packs/resumed.cxp");
+            let upload_id = store.create_multipart(&location).await.unwrap();
+            let mut parts = Vec::new();
+            for i in 0..2 {
+                let part = data[i * 1000..(i + 1) * 1000].to_vec();
+                parts.push(store.put_part(&location, &upload_id, i, part.into()).await.unwrap().content_id);
+            }
+            UploadState {
+                url: "packs/resumed.cxp".to_string(),
+                size: data.len() as u64,
+                modified_ms,
+                part_size: 1000,
+                upload_id,
+                parts,
+            }
+            .save(&ObjectStoreBackend::upload_state_path(&local))
+            .unwrap();
+
+            let stats = backend.push(&local).await.unwrap();
+            assert_eq!(stats.resumed_parts, 2);
+            assert_eq!(stats.parts + stats.resumed_parts, data.len().div_ceil(1000));
+            assert_eq!(store.get(&location).await.unwrap().bytes().await.unwrap().to_vec(), data);
+        });
+    }
+
+    #[test]
+    fn test_from_url() {
+        assert!(ObjectStoreBackend::from_url("s3://bucket").is_err());
+        assert!(ObjectStoreBackend::from_url("ftp://host/file.cxp").is_err());
+        assert!(ObjectStoreBackend::from_url("file.cxp").is_err());
+
+        let backend = ObjectStoreBackend::from_url("s3://team-packs/project/context.cxp").unwrap();
+        assert_eq!(backend.url(), "s3://team-packs/project/context.cxp");
+        assert_eq!(backend.location.as_ref(), "project/context.cxp");
+        assert!(backend.multipart.is_some());
+    }
+}
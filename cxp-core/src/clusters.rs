@@ -0,0 +1,444 @@
+//! Semantic Clusters
+//!
+//! A topic map of an archive: k-means over the chunk embeddings groups
+//! chunks by meaning, and every cluster is labeled with the TF-IDF keywords
+//! of the chunks nearest its centroid. `cxp cluster` stores the result as an
+//! extension of an existing archive, so users see "what's in this pack" and
+//! searches can be scoped to one cluster (`CxpReader::search_cluster`).
+//!
+//! Vectors are normalized and compared by cosine similarity (spherical
+//! k-means), seeded with k-means++ from a fixed seed so the same archive
+//! always yields the same clusters.
+//!
+//! Structure:
+//! ```text
+//! extensions/clusters/
+//! ├── manifest.msgpack     # extension metadata
+//! └── clusters.msgpack     # ClusterMap
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::Path;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::extensions::{Extension, ExtensionManifest};
+use crate::format::{ArchiveWriter, FileMap};
+use crate::keywords::KeywordExtractor;
+use crate::manifest::Manifest;
+use crate::packs::PackIndex;
+use crate::temp::replace_file;
+use crate::toc::TOC_PATH;
+use crate::{CxpError, Result};
+
+/// Extension namespace of the cluster map
+pub const CLUSTERS_NAMESPACE: &str = "clusters";
+
+/// Data key of the cluster map within its namespace
+pub const CLUSTERS_KEY: &str = "clusters.msgpack";
+
+/// Version of the cluster map layout
+pub const CLUSTERS_VERSION: &str = "1.0.0";
+
+/// Chunks nearest a centroid whose text labels the cluster
+const LABEL_SAMPLE: usize = 16;
+
+/// Options for clustering an archive
+#[derive(Debug, Clone)]
+pub struct ClusterOptions {
+    /// Number of clusters (capped at the number of embedded chunks)
+    pub k: usize,
+    /// Maximum k-means iterations
+    pub max_iterations: usize,
+    /// Keywords per cluster label
+    pub label_keywords: usize,
+    /// Seed of the k-means++ initialization
+    pub seed: u64,
+}
+
+impl Default for ClusterOptions {
+    fn default() -> Self {
+        Self {
+            k: 20,
+            max_iterations: 50,
+            label_keywords: 5,
+            seed: 42,
+        }
+    }
+}
+
+impl ClusterOptions {
+    /// Set the number of clusters
+    pub fn with_k(mut self, k: usize) -> Self {
+        self.k = k.max(1);
+        self
+    }
+
+    /// Set the maximum number of k-means iterations
+    pub fn with_max_iterations(mut self, iterations: usize) -> Self {
+        self.max_iterations = iterations.max(1);
+        self
+    }
+
+    /// Set the number of keywords per cluster label
+    pub fn with_label_keywords(mut self, keywords: usize) -> Self {
+        self.label_keywords = keywords;
+        self
+    }
+
+    /// Set the seed of the initialization
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// A group of chunks about the same topic
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cluster {
+    /// Cluster ID (index in `ClusterMap::clusters`)
+    pub id: usize,
+    /// Keywords describing the cluster, best first
+    pub label: Vec<String>,
+    /// Number of chunks in the cluster
+    pub chunks: usize,
+    /// Files with chunks in the cluster, most chunks first
+    pub files: Vec<String>,
+    /// Normalized centroid of the cluster's embeddings
+    pub centroid: Vec<f32>,
+}
+
+impl Cluster {
+    /// Label as one comma-separated string
+    pub fn label_text(&self) -> String {
+        self.label.join(", ")
+    }
+}
+
+/// Clusters of an archive and the cluster of every embedded chunk
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClusterMap {
+    /// Clusters, largest first
+    pub clusters: Vec<Cluster>,
+    /// Cluster ID by chunk hash
+    pub assignments: BTreeMap<String, usize>,
+}
+
+/// Extension for the cluster map
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClustersExtension;
+
+impl Extension for ClustersExtension {
+    fn namespace(&self) -> &str {
+        CLUSTERS_NAMESPACE
+    }
+
+    fn version(&self) -> &str {
+        CLUSTERS_VERSION
+    }
+}
+
+impl ClusterMap {
+    /// Cluster the given chunk embeddings
+    ///
+    /// `text` returns the text of a chunk by hash; it is asked for the chunks
+    /// nearest each centroid to label the clusters. Files are taken from
+    /// `file_map`.
+    pub fn build(
+        vectors: &HashMap<String, Vec<f32>>,
+        file_map: &FileMap,
+        options: &ClusterOptions,
+        mut text: impl FnMut(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let mut hashes: Vec<&str> = vectors.keys().map(String::as_str).collect();
+        hashes.sort_unstable();
+        let points: Vec<Vec<f32>> = hashes.iter().map(|hash| normalized(&vectors[*hash])).collect();
+        if let Some(dim) = points.first().map(Vec::len) {
+            if points.iter().any(|p| p.len() != dim) {
+                return Err(CxpError::InvalidFormat("Chunk embeddings differ in dimensions".to_string()));
+            }
+        }
+
+        let (centroids, assignment) = kmeans(&points, options.k, options.max_iterations, options.seed);
+        tracing::debug!("Clustered {} chunks into {} clusters", points.len(), centroids.len());
+
+        // Renumber the clusters by size, largest first
+        let mut sizes = vec![0usize; centroids.len()];
+        for &cluster in &assignment {
+            sizes[cluster] += 1;
+        }
+        let mut order: Vec<usize> = (0..centroids.len()).filter(|&c| sizes[c] > 0).collect();
+        order.sort_by(|&a, &b| sizes[b].cmp(&sizes[a]).then(a.cmp(&b)));
+        let mut renumber = vec![usize::MAX; centroids.len()];
+        for (id, &cluster) in order.iter().enumerate() {
+            renumber[cluster] = id;
+        }
+
+        let assignments: BTreeMap<String, usize> = hashes
+            .iter()
+            .zip(&assignment)
+            .map(|(hash, &cluster)| (hash.to_string(), renumber[cluster]))
+            .collect();
+
+        // Files by the number of their chunks in each cluster
+        let mut file_counts: Vec<HashMap<&str, usize>> = vec![HashMap::new(); order.len()];
+        for entry in file_map.files.values() {
+            for chunk in &entry.chunks {
+                if let Some(&cluster) = assignments.get(&chunk.hash) {
+                    *file_counts[cluster].entry(entry.path.as_str()).or_default() += 1;
+                }
+            }
+        }
+
+        // Text of the chunks nearest each centroid, one document per cluster
+        let mut documents = Vec::with_capacity(order.len());
+        for &cluster in &order {
+            let mut members: Vec<(usize, f32)> = assignment
+                .iter()
+                .enumerate()
+                .filter(|(_, &c)| c == cluster)
+                .map(|(i, _)| (i, dot(&points[i], &centroids[cluster])))
+                .collect();
+            members.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            let sample: Vec<String> = members
+                .iter()
+                .take(LABEL_SAMPLE)
+                .filter_map(|&(i, _)| text(hashes[i]))
+                .collect();
+            documents.push((renumber[cluster].to_string(), sample.join("\n")));
+        }
+        let labels = KeywordExtractor::new()
+            .with_max_keywords(options.label_keywords)
+            .extract(documents.iter().map(|(id, doc)| (id.as_str(), doc.as_str())));
+
+        let clusters = order
+            .iter()
+            .enumerate()
+            .map(|(id, &cluster)| {
+                let mut files: Vec<(&str, usize)> = file_counts[id].iter().map(|(p, &n)| (*p, n)).collect();
+                files.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+                Cluster {
+                    id,
+                    label: labels.get(&id.to_string()).cloned().unwrap_or_default(),
+                    chunks: sizes[cluster],
+                    files: files.into_iter().map(|(path, _)| path.to_string()).collect(),
+                    centroid: centroids[cluster].clone(),
+                }
+            })
+            .collect();
+
+        Ok(Self { clusters, assignments })
+    }
+
+    /// Cluster of a chunk
+    pub fn cluster_of(&self, chunk_hash: &str) -> Option<&Cluster> {
+        self.assignments.get(chunk_hash).and_then(|&id| self.clusters.get(id))
+    }
+
+    /// Hashes of the chunks in a cluster
+    pub fn chunks_in(&self, id: usize) -> impl Iterator<Item = &str> {
+        self.assignments
+            .iter()
+            .filter(move |(_, &cluster)| cluster == id)
+            .map(|(hash, _)| hash.as_str())
+    }
+
+    /// Cluster whose centroid is most similar to a query embedding
+    pub fn nearest(&self, query_embedding: &[f32]) -> Option<&Cluster> {
+        let query = normalized(query_embedding);
+        self.clusters
+            .iter()
+            .filter(|c| c.centroid.len() == query.len())
+            .max_by(|a, b| dot(&a.centroid, &query).total_cmp(&dot(&b.centroid, &query)))
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Store the cluster map in an existing archive, replacing a previous one
+    ///
+    /// The archive is rewritten next to itself and moved into place; the
+    /// manifest lists the `clusters` extension afterwards.
+    pub fn write_to<P: AsRef<Path>>(&self, archive_path: P) -> Result<()> {
+        let archive_path = archive_path.as_ref();
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(archive_path)?)?;
+        let packs = PackIndex::read_from_archive(&mut archive)?;
+
+        let prefix = format!("extensions/{}/", CLUSTERS_NAMESPACE);
+        replace_file(archive_path, |temp_path| {
+            let mut writer = ArchiveWriter::create(temp_path)?;
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i)?;
+                let name = entry.name().to_string();
+                if name.starts_with(&prefix) || name == TOC_PATH {
+                    continue;
+                }
+                let mut data = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut data)?;
+                if name == "manifest.msgpack" {
+                    let mut manifest = Manifest::from_msgpack(&data)?;
+                    if !manifest.extensions.iter().any(|e| e == CLUSTERS_NAMESPACE) {
+                        manifest.extensions.push(CLUSTERS_NAMESPACE.to_string());
+                    }
+                    data = manifest.to_msgpack()?;
+                }
+                writer.copy(&name, &data, &packs)?;
+            }
+            let manifest = ExtensionManifest::new(CLUSTERS_NAMESPACE, CLUSTERS_VERSION);
+            writer.write(&format!("{}manifest.msgpack", prefix), &manifest.to_msgpack()?)?;
+            writer.write(&format!("{}{}", prefix, CLUSTERS_KEY), &self.to_msgpack()?)?;
+            writer.finish()?;
+            Ok(())
+        })
+    }
+}
+
+/// Spherical k-means over normalized points: (centroids, cluster of each point)
+///
+/// Clusters that lose all their points are reseeded with the point farthest
+/// from its centroid.
+pub fn kmeans(points: &[Vec<f32>], k: usize, max_iterations: usize, seed: u64) -> (Vec<Vec<f32>>, Vec<usize>) {
+    let k = k.min(points.len());
+    if k == 0 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let mut centroids = kmeans_plus_plus(points, k, seed);
+    let mut assignment = vec![usize::MAX; points.len()];
+    for iteration in 0..max_iterations {
+        let nearest: Vec<(usize, f32)> = points.par_iter().map(|p| nearest_centroid(p, &centroids)).collect();
+        let changed = nearest.iter().zip(&assignment).filter(|((c, _), &old)| *c != old).count();
+        assignment = nearest.iter().map(|(c, _)| *c).collect();
+        if changed == 0 {
+            tracing::debug!("k-means converged after {} iterations", iteration);
+            break;
+        }
+
+        let dim = points[0].len();
+        let mut sums = vec![vec![0.0f32; dim]; k];
+        let mut counts = vec![0usize; k];
+        for (point, &cluster) in points.iter().zip(&assignment) {
+            counts[cluster] += 1;
+            for (sum, value) in sums[cluster].iter_mut().zip(point) {
+                *sum += value;
+            }
+        }
+        let mut farthest: Vec<usize> = (0..points.len()).collect();
+        farthest.sort_by(|&a, &b| nearest[b].1.total_cmp(&nearest[a].1).then(a.cmp(&b)));
+        let mut spare = farthest.into_iter();
+        for (cluster, sum) in sums.into_iter().enumerate() {
+            centroids[cluster] = if counts[cluster] > 0 {
+                normalized(&sum)
+            } else {
+                spare.next().map(|i| points[i].clone()).unwrap_or_else(|| centroids[cluster].clone())
+            };
+        }
+    }
+    (centroids, assignment)
+}
+
+/// k-means++ seeding: each next centroid is drawn proportional to its distance
+fn kmeans_plus_plus(points: &[Vec<f32>], k: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut state = seed;
+    let mut random = move || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut x = state;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (x ^ (x >> 31)) as f64 / u64::MAX as f64
+    };
+
+    let first = ((random() * points.len() as f64) as usize).min(points.len() - 1);
+    let mut centroids = vec![points[first].clone()];
+    let mut distances: Vec<f32> = points.iter().map(|p| 1.0 - dot(p, &centroids[0])).collect();
+    while centroids.len() < k {
+        let total: f64 = distances.iter().map(|&d| d.max(0.0) as f64).sum();
+        let next = if total <= 0.0 {
+            // Every point coincides with a centroid; any one will do
+            centroids.len()
+        } else {
+            let mut target = random() * total;
+            distances
+                .iter()
+                .position(|&d| {
+                    target -= d.max(0.0) as f64;
+                    target <= 0.0
+                })
+                .unwrap_or(points.len() - 1)
+        };
+        let point = points[next.min(points.len() - 1)].clone();
+        for (distance, p) in distances.iter_mut().zip(points) {
+            *distance = distance.min(1.0 - dot(p, &point));
+        }
+        centroids.push(point);
+    }
+    centroids
+}
+
+/// Index and cosine distance of the centroid nearest a point
+fn nearest_centroid(point: &[f32], centroids: &[Vec<f32>]) -> (usize, f32) {
+    centroids
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, 1.0 - dot(point, c)))
+        .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+        .unwrap_or((0, 0.0))
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = dot(vector, vector).sqrt();
+    if norm == 0.0 {
+        vector.to_vec()
+    } else {
+        vector.iter().map(|x| x / norm).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kmeans_separates_topics_and_labels_them() {
+        let mut vectors = HashMap::new();
+        let mut texts = HashMap::new();
+        for i in 0..6 {
+            let jitter = i as f32 * 0.01;
+            vectors.insert(format!("cache{i}"), vec![1.0, jitter, 0.0]);
+            texts.insert(format!("cache{i}"), format!("cache eviction policy lru entry{i}"));
+            vectors.insert(format!("parser{i}"), vec![0.0, jitter, 1.0]);
+            texts.insert(format!("parser{i}"), format!("parser token grammar syntax node{i}"));
+        }
+        vectors.insert("lonely".to_string(), vec![0.1, 1.0, 0.0]);
+
+        let options = ClusterOptions::default().with_k(2).with_label_keywords(3);
+        let map = ClusterMap::build(&vectors, &FileMap::default(), &options, |hash| texts.get(hash).cloned()).unwrap();
+
+        assert_eq!(map.clusters.len(), 2);
+        let cache = map.cluster_of("cache0").unwrap();
+        let parser = map.cluster_of("parser0").unwrap();
+        assert_ne!(cache.id, parser.id);
+        assert!((0..6).all(|i| map.cluster_of(&format!("cache{i}")).unwrap().id == cache.id));
+        assert!(cache.label.contains(&"cache".to_string()));
+        assert!(parser.label.contains(&"parser".to_string()));
+        assert_eq!(map.chunks_in(parser.id).count(), parser.chunks);
+        assert_eq!(map.nearest(&[0.0, 0.0, 2.0]).unwrap().id, parser.id);
+
+        let again = ClusterMap::build(&vectors, &FileMap::default(), &options, |hash| texts.get(hash).cloned()).unwrap();
+        assert_eq!(again, map);
+        assert_eq!(ClusterMap::from_msgpack(&map.to_msgpack().unwrap()).unwrap(), map);
+    }
+}
@@ -1,14 +1,126 @@
 //! Zstandard compression for chunks
 //!
 //! Provides efficient compression with good speed/ratio trade-off.
+//!
+//! Builds pick a [`Codec`]: zstd at any level (the default, level 3), or LZ4
+//! with the `lz4` feature for builds where speed matters more than size.
+//! Chunks are self-describing frames, so [`ChunkCodec`] recognizes the codec
+//! of each chunk by its magic number and archives mixing both (e.g. after a
+//! merge) read fine.
+//!
+//! Small chunks (2-8 KB) share little context within themselves. An archive
+//! can carry a zstd dictionary trained on its own chunks
+//! ([`DICTIONARY_PATH`]); [`ChunkCodec`] then compresses and decompresses
+//! every chunk with it.
 
 use crate::{CxpError, Result};
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
 use zstd::stream::{encode_all, decode_all};
-use std::io::Cursor;
+use std::fmt;
+use std::io::{Cursor, Read, Write};
+use std::sync::Arc;
 
 /// Default compression level (3 is a good balance)
 pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
 
+/// Magic number starting every zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Magic number starting every LZ4 frame
+const LZ4_MAGIC: [u8; 4] = [0x04, 0x22, 0x4D, 0x18];
+
+/// Compression algorithm for chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// Zstandard at a level from 1 (fastest) to 22 (smallest)
+    Zstd {
+        /// Compression level
+        level: i32,
+    },
+    /// LZ4 frames: much faster, larger output, no dictionary support
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+impl Codec {
+    /// Short name (`zstd` or `lz4`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Zstd { .. } => "zstd",
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => "lz4",
+        }
+    }
+
+    /// zstd level, if this is zstd
+    pub fn zstd_level(&self) -> Option<i32> {
+        match self {
+            Self::Zstd { level } => Some(*level),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => None,
+        }
+    }
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Self::Zstd {
+            level: DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Zstd { level } => write!(f, "zstd:{}", level),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => f.write_str("lz4"),
+        }
+    }
+}
+
+impl std::str::FromStr for Codec {
+    type Err = CxpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, level) = match s.split_once(':') {
+            Some((name, level)) => (name, Some(level)),
+            None => (s, None),
+        };
+        match (name.to_lowercase().as_str(), level) {
+            ("zstd", None) => Ok(Self::default()),
+            ("zstd", Some(level)) => match level.parse::<i32>() {
+                Ok(level) if zstd::compression_level_range().contains(&level) => Ok(Self::Zstd { level }),
+                _ => Err(CxpError::InvalidFormat(format!(
+                    "Invalid zstd level '{}' (expected {:?})",
+                    level,
+                    zstd::compression_level_range()
+                ))),
+            },
+            #[cfg(feature = "lz4")]
+            ("lz4", None) => Ok(Self::Lz4),
+            #[cfg(not(feature = "lz4"))]
+            ("lz4", None) => Err(CxpError::InvalidFormat(
+                "LZ4 compression requires the lz4 feature".to_string(),
+            )),
+            _ => Err(CxpError::InvalidFormat(format!(
+                "Unknown compression '{}' (expected zstd, zstd:<level> or lz4)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Path of the shared chunk dictionary inside an archive
+pub const DICTIONARY_PATH: &str = "compression/dict.zstd";
+
+/// Default maximum size of a trained dictionary (zstd's own default)
+pub const DEFAULT_DICTIONARY_SIZE: usize = 110 * 1024;
+
+/// Sample bytes fed to dictionary training (zstd recommends ~100x the dictionary size)
+pub const MAX_TRAINING_BYTES: usize = 100 * DEFAULT_DICTIONARY_SIZE;
+
 /// Compress data using Zstandard
 pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
     compress_with_level(data, DEFAULT_COMPRESSION_LEVEL)
@@ -26,6 +138,136 @@ pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
     decode_all(cursor).map_err(|e| CxpError::Compression(e.to_string()))
 }
 
+/// Codec a compressed chunk was written with (None if unrecognized)
+pub fn detect_codec(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&ZSTD_MAGIC) {
+        Some("zstd")
+    } else if data.starts_with(&LZ4_MAGIC) {
+        Some("lz4")
+    } else {
+        None
+    }
+}
+
+/// Compress data as an LZ4 frame
+#[cfg(feature = "lz4")]
+pub fn compress_lz4(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::with_capacity(data.len() / 2));
+    encoder.write_all(data).map_err(|e| CxpError::Compression(e.to_string()))?;
+    encoder.finish().map_err(|e| CxpError::Compression(e.to_string()))
+}
+
+/// Decompress an LZ4 frame
+#[cfg(feature = "lz4")]
+pub fn decompress_lz4(data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    lz4_flex::frame::FrameDecoder::new(data)
+        .read_to_end(&mut out)
+        .map_err(|e| CxpError::Compression(e.to_string()))?;
+    Ok(out)
+}
+
+/// Decompress an LZ4 frame (unsupported without the `lz4` feature)
+#[cfg(not(feature = "lz4"))]
+pub fn decompress_lz4(_data: &[u8]) -> Result<Vec<u8>> {
+    Err(CxpError::Compression(
+        "Chunk is LZ4-compressed; rebuild with the lz4 feature to read it".to_string(),
+    ))
+}
+
+/// Train a dictionary of at most `max_size` bytes over sample chunks
+///
+/// Fails when the samples are too few or too small to train on.
+pub fn train_dictionary<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> Result<Vec<u8>> {
+    zstd::dict::from_samples(samples, max_size)
+        .map_err(|e| CxpError::Compression(format!("Dictionary training failed: {}", e)))
+}
+
+/// A dictionary prepared for compression and decompression
+struct Dictionary {
+    raw: Vec<u8>,
+    encoder: EncoderDictionary<'static>,
+    decoder: DecoderDictionary<'static>,
+}
+
+/// Chunk compression with a codec and an optional shared zstd dictionary
+///
+/// Cheap to clone; the prepared dictionary is shared.
+#[derive(Clone, Default)]
+pub struct ChunkCodec {
+    codec: Codec,
+    dictionary: Option<Arc<Dictionary>>,
+}
+
+impl fmt::Debug for ChunkCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunkCodec")
+            .field("codec", &self.codec)
+            .field("dictionary_bytes", &self.dictionary.as_ref().map(|d| d.raw.len()))
+            .finish()
+    }
+}
+
+impl ChunkCodec {
+    /// Compress with `codec`, without a dictionary
+    pub fn new(codec: Codec) -> Self {
+        Self {
+            codec,
+            dictionary: None,
+        }
+    }
+
+    /// Compress and decompress with a zstd dictionary at `level`
+    pub fn with_dictionary(raw: Vec<u8>, level: i32) -> Self {
+        let encoder = EncoderDictionary::copy(&raw, level);
+        let decoder = DecoderDictionary::copy(&raw);
+        Self {
+            codec: Codec::Zstd { level },
+            dictionary: Some(Arc::new(Dictionary { raw, encoder, decoder })),
+        }
+    }
+
+    /// Codec new chunks are compressed with
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// The dictionary's bytes, if one is used
+    pub fn dictionary(&self) -> Option<&[u8]> {
+        self.dictionary.as_ref().map(|d| d.raw.as_slice())
+    }
+
+    /// Compress a chunk
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let Some(dictionary) = &self.dictionary else {
+            return match self.codec {
+                Codec::Zstd { level } => compress_with_level(data, level),
+                #[cfg(feature = "lz4")]
+                Codec::Lz4 => compress_lz4(data),
+            };
+        };
+        let mut encoder = zstd::stream::Encoder::with_prepared_dictionary(Vec::new(), &dictionary.encoder)
+            .map_err(|e| CxpError::Compression(e.to_string()))?;
+        encoder.write_all(data).map_err(|e| CxpError::Compression(e.to_string()))?;
+        encoder.finish().map_err(|e| CxpError::Compression(e.to_string()))
+    }
+
+    /// Decompress a chunk of either codec (chunks compressed without the dictionary also work)
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if data.starts_with(&LZ4_MAGIC) {
+            return decompress_lz4(data);
+        }
+        let Some(dictionary) = &self.dictionary else {
+            return decompress(data);
+        };
+        let mut decoder = zstd::stream::Decoder::with_prepared_dictionary(Cursor::new(data), &dictionary.decoder)
+            .map_err(|e| CxpError::Compression(e.to_string()))?;
+        let mut out = Vec::new();
+        decoder.read_to_end(&mut out).map_err(|e| CxpError::Compression(e.to_string()))?;
+        Ok(out)
+    }
+}
+
 /// Compression statistics
 #[derive(Debug, Clone, Default)]
 pub struct CompressionStats {
@@ -86,6 +328,58 @@ mod tests {
         assert!(stats.savings_percent() > 0.0);
     }
 
+    #[test]
+    fn test_dictionary_codec() {
+        // Many small, similar chunks: the case dictionaries are made for
+        let samples: Vec<Vec<u8>> = (0..500)
+            .map(|i| format!("pub fn handler_{i}(request: &Request) -> Result<Response> {{\n    let user = request.user()?;\n    tracing::info!(\"handling {{}}\", {i});\n    Ok(Response::ok(user.id + {i}))\n}}\n").into_bytes())
+            .collect();
+        let dictionary = train_dictionary(&samples, 4096).unwrap();
+        assert!(!dictionary.is_empty() && dictionary.len() <= 4096);
+
+        let codec = ChunkCodec::with_dictionary(dictionary.clone(), DEFAULT_COMPRESSION_LEVEL);
+        assert_eq!(codec.dictionary(), Some(dictionary.as_slice()));
+        let chunk = &samples[42];
+        let with_dictionary = codec.compress(chunk).unwrap();
+        let plain = compress(chunk).unwrap();
+        assert!(with_dictionary.len() < plain.len());
+        assert_eq!(&codec.decompress(&with_dictionary).unwrap(), chunk);
+
+        // Chunks written without the dictionary still decompress
+        assert_eq!(&codec.decompress(&plain).unwrap(), chunk);
+        // ... but dictionary chunks need it
+        assert!(ChunkCodec::default().decompress(&with_dictionary).is_err());
+
+        assert!(train_dictionary(&[b"tiny".to_vec()], 4096).is_err());
+    }
+
+    #[test]
+    fn test_codec_parse_and_dispatch() {
+        assert_eq!("zstd".parse::<Codec>().unwrap(), Codec::default());
+        assert_eq!("zstd:19".parse::<Codec>().unwrap(), Codec::Zstd { level: 19 });
+        assert_eq!(Codec::Zstd { level: 19 }.to_string(), "zstd:19");
+        assert!("zstd:99".parse::<Codec>().is_err());
+        assert!("brotli".parse::<Codec>().is_err());
+
+        let data = "fn main() { println!(\"hello\"); }\n".repeat(50).into_bytes();
+        let fast = ChunkCodec::new(Codec::Zstd { level: 1 }).compress(&data).unwrap();
+        let small = ChunkCodec::new(Codec::Zstd { level: 19 }).compress(&data).unwrap();
+        assert_eq!(detect_codec(&small), Some("zstd"));
+        assert_eq!(ChunkCodec::default().decompress(&fast).unwrap(), data);
+        assert_eq!(ChunkCodec::default().decompress(&small).unwrap(), data);
+
+        #[cfg(feature = "lz4")]
+        {
+            assert_eq!("lz4".parse::<Codec>().unwrap(), Codec::Lz4);
+            let lz4 = ChunkCodec::new(Codec::Lz4).compress(&data).unwrap();
+            assert_eq!(detect_codec(&lz4), Some("lz4"));
+            assert!(lz4.len() < data.len());
+            assert_eq!(ChunkCodec::default().decompress(&lz4).unwrap(), data);
+        }
+        #[cfg(not(feature = "lz4"))]
+        assert!("lz4".parse::<Codec>().is_err());
+    }
+
     #[test]
     fn test_empty_data() {
         let original = b"";
@@ -0,0 +1,496 @@
+//! RAG Context Assembly
+//!
+//! Turns search hits into a single prompt-ready string with source citations.
+//! Given a query and a token budget, the assembler:
+//!
+//! 1. Runs a search (keyword, or semantic with the "search" feature)
+//! 2. Deduplicates overlapping chunks
+//! 3. Expands each hit to its neighboring chunks in the same file
+//! 4. Orders the selected spans by file and position
+//! 5. Renders them with numbered citations
+//!
+//! ```text
+//! [1] src/lib.rs (bytes 0-8192)
+//! ```rs
+//! pub fn add(a: i32, b: i32) -> i32 { ... }
+//! ```
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::format::CxpReader;
+use crate::token::estimate_tokens;
+use crate::{CxpError, Result};
+
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::fusion::FusedResult;
+
+/// Default number of neighboring chunks added on each side of a hit
+pub const DEFAULT_NEIGHBOR_CHUNKS: usize = 1;
+
+/// A search hit pointing at one chunk of one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextHit {
+    /// File path inside the archive
+    pub file_path: String,
+    /// Chunk index within the file
+    pub chunk_index: usize,
+    /// Relevance score (higher is better)
+    pub score: f32,
+}
+
+/// A cited span of a file in the assembled context
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextSource {
+    /// Citation number as it appears in the text (1-based)
+    pub citation: usize,
+    /// File path inside the archive
+    pub file_path: String,
+    /// Start offset of the span in the file (bytes)
+    pub start_byte: usize,
+    /// End offset of the span in the file (bytes, exclusive)
+    pub end_byte: usize,
+    /// Best score of the hits inside this span
+    pub score: f32,
+}
+
+/// Result of context assembly
+#[derive(Debug, Clone, Default)]
+pub struct AssembledContext {
+    /// Prompt-ready text with citation headers
+    pub text: String,
+    /// Sources in citation order
+    pub sources: Vec<ContextSource>,
+    /// Estimated token count of `text`
+    pub estimated_tokens: u64,
+}
+
+impl AssembledContext {
+    /// Check if no context was selected
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+}
+
+/// Assembles retrieved chunks into a prompt-ready context string
+pub struct ContextAssembler<'a> {
+    /// Archive to read chunks from
+    reader: &'a CxpReader,
+    /// Maximum estimated tokens of the assembled text
+    token_budget: u64,
+    /// Neighboring chunks to include on each side of a hit
+    neighbors: usize,
+}
+
+impl<'a> ContextAssembler<'a> {
+    /// Create an assembler with a token budget
+    pub fn new(reader: &'a CxpReader, token_budget: u64) -> Self {
+        Self {
+            reader,
+            token_budget,
+            neighbors: DEFAULT_NEIGHBOR_CHUNKS,
+        }
+    }
+
+    /// Set the number of neighboring chunks added on each side of a hit
+    pub fn with_neighbors(mut self, neighbors: usize) -> Self {
+        self.neighbors = neighbors;
+        self
+    }
+
+    /// Find the chunks matching a keyword query
+    ///
+    /// Chunks are ranked by the number of distinct query terms they contain,
+    /// then by total occurrences. Matching is ASCII case-insensitive.
+    pub fn keyword_hits(&self, query: &str, top_k: usize) -> Result<Vec<ContextHit>> {
+        let terms: Vec<Vec<u8>> = query
+            .split_whitespace()
+            .map(|t| t.to_ascii_lowercase().into_bytes())
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        if terms.is_empty() || top_k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut paths: Vec<&str> = self.reader.file_paths();
+        paths.sort_unstable();
+
+        // (file, chunk index) -> (distinct terms, occurrences)
+        let mut matches: Vec<(ContextHit, usize, usize)> = Vec::new();
+
+        for path in paths {
+            let Some(entry) = self.reader.file_entry(path)? else {
+                continue;
+            };
+            if entry.is_image || entry.chunks.is_empty() {
+                continue;
+            }
+
+            let content = self.reader.read_file(path)?.to_ascii_lowercase();
+            let mut per_chunk: BTreeMap<usize, (BTreeSet<usize>, usize)> = BTreeMap::new();
+
+            for (term_idx, term) in terms.iter().enumerate() {
+                for pos in find_all(&content, term) {
+                    let chunk_index = entry
+                        .chunks
+                        .partition_point(|c| c.offset <= pos)
+                        .saturating_sub(1);
+                    let stats = per_chunk.entry(chunk_index).or_default();
+                    stats.0.insert(term_idx);
+                    stats.1 += 1;
+                }
+            }
+
+            for (chunk_index, (distinct, occurrences)) in per_chunk {
+                let distinct = distinct.len();
+                matches.push((
+                    ContextHit {
+                        file_path: path.to_string(),
+                        chunk_index,
+                        score: distinct as f32 + occurrences as f32 / (occurrences as f32 + 1.0),
+                    },
+                    distinct,
+                    occurrences,
+                ));
+            }
+        }
+
+        matches.sort_by_key(|m| std::cmp::Reverse((m.1, m.2)));
+
+        Ok(matches.into_iter().take(top_k).map(|(hit, _, _)| hit).collect())
+    }
+
+    /// Assemble context for a keyword query
+    pub fn assemble_keyword(&self, query: &str, top_k: usize) -> Result<AssembledContext> {
+        let hits = self.keyword_hits(query, top_k)?;
+        self.assemble_hits(&hits)
+    }
+
+    /// Locate a chunk by hash (alphabetically first file containing it)
+    pub fn locate_chunk(&self, hash: &str, score: f32) -> Option<ContextHit> {
+        let mut paths = self.reader.file_paths();
+        paths.sort_unstable();
+        paths.into_iter().find_map(|path| {
+            let entry = match self.reader.file_entry(path) {
+                Ok(entry) => entry?,
+                Err(e) => {
+                    tracing::warn!("Failed to load the entry of {}: {}", path, e);
+                    return None;
+                }
+            };
+            let chunk_index = entry.chunks.iter().position(|c| c.hash == hash)?;
+            Some(ContextHit {
+                file_path: path.to_string(),
+                chunk_index,
+                score,
+            })
+        })
+    }
+
+    /// Assemble context for a query embedding using semantic search
+    ///
+    /// You must call `load_embeddings()` on the reader first.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn assemble(&self, query_embedding: &[f32], top_k: usize) -> Result<AssembledContext> {
+        let results = self.reader.search_semantic(query_embedding, top_k)?;
+
+        let hits: Vec<ContextHit> = results
+            .iter()
+            .filter_map(|r| {
+                self.reader
+                    .embedding_chunk_hash(r.id)
+                    .and_then(|hash| self.locate_chunk(hash, r.distance))
+            })
+            .collect();
+
+        self.assemble_hits(&hits)
+    }
+
+    /// Assemble context from fused multi-query results
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn assemble_fused(&self, results: &[FusedResult]) -> Result<AssembledContext> {
+        let hits: Vec<ContextHit> = results
+            .iter()
+            .filter_map(|r| {
+                r.chunk_hash
+                    .as_deref()
+                    .and_then(|hash| self.locate_chunk(hash, r.score))
+            })
+            .collect();
+
+        self.assemble_hits(&hits)
+    }
+
+    /// Assemble context from ranked hits (best first)
+    ///
+    /// Hits are added greedily in rank order together with their neighbors
+    /// until the token budget is exhausted. Chunks that would overflow the
+    /// budget are skipped, so smaller later hits may still fit.
+    pub fn assemble_hits(&self, hits: &[ContextHit]) -> Result<AssembledContext> {
+        let mut selected: BTreeMap<&str, BTreeSet<usize>> = BTreeMap::new();
+        let mut scores: HashMap<(&str, usize), f32> = HashMap::new();
+        let mut used_tokens: u64 = 0;
+
+        for hit in hits {
+            let Some(entry) = self.reader.file_entry(&hit.file_path)? else {
+                continue;
+            };
+            if hit.chunk_index >= entry.chunks.len() {
+                continue;
+            }
+
+            let path = entry.path.as_str();
+            let key = (path, hit.chunk_index);
+            let best = scores.entry(key).or_insert(hit.score);
+            if hit.score > *best {
+                *best = hit.score;
+            }
+
+            // Core chunk first, then neighbors by increasing distance
+            let mut candidates = vec![hit.chunk_index];
+            for distance in 1..=self.neighbors {
+                if let Some(before) = hit.chunk_index.checked_sub(distance) {
+                    candidates.push(before);
+                }
+                if hit.chunk_index + distance < entry.chunks.len() {
+                    candidates.push(hit.chunk_index + distance);
+                }
+            }
+
+            for (i, chunk_index) in candidates.into_iter().enumerate() {
+                let chunks = selected.entry(path).or_default();
+                if chunks.contains(&chunk_index) {
+                    continue;
+                }
+
+                let mut cost = estimate_tokens(entry.chunks[chunk_index].length as u64);
+                let starts_span = (chunk_index == 0 || !chunks.contains(&(chunk_index - 1)))
+                    && !chunks.contains(&(chunk_index + 1));
+                if starts_span {
+                    cost += estimate_tokens(header_reserve(path) as u64);
+                }
+
+                if used_tokens + cost > self.token_budget {
+                    // Without its core chunk a hit contributes nothing
+                    if i == 0 {
+                        break;
+                    }
+                    continue;
+                }
+
+                chunks.insert(chunk_index);
+                used_tokens += cost;
+            }
+        }
+
+        self.render(&selected, &scores)
+    }
+
+    /// Render selected chunks as cited spans, ordered by file and position
+    fn render(
+        &self,
+        selected: &BTreeMap<&str, BTreeSet<usize>>,
+        scores: &HashMap<(&str, usize), f32>,
+    ) -> Result<AssembledContext> {
+        let mut context = AssembledContext::default();
+
+        for (&path, chunks) in selected {
+            let entry = self
+                .reader
+                .file_entry(path)?
+                .ok_or_else(|| CxpError::FileNotFound(path.to_string()))?;
+
+            for span in contiguous_spans(chunks) {
+                let first = &entry.chunks[span.start];
+                let last = &entry.chunks[span.end - 1];
+                let start_byte = first.offset;
+                let end_byte = last.offset + last.length;
+
+                let score = span
+                    .clone()
+                    .filter_map(|i| scores.get(&(path, i)).copied())
+                    .fold(0.0f32, f32::max);
+
+                let data = self.reader.read_file_chunks(path, span)?;
+                let body = String::from_utf8_lossy(&data);
+
+                let citation = context.sources.len() + 1;
+                context.text.push_str(&format!(
+                    "[{}] {} (bytes {}-{})\n```{}\n{}",
+                    citation, path, start_byte, end_byte, entry.extension, body
+                ));
+                if !body.ends_with('\n') {
+                    context.text.push('\n');
+                }
+                context.text.push_str("```\n\n");
+
+                context.sources.push(ContextSource {
+                    citation,
+                    file_path: path.to_string(),
+                    start_byte,
+                    end_byte,
+                    score,
+                });
+            }
+        }
+
+        context.estimated_tokens = estimate_tokens(context.text.len() as u64);
+
+        Ok(context)
+    }
+}
+
+/// Upper bound of the citation header and fences added around a span
+fn header_reserve(path: &str) -> usize {
+    path.len() + 64
+}
+
+/// Group sorted chunk indices into contiguous ranges
+fn contiguous_spans(chunks: &BTreeSet<usize>) -> Vec<std::ops::Range<usize>> {
+    let mut spans: Vec<std::ops::Range<usize>> = Vec::new();
+
+    for &index in chunks {
+        match spans.last_mut() {
+            Some(span) if span.end == index => span.end = index + 1,
+            _ => spans.push(index..index + 1),
+        }
+    }
+
+    spans
+}
+
+/// Find all (possibly overlapping) occurrences of a needle
+fn find_all(haystack: &[u8], needle: &[u8]) -> Vec<usize> {
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return Vec::new();
+    }
+
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(|(_, window)| *window == needle)
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CxpBuilder;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn build_archive() -> (TempDir, CxpReader) {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+
+        // Large enough to span several chunks
+        let mut long = String::new();
+        for i in 0..3000 {
+            long.push_str(&format!("line {} filler text {}\n", i, i * 7919 % 1000));
+        }
+        long.push_str("the needle lives near the end\n");
+        fs::write(src.join("long.txt"), &long).unwrap();
+        fs::write(src.join("short.md"), "# Notes\nA needle in a short file.\n").unwrap();
+        fs::write(src.join("other.rs"), "fn main() {}\n").unwrap();
+
+        let output = temp_dir.path().join("test.cxp");
+        let mut builder = CxpBuilder::new(&src);
+        builder.scan().unwrap().process().unwrap().build(&output).unwrap();
+
+        let reader = CxpReader::open(&output).unwrap();
+        (temp_dir, reader)
+    }
+
+    #[test]
+    fn test_contiguous_spans() {
+        let chunks: BTreeSet<usize> = [0, 1, 2, 5, 7, 8].into_iter().collect();
+        assert_eq!(contiguous_spans(&chunks), vec![0..3, 5..6, 7..9]);
+    }
+
+    #[test]
+    fn test_find_all() {
+        assert_eq!(find_all(b"aaa", b"aa"), vec![0, 1]);
+        assert!(find_all(b"abc", b"").is_empty());
+        assert!(find_all(b"ab", b"abc").is_empty());
+    }
+
+    #[test]
+    fn test_keyword_assembly_cites_sources() {
+        let (_dir, reader) = build_archive();
+        let assembler = ContextAssembler::new(&reader, 100_000);
+
+        let context = assembler.assemble_keyword("needle", 5).unwrap();
+
+        assert_eq!(context.sources.len(), 2);
+        // Ordered by file path
+        assert_eq!(context.sources[0].file_path, "long.txt");
+        assert_eq!(context.sources[1].file_path, "short.md");
+        assert!(context.text.contains("[1] long.txt"));
+        assert!(context.text.contains("[2] short.md"));
+        assert!(context.text.contains("the needle lives near the end"));
+        assert!(!context.text.contains("fn main"));
+    }
+
+    #[test]
+    fn test_neighbors_are_merged_into_one_span() {
+        let (_dir, reader) = build_archive();
+        let entry = &reader.file_map.files["long.txt"];
+        assert!(entry.chunks.len() >= 3);
+
+        let assembler = ContextAssembler::new(&reader, 1_000_000).with_neighbors(1);
+        let hits = vec![
+            ContextHit { file_path: "long.txt".to_string(), chunk_index: 1, score: 1.0 },
+            ContextHit { file_path: "long.txt".to_string(), chunk_index: 1, score: 0.5 },
+        ];
+        let context = assembler.assemble_hits(&hits).unwrap();
+
+        assert_eq!(context.sources.len(), 1);
+        assert_eq!(context.sources[0].start_byte, 0);
+        assert_eq!(context.sources[0].end_byte, entry.chunks[2].offset + entry.chunks[2].length);
+        assert_eq!(context.sources[0].score, 1.0);
+    }
+
+    #[test]
+    fn test_token_budget_is_respected() {
+        let (_dir, reader) = build_archive();
+        let budget = 1_500;
+        let assembler = ContextAssembler::new(&reader, budget).with_neighbors(3);
+
+        let hits: Vec<ContextHit> = (0..reader.file_map.files["long.txt"].chunks.len())
+            .map(|i| ContextHit { file_path: "long.txt".to_string(), chunk_index: i, score: 1.0 })
+            .collect();
+        let context = assembler.assemble_hits(&hits).unwrap();
+
+        assert!(!context.is_empty());
+        assert!(context.estimated_tokens <= budget);
+    }
+
+    #[test]
+    fn test_locate_chunk() {
+        let (_dir, reader) = build_archive();
+        let assembler = ContextAssembler::new(&reader, 1_000);
+        let hash = reader.file_map.files["short.md"].chunks[0].hash.clone();
+
+        let hit = assembler.locate_chunk(&hash, 0.5).unwrap();
+        assert_eq!(hit.file_path, "short.md");
+        assert_eq!(hit.chunk_index, 0);
+        assert!(assembler.locate_chunk("missing", 0.0).is_none());
+    }
+
+    #[test]
+    fn test_lazy_reader_assembles_context() {
+        let (dir, reader) = build_archive();
+        let hash = reader.file_map.files["short.md"].chunks[0].hash.clone();
+        let lazy = CxpReader::open_lazy(dir.path().join("test.cxp")).unwrap();
+        let assembler = ContextAssembler::new(&lazy, 100_000);
+
+        // Entries come from the shards; `file_map` stays empty
+        let context = assembler.assemble_keyword("needle", 5).unwrap();
+        assert_eq!(context.sources.len(), 2);
+        assert_eq!(assembler.locate_chunk(&hash, 0.5).unwrap().file_path, "short.md");
+        assert!(lazy.file_map.files.is_empty());
+    }
+}
@@ -818,7 +818,7 @@ mod tests {
 
         let retrieved = ext.get_settings();
         assert_eq!(retrieved.theme, "dark");
-        assert_eq!(retrieved.auto_index, false);
+        assert!(!retrieved.auto_index);
         assert_eq!(retrieved.max_context_files, 100);
     }
 
@@ -3,13 +3,13 @@
 //! Stores each unique chunk only once, identified by its SHA-256 hash.
 
 use crate::chunker::{Chunk, ChunkRef};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// Chunk store with deduplication
 #[derive(Debug, Default, Clone)]
 pub struct ChunkStore {
     /// Chunks indexed by their hash
-    chunks: HashMap<String, Chunk>,
+    chunks: BTreeMap<String, Chunk>,
     /// Statistics
     stats: DeduplicationStats,
 }
@@ -0,0 +1,325 @@
+//! Delta Patches between CXP Versions
+//!
+//! A delta (`.cxpd`) holds only what changed between two archives: chunks the
+//! new version references that the old one does not, file map changes, and
+//! any other entries (embeddings, extensions) whose bytes differ. Applying it
+//! to the base archive reproduces the new archive.
+//!
+//! Structure (ZIP, entries stored uncompressed like `.cxp`):
+//! ```text
+//! patch.cxpd
+//! ├── delta.msgpack        # CxpDelta header
+//! ├── chunks/<id>.zst      # New chunks (copied as stored)
+//! └── entries/<path>       # Changed non-chunk entries, by archive path
+//! ```
+
+use crate::compress::DICTIONARY_PATH;
+use crate::format::{read_file_map, stored_options, ArchiveWriter, FileEntry, FileMap};
+use crate::manifest::Manifest;
+use crate::map_shards::DEFAULT_SHARD_SIZE;
+use crate::packs::PackIndex;
+use crate::temp::replace_file;
+use crate::toc::TOC_PATH;
+use crate::{CxpError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use zip::{ZipArchive, ZipWriter};
+
+/// Current delta format version
+pub const DELTA_VERSION: u32 = 1;
+
+/// Path of the delta header inside a `.cxpd` file
+pub const DELTA_HEADER_PATH: &str = "delta.msgpack";
+
+/// Delta between two CXP archives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CxpDelta {
+    /// Delta format version
+    pub version: u32,
+    /// Fingerprint of the base file map (checked on apply)
+    pub base_fingerprint: String,
+    /// Fingerprint of the target file map (verified after apply)
+    pub target_fingerprint: String,
+    /// Manifest of the target archive
+    pub manifest: Manifest,
+    /// Files that were added or changed
+    pub upserted_files: Vec<FileEntry>,
+    /// Paths of removed files
+    pub removed_files: Vec<String>,
+    /// Hashes of chunks not present in the base archive
+    pub new_chunks: Vec<String>,
+    /// Other archive entries included in full (embeddings, extensions, ...)
+    pub changed_entries: Vec<String>,
+    /// Other archive entries removed from the base
+    pub removed_entries: Vec<String>,
+}
+
+impl CxpDelta {
+    /// Compute the delta from `old` to `new` and write it to `patch_path`
+    pub fn create<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(
+        old: P,
+        new: Q,
+        patch_path: R,
+    ) -> Result<Self> {
+        let mut old_archive = ZipArchive::new(File::open(old.as_ref())?)?;
+        let mut new_archive = ZipArchive::new(File::open(new.as_ref())?)?;
+
+        let old_map = read_file_map(&mut old_archive)?;
+        let new_map = read_file_map(&mut new_archive)?;
+        let manifest = Manifest::from_msgpack(&read_entry(&mut new_archive, "manifest.msgpack")?)?;
+        manifest.check_reader_version()?;
+
+        // File map changes
+        let upserted_files: Vec<FileEntry> = new_map
+            .files
+            .values()
+            .filter(|entry| {
+                old_map.files.get(&entry.path).is_none_or(|old| !same_entry(old, entry))
+            })
+            .cloned()
+            .collect();
+        let removed_files: Vec<String> = old_map
+            .files
+            .keys()
+            .filter(|path| !new_map.files.contains_key(*path))
+            .cloned()
+            .collect();
+
+        // Other entries, compared by CRC and size
+        let old_entries = other_entries(&mut old_archive)?;
+        let new_entries = other_entries(&mut new_archive)?;
+
+        // Chunks the base does not have; a changed compression dictionary
+        // invalidates all of the base's stored chunks
+        let same_dictionary = old_entries.get(DICTIONARY_PATH) == new_entries.get(DICTIONARY_PATH);
+        let old_chunks = chunk_hashes(&old_map);
+        let new_chunks: Vec<String> = chunk_hashes(&new_map)
+            .into_iter()
+            .filter(|hash| !same_dictionary || !old_chunks.contains(hash))
+            .map(str::to_string)
+            .collect();
+        let changed_entries: Vec<String> = new_entries
+            .iter()
+            .filter(|(name, sig)| old_entries.get(*name) != Some(sig))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let removed_entries: Vec<String> = old_entries
+            .keys()
+            .filter(|name| !new_entries.contains_key(*name))
+            .cloned()
+            .collect();
+
+        let delta = Self {
+            version: DELTA_VERSION,
+            base_fingerprint: fingerprint(&old_map),
+            target_fingerprint: fingerprint(&new_map),
+            manifest,
+            upserted_files,
+            removed_files,
+            new_chunks,
+            changed_entries,
+            removed_entries,
+        };
+
+        // Write the patch
+        let mut zip = ZipWriter::new(File::create(patch_path.as_ref())?);
+        let header = delta.to_msgpack()?;
+        zip.start_file(DELTA_HEADER_PATH, stored_options(header.len()))?;
+        zip.write_all(&header)?;
+
+        let packs = PackIndex::read_from_archive(&mut new_archive)?;
+        for hash in &delta.new_chunks {
+            let data = packs.read_stored(&mut new_archive, hash)?;
+            zip.start_file(PackIndex::legacy_path(hash), stored_options(data.len()))?;
+            zip.write_all(&data)?;
+        }
+
+        for name in &delta.changed_entries {
+            let data = read_entry(&mut new_archive, name)?;
+            zip.start_file(format!("entries/{}", name), stored_options(data.len()))?;
+            zip.write_all(&data)?;
+        }
+
+        zip.finish()?;
+
+        tracing::info!(
+            "Delta: {} files changed, {} removed, {} new chunks, {} changed entries",
+            delta.upserted_files.len(),
+            delta.removed_files.len(),
+            delta.new_chunks.len(),
+            delta.changed_entries.len()
+        );
+
+        Ok(delta)
+    }
+
+    /// Read the delta header from a patch file
+    pub fn read<P: AsRef<Path>>(patch_path: P) -> Result<Self> {
+        let mut patch = ZipArchive::new(File::open(patch_path.as_ref())?)?;
+        Self::from_msgpack(&read_entry(&mut patch, DELTA_HEADER_PATH)?)
+    }
+
+    /// Apply a patch to `base` and write the resulting archive to `output`
+    ///
+    /// `output` may be the same path as `base`; the archive is replaced only
+    /// after the result has been verified.
+    pub fn apply<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(
+        base: P,
+        patch_path: Q,
+        output: R,
+    ) -> Result<Self> {
+        let output = output.as_ref();
+        let mut base_archive = ZipArchive::new(File::open(base.as_ref())?)?;
+        let mut patch = ZipArchive::new(File::open(patch_path.as_ref())?)?;
+
+        let delta = Self::from_msgpack(&read_entry(&mut patch, DELTA_HEADER_PATH)?)?;
+        if delta.version > DELTA_VERSION {
+            return Err(CxpError::InvalidFormat(format!(
+                "Unsupported delta version {}",
+                delta.version
+            )));
+        }
+
+        let mut file_map = read_file_map(&mut base_archive)?;
+        if fingerprint(&file_map) != delta.base_fingerprint {
+            return Err(CxpError::InvalidFormat(
+                "Patch was not created against this base archive".to_string(),
+            ));
+        }
+
+        for path in &delta.removed_files {
+            file_map.files.remove(path);
+        }
+        for entry in &delta.upserted_files {
+            file_map.files.insert(entry.path.clone(), entry.clone());
+        }
+        if fingerprint(&file_map) != delta.target_fingerprint {
+            return Err(CxpError::InvalidFormat("Patched file map does not match target".to_string()));
+        }
+
+        let mut other: BTreeSet<String> = other_entries(&mut base_archive)?.into_keys().collect();
+        for name in &delta.removed_entries {
+            other.remove(name);
+        }
+        other.extend(delta.changed_entries.iter().cloned());
+
+        // Write next to the output and move into place once complete
+        let hashes = chunk_hashes(&file_map);
+        replace_file(output, |temp_output| {
+            let mut writer = ArchiveWriter::create(temp_output)?;
+            writer.write_manifest(&delta.manifest)?;
+            writer.write_file_map(&file_map, DEFAULT_SHARD_SIZE)?;
+
+            let new_chunks: HashSet<&str> = delta.new_chunks.iter().map(String::as_str).collect();
+            let base_packs = PackIndex::read_from_archive(&mut base_archive)?;
+            for hash in &hashes {
+                let data = if new_chunks.contains(hash) {
+                    read_entry(&mut patch, &PackIndex::legacy_path(hash))?
+                } else {
+                    base_packs.read_stored(&mut base_archive, hash)?
+                };
+                writer.write_chunk(hash, &data)?;
+            }
+            writer.write_filters(&file_map)?;
+
+            let changed: HashSet<&str> = delta.changed_entries.iter().map(String::as_str).collect();
+            for name in &other {
+                let data = if changed.contains(name.as_str()) {
+                    read_entry(&mut patch, &format!("entries/{}", name))?
+                } else {
+                    read_entry(&mut base_archive, name)?
+                };
+                writer.write(name, &data)?;
+            }
+            writer.finish()?;
+            Ok(())
+        })?;
+
+        tracing::info!("Applied delta: {} files, {} chunks", file_map.files.len(), hashes.len());
+
+        Ok(delta)
+    }
+
+    /// Whether the delta contains no changes
+    pub fn is_empty(&self) -> bool {
+        self.upserted_files.is_empty()
+            && self.removed_files.is_empty()
+            && self.new_chunks.is_empty()
+            && self.changed_entries.is_empty()
+            && self.removed_entries.is_empty()
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+}
+
+/// Content fingerprint of a file map (paths and chunk hashes, in path order)
+pub fn fingerprint(file_map: &FileMap) -> String {
+    let mut hasher = Sha256::new();
+    for (path, entry) in &file_map.files {
+        hasher.update(path.as_bytes());
+        hasher.update([0]);
+        for chunk in &entry.chunks {
+            hasher.update(chunk.hash.as_bytes());
+        }
+        hasher.update([b'\n']);
+    }
+    hex::encode(hasher.finalize())
+}
+
+fn same_entry(a: &FileEntry, b: &FileEntry) -> bool {
+    a.size == b.size
+        && a.extension == b.extension
+        && a.is_image == b.is_image
+        && a.modified == b.modified
+        && a.chunks.len() == b.chunks.len()
+        && a.chunks.iter().zip(&b.chunks).all(|(x, y)| x.hash == y.hash)
+}
+
+/// Unique chunk hashes referenced by a file map (sorted, matching the builder's write order)
+fn chunk_hashes(file_map: &FileMap) -> BTreeSet<&str> {
+    file_map
+        .files
+        .values()
+        .flat_map(|entry| entry.chunks.iter().map(|c| c.hash.as_str()))
+        .collect()
+}
+
+/// Entries that are neither chunks nor derived from the file map, with `(crc32, size)`
+fn other_entries<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<BTreeMap<String, (u32, u64)>> {
+    let mut entries = BTreeMap::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        let name = entry.name();
+        let derived = name == "manifest.msgpack"
+            || name == "file_map.msgpack"
+            || name == TOC_PATH
+            || name.starts_with("file_map/")
+            || name.starts_with("chunks/")
+            || name.starts_with("filters/");
+        if !derived {
+            entries.insert(name.to_string(), (entry.crc32(), entry.size()));
+        }
+    }
+    Ok(entries)
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| CxpError::InvalidFormat(format!("No {} found: {}", name, e)))?;
+    let mut data = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
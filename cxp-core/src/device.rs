@@ -0,0 +1,133 @@
+//! Execution Devices
+//!
+//! Selects the ONNX Runtime execution provider embedding models run on.
+//! GPU providers need ONNX Runtime with that provider compiled in (the
+//! `cuda`, `coreml` and `directml` features). When a provider cannot be
+//! registered - feature missing, no driver, wrong platform - the session
+//! falls back to the CPU with a warning, and `EmbeddingEngine::device()`
+//! reports `Device::Cpu`.
+//!
+//! # Example
+//! ```ignore
+//! let engine = EmbeddingEngine::load_with("./models/minilm", EmbeddingModel::MiniLM, Device::Cuda(0))?;
+//! println!("Embedding on {}", engine.device());
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use ort::execution_providers::{
+    CUDAExecutionProvider, CoreMLExecutionProvider, DirectMLExecutionProvider, ExecutionProvider,
+};
+use ort::session::builder::{GraphOptimizationLevel, SessionBuilder};
+use ort::session::Session;
+
+use crate::{CxpError, Result};
+
+/// Hardware an embedding model runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Device {
+    /// CPU (always available)
+    #[default]
+    Cpu,
+    /// NVIDIA GPU with the given device ID
+    Cuda(u32),
+    /// Apple Neural Engine / GPU via CoreML
+    CoreML,
+    /// DirectX 12 GPU on Windows
+    DirectML,
+}
+
+impl Device {
+    /// Whether this is a GPU or accelerator
+    pub fn is_accelerated(&self) -> bool {
+        *self != Device::Cpu
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Device::Cpu => write!(f, "cpu"),
+            Device::Cuda(id) => write!(f, "cuda:{}", id),
+            Device::CoreML => write!(f, "coreml"),
+            Device::DirectML => write!(f, "directml"),
+        }
+    }
+}
+
+impl FromStr for Device {
+    type Err = CxpError;
+
+    /// Parse `cpu`, `cuda`, `cuda:<id>`, `coreml` or `directml`
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "cpu" => Ok(Device::Cpu),
+            "cuda" | "gpu" => Ok(Device::Cuda(0)),
+            "coreml" => Ok(Device::CoreML),
+            "directml" | "dml" => Ok(Device::DirectML),
+            _ => match s.strip_prefix("cuda:").map(str::parse) {
+                Some(Ok(id)) => Ok(Device::Cuda(id)),
+                _ => Err(CxpError::Embedding(format!(
+                    "Unknown device '{}'. Valid options: cpu, cuda[:<id>], coreml, directml",
+                    s
+                ))),
+            },
+        }
+    }
+}
+
+/// Session builder running on `device`, or on the CPU if its provider cannot be registered
+///
+/// Returns the builder and the device sessions from it will actually use.
+pub(crate) fn session_builder(device: Device) -> Result<(SessionBuilder, Device)> {
+    let mut builder = Session::builder()?
+        .with_optimization_level(GraphOptimizationLevel::Level3)?
+        .with_intra_threads(num_cpus::get())?;
+
+    let registered = match device {
+        Device::Cpu => return Ok((builder, Device::Cpu)),
+        Device::Cuda(id) => CUDAExecutionProvider::default()
+            .with_device_id(id as i32)
+            .register(&mut builder),
+        Device::CoreML => CoreMLExecutionProvider::default().register(&mut builder),
+        Device::DirectML => {
+            // DirectML does not support memory patterns or parallel execution
+            builder = builder.with_memory_pattern(false)?.with_parallel_execution(false)?;
+            DirectMLExecutionProvider::default().register(&mut builder)
+        }
+    };
+
+    match registered {
+        Ok(()) => {
+            tracing::info!("Running embeddings on {}", device);
+            Ok((builder, device))
+        }
+        Err(e) => {
+            tracing::warn!("Device {} is not available ({}), falling back to CPU", device, e);
+            Ok((builder, Device::Cpu))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_device() {
+        assert_eq!("cpu".parse::<Device>().unwrap(), Device::Cpu);
+        assert_eq!("CUDA".parse::<Device>().unwrap(), Device::Cuda(0));
+        assert_eq!("cuda:2".parse::<Device>().unwrap(), Device::Cuda(2));
+        assert_eq!("coreml".parse::<Device>().unwrap(), Device::CoreML);
+        assert_eq!("directml".parse::<Device>().unwrap(), Device::DirectML);
+        assert!("cuda:x".parse::<Device>().is_err());
+        assert!("tpu".parse::<Device>().is_err());
+
+        for device in [Device::Cpu, Device::Cuda(1), Device::CoreML, Device::DirectML] {
+            assert_eq!(device.to_string().parse::<Device>().unwrap(), device);
+        }
+        assert!(!Device::default().is_accelerated());
+    }
+}
@@ -0,0 +1,269 @@
+//! Near-Duplicate Files
+//!
+//! The near-duplicate groups of [`crate::stats::duplicate_report`]: files
+//! whose content is identical or nearly so (vendored copies of a library, a
+//! config copied into every service) so they can be pruned before they are
+//! sent to a model.
+//!
+//! Files are compared by their sets of chunk hashes. Files with the same
+//! chunks are identical; the others get a MinHash signature, locality-
+//! sensitive hashing over signature bands proposes candidate pairs, and the
+//! exact Jaccard similarity of their chunk sets decides. No pair of files is
+//! compared unless their signatures collide, so the groups stay fast to
+//! compute on large archives.
+//!
+//! Edits inside a chunk change its hash, so files below the chunk size only
+//! match when identical.
+
+use crate::format::FileMap;
+use crate::stats::DuplicateOptions;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Hash functions of a MinHash signature
+const NUM_PERMUTATIONS: usize = 64;
+
+/// Signature rows per LSH band (16 bands of 4 rows)
+const ROWS_PER_BAND: usize = 4;
+
+/// Files in one LSH bucket beyond which candidates are not paired (boilerplate)
+const MAX_BUCKET_SIZE: usize = 256;
+
+/// Files similar enough to keep only one of them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DupeGroup {
+    /// Members; the first (largest) is the one to keep, the rest by similarity
+    pub files: Vec<DupeFile>,
+    /// Whether all members have exactly the same content
+    pub identical: bool,
+    /// Bytes of all members but the first
+    pub redundant_bytes: u64,
+}
+
+/// A member of a duplicate group
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DupeFile {
+    /// File path
+    pub path: String,
+    /// Original size
+    pub size: u64,
+    /// Jaccard similarity of its chunks to those of the group's first file
+    pub similarity: f64,
+}
+
+/// Group the files of `file_map` into identical and near-identical files
+///
+/// Groups come most redundant bytes first.
+pub(crate) fn near_duplicates(file_map: &FileMap, options: &DuplicateOptions) -> Vec<DupeGroup> {
+    // Files with the same distinct chunks share one representative set
+    let mut set_ids: HashMap<Vec<&str>, usize> = HashMap::new();
+    let mut sets: Vec<Vec<&str>> = Vec::new();
+    let mut members: Vec<Vec<&str>> = Vec::new();
+    for entry in file_map.files.values() {
+        if entry.size < options.min_size.max(1) || entry.chunks.is_empty() {
+            continue;
+        }
+        let mut set: Vec<&str> = entry.chunks.iter().map(|c| c.hash.as_str()).collect();
+        set.sort_unstable();
+        set.dedup();
+        let id = *set_ids.entry(set.clone()).or_insert_with(|| {
+            sets.push(set);
+            members.push(Vec::new());
+            sets.len() - 1
+        });
+        members[id].push(entry.path.as_str());
+    }
+
+    // Candidate pairs of sets whose signatures agree on a whole band
+    let signatures: Vec<[u64; NUM_PERMUTATIONS]> = sets.iter().map(|set| signature(set)).collect();
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+    for (id, sig) in signatures.iter().enumerate() {
+        for (band, rows) in sig.chunks(ROWS_PER_BAND).enumerate() {
+            let key = rows.iter().fold(band as u64, |acc, &row| mix(acc ^ row));
+            buckets.entry((band, key)).or_default().push(id);
+        }
+    }
+
+    let mut parent: Vec<usize> = (0..sets.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut checked = HashSet::new();
+    for ids in buckets.values() {
+        if ids.len() > MAX_BUCKET_SIZE {
+            tracing::debug!("Skipping LSH bucket of {} files", ids.len());
+            continue;
+        }
+        for (i, &a) in ids.iter().enumerate() {
+            for &b in &ids[i + 1..] {
+                if !checked.insert((a.min(b), a.max(b))) {
+                    continue;
+                }
+                if jaccard(&sets[a], &sets[b]) >= options.similarity_threshold {
+                    let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+                    if ra != rb {
+                        parent[ra.max(rb)] = ra.min(rb);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for id in 0..sets.len() {
+        let root = find(&mut parent, id);
+        clusters.entry(root).or_default().push(id);
+    }
+
+    let mut groups = Vec::new();
+    for ids in clusters.into_values() {
+        let mut files: Vec<(usize, &str, u64)> = ids
+            .iter()
+            .flat_map(|&id| members[id].iter().map(move |path| (id, *path)))
+            .map(|(id, path)| (id, path, file_map.files[path].size))
+            .collect();
+        if files.len() < 2 {
+            continue;
+        }
+        files.sort_by(|x, y| y.2.cmp(&x.2).then_with(|| x.1.cmp(y.1)));
+
+        let keep = files[0].0;
+        let identical = files.iter().all(|(id, path, _)| *id == keep && same_content(file_map, path, files[0].1));
+        let mut group = DupeGroup {
+            files: files
+                .iter()
+                .map(|&(id, path, size)| DupeFile {
+                    path: path.to_string(),
+                    size,
+                    similarity: if id == keep { 1.0 } else { jaccard(&sets[keep], &sets[id]) },
+                })
+                .collect(),
+            identical,
+            redundant_bytes: files[1..].iter().map(|(_, _, size)| size).sum(),
+        };
+        group.files[1..].sort_by(|x, y| y.similarity.total_cmp(&x.similarity).then_with(|| x.path.cmp(&y.path)));
+
+        groups.push(group);
+    }
+    groups.sort_by(|x, y| {
+        y.redundant_bytes
+            .cmp(&x.redundant_bytes)
+            .then_with(|| x.files[0].path.cmp(&y.files[0].path))
+    });
+    groups
+}
+
+/// Whether two files consist of the same chunks in the same order
+fn same_content(file_map: &FileMap, a: &str, b: &str) -> bool {
+    let (a, b) = (&file_map.files[a].chunks, &file_map.files[b].chunks);
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.hash == y.hash)
+}
+
+/// Jaccard similarity of two sorted, distinct sets
+fn jaccard(a: &[&str], b: &[&str]) -> f64 {
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                common += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    let union = a.len() + b.len() - common;
+    if union == 0 { 0.0 } else { common as f64 / union as f64 }
+}
+
+/// MinHash signature of a set of chunk hashes
+fn signature(set: &[&str]) -> [u64; NUM_PERMUTATIONS] {
+    let mut sig = [u64::MAX; NUM_PERMUTATIONS];
+    for hash in set {
+        // Chunk hashes are hex SHA-256; their prefix is already uniform
+        let base = hash
+            .get(..16)
+            .and_then(|prefix| u64::from_str_radix(prefix, 16).ok())
+            .unwrap_or_else(|| hash.bytes().fold(0xcbf2_9ce4_8422_2325, |acc, b| (acc ^ b as u64).wrapping_mul(0x100_0000_01b3)));
+        let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+        for slot in sig.iter_mut() {
+            seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            *slot = (*slot).min(mix(base ^ seed));
+        }
+    }
+    sig
+}
+
+/// splitmix64 finalizer
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::ChunkRef;
+    use crate::format::FileEntry;
+
+    fn file(path: &str, chunks: &[u32]) -> FileEntry {
+        FileEntry {
+            path: path.to_string(),
+            extension: "txt".to_string(),
+            size: chunks.len() as u64 * 4096,
+            chunks: chunks
+                .iter()
+                .map(|c| ChunkRef { hash: format!("{:064x}", mix(*c as u64)), offset: 0, length: 4096 })
+                .collect(),
+            is_image: false,
+            modified: None,
+            provenance: None,
+            keywords: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_groups_identical_and_near_identical_files() {
+        let mut file_map = FileMap::default();
+        let vendored: Vec<u32> = (0..40).collect();
+        let mut patched = vendored.clone();
+        patched[39] = 1000;
+        for entry in [
+            file("vendor/a/lib.js", &vendored),
+            file("vendor/b/lib.js", &vendored),
+            file("vendor/c/lib.js", &patched),
+            file("svc1/config.yml", &[500]),
+            file("svc2/config.yml", &[500]),
+            file("svc3/config.yml", &[501]),
+            file("src/main.rs", &(100..140).collect::<Vec<_>>()),
+        ] {
+            file_map.files.insert(entry.path.clone(), entry);
+        }
+
+        let groups = near_duplicates(&file_map, &DuplicateOptions::default());
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups.iter().map(|g| g.files.len()).sum::<usize>(), 5);
+
+        let libs = &groups[0];
+        assert!(!libs.identical);
+        assert_eq!(libs.files[0].path, "vendor/a/lib.js");
+        assert_eq!(libs.files[1].path, "vendor/b/lib.js");
+        assert_eq!(libs.files[1].similarity, 1.0);
+        assert!((libs.files[2].similarity - 39.0 / 41.0).abs() < 1e-9);
+        assert_eq!(libs.redundant_bytes, 80 * 4096);
+
+        let configs = &groups[1];
+        assert!(configs.identical);
+        assert_eq!(configs.files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), vec!["svc1/config.yml", "svc2/config.yml"]);
+
+        let strict = near_duplicates(&file_map, &DuplicateOptions::default().with_similarity_threshold(1.0));
+        assert!(strict.iter().all(|g| g.identical));
+        assert_eq!(strict.iter().map(|g| g.files.len()).sum::<usize>(), 4);
+    }
+}
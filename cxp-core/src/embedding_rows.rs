@@ -0,0 +1,582 @@
+//! Embedding Rows
+//!
+//! Where a reader keeps the embedding vectors it searches.
+//! `CxpReader::load_embeddings()` copies binary and rescoring (int8, f16 or
+//! f32) rows into RAM. `CxpReader::load_embeddings_with()` can instead
+//! memory-map the binary rows straight out of the archive file and read
+//! rescoring rows from disk only for the candidates a search rescores, so
+//! archives with more vectors than RAM stay searchable. Both rely on
+//! archive entries being stored uncompressed.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+use memmap2::{Mmap, MmapOptions};
+use rayon::prelude::*;
+use zip::{CompressionMethod, ZipArchive};
+
+use crate::backend::{ArchiveBackend, ReadSeek};
+use crate::format::check_embedding_counts;
+use crate::manifest::{EmbeddingPrecision, Int8Storage};
+use crate::{deserialize_binary_embeddings, deserialize_float_embeddings, deserialize_int8_embeddings};
+use crate::{BinaryEmbedding, CxpError, Int8Embedding, Result};
+
+/// Archive entry holding the binary embeddings
+const BINARY_EMBEDDINGS_PATH: &str = "embeddings/binary.bin";
+
+/// Archive entry holding the int8 rescoring embeddings
+const INT8_EMBEDDINGS_PATH: &str = "embeddings/int8.bin";
+
+/// Count and dimensions header of every embedding entry
+const HEADER_BYTES: u64 = 8;
+
+/// Options for `CxpReader::load_embeddings_with()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadOptions {
+    /// Bytes of embedding rows to keep in RAM; rows that do not fit are
+    /// memory-mapped or read on demand (None: no limit)
+    pub max_memory: Option<usize>,
+    /// Memory-map binary rows instead of loading them (file-backed archives only)
+    pub mmap: bool,
+    /// Read int8 (or float) rescoring rows from disk when a search needs them
+    pub int8_lazy: bool,
+}
+
+impl LoadOptions {
+    /// Default options (everything loaded into RAM)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the RAM held by embedding rows
+    pub fn with_max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Memory-map binary rows
+    pub fn with_mmap(mut self, mmap: bool) -> Self {
+        self.mmap = mmap;
+        self
+    }
+
+    /// Read rescoring rows from disk on demand
+    pub fn with_int8_lazy(mut self, lazy: bool) -> Self {
+        self.int8_lazy = lazy;
+        self
+    }
+}
+
+/// Position and shape of the rows of a stored embedding entry
+#[derive(Debug, Clone, Copy)]
+struct RowLayout {
+    /// Offset of the first row in the archive
+    start: u64,
+    /// Number of rows
+    count: usize,
+    /// Values per row
+    dimensions: usize,
+    /// Bytes per row
+    row_bytes: usize,
+}
+
+impl RowLayout {
+    /// Parse an entry header and check it against the entry size
+    fn parse(header: [u8; 8], data_start: u64, size: u64, row_bytes: impl Fn(usize) -> usize) -> Result<Self> {
+        let count = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let dimensions = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let layout = Self {
+            start: data_start + HEADER_BYTES,
+            count,
+            dimensions,
+            row_bytes: row_bytes(dimensions),
+        };
+        let expected = HEADER_BYTES + (count * layout.row_bytes) as u64;
+        if size != expected {
+            return Err(CxpError::Serialization(format!(
+                "Invalid embeddings data: expected {} bytes, got {}",
+                expected, size
+            )));
+        }
+        Ok(layout)
+    }
+
+    /// Offset of row `id` in the archive
+    fn offset(&self, id: usize) -> u64 {
+        self.start + (id * self.row_bytes) as u64
+    }
+}
+
+/// Binary rows, in RAM or mapped from the archive file
+enum BinaryRows {
+    Loaded(Vec<BinaryEmbedding>),
+    Mapped { map: Mmap, layout: RowLayout },
+}
+
+/// Rescoring rows, in RAM or read from the archive per search
+enum RescoreRows {
+    Loaded {
+        int8: Vec<Int8Embedding>,
+        float: Vec<Vec<f32>>,
+    },
+    Streamed {
+        backend: Arc<dyn ArchiveBackend>,
+        int8: Option<RowLayout>,
+        float: Option<(RowLayout, EmbeddingPrecision)>,
+    },
+}
+
+/// Vector a row is rescored with
+enum RescoreRow<'a> {
+    Float(&'a [f32]),
+    Int8(&'a Int8Embedding),
+    Binary,
+}
+
+/// A query in every representation rows are compared in
+pub(crate) struct EmbeddingQuery<'a> {
+    float: &'a [f32],
+    int8: Int8Embedding,
+    binary: BinaryEmbedding,
+}
+
+impl<'a> EmbeddingQuery<'a> {
+    pub(crate) fn new(float: &'a [f32]) -> Self {
+        Self {
+            float,
+            int8: Int8Embedding::from_float(float),
+            binary: BinaryEmbedding::from_float(float),
+        }
+    }
+
+    /// Binary form of the query (for the HNSW search)
+    pub(crate) fn binary(&self) -> &BinaryEmbedding {
+        &self.binary
+    }
+}
+
+/// Embedding rows of a reader
+pub(crate) struct EmbeddingRows {
+    binary: BinaryRows,
+    rescore: RescoreRows,
+}
+
+impl EmbeddingRows {
+    /// Load the embedding rows of an archive as `options` allow
+    ///
+    /// Binary rows are mapped when `options.mmap` is set or they exceed the
+    /// memory budget, rescoring rows are streamed when `options.int8_lazy`
+    /// is set or they exceed what is left of it. Archives without a file
+    /// on disk or with compressed entries are loaded into RAM instead.
+    pub(crate) fn load(
+        backend: &Arc<dyn ArchiveBackend>,
+        archive: &mut ZipArchive<Box<dyn ReadSeek>>,
+        precision: EmbeddingPrecision,
+        options: LoadOptions,
+    ) -> Result<Self> {
+        let mut budget = options.max_memory.unwrap_or(usize::MAX);
+
+        let binary = {
+            let mut entry = archive.by_name(BINARY_EMBEDDINGS_PATH)?;
+            let size = entry.size();
+            let stored = entry.compression() == CompressionMethod::Stored;
+            match backend.path() {
+                Some(path) if stored && size >= HEADER_BYTES && (options.mmap || size > budget as u64) => {
+                    let map = map_entry(path, entry.data_start(), size)?;
+                    let header = map[..HEADER_BYTES as usize].try_into().expect("header length");
+                    let layout = RowLayout::parse(header, 0, size, |dims| dims.div_ceil(8))?;
+                    BinaryRows::Mapped { map, layout }
+                }
+                _ => {
+                    if options.mmap && size > 0 {
+                        tracing::warn!("Binary embeddings cannot be memory-mapped from this archive; loading them");
+                    }
+                    let mut data = Vec::new();
+                    entry.read_to_end(&mut data)?;
+                    budget = budget.saturating_sub(data.len());
+                    BinaryRows::Loaded(deserialize_binary_embeddings(&data)?)
+                }
+            }
+        };
+
+        let float_path = precision.path().filter(|_| precision.is_float());
+        let mut rescore_size = 0;
+        let mut streamable = true;
+        for path in [Some(INT8_EMBEDDINGS_PATH), float_path].into_iter().flatten() {
+            if let Ok(entry) = archive.by_name(path) {
+                rescore_size += entry.size();
+                streamable &= entry.compression() == CompressionMethod::Stored;
+            }
+        }
+        let stream = options.int8_lazy || rescore_size > budget as u64;
+        if stream && !streamable {
+            tracing::warn!("Rescoring embeddings are compressed and cannot be read on demand; loading them");
+        }
+        let rescore = if stream && streamable {
+            let int8 = stream_entry(archive, INT8_EMBEDDINGS_PATH, |dims| 4 + dims)?;
+            let float = match float_path {
+                Some(path) => {
+                    let width = if precision == EmbeddingPrecision::F16 { 2 } else { 4 };
+                    stream_entry(archive, path, |dims| dims * width)?.map(|layout| (layout, precision))
+                }
+                None => None,
+            };
+            RescoreRows::Streamed { backend: Arc::clone(backend), int8, float }
+        } else {
+            let int8 = match archive.by_name(INT8_EMBEDDINGS_PATH) {
+                Ok(mut entry) => {
+                    let mut data = Vec::new();
+                    entry.read_to_end(&mut data)?;
+                    deserialize_int8_embeddings(&data)?
+                }
+                Err(_) => Vec::new(),
+            };
+            let float = match float_path.map(|path| archive.by_name(path)) {
+                Some(Ok(mut entry)) => {
+                    let mut data = Vec::new();
+                    entry.read_to_end(&mut data)?;
+                    deserialize_float_embeddings(&data, precision)?
+                }
+                _ => Vec::new(),
+            };
+            RescoreRows::Loaded { int8, float }
+        };
+
+        let rows = Self { binary, rescore };
+        if let Some(limit) = options.max_memory.filter(|&limit| rows.memory_bytes() > limit) {
+            tracing::warn!("Embeddings hold {} bytes in RAM, over the {} byte budget", rows.memory_bytes(), limit);
+        }
+        Ok(rows)
+    }
+
+    /// Number of rows
+    pub(crate) fn len(&self) -> usize {
+        match &self.binary {
+            BinaryRows::Loaded(rows) => rows.len(),
+            BinaryRows::Mapped { layout, .. } => layout.count,
+        }
+    }
+
+    /// Bytes of row data held in RAM (mapped and streamed rows excluded)
+    pub(crate) fn memory_bytes(&self) -> usize {
+        let binary = match &self.binary {
+            BinaryRows::Loaded(rows) => rows.iter().map(BinaryEmbedding::size_bytes).sum(),
+            BinaryRows::Mapped { .. } => 0,
+        };
+        let rescore = match &self.rescore {
+            RescoreRows::Loaded { int8, float } => {
+                int8.iter().map(Int8Embedding::size_bytes).sum::<usize>()
+                    + float.iter().map(|row| row.len() * 4).sum::<usize>()
+            }
+            RescoreRows::Streamed { .. } => 0,
+        };
+        binary + rescore
+    }
+
+    /// Whether binary rows are memory-mapped
+    pub(crate) fn is_mapped(&self) -> bool {
+        matches!(self.binary, BinaryRows::Mapped { .. })
+    }
+
+    /// Whether rescoring rows are read from disk per search
+    pub(crate) fn is_streamed(&self) -> bool {
+        matches!(self.rescore, RescoreRows::Streamed { .. })
+    }
+
+    /// Verify that rows, the chunk mapping and the vector dimensions agree
+    ///
+    /// Same checks as for embeddings built in memory; rows that are not in
+    /// RAM are checked through the dimensions in their entry header.
+    pub(crate) fn validate(&self, chunk_ids: Option<&[String]>, dimensions: usize, int8_storage: Int8Storage) -> Result<()> {
+        let (int8_rows, float_rows) = match &self.rescore {
+            RescoreRows::Loaded { int8, float } => (int8.len(), float.len()),
+            RescoreRows::Streamed { int8, float, .. } => (
+                int8.map_or(0, |layout| layout.count),
+                float.map_or(0, |(layout, _)| layout.count),
+            ),
+        };
+        check_embedding_counts(self.len(), int8_rows, float_rows, chunk_ids, int8_storage)?;
+
+        let bad_row = match (&self.binary, &self.rescore) {
+            (BinaryRows::Mapped { layout, .. }, _) if layout.count > 0 && layout.dimensions != dimensions => Some(0),
+            (BinaryRows::Loaded(binary), RescoreRows::Loaded { int8, .. }) => binary
+                .iter()
+                .zip(int8)
+                .position(|(binary, int8)| {
+                    binary.dimensions != dimensions
+                        || binary.bits.len() != dimensions.div_ceil(8)
+                        || int8.values.len() != dimensions
+                }),
+            (BinaryRows::Loaded(binary), RescoreRows::Streamed { .. }) => binary
+                .iter()
+                .position(|binary| binary.dimensions != dimensions || binary.bits.len() != dimensions.div_ceil(8)),
+            _ => None,
+        };
+        let bad_row = bad_row.or(match &self.rescore {
+            RescoreRows::Loaded { float, .. } => float.iter().position(|float| float.len() != dimensions),
+            RescoreRows::Streamed { int8, float, .. } => [int8.as_ref(), float.as_ref().map(|(layout, _)| layout)]
+                .into_iter()
+                .flatten()
+                .any(|layout| layout.count > 0 && layout.dimensions != dimensions)
+                .then_some(0),
+        });
+        match bad_row {
+            Some(row) => Err(CxpError::Embedding(format!(
+                "Embedding row {} does not have {} dimensions",
+                row, dimensions
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Binary similarity of row `id` to the query
+    fn binary_similarity(&self, id: usize, query: &BinaryEmbedding) -> f32 {
+        match &self.binary {
+            BinaryRows::Loaded(rows) => rows.get(id).map_or(0.0, |row| row.similarity(query)),
+            BinaryRows::Mapped { .. } => match self.binary_bits(id) {
+                Some(bits) => 1.0 - 2.0 * crate::simd::hamming(&query.bits, bits) as f32 / query.dimensions.max(1) as f32,
+                None => 0.0,
+            },
+        }
+    }
+
+    /// Packed bits of binary row `id`
+    fn binary_bits(&self, id: usize) -> Option<&[u8]> {
+        match &self.binary {
+            BinaryRows::Loaded(rows) => rows.get(id).map(|row| row.bits.as_slice()),
+            BinaryRows::Mapped { map, layout } => (id < layout.count).then(|| {
+                let start = layout.offset(id) as usize;
+                &map[start..start + layout.row_bytes]
+            }),
+        }
+    }
+
+    /// Score of a row: float or int8 dot product, binary similarity for
+    /// rows without a rescoring vector
+    fn score(&self, id: usize, row: RescoreRow<'_>, query: &EmbeddingQuery<'_>) -> f32 {
+        match row {
+            RescoreRow::Float(float) => crate::simd::dot_f32(float, query.float),
+            RescoreRow::Int8(int8) => int8.dot_product(&query.int8),
+            RescoreRow::Binary => self.binary_similarity(id, &query.binary),
+        }
+    }
+
+    /// Similarity of rows `ids` to a query, in the order of `ids`
+    pub(crate) fn scores(&self, ids: &[usize], query: &EmbeddingQuery<'_>) -> Result<Vec<f32>> {
+        if let RescoreRows::Loaded { int8, float } = &self.rescore {
+            return Ok(ids
+                .par_iter()
+                .map(|&id| {
+                    let row = match (float.get(id), int8.get(id)) {
+                        (Some(float), _) => RescoreRow::Float(float),
+                        (None, Some(int8)) => RescoreRow::Int8(int8),
+                        (None, None) => RescoreRow::Binary,
+                    };
+                    self.score(id, row, query)
+                })
+                .collect());
+        }
+
+        // Read rows in file order, then put the scores back in `ids` order
+        let mut sorted: Vec<(usize, usize)> = ids.iter().copied().enumerate().map(|(i, id)| (id, i)).collect();
+        sorted.sort_unstable();
+        let mut scores = vec![0.0; ids.len()];
+        let mut positions = sorted.iter().map(|&(_, i)| i);
+        self.for_each_rescore_row(sorted.iter().map(|&(id, _)| id), |id, row| {
+            if let Some(i) = positions.next() {
+                scores[i] = self.score(id, row, query);
+            }
+        })?;
+        Ok(scores)
+    }
+
+    /// Dequantized vector of every row, in row order
+    ///
+    /// Float rows are returned as stored and int8 rows are scaled back to
+    /// floats; rows stored only in binary form become vectors of +1/-1.
+    pub(crate) fn for_each_vector(&self, mut f: impl FnMut(usize, Vec<f32>)) -> Result<()> {
+        self.for_each_rescore_row(0..self.len(), |id, row| {
+            let vector = match row {
+                RescoreRow::Float(float) => float.to_vec(),
+                RescoreRow::Int8(int8) => int8.values.iter().map(|&v| v as f32 * int8.scale).collect(),
+                RescoreRow::Binary => {
+                    let bits = self.binary_bits(id).unwrap_or_default();
+                    (0..bits.len() * 8)
+                        .take(self.dimensions())
+                        .map(|i| if (bits[i / 8] >> (i % 8)) & 1 == 1 { 1.0 } else { -1.0 })
+                        .collect()
+                }
+            };
+            f(id, vector);
+        })
+    }
+
+    /// Dimensions of the binary rows
+    fn dimensions(&self) -> usize {
+        match &self.binary {
+            BinaryRows::Loaded(rows) => rows.first().map_or(0, |row| row.dimensions),
+            BinaryRows::Mapped { layout, .. } => layout.dimensions,
+        }
+    }
+
+    /// Call `f` with the rescoring vector of each row in `ids` (ascending)
+    fn for_each_rescore_row(
+        &self,
+        ids: impl Iterator<Item = usize>,
+        mut f: impl FnMut(usize, RescoreRow<'_>),
+    ) -> Result<()> {
+        let (backend, int8, float) = match &self.rescore {
+            RescoreRows::Loaded { int8, float } => {
+                for id in ids {
+                    match (float.get(id), int8.get(id)) {
+                        (Some(float), _) => f(id, RescoreRow::Float(float)),
+                        (None, Some(int8)) => f(id, RescoreRow::Int8(int8)),
+                        (None, None) => f(id, RescoreRow::Binary),
+                    }
+                }
+                return Ok(());
+            }
+            RescoreRows::Streamed { backend, int8, float } => (backend, int8, float),
+        };
+
+        let mut reader = BufReader::new(backend.open()?);
+        let mut position = reader.stream_position()?;
+        let mut bytes = Vec::new();
+        let mut float_row = Vec::new();
+        let mut int8_row = Int8Embedding { values: Vec::new(), scale: 0.0 };
+        for id in ids {
+            let (layout, float_precision) = match (float, int8) {
+                (Some((layout, precision)), _) if id < layout.count => (layout, Some(*precision)),
+                (_, Some(layout)) if id < layout.count => (layout, None),
+                _ => {
+                    f(id, RescoreRow::Binary);
+                    continue;
+                }
+            };
+
+            // Skip forward within the buffer, seek for anything else
+            let offset = layout.offset(id);
+            if offset >= position {
+                reader.seek_relative((offset - position) as i64)?;
+            } else {
+                reader.seek(SeekFrom::Start(offset))?;
+            }
+            bytes.resize(layout.row_bytes, 0);
+            reader.read_exact(&mut bytes)?;
+            position = offset + layout.row_bytes as u64;
+
+            match float_precision {
+                Some(EmbeddingPrecision::F16) => {
+                    float_row.clear();
+                    float_row.extend(bytes.chunks_exact(2).map(|b| half::f16::from_le_bytes([b[0], b[1]]).to_f32()));
+                    f(id, RescoreRow::Float(&float_row));
+                }
+                Some(_) => {
+                    float_row.clear();
+                    float_row.extend(bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])));
+                    f(id, RescoreRow::Float(&float_row));
+                }
+                None => {
+                    int8_row.scale = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    int8_row.values.clear();
+                    int8_row.values.extend(bytes[4..].iter().map(|&b| b as i8));
+                    f(id, RescoreRow::Int8(&int8_row));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Map the bytes of a stored archive entry
+fn map_entry(path: &Path, data_start: u64, size: u64) -> Result<Mmap> {
+    let file = File::open(path)?;
+    // SAFETY: archives are written once and replaced by rename, never
+    // modified in place, so the mapped bytes do not change under the reader
+    let map = unsafe { MmapOptions::new().offset(data_start).len(size as usize).map(&file)? };
+    Ok(map)
+}
+
+/// Layout of a stored entry read on demand (None if the archive lacks it)
+fn stream_entry(
+    archive: &mut ZipArchive<Box<dyn ReadSeek>>,
+    path: &str,
+    row_bytes: impl Fn(usize) -> usize,
+) -> Result<Option<RowLayout>> {
+    let mut entry = match archive.by_name(path) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    if entry.size() == 0 {
+        return Ok(None);
+    }
+    let mut header = [0u8; 8];
+    entry.read_exact(&mut header)?;
+    RowLayout::parse(header, entry.data_start(), entry.size(), row_bytes).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{open_zip, FileBackend};
+    use crate::{serialize_binary_embeddings, serialize_int8_embeddings, QuantizedEmbeddings};
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::write::FileOptions;
+
+    fn vectors() -> Vec<Vec<f32>> {
+        (0..20)
+            .map(|row| (0..16).map(|i| ((row * 7 + i * 3) % 11) as f32 - 5.0).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_mapped_and_streamed_rows_match_loaded() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("rows.cxp");
+        let embeddings = QuantizedEmbeddings::from_floats(&vectors());
+        {
+            let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+            let options = FileOptions::<()>::default().compression_method(CompressionMethod::Stored);
+            zip.start_file("manifest.msgpack", options).unwrap();
+            zip.write_all(b"padding so rows do not start at offset zero").unwrap();
+            zip.start_file(BINARY_EMBEDDINGS_PATH, options).unwrap();
+            zip.write_all(&serialize_binary_embeddings(&embeddings.binary).unwrap()).unwrap();
+            zip.start_file(INT8_EMBEDDINGS_PATH, options).unwrap();
+            zip.write_all(&serialize_int8_embeddings(&embeddings.int8).unwrap()).unwrap();
+            zip.finish().unwrap();
+        }
+
+        let backend: Arc<dyn ArchiveBackend> = Arc::new(FileBackend::new(&path));
+        let load = |options| {
+            let mut archive = open_zip(backend.as_ref()).unwrap();
+            EmbeddingRows::load(&backend, &mut archive, EmbeddingPrecision::Int8, options).unwrap()
+        };
+        let loaded = load(LoadOptions::new());
+        let lazy = load(LoadOptions::new().with_mmap(true).with_int8_lazy(true));
+        let budgeted = load(LoadOptions::new().with_max_memory(0));
+        assert!(!loaded.is_mapped() && !loaded.is_streamed());
+        assert!(lazy.is_mapped() && lazy.is_streamed());
+        assert!(budgeted.is_mapped() && budgeted.is_streamed());
+        assert_eq!(lazy.memory_bytes(), 0);
+        lazy.validate(None, 16, Int8Storage::All).unwrap();
+        assert!(lazy.validate(None, 8, Int8Storage::All).is_err());
+
+        let query_vector = vectors()[3].clone();
+        let query = EmbeddingQuery::new(&query_vector);
+        let ids = [19, 3, 0, 7, 3];
+        assert_eq!(loaded.scores(&ids, &query).unwrap(), lazy.scores(&ids, &query).unwrap());
+        assert_eq!(
+            loaded.binary_similarity(5, &query.binary),
+            lazy.binary_similarity(5, &query.binary)
+        );
+
+        let mut expected = Vec::new();
+        loaded.for_each_vector(|id, vector| expected.push((id, vector))).unwrap();
+        let mut actual = Vec::new();
+        lazy.for_each_vector(|id, vector| actual.push((id, vector))).unwrap();
+        assert_eq!(expected, actual);
+    }
+}
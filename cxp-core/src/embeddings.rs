@@ -3,11 +3,17 @@
 //! Supports multiple embedding models:
 //! - all-MiniLM-L6-v2 (384 dims, 90MB)
 //! - EmbeddingGemma (768 dims with MRL, 200MB)
+//! - BGE small/base, E5 small/base and nomic-embed-text
+//!
+//! Each model has a `ModelSpec` in the registry with its dimensions, pooling,
+//! maximum sequence length and the prompt prefixes it was trained with.
 //!
 //! Features:
 //! - Binary quantization (32x smaller vectors)
 //! - Int8 quantization for rescoring
-//! - Batch processing
+//! - Batch processing with batch sizes that adapt to available memory (`BatchSizer`)
+//! - Long inputs split into overlapping windows whose embeddings are averaged
+//! - Shared tokenization and pooling so the ort and tract engines produce identical vectors
 
 // Result type needed for engine implementations (not for quantization types)
 #[cfg(feature = "embeddings")]
@@ -19,15 +25,25 @@ use crate::CxpError;
 
 // ONNX-specific imports (only for native embeddings)
 #[cfg(feature = "embeddings")]
-use ndarray::{Array2, Axis};
+use ndarray::Array2;
+#[cfg(feature = "embeddings")]
+use ort::session::{RunOptions, Session};
 #[cfg(feature = "embeddings")]
-use ort::session::Session;
+use crate::device::{session_builder, Device};
 #[cfg(feature = "embeddings")]
 use tokenizers::Tokenizer;
+#[cfg(feature = "embeddings")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "embeddings")]
+use std::sync::mpsc::{self, RecvTimeoutError};
 
 #[cfg(feature = "embeddings")]
 use std::path::Path;
 
+use crate::EmbeddingPrecision;
+use std::str::FromStr;
+use std::time::Duration;
+
 /// Supported embedding models
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EmbeddingModel {
@@ -35,24 +51,360 @@ pub enum EmbeddingModel {
     MiniLM,
     /// EmbeddingGemma - 768 dimensions (MRL: 512/256/128), 200MB
     EmbeddingGemma,
+    /// bge-small-en-v1.5 - 384 dimensions, 130MB
+    BgeSmall,
+    /// bge-base-en-v1.5 - 768 dimensions, 440MB
+    BgeBase,
+    /// e5-small-v2 - 384 dimensions, 130MB
+    E5Small,
+    /// e5-base-v2 - 768 dimensions, 440MB
+    E5Base,
+    /// nomic-embed-text-v1.5 - 768 dimensions, 8192 token context, 550MB
+    NomicEmbed,
+}
+
+/// Registry entry describing how to run an embedding model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelSpec {
+    /// Short name for `--model-type`
+    pub key: &'static str,
+    /// Model name (stored in `manifest.embedding_model`)
+    pub name: &'static str,
+    /// Hugging Face model ID
+    pub id: &'static str,
+    /// Hugging Face repository with `onnx/model.onnx` and `tokenizer.json` (None if not downloadable)
+    pub onnx_repo: Option<&'static str>,
+    /// Embedding dimensions
+    pub dimensions: usize,
+    /// Pooling over token embeddings
+    pub pooling: Pooling,
+    /// Maximum sequence length in tokens
+    pub max_length: usize,
+    /// Prepended to search queries
+    pub query_prefix: &'static str,
+    /// Prepended to indexed documents
+    pub passage_prefix: &'static str,
 }
 
 impl EmbeddingModel {
-    /// Get the embedding dimension for this model
-    pub fn dimensions(&self) -> usize {
+    /// All registered models
+    pub const ALL: [EmbeddingModel; 7] = [
+        EmbeddingModel::MiniLM,
+        EmbeddingModel::EmbeddingGemma,
+        EmbeddingModel::BgeSmall,
+        EmbeddingModel::BgeBase,
+        EmbeddingModel::E5Small,
+        EmbeddingModel::E5Base,
+        EmbeddingModel::NomicEmbed,
+    ];
+
+    /// Registry entry of this model
+    pub fn spec(&self) -> &'static ModelSpec {
+        const fn spec(
+            key: &'static str,
+            name: &'static str,
+            (id, onnx_repo): (&'static str, Option<&'static str>),
+            dimensions: usize,
+            pooling: Pooling,
+            max_length: usize,
+            prefixes: (&'static str, &'static str),
+        ) -> ModelSpec {
+            ModelSpec { key, name, id, onnx_repo, dimensions, pooling, max_length, query_prefix: prefixes.0, passage_prefix: prefixes.1 }
+        }
+
+        const MINILM: ModelSpec = spec("minilm", "all-MiniLM-L6-v2", ("sentence-transformers/all-MiniLM-L6-v2", Some("sentence-transformers/all-MiniLM-L6-v2")), 384, Pooling::Mean, DEFAULT_MAX_LENGTH, ("", ""));
+        // Gated on Hugging Face; the ONNX export stores its weights in a separate file
+        const GEMMA: ModelSpec = spec("embeddinggemma", "EmbeddingGemma", ("google/embeddinggemma-300m", None), 768, Pooling::Mean, 2048, ("", ""));
+        const BGE_SMALL: ModelSpec = spec("bge-small", "bge-small-en-v1.5", ("BAAI/bge-small-en-v1.5", Some("BAAI/bge-small-en-v1.5")), 384, Pooling::Cls, 512, ("Represent this sentence for searching relevant passages: ", ""));
+        const BGE_BASE: ModelSpec = spec("bge-base", "bge-base-en-v1.5", ("BAAI/bge-base-en-v1.5", Some("BAAI/bge-base-en-v1.5")), 768, Pooling::Cls, 512, ("Represent this sentence for searching relevant passages: ", ""));
+        const E5_SMALL: ModelSpec = spec("e5-small", "e5-small-v2", ("intfloat/e5-small-v2", Some("Xenova/e5-small-v2")), 384, Pooling::Mean, 512, ("query: ", "passage: "));
+        const E5_BASE: ModelSpec = spec("e5-base", "e5-base-v2", ("intfloat/e5-base-v2", Some("Xenova/e5-base-v2")), 768, Pooling::Mean, 512, ("query: ", "passage: "));
+        const NOMIC: ModelSpec = spec("nomic-embed", "nomic-embed-text-v1.5", ("nomic-ai/nomic-embed-text-v1.5", Some("nomic-ai/nomic-embed-text-v1.5")), 768, Pooling::Mean, 8192, ("search_query: ", "search_document: "));
+
         match self {
-            EmbeddingModel::MiniLM => 384,
-            EmbeddingModel::EmbeddingGemma => 768,
+            EmbeddingModel::MiniLM => &MINILM,
+            EmbeddingModel::EmbeddingGemma => &GEMMA,
+            EmbeddingModel::BgeSmall => &BGE_SMALL,
+            EmbeddingModel::BgeBase => &BGE_BASE,
+            EmbeddingModel::E5Small => &E5_SMALL,
+            EmbeddingModel::E5Base => &E5_BASE,
+            EmbeddingModel::NomicEmbed => &NOMIC,
         }
     }
 
+    /// Get the embedding dimension for this model
+    pub fn dimensions(&self) -> usize {
+        self.spec().dimensions
+    }
+
     /// Get the model name
     pub fn name(&self) -> &'static str {
-        match self {
-            EmbeddingModel::MiniLM => "all-MiniLM-L6-v2",
-            EmbeddingModel::EmbeddingGemma => "EmbeddingGemma",
+        self.spec().name
+    }
+
+    /// Look up a model by its name (as stored in `manifest.embedding_model`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|model| model.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl FromStr for EmbeddingModel {
+    type Err = String;
+
+    /// Parse a registry key (`bge-small`) or model name (`bge-small-en-v1.5`)
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|model| model.spec().key.eq_ignore_ascii_case(s))
+            .or_else(|| Self::from_name(s))
+            .ok_or_else(|| {
+                let keys: Vec<_> = Self::ALL.iter().map(|model| model.spec().key).collect();
+                format!("Unknown model type '{}'. Valid options: {}", s, keys.join(", "))
+            })
+    }
+}
+
+/// Default maximum sequence length (in tokens) for text encoders
+pub const DEFAULT_MAX_LENGTH: usize = 512;
+
+/// Default number of tokens consecutive windows of a long input share
+pub const DEFAULT_WINDOW_OVERLAP: usize = 32;
+
+/// Strategy for pooling token embeddings into one sentence embedding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum Pooling {
+    /// Average over all non-padding tokens (attention-mask weighted)
+    #[default]
+    Mean,
+    /// Embedding of the first ([CLS]) token
+    Cls,
+}
+
+/// Split inputs into windows of `max_length` tokens sharing `overlap` tokens
+///
+/// Overrides the truncation in `tokenizer.json`. Each window gets the model's
+/// special tokens; the overlap is capped at a quarter of the window.
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm"))]
+pub(crate) fn configure_windows(
+    tokenizer: &mut tokenizers::Tokenizer,
+    max_length: usize,
+    overlap: usize,
+) -> crate::Result<()> {
+    let truncation = tokenizers::TruncationParams {
+        max_length,
+        stride: overlap.min(max_length / 4),
+        ..Default::default()
+    };
+    tokenizer
+        .with_truncation(Some(truncation))
+        .map_err(|e| crate::CxpError::Embedding(format!("Invalid window size {}: {}", max_length, e)))?;
+    Ok(())
+}
+
+/// Padded, row-major `[rows, seq_len]` model inputs
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm"))]
+pub(crate) struct EncodedBatch {
+    pub input_ids: Vec<i64>,
+    pub attention_mask: Vec<i64>,
+    pub seq_len: usize,
+    /// Rows (windows) of each input text
+    pub windows: Vec<usize>,
+}
+
+/// Tokenize a batch into model inputs
+///
+/// Shared by all engines so they see exactly the same token IDs. Texts
+/// longer than the window (see `configure_windows`) take several rows.
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm"))]
+pub(crate) fn encode_inputs(
+    tokenizer: &tokenizers::Tokenizer,
+    texts: &[&str],
+    max_length: usize,
+) -> crate::Result<EncodedBatch> {
+    let encodings = tokenizer
+        .encode_batch(texts.to_vec(), true)
+        .map_err(|e| crate::CxpError::Embedding(format!("Tokenization failed: {}", e)))?;
+
+    let windows: Vec<usize> = encodings.iter().map(|e| 1 + e.get_overflowing().len()).collect();
+    let rows: Vec<&tokenizers::Encoding> = encodings
+        .iter()
+        .flat_map(|e| std::iter::once(e).chain(e.get_overflowing()))
+        .collect();
+    let seq_len = rows.iter().map(|e| e.len()).max().unwrap_or(0).min(max_length);
+
+    let mut input_ids = vec![0i64; rows.len() * seq_len];
+    let mut attention_mask = vec![0i64; rows.len() * seq_len];
+
+    for (i, encoding) in rows.iter().enumerate() {
+        let ids = encoding.get_ids();
+        let mask = encoding.get_attention_mask();
+        let len = ids.len().min(seq_len);
+
+        for j in 0..len {
+            input_ids[i * seq_len + j] = ids[j] as i64;
+            attention_mask[i * seq_len + j] = mask[j] as i64;
+        }
+    }
+
+    Ok(EncodedBatch { input_ids, attention_mask, seq_len, windows })
+}
+
+/// Average the window embeddings of each text (`windows` from `encode_inputs`)
+pub fn pool_windows(rows: Vec<Vec<f32>>, windows: &[usize]) -> Vec<Vec<f32>> {
+    let mut rows = rows.into_iter();
+    windows
+        .iter()
+        .map(|&count| {
+            let mut pooled = rows.next().unwrap_or_default();
+            for row in rows.by_ref().take(count.saturating_sub(1)) {
+                for (acc, v) in pooled.iter_mut().zip(row) {
+                    *acc += v;
+                }
+            }
+            if count > 1 {
+                for v in pooled.iter_mut() {
+                    *v /= count as f32;
+                }
+            }
+            pooled
+        })
+        .collect()
+}
+
+/// Pool token embeddings into sentence embeddings
+///
+/// `hidden` is a row-major `[batch, seq_len, hidden_dim]` buffer and `mask`
+/// a row-major `[batch, seq_len]` attention mask. Padding tokens never
+/// contribute to the mean.
+pub fn pool_token_embeddings(
+    hidden: &[f32],
+    mask: &[i64],
+    batch_size: usize,
+    seq_len: usize,
+    hidden_dim: usize,
+    pooling: Pooling,
+) -> Vec<Vec<f32>> {
+    (0..batch_size)
+        .map(|b| {
+            let token = |t: usize| {
+                let start = (b * seq_len + t) * hidden_dim;
+                &hidden[start..start + hidden_dim]
+            };
+
+            match pooling {
+                Pooling::Cls if seq_len > 0 => token(0).to_vec(),
+                Pooling::Cls => vec![0.0; hidden_dim],
+                Pooling::Mean => {
+                    let mut pooled = vec![0.0f32; hidden_dim];
+                    let mut count = 0usize;
+
+                    for t in 0..seq_len {
+                        if mask[b * seq_len + t] == 0 {
+                            continue;
+                        }
+                        for (acc, &v) in pooled.iter_mut().zip(token(t)) {
+                            *acc += v;
+                        }
+                        count += 1;
+                    }
+
+                    if count > 0 {
+                        for v in pooled.iter_mut() {
+                            *v /= count as f32;
+                        }
+                    }
+
+                    pooled
+                }
+            }
+        })
+        .collect()
+}
+
+/// Fixed probe set used to compare embedding engines and platforms
+///
+/// Covers code, prose, very short input, non-ASCII text and input long enough
+/// to exercise truncation.
+pub const PROBE_TEXTS: &[&str] = &[
+    "fn main() {\n    println!(\"Hello, world!\");\n}",
+    "The quick brown fox jumps over the lazy dog.",
+    "a",
+    "Ein deutscher Satz mit Umlauten: äöü ß",
+    "SELECT id, name FROM users WHERE created_at > NOW() - INTERVAL '7 days';",
+    "# README\n\nThis project stores AI context in a compact, deduplicated archive format.",
+    "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt \
+     ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco \
+     laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in \
+     voluptate velit esse cillum dolore eu fugiat nulla pariatur.",
+];
+
+/// Deviation between two sets of embeddings for the same inputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingDeviation {
+    /// Cosine similarity per input
+    pub cosines: Vec<f32>,
+    /// Lowest cosine similarity
+    pub min_cosine: f32,
+    /// Mean cosine similarity
+    pub mean_cosine: f32,
+    /// Largest absolute difference of any single component
+    pub max_abs_diff: f32,
+}
+
+/// Compare two sets of embeddings produced for the same inputs
+///
+/// Returns an error if the sets differ in length or dimensions.
+pub fn compare_embeddings(a: &[Vec<f32>], b: &[Vec<f32>]) -> crate::Result<EmbeddingDeviation> {
+    if a.len() != b.len() {
+        return Err(crate::CxpError::Embedding(format!(
+            "Embedding count mismatch: {} vs {}",
+            a.len(),
+            b.len()
+        )));
+    }
+
+    let mut cosines = Vec::with_capacity(a.len());
+    let mut max_abs_diff = 0.0f32;
+
+    for (x, y) in a.iter().zip(b) {
+        if x.len() != y.len() {
+            return Err(crate::CxpError::Embedding(format!(
+                "Embedding dimension mismatch: {} vs {}",
+                x.len(),
+                y.len()
+            )));
+        }
+
+        let dot: f32 = x.iter().zip(y).map(|(p, q)| p * q).sum();
+        let norm_x: f32 = x.iter().map(|v| v * v).sum::<f32>().sqrt();
+        let norm_y: f32 = y.iter().map(|v| v * v).sum::<f32>().sqrt();
+        let cosine = if norm_x == 0.0 || norm_y == 0.0 {
+            if norm_x == norm_y { 1.0 } else { 0.0 }
+        } else {
+            dot / (norm_x * norm_y)
+        };
+        cosines.push(cosine);
+
+        for (p, q) in x.iter().zip(y) {
+            max_abs_diff = max_abs_diff.max((p - q).abs());
         }
     }
+
+    let min_cosine = cosines.iter().copied().fold(1.0f32, f32::min);
+    let mean_cosine = if cosines.is_empty() {
+        1.0
+    } else {
+        cosines.iter().sum::<f32>() / cosines.len() as f32
+    };
+
+    Ok(EmbeddingDeviation {
+        cosines,
+        min_cosine,
+        mean_cosine,
+        max_abs_diff,
+    })
 }
 
 /// Binary embedding (32x smaller than float32)
@@ -68,7 +420,7 @@ impl BinaryEmbedding {
     /// Create from float32 embedding using sign-based quantization
     pub fn from_float(embedding: &[f32]) -> Self {
         let dimensions = embedding.len();
-        let byte_count = (dimensions + 7) / 8;
+        let byte_count = dimensions.div_ceil(8);
         let mut bits = vec![0u8; byte_count];
 
         for (i, &value) in embedding.iter().enumerate() {
@@ -82,11 +434,12 @@ impl BinaryEmbedding {
 
     /// Compute Hamming distance to another binary embedding
     pub fn hamming_distance(&self, other: &BinaryEmbedding) -> u32 {
-        self.bits
-            .iter()
-            .zip(other.bits.iter())
-            .map(|(a, b)| (a ^ b).count_ones())
-            .sum()
+        crate::simd::hamming(&self.bits, &other.bits)
+    }
+
+    /// Similarity in [-1, 1] from the Hamming distance (comparable to a cosine)
+    pub fn similarity(&self, other: &BinaryEmbedding) -> f32 {
+        1.0 - 2.0 * self.hamming_distance(other) as f32 / self.dimensions.max(1) as f32
     }
 
     /// Size in bytes
@@ -125,12 +478,7 @@ impl Int8Embedding {
 
     /// Compute dot product with another Int8 embedding (returns approximate score)
     pub fn dot_product(&self, other: &Int8Embedding) -> f32 {
-        let sum: i32 = self.values
-            .iter()
-            .zip(other.values.iter())
-            .map(|(&a, &b)| (a as i32) * (b as i32))
-            .sum();
-
+        let sum = crate::simd::dot_i8(&self.values, &other.values);
         sum as f32 * self.scale * other.scale
     }
 
@@ -140,6 +488,100 @@ impl Int8Embedding {
     }
 }
 
+/// Options for embedding inference
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddingOptions {
+    /// Abort a single inference run after this long (`CxpError::Timeout`)
+    pub timeout: Option<Duration>,
+}
+
+impl EmbeddingOptions {
+    /// Set the inference timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// Default number of texts per inference run
+pub const DEFAULT_BATCH_SIZE: usize = 32;
+
+/// Largest batch size `BatchSizer` grows to
+pub const MAX_BATCH_SIZE: usize = 1024;
+
+/// Successful batches in a row before `BatchSizer` doubles the batch size
+const GROW_AFTER: usize = 8;
+
+/// Adapts the number of texts per inference run to available memory
+///
+/// Starts at a configured size, halves it when a batch runs out of memory
+/// (never growing back past a size that failed) and doubles it after a
+/// streak of successful batches.
+///
+/// ```ignore
+/// let mut sizer = BatchSizer::new(32);
+/// let mut rest = &texts[..];
+/// while !rest.is_empty() {
+///     let batch = &rest[..sizer.size().min(rest.len())];
+///     let Some(embeddings) = sizer.attempt(engine.embed_batch(batch))? else { continue };
+///     rest = &rest[batch.len()..];
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BatchSizer {
+    size: usize,
+    ceiling: usize,
+    streak: usize,
+}
+
+impl BatchSizer {
+    /// Start at `size` texts per batch (at least 1)
+    pub fn new(size: usize) -> Self {
+        let size = size.clamp(1, MAX_BATCH_SIZE);
+        Self { size, ceiling: MAX_BATCH_SIZE, streak: 0 }
+    }
+
+    /// Texts to put in the next batch
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Record the result of a batch of `size()` texts
+    ///
+    /// Returns `Ok(None)` if it ran out of memory and should be retried with
+    /// the (now smaller) `size()`; other errors, and running out of memory
+    /// with a single text, are returned as is.
+    pub fn attempt<T>(&mut self, result: crate::Result<T>) -> crate::Result<Option<T>> {
+        match result {
+            Ok(value) => {
+                self.streak += 1;
+                if self.streak >= GROW_AFTER && self.size < self.ceiling {
+                    self.size = (self.size * 2).min(self.ceiling);
+                    self.streak = 0;
+                    tracing::debug!("Embedding batch size raised to {}", self.size);
+                }
+                Ok(Some(value))
+            }
+            Err(e) if self.size > 1 && is_out_of_memory(&e) => {
+                self.ceiling = self.size / 2;
+                self.size = self.ceiling;
+                self.streak = 0;
+                tracing::warn!("Out of memory during embedding, retrying with batch size {}", self.size);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Whether an inference error is an allocation failure (CPU or GPU)
+pub fn is_out_of_memory(error: &crate::CxpError) -> bool {
+    let message = error.to_string().to_lowercase();
+    ["out of memory", "failed to allocate", "bad_alloc", "allocation failed", "memoryallocation"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
 /// Embedding engine for generating embeddings using ONNX
 #[cfg(feature = "embeddings")]
 pub struct EmbeddingEngine {
@@ -151,103 +593,200 @@ pub struct EmbeddingEngine {
     model: EmbeddingModel,
     /// Maximum sequence length
     max_length: usize,
+    /// Pooling strategy for token-level outputs
+    pooling: Pooling,
+    /// Inference options (timeout)
+    options: EmbeddingOptions,
+    /// Device the session runs on
+    device: Device,
+    /// Path of the ONNX weights
+    model_path: std::path::PathBuf,
+    /// Tokens shared by consecutive windows of a long input
+    window_overlap: usize,
 }
 
 #[cfg(feature = "embeddings")]
 impl EmbeddingEngine {
-    /// Load an embedding model from a directory
+    /// Load an embedding model from a directory (runs on the CPU)
     pub fn load<P: AsRef<Path>>(model_dir: P, model: EmbeddingModel) -> Result<Self> {
+        Self::load_with(model_dir, model, Device::Cpu)
+    }
+
+    /// Load an embedding model to run on `device`, falling back to the CPU
+    /// if the device is not available
+    pub fn load_with<P: AsRef<Path>>(model_dir: P, model: EmbeddingModel, device: Device) -> Result<Self> {
         let model_dir = model_dir.as_ref();
 
         // Load ONNX model
         let model_path = model_dir.join("model.onnx");
-        let session = Session::builder()?
-            .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
-            .with_intra_threads(num_cpus::get())?
-            .commit_from_file(&model_path)?;
+        let (builder, device) = session_builder(device)?;
+        let session = builder.commit_from_file(&model_path)?;
 
         // Load tokenizer
         let tokenizer_path = model_dir.join("tokenizer.json");
-        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
             .map_err(|e| CxpError::Embedding(format!("Failed to load tokenizer: {}", e)))?;
+        configure_windows(&mut tokenizer, model.spec().max_length, DEFAULT_WINDOW_OVERLAP)?;
 
         Ok(Self {
             session,
             tokenizer,
             model,
-            max_length: 512,
+            max_length: model.spec().max_length,
+            pooling: model.spec().pooling,
+            options: EmbeddingOptions::default(),
+            device,
+            model_path,
+            window_overlap: DEFAULT_WINDOW_OVERLAP,
         })
     }
 
+    /// Revision of the loaded weights: the first 16 hex digits of the SHA-256 of `model.onnx`
+    pub fn revision(&self) -> Result<String> {
+        model_revision(&self.model_path)
+    }
+
+    /// Device the model runs on (`Device::Cpu` after a fallback)
+    pub fn device(&self) -> Device {
+        self.device
+    }
+
+    /// Set inference options
+    pub fn with_options(mut self, options: EmbeddingOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set the pooling strategy (used when the model outputs token embeddings)
+    pub fn with_pooling(mut self, pooling: Pooling) -> Self {
+        self.pooling = pooling;
+        self
+    }
+
+    /// Set the maximum sequence length in tokens (the window size for long inputs)
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.set_windows(max_length, self.window_overlap);
+        self
+    }
+
+    /// Set how many tokens consecutive windows of a long input share
+    pub fn with_window_overlap(mut self, overlap: usize) -> Self {
+        self.set_windows(self.max_length, overlap);
+        self
+    }
+
+    /// Reconfigure the tokenizer, keeping the current windows if that fails
+    fn set_windows(&mut self, max_length: usize, overlap: usize) {
+        match configure_windows(&mut self.tokenizer, max_length, overlap) {
+            Ok(()) => (self.max_length, self.window_overlap) = (max_length, overlap),
+            Err(e) => tracing::warn!("{}", e),
+        }
+    }
+
+    /// Get the pooling strategy
+    pub fn pooling(&self) -> Pooling {
+        self.pooling
+    }
+
+    /// Get the maximum sequence length
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// Get the window overlap in tokens
+    pub fn window_overlap(&self) -> usize {
+        self.window_overlap
+    }
+
     /// Generate embeddings for a batch of texts
+    ///
+    /// Texts longer than `max_length()` tokens are embedded in overlapping
+    /// windows and get the mean of the window embeddings.
     pub fn embed_batch(&mut self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
         if texts.is_empty() {
             return Ok(Vec::new());
         }
 
         // Tokenize
-        let encodings = self.tokenizer
-            .encode_batch(texts.to_vec(), true)
-            .map_err(|e| CxpError::Embedding(format!("Tokenization failed: {}", e)))?;
+        let EncodedBatch { input_ids: ids, attention_mask: mask, seq_len, windows } =
+            encode_inputs(&self.tokenizer, texts, self.max_length)?;
+        let batch_size: usize = windows.iter().sum();
 
-        // Prepare input tensors
-        let batch_size = encodings.len();
-        let seq_len = encodings.iter().map(|e| e.len()).max().unwrap_or(0).min(self.max_length);
+        let input_ids = Array2::from_shape_vec((batch_size, seq_len), ids)
+            .map_err(|e| CxpError::Embedding(format!("Failed to create input_ids tensor: {}", e)))?;
+        let attention_mask = Array2::from_shape_vec((batch_size, seq_len), mask.clone())
+            .map_err(|e| CxpError::Embedding(format!("Failed to create attention_mask tensor: {}", e)))?;
 
-        let mut input_ids = Array2::<i64>::zeros((batch_size, seq_len));
-        let mut attention_mask = Array2::<i64>::zeros((batch_size, seq_len));
-
-        for (i, encoding) in encodings.iter().enumerate() {
-            let ids = encoding.get_ids();
-            let mask = encoding.get_attention_mask();
-            let len = ids.len().min(seq_len);
-
-            for j in 0..len {
-                input_ids[[i, j]] = ids[j] as i64;
-                attention_mask[[i, j]] = mask[j] as i64;
-            }
+        // BERT-style exports also take segment IDs (all zero for a single sentence)
+        let mut inputs = ort::inputs![
+            "input_ids" => ort::value::Value::from_array(input_ids)?,
+            "attention_mask" => ort::value::Value::from_array(attention_mask)?,
+        ];
+        if self.session.inputs.iter().any(|input| input.name == "token_type_ids") {
+            let token_type_ids = Array2::<i64>::zeros((batch_size, seq_len));
+            inputs.push(("token_type_ids".into(), ort::value::Value::from_array(token_type_ids)?.into()));
         }
 
-        // Run inference
-        let input_ids_value = ort::value::Value::from_array(input_ids)?;
-        let attention_mask_value = ort::value::Value::from_array(attention_mask)?;
-        let outputs = self.session.run(ort::inputs![
-            "input_ids" => input_ids_value,
-            "attention_mask" => attention_mask_value,
-        ])?;
+        // Run inference; with a timeout a watchdog thread terminates the run
+        let run_options = RunOptions::new()?;
+        let timed_out = AtomicBool::new(false);
+        let timeout = self.options.timeout;
+        let session = &mut self.session;
+        let outputs = std::thread::scope(|scope| {
+            let (done, finished) = mpsc::channel::<()>();
+            if let Some(timeout) = timeout {
+                let (run_options, timed_out) = (&run_options, &timed_out);
+                scope.spawn(move || {
+                    if finished.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                        timed_out.store(true, Ordering::Relaxed);
+                        let _ = run_options.terminate();
+                    }
+                });
+            }
+            let outputs = session.run_with_options(inputs, &run_options);
+            drop(done);
+            outputs
+        });
+        let outputs = outputs.map_err(|e| {
+            if timed_out.load(Ordering::Relaxed) {
+                CxpError::Timeout(format!("embedding inference ({} texts)", texts.len()))
+            } else {
+                e.into()
+            }
+        })?;
 
         // Extract embeddings (try sentence_embedding first, then last_hidden_state)
-        let embeddings = if let Some(output) = outputs.get("sentence_embedding") {
+        let rows = if let Some(output) = outputs.get("sentence_embedding") {
             // Already pooled sentence embeddings
-            output.try_extract_array::<f32>()?
+            let pooled = output.try_extract_array::<f32>()?
                 .into_dimensionality::<ndarray::Ix2>()
                 .map_err(|e| CxpError::Embedding(format!("Failed to convert to 2D: {}", e)))?
-                .to_owned()
+                .to_owned();
+            pooled.outer_iter().map(|row| row.to_vec()).collect()
         } else if let Some(output) = outputs.get("last_hidden_state") {
             // Need to pool over sequence dimension
             let hidden = output.try_extract_array::<f32>()?;
-            let shape = hidden.shape();
-
-            if shape.len() == 3 {
-                // batch x seq x hidden - need mean pooling
-                let arr3d = hidden.into_dimensionality::<ndarray::Ix3>()
-                    .map_err(|e| CxpError::Embedding(format!("Failed to convert to 3D: {}", e)))?;
-                arr3d.mean_axis(Axis(1))
-                    .ok_or_else(|| CxpError::Embedding("Mean pooling failed".into()))?
-                    .to_owned()
-            } else if shape.len() == 2 {
+            let shape = hidden.shape().to_vec();
+
+            match shape.as_slice() {
+                // batch x seq x hidden - pool with the attention mask
+                [b, s, d] if *b == batch_size && *s == seq_len => {
+                    let data: Vec<f32> = hidden.iter().copied().collect();
+                    pool_token_embeddings(&data, &mask, batch_size, seq_len, *d, self.pooling)
+                }
                 // Already batch x hidden
-                hidden.into_dimensionality::<ndarray::Ix2>()
-                    .map_err(|e| CxpError::Embedding(format!("Failed to convert to 2D: {}", e)))?
-                    .to_owned()
-            } else {
-                return Err(CxpError::Embedding(format!("Unexpected output shape: {:?}", shape)));
+                [_, _] => {
+                    let pooled = hidden.into_dimensionality::<ndarray::Ix2>()
+                        .map_err(|e| CxpError::Embedding(format!("Failed to convert to 2D: {}", e)))?;
+                    pooled.outer_iter().map(|row| row.to_vec()).collect()
+                }
+                _ => return Err(CxpError::Embedding(format!("Unexpected output shape: {:?}", shape))),
             }
         } else {
             return Err(CxpError::Embedding("No embedding output found".into()));
         };
 
-        Ok(embeddings.outer_iter().map(|row| row.to_vec()).collect())
+        Ok(pool_windows(rows, &windows))
     }
 
     /// Generate embedding for a single text
@@ -257,6 +796,24 @@ impl EmbeddingEngine {
             .ok_or_else(|| CxpError::Embedding("No embedding generated".into()))
     }
 
+    /// Embed search queries, with the model's query prefix
+    pub fn embed_queries(&mut self, queries: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let prefixed = with_prefix(self.model.spec().query_prefix, queries);
+        self.embed_batch(&prefixed.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    /// Embed one search query, with the model's query prefix
+    pub fn embed_query(&mut self, query: &str) -> Result<Vec<f32>> {
+        self.embed_queries(&[query])?.into_iter().next()
+            .ok_or_else(|| CxpError::Embedding("No embedding generated".into()))
+    }
+
+    /// Embed documents to be indexed, with the model's passage prefix
+    pub fn embed_passages(&mut self, passages: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let prefixed = with_prefix(self.model.spec().passage_prefix, passages);
+        self.embed_batch(&prefixed.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
     /// Generate binary embeddings for a batch
     pub fn embed_binary_batch(&mut self, texts: &[&str]) -> Result<Vec<BinaryEmbedding>> {
         let embeddings = self.embed_batch(texts)?;
@@ -280,6 +837,23 @@ impl EmbeddingEngine {
     }
 }
 
+/// Prepend `prefix` to every text
+#[cfg(feature = "embeddings")]
+fn with_prefix(prefix: &str, texts: &[&str]) -> Vec<String> {
+    texts.iter().map(|text| format!("{}{}", prefix, text)).collect()
+}
+
+/// Revision of an ONNX model file: the first 16 hex digits of its SHA-256
+#[cfg(feature = "embeddings")]
+pub fn model_revision(model_path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(model_path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize())[..16].to_string())
+}
+
 /// Batch embedding results with both binary and int8 representations
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct QuantizedEmbeddings {
@@ -287,6 +861,9 @@ pub struct QuantizedEmbeddings {
     pub binary: Vec<BinaryEmbedding>,
     /// Int8 embeddings for rescoring
     pub int8: Vec<Int8Embedding>,
+    /// Float embeddings for rescoring (f16/f32 precision only, otherwise empty)
+    #[serde(default)]
+    pub float: Vec<Vec<f32>>,
 }
 
 impl QuantizedEmbeddings {
@@ -294,14 +871,31 @@ impl QuantizedEmbeddings {
     pub fn from_floats(embeddings: &[Vec<f32>]) -> Self {
         let binary = embeddings.iter().map(|e| BinaryEmbedding::from_float(e)).collect();
         let int8 = embeddings.iter().map(|e| Int8Embedding::from_float(e)).collect();
-        Self { binary, int8 }
+        Self { binary, int8, float: Vec::new() }
+    }
+
+    /// Create from float embeddings, keeping the floats for f16/f32 precision
+    ///
+    /// F16 values are rounded to half precision, so they match what is stored.
+    pub fn with_precision(embeddings: &[Vec<f32>], precision: EmbeddingPrecision) -> Self {
+        let mut quantized = Self::from_floats(embeddings);
+        quantized.float = match precision {
+            EmbeddingPrecision::F32 => embeddings.to_vec(),
+            EmbeddingPrecision::F16 => embeddings
+                .iter()
+                .map(|e| e.iter().map(|&v| half::f16::from_f32(v).to_f32()).collect())
+                .collect(),
+            _ => Vec::new(),
+        };
+        quantized
     }
 
     /// Total size in bytes
     pub fn size_bytes(&self) -> usize {
         let binary_size: usize = self.binary.iter().map(|e| e.size_bytes()).sum();
         let int8_size: usize = self.int8.iter().map(|e| e.size_bytes()).sum();
-        binary_size + int8_size
+        let float_size: usize = self.float.iter().map(|e| e.len() * 4).sum();
+        binary_size + int8_size + float_size
     }
 }
 
@@ -309,6 +903,57 @@ impl QuantizedEmbeddings {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_model_from_name() {
+        for model in EmbeddingModel::ALL {
+            assert_eq!(EmbeddingModel::from_name(model.name()), Some(model));
+            assert_eq!(model.spec().key.parse::<EmbeddingModel>(), Ok(model));
+        }
+        assert_eq!(EmbeddingModel::from_name("embeddinggemma"), Some(EmbeddingModel::EmbeddingGemma));
+        assert_eq!(EmbeddingModel::from_name("unknown"), None);
+        assert_eq!("BGE-SMALL".parse::<EmbeddingModel>(), Ok(EmbeddingModel::BgeSmall));
+        assert!("bert".parse::<EmbeddingModel>().unwrap_err().contains("e5-small"));
+
+        let e5 = EmbeddingModel::E5Small.spec();
+        assert_eq!((e5.query_prefix, e5.passage_prefix), ("query: ", "passage: "));
+        assert_eq!(EmbeddingModel::BgeBase.spec().pooling, Pooling::Cls);
+        assert_eq!(EmbeddingModel::NomicEmbed.dimensions(), 768);
+    }
+
+    #[test]
+    fn test_batch_sizer() {
+        let oom = || Err::<(), _>(crate::CxpError::Embedding("Failed to allocate memory for requested buffer".into()));
+        let mut sizer = BatchSizer::new(32);
+
+        assert_eq!(sizer.attempt(oom()).unwrap(), None);
+        assert_eq!(sizer.size(), 16);
+
+        // Grows after a streak of successes, but not past a size that failed
+        for _ in 0..GROW_AFTER * 4 {
+            sizer.attempt(Ok(())).unwrap();
+        }
+        assert_eq!(sizer.size(), 16);
+
+        let mut sizer = BatchSizer::new(1);
+        for _ in 0..GROW_AFTER {
+            sizer.attempt(Ok(())).unwrap();
+        }
+        assert_eq!(sizer.size(), 2);
+        sizer.attempt(oom()).unwrap();
+        assert_eq!(sizer.size(), 1);
+        assert!(sizer.attempt(oom()).is_err());
+        assert!(sizer.attempt(Err::<(), _>(crate::CxpError::Embedding("bad input".into()))).is_err());
+    }
+
+    #[test]
+    fn test_pool_windows() {
+        let rows = vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 6.0], vec![-1.0, 0.5]];
+        // Text 0 has three windows, text 1 one
+        let pooled = pool_windows(rows, &[3, 1]);
+        assert_eq!(pooled, vec![vec![3.0, 4.0], vec![-1.0, 0.5]]);
+        assert!(pool_windows(Vec::new(), &[]).is_empty());
+    }
+
     #[test]
     fn test_binary_quantization() {
         let embedding = vec![0.5, -0.3, 0.1, -0.8, 0.0, 0.2, -0.1, 0.9];
@@ -327,6 +972,9 @@ mod tests {
 
         let distance = emb1.hamming_distance(&emb2);
         assert_eq!(distance, 4); // 4 bits differ
+
+        assert_eq!(emb1.similarity(&emb1), 1.0);
+        assert_eq!(emb1.similarity(&emb2), 0.0);
     }
 
     #[test]
@@ -349,6 +997,50 @@ mod tests {
         assert!((dot - 1.0).abs() < 0.1);
     }
 
+    #[test]
+    fn test_mean_pooling_ignores_padding() {
+        // batch 2, seq 3, hidden 2; second row has one padding token
+        let hidden = vec![
+            1.0, 2.0, 3.0, 4.0, 5.0, 6.0,
+            2.0, 2.0, 4.0, 4.0, 100.0, 100.0,
+        ];
+        let mask = vec![1, 1, 1, 1, 1, 0];
+
+        let pooled = pool_token_embeddings(&hidden, &mask, 2, 3, 2, Pooling::Mean);
+
+        assert_eq!(pooled[0], vec![3.0, 4.0]);
+        assert_eq!(pooled[1], vec![3.0, 3.0]);
+    }
+
+    #[test]
+    fn test_cls_pooling_takes_first_token() {
+        let hidden = vec![1.0, 2.0, 3.0, 4.0];
+        let mask = vec![1, 1];
+
+        let pooled = pool_token_embeddings(&hidden, &mask, 1, 2, 2, Pooling::Cls);
+
+        assert_eq!(pooled, vec![vec![1.0, 2.0]]);
+        assert_eq!(Pooling::default(), Pooling::Mean);
+    }
+
+    #[test]
+    fn test_compare_embeddings() {
+        let a = vec![vec![1.0, 0.0], vec![0.0, 2.0]];
+        let b = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+
+        let deviation = compare_embeddings(&a, &b).unwrap();
+        assert!((deviation.min_cosine - 1.0).abs() < 1e-6);
+        assert!((deviation.max_abs_diff - 1.0).abs() < 1e-6);
+
+        let c = vec![vec![1.0, 0.0], vec![0.0, -1.0]];
+        let deviation = compare_embeddings(&a, &c).unwrap();
+        assert!((deviation.min_cosine + 1.0).abs() < 1e-6);
+        assert!(deviation.mean_cosine.abs() < 1e-6);
+
+        assert!(compare_embeddings(&a, &b[..1]).is_err());
+        assert!(compare_embeddings(&[vec![1.0]], &[vec![1.0, 2.0]]).is_err());
+    }
+
     #[test]
     fn test_quantized_embeddings_size() {
         let embeddings = vec![
@@ -7,10 +7,11 @@
 use crate::{CxpError, Result};
 
 #[cfg(feature = "embeddings-wasm")]
-use crate::embeddings::{BinaryEmbedding, Int8Embedding, EmbeddingModel};
+use crate::embeddings::{
+    encode_inputs, pool_token_embeddings, BinaryEmbedding, EmbeddingModel, Int8Embedding,
+    configure_windows, pool_windows, EncodedBatch, Pooling, DEFAULT_WINDOW_OVERLAP,
+};
 
-#[cfg(feature = "embeddings-wasm")]
-use ndarray::{s, Array2, Axis};
 #[cfg(feature = "embeddings-wasm")]
 use tokenizers::Tokenizer;
 #[cfg(feature = "embeddings-wasm")]
@@ -30,6 +31,10 @@ pub struct TractEmbeddingEngine {
     embedding_model: EmbeddingModel,
     /// Maximum sequence length
     max_length: usize,
+    /// Pooling strategy for token-level outputs
+    pooling: Pooling,
+    /// Tokens shared by consecutive windows of a long input
+    window_overlap: usize,
 }
 
 #[cfg(feature = "embeddings-wasm")]
@@ -50,60 +55,80 @@ impl TractEmbeddingEngine {
 
         // Load tokenizer
         let tokenizer_path = model_dir.join("tokenizer.json");
-        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
             .map_err(|e| CxpError::Embedding(format!("Failed to load tokenizer: {}", e)))?;
+        configure_windows(&mut tokenizer, model.spec().max_length, DEFAULT_WINDOW_OVERLAP)?;
 
         Ok(Self {
             model: tract_model,
             tokenizer,
             embedding_model: model,
-            max_length: 512,
+            max_length: model.spec().max_length,
+            pooling: model.spec().pooling,
+            window_overlap: DEFAULT_WINDOW_OVERLAP,
         })
     }
 
-    /// Generate embeddings for a batch of texts
-    pub fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
-        if texts.is_empty() {
-            return Ok(Vec::new());
-        }
+    /// Set the pooling strategy (used when the model outputs token embeddings)
+    pub fn with_pooling(mut self, pooling: Pooling) -> Self {
+        self.pooling = pooling;
+        self
+    }
 
-        // Tokenize
-        let encodings = self.tokenizer
-            .encode_batch(texts.to_vec(), true)
-            .map_err(|e| CxpError::Embedding(format!("Tokenization failed: {}", e)))?;
+    /// Set the maximum sequence length in tokens
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.set_windows(max_length, self.window_overlap);
+        self
+    }
 
-        // Prepare input tensors
-        let batch_size = encodings.len();
-        let seq_len = encodings.iter().map(|e| e.len()).max().unwrap_or(0).min(self.max_length);
+    /// Set how many tokens consecutive windows of a long input share
+    pub fn with_window_overlap(mut self, overlap: usize) -> Self {
+        self.set_windows(self.max_length, overlap);
+        self
+    }
 
-        let mut input_ids = Array2::<i64>::zeros((batch_size, seq_len));
-        let mut attention_mask = Array2::<i64>::zeros((batch_size, seq_len));
+    /// Reconfigure the tokenizer, keeping the current windows if that fails
+    fn set_windows(&mut self, max_length: usize, overlap: usize) {
+        match configure_windows(&mut self.tokenizer, max_length, overlap) {
+            Ok(()) => (self.max_length, self.window_overlap) = (max_length, overlap),
+            Err(e) => tracing::warn!("{}", e),
+        }
+    }
 
-        for (i, encoding) in encodings.iter().enumerate() {
-            let ids = encoding.get_ids();
-            let mask = encoding.get_attention_mask();
-            let len = ids.len().min(seq_len);
+    /// Get the pooling strategy
+    pub fn pooling(&self) -> Pooling {
+        self.pooling
+    }
 
-            for j in 0..len {
-                input_ids[[i, j]] = ids[j] as i64;
-                attention_mask[[i, j]] = mask[j] as i64;
-            }
+    /// Get the maximum sequence length
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// Get the window overlap in tokens
+    pub fn window_overlap(&self) -> usize {
+        self.window_overlap
+    }
+
+    /// Generate embeddings for a batch of texts
+    pub fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
         }
 
+        // Tokenize (shared with the ort engine)
+        let EncodedBatch { input_ids: ids, attention_mask: mask, seq_len, windows } =
+            encode_inputs(&self.tokenizer, texts, self.max_length)?;
+        let batch_size: usize = windows.iter().sum();
+
         // Convert to tract tensors
-        let input_ids_tensor = tract_ndarray::Array2::from_shape_vec(
-            (batch_size, seq_len),
-            input_ids.iter().copied().collect(),
-        )
-        .map_err(|e| CxpError::Embedding(format!("Failed to create input_ids tensor: {}", e)))?
-        .into_dyn();
-
-        let attention_mask_tensor = tract_ndarray::Array2::from_shape_vec(
-            (batch_size, seq_len),
-            attention_mask.iter().copied().collect(),
-        )
-        .map_err(|e| CxpError::Embedding(format!("Failed to create attention_mask tensor: {}", e)))?
-        .into_dyn();
+        let input_ids_tensor = tract_ndarray::Array2::from_shape_vec((batch_size, seq_len), ids)
+            .map_err(|e| CxpError::Embedding(format!("Failed to create input_ids tensor: {}", e)))?
+            .into_dyn();
+
+        let attention_mask_tensor = tract_ndarray::Array2::from_shape_vec((batch_size, seq_len), mask.clone())
+            .map_err(|e| CxpError::Embedding(format!("Failed to create attention_mask tensor: {}", e)))?
+            .into_dyn();
 
         // Run inference
         let outputs = self.model
@@ -116,63 +141,34 @@ impl TractEmbeddingEngine {
         // Extract embeddings from output
         // The output is typically the first tensor
         let output_tensor = outputs
-            .get(0)
+            .first()
             .ok_or_else(|| CxpError::Embedding("No output tensor found".into()))?;
 
         let output_array = output_tensor
             .to_array_view::<f32>()
             .map_err(|e| CxpError::Embedding(format!("Failed to convert output to array: {}", e)))?;
 
+        let shape = output_array.shape().to_vec();
+        let data: Vec<f32> = output_array.iter().copied().collect();
+        let dims = self.embedding_model.dimensions();
+
         // Handle different output shapes
-        let result = match output_array.shape() {
+        let rows = match shape.as_slice() {
             // Shape: [batch_size, embedding_dim] - already pooled
-            [b, d] if *b == batch_size && *d == self.embedding_model.dimensions() => {
-                (0..batch_size)
-                    .map(|i| {
-                        output_array
-                            .slice(s![i, ..])
-                            .iter()
-                            .copied()
-                            .collect::<Vec<f32>>()
-                    })
-                    .collect()
-            }
-            // Shape: [batch_size, seq_len, hidden_dim] - needs mean pooling
-            [b, _s, d] if *b == batch_size && *d == self.embedding_model.dimensions() => {
-                (0..batch_size)
-                    .map(|i| {
-                        let sequence = output_array.slice(s![i, .., ..]);
-                        let seq_len_actual = attention_mask.row(i).iter().filter(|&&x| x != 0).count();
-
-                        // Mean pooling over sequence dimension
-                        let mut pooled = vec![0.0f32; *d];
-                        for t in 0..seq_len_actual {
-                            for dim in 0..*d {
-                                pooled[dim] += sequence[[t, dim]];
-                            }
-                        }
-
-                        if seq_len_actual > 0 {
-                            for val in pooled.iter_mut() {
-                                *val /= seq_len_actual as f32;
-                            }
-                        }
-
-                        pooled
-                    })
-                    .collect()
+            [b, d] if *b == batch_size && *d == dims => {
+                data.chunks(*d).map(|row| row.to_vec()).collect()
             }
-            shape => {
-                return Err(CxpError::Embedding(format!(
-                    "Unexpected output shape: {:?}, expected [batch_size, {}] or [batch_size, seq_len, {}]",
-                    shape,
-                    self.embedding_model.dimensions(),
-                    self.embedding_model.dimensions()
-                )));
+            // Shape: [batch_size, seq_len, hidden_dim] - pool with the attention mask
+            [b, s, d] if *b == batch_size && *s == seq_len && *d == dims => {
+                pool_token_embeddings(&data, &mask, batch_size, seq_len, *d, self.pooling)
             }
+            shape => return Err(CxpError::Embedding(format!(
+                "Unexpected output shape: {:?}, expected [batch_size, {}] or [batch_size, seq_len, {}]",
+                shape, dims, dims
+            ))),
         };
 
-        Ok(result)
+        Ok(pool_windows(rows, &windows))
     }
 
     /// Generate embedding for a single text
@@ -219,6 +215,8 @@ mod tests {
         fn _verify_api(engine: &TractEmbeddingEngine) {
             let _ = engine.model();
             let _ = engine.dimensions();
+            let _ = engine.pooling();
+            let _ = engine.max_length();
         }
     }
 }
@@ -1,12 +1,30 @@
 //! Error types for CXP operations
+//!
+//! Failures callers commonly handle (a missing chunk, an index of the wrong
+//! dimension, an archive needing a newer reader) have their own variants
+//! with structured fields; the rest carry a message. Every error maps to a
+//! stable [`ErrorCode`] for FFI and other non-Rust consumers, and errors
+//! caused by another error expose it through `source()`:
+//!
+//! ```ignore
+//! match reader.read_chunk(hash) {
+//!     Err(CxpError::ChunkMissing { hash }) => repair(&hash),
+//!     Err(e) => eprintln!("[{}] {}", e.code().name(), e),
+//!     Ok(data) => use_chunk(data),
+//! }
+//! ```
 
 use thiserror::Error;
 
 /// CXP Error types
 #[derive(Error, Debug)]
 pub enum CxpError {
-    #[error("IO error: {0}")]
-    Io(String),
+    #[error("IO error: {message}")]
+    Io {
+        message: String,
+        #[source]
+        source: Option<std::io::Error>,
+    },
 
     #[error("ZIP error: {0}")]
     Zip(#[from] zip::result::ZipError),
@@ -20,6 +38,12 @@ pub enum CxpError {
     #[error("Chunk error: {0}")]
     Chunk(String),
 
+    #[error("Chunk not found: {hash}")]
+    ChunkMissing { hash: String },
+
+    #[error("Chunk of {path} does not match its hash: expected {expected}, got {actual}")]
+    ChunkHashMismatch { path: String, expected: String, actual: String },
+
     #[error("Manifest error: {0}")]
     Manifest(String),
 
@@ -38,13 +62,195 @@ pub enum CxpError {
     #[error("Index error: {0}")]
     Index(String),
 
+    #[error("Index dimension mismatch: expected {expected}, got {got}")]
+    IndexDimensionMismatch { expected: usize, got: usize },
+
     #[error("Search error: {0}")]
     Search(String),
+
+    #[error("Tokenizer error: {0}")]
+    Tokenizer(String),
+
+    #[error("Watch error: {0}")]
+    Watch(String),
+
+    #[error("Operation cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Operation timed out: {0}")]
+    Timeout(String),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    #[error("Unsupported version: archive requires a CXP reader {required} or newer, this is {current}; please upgrade cxp")]
+    UnsupportedFormatVersion { required: String, current: String },
+
+    #[error("Git error: {0}")]
+    Git(String),
+
+    #[error("{context}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<CxpError>,
+    },
+}
+
+/// Stable numeric error codes (0 means no error)
+///
+/// Codes are never reused or renumbered; new kinds get new numbers.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    Io = 1,
+    Zip = 2,
+    Serialization = 3,
+    InvalidFormat = 4,
+    Chunk = 5,
+    ChunkMissing = 6,
+    ChunkHashMismatch = 7,
+    Manifest = 8,
+    FileNotFound = 9,
+    UnsupportedFileType = 10,
+    Compression = 11,
+    Embedding = 12,
+    Index = 13,
+    IndexDimensionMismatch = 14,
+    Search = 15,
+    Tokenizer = 16,
+    Watch = 17,
+    Cancelled = 18,
+    Timeout = 19,
+    Storage = 20,
+    UnsupportedFormatVersion = 21,
+    Git = 22,
+}
+
+impl ErrorCode {
+    /// Snake-case name, e.g. `chunk_missing`
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCode::Io => "io",
+            ErrorCode::Zip => "zip",
+            ErrorCode::Serialization => "serialization",
+            ErrorCode::InvalidFormat => "invalid_format",
+            ErrorCode::Chunk => "chunk",
+            ErrorCode::ChunkMissing => "chunk_missing",
+            ErrorCode::ChunkHashMismatch => "chunk_hash_mismatch",
+            ErrorCode::Manifest => "manifest",
+            ErrorCode::FileNotFound => "file_not_found",
+            ErrorCode::UnsupportedFileType => "unsupported_file_type",
+            ErrorCode::Compression => "compression",
+            ErrorCode::Embedding => "embedding",
+            ErrorCode::Index => "index",
+            ErrorCode::IndexDimensionMismatch => "index_dimension_mismatch",
+            ErrorCode::Search => "search",
+            ErrorCode::Tokenizer => "tokenizer",
+            ErrorCode::Watch => "watch",
+            ErrorCode::Cancelled => "cancelled",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Storage => "storage",
+            ErrorCode::UnsupportedFormatVersion => "unsupported_format_version",
+            ErrorCode::Git => "git",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl CxpError {
+    /// IO error with a message and no underlying error
+    pub fn io(message: impl Into<String>) -> Self {
+        CxpError::Io {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Wrap this error with a description of what was being done
+    pub fn context(self, context: impl Into<String>) -> Self {
+        CxpError::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The error below any added context
+    pub fn root(&self) -> &CxpError {
+        match self {
+            CxpError::Context { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// The contexts and the error below them on one line
+    ///
+    /// For consumers that show only a message (FFI, JS), since `Display` of a
+    /// context error leaves the cause to `source()`.
+    pub fn full_message(&self) -> String {
+        match self {
+            CxpError::Context { context, source } => format!("{}: {}", context, source.full_message()),
+            other => other.to_string(),
+        }
+    }
+
+    /// Stable code of the error (context is looked through)
+    pub fn code(&self) -> ErrorCode {
+        match self.root() {
+            CxpError::Io { .. } => ErrorCode::Io,
+            CxpError::Zip(_) => ErrorCode::Zip,
+            CxpError::Serialization(_) => ErrorCode::Serialization,
+            CxpError::InvalidFormat(_) => ErrorCode::InvalidFormat,
+            CxpError::Chunk(_) => ErrorCode::Chunk,
+            CxpError::ChunkMissing { .. } => ErrorCode::ChunkMissing,
+            CxpError::ChunkHashMismatch { .. } => ErrorCode::ChunkHashMismatch,
+            CxpError::Manifest(_) => ErrorCode::Manifest,
+            CxpError::FileNotFound(_) => ErrorCode::FileNotFound,
+            CxpError::UnsupportedFileType(_) => ErrorCode::UnsupportedFileType,
+            CxpError::Compression(_) => ErrorCode::Compression,
+            CxpError::Embedding(_) => ErrorCode::Embedding,
+            CxpError::Index(_) => ErrorCode::Index,
+            CxpError::IndexDimensionMismatch { .. } => ErrorCode::IndexDimensionMismatch,
+            CxpError::Search(_) => ErrorCode::Search,
+            CxpError::Tokenizer(_) => ErrorCode::Tokenizer,
+            CxpError::Watch(_) => ErrorCode::Watch,
+            CxpError::Cancelled(_) => ErrorCode::Cancelled,
+            CxpError::Timeout(_) => ErrorCode::Timeout,
+            CxpError::Storage(_) => ErrorCode::Storage,
+            CxpError::UnsupportedFormatVersion { .. } => ErrorCode::UnsupportedFormatVersion,
+            CxpError::Git(_) => ErrorCode::Git,
+            CxpError::Context { .. } => unreachable!("root() looks through context"),
+        }
+    }
 }
 
 /// Result type for CXP operations
 pub type Result<T> = std::result::Result<T, CxpError>;
 
+/// Adds context to the error of a `Result`
+pub trait ResultExt<T> {
+    /// Wrap an error with a description of what was being done
+    fn context(self, context: impl Into<String>) -> Result<T>;
+
+    /// Like `context`, building the description only on error
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T, E: Into<CxpError>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|e| e.into().context(context()))
+    }
+}
+
 impl From<rmp_serde::encode::Error> for CxpError {
     fn from(e: rmp_serde::encode::Error) -> Self {
         CxpError::Serialization(e.to_string())
@@ -65,7 +271,10 @@ impl From<serde_json::Error> for CxpError {
 
 impl From<std::io::Error> for CxpError {
     fn from(e: std::io::Error) -> Self {
-        CxpError::Io(e.to_string())
+        CxpError::Io {
+            message: e.to_string(),
+            source: Some(e),
+        }
     }
 }
 
@@ -76,6 +285,20 @@ impl From<ort::Error> for CxpError {
     }
 }
 
+#[cfg(feature = "cloud")]
+impl From<object_store::Error> for CxpError {
+    fn from(e: object_store::Error) -> Self {
+        CxpError::Storage(e.to_string())
+    }
+}
+
+#[cfg(feature = "git")]
+impl From<git2::Error> for CxpError {
+    fn from(e: git2::Error) -> Self {
+        CxpError::Git(e.message().to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,7 +313,8 @@ mod tests {
     fn test_error_from_io() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
         let cxp_err: CxpError = io_err.into();
-        assert!(matches!(cxp_err, CxpError::Io(_)));
+        assert!(matches!(cxp_err, CxpError::Io { source: Some(_), .. }));
+        assert_eq!(cxp_err.code(), ErrorCode::Io);
     }
 
     #[test]
@@ -126,10 +350,44 @@ mod tests {
             CxpError::Embedding("test".into()),
             CxpError::Index("test".into()),
             CxpError::Search("test".into()),
+            CxpError::Tokenizer("test".into()),
+            CxpError::Watch("test".into()),
+            CxpError::Cancelled("test".into()),
+            CxpError::Timeout("test".into()),
+            CxpError::Storage("test".into()),
+            CxpError::UnsupportedFormatVersion { required: "2.0.0".into(), current: "1.0.0".into() },
+            CxpError::ChunkMissing { hash: "abc".into() },
+            CxpError::ChunkHashMismatch { path: "a.txt".into(), expected: "abc".into(), actual: "def".into() },
+            CxpError::IndexDimensionMismatch { expected: 384, got: 512 },
         ];
 
         for err in errors {
             assert!(!err.to_string().is_empty());
         }
     }
+
+    #[test]
+    fn test_error_context_and_codes() {
+        let err: Result<()> = Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied").into());
+        let err = err.context("writing chunk").unwrap_err().context("building archive");
+        assert_eq!(err.to_string(), "building archive");
+        assert_eq!(err.full_message(), "building archive: writing chunk: IO error: denied");
+        assert_eq!(err.code(), ErrorCode::Io);
+        assert_eq!(err.code() as i32, 1);
+
+        // The chain ends at the io::Error
+        let mut chain = Vec::new();
+        let mut current: Option<&dyn std::error::Error> = Some(&err);
+        while let Some(e) = current {
+            chain.push(e.to_string());
+            current = e.source();
+        }
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain[3], "denied");
+        assert_eq!(chain[..2], ["building archive", "writing chunk"]);
+
+        let err = CxpError::IndexDimensionMismatch { expected: 384, got: 512 };
+        assert_eq!(err.code().name(), "index_dimension_mismatch");
+        assert_eq!(err.to_string(), "Index dimension mismatch: expected 384, got 512");
+    }
 }
@@ -0,0 +1,399 @@
+//! Query Expansion
+//!
+//! Terse queries ("db init", "auth err") embed poorly: they share few tokens
+//! with the code and prose that answers them. A [`QueryExpansion`] turns one
+//! query into several variants before embedding; the variants are searched
+//! separately and fused with Reciprocal Rank Fusion (see
+//! `CxpReader::search_expanded`).
+//!
+//! | Strategy | Variants |
+//! |----------|----------|
+//! | [`NoExpansion`] | the query itself |
+//! | [`SynonymExpansion`] | the query with abbreviations and synonyms swapped in |
+//! | [`HydeExpansion`] | a hypothetical document answering the query (HyDE) |
+//!
+//! HyDE pseudo-documents come from a template by default. For better ones,
+//! pipe the prompt to an external command (any LLM CLI that reads stdin) or
+//! supply a custom generator.
+
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use crate::{CxpError, Result};
+
+/// Default number of variants produced by [`SynonymExpansion`] (including the query)
+pub const DEFAULT_MAX_VARIANTS: usize = 4;
+
+/// Built-in synonym groups for code search
+const CODE_SYNONYMS: &[&[&str]] = &[
+    &["auth", "authentication", "login"],
+    &["config", "configuration", "settings"],
+    &["db", "database"],
+    &["err", "error", "failure"],
+    &["fn", "function", "method"],
+    &["init", "initialize", "setup"],
+    &["env", "environment"],
+    &["deps", "dependencies"],
+    &["msg", "message"],
+    &["req", "request"],
+    &["resp", "response"],
+    &["dir", "directory", "folder"],
+    &["repo", "repository"],
+    &["param", "parameter", "argument"],
+    &["impl", "implementation"],
+    &["test", "spec"],
+    &["delete", "remove"],
+    &["create", "add", "insert"],
+];
+
+/// Words left out of template pseudo-documents
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "do", "does", "for", "how", "i", "in", "is", "it", "of", "on", "or", "the", "to",
+    "what", "where", "which", "with",
+];
+
+/// Turns a query into the variants that are searched
+pub trait QueryExpansion: Send + Sync {
+    /// Query variants to embed and search, the original query first
+    fn expand(&self, query: &str) -> Result<Vec<String>>;
+}
+
+/// Searches the query as given
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoExpansion;
+
+impl QueryExpansion for NoExpansion {
+    fn expand(&self, query: &str) -> Result<Vec<String>> {
+        Ok(vec![query.to_string()])
+    }
+}
+
+/// Adds variants with abbreviations and synonyms replaced
+#[derive(Debug, Clone)]
+pub struct SynonymExpansion {
+    groups: Vec<Vec<String>>,
+    max_variants: usize,
+}
+
+impl Default for SynonymExpansion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SynonymExpansion {
+    /// Expansion with the built-in code synonyms
+    pub fn new() -> Self {
+        Self {
+            groups: CODE_SYNONYMS
+                .iter()
+                .map(|group| group.iter().map(|word| word.to_string()).collect())
+                .collect(),
+            max_variants: DEFAULT_MAX_VARIANTS,
+        }
+    }
+
+    /// Add a group of interchangeable words (matched case-insensitively)
+    pub fn with_synonyms<I, S>(mut self, words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let group: Vec<String> = words.into_iter().map(|word| word.into().to_lowercase()).collect();
+        if group.len() > 1 {
+            self.groups.push(group);
+        }
+        self
+    }
+
+    /// Limit the number of variants (including the original query)
+    pub fn with_max_variants(mut self, max_variants: usize) -> Self {
+        self.max_variants = max_variants.max(1);
+        self
+    }
+
+    /// Synonyms of a word, without the word itself
+    fn synonyms(&self, word: &str) -> Vec<&str> {
+        self.groups
+            .iter()
+            .filter(|group| group.iter().any(|w| w == word))
+            .flatten()
+            .filter(|w| *w != word)
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+impl QueryExpansion for SynonymExpansion {
+    fn expand(&self, query: &str) -> Result<Vec<String>> {
+        let words: Vec<&str> = query.split_whitespace().collect();
+        let mut variants = vec![query.to_string()];
+
+        // One substitution per variant, walking the query left to right
+        'words: for (i, word) in words.iter().enumerate() {
+            let key = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+            for synonym in self.synonyms(&key) {
+                if variants.len() >= self.max_variants {
+                    break 'words;
+                }
+                let mut replaced = words.clone();
+                replaced[i] = synonym;
+                let variant = replaced.join(" ");
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+            }
+        }
+
+        Ok(variants)
+    }
+}
+
+/// Produces a pseudo-document for a query
+pub type HydeGenerator = Arc<dyn Fn(&str) -> Result<String> + Send + Sync>;
+
+/// Where HyDE pseudo-documents come from
+#[derive(Clone)]
+enum Generator {
+    Template,
+    Command(Vec<String>),
+    Custom(HydeGenerator),
+}
+
+/// Hypothetical Document Embeddings
+///
+/// Searches for a generated passage that answers the query, in addition to
+/// the query itself: answers look more like other answers than like questions.
+#[derive(Clone)]
+pub struct HydeExpansion {
+    generator: Generator,
+    keep_query: bool,
+}
+
+impl fmt::Debug for HydeExpansion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let generator = match &self.generator {
+            Generator::Template => "template".to_string(),
+            Generator::Command(command) => format!("command {:?}", command),
+            Generator::Custom(_) => "custom".to_string(),
+        };
+        f.debug_struct("HydeExpansion")
+            .field("generator", &generator)
+            .field("keep_query", &self.keep_query)
+            .finish()
+    }
+}
+
+impl Default for HydeExpansion {
+    fn default() -> Self {
+        Self::template()
+    }
+}
+
+impl HydeExpansion {
+    /// Pseudo-documents from a built-in template (no LLM needed)
+    pub fn template() -> Self {
+        Self {
+            generator: Generator::Template,
+            keep_query: true,
+        }
+    }
+
+    /// Pseudo-documents from an external command
+    ///
+    /// The prompt is written to the command's stdin and its stdout is used as
+    /// the pseudo-document, e.g. `["ollama", "run", "llama3"]`.
+    pub fn command<I, S>(command: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let command: Vec<String> = command.into_iter().map(Into::into).collect();
+        if command.is_empty() {
+            return Err(CxpError::InvalidFormat("HyDE command is empty".to_string()));
+        }
+        Ok(Self {
+            generator: Generator::Command(command),
+            keep_query: true,
+        })
+    }
+
+    /// Pseudo-documents from a custom generator (e.g. an LLM API client)
+    pub fn with_generator<F>(generator: F) -> Self
+    where
+        F: Fn(&str) -> Result<String> + Send + Sync + 'static,
+    {
+        Self {
+            generator: Generator::Custom(Arc::new(generator)),
+            keep_query: true,
+        }
+    }
+
+    /// Whether the original query is searched too (default: true)
+    pub fn with_keep_query(mut self, keep_query: bool) -> Self {
+        self.keep_query = keep_query;
+        self
+    }
+
+    /// Prompt sent to external generators
+    pub fn prompt(query: &str) -> String {
+        format!(
+            "Write a short passage of source code or documentation that answers the question below. \
+             Reply with the passage only.\n\nQuestion: {}\n",
+            query
+        )
+    }
+
+    /// Generate the pseudo-document for a query
+    pub fn generate(&self, query: &str) -> Result<String> {
+        match &self.generator {
+            Generator::Template => Ok(template_document(query)),
+            Generator::Command(command) => run_command(command, &Self::prompt(query)),
+            Generator::Custom(generator) => generator(query),
+        }
+    }
+}
+
+impl QueryExpansion for HydeExpansion {
+    fn expand(&self, query: &str) -> Result<Vec<String>> {
+        let document = self.generate(query)?;
+        let document = document.trim();
+        if document.is_empty() {
+            return Err(CxpError::Search("HyDE generator returned an empty document".to_string()));
+        }
+
+        let mut variants = Vec::with_capacity(2);
+        if self.keep_query {
+            variants.push(query.to_string());
+        }
+        variants.push(document.to_string());
+        Ok(variants)
+    }
+}
+
+/// Code-shaped pseudo-document: the query as a doc comment above a function stub
+fn template_document(query: &str) -> String {
+    let keywords: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .map(str::to_lowercase)
+        .filter(|word| !word.is_empty() && !STOPWORDS.contains(&word.as_str()))
+        .collect();
+    let name = if keywords.is_empty() { "handle".to_string() } else { keywords.join("_") };
+
+    format!(
+        "/// {query}\n///\n/// Handles {keywords}.\nfn {name}() {{\n    // {keywords}\n}}\n",
+        query = query.trim(),
+        keywords = keywords.join(" "),
+        name = name,
+    )
+}
+
+/// Run a generator command with the prompt on stdin
+fn run_command(command: &[String], prompt: &str) -> Result<String> {
+    let mut child = Command::new(&command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| CxpError::io(format!("Failed to run HyDE command '{}': {}", command[0], e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(prompt.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(CxpError::Search(format!(
+            "HyDE command '{}' failed ({}): {}",
+            command[0],
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Named expansion strategies (for CLIs and config files)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExpansionKind {
+    /// No expansion
+    #[default]
+    None,
+    /// [`SynonymExpansion`] with the built-in synonyms
+    Synonyms,
+    /// [`HydeExpansion`]
+    Hyde,
+}
+
+impl std::str::FromStr for ExpansionKind {
+    type Err = CxpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" | "off" => Ok(Self::None),
+            "synonyms" | "synonym" => Ok(Self::Synonyms),
+            "hyde" => Ok(Self::Hyde),
+            _ => Err(CxpError::InvalidFormat(format!(
+                "Unknown query expansion '{}' (expected none, synonyms or hyde)",
+                s
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synonym_expansion() {
+        let expansion = SynonymExpansion::new();
+        let variants = expansion.expand("db init").unwrap();
+        assert_eq!(variants, vec!["db init", "database init", "db initialize", "db setup"]);
+
+        assert_eq!(expansion.expand("parse tokens").unwrap(), vec!["parse tokens"]);
+
+        let custom = SynonymExpansion::new()
+            .with_synonyms(["Cache", "memo"])
+            .with_max_variants(2);
+        assert_eq!(custom.expand("cache auth").unwrap(), vec!["cache auth", "memo auth"]);
+        assert_eq!(NoExpansion.expand("cache").unwrap(), vec!["cache"]);
+    }
+
+    #[test]
+    fn test_hyde_expansion() {
+        let variants = HydeExpansion::template().expand("how is retry backoff done?").unwrap();
+        assert_eq!(variants.len(), 2);
+        assert_eq!(variants[0], "how is retry backoff done?");
+        assert!(variants[1].contains("fn retry_backoff_done()"));
+
+        let custom = HydeExpansion::with_generator(|query| Ok(format!("answer to {}", query)))
+            .with_keep_query(false);
+        assert_eq!(custom.expand("x").unwrap(), vec!["answer to x"]);
+
+        let empty = HydeExpansion::with_generator(|_| Ok("  \n".to_string()));
+        assert!(empty.expand("x").is_err());
+        assert!(HydeExpansion::command(Vec::<String>::new()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hyde_command() {
+        // `cat` echoes the prompt back as the pseudo-document
+        let variants = HydeExpansion::command(["cat"]).unwrap().expand("retry logic").unwrap();
+        assert!(variants[1].contains("Question: retry logic"));
+
+        assert!(HydeExpansion::command(["false"]).unwrap().expand("x").is_err());
+    }
+
+    #[test]
+    fn test_expansion_kind() {
+        assert_eq!("HyDE".parse::<ExpansionKind>().unwrap(), ExpansionKind::Hyde);
+        assert_eq!("synonyms".parse::<ExpansionKind>().unwrap(), ExpansionKind::Synonyms);
+        assert_eq!("none".parse::<ExpansionKind>().unwrap(), ExpansionKind::None);
+        assert!("llm".parse::<ExpansionKind>().is_err());
+    }
+}
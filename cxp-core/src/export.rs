@@ -0,0 +1,398 @@
+//! Record Export
+//!
+//! Flattens an archive into one record per chunk or per file - path, hash,
+//! text and optionally the embedding - so packs can feed vector-DB ingestion
+//! or analytics jobs:
+//!
+//! - JSON Lines (always available): one JSON object per line
+//! - Parquet (feature `arrow`): one row per record, zstd-compressed
+//!
+//! Images are skipped; other binary content is exported as lossy UTF-8.
+
+use crate::chunker::compute_hash;
+use crate::{CxpError, CxpReader, Result};
+use serde::Serialize;
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Output format of an export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// JSON Lines
+    #[default]
+    Jsonl,
+    /// Apache Parquet (requires the `arrow` feature)
+    Parquet,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = CxpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "jsonl" | "ndjson" => Ok(Self::Jsonl),
+            "parquet" => Ok(Self::Parquet),
+            _ => Err(CxpError::InvalidFormat(format!(
+                "Unknown export format '{}' (expected jsonl or parquet)",
+                s
+            ))),
+        }
+    }
+}
+
+/// What one record stands for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportGranularity {
+    /// One record per chunk of every file
+    #[default]
+    Chunk,
+    /// One record per file
+    File,
+}
+
+impl std::str::FromStr for ExportGranularity {
+    type Err = CxpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "chunk" | "chunks" => Ok(Self::Chunk),
+            "file" | "files" => Ok(Self::File),
+            _ => Err(CxpError::InvalidFormat(format!(
+                "Unknown export granularity '{}' (expected chunk or file)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Options of an export
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// One record per chunk or per file
+    pub granularity: ExportGranularity,
+    /// Include embeddings (requires `load_embeddings()` on the reader)
+    pub embeddings: bool,
+}
+
+impl ExportOptions {
+    /// One record per chunk, without embeddings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set what one record stands for
+    pub fn with_granularity(mut self, granularity: ExportGranularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    /// Include embeddings (file records get the mean of their chunk vectors)
+    pub fn with_embeddings(mut self, embeddings: bool) -> Self {
+        self.embeddings = embeddings;
+        self
+    }
+}
+
+/// One exported chunk or file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportRecord {
+    /// Unique ID (`path` for files, `path#index` for chunks)
+    pub id: String,
+    /// File path within the archive
+    pub path: String,
+    /// Chunk index within the file (chunk records only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk_index: Option<u32>,
+    /// Byte offset within the file (chunk records only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    /// SHA-256 of the chunk or file content
+    pub hash: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Content as UTF-8
+    pub text: String,
+    /// Embedding (when requested and available)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// Result of an export
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportStats {
+    /// Records written
+    pub records: usize,
+    /// Files the records came from
+    pub files: usize,
+    /// Records that carry an embedding
+    pub embedded: usize,
+}
+
+/// Produce every record of `reader`, in path order
+pub fn export_records<F>(reader: &CxpReader, options: &ExportOptions, mut sink: F) -> Result<ExportStats>
+where
+    F: FnMut(ExportRecord) -> Result<()>,
+{
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    let vectors: Option<HashMap<String, Vec<f32>>> = match options.embeddings {
+        true => Some(reader.chunk_embedding_vectors()?),
+        false => None,
+    };
+    #[cfg(not(all(feature = "embeddings", feature = "search")))]
+    if options.embeddings {
+        return Err(CxpError::Embedding(
+            "Exporting embeddings requires the embeddings and search features".to_string(),
+        ));
+    }
+
+    let mut paths = reader.file_paths();
+    paths.sort_unstable();
+
+    let mut stats = ExportStats::default();
+    for path in paths {
+        let Some(entry) = reader.file_entry(path)? else {
+            continue;
+        };
+        if entry.is_image {
+            continue;
+        }
+        let content = reader.read_file_chunks(path, 0..entry.chunks.len())?;
+        stats.files += 1;
+
+        #[cfg_attr(not(all(feature = "embeddings", feature = "search")), allow(unused_variables))]
+        let embedding_of = |hash: &str| -> Option<Vec<f32>> {
+            #[cfg(all(feature = "embeddings", feature = "search"))]
+            return vectors.as_ref().and_then(|v| v.get(hash).cloned());
+            #[cfg(not(all(feature = "embeddings", feature = "search")))]
+            None
+        };
+
+        let records: Vec<ExportRecord> = match options.granularity {
+            ExportGranularity::Chunk => entry
+                .chunks
+                .iter()
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let end = (chunk.offset + chunk.length).min(content.len());
+                    let data = &content[chunk.offset.min(end)..end];
+                    ExportRecord {
+                        id: format!("{}#{}", path, i),
+                        path: path.to_string(),
+                        chunk_index: Some(i as u32),
+                        offset: Some(chunk.offset as u64),
+                        hash: chunk.hash.clone(),
+                        size: data.len() as u64,
+                        text: String::from_utf8_lossy(data).into_owned(),
+                        embedding: embedding_of(&chunk.hash),
+                    }
+                })
+                .collect(),
+            ExportGranularity::File => {
+                let chunk_vectors: Vec<Vec<f32>> =
+                    entry.chunks.iter().filter_map(|c| embedding_of(&c.hash)).collect();
+                vec![ExportRecord {
+                    id: path.to_string(),
+                    path: path.to_string(),
+                    chunk_index: None,
+                    offset: None,
+                    hash: compute_hash(&content),
+                    size: content.len() as u64,
+                    text: String::from_utf8_lossy(&content).into_owned(),
+                    embedding: mean(&chunk_vectors),
+                }]
+            }
+        };
+
+        for record in records {
+            stats.records += 1;
+            stats.embedded += usize::from(record.embedding.is_some());
+            sink(record)?;
+        }
+    }
+    Ok(stats)
+}
+
+/// Write the records of `reader` as JSON Lines
+pub fn write_jsonl<W: Write>(reader: &CxpReader, mut writer: W, options: &ExportOptions) -> Result<ExportStats> {
+    let stats = export_records(reader, options, |record| {
+        serde_json::to_writer(&mut writer, &record).map_err(|e| CxpError::Serialization(e.to_string()))?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    })?;
+    writer.flush()?;
+    Ok(stats)
+}
+
+/// Write the records of `reader` as a Parquet file
+///
+/// Columns: `id`, `path`, `chunk_index`, `offset`, `hash`, `size`, `text`
+/// and `embedding` (list of float32, null when absent).
+#[cfg(feature = "arrow")]
+pub fn write_parquet<W: Write + Send>(reader: &CxpReader, writer: W, options: &ExportOptions) -> Result<ExportStats> {
+    use arrow_schema::{DataType, Field, Schema};
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::{Compression, ZstdLevel};
+    use parquet::file::properties::WriterProperties;
+    use std::sync::Arc;
+
+    /// Records per row group batch
+    const BATCH_SIZE: usize = 1024;
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("path", DataType::Utf8, false),
+        Field::new("chunk_index", DataType::UInt32, true),
+        Field::new("offset", DataType::UInt64, true),
+        Field::new("hash", DataType::Utf8, false),
+        Field::new("size", DataType::UInt64, false),
+        Field::new("text", DataType::LargeUtf8, false),
+        Field::new(
+            "embedding",
+            DataType::List(Arc::new(Field::new("item", DataType::Float32, true))),
+            true,
+        ),
+    ]));
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut parquet = ArrowWriter::try_new(writer, schema.clone(), Some(properties)).map_err(parquet_error)?;
+
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let stats = export_records(reader, options, |record| {
+        batch.push(record);
+        if batch.len() == BATCH_SIZE {
+            parquet.write(&record_batch(&schema, &batch)?).map_err(parquet_error)?;
+            batch.clear();
+        }
+        Ok(())
+    })?;
+    if !batch.is_empty() {
+        parquet.write(&record_batch(&schema, &batch)?).map_err(parquet_error)?;
+    }
+    parquet.close().map_err(parquet_error)?;
+    Ok(stats)
+}
+
+#[cfg(feature = "arrow")]
+fn record_batch(schema: &arrow_schema::SchemaRef, records: &[ExportRecord]) -> Result<arrow_array::RecordBatch> {
+    use arrow_array::builder::{Float32Builder, ListBuilder};
+    use arrow_array::{ArrayRef, LargeStringArray, StringArray, UInt32Array, UInt64Array};
+    use std::sync::Arc;
+
+    let mut embeddings = ListBuilder::new(Float32Builder::new());
+    for record in records {
+        match &record.embedding {
+            Some(vector) => {
+                embeddings.values().append_slice(vector);
+                embeddings.append(true);
+            }
+            None => embeddings.append(false),
+        }
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(records.iter().map(|r| r.id.as_str()))),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|r| r.path.as_str()))),
+        Arc::new(records.iter().map(|r| r.chunk_index).collect::<UInt32Array>()),
+        Arc::new(records.iter().map(|r| r.offset).collect::<UInt64Array>()),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|r| r.hash.as_str()))),
+        Arc::new(UInt64Array::from_iter_values(records.iter().map(|r| r.size))),
+        Arc::new(LargeStringArray::from_iter_values(records.iter().map(|r| r.text.as_str()))),
+        Arc::new(embeddings.finish()),
+    ];
+    arrow_array::RecordBatch::try_new(schema.clone(), columns).map_err(|e| CxpError::Serialization(e.to_string()))
+}
+
+#[cfg(feature = "arrow")]
+fn parquet_error(e: parquet::errors::ParquetError) -> CxpError {
+    CxpError::Serialization(format!("Parquet: {}", e))
+}
+
+/// Element-wise mean of equally sized vectors (None if there are none)
+fn mean(vectors: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let first = vectors.first()?;
+    let mut sum = vec![0.0f32; first.len()];
+    for vector in vectors {
+        for (total, value) in sum.iter_mut().zip(vector) {
+            *total += value;
+        }
+    }
+    let count = vectors.len() as f32;
+    Some(sum.into_iter().map(|total| total / count).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CxpBuilder;
+    use tempfile::TempDir;
+
+    fn archive(dir: &TempDir) -> std::path::PathBuf {
+        let source = dir.path().join("src");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(source.join("big.txt"), "lorem ipsum dolor sit amet\n".repeat(2000)).unwrap();
+        let cxp_path = dir.path().join("export.cxp");
+        CxpBuilder::new(&source).scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+        cxp_path
+    }
+
+    #[test]
+    fn test_export_jsonl() {
+        let dir = TempDir::new().unwrap();
+        let reader = CxpReader::open(archive(&dir)).unwrap();
+
+        let mut output = Vec::new();
+        let stats = write_jsonl(&reader, &mut output, &ExportOptions::new()).unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), stats.records);
+        assert_eq!(stats.files, 2);
+        assert!(stats.records > 2);
+
+        // Chunks of a file concatenate back to the file
+        let big: String = lines
+            .iter()
+            .filter(|l| l["path"] == "big.txt")
+            .map(|l| l["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(big, "lorem ipsum dolor sit amet\n".repeat(2000));
+        let main = lines.iter().find(|l| l["path"] == "main.rs").unwrap();
+        assert_eq!(main["id"], "main.rs#0");
+        assert_eq!(main["hash"], compute_hash(b"fn main() {}\n"));
+        assert!(main.get("embedding").is_none());
+
+        let mut output = Vec::new();
+        let options = ExportOptions::new().with_granularity(ExportGranularity::File);
+        let stats = write_jsonl(&reader, &mut output, &options).unwrap();
+        assert_eq!(stats.records, 2);
+        let first: serde_json::Value = serde_json::from_slice(output.split(|b| *b == b'\n').next().unwrap()).unwrap();
+        assert_eq!(first["id"], "big.txt");
+        assert!(first.get("chunk_index").is_none());
+
+        assert_eq!("parquet".parse::<ExportFormat>().unwrap(), ExportFormat::Parquet);
+        assert!("csv".parse::<ExportFormat>().is_err());
+        assert_eq!(mean(&[vec![1.0, 2.0], vec![3.0, 4.0]]), Some(vec![2.0, 3.0]));
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_export_parquet() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let dir = TempDir::new().unwrap();
+        let reader = CxpReader::open(archive(&dir)).unwrap();
+        let parquet_path = dir.path().join("export.parquet");
+        let stats = write_parquet(&reader, std::fs::File::create(&parquet_path).unwrap(), &ExportOptions::new()).unwrap();
+
+        let file = SerializedFileReader::new(std::fs::File::open(&parquet_path).unwrap()).unwrap();
+        let metadata = file.metadata();
+        assert_eq!(metadata.file_metadata().num_rows() as usize, stats.records);
+        let columns: Vec<&str> = metadata.file_metadata().schema_descr().columns().iter().map(|c| c.name()).collect();
+        assert_eq!(&columns[..7], &["id", "path", "chunk_index", "offset", "hash", "size", "text"]);
+    }
+}
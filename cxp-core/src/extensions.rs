@@ -15,7 +15,7 @@
 
 use crate::{CxpError, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// Extension metadata stored in manifest.msgpack
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,7 +27,7 @@ pub struct ExtensionManifest {
     /// Optional description
     pub description: Option<String>,
     /// Custom metadata
-    pub metadata: HashMap<String, String>,
+    pub metadata: BTreeMap<String, String>,
 }
 
 impl ExtensionManifest {
@@ -37,7 +37,7 @@ impl ExtensionManifest {
             namespace: namespace.into(),
             version: version.into(),
             description: None,
-            metadata: HashMap::new(),
+            metadata: BTreeMap::new(),
         }
     }
 
@@ -77,17 +77,17 @@ pub trait Extension {
 #[derive(Debug, Default)]
 pub struct ExtensionManager {
     /// Registered extensions
-    extensions: HashMap<String, ExtensionManifest>,
+    extensions: BTreeMap<String, ExtensionManifest>,
     /// Extension data cache (namespace -> key -> data)
-    data_cache: HashMap<String, HashMap<String, Vec<u8>>>,
+    data_cache: BTreeMap<String, BTreeMap<String, Vec<u8>>>,
 }
 
 impl ExtensionManager {
     /// Create a new extension manager
     pub fn new() -> Self {
         Self {
-            extensions: HashMap::new(),
-            data_cache: HashMap::new(),
+            extensions: BTreeMap::new(),
+            data_cache: BTreeMap::new(),
         }
     }
 
@@ -113,7 +113,7 @@ impl ExtensionManager {
 
         self.data_cache
             .entry(namespace.to_string())
-            .or_insert_with(HashMap::new)
+            .or_default()
             .insert(key.to_string(), data.to_vec());
 
         Ok(())
@@ -149,12 +149,12 @@ impl ExtensionManager {
     }
 
     /// Get all extension data (for writing to ZIP)
-    pub fn all_data(&self) -> &HashMap<String, HashMap<String, Vec<u8>>> {
+    pub fn all_data(&self) -> &BTreeMap<String, BTreeMap<String, Vec<u8>>> {
         &self.data_cache
     }
 
     /// Get extension manifests (for writing to ZIP)
-    pub fn manifests(&self) -> &HashMap<String, ExtensionManifest> {
+    pub fn manifests(&self) -> &BTreeMap<String, ExtensionManifest> {
         &self.extensions
     }
 
@@ -162,7 +162,7 @@ impl ExtensionManager {
     pub fn load_data(&mut self, namespace: String, key: String, data: Vec<u8>) {
         self.data_cache
             .entry(namespace)
-            .or_insert_with(HashMap::new)
+            .or_default()
             .insert(key, data);
     }
 
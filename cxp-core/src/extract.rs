@@ -0,0 +1,291 @@
+//! Directory Export
+//!
+//! Reconstructs the source tree of an archive on disk
+//! (`CxpReader::extract_all`): every file is written under its original
+//! relative path, chunk by chunk, with each chunk checked against its SHA-256
+//! hash before it is written. Modification times are restored when the
+//! archive recorded them.
+//!
+//! Paths that would escape the destination (absolute paths, `..`) are
+//! rejected before anything is written.
+
+use crate::chunker::compute_hash;
+use crate::compress::ChunkCodec;
+use crate::filter::PathPattern;
+use crate::format::{FileEntry, StoredChunks};
+use crate::{CxpError, Result};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use zip::ZipArchive;
+
+/// What to do when a file already exists at the destination
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Abort the export
+    #[default]
+    Fail,
+    /// Keep the existing file
+    Skip,
+    /// Replace the existing file
+    Overwrite,
+    /// Replace the existing file only if the archived copy is newer
+    IfNewer,
+}
+
+impl std::str::FromStr for OverwritePolicy {
+    type Err = CxpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "fail" | "error" => Ok(Self::Fail),
+            "skip" | "keep" => Ok(Self::Skip),
+            "overwrite" | "replace" => Ok(Self::Overwrite),
+            "newer" | "if-newer" => Ok(Self::IfNewer),
+            _ => Err(CxpError::InvalidFormat(format!(
+                "Unknown overwrite policy '{}' (expected fail, skip, overwrite or newer)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Options for `CxpReader::extract_all_with`
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    /// Handling of files that already exist
+    pub overwrite: OverwritePolicy,
+    /// Restore recorded modification times (default: true)
+    pub preserve_mtime: bool,
+    /// Check every chunk against its hash (default: true)
+    pub verify: bool,
+    /// Only extract files matching this pattern (all files if None)
+    pub pattern: Option<PathPattern>,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            overwrite: OverwritePolicy::default(),
+            preserve_mtime: true,
+            verify: true,
+            pattern: None,
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// Default options (fail on existing files, restore mtimes, verify chunks)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the handling of existing files
+    pub fn with_overwrite(mut self, overwrite: OverwritePolicy) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Restore recorded modification times
+    pub fn with_mtime(mut self, preserve: bool) -> Self {
+        self.preserve_mtime = preserve;
+        self
+    }
+
+    /// Check chunks against their hashes
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Only extract files matching `pattern`
+    pub fn with_pattern(mut self, pattern: PathPattern) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+}
+
+/// Result of an export
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractStats {
+    /// Files written
+    pub files_written: usize,
+    /// Existing files left untouched
+    pub files_skipped: usize,
+    /// Bytes written
+    pub bytes_written: u64,
+    /// Distinct chunks checked against their hash
+    pub chunks_verified: usize,
+}
+
+/// Destination of `path` inside `dest`, rejecting paths that would escape it
+pub fn safe_join(dest: &Path, path: &str) -> Result<PathBuf> {
+    let mut target = dest.to_path_buf();
+    let mut depth = 0;
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => {
+                target.push(part);
+                depth += 1;
+            }
+            Component::CurDir => {}
+            _ => {
+                return Err(CxpError::InvalidFormat(format!(
+                    "Refusing to extract '{}' outside the destination",
+                    path
+                )))
+            }
+        }
+    }
+    if depth == 0 {
+        return Err(CxpError::InvalidFormat(format!("Invalid file path '{}'", path)));
+    }
+    Ok(target)
+}
+
+/// Whether `entry` should be written to `target` under `policy`
+pub(crate) fn should_write(entry: &FileEntry, target: &Path, policy: OverwritePolicy) -> Result<bool> {
+    let Ok(existing) = std::fs::metadata(target) else {
+        return Ok(true);
+    };
+    match policy {
+        OverwritePolicy::Fail => Err(CxpError::io(format!("{} already exists", target.display()))),
+        OverwritePolicy::Skip => Ok(false),
+        OverwritePolicy::Overwrite => Ok(true),
+        OverwritePolicy::IfNewer => Ok(match (entry.modified, existing.modified()) {
+            (Some(archived), Ok(current)) => archived > chrono::DateTime::<chrono::Utc>::from(current),
+            _ => false,
+        }),
+    }
+}
+
+/// Write one file to `target`, chunk by chunk
+///
+/// Chunks already in `verified` are not hashed again. Returns the number of
+/// bytes written.
+pub(crate) fn write_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    stored: &StoredChunks,
+    codec: &ChunkCodec,
+    entry: &FileEntry,
+    target: &Path,
+    options: &ExtractOptions,
+    verified: &mut HashSet<String>,
+) -> Result<u64> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Write next to the target so a failed check never leaves a partial file behind
+    let mut temp_name = target.as_os_str().to_os_string();
+    temp_name.push(".cxp-part");
+    let temp_path = PathBuf::from(temp_name);
+
+    let result = (|| {
+        let mut file = File::create(&temp_path)?;
+        let mut written = 0u64;
+        for chunk in &entry.chunks {
+            let data = codec.decompress(&stored.read(archive, &chunk.hash)?)?;
+
+            if options.verify && !verified.contains(&chunk.hash) {
+                let actual = compute_hash(&data);
+                if actual != chunk.hash {
+                    return Err(CxpError::ChunkHashMismatch {
+                        path: entry.path.clone(),
+                        expected: chunk.hash.clone(),
+                        actual,
+                    });
+                }
+                verified.insert(chunk.hash.clone());
+            }
+            file.write_all(&data)?;
+            written += data.len() as u64;
+        }
+        if written != entry.size {
+            return Err(CxpError::Chunk(format!(
+                "{} has {} bytes, expected {}",
+                entry.path, written, entry.size
+            )));
+        }
+        if options.preserve_mtime {
+            if let Some(modified) = entry.modified {
+                file.set_modified(modified.into())?;
+            }
+        }
+        Ok(written)
+    })();
+
+    match result {
+        Ok(written) => {
+            std::fs::rename(&temp_path, target)
+                .map_err(|e| CxpError::io(format!("Failed to write {}: {}", target.display(), e)))?;
+            Ok(written)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CxpBuilder, CxpReader};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_safe_join() {
+        let dest = Path::new("/out");
+        assert_eq!(safe_join(dest, "src/main.rs").unwrap(), PathBuf::from("/out/src/main.rs"));
+        assert_eq!(safe_join(dest, "./a.txt").unwrap(), PathBuf::from("/out/a.txt"));
+        assert!(safe_join(dest, "../etc/passwd").is_err());
+        assert!(safe_join(dest, "src/../../x").is_err());
+        assert!(safe_join(dest, "/etc/passwd").is_err());
+        assert!(safe_join(dest, "").is_err());
+        assert_eq!("newer".parse::<OverwritePolicy>().unwrap(), OverwritePolicy::IfNewer);
+        assert!("sometimes".parse::<OverwritePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_extract_all() {
+        let source = TempDir::new().unwrap();
+        std::fs::create_dir_all(source.path().join("src/nested")).unwrap();
+        std::fs::write(source.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(source.path().join("src/nested/lib.rs"), "pub fn f() {}\n".repeat(2000)).unwrap();
+        std::fs::write(source.path().join("README.md"), "# Readme\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("tree.cxp");
+        CxpBuilder::new(source.path()).scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+        let reader = CxpReader::open(&cxp_path).unwrap();
+
+        let dest = output.path().join("unpacked");
+        let stats = reader.extract_all(&dest, OverwritePolicy::Fail).unwrap();
+        assert_eq!(stats.files_written, 3);
+        assert!(stats.chunks_verified >= 3);
+        for path in ["src/main.rs", "src/nested/lib.rs", "README.md"] {
+            assert_eq!(
+                std::fs::read(dest.join(path)).unwrap(),
+                std::fs::read(source.path().join(path)).unwrap()
+            );
+        }
+        let original = std::fs::metadata(source.path().join("README.md")).unwrap().modified().unwrap();
+        let restored = std::fs::metadata(dest.join("README.md")).unwrap().modified().unwrap();
+        let drift = original.duration_since(restored).unwrap_or_else(|e| e.duration());
+        assert!(drift.as_secs() < 1);
+
+        // Existing files
+        assert!(reader.extract_all(&dest, OverwritePolicy::Fail).is_err());
+        std::fs::write(dest.join("README.md"), "local edit\n").unwrap();
+        let stats = reader.extract_all(&dest, OverwritePolicy::Skip).unwrap();
+        assert_eq!((stats.files_written, stats.files_skipped), (0, 3));
+        assert_eq!(std::fs::read_to_string(dest.join("README.md")).unwrap(), "local edit\n");
+        let stats = reader.extract_all(&dest, OverwritePolicy::IfNewer).unwrap();
+        assert_eq!(stats.files_written, 0);
+        let stats = reader.extract_all(&dest, OverwritePolicy::Overwrite).unwrap();
+        assert_eq!(stats.files_written, 3);
+        assert_eq!(std::fs::read_to_string(dest.join("README.md")).unwrap(), "# Readme\n");
+    }
+}
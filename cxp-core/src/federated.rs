@@ -0,0 +1,237 @@
+//! Federated Search over Standalone Archives
+//!
+//! Searches several independent `.cxp` files at once - the standalone
+//! counterpart of `CxpManager`'s federation over child CXPs. Each archive is
+//! searched with [`quick::search_reader`] (semantic when possible, keywords
+//! otherwise) and the per-archive rankings are merged.
+//!
+//! Archives are opened in batches whose estimated footprint stays within a
+//! memory limit; the archives of a batch are searched concurrently and closed
+//! before the next batch starts. An archive larger than the limit is searched
+//! on its own.
+//!
+//! Scores of different archives are not comparable (keyword counts vs. cosine
+//! similarities), so hits are merged with Reciprocal Rank Fusion: a hit ranked
+//! `r` in its archive scores `1 / (k + r)`, ties broken by the original score.
+
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::format::CxpReader;
+use crate::fusion::DEFAULT_RRF_K;
+use crate::quick::{self, Hit, Options};
+use crate::Result;
+
+/// Default memory limit for open archives (matches `CxpManagerConfig`)
+pub const DEFAULT_MEMORY_LIMIT: u64 = 500 * 1024 * 1024;
+
+/// A hit labeled with its archive
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FederatedHit {
+    /// Archive the hit comes from
+    pub archive: PathBuf,
+    /// Rank within its archive (1-based)
+    pub rank: usize,
+    /// Fused score across archives (higher is better)
+    pub score: f32,
+    /// The archive's own hit
+    pub hit: Hit,
+}
+
+/// An archive that could not be searched
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArchiveFailure {
+    /// Archive path
+    pub archive: PathBuf,
+    /// Error message
+    pub error: String,
+}
+
+/// Merged results of a federated search
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FederatedResults {
+    /// Hits across all archives, best first
+    pub hits: Vec<FederatedHit>,
+    /// Archives that failed to open or search
+    pub failures: Vec<ArchiveFailure>,
+    /// Number of archives searched successfully
+    pub archives_searched: usize,
+    /// Number of batches the archives were split into
+    pub batches: usize,
+}
+
+/// Search across several archives
+#[derive(Debug, Clone)]
+pub struct FederatedSearch {
+    archives: Vec<PathBuf>,
+    memory_limit: u64,
+    options: Options,
+}
+
+impl FederatedSearch {
+    /// Search the given archives with the default memory limit and options
+    pub fn new<I, P>(archives: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        Self {
+            archives: archives.into_iter().map(Into::into).collect(),
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            options: Options::default(),
+        }
+    }
+
+    /// Limit the estimated memory of archives open at the same time
+    pub fn with_memory_limit(mut self, bytes: u64) -> Self {
+        self.memory_limit = bytes.max(1);
+        self
+    }
+
+    /// Set the search options (model directory, embeddings on/off)
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Archives to search
+    pub fn archives(&self) -> &[PathBuf] {
+        &self.archives
+    }
+
+    /// Search every archive and merge the top `top_k` hits
+    ///
+    /// Archives that fail are reported in `failures` instead of failing the
+    /// whole search.
+    pub fn search(&self, query: &str, top_k: usize) -> FederatedResults {
+        let mut results = FederatedResults::default();
+        let batches = self.batches();
+        results.batches = batches.len();
+
+        for batch in batches {
+            let outcomes: Vec<(&PathBuf, Result<Vec<Hit>>)> = batch
+                .par_iter()
+                .map(|archive| (*archive, self.search_archive(archive, query, top_k)))
+                .collect();
+
+            for (archive, outcome) in outcomes {
+                match outcome {
+                    Ok(hits) => {
+                        results.archives_searched += 1;
+                        results.hits.extend(hits.into_iter().enumerate().map(|(i, hit)| FederatedHit {
+                            archive: archive.clone(),
+                            rank: i + 1,
+                            score: 1.0 / (DEFAULT_RRF_K + (i + 1) as f32),
+                            hit,
+                        }));
+                    }
+                    Err(e) => {
+                        tracing::warn!("Skipping {:?}: {}", archive, e);
+                        results.failures.push(ArchiveFailure {
+                            archive: archive.clone(),
+                            error: e.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        results.hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b.hit.score.total_cmp(&a.hit.score))
+                .then_with(|| a.archive.cmp(&b.archive))
+        });
+        results.hits.truncate(top_k);
+        results
+    }
+
+    /// Search a single archive
+    fn search_archive(&self, archive: &Path, query: &str, top_k: usize) -> Result<Vec<Hit>> {
+        let reader = CxpReader::open(archive)?.with_access_tracking(false);
+        quick::search_reader(&reader, query, top_k, &self.options)
+    }
+
+    /// Split the archives into batches that fit the memory limit
+    ///
+    /// The footprint of an archive is estimated by its file size, an upper
+    /// bound for the file map and embeddings a reader keeps in memory.
+    fn batches(&self) -> Vec<Vec<&PathBuf>> {
+        let mut batches: Vec<Vec<&PathBuf>> = Vec::new();
+        let mut batch_bytes = 0;
+
+        for archive in &self.archives {
+            let bytes = std::fs::metadata(archive).map(|m| m.len()).unwrap_or(0);
+            if bytes > self.memory_limit {
+                tracing::warn!("{:?} ({} bytes) exceeds the memory limit, searching it alone", archive, bytes);
+            }
+
+            match batches.last_mut() {
+                Some(batch) if batch_bytes + bytes <= self.memory_limit => {
+                    batch.push(archive);
+                    batch_bytes += bytes;
+                }
+                _ => {
+                    batches.push(vec![archive]);
+                    batch_bytes = bytes;
+                }
+            }
+        }
+
+        batches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CxpBuilder;
+    use tempfile::TempDir;
+
+    fn build(dir: &Path, name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let source = dir.join(name);
+        std::fs::create_dir_all(&source).unwrap();
+        for (path, content) in files {
+            std::fs::write(source.join(path), content).unwrap();
+        }
+        let out = dir.join(format!("{}.cxp", name));
+        let mut builder = CxpBuilder::new(&source);
+        builder.with_build_info(false);
+        builder.scan().unwrap().process().unwrap();
+        builder.build(&out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_federated_search() {
+        let dir = TempDir::new().unwrap();
+        let api = build(dir.path(), "api", &[
+            ("client.rs", "fn fetch() { retry(3); retry_backoff(); }\n"),
+            ("readme.md", "HTTP client with retry logic\n"),
+        ]);
+        let worker = build(dir.path(), "worker", &[("jobs.rs", "fn run() { retry(5); }\n")]);
+        let missing = dir.path().join("missing.cxp");
+
+        let options = Options::new().with_embeddings(false);
+        let search = FederatedSearch::new([&api, &worker, &missing]).with_options(options.clone());
+        let results = search.search("retry", 10);
+
+        assert_eq!(results.archives_searched, 2);
+        assert_eq!(results.failures.len(), 1);
+        assert_eq!(results.failures[0].archive, missing);
+        assert_eq!(results.hits.len(), 3);
+        // Both archives' best hits come before any second-ranked hit
+        assert_eq!(results.hits[0].rank, 1);
+        assert_eq!(results.hits[1].rank, 1);
+        assert_eq!(results.hits[2].archive, api);
+        assert_eq!(results.hits[2].rank, 2);
+        assert_eq!(search.search("retry", 1).hits.len(), 1);
+
+        // A tiny memory limit puts every archive in its own batch
+        let results = FederatedSearch::new([&api, &worker]).with_options(options).with_memory_limit(1).search("retry", 10);
+        assert_eq!(results.batches, 2);
+        assert_eq!(results.hits.len(), 3);
+    }
+}
@@ -0,0 +1,352 @@
+//! C ABI
+//!
+//! A small, stable C interface for native hosts (Swift, Kotlin/JNI, C++) that
+//! cannot link against the Rust API. Enabled with the `ffi` feature; the
+//! matching header is `include/cxp.h` (regenerate it with
+//! `cbindgen --config cbindgen.toml --output include/cxp.h`).
+//!
+//! Build a shared or static library with:
+//! ```text
+//! cargo rustc -p cxp-core --release --features ffi --crate-type cdylib
+//! cargo rustc -p cxp-core --release --features ffi --crate-type staticlib
+//! ```
+//!
+//! Conventions:
+//! - Archives are opaque `CxpArchive` handles from `cxp_open`, released with `cxp_close`
+//! - Functions returning data fill a `CxpBuffer` owned by the caller, released with
+//!   `cxp_free`; on failure the buffer is left empty
+//! - Fallible functions return `CXP_OK` (0) or a negative status; `cxp_last_error`
+//!   describes the last failure on the calling thread and `cxp_last_error_code`
+//!   returns its `CxpErrorCode`
+//! - Strings are NUL-terminated UTF-8; structured results are JSON
+//!
+//! ```c
+//! CxpArchive *archive = cxp_open("project.cxp");
+//! CxpBuffer buf;
+//! if (archive && cxp_read_file(archive, "src/main.rs", &buf) == CXP_OK) {
+//!     fwrite(buf.data, 1, buf.len, stdout);
+//!     cxp_free(buf);
+//! }
+//! cxp_close(archive);
+//! ```
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use crate::format::CxpReader;
+use crate::quick::{self, Options};
+use crate::CxpError;
+
+/// Success
+pub const CXP_OK: i32 = 0;
+/// A required pointer argument was null or a string was not UTF-8
+pub const CXP_ERR_INVALID_ARGUMENT: i32 = -1;
+/// The operation failed (see `cxp_last_error`)
+pub const CXP_ERR_FAILED: i32 = -2;
+/// The library panicked; the handle should not be used further
+pub const CXP_ERR_PANIC: i32 = -3;
+
+/// Opaque handle to an open archive
+pub struct CxpArchive {
+    reader: CxpReader,
+    options: Options,
+}
+
+/// Bytes returned by the library, freed with `cxp_free`
+#[repr(C)]
+pub struct CxpBuffer {
+    /// Start of the data (null for an empty buffer)
+    pub data: *mut u8,
+    /// Length in bytes
+    pub len: usize,
+}
+
+impl CxpBuffer {
+    const EMPTY: Self = Self {
+        data: ptr::null_mut(),
+        len: 0,
+    };
+
+    fn from_vec(data: Vec<u8>) -> Self {
+        let mut data = data.into_boxed_slice();
+        let buffer = Self {
+            data: data.as_mut_ptr(),
+            len: data.len(),
+        };
+        std::mem::forget(data);
+        buffer
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<(CString, i32)>> = const { RefCell::new(None) };
+}
+
+/// Remember a failure; `code` is an `ErrorCode`, or 0 for argument errors and panics
+fn set_last_error(message: String, code: i32) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some((message, code)));
+}
+
+/// Failure of an FFI call
+enum FfiError {
+    /// Bad pointer or string argument
+    InvalidArgument(String),
+    /// Error from the library
+    Cxp(CxpError),
+}
+
+impl From<CxpError> for FfiError {
+    fn from(e: CxpError) -> Self {
+        Self::Cxp(e)
+    }
+}
+
+type FfiResult<T> = std::result::Result<T, FfiError>;
+
+/// Run `f`, translating errors and panics into status codes
+fn guard(f: impl FnOnce() -> FfiResult<()>) -> i32 {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => CXP_OK,
+        Ok(Err(FfiError::InvalidArgument(message))) => {
+            set_last_error(message, 0);
+            CXP_ERR_INVALID_ARGUMENT
+        }
+        Ok(Err(FfiError::Cxp(e))) => {
+            set_last_error(e.full_message(), e.code() as i32);
+            CXP_ERR_FAILED
+        }
+        Err(_) => {
+            set_last_error("Panic inside cxp-core".to_string(), 0);
+            CXP_ERR_PANIC
+        }
+    }
+}
+
+/// Borrow a NUL-terminated UTF-8 argument
+///
+/// # Safety
+/// `s` must be null or point to a NUL-terminated string valid for `'a`.
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> FfiResult<&'a str> {
+    if s.is_null() {
+        return Err(FfiError::InvalidArgument(format!("{} is null", name)));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| FfiError::InvalidArgument(format!("{} is not UTF-8", name)))
+}
+
+/// Borrow the archive behind a handle
+///
+/// # Safety
+/// `archive` must be null or a live handle from `cxp_open`.
+unsafe fn archive_arg<'a>(archive: *mut CxpArchive) -> FfiResult<&'a mut CxpArchive> {
+    archive
+        .as_mut()
+        .ok_or_else(|| FfiError::InvalidArgument("archive is null".to_string()))
+}
+
+/// Run `f` and store the bytes it returns in `out` (left empty on failure)
+///
+/// # Safety
+/// `out` must be null or valid for writes.
+unsafe fn fill(out: *mut CxpBuffer, f: impl FnOnce() -> FfiResult<Vec<u8>>) -> i32 {
+    if let Some(out) = out.as_mut() {
+        *out = CxpBuffer::EMPTY;
+    }
+    guard(|| {
+        let data = f()?;
+        write_out(out, data)
+    })
+}
+
+/// Store `data` in `out`
+///
+/// # Safety
+/// `out` must be null or valid for writes.
+unsafe fn write_out(out: *mut CxpBuffer, data: Vec<u8>) -> FfiResult<()> {
+    let out = out
+        .as_mut()
+        .ok_or_else(|| FfiError::InvalidArgument("out is null".to_string()))?;
+    *out = CxpBuffer::from_vec(data);
+    Ok(())
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> FfiResult<Vec<u8>> {
+    serde_json::to_vec(value).map_err(|e| FfiError::Cxp(CxpError::Serialization(e.to_string())))
+}
+
+/// Version of the library as a static NUL-terminated string
+#[no_mangle]
+pub extern "C" fn cxp_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Message of the last failed call on this thread, or null
+///
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn cxp_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |(message, _)| message.as_ptr()))
+}
+
+/// `CxpErrorCode` of the last failed call on this thread
+///
+/// 0 if there was none, or if it failed with `CXP_ERR_INVALID_ARGUMENT` or
+/// `CXP_ERR_PANIC`.
+#[no_mangle]
+pub extern "C" fn cxp_last_error_code() -> i32 {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(0, |(_, code)| *code))
+}
+
+/// Open an archive, returning null on failure
+///
+/// # Safety
+/// `path` must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cxp_open(path: *const c_char) -> *mut CxpArchive {
+    let mut archive = ptr::null_mut();
+    guard(|| {
+        let reader = CxpReader::open(str_arg(path, "path")?)?;
+        archive = Box::into_raw(Box::new(CxpArchive {
+            reader,
+            options: Options::default(),
+        }));
+        Ok(())
+    });
+    archive
+}
+
+/// Close an archive opened with `cxp_open` (null is ignored)
+///
+/// # Safety
+/// `archive` must be null or a handle from `cxp_open` that has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn cxp_close(archive: *mut CxpArchive) {
+    if !archive.is_null() {
+        drop(Box::from_raw(archive));
+    }
+}
+
+/// Read a file's content into `out`
+///
+/// # Safety
+/// `archive` must be a live handle, `path` a NUL-terminated string and `out`
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn cxp_read_file(archive: *mut CxpArchive, path: *const c_char, out: *mut CxpBuffer) -> i32 {
+    fill(out, || {
+        let archive = archive_arg(archive)?;
+        Ok(archive.reader.read_file(str_arg(path, "path")?)?)
+    })
+}
+
+/// List the archive's file paths into `out` as a JSON array of strings
+///
+/// # Safety
+/// `archive` must be a live handle and `out` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn cxp_list_files(archive: *mut CxpArchive, out: *mut CxpBuffer) -> i32 {
+    fill(out, || {
+        let archive = archive_arg(archive)?;
+        to_json(&archive.reader.file_paths())
+    })
+}
+
+/// Search the archive and write the hits to `out` as a JSON array
+///
+/// Each hit is `{"file_path", "chunk_index", "score", "text"}`. Search is
+/// semantic when the archive has embeddings and `CXP_MODEL_DIR` points to the
+/// model, and by keywords otherwise.
+///
+/// # Safety
+/// `archive` must be a live handle, `query` a NUL-terminated string and `out`
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn cxp_search(archive: *mut CxpArchive, query: *const c_char, top_k: usize, out: *mut CxpBuffer) -> i32 {
+    fill(out, || {
+        let archive = archive_arg(archive)?;
+        let hits = quick::search_reader(&archive.reader, str_arg(query, "query")?, top_k, &archive.options)?;
+        to_json(&hits)
+    })
+}
+
+/// Free a buffer returned by the library (empty buffers are ignored)
+///
+/// # Safety
+/// `buffer` must come from this library and not have been freed before.
+#[no_mangle]
+pub unsafe extern "C" fn cxp_free(buffer: CxpBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CxpBuilder;
+    use tempfile::TempDir;
+
+    fn take(buffer: CxpBuffer) -> Vec<u8> {
+        let data = unsafe { std::slice::from_raw_parts(buffer.data, buffer.len).to_vec() };
+        unsafe { cxp_free(buffer) };
+        data
+    }
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(cxp_last_error()).to_string_lossy().into_owned() }
+    }
+
+    #[test]
+    fn test_ffi_roundtrip() {
+        let source = TempDir::new().unwrap();
+        std::fs::write(source.path().join("auth.rs"), "fn login() { check_password(); }\n").unwrap();
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("ffi.cxp");
+
+        let mut builder = CxpBuilder::new(source.path());
+        builder.scan().unwrap().process().unwrap();
+        builder.build(&cxp_path).unwrap();
+
+        let path = CString::new(cxp_path.to_str().unwrap()).unwrap();
+        let archive = unsafe { cxp_open(path.as_ptr()) };
+        assert!(!archive.is_null());
+
+        let mut buffer = CxpBuffer::EMPTY;
+        let file = CString::new("auth.rs").unwrap();
+        assert_eq!(unsafe { cxp_read_file(archive, file.as_ptr(), &mut buffer) }, CXP_OK);
+        assert_eq!(take(buffer), b"fn login() { check_password(); }\n");
+
+        let mut buffer = CxpBuffer::EMPTY;
+        assert_eq!(unsafe { cxp_list_files(archive, &mut buffer) }, CXP_OK);
+        assert_eq!(take(buffer), br#"["auth.rs"]"#);
+
+        let mut buffer = CxpBuffer::EMPTY;
+        let query = CString::new("password").unwrap();
+        assert_eq!(unsafe { cxp_search(archive, query.as_ptr(), 5, &mut buffer) }, CXP_OK);
+        let hits: serde_json::Value = serde_json::from_slice(&take(buffer)).unwrap();
+        assert_eq!(hits[0]["file_path"], "auth.rs");
+
+        let missing = CString::new("missing.rs").unwrap();
+        let mut buffer = CxpBuffer { data: ptr::NonNull::dangling().as_ptr(), len: 1 };
+        assert_eq!(unsafe { cxp_read_file(archive, missing.as_ptr(), &mut buffer) }, CXP_ERR_FAILED);
+        assert!(buffer.data.is_null());
+        assert!(last_error().contains("missing.rs"));
+        assert_eq!(cxp_last_error_code(), crate::ErrorCode::FileNotFound as i32);
+
+        assert_eq!(unsafe { cxp_read_file(archive, ptr::null(), &mut buffer) }, CXP_ERR_INVALID_ARGUMENT);
+        unsafe { cxp_close(archive) };
+    }
+
+    #[test]
+    fn test_ffi_open_failure() {
+        let path = CString::new("/nonexistent/archive.cxp").unwrap();
+        assert!(unsafe { cxp_open(path.as_ptr()) }.is_null());
+        assert!(!last_error().is_empty());
+        assert!(unsafe { cxp_open(ptr::null()) }.is_null());
+        unsafe { cxp_close(ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,280 @@
+//! Scan Filters
+//!
+//! Controls which files `CxpBuilder::scan()` picks up:
+//!
+//! - `include` globs: only matching files are packed (all files if empty)
+//! - `exclude` globs: matching files, and everything under matching
+//!   directories, are skipped
+//! - `max_file_size`: larger files are skipped
+//! - `skip_hidden`: dotfiles and dot-directories are skipped (off by default,
+//!   `cxp build` turns it on unless `--hidden` is given)
+//!
+//! Globs are matched against the `/`-separated path relative to the source
+//! directory; `*` also matches across directories, so `*.rs` matches
+//! `src/lib.rs`.
+//!
+//! [`PathPattern`] selects files of an existing archive the same way (or by
+//! regular expression, with the `regex` feature), for
+//! `CxpReader::files_matching` and the `--glob` / `--regex` options of
+//! `cxp list`, `extract` and `query`.
+
+use crate::{CxpError, Result};
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+#[cfg(feature = "regex")]
+use regex::Regex;
+use std::path::Path;
+
+/// Which scanned files to pack
+#[derive(Debug, Clone, Default)]
+pub struct ScanFilter {
+    /// Only pack files matching one of these globs (all files if empty)
+    pub include: Vec<String>,
+    /// Skip files and directories matching one of these globs
+    pub exclude: Vec<String>,
+    /// Skip files larger than this many bytes
+    pub max_file_size: Option<u64>,
+    /// Skip hidden files and directories (names starting with `.`)
+    pub skip_hidden: bool,
+}
+
+impl ScanFilter {
+    /// Filter that packs every file
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add include globs
+    pub fn with_include<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.include.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add exclude globs
+    pub fn with_exclude<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Skip files larger than `bytes`
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Skip hidden files and directories
+    pub fn with_skip_hidden(mut self, skip: bool) -> Self {
+        self.skip_hidden = skip;
+        self
+    }
+
+    /// Compile the globs
+    pub(crate) fn compile(&self) -> Result<CompiledFilter> {
+        Ok(CompiledFilter {
+            include: (!self.include.is_empty()).then(|| globset(&self.include)).transpose()?,
+            exclude: globset(&self.exclude)?,
+            max_file_size: self.max_file_size,
+            skip_hidden: self.skip_hidden,
+        })
+    }
+}
+
+/// A `ScanFilter` with its globs compiled
+pub(crate) struct CompiledFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    max_file_size: Option<u64>,
+    skip_hidden: bool,
+}
+
+impl CompiledFilter {
+    /// Whether to descend into the directory at `relative`
+    pub fn allows_dir(&self, relative: &Path) -> bool {
+        if self.skip_hidden && is_hidden(relative) {
+            return false;
+        }
+        !self.exclude.is_match(slash_path(relative))
+    }
+
+    /// Whether to pack the file at `relative` with `size` bytes
+    ///
+    /// Parent directories are checked by `allows_dir()` during the walk.
+    pub fn allows_file(&self, relative: &Path, size: u64) -> bool {
+        if self.skip_hidden && is_hidden(relative) {
+            return false;
+        }
+        let path = slash_path(relative);
+        if self.exclude.is_match(&path) {
+            return false;
+        }
+        if let Some(include) = &self.include {
+            if !include.is_match(&path) {
+                return false;
+            }
+        }
+        match self.max_file_size {
+            Some(max) if size > max => {
+                tracing::debug!("Skipping {} ({} bytes, limit {})", path, size, max);
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Pattern selecting archive paths by glob or regular expression
+#[derive(Debug, Clone)]
+pub enum PathPattern {
+    /// Glob matched against the whole path (`src/**/*.rs`)
+    Glob(GlobMatcher),
+    /// Regular expression found anywhere in the path (anchor with `^`/`$`)
+    #[cfg(feature = "regex")]
+    Regex(Regex),
+}
+
+impl PathPattern {
+    /// Compile a glob
+    pub fn glob(pattern: &str) -> Result<Self> {
+        Glob::new(pattern)
+            .map(|glob| Self::Glob(glob.compile_matcher()))
+            .map_err(|e| CxpError::InvalidFormat(format!("Invalid glob '{}': {}", pattern, e)))
+    }
+
+    /// Compile a regular expression
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> Result<Self> {
+        Regex::new(pattern)
+            .map(Self::Regex)
+            .map_err(|e| CxpError::InvalidFormat(format!("Invalid regex '{}': {}", pattern, e)))
+    }
+
+    /// Whether the archive path matches
+    pub fn is_match(&self, path: &str) -> bool {
+        match self {
+            Self::Glob(glob) => glob.is_match(path),
+            #[cfg(feature = "regex")]
+            Self::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+fn globset(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| CxpError::InvalidFormat(format!("Invalid glob '{}': {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| CxpError::InvalidFormat(format!("Invalid glob set: {}", e)))
+}
+
+fn is_hidden(relative: &Path) -> bool {
+    relative
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+fn slash_path(relative: &Path) -> String {
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CxpBuilder, CxpReader};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_filter() {
+        let source = TempDir::new().unwrap();
+        let root = source.path();
+        for dir in ["src", "target/debug", ".github", "docs"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join("src/lib.rs"), "pub fn f() {}\n").unwrap();
+        std::fs::write(root.join("src/big.rs"), "// padding\n".repeat(1000)).unwrap();
+        std::fs::write(root.join("target/debug/out.rs"), "// generated\n").unwrap();
+        std::fs::write(root.join(".github/ci.yml"), "on: push\n").unwrap();
+        std::fs::write(root.join(".env.txt"), "KEY=1\n").unwrap();
+        std::fs::write(root.join("docs/guide.md"), "# Guide\n").unwrap();
+
+        let paths = |filter: ScanFilter| {
+            let output = TempDir::new().unwrap();
+            let cxp_path = output.path().join("out.cxp");
+            let mut builder = CxpBuilder::new(root);
+            builder.with_scan_filter(filter);
+            builder.scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+            let reader = CxpReader::open(&cxp_path).unwrap();
+            let mut paths: Vec<String> = reader.file_paths().into_iter().map(String::from).collect();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(
+            paths(ScanFilter::new()),
+            vec![".env.txt", ".github/ci.yml", "docs/guide.md", "src/big.rs", "src/lib.rs", "target/debug/out.rs"]
+        );
+        assert_eq!(
+            paths(ScanFilter::new().with_skip_hidden(true)),
+            vec!["docs/guide.md", "src/big.rs", "src/lib.rs", "target/debug/out.rs"]
+        );
+        assert_eq!(
+            paths(ScanFilter::new().with_exclude(["target", "*.md"])),
+            vec![".env.txt", ".github/ci.yml", "src/big.rs", "src/lib.rs"]
+        );
+        assert_eq!(
+            paths(ScanFilter::new().with_include(["src/**"]).with_max_file_size(1024)),
+            vec!["src/lib.rs"]
+        );
+
+        let mut builder = CxpBuilder::new(root);
+        builder.with_scan_filter(ScanFilter::new().with_include(["src/[a"]));
+        assert!(builder.scan().is_err());
+    }
+
+    #[test]
+    fn test_files_matching() {
+        let source = TempDir::new().unwrap();
+        let root = source.path();
+        std::fs::create_dir_all(root.join("src/util")).unwrap();
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "pub mod util;\n").unwrap();
+        std::fs::write(root.join("src/util/io.rs"), "pub fn read() {}\n").unwrap();
+        std::fs::write(root.join("docs/guide.md"), "# Guide\n").unwrap();
+        std::fs::write(root.join("build.rs"), "fn main() {}\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("out.cxp");
+        CxpBuilder::new(root).scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+        let reader = CxpReader::open(&cxp_path).unwrap();
+
+        let glob = PathPattern::glob("src/**/*.rs").unwrap();
+        assert_eq!(reader.files_matching(&glob), vec!["src/lib.rs", "src/util/io.rs"]);
+        assert!(reader.files_matching(&PathPattern::glob("*.py").unwrap()).is_empty());
+        assert!(PathPattern::glob("src/[a").is_err());
+        #[cfg(feature = "regex")]
+        {
+            let regex = PathPattern::regex(r"^[^/]+\.(rs|md)$").unwrap();
+            assert_eq!(reader.files_matching(&regex), vec!["build.rs"]);
+            assert!(PathPattern::regex("(unclosed").is_err());
+        }
+
+        let dest = output.path().join("extracted");
+        let stats = reader
+            .extract_all_with(&dest, &crate::ExtractOptions::new().with_pattern(glob))
+            .unwrap();
+        assert_eq!(stats.files_written, 2);
+        assert!(dest.join("src/util/io.rs").exists());
+        assert!(!dest.join("build.rs").exists());
+    }
+}
@@ -4,47 +4,117 @@
 //! ```text
 //! file.cxp (ZIP)
 //! ├── manifest.msgpack     # Metadata & stats
-//! ├── file_map.msgpack     # File -> Chunk references
+//! ├── file_map/            # File -> Chunk references
+//! │   ├── index.msgpack    # Shard path ranges
+//! │   └── 00000.msgpack    # Sorted shards (lazy, prefix-scoped loading)
 //! ├── chunks/
-//! │   ├── 0001.zst         # Compressed chunks
-//! │   ├── 0002.zst
-//! │   └── ...
+//! │   ├── packs.msgpack    # Offset of every chunk in the packs
+//! │   ├── pack-000.bin     # Compressed chunks back to back (see crate::packs)
+//! │   └── ...              # (older archives: one <id>.zst entry per chunk)
+//! ├── filters/             # Bloom filters for fast negative lookups
+//! │   ├── paths.bloom
+//! │   └── chunks.bloom
 //! ├── embeddings/          # Optional: Semantic search support
 //! │   ├── binary.bin       # Binary quantized embeddings
-//! │   ├── int8.bin         # Int8 quantized embeddings for rescoring
-//! │   └── index.hnsw       # HNSW index for fast search
-//! └── extensions/          # Optional app-specific data
-//!     └── ...
+//! │   ├── int8.bin         # Int8 quantized embeddings for rescoring (optional, see Int8Storage)
+//! │   ├── f16.bin|f32.bin  # Float rescoring vectors instead of int8.bin (see EmbeddingPrecision)
+//! │   └── index.hnsw       # HNSW index for fast search (index.ivf with IndexBackend::IvfFlat)
+//! ├── extensions/          # Optional app-specific data
+//! │   ├── summaries/       # Optional: file and directory summaries
+//! │   ├── graph/           # Optional: knowledge graph of files and functions
+//! │   ├── clusters/        # Optional: topic clusters of the chunk embeddings
+//! │   └── ...
+//! ├── annotations.msgpack  # Optional: user tags and notes per file
+//! ├── symbols.msgpack      # Optional: where functions and types are defined
+//! ├── snapshots/           # Optional: file maps of earlier versions of the tree
+//! └── toc.msgpack          # Table of contents (sections, extensions, indices)
 //! ```
 
-use crate::chunker::{chunk_content, Chunk, ChunkRef};
-use crate::compress::{compress, decompress};
+use crate::chunker::{chunk_id, Chunk, ChunkRef, Chunker, ChunkingAlgorithm};
+use crate::compress::{train_dictionary, ChunkCodec, Codec, DEFAULT_COMPRESSION_LEVEL, DEFAULT_DICTIONARY_SIZE, DICTIONARY_PATH, MAX_TRAINING_BYTES};
 use crate::dedup::ChunkStore;
-use crate::manifest::Manifest;
-use crate::extensions::{Extension, ExtensionManager};
-use crate::{is_text_file, is_image_file, CxpError, Result};
+use crate::manifest::{EmbeddingPrecision, IndexBackend, IndexParams, Int8Storage, Manifest, Redaction, RedactionReport};
+use crate::extensions::{Extension, ExtensionManager, ExtensionManifest};
+use crate::build_info::{BuildInfo, BUILD_INFO_KEY, BUILD_INFO_NAMESPACE, BUILD_INFO_VERSION};
+use crate::toc::{Toc, TOC_PATH};
+use crate::clusters::{ClusterMap, CLUSTERS_KEY, CLUSTERS_NAMESPACE};
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::clusters::ClusterOptions;
+use crate::graph::{GraphExtension, GraphPlugin, KnowledgeGraph, Node, GRAPH_KEY, GRAPH_NAMESPACE};
+use crate::keywords::KeywordExtractor;
+use crate::symbols::{Symbol, SymbolIndex, SymbolParser, SYMBOLS_PATH};
+use crate::summaries::{Summaries, SummariesExtension, Summarizer, SUMMARIES_KEY, SUMMARIES_NAMESPACE};
+use crate::gc::{GcOptions, GcStats};
+use crate::journal::{journal_base, Journal, JournalRecord};
+use crate::snapshots::{file_map_to_msgpack, SnapshotIndex, SnapshotInfo, SNAPSHOT_INDEX_PATH};
+use crate::map_shards::{ShardIndex, DEFAULT_SHARD_SIZE, SHARD_INDEX_PATH};
+use crate::packs::{PackIndex, PackWriter, DEFAULT_PACK_SIZE, PACKS_READER_VERSION, PACK_INDEX_PATH};
+use crate::bloom::{BloomFilter, CHUNK_FILTER_PATH, DEFAULT_FALSE_POSITIVE_RATE, PATH_FILTER_PATH};
+use crate::recursive::{CxpRef, CxpRefMeta, CxpStorage};
+use crate::cancel::CancellationToken;
+use crate::checkpoint::Checkpoint;
+use crate::access_log::AccessLog;
+use crate::annotations::{Annotations, ANNOTATIONS_PATH};
+use crate::filter::{CompiledFilter, PathPattern, ScanFilter};
+use crate::progress::{NoProgress, ProgressReporter};
+#[cfg(feature = "scanner")]
+use crate::scanner::ScanPlan;
+use crate::records::Record;
+use crate::extract::{safe_join, should_write, write_entry, ExtractOptions, ExtractStats, OverwritePolicy};
+use crate::provenance::{git_head, Provenance};
+use crate::usage::UsageRecorder;
+use crate::backend::{open_zip, ArchiveBackend, ArchivePool, FileBackend, MemoryBackend, PooledArchive, SharedReaderBackend};
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::cancel::Deadline;
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::manager::SearchOptions;
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::embedding_rows::{EmbeddingQuery, EmbeddingRows, LoadOptions};
+use crate::global_index::{GlobalIndex, GLOBAL_INDEX_PATH};
+use crate::temp::{TempGuard, TempPolicy};
+use crate::{is_text_file, CxpError, Result, ResultExt, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+#[cfg(feature = "multimodal")]
+use crate::is_image_file;
 
 // Embedding types (shared across embeddings and search features)
-#[cfg(any(feature = "embeddings", feature = "embeddings-wasm"))]
+#[cfg(all(feature = "embeddings", feature = "search"))]
 use crate::{BinaryEmbedding, Int8Embedding, QuantizedEmbeddings};
 
 // Search-specific types
 #[cfg(all(feature = "embeddings", feature = "search"))]
-use crate::{EmbeddingEngine, EmbeddingModel, HnswConfig, HnswIndex};
+use crate::{EmbeddingEngine, EmbeddingModel, SearchResult, VectorIndex};
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::fusion::{reciprocal_rank_fusion, DedupBy, FusedResult, Fusion};
 
 // Multimodal engine and unified index
 #[cfg(all(feature = "multimodal", feature = "search"))]
-use crate::{MultimodalEngine, UnifiedIndex, HnswConfig};
+use crate::{MultimodalEngine, UnifiedIndex};
+
+#[cfg(all(feature = "search", any(feature = "embeddings", feature = "multimodal")))]
+use crate::HnswConfig;
+#[cfg(all(feature = "search", any(feature = "embeddings", feature = "multimodal")))]
+use crate::embeddings::{BatchSizer, DEFAULT_BATCH_SIZE};
+
+#[cfg(feature = "redact")]
+use crate::redact::{scrub, Redactor, Scrubber};
+#[cfg(feature = "git")]
+use crate::git_history::{GitHistory, GitHistoryExtension, GIT_HISTORY_KEY, GIT_HISTORY_NAMESPACE};
 
 // Serialization functions for embeddings (only used by the embeddings feature, not multimodal-only)
 #[cfg(all(feature = "embeddings", feature = "search"))]
-use crate::{serialize_binary_embeddings, deserialize_binary_embeddings, serialize_int8_embeddings, deserialize_int8_embeddings};
+use crate::{serialize_binary_embeddings, serialize_int8_embeddings};
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::serialize_float_embeddings;
 
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 use walkdir::WalkDir;
 use zip::write::FileOptions;
 use zip::{ZipArchive, ZipWriter, CompressionMethod};
@@ -53,11 +123,15 @@ use zip::{ZipArchive, ZipWriter, CompressionMethod};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct FileMap {
     /// Map of file path -> list of chunk references
-    pub files: HashMap<String, FileEntry>,
+    pub files: BTreeMap<String, FileEntry>,
 }
 
 /// Entry for a single file in the file map
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Written as a positional MessagePack array. Fields added later go at the
+/// end: readers fill in the ones an older archive lacks and skip the ones a
+/// newer archive appends (see the `Deserialize` impl).
+#[derive(Debug, Clone, Serialize)]
 pub struct FileEntry {
     /// Original file path (relative)
     pub path: String,
@@ -68,8 +142,79 @@ pub struct FileEntry {
     /// Chunk references that make up this file
     pub chunks: Vec<ChunkRef>,
     /// Is this an image file? (only relevant with multimodal feature)
-    #[serde(default)]
     pub is_image: bool,
+    /// Source modification time (used for tiering)
+    pub modified: Option<chrono::DateTime<chrono::Utc>>,
+    /// Origin path, git commit and license at build time (None if not recorded)
+    pub provenance: Option<Provenance>,
+    /// Characteristic terms of the file (filled by `CxpBuilder::with_keywords`)
+    pub keywords: Vec<String>,
+}
+
+impl<'de> Deserialize<'de> for FileEntry {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        use serde::de::{Error, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+        /// Field-named form used by self-describing formats (e.g. JSON journals)
+        #[derive(Deserialize)]
+        struct NamedEntry {
+            path: String,
+            extension: String,
+            size: u64,
+            chunks: Vec<ChunkRef>,
+            #[serde(default)]
+            is_image: bool,
+            #[serde(default)]
+            modified: Option<chrono::DateTime<chrono::Utc>>,
+            #[serde(default)]
+            provenance: Option<Provenance>,
+            #[serde(default)]
+            keywords: Vec<String>,
+        }
+
+        struct EntryVisitor;
+
+        impl<'de> Visitor<'de> for EntryVisitor {
+            type Value = FileEntry;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a file entry")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<FileEntry, A::Error> {
+                let entry = FileEntry {
+                    path: seq.next_element()?.ok_or_else(|| A::Error::invalid_length(0, &self))?,
+                    extension: seq.next_element()?.ok_or_else(|| A::Error::invalid_length(1, &self))?,
+                    size: seq.next_element()?.ok_or_else(|| A::Error::invalid_length(2, &self))?,
+                    chunks: seq.next_element()?.ok_or_else(|| A::Error::invalid_length(3, &self))?,
+                    // Optional fields missing from older archives
+                    is_image: seq.next_element()?.unwrap_or_default(),
+                    modified: seq.next_element()?.unwrap_or_default(),
+                    provenance: seq.next_element()?.unwrap_or_default(),
+                    keywords: seq.next_element()?.unwrap_or_default(),
+                };
+                // Fields appended by newer writers
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(entry)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<FileEntry, A::Error> {
+                let named = NamedEntry::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+                Ok(FileEntry {
+                    path: named.path,
+                    extension: named.extension,
+                    size: named.size,
+                    chunks: named.chunks,
+                    is_image: named.is_image,
+                    modified: named.modified,
+                    provenance: named.provenance,
+                    keywords: named.keywords,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(EntryVisitor)
+    }
 }
 
 /// A CXP file handle
@@ -89,8 +234,12 @@ impl CxpFile {
         let path = path.as_ref();
         let file = File::open(path)
             .map_err(|e| CxpError::InvalidFormat(format!("Failed to open CXP file: {}", e)))?;
+        Self::from_reader(file)
+    }
 
-        let mut archive = ZipArchive::new(file)
+    /// Open a CXP archive from any seekable source (e.g. an embedded child's bytes)
+    pub fn from_reader<R: Read + std::io::Seek>(reader: R) -> Result<Self> {
+        let mut archive = ZipArchive::new(reader)
             .map_err(|e| CxpError::InvalidFormat(format!("Failed to read CXP archive: {}", e)))?;
 
         // Read manifest
@@ -102,17 +251,10 @@ impl CxpFile {
                 .map_err(|e| CxpError::InvalidFormat(format!("Failed to read manifest: {}", e)))?;
             Manifest::from_msgpack(&data)?
         };
+        manifest.check_reader_version()?;
 
         // Read file map
-        let file_map = {
-            let mut entry = archive.by_name("file_map.msgpack")
-                .map_err(|e| CxpError::InvalidFormat(format!("No file_map found: {}", e)))?;
-            let mut data = Vec::new();
-            entry.read_to_end(&mut data)
-                .map_err(|e| CxpError::InvalidFormat(format!("Failed to read file_map: {}", e)))?;
-            rmp_serde::from_slice(&data)
-                .map_err(|e| CxpError::Serialization(format!("Failed to parse file_map: {}", e)))?
-        };
+        let file_map = read_file_map(&mut archive)?;
 
         // Create chunk store (chunks loaded on demand)
         let chunk_store = ChunkStore::new();
@@ -171,6 +313,26 @@ impl CxpFile {
     pub fn file_count(&self) -> usize {
         self.file_map.files.len()
     }
+
+    /// Remove chunks no live file map references from the archive at `path`
+    ///
+    /// Drops snapshots beyond `options.keep_last` first. See [`crate::gc`].
+    pub fn gc<P: AsRef<Path>>(path: P, options: &GcOptions) -> Result<GcStats> {
+        crate::gc::collect_garbage(path, options)
+    }
+}
+
+/// A file whose content is already in memory
+#[derive(Debug, Clone)]
+pub(crate) struct PendingFile {
+    /// Path inside the archive
+    pub path: String,
+    /// File content
+    pub content: Vec<u8>,
+    /// Modification time
+    pub modified: Option<chrono::DateTime<chrono::Utc>>,
+    /// Origin, commit and license
+    pub provenance: Option<Provenance>,
 }
 
 /// Builder for creating CXP files
@@ -191,8 +353,85 @@ pub struct CxpBuilder {
     file_map: FileMap,
     /// Chunk store with deduplication
     chunk_store: ChunkStore,
+    /// Files per file map shard
+    shard_size: usize,
+    /// Checked between files, embedding batches and chunks
+    cancellation: CancellationToken,
+    /// Receives scan, chunk, embedding and write progress
+    progress: Arc<dyn ProgressReporter>,
+    /// Journal of processed files and embeddings for resuming (None disables it)
+    checkpoint: Option<Checkpoint>,
+    /// Child CXPs stored inside the archive (path in ZIP -> archive bytes)
+    embedded_children: BTreeMap<String, Vec<u8>>,
+    /// Index over the files of all children (written to `global_index.msgpack`)
+    global_index: Option<GlobalIndex>,
     /// Extension manager for app-specific data
     extension_manager: ExtensionManager,
+    /// Build telemetry written to `extensions/build_info/` (None disables it)
+    build_info: Option<BuildInfo>,
+    /// Splits file content into chunks
+    chunker: Arc<dyn Chunker>,
+    /// Chunk compression algorithm and level
+    compression: Codec,
+    /// Train a zstd dictionary over the chunks and compress with it
+    train_dictionary: bool,
+    /// Which embeddings keep their int8 vector
+    int8_storage: Int8Storage,
+    /// Format of the stored rescoring vectors
+    embedding_precision: EmbeddingPrecision,
+    /// Hamming distance under which a chunk reuses an earlier chunk's embedding
+    embedding_dedup: Option<u32>,
+    /// HNSW graph parameters (None for the defaults)
+    index_params: Option<IndexParams>,
+    /// Vector index kind for text embeddings
+    index_backend: IndexBackend,
+    /// Texts per embedding batch (adapted during the build; None for the default)
+    embedding_batch_size: Option<usize>,
+    /// User tags and notes per file
+    annotations: Annotations,
+    /// Record origin path, git commit and license of every file (off by default)
+    record_provenance: bool,
+    /// Files whose content is already in memory, processed by `process()`
+    pending: Vec<PendingFile>,
+    /// Leave the file list alone in `scan()` (sources are not on disk)
+    skip_scan: bool,
+    /// Which files `scan()` picks up
+    scan_filter: ScanFilter,
+    /// Files selected by a profile-aware scan (replaces the directory walk)
+    #[cfg(feature = "scanner")]
+    scan_plan: Option<ScanPlan>,
+    /// Replaces secrets in file content before chunking (None disables it)
+    #[cfg(feature = "redact")]
+    redactor: Option<Redactor>,
+    /// Additional detectors (e.g. PII) applied together with the redactor
+    #[cfg(feature = "redact")]
+    scrubbers: Vec<Arc<dyn Scrubber>>,
+    /// Recent commits written to `extensions/git/` (None disables it)
+    #[cfg(feature = "git")]
+    git_history: Option<GitHistory>,
+    /// Keep author names and emails in the git history
+    #[cfg(feature = "git")]
+    git_authors: bool,
+    /// Writes `extensions/summaries/` (None disables it)
+    summarizer: Option<Arc<dyn Summarizer>>,
+    /// Fills file keywords and manifest topics (None disables it)
+    keywords: Option<KeywordExtractor>,
+    /// Populate `extensions/graph/` (empty disables it)
+    graph_plugins: Vec<Arc<dyn GraphPlugin>>,
+    /// Writes `symbols.msgpack` (None disables it)
+    symbol_parser: Option<Arc<dyn SymbolParser>>,
+    /// Labeled generations of the file map taken by `snapshot()`
+    snapshots: SnapshotIndex,
+    /// File maps of the snapshots (parallel to `snapshots`)
+    snapshot_maps: Vec<FileMap>,
+    /// Chunks only snapshots reference any more
+    snapshot_chunks: ChunkStore,
+    /// Write `build()` output to a temp file and rename it into place
+    atomic_write: bool,
+    /// Store chunks in packs instead of one entry each (see [`crate::packs`])
+    packed_chunks: bool,
+    /// The archive as last written by `build()` or `append_journal()`
+    written: Option<WrittenArchive>,
     /// Embedding engine (optional)
     #[cfg(all(feature = "embeddings", feature = "search"))]
     embedding_engine: Option<EmbeddingEngine>,
@@ -202,12 +441,27 @@ pub struct CxpBuilder {
     /// Chunk embeddings (optional)
     #[cfg(all(feature = "embeddings", feature = "search"))]
     chunk_embeddings: Option<QuantizedEmbeddings>,
-    /// HNSW search index (optional - used for text-only embeddings)
+    /// Chunk hashes in embedding order (embedding ID -> chunk)
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    embedding_chunks: Vec<String>,
+    /// Near-duplicate chunk hash -> chunk whose embedding stands in for it
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    embedding_aliases: BTreeMap<String, String>,
+    /// Embeddings of the previous build, reused for unchanged chunks
     #[cfg(all(feature = "embeddings", feature = "search"))]
-    search_index: Option<HnswIndex>,
+    embedding_cache: HashMap<String, (BinaryEmbedding, Int8Embedding, Option<Vec<f32>>)>,
+    /// Search index (optional - used for text-only embeddings)
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    search_index: Option<VectorIndex>,
     /// Unified index (optional - used for multimodal embeddings)
     #[cfg(all(feature = "multimodal", feature = "search"))]
     unified_index: Option<UnifiedIndex>,
+    /// Unified index vector ID of each embedded chunk (by hash)
+    #[cfg(all(feature = "multimodal", feature = "search"))]
+    unified_chunks: HashMap<String, u64>,
+    /// Unified index vector ID of each embedded image (by relative path)
+    #[cfg(all(feature = "multimodal", feature = "search"))]
+    unified_images: HashMap<String, u64>,
 }
 
 impl CxpBuilder {
@@ -220,10 +474,54 @@ impl CxpBuilder {
             image_files: Vec::new(),
             #[cfg(feature = "multimodal")]
             process_images: false,
-            manifest: Manifest::new(),
+            manifest: Manifest {
+                redactions: cfg!(feature = "redact").then(RedactionReport::default),
+                ..Manifest::new()
+            },
             file_map: FileMap::default(),
             chunk_store: ChunkStore::new(),
+            shard_size: DEFAULT_SHARD_SIZE,
+            cancellation: CancellationToken::default(),
+            progress: Arc::new(NoProgress),
+            checkpoint: None,
+            embedded_children: BTreeMap::new(),
+            global_index: None,
             extension_manager: ExtensionManager::new(),
+            build_info: Some(BuildInfo::current()),
+            chunker: ChunkingAlgorithm::default().chunker(),
+            compression: Codec::default(),
+            train_dictionary: false,
+            int8_storage: Int8Storage::default(),
+            embedding_precision: EmbeddingPrecision::default(),
+            embedding_dedup: None,
+            index_params: None,
+            index_backend: IndexBackend::default(),
+            embedding_batch_size: None,
+            annotations: Annotations::new(),
+            record_provenance: false,
+            pending: Vec::new(),
+            skip_scan: false,
+            scan_filter: ScanFilter::default(),
+            #[cfg(feature = "scanner")]
+            scan_plan: None,
+            #[cfg(feature = "redact")]
+            redactor: Some(Redactor::default()),
+            #[cfg(feature = "redact")]
+            scrubbers: Vec::new(),
+            #[cfg(feature = "git")]
+            git_history: None,
+            #[cfg(feature = "git")]
+            git_authors: false,
+            summarizer: None,
+            keywords: None,
+            graph_plugins: Vec::new(),
+            symbol_parser: None,
+            snapshots: SnapshotIndex::new(),
+            snapshot_maps: Vec::new(),
+            snapshot_chunks: ChunkStore::new(),
+            atomic_write: false,
+            packed_chunks: true,
+            written: None,
             #[cfg(all(feature = "embeddings", feature = "search"))]
             embedding_engine: None,
             #[cfg(all(feature = "multimodal", feature = "search"))]
@@ -231,829 +529,3898 @@ impl CxpBuilder {
             #[cfg(all(feature = "embeddings", feature = "search"))]
             chunk_embeddings: None,
             #[cfg(all(feature = "embeddings", feature = "search"))]
+            embedding_chunks: Vec::new(),
+            #[cfg(all(feature = "embeddings", feature = "search"))]
+            embedding_aliases: BTreeMap::new(),
+            #[cfg(all(feature = "embeddings", feature = "search"))]
+            embedding_cache: HashMap::new(),
+            #[cfg(all(feature = "embeddings", feature = "search"))]
             search_index: None,
             #[cfg(all(feature = "multimodal", feature = "search"))]
             unified_index: None,
+            #[cfg(all(feature = "multimodal", feature = "search"))]
+            unified_chunks: HashMap::new(),
+            #[cfg(all(feature = "multimodal", feature = "search"))]
+            unified_images: HashMap::new(),
         }
     }
 
-    /// Enable image processing (requires multimodal feature)
-    #[cfg(feature = "multimodal")]
-    pub fn with_images(&mut self) -> &mut Self {
-        self.process_images = true;
-        self
+    /// Source directory being archived
+    pub fn source_dir(&self) -> &Path {
+        &self.source_dir
     }
 
-    /// Scan the source directory for files
-    pub fn scan(&mut self) -> Result<&mut Self> {
-        tracing::info!("Scanning directory: {:?}", self.source_dir);
+    /// Manifest of the archive being built
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
 
-        self.files = WalkDir::new(&self.source_dir)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .filter(|e| {
-                // Filter by text extensions
-                e.path()
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-                    .map(is_text_file)
-                    .unwrap_or(false)
-            })
-            .map(|e| e.path().to_path_buf())
-            .collect();
+    /// Set the number of files per file map shard
+    pub fn with_shard_size(&mut self, shard_size: usize) -> &mut Self {
+        self.shard_size = shard_size.max(1);
+        self
+    }
 
-        tracing::info!("Found {} text files to process", self.files.len());
+    /// Formerly set where index temp files were written; has no effect
+    #[deprecated(since = "1.1.0", note = "indexes are serialized in memory; no temp files are written")]
+    pub fn with_temp_policy(&mut self, _policy: TempPolicy) -> &mut Self {
+        self
+    }
 
-        // Scan for images if enabled
-        #[cfg(feature = "multimodal")]
-        if self.process_images {
-            self.image_files = WalkDir::new(&self.source_dir)
-                .follow_links(true)
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(|e| e.file_type().is_file())
-                .filter(|e| {
-                    // Filter by image extensions
-                    e.path()
-                        .extension()
-                        .and_then(|ext| ext.to_str())
-                        .map(is_image_file)
-                        .unwrap_or(false)
-                })
-                .map(|e| e.path().to_path_buf())
-                .collect();
+    /// Formerly the temp file policy of `build()`; always the default policy
+    #[deprecated(since = "1.1.0", note = "indexes are serialized in memory; no temp files are written")]
+    pub fn temp_policy(&self) -> &TempPolicy {
+        static DEFAULT: TempPolicy = TempPolicy::System;
+        &DEFAULT
+    }
 
-            tracing::info!("Found {} image files to process", self.image_files.len());
-        }
+    /// Abort scanning, processing, embedding and building once `token` is cancelled
+    pub fn with_cancellation(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Report scan, chunking, embedding and write progress to `reporter`
+    pub fn with_progress(&mut self, reporter: Arc<dyn ProgressReporter>) -> &mut Self {
+        self.progress = reporter;
+        self
+    }
 
+    /// Journal processed files, chunks and embeddings to `dir`
+    ///
+    /// Rerunning an interrupted build with the same directory skips the work
+    /// already journaled; the journal is deleted once `build()` succeeds.
+    /// See [`crate::checkpoint`].
+    pub fn with_checkpoint<P: AsRef<Path>>(&mut self, dir: P) -> Result<&mut Self> {
+        let dir = dir.as_ref();
+        let checkpoint = Checkpoint::open(dir).with_context(|| format!("Failed to open checkpoint {}", dir.display()))?;
+        self.checkpoint = Some(checkpoint);
         Ok(self)
     }
 
-    /// Process all scanned files
-    pub fn process(&mut self) -> Result<&mut Self> {
-        let source_dir = self.source_dir.clone();
+    /// Write `build()` output to a temp sibling and rename it over the output
+    ///
+    /// A crash or failed build then leaves the previous archive intact
+    /// instead of a partially written one.
+    pub fn with_atomic_write(&mut self, enabled: bool) -> &mut Self {
+        self.atomic_write = enabled;
+        self
+    }
 
-        // Process text files and collect chunks
-        let results: Vec<_> = self.files
-            .iter()
-            .filter_map(|path| {
-                self.process_file(path, &source_dir).ok()
-            })
-            .collect();
+    /// Store chunks in a few large packs (the default) or one entry per chunk
+    ///
+    /// Readers older than packs (see [`crate::packs`]) only understand one
+    /// entry per chunk, so packed archives require at least
+    /// [`PACKS_READER_VERSION`](crate::packs::PACKS_READER_VERSION).
+    pub fn with_packed_chunks(&mut self, enabled: bool) -> &mut Self {
+        self.packed_chunks = enabled;
+        self
+    }
 
-        // Add to chunk store and file map
-        for (entry, chunks) in results {
-            let chunk_refs = self.chunk_store.add_many(chunks);
+    /// Store a global index over the children's files in the archive
+    pub fn with_global_index(&mut self, index: GlobalIndex) -> &mut Self {
+        self.global_index = Some(index);
+        self
+    }
 
-            // Update manifest with file type info
-            self.manifest.add_file_type(&entry.extension, &entry.path, entry.size);
+    /// Enable or disable recording build telemetry (enabled by default)
+    pub fn with_build_info(&mut self, enabled: bool) -> &mut Self {
+        self.build_info = enabled.then(BuildInfo::current);
+        self
+    }
 
-            // Store file entry with chunk refs
-            let entry_with_refs = FileEntry {
-                chunks: chunk_refs,
-                ..entry
-            };
-            self.file_map.files.insert(entry_with_refs.path.clone(), entry_with_refs);
-        }
+    /// Queue a file whose content is already in memory for `process()`
+    pub(crate) fn add_pending(&mut self, file: PendingFile) -> &mut Self {
+        self.pending.push(file);
+        self
+    }
 
-        // Process images if enabled (store as single chunks - whole image = 1 chunk)
-        #[cfg(feature = "multimodal")]
-        if self.process_images {
-            for path in &self.image_files.clone() {
-                if let Ok((entry, chunk)) = self.process_image(path, &source_dir) {
-                    // Create chunk ref before adding to store
-                    let chunk_ref = ChunkRef::from(&chunk);
-                    self.chunk_store.add(chunk);
+    /// Only process files added with `add_pending()`; `scan()` becomes a no-op
+    #[cfg(feature = "git")]
+    pub(crate) fn skip_scan(&mut self) -> &mut Self {
+        self.skip_scan = true;
+        self
+    }
 
-                    // Update manifest with file type info
-                    self.manifest.add_file_type(&entry.extension, &entry.path, entry.size);
+    /// Enable or disable recording per-file provenance (disabled by default)
+    ///
+    /// Call before `process()`. Each file entry gets its absolute origin
+    /// path, the git commit of the source repository and its license. Off by
+    /// default since origin paths reveal the local directory layout. Files
+    /// read by `from_git()` record their path within the repository instead.
+    pub fn with_provenance(&mut self, enabled: bool) -> &mut Self {
+        self.record_provenance = enabled;
+        self
+    }
 
-                    // Store file entry with chunk ref
-                    let entry_with_ref = FileEntry {
-                        chunks: vec![chunk_ref],
-                        is_image: true,
-                        ..entry
-                    };
-                    self.file_map.files.insert(entry_with_ref.path.clone(), entry_with_ref);
-                }
-            }
+    /// Set the chunking algorithm (default: Gear-based FastCDC)
+    ///
+    /// Call before `process()`; the choice is recorded in the manifest.
+    pub fn with_chunking(&mut self, algorithm: ChunkingAlgorithm) -> &mut Self {
+        self.chunker = algorithm.chunker();
+        self
+    }
 
-            tracing::info!("Processed {} image files", self.image_files.len());
-        }
+    /// Use a custom chunker (call before `process()`)
+    pub fn with_chunker(&mut self, chunker: Arc<dyn Chunker>) -> &mut Self {
+        self.chunker = chunker;
+        self
+    }
 
-        // Update manifest stats
-        let dedup_stats = self.chunk_store.stats();
-        self.manifest.stats.total_files = self.file_map.files.len();
-        self.manifest.stats.unique_chunks = dedup_stats.unique_chunks;
-        self.manifest.stats.original_size_bytes = dedup_stats.total_bytes as u64;
-        self.manifest.stats.dedup_savings_percent = dedup_stats.savings_percent();
+    /// Set the chunk compression algorithm and level (default: zstd level 3)
+    pub fn with_compression(&mut self, codec: Codec) -> &mut Self {
+        self.compression = codec;
+        self
+    }
 
-        tracing::info!(
-            "Processed {} files, {} unique chunks, {:.1}% dedup savings",
-            self.manifest.stats.total_files,
-            self.manifest.stats.unique_chunks,
-            self.manifest.stats.dedup_savings_percent
-        );
+    /// Compress chunks with a zstd dictionary trained on this archive's chunks
+    ///
+    /// The dictionary is stored at `compression/dict.zstd`. It is dropped when
+    /// it does not pay for its own size (few or dissimilar chunks). Only zstd
+    /// supports dictionaries; LZ4 builds ignore this.
+    pub fn with_trained_dictionary(&mut self) -> &mut Self {
+        self.train_dictionary = true;
+        self
+    }
 
-        Ok(self)
+    /// Store int8 embeddings for all chunks, hot-tier chunks only, or none
+    ///
+    /// Skipping int8 roughly halves the embedding section; search then ranks
+    /// chunks without an int8 vector by their binary similarity.
+    pub fn with_int8_storage(&mut self, storage: Int8Storage) -> &mut Self {
+        self.int8_storage = storage;
+        self
     }
 
-    /// Enable embedding generation (requires both "embeddings" and "search" features)
+    /// Store rescoring vectors as binary only, int8 (default), f16 or f32
     ///
-    /// This loads an embedding model and will generate embeddings for all chunks
-    /// during the build process. The embeddings are stored in the CXP file along
-    /// with a HNSW index for fast semantic search.
+    /// F16 and f32 rescore without quantization error at 2x and 4x the size
+    /// of int8; `with_int8_storage` still selects which chunks keep them.
+    pub fn with_embedding_precision(&mut self, precision: EmbeddingPrecision) -> &mut Self {
+        self.embedding_precision = precision;
+        self
+    }
+
+    /// Skip embedding chunks within `max_hamming` bits of an already-embedded chunk
     ///
-    /// # Arguments
-    /// * `model_path` - Path to the directory containing model.onnx and tokenizer.json
-    /// * `model` - The embedding model to use (e.g., EmbeddingModel::MiniLM)
+    /// Near-duplicates (templates, boilerplate) share the embedding of the
+    /// first such chunk. The alias mapping is stored at
+    /// `embeddings/aliases.msgpack` so search results still resolve to every
+    /// aliased chunk.
+    pub fn with_embedding_dedup(&mut self, max_hamming: u32) -> &mut Self {
+        self.embedding_dedup = Some(max_hamming);
+        self
+    }
+
+    /// Build the search index with these HNSW parameters (default M 16, ef 128/64)
     ///
-    /// # Example
-    /// ```ignore
-    /// builder
-    ///     .scan()?
-    ///     .with_embeddings("./models/all-MiniLM-L6-v2", EmbeddingModel::MiniLM)?
-    ///     .process()?
-    ///     .build("output.cxp")?;
-    /// ```
-    #[cfg(all(feature = "embeddings", feature = "search"))]
-    pub fn with_embeddings<P: AsRef<Path>>(
-        &mut self,
-        model_path: P,
-        model: EmbeddingModel,
-    ) -> Result<&mut Self> {
-        tracing::info!("Loading embedding model: {}", model.name());
+    /// The parameters are stored in the manifest so readers load the index
+    /// with matching settings.
+    pub fn with_index_params(&mut self, params: IndexParams) -> Result<&mut Self> {
+        params.validate()?;
+        self.index_params = Some(params);
+        Ok(self)
+    }
 
-        let engine = EmbeddingEngine::load(model_path, model)?;
+    /// Index text embeddings with `backend` instead of HNSW
+    ///
+    /// `IndexBackend::IvfFlat` trades some query latency and recall for far
+    /// less memory on large archives. The backend is recorded in the manifest.
+    pub fn with_index_backend(&mut self, backend: IndexBackend) -> Result<&mut Self> {
+        backend.validate()?;
+        self.index_backend = backend;
+        Ok(self)
+    }
 
-        // Update manifest with embedding info
-        self.manifest.embedding_model = Some(model.name().to_string());
-        self.manifest.embedding_dim = Some(model.dimensions());
+    /// Start embedding with `size` texts per batch (default 32)
+    ///
+    /// The size is halved when a batch runs out of memory and doubled while
+    /// batches succeed; the size a build ended with is logged and kept for
+    /// the next build with this builder.
+    pub fn with_embedding_batch_size(&mut self, size: usize) -> &mut Self {
+        self.embedding_batch_size = Some(size);
+        self
+    }
 
-        self.embedding_engine = Some(engine);
+    /// Build telemetry recorded so far
+    pub fn build_info(&self) -> Option<&BuildInfo> {
+        self.build_info.as_ref()
+    }
 
-        Ok(self)
+    /// Set a custom manifest metadata entry (e.g. owner or project)
+    pub fn with_metadata(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.manifest.metadata.insert(key.into(), value.into());
+        self
     }
 
-    /// Enable multimodal embedding generation (requires both "multimodal" and "search" features)
+    /// Tag a file (path relative to the source directory, e.g. `src/main.rs`)
     ///
-    /// This loads a SigLIP 2 model and will generate embeddings for both text chunks
-    /// and images during the build process. The embeddings share the same 512-dimensional
-    /// vector space, enabling cross-modal search (e.g., search images with text queries).
-    ///
-    /// # Arguments
-    /// * `model_path` - Path to the directory containing image_encoder.onnx, text_encoder.onnx, and tokenizer.json
-    ///
-    /// # Example
-    /// ```ignore
-    /// builder
-    ///     .with_images()
-    ///     .scan()?
-    ///     .with_multimodal_embeddings("./models/siglip2")?
-    ///     .process()?
-    ///     .build("output.cxp")?;
-    /// ```
-    #[cfg(all(feature = "multimodal", feature = "search"))]
-    pub fn with_multimodal_embeddings<P: AsRef<Path>>(
-        &mut self,
-        model_path: P,
-    ) -> Result<&mut Self> {
-        tracing::info!("Loading multimodal model: SigLIP 2");
-
-        let engine = MultimodalEngine::load(model_path)?;
+    /// Tags of paths that are not part of the build are dropped when writing.
+    pub fn tag_file<I, S>(&mut self, path: &str, tags: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.annotations.tag(path, tags);
+        self
+    }
 
-        // Update manifest with embedding info
-        self.manifest.embedding_model = Some("SigLIP-2".to_string());
-        self.manifest.embedding_dim = Some(engine.dimensions());
+    /// Attach a key/value note to a file
+    pub fn annotate_file(&mut self, path: &str, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.annotations.annotate(path, key, value);
+        self
+    }
 
-        self.multimodal_engine = Some(engine);
+    /// Set which files `scan()` picks up (include/exclude globs, size limit, hidden files)
+    ///
+    /// By default every file with a supported extension is packed, hidden ones included.
+    /// Files added from memory are not filtered.
+    pub fn with_scan_filter(&mut self, filter: ScanFilter) -> &mut Self {
+        self.scan_filter = filter;
+        self
+    }
 
-        Ok(self)
+    /// Pack the files a profile-aware scan selected instead of walking the source directory
+    ///
+    /// Only planned files under the source directory are packed, still subject
+    /// to the scan filter. Each file is tagged with its tier (`tier:hot`, ...),
+    /// which `CxpSplitter` uses for `SplitMode::ByTier`. If all planned files
+    /// share one tier, it becomes the archive's tier.
+    #[cfg(feature = "scanner")]
+    pub fn with_scan_plan(&mut self, plan: ScanPlan) -> &mut Self {
+        let mut tiers = plan.files.iter().map(|f| f.tier);
+        if let Some(first) = tiers.next() {
+            if tiers.all(|tier| tier == first) {
+                self.manifest.tier = first.into();
+            }
+        }
+        self.with_metadata("scan.profile", plan.profile.name().to_lowercase());
+        self.scan_plan = Some(plan);
+        self
     }
 
-    /// Generate embeddings for all chunks
+    /// Add a file from memory (generated content, API responses, ...)
     ///
-    /// This is automatically called during `build()` if embeddings are enabled.
-    /// You can also call it manually after `process()` to inspect the embeddings.
-    #[cfg(all(feature = "embeddings", feature = "search"))]
-    pub fn generate_embeddings(&mut self) -> Result<&mut Self> {
-        let engine = self.embedding_engine.as_ref()
-            .ok_or_else(|| CxpError::Embedding(
-                "Embedding engine not initialized. Call with_embeddings() first.".to_string()
-            ))?;
+    /// `path` is the relative, `/`-separated path inside the archive. The file
+    /// is chunked in `process()` together with scanned files and replaces a
+    /// scanned file, or a file added earlier, with the same path. It has no
+    /// modification time, so builds from the same input are reproducible.
+    /// Fails on paths that are absolute or contain `..`.
+    pub fn add_file_bytes(&mut self, path: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Result<&mut Self> {
+        let path = path.into();
+        validate_memory_path(&path)?;
+        Ok(self.add_pending(PendingFile {
+            path,
+            content: bytes.into(),
+            modified: None,
+            provenance: None,
+        }))
+    }
 
-        tracing::info!("Generating embeddings for {} unique chunks", self.chunk_store.len());
+    /// Add a text file from memory (see `add_file_bytes()`)
+    pub fn add_file_str(&mut self, path: impl Into<String>, text: &str) -> Result<&mut Self> {
+        self.add_file_bytes(path, text.as_bytes())
+    }
 
-        // Collect all chunk texts
-        let chunks: Vec<_> = self.chunk_store.chunks().collect();
-        let chunk_texts: Vec<&str> = chunks
+    /// Add text records as files, with their metadata as annotation notes
+    ///
+    /// Records are added like `add_file_str()`.
+    pub fn add_records<I>(&mut self, records: I) -> Result<&mut Self>
+    where
+        I: IntoIterator<Item = Record>,
+    {
+        for record in records {
+            validate_memory_path(&record.path)?;
+            for (key, value) in record.notes() {
+                self.annotations.annotate(&record.path, key, value);
+            }
+            self.add_file_bytes(record.path, record.text.into_bytes())?;
+        }
+        Ok(self)
+    }
+
+    /// Enable or disable secret redaction (enabled by default)
+    ///
+    /// Call before `process()`. Secrets are replaced with `[REDACTED:<kind>]`
+    /// and listed in the manifest's redaction report.
+    #[cfg(feature = "redact")]
+    pub fn with_redaction(&mut self, enabled: bool) -> &mut Self {
+        self.redactor = enabled.then(Redactor::default);
+        if enabled {
+            self.manifest.redactions.get_or_insert_with(RedactionReport::default);
+        } else if self.scrubbers.is_empty() {
+            self.manifest.redactions = None;
+        }
+        self
+    }
+
+    /// Redact secrets with a custom redactor (e.g. with extra patterns)
+    #[cfg(feature = "redact")]
+    pub fn with_redactor(&mut self, redactor: Redactor) -> &mut Self {
+        self.redactor = Some(redactor);
+        self.manifest.redactions.get_or_insert_with(RedactionReport::default);
+        self
+    }
+
+    /// Attach a detector (e.g. [`crate::pii::RegexScrubber::email`]) to the redaction pass
+    ///
+    /// Runs even with secret redaction disabled; matches are replaced with
+    /// `[REDACTED:<kind>]` and listed in the manifest's redaction report.
+    #[cfg(feature = "redact")]
+    pub fn with_scrubber(&mut self, scrubber: impl Scrubber + 'static) -> &mut Self {
+        self.scrubbers.push(Arc::new(scrubber));
+        self.manifest.redactions.get_or_insert_with(RedactionReport::default);
+        self
+    }
+
+    /// Summarize every text file and directory into `extensions/summaries/`
+    ///
+    /// Files are summarized after redaction, from the content that is
+    /// archived. See [`crate::summaries`] for the built-in summarizers.
+    pub fn with_summarizer(&mut self, summarizer: impl Summarizer + 'static) -> &mut Self {
+        self.summarizer = Some(Arc::new(summarizer));
+        self
+    }
+
+    /// Extract keywords of every text file and topics of the archive
+    ///
+    /// Keywords land in `FileEntry::keywords` (and from there in the
+    /// `GlobalIndex`), topics in `Manifest::topics`. See [`crate::keywords`].
+    pub fn with_keywords(&mut self, extractor: KeywordExtractor) -> &mut Self {
+        self.keywords = Some(extractor);
+        self
+    }
+
+    /// Add a plugin populating the knowledge graph in `extensions/graph/`
+    ///
+    /// Every file gets a node; plugins add edges and further nodes from the
+    /// archived text. See [`crate::graph`] for the built-in plugins.
+    pub fn with_graph_plugin(&mut self, plugin: impl GraphPlugin + 'static) -> &mut Self {
+        self.graph_plugins.push(Arc::new(plugin));
+        self
+    }
+
+    /// Index the definitions of the source files into `symbols.msgpack`
+    ///
+    /// `LineSymbolParser` covers the common languages without dependencies.
+    /// See [`crate::symbols`].
+    pub fn with_symbols(&mut self, parser: impl SymbolParser + 'static) -> &mut Self {
+        self.symbol_parser = Some(Arc::new(parser));
+        self
+    }
+
+    /// Store up to `depth` recent commits in `extensions/git/`
+    ///
+    /// Commits are read from the repository containing the source directory,
+    /// starting at the commit passed to `from_git()` or `HEAD`. Their
+    /// messages go through the same redaction as file content and are
+    /// embedded with the chunk model when embeddings are enabled. Authors are
+    /// replaced with pseudonyms unless `with_git_authors(true)` is set.
+    #[cfg(feature = "git")]
+    pub fn with_git_history(&mut self, depth: usize) -> Result<&mut Self> {
+        let rev = self.manifest.metadata.get("git.commit").map_or("HEAD", String::as_str);
+        self.git_history = Some(GitHistory::collect(&self.source_dir, rev, depth)?);
+        Ok(self)
+    }
+
+    /// Keep author names and emails in the git history (default: pseudonyms)
+    #[cfg(feature = "git")]
+    pub fn with_git_authors(&mut self, enabled: bool) -> &mut Self {
+        self.git_authors = enabled;
+        self
+    }
+
+    /// Attach application-defined metadata (any JSON-compatible value) to the manifest
+    pub fn with_custom_metadata(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> &mut Self {
+        self.manifest.set_custom_metadata(key, value);
+        self
+    }
+
+    /// Refuse to open the archive with readers older than `version` (`major.minor.patch`)
+    ///
+    /// Readers compare it against [`crate::VERSION`] and fail with an upgrade
+    /// message, so archives relying on newer format capabilities are not
+    /// misread by old installations.
+    pub fn with_min_reader_version(&mut self, version: &str) -> Result<&mut Self> {
+        if crate::manifest::parse_version(version).is_none() {
+            return Err(CxpError::Manifest(format!("Invalid min_reader_version '{}'", version)));
+        }
+        self.manifest.min_reader_version = Some(version.trim().to_string());
+        Ok(self)
+    }
+
+    /// Add the time since `started` to a build phase
+    fn record_phase(&mut self, phase: &str, started: Instant) {
+        if let Some(ref mut info) = self.build_info {
+            info.record_phase(phase, started.elapsed());
+        }
+    }
+
+    /// Embed an existing CXP file as a child (stored under `children/<id>.cxp`)
+    ///
+    /// The child is referenced from the manifest with metadata taken from its
+    /// own manifest; embedding the same id again replaces the previous child.
+    pub fn embed_child<P: AsRef<Path>>(&mut self, id: &str, cxp_path: P) -> Result<&mut Self> {
+        let data = std::fs::read(cxp_path.as_ref())?;
+        self.embed_child_bytes(id, data)
+    }
+
+    /// Embed a child CXP from its archive bytes
+    pub fn embed_child_bytes(&mut self, id: &str, data: Vec<u8>) -> Result<&mut Self> {
+        if id.is_empty() || id.contains('/') {
+            return Err(CxpError::InvalidFormat(format!("Invalid child id '{}'", id)));
+        }
+
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data.as_slice()))
+            .map_err(|e| CxpError::InvalidFormat(format!("Child '{}' is not a CXP archive: {}", id, e)))?;
+        let manifest = {
+            let mut entry = archive.by_name("manifest.msgpack")
+                .map_err(|e| CxpError::InvalidFormat(format!("Child '{}' has no manifest: {}", id, e)))?;
+            let mut manifest_data = Vec::new();
+            entry.read_to_end(&mut manifest_data)?;
+            Manifest::from_msgpack(&manifest_data)?
+        };
+
+        let path_in_zip = format!("children/{}.cxp", id);
+        let mut child_ref = CxpRef::embedded(id, id, path_in_zip.clone());
+        child_ref.meta = CxpRefMeta::from_manifest(&manifest);
+        child_ref.meta.child_count = manifest.child_count();
+        child_ref.meta.has_children = manifest.has_children();
+        child_ref.meta.size_bytes = data.len() as u64;
+        child_ref.tier = manifest.tier;
+
+        self.manifest.add_child(child_ref);
+        self.embedded_children.insert(path_in_zip, data);
+        Ok(self)
+    }
+
+    /// Enable image processing (requires multimodal feature)
+    #[cfg(feature = "multimodal")]
+    pub fn with_images(&mut self) -> &mut Self {
+        self.process_images = true;
+        self
+    }
+
+    /// Scan the source directory for files
+    pub fn scan(&mut self) -> Result<&mut Self> {
+        if self.skip_scan {
+            tracing::debug!("Sources are not read from {:?}, nothing to scan", self.source_dir);
+            return Ok(self);
+        }
+        tracing::info!("Scanning directory: {:?}", self.source_dir);
+        let started = Instant::now();
+
+        let filter = self.scan_filter.compile()?;
+        #[cfg(feature = "scanner")]
+        if self.scan_plan.is_some() {
+            self.files = self.planned_files(&filter, is_text_file);
+            #[cfg(feature = "multimodal")]
+            if self.process_images {
+                self.image_files = self.planned_files(&filter, is_image_file);
+            }
+            tracing::info!("Scan plan selected {} text files", self.files.len());
+            self.progress.scanned(self.scanned_files());
+            self.record_phase("scan", started);
+            return Ok(self);
+        }
+        self.files = self.walk_files(&filter, is_text_file);
+
+        self.cancellation.check("scan")?;
+        tracing::info!("Found {} text files to process", self.files.len());
+
+        // Scan for images if enabled
+        #[cfg(feature = "multimodal")]
+        if self.process_images {
+            self.image_files = self.walk_files(&filter, is_image_file);
+
+            self.cancellation.check("scan")?;
+            tracing::info!("Found {} image files to process", self.image_files.len());
+        }
+
+        self.progress.scanned(self.scanned_files());
+        self.record_phase("scan", started);
+        Ok(self)
+    }
+
+    /// Text and image files found by `scan()`
+    fn scanned_files(&self) -> usize {
+        #[cfg(feature = "multimodal")]
+        if self.process_images {
+            return self.files.len() + self.image_files.len();
+        }
+        self.files.len()
+    }
+
+    /// Files under the source directory with a matching extension that pass `filter`
+    fn walk_files(&self, filter: &CompiledFilter, has_extension: fn(&str) -> bool) -> Vec<PathBuf> {
+        let relative = |path: &Path| path.strip_prefix(&self.source_dir).unwrap_or(path).to_path_buf();
+        WalkDir::new(&self.source_dir)
+            .follow_links(true)
+            .into_iter()
+            .filter_entry(|e| !e.file_type().is_dir() || e.depth() == 0 || filter.allows_dir(&relative(e.path())))
+            .take_while(|_| !self.cancellation.is_cancelled())
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| {
+                // Filter by extension
+                e.path()
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(has_extension)
+                    .unwrap_or(false)
+            })
+            .filter(|e| {
+                let size = e.metadata().map(|m| m.len()).unwrap_or(0);
+                filter.allows_file(&relative(e.path()), size)
+            })
+            .map(|e| e.path().to_path_buf())
+            .collect()
+    }
+
+    /// Planned files under the source directory with a matching extension that pass `filter`
+    ///
+    /// Tags every returned file with its tier.
+    #[cfg(feature = "scanner")]
+    fn planned_files(&mut self, filter: &CompiledFilter, has_extension: fn(&str) -> bool) -> Vec<PathBuf> {
+        let Some(plan) = &self.scan_plan else {
+            return Vec::new();
+        };
+        let mut files = Vec::new();
+        for planned in &plan.files {
+            let Ok(relative) = planned.path.strip_prefix(&self.source_dir) else {
+                tracing::warn!("Skipping {:?}: not under {:?}", planned.path, self.source_dir);
+                continue;
+            };
+            let matches = relative.extension().and_then(|e| e.to_str()).is_some_and(has_extension);
+            if !matches || !filter.allows_file(relative, planned.size) {
+                continue;
+            }
+            let tag = format!("tier:{}", planned.tier.name().to_lowercase());
+            self.annotations.tag(&relative.to_string_lossy(), [tag]);
+            files.push(planned.path.clone());
+        }
+        files
+    }
+
+    /// Process all scanned files
+    pub fn process(&mut self) -> Result<&mut Self> {
+        let started = Instant::now();
+        let source_dir = self.source_dir.clone();
+
+        // A path added from memory more than once keeps its last content
+        let mut seen = HashSet::new();
+        let mut pending: Vec<PendingFile> = std::mem::take(&mut self.pending)
+            .into_iter()
+            .rev()
+            .filter(|file| seen.insert(file.path.clone()))
+            .collect();
+        pending.reverse();
+        self.pending = pending;
+
+        let total = self.scanned_files() + self.pending.len();
+        let mut done = 0;
+
+        let settings = self.checkpoint_settings();
+        if let Some(checkpoint) = self.checkpoint.as_mut() {
+            checkpoint.begin_files(&settings)?;
+        }
+
+        // Process text files and collect chunks
+        let mut results: Vec<_> = self.files
             .iter()
-            .map(|c| {
-                std::str::from_utf8(&c.data)
-                    .unwrap_or("[binary data]")
+            .take_while(|_| !self.cancellation.is_cancelled())
+            .filter_map(|path| {
+                let result = self.process_file_resumable(path, &source_dir).ok();
+                done += 1;
+                self.progress.chunked(&path.strip_prefix(&source_dir).unwrap_or(path).to_string_lossy(), done, total);
+                result
             })
             .collect();
+        self.cancellation.check("process")?;
+
+        // Files added from memory (e.g. read from git) replace scanned ones
+        let pending: HashSet<&str> = self.pending.iter().map(|f| f.path.as_str()).collect();
+        if !pending.is_empty() {
+            results.retain(|(entry, _, _)| !pending.contains(entry.path.as_str()));
+        }
+        for file in std::mem::take(&mut self.pending) {
+            self.cancellation.check("process")?;
+            done += 1;
+            self.progress.chunked(&file.path, done, total);
+            results.push(self.process_content(file));
+        }
+        let git_commit = self.git_commit();
+
+        // Add to chunk store and file map
+        for (mut entry, chunks, redactions) in results {
+            self.record_redactions(&entry.path, redactions);
+            if let Some(provenance) = entry.provenance.as_mut() {
+                provenance.git_commit = provenance.git_commit.take().or_else(|| git_commit.clone());
+            }
+            let chunk_refs = self.chunk_store.add_many(chunks);
 
-        // Process in batches to avoid OOM
-        const BATCH_SIZE: usize = 32;
-        let mut all_embeddings = Vec::new();
+            // Update manifest with file type info
+            self.manifest.add_file_type(&entry.extension, &entry.path, entry.size);
 
-        for batch in chunk_texts.chunks(BATCH_SIZE) {
-            let embeddings = engine.embed_batch(batch)?;
-            all_embeddings.extend(embeddings);
+            // Store file entry with chunk refs
+            let entry_with_refs = FileEntry {
+                chunks: chunk_refs,
+                ..entry
+            };
+            self.file_map.files.insert(entry_with_refs.path.clone(), entry_with_refs);
         }
 
-        tracing::info!("Generated {} embeddings", all_embeddings.len());
+        // Process images if enabled (store as single chunks - whole image = 1 chunk)
+        #[cfg(feature = "multimodal")]
+        if self.process_images {
+            for path in &self.image_files.clone() {
+                self.cancellation.check("process")?;
+                done += 1;
+                self.progress.chunked(&path.strip_prefix(&source_dir).unwrap_or(path).to_string_lossy(), done, total);
+                if let Ok((mut entry, chunk)) = self.process_image(path, &source_dir) {
+                    if let Some(provenance) = entry.provenance.as_mut() {
+                        provenance.git_commit = git_commit.clone();
+                    }
+                    // Create chunk ref before adding to store
+                    let chunk_ref = ChunkRef::from(&chunk);
+                    self.chunk_store.add(chunk);
+
+                    // Update manifest with file type info
+                    self.manifest.add_file_type(&entry.extension, &entry.path, entry.size);
+
+                    // Store file entry with chunk ref
+                    let entry_with_ref = FileEntry {
+                        chunks: vec![chunk_ref],
+                        is_image: true,
+                        ..entry
+                    };
+                    self.file_map.files.insert(entry_with_ref.path.clone(), entry_with_ref);
+                }
+            }
+
+            tracing::info!("Processed {} image files", self.image_files.len());
+        }
 
-        // Create quantized embeddings
-        let quantized = QuantizedEmbeddings::from_floats(&all_embeddings);
+        // Update manifest stats
+        let dedup_stats = self.chunk_store.stats();
+        self.manifest.stats.total_files = self.file_map.files.len();
+        self.manifest.stats.unique_chunks = dedup_stats.unique_chunks;
+        self.manifest.stats.original_size_bytes = dedup_stats.total_bytes as u64;
+        self.manifest.stats.dedup_savings_percent = dedup_stats.savings_percent();
 
         tracing::info!(
-            "Quantized embeddings size: {:.2} MB (binary) + {:.2} MB (int8)",
-            quantized.binary.iter().map(|e| e.size_bytes()).sum::<usize>() as f64 / 1024.0 / 1024.0,
-            quantized.int8.iter().map(|e| e.size_bytes()).sum::<usize>() as f64 / 1024.0 / 1024.0
+            "Processed {} files, {} unique chunks, {:.1}% dedup savings",
+            self.manifest.stats.total_files,
+            self.manifest.stats.unique_chunks,
+            self.manifest.stats.dedup_savings_percent
         );
 
-        // Build HNSW index for binary embeddings
-        let config = HnswConfig::binary(engine.dimensions());
-        let mut index = HnswIndex::new(config)?;
+        self.record_phase("process", started);
+        Ok(self)
+    }
+
+    /// Re-process changed paths without rescanning the source tree
+    ///
+    /// Each path (absolute, inside the source directory) is re-read if it still
+    /// exists and passes the scan filter, and dropped from the archive
+    /// otherwise; removed directories drop every file below them. Chunks no longer referenced are discarded and the
+    /// manifest stats are recomputed. Embeddings of unchanged chunks are kept
+    /// so the next `build()` only embeds new chunks and appends them to the
+    /// search index; rows of removed chunks are tombstoned (see
+    /// `compact_embeddings()`).
+    pub fn update_files(&mut self, paths: &[PathBuf]) -> Result<&mut Self> {
+        let started = Instant::now();
+        let source_dir = self.source_dir.clone();
+        let filter = self.scan_filter.compile()?;
+        let relative_path = |path: &Path| path.strip_prefix(&source_dir).unwrap_or(path).to_path_buf();
+        // Same checks as the walk in scan(), parent directories included
+        let allowed = |path: &Path| {
+            let relative = relative_path(path);
+            let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+            relative
+                .ancestors()
+                .skip(1)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .all(|dir| filter.allows_dir(dir))
+                && filter.allows_file(&relative, size)
+        };
+        let mut updated = Vec::new();
+
+        for path in paths {
+            let Ok(relative) = path.strip_prefix(&source_dir) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().to_string();
+            if relative.is_empty() {
+                continue;
+            }
+
+            // Drop the old entries for this path (and everything below it)
+            let dir_prefix = format!("{}/", relative);
+            #[cfg(all(feature = "multimodal", feature = "search"))]
+            if let Some(unified) = self.unified_index.as_mut() {
+                for (_, id) in self.unified_images.extract_if(|p, _| p == &relative || p.starts_with(&dir_prefix)) {
+                    unified.remove(id)?;
+                }
+            }
+            self.file_map
+                .files
+                .retain(|p, _| p != &relative && !p.starts_with(&dir_prefix));
+            self.files.retain(|f| f != path && !f.starts_with(path));
+            #[cfg(feature = "multimodal")]
+            self.image_files.retain(|f| f != path && !f.starts_with(path));
+
+            if path.is_dir() {
+                updated.extend(
+                    WalkDir::new(path)
+                        .follow_links(true)
+                        .into_iter()
+                        .filter_entry(|e| !e.file_type().is_dir() || filter.allows_dir(&relative_path(e.path())))
+                        .filter_map(|e| e.ok())
+                        .filter(|e| e.file_type().is_file() && allowed(e.path()))
+                        .map(|e| e.path().to_path_buf()),
+                );
+            } else if path.is_file() && allowed(path) {
+                updated.push(path.clone());
+            }
+        }
+
+        let git_commit = self.git_commit();
+        for path in updated {
+            self.cancellation.check("update")?;
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+            // Files that vanish or become unreadable mid-update are skipped like in process()
+            if is_text_file(ext) {
+                let Ok((entry, chunks, redactions)) = self.process_file(&path, &source_dir) else {
+                    continue;
+                };
+                self.record_redactions(&entry.path, redactions);
+                let entry = FileEntry {
+                    chunks: chunks.iter().map(ChunkRef::from).collect(),
+                    provenance: entry.provenance.map(|p| Provenance { git_commit: git_commit.clone(), ..p }),
+                    ..entry
+                };
+                for chunk in chunks {
+                    self.chunk_store.add(chunk);
+                }
+                self.file_map.files.insert(entry.path.clone(), entry);
+                self.files.push(path);
+                continue;
+            }
+
+            #[cfg(feature = "multimodal")]
+            if self.process_images && is_image_file(ext) {
+                let Ok((entry, chunk)) = self.process_image(&path, &source_dir) else {
+                    continue;
+                };
+                let entry = FileEntry {
+                    chunks: vec![ChunkRef::from(&chunk)],
+                    provenance: entry.provenance.map(|p| Provenance { git_commit: git_commit.clone(), ..p }),
+                    ..entry
+                };
+                self.chunk_store.add(chunk);
+                self.file_map.files.insert(entry.path.clone(), entry);
+                self.image_files.push(path);
+            }
+        }
+
+        // Rebuild the chunk store from the files that are left (drops orphaned
+        // chunks, except those a snapshot still references)
+        let mut chunk_store = ChunkStore::new();
+        for entry in self.file_map.files.values() {
+            for chunk_ref in &entry.chunks {
+                if let Some(chunk) = self.chunk_store.get(&chunk_ref.hash) {
+                    chunk_store.add(chunk.clone());
+                }
+            }
+        }
+        let snapshot_hashes: HashSet<&str> = self
+            .snapshot_maps
+            .iter()
+            .flat_map(|file_map| file_map.files.values())
+            .flat_map(|entry| entry.chunks.iter().map(|c| c.hash.as_str()))
+            .collect();
+        for (hash, chunk) in std::mem::replace(&mut self.chunk_store, chunk_store).iter() {
+            if !self.chunk_store.contains(hash) && snapshot_hashes.contains(hash.as_str()) {
+                self.snapshot_chunks.add(chunk.clone());
+            }
+        }
+
+        // Recompute file type info and stats
+        self.manifest.file_types.clear();
+        for entry in self.file_map.files.values() {
+            self.manifest.add_file_type(&entry.extension, &entry.path, entry.size);
+        }
+
+        let dedup_stats = self.chunk_store.stats();
+        self.manifest.stats.total_files = self.file_map.files.len();
+        self.manifest.stats.unique_chunks = dedup_stats.unique_chunks;
+        self.manifest.stats.original_size_bytes = dedup_stats.total_bytes as u64;
+        self.manifest.stats.dedup_savings_percent = dedup_stats.savings_percent();
+        self.manifest.touch();
 
-        tracing::info!("Building HNSW index...");
+        // Hot-only int8 storage needs hot rows in front, so those builds
+        // re-index from the kept embeddings instead of appending
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if self.int8_storage != Int8Storage::HotOnly {
+            self.retire_removed_embeddings()?;
+        } else if let Some(embeddings) = self.chunk_embeddings.take() {
+            let floats = embeddings.float.into_iter().map(Some).chain(std::iter::repeat(None));
+            self.embedding_cache = std::mem::take(&mut self.embedding_chunks)
+                .into_iter()
+                .zip(embeddings.binary.into_iter().zip(embeddings.int8).zip(floats))
+                .map(|(hash, ((binary, int8), float))| (hash, (binary, int8, float)))
+                .collect();
+            // Aliased chunks reuse their representative's embedding
+            for (alias, representative) in std::mem::take(&mut self.embedding_aliases) {
+                if let Some(row) = self.embedding_cache.get(&representative).cloned() {
+                    self.embedding_cache.insert(alias, row);
+                }
+            }
+            self.search_index = None;
+        }
 
-        for (i, binary_emb) in quantized.binary.iter().enumerate() {
-            index.add_binary_embedding(i as u64, binary_emb)?;
+        // Unified vectors of removed chunks leave the index; new ones are
+        // appended by the next build()
+        #[cfg(all(feature = "multimodal", feature = "search"))]
+        if let Some(unified) = self.unified_index.as_mut() {
+            let chunk_store = &self.chunk_store;
+            for (_, id) in self.unified_chunks.extract_if(|hash, _| !chunk_store.contains(hash)) {
+                unified.remove(id)?;
+            }
         }
 
-        tracing::info!("HNSW index built with {} vectors", index.len());
+        tracing::info!(
+            "Updated {} paths: {} files, {} unique chunks",
+            paths.len(),
+            self.manifest.stats.total_files,
+            self.manifest.stats.unique_chunks
+        );
+
+        self.record_phase("update", started);
+        Ok(self)
+    }
+
+    /// Record the current file map as a snapshot named `label`
+    ///
+    /// Later `update_files()` calls change the head of the archive while the
+    /// snapshot keeps the files as they are now; `build()` stores every
+    /// snapshot next to the head, sharing unchanged chunks. Read it back with
+    /// `CxpReader::open_snapshot()`. See [`crate::snapshots`].
+    ///
+    /// # Example
+    /// ```ignore
+    /// builder.scan()?.process()?.snapshot("v1")?;
+    /// // ... files change ...
+    /// builder.update_files(&changed)?.snapshot("v2")?.build("history.cxp")?;
+    /// ```
+    pub fn snapshot(&mut self, label: &str) -> Result<&mut Self> {
+        let info = self.snapshots.push(label, &self.file_map)?;
+        tracing::info!("Snapshot '{}': {} files", info.label, info.files);
+        self.snapshot_maps.push(self.file_map.clone());
+        Ok(self)
+    }
+
+    /// Keep the snapshots of an existing archive when building over it
+    ///
+    /// Every generation of the archive at `path` is carried forward with its
+    /// label, number and time, together with the chunks it references, so a
+    /// rebuild does not drop the history. Call it before taking new snapshots;
+    /// an archive without snapshots adds nothing.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut builder = CxpBuilder::new("./project");
+    /// builder.with_snapshots_from("project.cxp")?;
+    /// builder.scan()?.process()?.snapshot("v2")?.build("project.cxp")?;
+    /// ```
+    pub fn with_snapshots_from<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self> {
+        if !self.snapshots.is_empty() {
+            return Err(CxpError::InvalidFormat(
+                "Snapshots of an existing archive must be loaded before taking new ones".to_string(),
+            ));
+        }
+        let reader = CxpReader::open(path.as_ref())?;
+        let mut maps = Vec::with_capacity(reader.snapshots.snapshots.len());
+        let mut chunks = ChunkStore::new();
+        for info in &reader.snapshots.snapshots {
+            let file_map = reader.snapshots.read_file_map(&mut *reader.archive()?, &info.label)?;
+            for chunk_ref in file_map.files.values().flat_map(|entry| &entry.chunks) {
+                if !chunks.contains(&chunk_ref.hash) {
+                    let data = reader.read_chunk(&chunk_ref.hash)?;
+                    chunks.add(Chunk { hash: chunk_ref.hash.clone(), length: data.len(), data, offset: 0 });
+                }
+            }
+            maps.push(file_map);
+        }
+
+        tracing::info!("Carrying {} snapshots forward from {:?}", maps.len(), path.as_ref());
+        self.snapshots = reader.snapshots.clone();
+        self.snapshot_maps = maps;
+        for chunk in chunks.into_chunks() {
+            self.snapshot_chunks.add(chunk);
+        }
+        Ok(self)
+    }
+
+    /// Enable embedding generation (requires both "embeddings" and "search" features)
+    ///
+    /// This loads an embedding model and will generate embeddings for all chunks
+    /// during the build process. The embeddings are stored in the CXP file along
+    /// with a HNSW index for fast semantic search.
+    ///
+    /// # Arguments
+    /// * `model_path` - Path to the directory containing model.onnx and tokenizer.json
+    /// * `model` - The embedding model to use (e.g., EmbeddingModel::MiniLM)
+    ///
+    /// # Example
+    /// ```ignore
+    /// builder
+    ///     .scan()?
+    ///     .with_embeddings("./models/all-MiniLM-L6-v2", EmbeddingModel::MiniLM)?
+    ///     .process()?
+    ///     .build("output.cxp")?;
+    /// ```
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn with_embeddings<P: AsRef<Path>>(
+        &mut self,
+        model_path: P,
+        model: EmbeddingModel,
+    ) -> Result<&mut Self> {
+        tracing::info!("Loading embedding model: {}", model.name());
+
+        let engine = EmbeddingEngine::load(model_path, model)?;
+        Ok(self.with_embedding_engine(engine))
+    }
+
+    /// Generate embeddings with an already loaded engine (e.g. one running on a GPU)
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn with_embedding_engine(&mut self, engine: EmbeddingEngine) -> &mut Self {
+        let model = engine.model();
+        // Embeddings kept from an update are only reusable with the same model
+        if self.manifest.embedding_model.as_deref() != Some(model.name()) {
+            self.embedding_cache.clear();
+            self.chunk_embeddings = None;
+            self.embedding_chunks.clear();
+            self.embedding_aliases.clear();
+            self.search_index = None;
+        }
+        self.manifest.embedding_model = Some(model.name().to_string());
+        self.manifest.embedding_dim = Some(model.dimensions());
+        self.manifest.embedding_model_id = Some(model.spec().id.to_string());
+        self.manifest.embedding_model_revision = match engine.revision() {
+            Ok(revision) => Some(revision),
+            Err(e) => {
+                tracing::warn!("Failed to hash embedding model: {}", e);
+                None
+            }
+        };
+        self.embedding_engine = Some(engine);
+        self
+    }
+
+    /// Enable multimodal embedding generation (requires both "multimodal" and "search" features)
+    ///
+    /// This loads a SigLIP 2 model and will generate embeddings for both text chunks
+    /// and images during the build process. The embeddings share the same 512-dimensional
+    /// vector space, enabling cross-modal search (e.g., search images with text queries).
+    ///
+    /// # Arguments
+    /// * `model_path` - Path to the directory containing image_encoder.onnx, text_encoder.onnx, and tokenizer.json
+    ///
+    /// # Example
+    /// ```ignore
+    /// builder
+    ///     .with_images()
+    ///     .scan()?
+    ///     .with_multimodal_embeddings("./models/siglip2")?
+    ///     .process()?
+    ///     .build("output.cxp")?;
+    /// ```
+    #[cfg(all(feature = "multimodal", feature = "search"))]
+    pub fn with_multimodal_embeddings<P: AsRef<Path>>(
+        &mut self,
+        model_path: P,
+    ) -> Result<&mut Self> {
+        tracing::info!("Loading multimodal model: SigLIP 2");
+
+        let engine = MultimodalEngine::load(model_path)?;
+        Ok(self.with_multimodal_engine(engine))
+    }
+
+    /// Generate multimodal embeddings with an already loaded engine
+    #[cfg(all(feature = "multimodal", feature = "search"))]
+    pub fn with_multimodal_engine(&mut self, engine: MultimodalEngine) -> &mut Self {
+        self.manifest.embedding_model = Some("SigLIP-2".to_string());
+        self.manifest.embedding_dim = Some(engine.dimensions());
+        self.multimodal_engine = Some(engine);
+        self
+    }
+
+    /// Generate embeddings for all chunks
+    ///
+    /// This is automatically called during `build()` if embeddings are enabled.
+    /// You can also call it manually after `process()` to inspect the embeddings.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn generate_embeddings(&mut self) -> Result<&mut Self> {
+        let started = Instant::now();
+        let engine = self.embedding_engine.as_mut()
+            .ok_or_else(|| CxpError::Embedding(
+                "Embedding engine not initialized. Call with_embeddings() first.".to_string()
+            ))?;
+
+        // After an update only chunks without a row or alias are embedded and
+        // appended to the kept rows and index
+        let appending = self.chunk_embeddings.is_some() && self.search_index.is_some();
+        let mut chunks: Vec<_> = self.chunk_store.chunks().collect();
+        if appending {
+            let indexed: HashSet<&str> = self
+                .embedding_chunks
+                .iter()
+                .chain(self.embedding_aliases.keys())
+                .map(String::as_str)
+                .collect();
+            chunks.retain(|c| !indexed.contains(c.hash.as_str()));
+        }
+        tracing::info!("Generating embeddings for {} unique chunks", chunks.len());
+
+        // Collect the texts of chunks without a cached embedding; hot chunks
+        // come first when only they keep int8 vectors
+        if self.int8_storage == Int8Storage::HotOnly && !appending {
+            let hot = hot_chunk_hashes(&self.file_map);
+            chunks.sort_by_key(|c| !hot.contains(c.hash.as_str()));
+        }
+
+        // Embeddings journaled by an interrupted build count as cached
+        if let Some(checkpoint) = self.checkpoint.as_mut() {
+            let model = self.manifest.embedding_model.as_deref().unwrap_or_default();
+            checkpoint.begin_embeddings(model)?;
+            for (hash, embedding) in checkpoint.take_embeddings() {
+                self.embedding_cache.entry(hash).or_insert(embedding);
+            }
+        }
+
+        // F16/f32 builds need the float vector, which int8-only cache rows lack
+        let needs_float = self.embedding_precision.is_float();
+        let uncached: Vec<&Chunk> = chunks
+            .iter()
+            .copied()
+            .filter(|c| match self.embedding_cache.get(&c.hash) {
+                Some((_, _, float)) => needs_float && float.is_none(),
+                None => true,
+            })
+            .collect();
+
+        if !self.embedding_cache.is_empty() {
+            tracing::info!(
+                "Reusing {} cached embeddings",
+                chunks.len() - uncached.len()
+            );
+        }
+
+        // Process in batches sized to the available memory
+        let mut sizer = BatchSizer::new(self.embedding_batch_size.unwrap_or(DEFAULT_BATCH_SIZE));
+        let cached = chunks.len() - uncached.len();
+        let mut generated = 0;
+        while generated < uncached.len() {
+            self.cancellation.check("embedding")?;
+            let batch = &uncached[generated..(generated + sizer.size()).min(uncached.len())];
+            let texts: Vec<&str> = batch
+                .iter()
+                .map(|c| std::str::from_utf8(&c.data).unwrap_or("[binary data]"))
+                .collect();
+            let Some(floats) = sizer.attempt(engine.embed_passages(&texts))? else {
+                continue;
+            };
+            let embeddings = QuantizedEmbeddings::with_precision(&floats, self.embedding_precision);
+            if embeddings.binary.len() != batch.len() {
+                return Err(CxpError::Embedding("Embedding count does not match chunk count".to_string()));
+            }
+            if let Some(checkpoint) = &self.checkpoint {
+                let hashes: Vec<&str> = batch.iter().map(|c| c.hash.as_str()).collect();
+                if let Err(e) = checkpoint.record_embeddings(&hashes, &embeddings) {
+                    tracing::warn!("Failed to journal embeddings: {}", e);
+                }
+            }
+            let floats = embeddings.float.into_iter().map(Some).chain(std::iter::repeat(None));
+            for (chunk, ((binary, int8), float)) in batch.iter().zip(embeddings.binary.into_iter().zip(embeddings.int8).zip(floats)) {
+                self.embedding_cache.insert(chunk.hash.clone(), (binary, int8, float));
+            }
+            generated += batch.len();
+            self.progress.embedded(cached + generated, chunks.len());
+        }
+
+        tracing::info!("Generated {} embeddings (batch size {})", generated, sizer.size());
+        self.embedding_batch_size = Some(sizer.size());
+
+        // Collect new and cached embeddings in chunk order
+        let mut quantized = QuantizedEmbeddings {
+            binary: Vec::with_capacity(chunks.len()),
+            int8: Vec::with_capacity(chunks.len()),
+            float: Vec::new(),
+        };
+        let mut floats = Vec::with_capacity(chunks.len());
+        for chunk in &chunks {
+            let (binary, int8, float) = self.embedding_cache.remove(&chunk.hash).ok_or_else(|| {
+                CxpError::Embedding("Embedding count does not match chunk count".to_string())
+            })?;
+            quantized.binary.push(binary);
+            quantized.int8.push(int8);
+            floats.push(float.filter(|_| needs_float));
+        }
+        self.embedding_cache.clear();
+
+        // Build the search index for binary embeddings, or extend the kept one
+        let (mut index, mut kept, mut embedding_chunks) = match (self.search_index.take(), self.chunk_embeddings.take()) {
+            (Some(index), Some(kept)) if appending => (index, kept, std::mem::take(&mut self.embedding_chunks)),
+            _ => {
+                tracing::info!("Building {} index...", self.index_backend);
+                let index = VectorIndex::binary(
+                    engine.dimensions(),
+                    self.index_backend,
+                    &self.index_params.unwrap_or_default(),
+                    &quantized.binary,
+                )?;
+                let kept = QuantizedEmbeddings {
+                    binary: Vec::with_capacity(chunks.len()),
+                    int8: Vec::with_capacity(chunks.len()),
+                    float: Vec::new(),
+                };
+                self.embedding_aliases.clear();
+                (index, kept, Vec::with_capacity(chunks.len()))
+            }
+        };
+        let appended = embedding_chunks.len();
+
+        // With dedup enabled, a chunk close enough to an indexed one becomes its
+        // alias instead of getting a row of its own (first chunk wins, so hot
+        // chunks stay in front)
+        let rows = quantized.binary.into_iter().zip(quantized.int8).zip(floats);
+        for (chunk, ((binary, int8), float)) in chunks.iter().zip(rows) {
+            self.cancellation.check("indexing")?;
+            if let (Some(max_hamming), false) = (self.embedding_dedup, index.is_empty()) {
+                let nearest = index.search_binary_embedding(&binary, 1)?;
+                let representative = nearest
+                    .first()
+                    .map(|r| r.id as usize)
+                    .filter(|&row| kept.binary[row].hamming_distance(&binary) <= max_hamming);
+                if let Some(row) = representative {
+                    self.embedding_aliases.insert(chunk.hash.clone(), embedding_chunks[row].clone());
+                    continue;
+                }
+            }
+            index.add_binary_embedding(embedding_chunks.len() as u64, &binary)?;
+            embedding_chunks.push(chunk.hash.clone());
+            kept.binary.push(binary);
+            kept.int8.push(int8);
+            kept.float.extend(float);
+        }
+
+        if appending {
+            tracing::info!(
+                "Appended {} embeddings to the {} index ({} vectors)",
+                embedding_chunks.len() - appended,
+                index.backend(),
+                index.len()
+            );
+        } else {
+            tracing::info!("{} index built with {} vectors", index.backend(), index.len());
+        }
+        if !self.embedding_aliases.is_empty() {
+            tracing::info!(
+                "Pruned {} near-duplicate embeddings (max Hamming distance {})",
+                self.embedding_aliases.len(),
+                self.embedding_dedup.unwrap_or(0)
+            );
+        }
+        tracing::info!(
+            "Quantized embeddings size: {:.2} MB (binary) + {:.2} MB (int8){}",
+            kept.binary.iter().map(|e| e.size_bytes()).sum::<usize>() as f64 / 1024.0 / 1024.0,
+            kept.int8.iter().map(|e| e.size_bytes()).sum::<usize>() as f64 / 1024.0 / 1024.0,
+            if needs_float { format!(" + {} float vectors ({:?})", kept.float.len(), self.embedding_precision) } else { String::new() }
+        );
+
+        self.embedding_chunks = embedding_chunks;
+        self.chunk_embeddings = Some(kept);
+        self.search_index = Some(index);
+
+        self.record_phase("embeddings", started);
+        Ok(self)
+    }
+
+    /// Drop tombstoned embedding rows and rebuild the search index from the rest
+    ///
+    /// Incremental updates leave the rows of removed chunks in place (see
+    /// `update_files()`); this reclaims them without re-embedding anything.
+    /// Embedding IDs of the remaining rows change.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn compact_embeddings(&mut self) -> Result<&mut Self> {
+        let Some(embeddings) = self.chunk_embeddings.as_mut() else {
+            return Ok(self);
+        };
+        let dropped = compact_embedding_rows(embeddings, &mut self.embedding_chunks);
+        if dropped == 0 {
+            return Ok(self);
+        }
+        let dimensions = self.manifest.embedding_dim
+            .ok_or_else(|| CxpError::Embedding("No embedding dimension in manifest".to_string()))?;
+        self.search_index = Some(VectorIndex::from_embeddings(
+            dimensions,
+            self.index_backend,
+            &self.index_params.unwrap_or_default(),
+            &embeddings.binary,
+        )?);
+        tracing::info!("Compacted embeddings: dropped {} tombstoned rows, {} left", dropped, embeddings.binary.len());
+        Ok(self)
+    }
+
+    /// Embedding rows that still belong to a chunk
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    fn live_embedding_rows(&self) -> usize {
+        self.embedding_chunks.iter().filter(|hash| hash.as_str() != EMBEDDING_TOMBSTONE).count()
+    }
+
+    /// Tombstone the embedding rows of chunks an update removed
+    ///
+    /// A removed chunk with near-duplicate aliases hands its row to the first
+    /// alias still in the archive. Other rows leave the search index and keep
+    /// an empty chunk ID until `compact_embeddings()`.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    fn retire_removed_embeddings(&mut self) -> Result<()> {
+        let Some(index) = self.search_index.as_mut() else {
+            return Ok(());
+        };
+        let chunk_store = &self.chunk_store;
+        let (retired, promoted) = retire_embedding_rows(
+            &mut self.embedding_chunks,
+            &mut self.embedding_aliases,
+            |hash| chunk_store.contains(hash),
+        );
+        for &row in &retired {
+            index.remove(row as u64)?;
+        }
+        let retired = retired.len();
+        if retired + promoted > 0 {
+            tracing::info!(
+                "Tombstoned {} embeddings of removed chunks ({} handed to near-duplicates)",
+                retired,
+                promoted
+            );
+        }
+        Ok(())
+    }
+
+    /// Generate multimodal embeddings for all chunks (text + images)
+    ///
+    /// This is automatically called during `build()` if multimodal embeddings are enabled.
+    /// Creates a UnifiedIndex with both text and image embeddings in the same vector space.
+    /// After `update_files()` only new chunks and images are embedded and appended.
+    #[cfg(all(feature = "multimodal", feature = "search"))]
+    pub fn generate_multimodal_embeddings(&mut self) -> Result<&mut Self> {
+        let started = Instant::now();
+        if self.unified_index.is_none() {
+            self.unified_chunks.clear();
+            self.unified_images.clear();
+        }
+        tracing::info!("Generating multimodal embeddings for {} unique chunks", self.chunk_store.len());
+
+        // Process in batches sized to the available memory
+        let mut sizer = BatchSizer::new(self.embedding_batch_size.unwrap_or(DEFAULT_BATCH_SIZE));
+
+        // Step 1: Generate text embeddings
+        let chunks: Vec<(usize, &Chunk)> = self
+            .chunk_store
+            .chunks()
+            .enumerate()
+            .filter(|(_, c)| !self.unified_chunks.contains_key(&c.hash))
+            .collect();
+        let chunk_texts: Vec<&str> = chunks
+            .iter()
+            .map(|(_, c)| {
+                std::str::from_utf8(&c.data)
+                    .unwrap_or("[binary data]")
+            })
+            .collect();
+
+        // Images not embedded yet, with their relative paths
+        let images: Vec<(&Path, String)> = if self.process_images {
+            self.image_files
+                .iter()
+                .map(|img_path| {
+                    let relative = img_path
+                        .strip_prefix(&self.source_dir)
+                        .unwrap_or(img_path)
+                        .to_string_lossy()
+                        .to_string();
+                    (img_path.as_path(), relative)
+                })
+                .filter(|(_, relative)| !self.unified_images.contains_key(relative))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        tracing::info!("Generating text embeddings for {} chunks...", chunk_texts.len());
+        let embedding_total = chunk_texts.len() + images.len();
+
+        let mut all_text_embeddings = Vec::new();
+        {
+            let engine = self.multimodal_engine.as_mut()
+                .ok_or_else(|| CxpError::Embedding(
+                    "Multimodal engine not initialized. Call with_multimodal_embeddings() first.".to_string()
+                ))?;
+
+            while all_text_embeddings.len() < chunk_texts.len() {
+                self.cancellation.check("embedding")?;
+                let start = all_text_embeddings.len();
+                let batch = &chunk_texts[start..(start + sizer.size()).min(chunk_texts.len())];
+                let Some(embeddings) = sizer.attempt(engine.embed_batch_text(batch))? else {
+                    continue;
+                };
+                all_text_embeddings.extend(embeddings);
+                self.progress.embedded(all_text_embeddings.len(), embedding_total);
+            }
+        }
+
+        tracing::info!("Generated {} text embeddings", all_text_embeddings.len());
+
+        // Step 2: Generate image embeddings if needed
+        let mut all_image_embeddings = Vec::new();
+        if !images.is_empty() {
+            tracing::info!("Generating image embeddings for {} images...", images.len());
+
+            let image_paths: Vec<_> = images.iter().map(|(path, _)| *path).collect();
+
+            let engine = self.multimodal_engine.as_mut()
+                .ok_or_else(|| CxpError::Embedding(
+                    "Multimodal engine not initialized.".to_string()
+                ))?;
+
+            while all_image_embeddings.len() < image_paths.len() {
+                self.cancellation.check("embedding")?;
+                let start = all_image_embeddings.len();
+                let batch = &image_paths[start..(start + sizer.size()).min(image_paths.len())];
+                let Some(embeddings) = sizer.attempt(engine.embed_batch_images(batch))? else {
+                    continue;
+                };
+                all_image_embeddings.extend(embeddings);
+                self.progress.embedded(chunk_texts.len() + all_image_embeddings.len(), embedding_total);
+            }
+        }
+
+        tracing::info!(
+            "Generated {} image embeddings (batch size {})",
+            all_image_embeddings.len(),
+            sizer.size()
+        );
+        self.embedding_batch_size = Some(sizer.size());
+
+        // Step 3: Build the unified index, or extend the kept one
+        let mut unified_index = match self.unified_index.take() {
+            Some(index) => index,
+            None => {
+                let config = HnswConfig::multimodal_float32().with_params(&self.index_params.unwrap_or_default());
+                UnifiedIndex::new(config)?
+            }
+        };
+
+        // IDs of removed vectors are never reused
+        let mut vector_id: u64 = self
+            .unified_chunks
+            .values()
+            .chain(self.unified_images.values())
+            .max()
+            .map_or(0, |&id| id + 1);
+
+        // Add text embeddings to index
+        for ((chunk_idx, chunk), embedding) in chunks.iter().zip(all_text_embeddings) {
+            let chunk_id = *chunk_idx as u64;
+
+            // Find which file this chunk belongs to (for metadata)
+            let file_path = self.find_file_for_chunk_id(chunk_id)
+                .unwrap_or_else(|| "unknown".to_string());
+
+            unified_index.add_text(vector_id, &embedding, chunk_id, &file_path)?;
+            self.unified_chunks.insert(chunk.hash.clone(), vector_id);
+            vector_id += 1;
+        }
+
+        // Add image embeddings to index
+        for ((_, relative_path), embedding) in images.iter().zip(all_image_embeddings) {
+            unified_index.add_image(vector_id, &embedding, relative_path)?;
+            self.unified_images.insert(relative_path.clone(), vector_id);
+            vector_id += 1;
+        }
+
+        tracing::info!("Total vectors in unified index: {}", unified_index.len());
+        tracing::info!("  - Text chunks: {}", unified_index.text_count());
+        tracing::info!("  - Images: {}", unified_index.image_count());
+
+        self.unified_index = Some(unified_index);
+
+        self.record_phase("multimodal_embeddings", started);
+        Ok(self)
+    }
+
+    /// Find which file a chunk ID belongs to (helper for metadata)
+    #[cfg(all(feature = "multimodal", feature = "search"))]
+    fn find_file_for_chunk_id(&self, chunk_id: u64) -> Option<String> {
+        // Iterate through file map to find which file contains this chunk
+        for (path, entry) in &self.file_map.files {
+            if !entry.is_image {
+                // Check if this chunk ID is in the file's chunk range
+                // This is a simplified approach - in production you'd maintain a proper mapping
+                if chunk_id < entry.chunks.len() as u64 {
+                    return Some(path.clone());
+                }
+            }
+        }
+        None
+    }
+
+    /// Add an extension with data to this CXP file
+    ///
+    /// The extension will be registered and its data will be stored in the
+    /// extensions/{namespace}/ directory in the CXP file.
+    ///
+    /// # Arguments
+    /// * `ext` - An object implementing the Extension trait
+    /// * `data` - A HashMap of file names to their data (e.g., "conversations.msgpack" -> bytes)
+    ///
+    /// # Example
+    /// ```ignore
+    /// use std::collections::HashMap;
+    ///
+    /// let mut builder = CxpBuilder::new("./src");
+    /// let contextai = ContextAIExtension::new();
+    /// let data = contextai.to_extension_data()?;
+    ///
+    /// builder.add_extension(&contextai, data)?;
+    /// ```
+    pub fn add_extension<E: Extension + Clone>(
+        &mut self,
+        ext: &E,
+        data: HashMap<String, Vec<u8>>,
+    ) -> Result<&mut Self> {
+        // Register the extension
+        self.extension_manager.register(ext.clone());
+
+        // Add all data files
+        for (key, bytes) in data {
+            self.extension_manager.write_data(ext.namespace(), &key, &bytes)?;
+        }
+
+        // Update manifest to include this extension
+        if !self.manifest.extensions.contains(&ext.namespace().to_string()) {
+            self.manifest.extensions.push(ext.namespace().to_string());
+        }
+
+        tracing::info!(
+            "Added extension '{}' (v{}) with {} data files",
+            ext.namespace(),
+            ext.version(),
+            self.extension_manager.list_data_keys(ext.namespace()).len()
+        );
+
+        Ok(self)
+    }
+
+    /// Process a single file, returning the secrets redacted from it
+    fn process_file(&self, path: &Path, base_dir: &Path) -> Result<(FileEntry, Vec<Chunk>, Vec<Redaction>)> {
+        // Read file content
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let mut content = Vec::with_capacity(metadata.len() as usize);
+        file.read_to_end(&mut content)?;
+
+        // Get relative path
+        let relative_path = path
+            .strip_prefix(base_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+
+        // License detection sees the original header
+        let provenance = self.record_provenance.then(|| Provenance::for_file(path, &content));
+
+        Ok(self.process_content(PendingFile {
+            path: relative_path,
+            content,
+            modified: metadata.modified().ok().map(Into::into),
+            provenance,
+        }))
+    }
+
+    /// `process_file()`, taking unchanged files from the checkpoint and journaling new ones
+    fn process_file_resumable(&self, path: &Path, base_dir: &Path) -> Result<(FileEntry, Vec<Chunk>, Vec<Redaction>)> {
+        let Some(checkpoint) = &self.checkpoint else {
+            return self.process_file(path, base_dir);
+        };
+        let metadata = std::fs::metadata(path)?;
+        let relative_path = path.strip_prefix(base_dir).unwrap_or(path).to_string_lossy();
+        if let Some(result) = checkpoint.file(&relative_path, metadata.len(), metadata.modified().ok().map(Into::into)) {
+            return Ok(result);
+        }
+
+        let (entry, chunks, redactions) = self.process_file(path, base_dir)?;
+        if let Err(e) = checkpoint.record_file(metadata.len(), &entry, &chunks, &redactions) {
+            tracing::warn!("Failed to journal {}: {}", entry.path, e);
+        }
+        Ok((entry, chunks, redactions))
+    }
+
+    /// Settings that journaled files depend on
+    fn checkpoint_settings(&self) -> String {
+        #[cfg(feature = "redact")]
+        let scrubbers = self.active_scrubbers().len();
+        #[cfg(not(feature = "redact"))]
+        let scrubbers = 0;
+        format!(
+            "chunker={};scrubbers={};provenance={}",
+            self.chunker.name(),
+            scrubbers,
+            self.record_provenance
+        )
+    }
+
+    /// Redact and chunk file content that is already in memory
+    fn process_content(&self, file: PendingFile) -> (FileEntry, Vec<Chunk>, Vec<Redaction>) {
+        #[cfg_attr(not(feature = "redact"), allow(unused_mut))]
+        let PendingFile { path, mut content, modified, provenance } = file;
+        let provenance = provenance.filter(|_| self.record_provenance);
+
+        // Get extension
+        let extension = Path::new(&path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        // Replace secrets and PII before anything is chunked or embedded
+        #[cfg(feature = "redact")]
+        let redactions = match scrub(&content, &self.active_scrubbers()) {
+            Some((redacted, redactions)) => {
+                content = redacted;
+                redactions
+            }
+            None => Vec::new(),
+        };
+        #[cfg(not(feature = "redact"))]
+        let redactions = Vec::new();
+
+        // Chunk the content
+        let chunks = self.chunker.chunk(&content);
+
+        let entry = FileEntry {
+            path,
+            extension,
+            size: content.len() as u64,
+            chunks: Vec::new(), // Will be filled in with refs later
+            is_image: false,
+            modified,
+            provenance,
+            keywords: Vec::new(),
+        };
+
+        (entry, chunks, redactions)
+    }
+
+    /// Commit of the source repository, if provenance is recorded
+    fn git_commit(&self) -> Option<String> {
+        if self.record_provenance {
+            git_head(&self.source_dir)
+        } else {
+            None
+        }
+    }
+
+    /// Redactor and additional scrubbers in the order they are applied
+    #[cfg(feature = "redact")]
+    fn active_scrubbers(&self) -> Vec<&dyn Scrubber> {
+        self.redactor
+            .iter()
+            .map(|r| r as &dyn Scrubber)
+            .chain(self.scrubbers.iter().map(|s| s.as_ref()))
+            .collect()
+    }
+
+    /// Archived content of the text files whose chunks are in memory
+    fn text_files(&self, purpose: &str) -> Vec<(&str, String)> {
+        let mut texts = Vec::new();
+        'files: for (path, entry) in &self.file_map.files {
+            let mut content = Vec::with_capacity(entry.size as usize);
+            for chunk_ref in &entry.chunks {
+                // Unchanged files of an incremental update are not in memory
+                let Some(chunk) = self.chunk_store.get(&chunk_ref.hash) else {
+                    tracing::debug!("Skipping {} for {}: content not in memory", path, purpose);
+                    continue 'files;
+                };
+                content.extend_from_slice(&chunk.data);
+            }
+            if let Ok(text) = String::from_utf8(content) {
+                texts.push((path.as_str(), text));
+            }
+        }
+        texts
+    }
+
+    /// Summaries of the archived text files and their directories
+    fn add_summaries(&mut self) -> Result<()> {
+        let Some(summarizer) = self.summarizer.clone() else {
+            return Ok(());
+        };
+
+        let texts = self.text_files("summaries");
+        let summaries = Summaries::build(texts.iter().map(|(path, text)| (*path, text.as_str())), summarizer.as_ref())?;
+        let mut data = HashMap::new();
+        data.insert(SUMMARIES_KEY.to_string(), summaries.to_msgpack()?);
+        self.add_extension(&SummariesExtension, data)?;
+        Ok(())
+    }
+
+    /// Keywords of the archived text files and topics of the archive
+    ///
+    /// Unchanged files of an incremental update keep their keywords and
+    /// still count towards the topics.
+    fn add_keywords(&mut self) {
+        let Some(extractor) = self.keywords.clone() else {
+            return;
+        };
+
+        let texts = self.text_files("keywords");
+        let keywords = extractor.extract(texts.iter().map(|(path, text)| (*path, text.as_str())));
+        drop(texts);
+        for (path, keywords) in keywords {
+            if let Some(entry) = self.file_map.files.get_mut(&path) {
+                entry.keywords = keywords;
+            }
+        }
+        self.manifest.topics = extractor.topics(self.file_map.files.values().map(|e| e.keywords.as_slice()));
+    }
+
+    /// Symbol index of the archived text files (None without a parser)
+    ///
+    /// Unchanged files of an incremental update are not indexed.
+    fn symbol_index(&self) -> Option<SymbolIndex> {
+        let parser = self.symbol_parser.as_ref()?;
+        let texts = self.text_files("symbols");
+        Some(SymbolIndex::build(texts.iter().map(|(path, text)| (*path, text.as_str())), parser.as_ref()))
+    }
+
+    /// Knowledge graph of the archived files from the graph plugins
+    ///
+    /// Unchanged files of an incremental update only get their file node.
+    fn add_graph(&mut self) -> Result<()> {
+        if self.graph_plugins.is_empty() {
+            return Ok(());
+        }
+
+        let mut graph = KnowledgeGraph::new();
+        for path in self.file_map.files.keys() {
+            graph.add_node(Node::file(path));
+        }
+        let texts = self.text_files("graph");
+        let files: Vec<(&str, &str)> = texts.iter().map(|(path, text)| (*path, text.as_str())).collect();
+        for plugin in &self.graph_plugins {
+            plugin.populate(&files, &mut graph)?;
+            tracing::debug!("Graph plugin {}: {} nodes, {} edges", plugin.name(), graph.node_count(), graph.edge_count());
+        }
+        graph.prune();
+        drop(texts);
+
+        let mut data = HashMap::new();
+        data.insert(GRAPH_KEY.to_string(), graph.to_msgpack()?);
+        self.add_extension(&GraphExtension, data)?;
+        Ok(())
+    }
+
+    /// Git history of the archived files, with redacted and embedded messages
+    #[cfg(feature = "git")]
+    fn add_git_history(&mut self) -> Result<()> {
+        let Some(history) = self.git_history.as_ref() else {
+            return Ok(());
+        };
+        let mut history = history.for_files(&self.file_map);
+        if !self.git_authors {
+            history.anonymize_authors();
+        }
+
+        #[cfg(feature = "redact")]
+        {
+            let scrubbers = self.active_scrubbers();
+            let mut redacted = 0;
+            for commit in &mut history.commits {
+                if let Some((message, redactions)) = scrub(commit.message.as_bytes(), &scrubbers) {
+                    commit.message = String::from_utf8_lossy(&message).into_owned();
+                    redacted += redactions.len();
+                }
+            }
+            if redacted > 0 {
+                tracing::info!("Redacted {} values in commit messages", redacted);
+            }
+        }
+
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if let Some(engine) = self.embedding_engine.as_mut() {
+            history.embed_messages(engine)?;
+        }
+
+        let mut data = HashMap::new();
+        data.insert(GIT_HISTORY_KEY.to_string(), history.to_msgpack()?);
+        self.add_extension(&GitHistoryExtension, data)?;
+        Ok(())
+    }
+
+    /// Record the secrets redacted from `path` (no-op when redaction is disabled)
+    fn record_redactions(&mut self, path: &str, redactions: Vec<Redaction>) {
+        if let Some(report) = self.manifest.redactions.as_mut() {
+            if !redactions.is_empty() {
+                tracing::info!("Redacted {} values in {}", redactions.len(), path);
+            }
+            report.set_file(path, redactions);
+        }
+    }
+
+    /// Process a single image file (stores entire image as one chunk)
+    #[cfg(feature = "multimodal")]
+    fn process_image(&self, path: &Path, base_dir: &Path) -> Result<(FileEntry, Chunk)> {
+        // Read image file
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let mut content = Vec::with_capacity(metadata.len() as usize);
+        file.read_to_end(&mut content)?;
+
+        // Get relative path
+        let relative_path = path
+            .strip_prefix(base_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+
+        // Get extension
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        // Create single chunk for entire image
+        let chunk = Chunk::new(content, 0);
+
+        let entry = FileEntry {
+            path: relative_path,
+            extension,
+            size: metadata.len(),
+            chunks: Vec::new(), // Will be filled in with ref later
+            is_image: true,
+            modified: metadata.modified().ok().map(Into::into),
+            provenance: self.record_provenance.then(|| Provenance::for_file(path, &[])),
+            keywords: Vec::new(),
+        };
+
+        Ok((entry, chunk))
+    }
+
+    /// Build and write the CXP file
+    ///
+    /// If the build is cancelled while writing, the partial output file is removed.
+    pub fn build<P: AsRef<Path>>(&mut self, output_path: P) -> Result<()> {
+        let output_path = output_path.as_ref();
+        tracing::info!("Building CXP file: {:?}", output_path);
+        self.cancellation.check("build")?;
+
+        // Generate embeddings if engine is set but embeddings haven't been
+        // generated yet, or an update added chunks without one
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if self.embedding_engine.is_some()
+            && (self.chunk_embeddings.is_none()
+                || self.live_embedding_rows() + self.embedding_aliases.len() != self.chunk_store.len())
+        {
+            self.generate_embeddings()?;
+        }
+
+        // Generate multimodal embeddings if multimodal engine is set
+        #[cfg(all(feature = "multimodal", feature = "search"))]
+        if self.multimodal_engine.is_some()
+            && (self.unified_index.is_none() || self.unified_chunks.len() != self.chunk_store.len()
+                || (self.process_images && self.unified_images.len() != self.image_files.len()))
+        {
+            self.generate_multimodal_embeddings()?;
+        }
+
+        // Embedding rows must line up with the chunks they were computed from
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if let Some(ref embeddings) = self.chunk_embeddings {
+            let dimensions = self.manifest.embedding_dim
+                .ok_or_else(|| CxpError::Embedding("No embedding dimension in manifest".to_string()))?;
+            validate_embeddings(embeddings, Some(&self.embedding_chunks), dimensions, Int8Storage::All)?;
+            validate_chunk_mapping(&self.embedding_chunks, |hash| self.chunk_store.contains(hash))?;
+            validate_embedding_aliases(&self.embedding_aliases, &self.embedding_chunks)?;
+
+            let live_rows = self.live_embedding_rows();
+            if live_rows + self.embedding_aliases.len() != self.chunk_store.len() {
+                return Err(CxpError::Embedding(format!(
+                    "{} embeddings and {} aliases for {} unique chunks",
+                    live_rows,
+                    self.embedding_aliases.len(),
+                    self.chunk_store.len()
+                )));
+            }
+            if let Some(ref index) = self.search_index {
+                if index.len() != live_rows {
+                    return Err(CxpError::Index(format!(
+                        "Search index has {} vectors for {} embeddings",
+                        index.len(),
+                        live_rows
+                    )));
+                }
+            }
+        }
+
+        #[cfg(feature = "git")]
+        self.add_git_history()?;
+        self.add_summaries()?;
+        self.add_keywords();
+        self.add_graph()?;
+
+        // Atomic builds write next to the output and only replace it once complete
+        let codec = if self.atomic_write {
+            crate::temp::replace_file(output_path, |temp| self.write_archive(File::create(temp)?, temp))?
+        } else {
+            match self.write_archive(File::create(output_path)?, output_path) {
+                Ok(codec) => codec,
+                Err(e) => {
+                    if matches!(e, CxpError::Cancelled(_)) {
+                        std::fs::remove_file(output_path)?;
+                    }
+                    return Err(e);
+                }
+            }
+        };
+
+        // The journal of the previous version no longer applies
+        crate::journal::remove(output_path)?;
+        self.written = Some(WrittenArchive {
+            path: output_path.to_path_buf(),
+            base: journal_base(&self.manifest),
+            codec,
+            file_map: self.file_map.clone(),
+            chunks: self.chunk_store.chunks().chain(self.snapshot_chunks.chunks()).map(|c| c.hash.clone()).collect(),
+            sequence: 0,
+        });
+        if let Some(checkpoint) = self.checkpoint.take() {
+            checkpoint.remove()?;
+        }
+        Ok(())
+    }
+
+    /// Append the changes since the last `build()` to the archive's journal
+    ///
+    /// Instead of rewriting `output_path`, files changed or removed by
+    /// `update_files()` and their new chunks are appended to
+    /// `<output_path>.journal`, which `CxpReader::open()` replays. Changes are
+    /// relative to the archive this builder last built or journaled; any other
+    /// archive is read from disk together with its journal, so a fresh
+    /// builder can journal onto an archive built earlier. Embeddings,
+    /// summaries and other indices catch up with the next `build()`. See
+    /// [`crate::journal`].
+    ///
+    /// # Example
+    /// ```ignore
+    /// builder.scan()?.process()?.build("live.cxp")?;
+    /// builder.update_files(&changed)?.append_journal("live.cxp")?;
+    /// ```
+    pub fn append_journal<P: AsRef<Path>>(&mut self, output_path: P) -> Result<&mut Self> {
+        let output_path = output_path.as_ref();
+        if self.written.as_ref().is_none_or(|w| w.path != output_path) {
+            self.written = Some(WrittenArchive::open(output_path)?);
+        }
+        let written = self.written.as_mut().expect("written archive was just loaded");
+
+        let files: Vec<FileEntry> = self
+            .file_map
+            .files
+            .values()
+            .filter(|entry| !written.file_map.files.get(&entry.path).is_some_and(|old| same_content(old, entry)))
+            .cloned()
+            .collect();
+        let removed: Vec<String> = written
+            .file_map
+            .files
+            .keys()
+            .filter(|path| !self.file_map.files.contains_key(*path))
+            .cloned()
+            .collect();
+        if files.is_empty() && removed.is_empty() {
+            return Ok(self);
+        }
+
+        let mut seen = HashSet::new();
+        let hashes: Vec<String> = files
+            .iter()
+            .flat_map(|entry| entry.chunks.iter().map(|c| c.hash.as_str()))
+            .filter(|hash| !written.chunks.contains(*hash) && seen.insert(*hash))
+            .map(str::to_string)
+            .collect();
+        let stored: Vec<Vec<u8>> = hashes
+            .par_iter()
+            .map(|hash| {
+                let chunk = self.chunk_store.get(hash).ok_or_else(|| CxpError::ChunkMissing { hash: hash.clone() })?;
+                written.codec.compress(&chunk.data)
+            })
+            .collect::<Result<_>>()?;
+
+        let record = JournalRecord {
+            base: written.base.clone(),
+            sequence: written.sequence,
+            created_at: chrono::Utc::now(),
+            files,
+            removed,
+            chunks: hashes.into_iter().zip(&stored).map(|(hash, data)| (hash, data.len() as u64)).collect(),
+        };
+        crate::journal::append_record(output_path, &record, &stored)?;
+        tracing::info!(
+            "Journaled update {} of {:?}: {} files changed, {} removed, {} new chunks",
+            record.sequence,
+            output_path,
+            record.files.len(),
+            record.removed.len(),
+            record.chunks.len()
+        );
+
+        written.sequence += 1;
+        written.chunks.extend(record.chunks.into_iter().map(|(hash, _)| hash));
+        written.file_map = self.file_map.clone();
+        Ok(self)
+    }
+
+    /// Number of records in the journal of the archive last built or journaled
+    pub fn journal_records(&self) -> u64 {
+        self.written.as_ref().map_or(0, |w| w.sequence)
+    }
+
+    /// Write all archive entries to a freshly created output file
+    ///
+    /// Returns the codec the chunks were compressed with.
+    fn write_archive(&mut self, file: File, output_path: &Path) -> Result<ChunkCodec> {
+        let started = Instant::now();
+        let mut zip = ZipWriter::new(file);
+
+        // Every written entry is recorded in the table of contents
+        let mut toc = Toc::new();
+
+        // Mark embeddings before the manifest is written so readers can detect them
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        let has_embeddings = self.chunk_embeddings.is_some();
+        #[cfg(not(all(feature = "embeddings", feature = "search")))]
+        let has_embeddings = false;
+        #[cfg(all(feature = "multimodal", feature = "search"))]
+        let has_embeddings = has_embeddings || self.unified_index.is_some();
+
+        if has_embeddings && !self.manifest.extensions.contains(&"embeddings".to_string()) {
+            self.manifest.extensions.push("embeddings".to_string());
+        }
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if self.chunk_embeddings.is_some() {
+            // Readers that predate float storage see f16/f32 archives as binary only
+            self.manifest.int8_embeddings = match self.embedding_precision {
+                EmbeddingPrecision::Int8 => self.int8_storage,
+                _ => Int8Storage::None,
+            };
+            self.manifest.embedding_precision = match self.int8_storage {
+                Int8Storage::None => EmbeddingPrecision::Binary,
+                _ => self.embedding_precision,
+            };
+            self.manifest.embedding_tombstones = self.embedding_chunks.len() - self.live_embedding_rows();
+            if self.manifest.embedding_tombstones > 0 {
+                // Older readers would map the empty chunk IDs to search hits
+                self.manifest.require_reader_version(EMBEDDING_TOMBSTONES_READER_VERSION);
+            }
+        }
+        if has_embeddings {
+            self.manifest.index_params = Some(self.index_params.unwrap_or_default());
+        }
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if let Some(index) = &self.search_index {
+            self.manifest.index_backend = index.backend();
+        }
+        self.manifest.chunker = Some(self.chunker.name());
+        if let Some(report) = self.manifest.redactions.as_ref() {
+            self.manifest.redactions = Some(report.for_files(&self.file_map));
+        }
+        if self.build_info.is_some() && !self.manifest.extensions.iter().any(|e| e == BUILD_INFO_NAMESPACE) {
+            self.manifest.extensions.push(BUILD_INFO_NAMESPACE.to_string());
+        }
+
+        // Compress chunks up front so per-type compression stats go into the manifest
+        // (chunks of older snapshots are stored after the head's)
+        let chunk_store = &self.chunk_store;
+        let chunks: Vec<_> = chunk_store
+            .chunks()
+            .chain(self.snapshot_chunks.chunks().filter(|c| !chunk_store.contains(&c.hash)))
+            .collect();
+        self.cancellation.check("build")?;
+        let mut codec = ChunkCodec::new(self.compression);
+        let mut compressed_chunks: Vec<Vec<u8>> = chunks
+            .par_iter()
+            .map(|chunk| codec.compress(&chunk.data))
+            .collect::<Result<_>>()?;
+        if self.train_dictionary {
+            match self.compression.zstd_level() {
+                Some(level) => {
+                    let data: Vec<&[u8]> = chunks.iter().map(|c| c.data.as_slice()).collect();
+                    if let Some((dictionary_codec, with_dictionary)) = compress_with_trained_dictionary(&data, &compressed_chunks, level)? {
+                        codec = dictionary_codec;
+                        compressed_chunks = with_dictionary;
+                    }
+                }
+                None => tracing::warn!("{} does not support dictionaries, building without one", self.compression.name()),
+            }
+        }
+        self.manifest.compression = Some(self.compression.to_string());
+        let compressed_sizes: HashMap<&str, u64> = chunks
+            .iter()
+            .zip(&compressed_chunks)
+            .map(|(chunk, data)| (chunk.hash.as_str(), data.len() as u64))
+            .collect();
+        self.manifest.record_chunk_compression(&self.file_map, |hash| compressed_sizes.get(hash).copied());
+
+        // Write manifest
+        if self.packed_chunks {
+            self.manifest.require_reader_version(PACKS_READER_VERSION);
+        }
+        let manifest_data = self.manifest.to_msgpack()?;
+        zip.start_file("manifest.msgpack", stored_options(manifest_data.len()))?;
+        zip.write_all(&manifest_data)?;
+        toc.record("manifest.msgpack", manifest_data.len() as u64);
+
+        // Write file map as sorted shards (enables lazy, prefix-scoped loading)
+        let (shard_index, shards) = ShardIndex::build(&self.file_map, self.shard_size)?;
+        for (shard_path, shard_data) in &shards {
+            zip.start_file(shard_path, stored_options(shard_data.len()))?;
+            zip.write_all(shard_data)?;
+            toc.record(shard_path, shard_data.len() as u64);
+        }
+        let shard_index_data = shard_index.to_msgpack()?;
+        zip.start_file(SHARD_INDEX_PATH, stored_options(shard_index_data.len()))?;
+        zip.write_all(&shard_index_data)?;
+        toc.record(SHARD_INDEX_PATH, shard_index_data.len() as u64);
+
+        // Write the chunk dictionary, if any
+        if let Some(dictionary) = codec.dictionary() {
+            zip.start_file(DICTIONARY_PATH, stored_options(dictionary.len()))?;
+            zip.write_all(dictionary)?;
+            toc.record(DICTIONARY_PATH, dictionary.len() as u64);
+        }
+
+        // Write chunks, into packs or one entry each
+        let total_chunks = chunks.len();
+        let mut packs = self.packed_chunks.then(|| PackWriter::new(DEFAULT_PACK_SIZE));
+
+        for (i, (chunk, compressed)) in chunks.iter().zip(&compressed_chunks).enumerate() {
+            self.cancellation.check("build")?;
+            let entry = match packs.as_mut() {
+                Some(packs) => packs.push(&chunk.hash, compressed).map(|pack| (pack.path, Cow::Owned(pack.data), pack.chunks)),
+                None => Some((format!("chunks/{}.zst", chunk.id()), Cow::Borrowed(compressed.as_slice()), 1)),
+            };
+            if let Some((name, data, entries)) = entry {
+                zip.start_file(&name, stored_options(data.len()))?;
+                zip.write_all(&data)?;
+                toc.record_entries(&name, data.len() as u64, entries);
+            }
+
+            self.progress.written(i + 1, total_chunks);
+            if (i + 1) % 100 == 0 || i + 1 == total_chunks {
+                tracing::debug!("Written {}/{} chunks", i + 1, total_chunks);
+            }
+        }
+        if let Some(packs) = packs {
+            let (last, index) = packs.finish();
+            if let Some(pack) = last {
+                zip.start_file(&pack.path, stored_options(pack.data.len()))?;
+                zip.write_all(&pack.data)?;
+                toc.record_entries(&pack.path, pack.data.len() as u64, pack.chunks);
+            }
+            if !index.is_empty() {
+                let index_data = index.to_msgpack()?;
+                zip.start_file(PACK_INDEX_PATH, stored_options(index_data.len()))?;
+                zip.write_all(&index_data)?;
+                toc.record_entries(PACK_INDEX_PATH, index_data.len() as u64, 0);
+                tracing::debug!("Packed {} chunks into {} packs", index.len(), index.packs);
+            }
+        }
+
+        // Write bloom filters over file paths and chunk hashes
+        let path_filter = BloomFilter::from_items(self.file_map.files.keys(), DEFAULT_FALSE_POSITIVE_RATE);
+        let chunk_hashes: Vec<&str> = chunks.iter().map(|c| c.hash.as_str()).collect();
+        let chunk_filter = BloomFilter::from_items(chunk_hashes, DEFAULT_FALSE_POSITIVE_RATE);
+        for (filter_path, filter) in [(PATH_FILTER_PATH, &path_filter), (CHUNK_FILTER_PATH, &chunk_filter)] {
+            let filter_data = filter.to_bytes();
+            zip.start_file(filter_path, stored_options(filter_data.len()))?;
+            zip.write_all(&filter_data)?;
+            toc.record(filter_path, filter_data.len() as u64);
+        }
+
+        // Write embeddings if present
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if let Some(ref embeddings) = self.chunk_embeddings {
+            tracing::info!("Writing embeddings to CXP file...");
+
+            // Write binary embeddings
+            let binary_data = serialize_binary_embeddings(&embeddings.binary)?;
+            zip.start_file("embeddings/binary.bin", stored_options(binary_data.len()))?;
+            zip.write_all(&binary_data)?;
+            toc.record("embeddings/binary.bin", binary_data.len() as u64);
+
+            // Write rescoring vectors (hot chunks are the leading rows)
+            let rescore_rows = match self.int8_storage {
+                Int8Storage::All => embeddings.binary.len(),
+                Int8Storage::HotOnly => {
+                    let hot = hot_chunk_hashes(&self.file_map);
+                    self.embedding_chunks.iter().take_while(|hash| hot.contains(hash.as_str())).count()
+                }
+                Int8Storage::None => 0,
+            };
+            let precision = self.manifest.embedding_precision;
+            let rescore_data = match precision {
+                EmbeddingPrecision::Binary => None,
+                EmbeddingPrecision::Int8 => Some(serialize_int8_embeddings(&embeddings.int8[..rescore_rows])?),
+                _ => Some(serialize_float_embeddings(&embeddings.float[..rescore_rows], precision)?),
+            };
+            if let (Some(data), Some(path)) = (rescore_data, precision.path()) {
+                zip.start_file(path, stored_options(data.len()))?;
+                zip.write_all(&data)?;
+                toc.record(path, data.len() as u64);
+            }
+            tracing::info!(
+                "Rescoring vectors: {} of {} ({:?}, {:?})",
+                rescore_rows,
+                embeddings.binary.len(),
+                precision,
+                self.int8_storage
+            );
+
+            // Write embedding ID -> chunk hash mapping
+            let chunk_ids_data = rmp_serde::to_vec(&self.embedding_chunks)?;
+            zip.start_file("embeddings/chunk_ids.msgpack", stored_options(chunk_ids_data.len()))?;
+            zip.write_all(&chunk_ids_data)?;
+            toc.record("embeddings/chunk_ids.msgpack", chunk_ids_data.len() as u64);
+
+            // Write near-duplicate chunk -> embedded chunk mapping
+            if !self.embedding_aliases.is_empty() {
+                let aliases_data = rmp_serde::to_vec(&self.embedding_aliases)?;
+                zip.start_file(EMBEDDING_ALIASES_PATH, stored_options(aliases_data.len()))?;
+                zip.write_all(&aliases_data)?;
+                toc.record(EMBEDDING_ALIASES_PATH, aliases_data.len() as u64);
+            }
+
+            tracing::info!("Embeddings written successfully");
+        }
+
+        // Write HNSW index if present
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if let Some(ref index) = self.search_index {
+            tracing::info!("Writing {} index to CXP file...", index.backend());
+
+            let index_data = index.to_bytes()?;
+            let path = index.backend().path();
+            zip.start_file(path, stored_options(index_data.len()))?;
+            zip.write_all(&index_data)?;
+            toc.record(path, index_data.len() as u64);
+
+            tracing::info!("Search index written successfully ({} vectors)", index.len());
+        }
+
+        // Write UnifiedIndex if present (for multimodal)
+        #[cfg(all(feature = "multimodal", feature = "search"))]
+        if let Some(ref index) = self.unified_index {
+            tracing::info!("Writing UnifiedIndex to CXP file...");
+
+            let (index_data, meta_data) = index.to_bytes()?;
+
+            zip.start_file("embeddings/unified.index", stored_options(index_data.len()))?;
+            zip.write_all(&index_data)?;
+            toc.record("embeddings/unified.index", index_data.len() as u64);
+
+            zip.start_file("embeddings/unified.meta", stored_options(meta_data.len()))?;
+            zip.write_all(&meta_data)?;
+            toc.record("embeddings/unified.meta", meta_data.len() as u64);
+
+            tracing::info!("UnifiedIndex written successfully ({} vectors: {} text, {} images)",
+                index.len(), index.text_count(), index.image_count());
+        }
+
+        // Write the global index over the children
+        if let Some(ref index) = self.global_index {
+            let index_data = index.to_msgpack()?;
+            zip.start_file(GLOBAL_INDEX_PATH, stored_options(index_data.len()))?;
+            zip.write_all(&index_data)?;
+            toc.record(GLOBAL_INDEX_PATH, index_data.len() as u64);
+        }
+
+        // Write annotations of the files in this build
+        let annotations = self.annotations.for_files(&self.file_map);
+        if annotations.files.len() < self.annotations.files.len() {
+            tracing::warn!(
+                "Dropping annotations of {} paths that are not in the archive",
+                self.annotations.files.len() - annotations.files.len()
+            );
+        }
+        if !annotations.is_empty() {
+            let annotations_data = annotations.to_msgpack()?;
+            zip.start_file(ANNOTATIONS_PATH, stored_options(annotations_data.len()))?;
+            zip.write_all(&annotations_data)?;
+            toc.record(ANNOTATIONS_PATH, annotations_data.len() as u64);
+        }
+
+        // Write the symbol index
+        if let Some(symbols) = self.symbol_index() {
+            let symbols_data = symbols.to_msgpack()?;
+            zip.start_file(SYMBOLS_PATH, stored_options(symbols_data.len()))?;
+            zip.write_all(&symbols_data)?;
+            toc.record(SYMBOLS_PATH, symbols_data.len() as u64);
+            tracing::info!("Indexed {} symbols", symbols.len());
+        }
+
+        // Write the snapshots
+        if !self.snapshots.is_empty() {
+            for (info, file_map) in self.snapshots.snapshots.iter().zip(&self.snapshot_maps) {
+                let snapshot_data = file_map_to_msgpack(file_map)?;
+                zip.start_file(info.path(), stored_options(snapshot_data.len()))?;
+                zip.write_all(&snapshot_data)?;
+                toc.record(&info.path(), snapshot_data.len() as u64);
+            }
+            let index_data = self.snapshots.to_msgpack()?;
+            zip.start_file(SNAPSHOT_INDEX_PATH, stored_options(index_data.len()))?;
+            zip.write_all(&index_data)?;
+            toc.record(SNAPSHOT_INDEX_PATH, index_data.len() as u64);
+        }
+
+        // Write embedded child CXPs
+        for (path_in_zip, data) in &self.embedded_children {
+            zip.start_file(path_in_zip, stored_options(data.len()))?;
+            zip.write_all(data)?;
+            toc.record(path_in_zip, data.len() as u64);
+        }
+
+        // Write extension data if present
+        if !self.extension_manager.list_extensions().is_empty() {
+            tracing::info!("Writing extension data to CXP file...");
+
+            // Write extension manifests
+            for manifest in self.extension_manager.manifests().values() {
+                let manifest_path = format!("extensions/{}/manifest.msgpack", manifest.namespace);
+                let manifest_data = manifest.to_msgpack()?;
+                zip.start_file(&manifest_path, stored_options(manifest_data.len()))?;
+                zip.write_all(&manifest_data)?;
+                toc.record(&manifest_path, manifest_data.len() as u64);
+            }
+
+            // Write extension data files
+            for (namespace, data_map) in self.extension_manager.all_data() {
+                for (key, data) in data_map {
+                    let data_path = format!("extensions/{}/{}", namespace, key);
+                    zip.start_file(&data_path, stored_options(data.len()))?;
+                    zip.write_all(data)?;
+                    toc.record(&data_path, data.len() as u64);
+                }
+            }
+
+            tracing::info!(
+                "Written {} extensions to CXP file",
+                self.extension_manager.list_extensions().len()
+            );
+        }
+
+        // Write build telemetry after everything else so the write phase is covered
+        if let Some(ref mut info) = self.build_info {
+            info.record_phase("write", started.elapsed());
+            info.set_parameter("shard_size", self.shard_size);
+            info.set_parameter("min_chunk_size", MIN_CHUNK_SIZE);
+            info.set_parameter("avg_chunk_size", AVG_CHUNK_SIZE);
+            info.set_parameter("max_chunk_size", MAX_CHUNK_SIZE);
+            info.set_parameter("chunker", self.chunker.name());
+            info.set_parameter("compression", self.compression);
+            if let Some(level) = self.compression.zstd_level() {
+                info.set_parameter("compression_level", level);
+            }
+            if let Some(ref model) = self.manifest.embedding_model {
+                info.set_parameter("embedding_model", model);
+            }
+            if let Some(dim) = self.manifest.embedding_dim {
+                info.set_parameter("embedding_dim", dim);
+            }
+            if let (Some(_), Some(size)) = (&self.manifest.embedding_model, self.embedding_batch_size) {
+                info.set_parameter("embedding_batch_size", size);
+            }
+            if let Some(max_hamming) = self.embedding_dedup {
+                info.set_parameter("embedding_dedup", max_hamming);
+            }
+
+            let ext_manifest = ExtensionManifest::new(BUILD_INFO_NAMESPACE, BUILD_INFO_VERSION);
+            let manifest_path = format!("extensions/{}/manifest.msgpack", BUILD_INFO_NAMESPACE);
+            let manifest_data = ext_manifest.to_msgpack()?;
+            zip.start_file(&manifest_path, stored_options(manifest_data.len()))?;
+            zip.write_all(&manifest_data)?;
+            toc.record(&manifest_path, manifest_data.len() as u64);
+
+            let info_path = format!("extensions/{}/{}", BUILD_INFO_NAMESPACE, BUILD_INFO_KEY);
+            let info_data = info.to_msgpack()?;
+            zip.start_file(&info_path, stored_options(info_data.len()))?;
+            zip.write_all(&info_data)?;
+            toc.record(&info_path, info_data.len() as u64);
+        }
+
+        // Write the table of contents last so it covers every entry
+        let toc_data = toc.to_msgpack()?;
+        zip.start_file(TOC_PATH, stored_options(toc_data.len()))?;
+        zip.write_all(&toc_data)?;
+
+        zip.finish()?;
+
+        // Update manifest with final size
+        let final_size = std::fs::metadata(output_path)?.len();
+        self.manifest.stats.cxp_size_bytes = final_size;
+        self.manifest.stats.compression_ratio =
+            final_size as f64 / self.manifest.stats.original_size_bytes as f64;
+
+        tracing::info!(
+            "CXP file created: {:.2} MB (compression ratio: {:.2}%)",
+            final_size as f64 / 1024.0 / 1024.0,
+            self.manifest.stats.compression_ratio * 100.0
+        );
+
+        Ok(codec)
+    }
+}
+
+/// The archive as `CxpBuilder` last wrote it, the base for journaled updates
+struct WrittenArchive {
+    /// Output path of the build
+    path: PathBuf,
+    /// Version recorded in journal records (see `journal_base`)
+    base: String,
+    /// Codec (and dictionary) the archive's chunks are compressed with
+    codec: ChunkCodec,
+    /// File map including the journaled updates so far
+    file_map: FileMap,
+    /// Hashes of the chunks in the archive or its journal
+    chunks: HashSet<String>,
+    /// Sequence number of the next journal record
+    sequence: u64,
+}
+
+impl WrittenArchive {
+    /// Read an archive and its journal from disk (for a builder that did not write it)
+    fn open(path: &Path) -> Result<Self> {
+        let mut reader = CxpReader::open(path)?;
+        reader.load_file_map()?;
+        let mut chunks: HashSet<String> = reader
+            .file_map
+            .files
+            .values()
+            .flat_map(|entry| entry.chunks.iter().map(|c| c.hash.clone()))
+            .collect();
+        chunks.extend(reader.stored_chunks.journal.keys().cloned());
+        Ok(Self {
+            path: path.to_path_buf(),
+            base: journal_base(&reader.manifest),
+            codec: reader.codec.clone(),
+            file_map: std::mem::take(&mut reader.file_map),
+            chunks,
+            sequence: reader.journal_records as u64,
+        })
+    }
+}
+
+/// Whether two entries of the same path have the same content
+fn same_content(a: &FileEntry, b: &FileEntry) -> bool {
+    a.size == b.size
+        && a.modified == b.modified
+        && a.chunks.len() == b.chunks.len()
+        && a.chunks.iter().zip(&b.chunks).all(|(x, y)| x.hash == y.hash)
+}
+
+/// A stored chunk as reported by [`CxpReader::chunks`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkInfo {
+    /// Chunk hash (SHA-256 hex)
+    pub hash: String,
+    /// Size of the compressed chunk entry
+    pub compressed_size: u64,
+    /// Size of the chunk data
+    pub uncompressed_size: u64,
+    /// Number of file references to this chunk
+    pub ref_count: usize,
+}
+
+/// Vector indexes stored in an archive, as reported by [`CxpReader::available_indexes`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AvailableIndexes {
+    /// Text embeddings with a binary HNSW index (`load_embeddings()`)
+    pub text: bool,
+    /// Multimodal UnifiedIndex over text and images (`load_unified_index()`)
+    pub unified: bool,
+}
+
+impl AvailableIndexes {
+    /// Whether the archive has no vector index
+    pub fn is_empty(&self) -> bool {
+        !self.text && !self.unified
+    }
+}
+
+/// Streaming reader over a file (or byte range) in a CXP archive
+///
+/// Created by [`CxpReader::open_file_stream`] and [`CxpReader::open_file_range`].
+pub struct FileStream {
+    /// Own archive handle (returned to the reader's pool when dropped)
+    archive: PooledArchive,
+    /// Chunks of the file in order
+    chunks: Vec<ChunkRef>,
+    /// Original file size
+    size: u64,
+    /// Index of the next chunk to decompress
+    next_chunk: usize,
+    /// Current decompressed chunk
+    buffer: Vec<u8>,
+    /// Read position within `buffer`
+    position: usize,
+    /// Bytes to skip at the start of the first chunk
+    skip: usize,
+    /// Bytes left in the requested range
+    remaining: u64,
+    /// Chunk decompression (shares the reader's dictionary)
+    codec: ChunkCodec,
+    /// Chunks stored outside the archive's chunk entries
+    stored: Arc<StoredChunks>,
+}
+
+impl FileStream {
+    /// Original size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Bytes left to read
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Decompress the next chunk into the buffer (returns false at end of file)
+    fn load_next_chunk(&mut self) -> Result<bool> {
+        let Some(chunk_ref) = self.chunks.get(self.next_chunk) else {
+            return Ok(false);
+        };
+
+        let compressed = self.stored.read(&mut self.archive, &chunk_ref.hash)?;
+        self.buffer = self.codec.decompress(&compressed)?;
+        self.position = std::mem::take(&mut self.skip).min(self.buffer.len());
+        self.next_chunk += 1;
+        Ok(true)
+    }
+}
+
+impl Read for FileStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+
+        while self.position >= self.buffer.len() {
+            if !self.load_next_chunk().map_err(std::io::Error::other)? {
+                return Ok(0);
+            }
+        }
+
+        let available = (self.buffer.len() - self.position)
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let n = buf.len().min(available);
+        buf[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Chunk hashes referenced by hot-tier files
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn hot_chunk_hashes(file_map: &FileMap) -> std::collections::HashSet<&str> {
+    file_map
+        .files
+        .values()
+        .filter(|entry| crate::recursive::FileTier::from_modified(entry.modified) == crate::recursive::FileTier::Hot)
+        .flat_map(|entry| entry.chunks.iter().map(|c| c.hash.as_str()))
+        .collect()
+}
+
+/// Verify that binary, int8 and float rows, the chunk mapping and the vector dimensions agree
+///
+/// `int8_storage` tells whether int8 rows must cover every embedding or may
+/// stop early (hot-only) or be absent. Float rows may stop early.
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn validate_embeddings(
+    embeddings: &QuantizedEmbeddings,
+    chunk_ids: Option<&[String]>,
+    dimensions: usize,
+    int8_storage: Int8Storage,
+) -> Result<()> {
+    check_embedding_counts(
+        embeddings.binary.len(),
+        embeddings.int8.len(),
+        embeddings.float.len(),
+        chunk_ids,
+        int8_storage,
+    )?;
+    if let Some(row) = embeddings.float.iter().position(|float| float.len() != dimensions) {
+        return Err(CxpError::Embedding(format!(
+            "Float embedding row {} does not have {} dimensions",
+            row, dimensions
+        )));
+    }
+
+    for (row, (binary, int8)) in embeddings.binary.iter().zip(&embeddings.int8).enumerate() {
+        if binary.dimensions != dimensions
+            || binary.bits.len() != dimensions.div_ceil(8)
+            || int8.values.len() != dimensions
+        {
+            return Err(CxpError::Embedding(format!(
+                "Embedding row {} does not have {} dimensions",
+                row, dimensions
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify that int8 rows, float rows and the chunk mapping cover `rows` binary embeddings
+#[cfg(all(feature = "embeddings", feature = "search"))]
+pub(crate) fn check_embedding_counts(
+    rows: usize,
+    int8_rows: usize,
+    float_rows: usize,
+    chunk_ids: Option<&[String]>,
+    int8_storage: Int8Storage,
+) -> Result<()> {
+    let int8_ok = match int8_storage {
+        Int8Storage::All => int8_rows == rows,
+        Int8Storage::HotOnly => int8_rows <= rows,
+        Int8Storage::None => int8_rows == 0,
+    };
+    if !int8_ok {
+        return Err(CxpError::Embedding(format!(
+            "{} binary embeddings but {} int8 embeddings ({:?})",
+            rows,
+            int8_rows,
+            int8_storage
+        )));
+    }
+    if float_rows > rows {
+        return Err(CxpError::Embedding(format!(
+            "{} binary embeddings but {} float embeddings",
+            rows,
+            float_rows
+        )));
+    }
+    if let Some(chunk_ids) = chunk_ids {
+        if chunk_ids.len() != rows {
+            return Err(CxpError::Embedding(format!(
+                "{} embeddings but {} chunk mappings",
+                rows,
+                chunk_ids.len()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Near-duplicate chunk -> embedded chunk mapping of pruned embeddings
+#[cfg(all(feature = "embeddings", feature = "search"))]
+pub(crate) const EMBEDDING_ALIASES_PATH: &str = "embeddings/aliases.msgpack";
+
+/// Oldest reader that skips tombstoned embedding rows (required while an archive has any)
+pub const EMBEDDING_TOMBSTONES_READER_VERSION: &str = "1.1.0";
+
+/// Chunk ID of an embedding row whose chunk an incremental update removed
+///
+/// The row stays out of the search index until it is compacted away
+/// (`CxpBuilder::compact_embeddings()`, `cxp optimize`).
+#[cfg(all(feature = "embeddings", feature = "search"))]
+pub(crate) const EMBEDDING_TOMBSTONE: &str = "";
+
+/// Tombstone the rows of chunks that no longer exist, returning the tombstoned rows and the number of promoted aliases
+///
+/// A gone chunk with near-duplicate aliases hands its row to the first
+/// alias that still exists instead; aliases of gone chunks are dropped.
+#[cfg(all(feature = "embeddings", feature = "search"))]
+pub(crate) fn retire_embedding_rows<F: Fn(&str) -> bool>(
+    chunk_ids: &mut [String],
+    aliases: &mut BTreeMap<String, String>,
+    chunk_exists: F,
+) -> (Vec<usize>, usize) {
+    aliases.retain(|alias, _| chunk_exists(alias));
+    let mut heirs: HashMap<&str, &str> = HashMap::new();
+    for (alias, representative) in aliases.iter() {
+        heirs.entry(representative.as_str()).or_insert(alias.as_str());
+    }
+
+    let mut promoted: BTreeMap<String, String> = BTreeMap::new();
+    let mut retired = Vec::new();
+    for (row, hash) in chunk_ids.iter_mut().enumerate() {
+        if hash.as_str() == EMBEDDING_TOMBSTONE || chunk_exists(hash) {
+            continue;
+        }
+        match heirs.get(hash.as_str()) {
+            Some(&heir) => {
+                promoted.insert(std::mem::replace(hash, heir.to_string()), heir.to_string());
+            }
+            None => {
+                *hash = EMBEDDING_TOMBSTONE.to_string();
+                retired.push(row);
+            }
+        }
+    }
+
+    for heir in promoted.values() {
+        aliases.remove(heir);
+    }
+    for representative in aliases.values_mut() {
+        if let Some(heir) = promoted.get(representative) {
+            *representative = heir.clone();
+        }
+    }
+    (retired, promoted.len())
+}
+
+/// Remove tombstoned rows from `embeddings` and `chunk_ids`, returning how many were dropped
+#[cfg(all(feature = "embeddings", feature = "search"))]
+pub(crate) fn compact_embedding_rows(embeddings: &mut QuantizedEmbeddings, chunk_ids: &mut Vec<String>) -> usize {
+    let live: Vec<bool> = chunk_ids.iter().map(|hash| hash.as_str() != EMBEDDING_TOMBSTONE).collect();
+    let mut keep = live.iter();
+    embeddings.binary.retain(|_| *keep.next().unwrap_or(&true));
+    // Int8 and float rows may stop early; the live ones among them stay a prefix
+    let mut keep = live.iter();
+    embeddings.int8.retain(|_| *keep.next().unwrap_or(&true));
+    let mut keep = live.iter();
+    embeddings.float.retain(|_| *keep.next().unwrap_or(&true));
+    chunk_ids.retain(|hash| hash.as_str() != EMBEDDING_TOMBSTONE);
+    live.len() - chunk_ids.len()
+}
+
+/// Verify that every alias points at an embedded chunk and is not embedded itself
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn validate_embedding_aliases(aliases: &BTreeMap<String, String>, chunk_ids: &[String]) -> Result<()> {
+    let embedded: std::collections::HashSet<&str> = chunk_ids
+        .iter()
+        .map(String::as_str)
+        .filter(|&hash| hash != EMBEDDING_TOMBSTONE)
+        .collect();
+    for (alias, representative) in aliases {
+        if embedded.contains(alias.as_str()) || !embedded.contains(representative.as_str()) {
+            return Err(CxpError::Embedding(format!(
+                "Embedding alias {} -> {} does not map a pruned chunk to an embedded one",
+                alias, representative
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Verify that every mapped chunk hash is unique and present in the archive (tombstones aside)
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn validate_chunk_mapping<F: FnMut(&str) -> bool>(chunk_ids: &[String], mut chunk_exists: F) -> Result<()> {
+    let mut seen = std::collections::HashSet::with_capacity(chunk_ids.len());
+    for (row, hash) in chunk_ids.iter().enumerate() {
+        if hash.as_str() == EMBEDDING_TOMBSTONE {
+            continue;
+        }
+        if hash.len() < 16 || !chunk_exists(hash) {
+            return Err(CxpError::Embedding(format!(
+                "Embedding row {} refers to missing chunk {}",
+                row, hash
+            )));
+        }
+        if !seen.insert(hash.as_str()) {
+            return Err(CxpError::Embedding(format!(
+                "Embedding row {} duplicates chunk {}",
+                row, hash
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Where the stored (compressed) bytes of chunks are found
+///
+/// Chunks are in the archive's packs (or `chunks/<id>.zst` entries of older
+/// archives), except those appended by journaled updates. Shared by a reader
+/// and its file streams.
+#[derive(Debug, Default)]
+pub(crate) struct StoredChunks {
+    /// Offset table of the archive's packs
+    packs: PackIndex,
+    /// Chunks of replayed journal records by hash
+    journal: HashMap<String, Vec<u8>>,
+}
+
+impl StoredChunks {
+    /// Stored bytes of a chunk
+    pub(crate) fn read<R: Read + std::io::Seek>(&self, archive: &mut ZipArchive<R>, hash: &str) -> Result<Vec<u8>> {
+        match self.journal.get(hash) {
+            Some(data) => Ok(data.clone()),
+            None => self.packs.read_stored(archive, hash),
+        }
+    }
+
+    /// Whether a chunk is stored
+    pub(crate) fn contains<R: Read + std::io::Seek>(&self, archive: &ZipArchive<R>, hash: &str) -> bool {
+        self.journal.contains_key(hash) || self.packs.contains(archive, hash)
+    }
+}
+
+/// ZIP options for an uncompressed entry of `len` bytes (chunks are compressed by us)
+///
+/// The Zip64 end of central directory (more than 65535 entries, offsets past
+/// 4 GiB) is written automatically, but an entry of 4 GiB or more needs its
+/// Zip64 extra field announced before its data is written.
+pub(crate) fn stored_options(len: usize) -> FileOptions<'static, ()> {
+    FileOptions::<()>::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(len as u64 >= u32::MAX as u64)
+}
+
+/// Writes raw entries into a new archive, recording each in the table of contents
+///
+/// Used where archives are assembled from existing compressed chunks (delta
+/// apply, merge) instead of going through `CxpBuilder`.
+pub(crate) struct ArchiveWriter<W: Write + std::io::Seek = File> {
+    zip: ZipWriter<W>,
+    toc: Toc,
+    packs: PackWriter,
+}
+
+impl ArchiveWriter {
+    /// Create the archive file
+    pub(crate) fn create(path: &Path) -> Result<Self> {
+        Ok(Self::new(File::create(path)?))
+    }
+}
+
+impl<W: Write + std::io::Seek> ArchiveWriter<W> {
+    /// Write the archive to any seekable writer (e.g. an in-memory buffer)
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            zip: ZipWriter::new(writer),
+            toc: Toc::new(),
+            packs: PackWriter::new(DEFAULT_PACK_SIZE),
+        }
+    }
+
+    /// Write one stored entry
+    pub(crate) fn write(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.write_entries(name, data, 1)
+    }
+
+    /// Write one stored entry counting as `entries` in the table of contents
+    fn write_entries(&mut self, name: &str, data: &[u8], entries: usize) -> Result<()> {
+        self.zip.start_file(name, stored_options(data.len()))?;
+        self.zip.write_all(data)?;
+        self.toc.record_entries(name, data.len() as u64, entries);
+        Ok(())
+    }
+
+    /// Copy an entry of an existing archive, counting its packed chunks in the table of contents
+    pub(crate) fn copy(&mut self, name: &str, data: &[u8], packs: &PackIndex) -> Result<()> {
+        let entries = match PackIndex::pack_number(name) {
+            Some(pack) => packs.chunks_in(pack),
+            None if name == PACK_INDEX_PATH => 0,
+            None => 1,
+        };
+        self.write_entries(name, data, entries)
+    }
+
+    /// Write the manifest, requiring a reader that understands the packs chunks
+    /// go into and any tombstoned embedding rows
+    pub(crate) fn write_manifest(&mut self, manifest: &Manifest) -> Result<()> {
+        let mut manifest = manifest.clone();
+        manifest.require_reader_version(PACKS_READER_VERSION);
+        if manifest.embedding_tombstones > 0 {
+            manifest.require_reader_version(EMBEDDING_TOMBSTONES_READER_VERSION);
+        }
+        self.write("manifest.msgpack", &manifest.to_msgpack()?)
+    }
+
+    /// Add a stored (compressed) chunk to the packs, writing each pack once full
+    pub(crate) fn write_chunk(&mut self, hash: &str, data: &[u8]) -> Result<()> {
+        match self.packs.push(hash, data) {
+            Some(pack) => self.write_entries(&pack.path, &pack.data, pack.chunks),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the file map as sorted shards plus the shard index
+    pub(crate) fn write_file_map(&mut self, file_map: &FileMap, shard_size: usize) -> Result<()> {
+        let (shard_index, shards) = ShardIndex::build(file_map, shard_size)?;
+        for (shard_path, shard_data) in &shards {
+            self.write(shard_path, shard_data)?;
+        }
+        self.write(SHARD_INDEX_PATH, &shard_index.to_msgpack()?)
+    }
+
+    /// Write bloom filters over the file paths and chunk hashes of a file map
+    pub(crate) fn write_filters(&mut self, file_map: &FileMap) -> Result<()> {
+        let hashes: std::collections::BTreeSet<&str> = file_map
+            .files
+            .values()
+            .flat_map(|entry| entry.chunks.iter().map(|c| c.hash.as_str()))
+            .collect();
+        let path_filter = BloomFilter::from_items(file_map.files.keys(), DEFAULT_FALSE_POSITIVE_RATE);
+        let chunk_filter = BloomFilter::from_items(hashes, DEFAULT_FALSE_POSITIVE_RATE);
+        self.write(PATH_FILTER_PATH, &path_filter.to_bytes())?;
+        self.write(CHUNK_FILTER_PATH, &chunk_filter.to_bytes())
+    }
+
+    /// Write the last pack, the pack index and the table of contents, and close the archive
+    pub(crate) fn finish(mut self) -> Result<W> {
+        let packs = std::mem::replace(&mut self.packs, PackWriter::new(DEFAULT_PACK_SIZE));
+        let (last, index) = packs.finish();
+        if let Some(pack) = last {
+            self.write_entries(&pack.path, &pack.data, pack.chunks)?;
+        }
+        if !index.is_empty() {
+            self.write_entries(PACK_INDEX_PATH, &index.to_msgpack()?, 0)?;
+        }
+
+        let toc_data = self.toc.to_msgpack()?;
+        self.zip.start_file(TOC_PATH, stored_options(toc_data.len()))?;
+        self.zip.write_all(&toc_data)?;
+        Ok(self.zip.finish()?)
+    }
+}
+
+/// Read the full file map from its shards (or `file_map.msgpack` in older archives)
+pub(crate) fn read_file_map<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>) -> Result<FileMap> {
+    fn read_entry<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>> {
+        let mut entry = archive.by_name(name)
+            .map_err(|e| CxpError::InvalidFormat(format!("No {} found: {}", name, e)))?;
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)
+            .map_err(|e| CxpError::InvalidFormat(format!("Failed to read {}: {}", name, e)))?;
+        Ok(data)
+    }
+
+    let index = match read_entry(archive, SHARD_INDEX_PATH) {
+        Ok(data) => ShardIndex::from_msgpack(&data)?,
+        Err(_) => {
+            let data = read_entry(archive, "file_map.msgpack")?;
+            return rmp_serde::from_slice(&data)
+                .map_err(|e| CxpError::Serialization(format!("Failed to parse file_map: {}", e)));
+        }
+    };
+
+    let mut file_map = FileMap::default();
+    for shard in &index.shards {
+        let data = read_entry(archive, &shard.path)?;
+        let shard: FileMap = rmp_serde::from_slice(&data)
+            .map_err(|e| CxpError::Serialization(format!("Failed to parse {}: {}", shard.path, e)))?;
+        file_map.files.extend(shard.files);
+    }
+    Ok(file_map)
+}
+
+/// Codec for an archive's chunks (with its dictionary, if it has one)
+pub(crate) fn read_chunk_codec<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>) -> Result<ChunkCodec> {
+    match archive.by_name(DICTIONARY_PATH) {
+        Ok(mut entry) => {
+            let mut dictionary = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut dictionary)?;
+            Ok(ChunkCodec::with_dictionary(dictionary, DEFAULT_COMPRESSION_LEVEL))
+        }
+        Err(_) => Ok(ChunkCodec::default()),
+    }
+}
+
+/// Train a dictionary on a sample of the chunks and recompress them with it
+///
+/// Returns None when training fails or the dictionary does not pay for its
+/// own size compared to the `plain` compressed chunks.
+pub(crate) fn compress_with_trained_dictionary(chunks: &[&[u8]], plain: &[Vec<u8>], level: i32) -> Result<Option<(ChunkCodec, Vec<Vec<u8>>)>> {
+    // Evenly spaced samples up to the training budget
+    let total_bytes: usize = chunks.iter().map(|data| data.len()).sum();
+    let stride = total_bytes.div_ceil(MAX_TRAINING_BYTES).max(1);
+    let samples: Vec<&[u8]> = chunks.iter().step_by(stride).copied().collect();
+
+    let dictionary = match train_dictionary(&samples, DEFAULT_DICTIONARY_SIZE) {
+        Ok(dictionary) => dictionary,
+        Err(e) => {
+            tracing::warn!("Building without a dictionary: {}", e);
+            return Ok(None);
+        }
+    };
+
+    let codec = ChunkCodec::with_dictionary(dictionary, level);
+    let compressed: Vec<Vec<u8>> = chunks
+        .par_iter()
+        .map(|data| codec.compress(data))
+        .collect::<Result<_>>()?;
+
+    let plain_bytes: usize = plain.iter().map(Vec::len).sum();
+    let dictionary_bytes = codec.dictionary().map_or(0, <[u8]>::len);
+    let with_dictionary_bytes = compressed.iter().map(Vec::len).sum::<usize>() + dictionary_bytes;
+    tracing::info!(
+        "Chunk dictionary ({} bytes, {} samples): {} -> {} bytes",
+        dictionary_bytes,
+        samples.len(),
+        plain_bytes,
+        with_dictionary_bytes
+    );
+    if with_dictionary_bytes >= plain_bytes {
+        tracing::info!("Dictionary does not pay off, building without it");
+        return Ok(None);
+    }
+
+    Ok(Some((codec, compressed)))
+}
+
+/// Load one `extensions/<namespace>/<key>` entry into the extension manager
+fn load_extension_entry(manager: &mut ExtensionManager, namespace: &str, key: &str, data: Vec<u8>) {
+    if key == "manifest.msgpack" {
+        if let Ok(ext_manifest) = crate::extensions::ExtensionManifest::from_msgpack(&data) {
+            manager.load_manifest(ext_manifest);
+        }
+    } else {
+        manager.load_data(namespace.to_string(), key.to_string(), data);
+    }
+}
+
+/// Reader for CXP files
+pub struct CxpReader {
+    /// The manifest
+    pub manifest: Manifest,
+    /// File map
+    pub file_map: FileMap,
+    /// Storage the archive is read from
+    backend: Arc<dyn ArchiveBackend>,
+    /// Table of contents (None for archives written before it existed)
+    toc: Option<Toc>,
+    /// Bloom filter over file paths (None for older archives)
+    path_filter: Option<BloomFilter>,
+    /// Bloom filter over chunk hashes (None for older archives)
+    chunk_filter: Option<BloomFilter>,
+    /// Shard index when file entries are loaded on demand
+    shard_index: Option<ShardIndex>,
+    /// Loaded file map shards (parallel to `shard_index`)
+    shards: Vec<OnceLock<FileMap>>,
+    /// Extension manager for reading app-specific data
+    extension_manager: ExtensionManager,
+    /// User tags and notes per file
+    annotations: Annotations,
+    /// Where embedded children are extracted when opened
+    temp_policy: TempPolicy,
+    /// Checked by index loading and search entry points
+    cancellation: CancellationToken,
+    /// Chunk (de)compression, with the archive's trained dictionary if any
+    codec: ChunkCodec,
+    /// Sidecar log that file reads are recorded in (None: no tracking)
+    access_log: Option<AccessLog>,
+    /// Opt-in usage counters (None: no metrics)
+    usage: Option<Arc<UsageRecorder>>,
+    /// Timeout applied to multi-query searches
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    search_options: SearchOptions,
+    /// Temp copy backing this reader (extracted embedded child), removed on drop
+    backing: Option<TempGuard>,
+    /// Idle ZIP handles, reused by reads
+    archives: ArchivePool,
+    /// Cached text search index and embeddings (set once by `load_embeddings()`)
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    text_search: OnceLock<TextSearch>,
+    /// Embedding model used to encode text queries (locked while encoding)
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    query_engine: parking_lot::Mutex<Option<EmbeddingEngine>>,
+    /// Cached UnifiedIndex for multimodal search
+    #[cfg(all(feature = "multimodal", feature = "search"))]
+    unified_index: OnceLock<UnifiedIndex>,
+    /// Symbol index, read on first use (None if the archive has none)
+    symbols: OnceLock<Option<SymbolIndex>>,
+    /// Labeled generations of the file map (empty if the archive has none)
+    snapshots: SnapshotIndex,
+    /// Label of the generation this reader shows (None for the head)
+    snapshot: Option<String>,
+    /// Chunks stored outside the archive's chunk entries (journaled updates)
+    stored_chunks: Arc<StoredChunks>,
+    /// Journal records replayed on open
+    journal_records: usize,
+}
 
-        self.chunk_embeddings = Some(quantized);
-        self.search_index = Some(index);
+/// Text search state of a reader, loaded once and then only read
+#[cfg(all(feature = "embeddings", feature = "search"))]
+struct TextSearch {
+    /// Vector index over the binary embeddings
+    index: VectorIndex,
+    /// Embeddings for rescoring (in RAM, mapped or read on demand)
+    rows: EmbeddingRows,
+    /// Chunk hashes in embedding order (embedding ID -> chunk)
+    chunks: Option<Vec<String>>,
+    /// Embedded chunk hash -> near-duplicate chunks sharing its embedding
+    aliases: HashMap<String, Vec<String>>,
+}
 
-        Ok(self)
+impl CxpReader {
+    /// Open a CXP file for reading
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with(path.as_ref(), false)
     }
 
-    /// Generate multimodal embeddings for all chunks (text + images)
+    /// Open a CXP file without deserializing the full file map
     ///
-    /// This is automatically called during `build()` if multimodal embeddings are enabled.
-    /// Creates a UnifiedIndex with both text and image embeddings in the same vector space.
-    #[cfg(all(feature = "multimodal", feature = "search"))]
-    pub fn generate_multimodal_embeddings(&mut self) -> Result<&mut Self> {
-        tracing::info!("Generating multimodal embeddings for {} unique chunks", self.chunk_store.len());
+    /// File entries are loaded per shard on demand by [`file_entry`](Self::file_entry),
+    /// [`list_prefix`](Self::list_prefix) and the read methods; `file_map` stays empty
+    /// until [`load_file_map`](Self::load_file_map) is called. Archives without
+    /// shards are opened eagerly.
+    pub fn open_lazy<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with(path.as_ref(), true)
+    }
 
-        // Process in batches to avoid OOM
-        const BATCH_SIZE: usize = 32;
+    /// Open a CXP archive held in memory
+    ///
+    /// The reader never touches the filesystem (no temp files, no access
+    /// log), so this works on targets without one such as
+    /// `wasm32-unknown-unknown`.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        Self::from_backend(MemoryBackend::new(data))
+    }
 
-        // Step 1: Generate text embeddings
-        let chunks: Vec<_> = self.chunk_store.chunks().collect();
-        let chunk_texts: Vec<&str> = chunks
-            .iter()
-            .map(|c| {
-                std::str::from_utf8(&c.data)
-                    .unwrap_or("[binary data]")
-            })
-            .collect();
+    /// Open a CXP archive from any seekable reader
+    ///
+    /// The reader is shared by all reads instead of being copied into memory.
+    pub fn from_reader<R: Read + std::io::Seek + Send + 'static>(reader: R) -> Result<Self> {
+        Self::from_backend(SharedReaderBackend::new(reader)?)
+    }
 
-        tracing::info!("Generating text embeddings for {} chunks...", chunk_texts.len());
+    /// Open a CXP archive from a storage backend
+    pub fn from_backend<B: ArchiveBackend + 'static>(backend: B) -> Result<Self> {
+        Self::open_backend(Arc::new(backend), false)
+    }
 
-        let mut all_text_embeddings = Vec::new();
-        {
-            let engine = self.multimodal_engine.as_mut()
-                .ok_or_else(|| CxpError::Embedding(
-                    "Multimodal engine not initialized. Call with_multimodal_embeddings() first.".to_string()
-                ))?;
+    /// Open a CXP archive from an async backend without blocking the runtime
+    ///
+    /// Reads on the returned reader block on the runtime; call them from
+    /// blocking threads (`tokio::task::spawn_blocking`).
+    #[cfg(feature = "tokio")]
+    pub async fn open_async<B: crate::backend::AsyncArchiveBackend>(backend: B) -> Result<Self> {
+        let backend = crate::backend::BlockingBackend::new(backend).await?;
+        tokio::task::spawn_blocking(move || Self::from_backend(backend))
+            .await
+            .map_err(|e| CxpError::io(format!("Open task failed: {}", e)))?
+    }
 
-            for batch in chunk_texts.chunks(BATCH_SIZE) {
-                let embeddings = engine.embed_batch_text(batch)?;
-                all_text_embeddings.extend(embeddings);
-            }
+    fn open_with(path: &Path, lazy: bool) -> Result<Self> {
+        let mut reader = Self::open_backend(Arc::new(FileBackend::new(path)), lazy)?;
+        let journal = Journal::open(path, &journal_base(&reader.manifest))?;
+        if !journal.is_empty() {
+            reader.apply_journal(journal)?;
         }
+        Ok(reader)
+    }
 
-        tracing::info!("Generated {} text embeddings", all_text_embeddings.len());
+    /// Show the archive with its journaled updates (see [`crate::journal`])
+    ///
+    /// The whole file map is loaded; the bloom filters do not know the
+    /// journaled paths and chunks, so they are dropped.
+    fn apply_journal(&mut self, journal: Journal) -> Result<()> {
+        self.load_file_map()?;
+        journal.apply(&mut self.file_map);
+        self.path_filter = None;
+        self.chunk_filter = None;
+
+        self.manifest.file_types.clear();
+        for entry in self.file_map.files.values() {
+            self.manifest.add_file_type(&entry.extension, &entry.path, entry.size);
+        }
+        self.manifest.stats.total_files = self.file_map.files.len();
 
-        // Step 2: Generate image embeddings if needed
-        let mut all_image_embeddings = Vec::new();
-        let image_paths_strings: Vec<String> = if self.process_images && !self.image_files.is_empty() {
-            tracing::info!("Generating image embeddings for {} images...", self.image_files.len());
+        tracing::info!("Replayed {} journal records ({} chunks)", journal.records, journal.chunks.len());
+        self.journal_records = journal.records;
+        self.stored_chunks = Arc::new(StoredChunks {
+            packs: self.stored_chunks.packs.clone(),
+            journal: journal.chunks,
+        });
+        Ok(())
+    }
 
-            let image_paths: Vec<_> = self.image_files.iter().map(|p| p.as_path()).collect();
+    fn open_backend(backend: Arc<dyn ArchiveBackend>, lazy: bool) -> Result<Self> {
+        let mut archive = open_zip(backend.as_ref())?;
 
-            {
-                let engine = self.multimodal_engine.as_mut()
-                    .ok_or_else(|| CxpError::Embedding(
-                        "Multimodal engine not initialized.".to_string()
-                    ))?;
+        // Read manifest
+        let manifest = {
+            let mut manifest_file = archive.by_name("manifest.msgpack")?;
+            let mut data = Vec::new();
+            manifest_file.read_to_end(&mut data)?;
+            Manifest::from_msgpack(&data)?
+        };
+        manifest.check_reader_version()?;
+
+        // Read shard index (absent in older archives, which store file_map.msgpack)
+        let mut shard_index = match archive.by_name(SHARD_INDEX_PATH) {
+            Ok(mut index_file) => {
+                let mut data = Vec::new();
+                index_file.read_to_end(&mut data)?;
+                Some(ShardIndex::from_msgpack(&data)?)
+            }
+            Err(_) => None,
+        };
 
-                for batch in image_paths.chunks(BATCH_SIZE) {
-                    let embeddings = engine.embed_batch_images(batch)?;
-                    all_image_embeddings.extend(embeddings);
+        // Read bloom filters (absent in older archives)
+        let mut read_filter = |name: &str| -> Result<Option<BloomFilter>> {
+            match archive.by_name(name) {
+                Ok(mut filter_file) => {
+                    let mut data = Vec::new();
+                    filter_file.read_to_end(&mut data)?;
+                    Ok(Some(BloomFilter::from_bytes(&data)?))
                 }
+                Err(_) => Ok(None),
             }
+        };
+        let path_filter = read_filter(PATH_FILTER_PATH)?;
+        let chunk_filter = read_filter(CHUNK_FILTER_PATH)?;
 
-            // Collect relative paths
-            self.image_files.iter()
-                .map(|img_path| {
-                    img_path
-                        .strip_prefix(&self.source_dir)
-                        .unwrap_or(img_path)
-                        .to_string_lossy()
-                        .to_string()
-                })
-                .collect()
+        // Read file map (skipped in lazy mode)
+        let file_map = if lazy && shard_index.is_some() {
+            FileMap::default()
         } else {
-            Vec::new()
+            shard_index = None;
+            read_file_map(&mut archive)?
+        };
+        let shards = shard_index
+            .as_ref()
+            .map(|index| index.shards.iter().map(|_| OnceLock::new()).collect())
+            .unwrap_or_default();
+
+        // Read table of contents (absent in older archives)
+        let toc = match archive.by_name(TOC_PATH) {
+            Ok(mut toc_file) => {
+                let mut data = Vec::new();
+                toc_file.read_to_end(&mut data)?;
+                Some(Toc::from_msgpack(&data)?)
+            }
+            Err(_) => None,
         };
 
-        tracing::info!("Generated {} image embeddings", all_image_embeddings.len());
-
-        // Step 3: Build unified index with all embeddings
-        let config = HnswConfig::multimodal_float32();
-        let mut unified_index = UnifiedIndex::new(config)?;
+        // Read the shared compression dictionary (absent unless trained)
+        let codec = read_chunk_codec(&mut archive)?;
 
-        let mut vector_id: u64 = 0;
+        // Read user tags and notes (absent unless annotated)
+        let annotations = Annotations::read_from_archive(&mut archive)?;
 
-        // Add text embeddings to index
-        for (chunk_idx, embedding) in all_text_embeddings.into_iter().enumerate() {
-            let chunk_id = chunk_idx as u64;
+        // Read the snapshot list (absent unless snapshots were taken)
+        let snapshots = SnapshotIndex::read_from_archive(&mut archive)?;
 
-            // Find which file this chunk belongs to (for metadata)
-            let file_path = self.find_file_for_chunk_id(chunk_id)
-                .unwrap_or_else(|| "unknown".to_string());
+        // Read the chunk pack offsets (absent in archives with one entry per chunk)
+        let packs = PackIndex::read_from_archive(&mut archive)?;
 
-            unified_index.add_text(vector_id, &embedding, chunk_id, &file_path)?;
-            vector_id += 1;
-        }
+        // Load extension data if present
+        let mut extension_manager = ExtensionManager::new();
 
-        // Add image embeddings to index
-        for (img_idx, embedding) in all_image_embeddings.into_iter().enumerate() {
-            if img_idx < image_paths_strings.len() {
-                let relative_path = &image_paths_strings[img_idx];
-                unified_index.add_image(vector_id, &embedding, relative_path)?;
-                vector_id += 1;
+        if let Some(ref toc) = toc {
+            // Look up extension entries by name
+            for (namespace, entries) in &toc.extensions {
+                for entry in entries {
+                    let mut file = archive.by_name(&format!("extensions/{}/{}", namespace, entry.name))?;
+                    let mut data = Vec::new();
+                    file.read_to_end(&mut data)?;
+                    load_extension_entry(&mut extension_manager, namespace, &entry.name, data);
+                }
+            }
+        } else {
+            // Iterate through all files in the ZIP archive to find extensions
+            for i in 0..archive.len() {
+                let file = archive.by_index(i)?;
+                let file_name = file.name().to_string();
+
+                // Check if this is an extension file
+                if file_name.starts_with("extensions/") {
+                    let parts: Vec<&str> = file_name.split('/').collect();
+                    if parts.len() >= 3 {
+                        let namespace = parts[1];
+                        let file_key = parts[2..].join("/");
+
+                        // Read the file data
+                        let mut data = Vec::new();
+                        drop(file); // Close the borrowed file
+                        let mut file = archive.by_index(i)?;
+                        file.read_to_end(&mut data)?;
+
+                        load_extension_entry(&mut extension_manager, namespace, &file_key, data);
+                    }
+                }
             }
         }
 
-        tracing::info!("Total vectors in unified index: {}", unified_index.len());
-        tracing::info!("  - Text chunks: {}", unified_index.text_count());
-        tracing::info!("  - Images: {}", unified_index.image_count());
+        if !extension_manager.list_extensions().is_empty() {
+            tracing::info!(
+                "Loaded {} extensions from CXP file",
+                extension_manager.list_extensions().len()
+            );
+        }
 
-        self.unified_index = Some(unified_index);
+        // Keep the handle the metadata was read with for later reads
+        let archives = ArchivePool::default();
+        archives.put(archive);
 
-        Ok(self)
+        Ok(Self {
+            manifest,
+            file_map,
+            backend,
+            toc,
+            path_filter,
+            chunk_filter,
+            shard_index,
+            shards,
+            extension_manager,
+            annotations,
+            temp_policy: TempPolicy::default(),
+            cancellation: CancellationToken::default(),
+            codec,
+            access_log: None,
+            usage: None,
+            #[cfg(all(feature = "embeddings", feature = "search"))]
+            search_options: SearchOptions::default(),
+            backing: None,
+            archives,
+            #[cfg(all(feature = "embeddings", feature = "search"))]
+            text_search: OnceLock::new(),
+            #[cfg(all(feature = "embeddings", feature = "search"))]
+            query_engine: parking_lot::Mutex::new(None),
+            #[cfg(all(feature = "multimodal", feature = "search"))]
+            unified_index: OnceLock::new(),
+            symbols: OnceLock::new(),
+            snapshots,
+            snapshot: None,
+            stored_chunks: Arc::new(StoredChunks { packs, journal: HashMap::new() }),
+            journal_records: 0,
+        })
     }
 
-    /// Find which file a chunk ID belongs to (helper for metadata)
-    #[cfg(all(feature = "multimodal", feature = "search"))]
-    fn find_file_for_chunk_id(&self, chunk_id: u64) -> Option<String> {
-        // Iterate through file map to find which file contains this chunk
-        for (path, entry) in &self.file_map.files {
-            if !entry.is_image {
-                // Check if this chunk ID is in the file's chunk range
-                // This is a simplified approach - in production you'd maintain a proper mapping
-                if chunk_id < entry.chunks.len() as u64 {
-                    return Some(path.clone());
-                }
-            }
-        }
-        None
+    /// ZIP handle on the archive, taken from the pool of idle ones
+    fn archive(&self) -> Result<PooledArchive> {
+        self.archives.get(self.backend.as_ref())
     }
 
-    /// Add an extension with data to this CXP file
-    ///
-    /// The extension will be registered and its data will be stored in the
-    /// extensions/{namespace}/ directory in the CXP file.
-    ///
-    /// # Arguments
-    /// * `ext` - An object implementing the Extension trait
-    /// * `data` - A HashMap of file names to their data (e.g., "conversations.msgpack" -> bytes)
-    ///
-    /// # Example
-    /// ```ignore
-    /// use std::collections::HashMap;
-    ///
-    /// let mut builder = CxpBuilder::new("./src");
-    /// let contextai = ContextAIExtension::new();
-    /// let data = contextai.to_extension_data()?;
-    ///
-    /// builder.add_extension(&contextai, data)?;
-    /// ```
-    pub fn add_extension<E: Extension + Clone>(
-        &mut self,
-        ext: &E,
-        data: HashMap<String, Vec<u8>>,
-    ) -> Result<&mut Self> {
-        // Register the extension
-        self.extension_manager.register(ext.clone());
-
-        // Add all data files
-        for (key, bytes) in data {
-            self.extension_manager.write_data(ext.namespace(), &key, &bytes)?;
-        }
-
-        // Update manifest to include this extension
-        if !self.manifest.extensions.contains(&ext.namespace().to_string()) {
-            self.manifest.extensions.push(ext.namespace().to_string());
-        }
-
-        tracing::info!(
-            "Added extension '{}' (v{}) with {} data files",
-            ext.namespace(),
-            ext.version(),
-            self.extension_manager.list_data_keys(ext.namespace()).len()
-        );
+    /// Set where embedded children are extracted by `open_child()`
+    pub fn with_temp_policy(mut self, policy: TempPolicy) -> Self {
+        self.temp_policy = policy;
+        self
+    }
 
-        Ok(self)
+    /// Temp file policy used when opening embedded children
+    pub fn temp_policy(&self) -> &TempPolicy {
+        &self.temp_policy
     }
 
-    /// Process a single file
-    fn process_file(&self, path: &Path, base_dir: &Path) -> Result<(FileEntry, Vec<Chunk>)> {
-        // Read file content
-        let mut file = File::open(path)?;
-        let metadata = file.metadata()?;
-        let mut content = Vec::with_capacity(metadata.len() as usize);
-        file.read_to_end(&mut content)?;
+    /// Abort index loading and searches once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
 
-        // Get relative path
-        let relative_path = path
-            .strip_prefix(base_dir)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .to_string();
+    /// Record file reads in the archive's access log sidecar
+    ///
+    /// Children opened through this reader record into the same log.
+    pub fn with_access_tracking(mut self, enabled: bool) -> Self {
+        self.access_log = match self.backend.path() {
+            Some(path) if enabled => Some(AccessLog::for_archive(path)),
+            _ => None,
+        };
+        self
+    }
 
-        // Get extension
-        let extension = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
+    /// Record file reads in an explicit access log
+    pub fn with_access_log(mut self, log: AccessLog) -> Self {
+        self.access_log = Some(log);
+        self
+    }
 
-        // Chunk the content
-        let chunks = chunk_content(&content);
+    /// Access log file reads are recorded in
+    pub fn access_log(&self) -> Option<&AccessLog> {
+        self.access_log.as_ref()
+    }
 
-        let entry = FileEntry {
-            path: relative_path,
-            extension,
-            size: metadata.len(),
-            chunks: Vec::new(), // Will be filled in with refs later
-            is_image: false,
+    /// Record query counts and hit rates in the archive's usage sidecar
+    ///
+    /// Metrics are opt-in, aggregate only and never leave the machine.
+    pub fn with_usage_metrics(mut self, enabled: bool) -> Self {
+        self.usage = match self.backend.path() {
+            Some(path) if enabled => Some(Arc::new(UsageRecorder::for_archive(path))),
+            _ => None,
         };
-
-        Ok((entry, chunks))
+        self
     }
 
-    /// Process a single image file (stores entire image as one chunk)
-    #[cfg(feature = "multimodal")]
-    fn process_image(&self, path: &Path, base_dir: &Path) -> Result<(FileEntry, Chunk)> {
-        // Read image file
-        let mut file = File::open(path)?;
-        let metadata = file.metadata()?;
-        let mut content = Vec::with_capacity(metadata.len() as usize);
-        file.read_to_end(&mut content)?;
+    /// Record usage with an explicit (possibly shared) recorder
+    pub fn with_usage_recorder(mut self, recorder: Arc<UsageRecorder>) -> Self {
+        self.usage = Some(recorder);
+        self
+    }
 
-        // Get relative path
-        let relative_path = path
-            .strip_prefix(base_dir)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .to_string();
+    /// Recorder usage is counted in, for hosts recording their own queries and reads
+    pub fn usage_recorder(&self) -> Option<&UsageRecorder> {
+        self.usage.as_deref()
+    }
 
-        // Get extension
-        let extension = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
+    /// Set search options (`timeout` bounds `search_multi()` across query variants)
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn with_search_options(mut self, options: SearchOptions) -> Self {
+        self.search_options = options;
+        self
+    }
 
-        // Create single chunk for entire image
-        let chunk = Chunk::new(content, 0);
+    /// Get the manifest
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
 
-        let entry = FileEntry {
-            path: relative_path,
-            extension,
-            size: metadata.len(),
-            chunks: Vec::new(), // Will be filled in with ref later
-            is_image: true,
-        };
+    /// Trained zstd dictionary the chunks are compressed with (None if untrained)
+    pub fn compression_dictionary(&self) -> Option<&[u8]> {
+        self.codec.dictionary()
+    }
 
-        Ok((entry, chunk))
+    /// Global index over the children's files (`None` if the archive has none)
+    pub fn global_index(&self) -> Result<Option<GlobalIndex>> {
+        GlobalIndex::read_from_archive(&mut *self.archive()?)
     }
 
-    /// Build and write the CXP file
-    pub fn build<P: AsRef<Path>>(&mut self, output_path: P) -> Result<()> {
-        let output_path = output_path.as_ref();
-        tracing::info!("Building CXP file: {:?}", output_path);
+    /// Reference to a child CXP by id
+    pub fn child(&self, id: &str) -> Option<&CxpRef> {
+        self.manifest.children.get(id)
+    }
 
-        // Generate embeddings if engine is set but embeddings haven't been generated yet
-        #[cfg(all(feature = "embeddings", feature = "search"))]
-        if self.embedding_engine.is_some() && self.chunk_embeddings.is_none() {
-            self.generate_embeddings()?;
-        }
+    /// Archive bytes of an embedded child
+    pub fn read_child(&self, id: &str) -> Result<Vec<u8>> {
+        let child = self.child(id)
+            .ok_or_else(|| CxpError::FileNotFound(format!("child {}", id)))?;
+        let CxpStorage::Embedded { path_in_zip } = &child.storage else {
+            return Err(CxpError::InvalidFormat(format!("Child '{}' is not embedded", id)));
+        };
 
-        // Generate multimodal embeddings if multimodal engine is set
-        #[cfg(all(feature = "multimodal", feature = "search"))]
-        if self.multimodal_engine.is_some() && self.unified_index.is_none() {
-            self.generate_multimodal_embeddings()?;
+        let mut archive = self.archive()?;
+        let mut entry = archive.by_name(path_in_zip)
+            .map_err(|e| CxpError::InvalidFormat(format!("No {} found: {}", path_in_zip, e)))?;
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Open a child CXP
+    ///
+    /// Embedded children are extracted to a temp file (following this reader's
+    /// temp policy) that is removed when the returned reader is dropped; children
+    /// of an in-memory archive stay in memory. Relative external paths are
+    /// resolved against this archive's directory.
+    pub fn open_child(&self, id: &str) -> Result<CxpReader> {
+        let child = self.child(id)
+            .ok_or_else(|| CxpError::FileNotFound(format!("child {}", id)))?;
+
+        match &child.storage {
+            CxpStorage::Embedded { .. } if self.backend.path().is_none() => {
+                Ok(Self::from_bytes(self.read_child(id)?)?.with_cancellation(self.cancellation.clone()))
+            }
+            CxpStorage::Embedded { .. } => {
+                let data = self.read_child(id)?;
+                let mut temp = self.temp_policy.guard("cxp_child")?;
+                let path = temp.with_extension("cxp");
+                std::fs::write(&path, data)?;
+
+                let mut reader = Self::open(&path)?
+                    .with_temp_policy(self.temp_policy.clone())
+                    .with_cancellation(self.cancellation.clone());
+                reader.backing = Some(temp);
+                reader.access_log = self.access_log.as_ref().map(|log| log.child(id));
+                Ok(reader)
+            }
+            CxpStorage::External { path } => {
+                let path = match self.backend.path().and_then(Path::parent) {
+                    Some(dir) if path.is_relative() => dir.join(path),
+                    _ => path.clone(),
+                };
+                let mut reader = Self::open(path)?
+                    .with_temp_policy(self.temp_policy.clone())
+                    .with_cancellation(self.cancellation.clone());
+                reader.access_log = self.access_log.as_ref().map(|log| log.child(id));
+                Ok(reader)
+            }
+            CxpStorage::Remote { url, .. } => Err(CxpError::io(format!(
+                "Remote CXP loading not yet implemented: {}",
+                url
+            ))),
         }
+    }
 
-        let file = File::create(output_path)?;
-        let mut zip = ZipWriter::new(file);
+    /// Extract embedded children into `dest_dir`, recursing into their own children
+    ///
+    /// Each child is written to `<dest_dir>/<id>.cxp`; its embedded children go
+    /// to `<dest_dir>/<id>/`. Returns the written paths.
+    pub fn extract_children<P: AsRef<Path>>(&self, dest_dir: P) -> Result<Vec<PathBuf>> {
+        let dest_dir = dest_dir.as_ref();
+        let mut written = Vec::new();
+
+        for child in self.manifest.children.iter().filter(|c| c.is_embedded()) {
+            std::fs::create_dir_all(dest_dir)?;
+            let path = dest_dir.join(format!("{}.cxp", child.id));
+            std::fs::write(&path, self.read_child(&child.id)?)?;
+            written.push(path.clone());
+
+            let reader = Self::open(&path)?
+                .with_temp_policy(self.temp_policy.clone())
+                .with_cancellation(self.cancellation.clone());
+            written.extend(reader.extract_children(dest_dir.join(&child.id))?);
+        }
 
-        let options = FileOptions::<()>::default()
-            .compression_method(CompressionMethod::Stored); // We compress chunks ourselves
+        Ok(written)
+    }
 
-        // Write manifest
-        let manifest_data = self.manifest.to_msgpack()?;
-        zip.start_file("manifest.msgpack", options.clone())?;
-        zip.write_all(&manifest_data)?;
+    /// Get the table of contents (None for archives written before it existed)
+    pub fn toc(&self) -> Option<&Toc> {
+        self.toc.as_ref()
+    }
 
-        // Write file map
-        let file_map_data = rmp_serde::to_vec(&self.file_map)?;
-        zip.start_file("file_map.msgpack", options.clone())?;
-        zip.write_all(&file_map_data)?;
+    /// Get all file paths
+    ///
+    /// In lazy mode this loads every shard; prefer [`list_prefix`](Self::list_prefix).
+    pub fn file_paths(&self) -> Vec<&str> {
+        if self.shard_index.is_none() {
+            return self.file_map.files.keys().map(|s| s.as_str()).collect();
+        }
 
-        // Write chunks
-        let chunks: Vec<_> = self.chunk_store.chunks().collect();
-        let total_chunks = chunks.len();
+        self.list_prefix("").unwrap_or_else(|e| {
+            tracing::warn!("Failed to load file map shards: {}", e);
+            Vec::new()
+        })
+    }
 
-        for (i, chunk) in chunks.iter().enumerate() {
-            let chunk_name = format!("chunks/{}.zst", chunk.id());
-            let compressed = compress(&chunk.data)?;
+    /// Paths matching a glob or regular expression (see [`PathPattern`])
+    pub fn files_matching(&self, pattern: &PathPattern) -> Vec<&str> {
+        let mut paths: Vec<&str> = self.file_paths().into_iter().filter(|path| pattern.is_match(path)).collect();
+        paths.sort_unstable();
+        paths
+    }
 
-            zip.start_file(&chunk_name, options.clone())?;
-            zip.write_all(&compressed)?;
+    /// Check whether the archive may contain a file (`false` is definite)
+    ///
+    /// Answers from the bloom filter only; always `true` for archives without one.
+    pub fn might_contain_file(&self, path: &str) -> bool {
+        self.path_filter.as_ref().is_none_or(|f| f.contains(path.as_bytes()))
+    }
 
-            if (i + 1) % 100 == 0 || i + 1 == total_chunks {
-                tracing::debug!("Written {}/{} chunks", i + 1, total_chunks);
-            }
+    /// Check whether the archive contains a file
+    ///
+    /// Negatives are answered by the bloom filter without touching the file map.
+    pub fn contains_file(&self, path: &str) -> Result<bool> {
+        if !self.might_contain_file(path) {
+            return Ok(false);
         }
+        Ok(self.file_entry(path)?.is_some())
+    }
 
-        // Write embeddings if present
-        #[cfg(all(feature = "embeddings", feature = "search"))]
-        if let Some(ref embeddings) = self.chunk_embeddings {
-            tracing::info!("Writing embeddings to CXP file...");
+    /// Check whether the archive may contain a chunk (`false` is definite)
+    pub fn might_contain_chunk(&self, hash: &str) -> bool {
+        self.chunk_filter.as_ref().is_none_or(|f| f.contains(hash.as_bytes()))
+    }
 
-            // Write binary embeddings
-            let binary_data = serialize_binary_embeddings(&embeddings.binary)?;
-            zip.start_file("embeddings/binary.bin", options.clone())?;
-            zip.write_all(&binary_data)?;
+    /// Check whether the archive contains a chunk
+    ///
+    /// Negatives are answered by the bloom filter without opening the chunk directory.
+    pub fn contains_chunk(&self, hash: &str) -> Result<bool> {
+        if !self.might_contain_chunk(hash) {
+            return Ok(false);
+        }
 
-            // Write int8 embeddings
-            let int8_data = serialize_int8_embeddings(&embeddings.int8)?;
-            zip.start_file("embeddings/int8.bin", options.clone())?;
-            zip.write_all(&int8_data)?;
+        Ok(self.stored_chunks.contains(&*self.archive()?, hash))
+    }
 
-            // Mark that we have embeddings
-            if !self.manifest.extensions.contains(&"embeddings".to_string()) {
-                self.manifest.extensions.push("embeddings".to_string());
-            }
+    /// Check whether file entries are loaded on demand
+    pub fn is_lazy(&self) -> bool {
+        self.shard_index.is_some()
+    }
 
-            tracing::info!("Embeddings written successfully");
+    /// Look up a file entry, loading its shard if needed
+    pub fn file_entry(&self, path: &str) -> Result<Option<&FileEntry>> {
+        let Some(ref index) = self.shard_index else {
+            return Ok(self.file_map.files.get(path));
+        };
+
+        match index.shard_for_path(path) {
+            Some(i) => Ok(self.load_shard(i)?.files.get(path)),
+            None => Ok(None),
         }
+    }
 
-        // Write HNSW index if present
-        #[cfg(all(feature = "embeddings", feature = "search"))]
-        if let Some(ref index) = self.search_index {
-            tracing::info!("Writing HNSW index to CXP file...");
+    /// List file paths starting with `prefix` in sorted order
+    ///
+    /// In lazy mode only the shards whose path range overlaps the prefix are loaded.
+    pub fn list_prefix(&self, prefix: &str) -> Result<Vec<&str>> {
+        fn matching<'a>(map: &'a FileMap, prefix: &str) -> Vec<&'a str> {
+            use std::ops::Bound;
+
+            map.files
+                .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+                .take_while(|(path, _)| path.starts_with(prefix))
+                .map(|(path, _)| path.as_str())
+                .collect()
+        }
 
-            // Save index to a temporary file first (USearch limitation)
-            let temp_dir = std::env::temp_dir();
-            let temp_index_path = temp_dir.join(format!("cxp_index_{}.hnsw", uuid::Uuid::new_v4()));
+        let Some(ref index) = self.shard_index else {
+            return Ok(matching(&self.file_map, prefix));
+        };
 
-            index.save(&temp_index_path)?;
+        let mut paths = Vec::new();
+        for i in index.shards_for_prefix(prefix) {
+            paths.extend(matching(self.load_shard(i)?, prefix));
+        }
+        Ok(paths)
+    }
 
-            // Read the index file and write to ZIP
-            let mut index_file = File::open(&temp_index_path)?;
-            let mut index_data = Vec::new();
-            index_file.read_to_end(&mut index_data)?;
+    /// Load every shard into `file_map`, switching a lazy reader to eager mode
+    pub fn load_file_map(&mut self) -> Result<()> {
+        if self.shard_index.is_none() {
+            return Ok(());
+        }
 
-            zip.start_file("embeddings/index.hnsw", options.clone())?;
-            zip.write_all(&index_data)?;
+        let mut files = BTreeMap::new();
+        for i in 0..self.shards.len() {
+            files.extend(self.load_shard(i)?.files.clone());
+        }
 
-            // Clean up temp file
-            std::fs::remove_file(&temp_index_path)?;
+        self.file_map = FileMap { files };
+        self.shard_index = None;
+        self.shards.clear();
+        Ok(())
+    }
 
-            tracing::info!("HNSW index written successfully ({} vectors)", index.len());
+    /// Load (or get the cached) file map shard `i`
+    fn load_shard(&self, i: usize) -> Result<&FileMap> {
+        if let Some(shard) = self.shards[i].get() {
+            return Ok(shard);
         }
 
-        // Write UnifiedIndex if present (for multimodal)
-        #[cfg(all(feature = "multimodal", feature = "search"))]
-        if let Some(ref index) = self.unified_index {
-            tracing::info!("Writing UnifiedIndex to CXP file...");
+        let index = self.shard_index.as_ref()
+            .ok_or_else(|| CxpError::InvalidFormat("Reader has no shard index".to_string()))?;
+        let shard_path = &index.shards[i].path;
 
-            // Save index to temporary files
-            let temp_dir = std::env::temp_dir();
-            let temp_base_path = temp_dir.join(format!("cxp_unified_{}", uuid::Uuid::new_v4()));
+        let mut archive = self.archive()?;
+        let mut shard_file = archive.by_name(shard_path)?;
+        let mut data = Vec::new();
+        shard_file.read_to_end(&mut data)?;
+        let shard: FileMap = rmp_serde::from_slice(&data)?;
 
-            index.save(&temp_base_path)?;
+        tracing::debug!("Loaded file map shard {} ({} files)", shard_path, shard.files.len());
+        Ok(self.shards[i].get_or_init(|| shard))
+    }
 
-            // Read the index file and write to ZIP
-            let temp_index_path = temp_base_path.with_extension("index");
-            let mut index_file = File::open(&temp_index_path)?;
-            let mut index_data = Vec::new();
-            index_file.read_to_end(&mut index_data)?;
+    /// Read a file's content by reconstructing from chunks
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        let chunk_count = self.file_entry(path)?
+            .ok_or_else(|| CxpError::FileNotFound(path.to_string()))?
+            .chunks
+            .len();
 
-            zip.start_file("embeddings/unified.index", options.clone())?;
-            zip.write_all(&index_data)?;
+        let content = self.read_file_chunks(path, 0..chunk_count)?;
+        self.record_access(path);
+        Ok(content)
+    }
 
-            // Read the metadata file and write to ZIP
-            let temp_meta_path = temp_base_path.with_extension("meta");
-            let mut meta_file = File::open(&temp_meta_path)?;
-            let mut meta_data = Vec::new();
-            meta_file.read_to_end(&mut meta_data)?;
+    /// Record a file read in the access log (if tracking is on)
+    pub(crate) fn record_access(&self, path: &str) {
+        if let Some(ref log) = self.access_log {
+            if let Err(e) = log.record(Some(path)) {
+                tracing::warn!("Could not record access to {}: {}", path, e);
+            }
+        }
+    }
 
-            zip.start_file("embeddings/unified.meta", options.clone())?;
-            zip.write_all(&meta_data)?;
+    /// Read a contiguous range of a file's chunks (by chunk index within the file)
+    ///
+    /// The range is clamped to the file's chunk count.
+    pub fn read_file_chunks(&self, path: &str, range: std::ops::Range<usize>) -> Result<Vec<u8>> {
+        let entry = self.file_entry(path)?
+            .ok_or_else(|| CxpError::FileNotFound(path.to_string()))?;
 
-            // Clean up temp files
-            std::fs::remove_file(&temp_index_path)?;
-            std::fs::remove_file(&temp_meta_path)?;
+        let end = range.end.min(entry.chunks.len());
+        let start = range.start.min(end);
+        let chunk_refs = &entry.chunks[start..end];
 
-            // Mark that we have embeddings
-            if !self.manifest.extensions.contains(&"embeddings".to_string()) {
-                self.manifest.extensions.push("embeddings".to_string());
-            }
+        let mut archive = self.archive()?;
 
-            tracing::info!("UnifiedIndex written successfully ({} vectors: {} text, {} images)",
-                index.len(), index.text_count(), index.image_count());
+        let capacity: usize = chunk_refs.iter().map(|c| c.length).sum();
+        let mut content = Vec::with_capacity(capacity);
+
+        for chunk_ref in chunk_refs {
+            let compressed = self.stored_chunks.read(&mut archive, &chunk_ref.hash)?;
+            let decompressed = self.codec.decompress(&compressed)?;
+            content.extend_from_slice(&decompressed);
         }
 
-        // Write extension data if present
-        if !self.extension_manager.list_extensions().is_empty() {
-            tracing::info!("Writing extension data to CXP file...");
+        Ok(content)
+    }
 
-            // Write extension manifests
-            for manifest in self.extension_manager.manifests().values() {
-                let manifest_path = format!("extensions/{}/manifest.msgpack", manifest.namespace);
-                let manifest_data = manifest.to_msgpack()?;
-                zip.start_file(&manifest_path, options.clone())?;
-                zip.write_all(&manifest_data)?;
-            }
+    /// Open a file for streaming reads
+    ///
+    /// Chunks are decompressed one at a time as the stream is read, so memory
+    /// use stays bounded by the largest chunk regardless of file size.
+    pub fn open_file_stream(&self, path: &str) -> Result<FileStream> {
+        self.open_file_range(path, 0..u64::MAX)
+    }
 
-            // Write extension data files
-            for (namespace, data_map) in self.extension_manager.all_data() {
-                for (key, data) in data_map {
-                    let data_path = format!("extensions/{}/{}", namespace, key);
-                    zip.start_file(&data_path, options.clone())?;
-                    zip.write_all(data)?;
-                }
-            }
+    /// Open a byte range of a file for streaming reads
+    ///
+    /// The range is clamped to the file size. Only chunks overlapping the
+    /// range (located via the chunk offsets) are decompressed.
+    pub fn open_file_range(&self, path: &str, range: std::ops::Range<u64>) -> Result<FileStream> {
+        let entry = self.file_entry(path)?
+            .ok_or_else(|| CxpError::FileNotFound(path.to_string()))?;
 
-            tracing::info!(
-                "Written {} extensions to CXP file",
-                self.extension_manager.list_extensions().len()
-            );
+        let end = range.end.min(entry.size);
+        let start = range.start.min(end);
+
+        let first = entry.chunks.partition_point(|c| (c.offset + c.length) as u64 <= start);
+        let last = entry.chunks.partition_point(|c| (c.offset as u64) < end);
+        let chunks = entry.chunks[first..last.max(first)].to_vec();
+        let skip = chunks.first().map_or(0, |c| (start - c.offset as u64) as usize);
+
+        let archive = self.archive()?;
+
+        Ok(FileStream {
+            archive,
+            chunks,
+            size: entry.size,
+            next_chunk: 0,
+            buffer: Vec::new(),
+            position: 0,
+            skip,
+            remaining: end - start,
+            codec: self.codec.clone(),
+            stored: Arc::clone(&self.stored_chunks),
+        })
+    }
+
+    /// Read a single chunk by its hash
+    pub fn read_chunk(&self, hash: &str) -> Result<Vec<u8>> {
+        if hash.len() < 16 {
+            return Err(CxpError::Chunk(format!("Invalid chunk hash: {}", hash)));
         }
 
-        zip.finish()?;
+        let compressed = self.stored_chunks.read(&mut *self.archive()?, hash)?;
+        self.codec.decompress(&compressed)
+    }
 
-        // Update manifest with final size
-        let final_size = std::fs::metadata(output_path)?.len();
-        self.manifest.stats.cxp_size_bytes = final_size;
-        self.manifest.stats.compression_ratio =
-            final_size as f64 / self.manifest.stats.original_size_bytes as f64;
+    /// Write every file to `dest` under its original relative path
+    ///
+    /// Chunks are checked against their hashes and recorded modification
+    /// times are restored. See [`Self::extract_all_with`] for other options.
+    pub fn extract_all<P: AsRef<Path>>(&self, dest: P, overwrite: OverwritePolicy) -> Result<ExtractStats> {
+        self.extract_all_with(dest, &ExtractOptions::new().with_overwrite(overwrite))
+    }
+
+    /// Write every file to `dest` with custom options
+    ///
+    /// All paths are validated before the first file is written. Files are
+    /// streamed chunk by chunk and only moved into place once complete.
+    pub fn extract_all_with<P: AsRef<Path>>(&self, dest: P, options: &ExtractOptions) -> Result<ExtractStats> {
+        let dest = dest.as_ref();
+        let mut paths = match options.pattern {
+            Some(ref pattern) => self.files_matching(pattern),
+            None => self.file_paths(),
+        };
+        paths.sort_unstable();
+        let targets = paths
+            .iter()
+            .map(|path| Ok((*path, safe_join(dest, path)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        std::fs::create_dir_all(dest)?;
+        let mut archive = self.archive()?;
+        let mut verified = std::collections::HashSet::new();
+        let mut stats = ExtractStats::default();
+        for (path, target) in targets {
+            self.cancellation.check("extract")?;
+            let entry = self.file_entry(path)?
+                .ok_or_else(|| CxpError::FileNotFound(path.to_string()))?;
+            if !should_write(entry, &target, options.overwrite)? {
+                stats.files_skipped += 1;
+                continue;
+            }
+            stats.bytes_written += write_entry(&mut archive, &self.stored_chunks, &self.codec, entry, &target, options, &mut verified)?;
+            stats.files_written += 1;
+        }
+        stats.chunks_verified = verified.len();
 
         tracing::info!(
-            "CXP file created: {:.2} MB (compression ratio: {:.2}%)",
-            final_size as f64 / 1024.0 / 1024.0,
-            self.manifest.stats.compression_ratio * 100.0
+            "Extracted {} files ({} bytes) to {:?}, skipped {}",
+            stats.files_written,
+            stats.bytes_written,
+            dest,
+            stats.files_skipped
         );
+        Ok(stats)
+    }
 
-        Ok(())
+    /// Collect a structured statistics report (chunk sizes, dedup, compression, embeddings)
+    pub fn statistics(&self) -> Result<crate::stats::ArchiveStatistics> {
+        if self.shard_index.is_none() {
+            return crate::stats::collect(&self.file_map, self.backend.as_ref());
+        }
+
+        let mut file_map = FileMap::default();
+        for i in 0..self.shards.len() {
+            file_map.files.extend(self.load_shard(i)?.files.clone());
+        }
+        crate::stats::collect(&file_map, self.backend.as_ref())
     }
-}
 
-/// Reader for CXP files
-pub struct CxpReader {
-    /// The manifest
-    pub manifest: Manifest,
-    /// File map
-    pub file_map: FileMap,
-    /// ZIP archive handle
-    archive_path: PathBuf,
-    /// Extension manager for reading app-specific data
-    extension_manager: ExtensionManager,
-    /// Cached HNSW index for semantic search (text-only)
-    #[cfg(all(feature = "embeddings", feature = "search"))]
-    search_index: Option<HnswIndex>,
-    /// Cached embeddings for rescoring
-    #[cfg(all(feature = "embeddings", feature = "search"))]
-    embeddings: Option<QuantizedEmbeddings>,
-    /// Cached UnifiedIndex for multimodal search
-    #[cfg(all(feature = "multimodal", feature = "search"))]
-    unified_index: Option<UnifiedIndex>,
-}
+    /// Number of journaled updates replayed on open (see [`crate::journal`])
+    pub fn journal_records(&self) -> usize {
+        self.journal_records
+    }
 
-impl CxpReader {
-    /// Open a CXP file for reading
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref().to_path_buf();
-        let file = File::open(&path)?;
-        let mut archive = ZipArchive::new(file)?;
+    /// Snapshots stored with `CxpBuilder::snapshot()`, oldest first
+    pub fn snapshots(&self) -> &[SnapshotInfo] {
+        &self.snapshots.snapshots
+    }
 
-        // Read manifest
-        let manifest = {
-            let mut manifest_file = archive.by_name("manifest.msgpack")?;
-            let mut data = Vec::new();
-            manifest_file.read_to_end(&mut data)?;
-            Manifest::from_msgpack(&data)?
-        };
+    /// Label of the snapshot this reader shows (None for the head)
+    pub fn snapshot_label(&self) -> Option<&str> {
+        self.snapshot.as_deref()
+    }
 
-        // Read file map
-        let file_map = {
-            let mut file_map_file = archive.by_name("file_map.msgpack")?;
-            let mut data = Vec::new();
-            file_map_file.read_to_end(&mut data)?;
-            rmp_serde::from_slice(&data)?
-        };
+    /// Reader over the files as they were in snapshot `label`
+    ///
+    /// Listing, reading and extracting files see that generation; the
+    /// manifest, search indices, symbols and extensions stay those of the
+    /// head. See [`crate::snapshots`].
+    pub fn open_snapshot(&self, label: &str) -> Result<CxpReader> {
+        let file_map = self.snapshots.read_file_map(&mut *self.archive()?, label)?;
+        let mut reader = Self::open_backend(Arc::clone(&self.backend), true)?
+            .with_temp_policy(self.temp_policy.clone())
+            .with_cancellation(self.cancellation.clone());
+        reader.file_map = file_map;
+        reader.shard_index = None;
+        reader.shards = Vec::new();
+        // The bloom filters only cover the head
+        reader.path_filter = None;
+        reader.chunk_filter = None;
+        reader.snapshot = Some(label.to_string());
+        Ok(reader)
+    }
 
-        // Load extension data if present
-        let mut extension_manager = ExtensionManager::new();
+    /// User tags and notes of the archive's files
+    pub fn annotations(&self) -> &Annotations {
+        &self.annotations
+    }
 
-        // Iterate through all files in the ZIP archive to find extensions
-        for i in 0..archive.len() {
-            let file = archive.by_index(i)?;
-            let file_name = file.name().to_string();
+    /// Tags of a file (sorted)
+    pub fn file_tags(&self, path: &str) -> Vec<&str> {
+        self.annotations.tags(path)
+    }
 
-            // Check if this is an extension file
-            if file_name.starts_with("extensions/") {
-                let parts: Vec<&str> = file_name.split('/').collect();
-                if parts.len() >= 3 {
-                    let namespace = parts[1];
-                    let file_key = parts[2..].join("/");
+    /// Files carrying `tag` (path order)
+    pub fn files_with_tag(&self, tag: &str) -> Vec<&str> {
+        self.annotations.files_with_tag(tag)
+    }
 
-                    // Read the file data
-                    let mut data = Vec::new();
-                    drop(file); // Close the borrowed file
-                    let mut file = archive.by_index(i)?;
-                    file.read_to_end(&mut data)?;
+    /// Clusters of files that share chunks, with shared-content percentages,
+    /// and groups of identical and near-identical files
+    pub fn duplicate_report(&self) -> Result<crate::stats::DuplicateReport> {
+        self.duplicate_report_with(&crate::stats::DuplicateOptions::default())
+    }
 
-                    if file_key == "manifest.msgpack" {
-                        // Load extension manifest
-                        if let Ok(ext_manifest) = crate::extensions::ExtensionManifest::from_msgpack(&data) {
-                            extension_manager.load_manifest(ext_manifest);
-                        }
-                    } else {
-                        // Load extension data
-                        extension_manager.load_data(namespace.to_string(), file_key, data);
-                    }
-                }
-            }
+    /// Like [`Self::duplicate_report`] with custom clustering options
+    pub fn duplicate_report_with(
+        &self,
+        options: &crate::stats::DuplicateOptions,
+    ) -> Result<crate::stats::DuplicateReport> {
+        if self.shard_index.is_none() {
+            return Ok(crate::stats::duplicate_report(&self.file_map, options));
         }
 
-        if !extension_manager.list_extensions().is_empty() {
-            tracing::info!(
-                "Loaded {} extensions from CXP file",
-                extension_manager.list_extensions().len()
-            );
+        let mut file_map = FileMap::default();
+        for i in 0..self.shards.len() {
+            file_map.files.extend(self.load_shard(i)?.files.clone());
         }
-
-        Ok(Self {
-            manifest,
-            file_map,
-            archive_path: path,
-            extension_manager,
-            #[cfg(all(feature = "embeddings", feature = "search"))]
-            search_index: None,
-            #[cfg(all(feature = "embeddings", feature = "search"))]
-            embeddings: None,
-            #[cfg(all(feature = "multimodal", feature = "search"))]
-            unified_index: None,
-        })
+        Ok(crate::stats::duplicate_report(&file_map, options))
     }
 
-    /// Get the manifest
-    pub fn manifest(&self) -> &Manifest {
-        &self.manifest
-    }
+    /// Directory hierarchy of the files with per-directory file counts and sizes
+    pub fn tree(&self) -> Result<crate::tree::DirTree> {
+        if self.shard_index.is_none() {
+            return Ok(crate::tree::DirTree::from_file_map(&self.file_map));
+        }
 
-    /// Get all file paths
-    pub fn file_paths(&self) -> Vec<&str> {
-        self.file_map.files.keys().map(|s| s.as_str()).collect()
+        let mut file_map = FileMap::default();
+        for i in 0..self.shards.len() {
+            file_map.files.extend(self.load_shard(i)?.files.clone());
+        }
+        Ok(crate::tree::DirTree::from_file_map(&file_map))
     }
 
-    /// Read a file's content by reconstructing from chunks
-    pub fn read_file(&self, path: &str) -> Result<Vec<u8>> {
-        let entry = self.file_map.files.get(path)
-            .ok_or_else(|| CxpError::FileNotFound(path.to_string()))?;
-
-        let file = File::open(&self.archive_path)?;
-        let mut archive = ZipArchive::new(file)?;
+    /// Enumerate stored chunks with their sizes and reference counts (sorted by hash)
+    ///
+    /// Chunks stored in the archive but not referenced by any file are reported
+    /// with `ref_count == 0`; since their full hash and size are only recorded
+    /// in the file map, `hash` is the 16-character storage id and
+    /// `uncompressed_size` is 0.
+    pub fn chunks(&self) -> Result<impl Iterator<Item = ChunkInfo>> {
+        let mut chunks: BTreeMap<String, ChunkInfo> = BTreeMap::new();
+        let mut count_refs = |file_map: &FileMap| {
+            for chunk in file_map.files.values().flat_map(|e| &e.chunks) {
+                chunks
+                    .entry(chunk.hash.clone())
+                    .or_insert_with(|| ChunkInfo {
+                        hash: chunk.hash.clone(),
+                        compressed_size: 0,
+                        uncompressed_size: chunk.length as u64,
+                        ref_count: 0,
+                    })
+                    .ref_count += 1;
+            }
+        };
 
-        let mut content = Vec::with_capacity(entry.size as usize);
+        if self.shard_index.is_none() {
+            count_refs(&self.file_map);
+        } else {
+            for i in 0..self.shards.len() {
+                count_refs(self.load_shard(i)?);
+            }
+        }
 
-        for chunk_ref in &entry.chunks {
-            let chunk_name = format!("chunks/{}.zst", &chunk_ref.hash[..16]);
-            let mut chunk_file = archive.by_name(&chunk_name)?;
+        // Compressed sizes come from the pack index and ZIP directory (no decompression)
+        let mut stored: HashMap<String, u64> =
+            self.stored_chunks.packs.stored_sizes(&mut *self.archive()?)?.into_iter().collect();
 
-            let mut compressed = Vec::new();
-            chunk_file.read_to_end(&mut compressed)?;
+        for (hash, data) in &self.stored_chunks.journal {
+            stored.insert(chunk_id(hash).to_string(), data.len() as u64);
+        }
 
-            let decompressed = decompress(&compressed)?;
-            content.extend_from_slice(&decompressed);
+        for info in chunks.values_mut() {
+            if let Some(size) = stored.remove(chunk_id(&info.hash)) {
+                info.compressed_size = size;
+            }
+        }
+        for (id, size) in stored {
+            chunks.insert(id.clone(), ChunkInfo {
+                hash: id,
+                compressed_size: size,
+                uncompressed_size: 0,
+                ref_count: 0,
+            });
         }
 
-        Ok(content)
+        Ok(chunks.into_values())
     }
 
     /// Check if this CXP file has embeddings
@@ -1083,35 +4450,99 @@ impl CxpReader {
 
         tracing::info!("Loading embeddings from CXP file...");
 
-        let file = File::open(&self.archive_path)?;
-        let mut archive = ZipArchive::new(file)?;
+        let mut archive = self.archive()?;
 
         // Load binary embeddings
         let binary_embeddings = {
             let mut file = archive.by_name("embeddings/binary.bin")?;
             let mut data = Vec::new();
             file.read_to_end(&mut data)?;
-            deserialize_binary_embeddings(&data)?
+            crate::deserialize_binary_embeddings(&data)?
         };
 
-        // Load int8 embeddings
-        let int8_embeddings = {
-            let mut file = archive.by_name("embeddings/int8.bin")?;
-            let mut data = Vec::new();
-            file.read_to_end(&mut data)?;
-            deserialize_int8_embeddings(&data)?
+        // Load int8 embeddings (absent when the archive stores binary only)
+        let int8_embeddings = match archive.by_name("embeddings/int8.bin") {
+            Ok(mut file) => {
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+                crate::deserialize_int8_embeddings(&data)?
+            }
+            Err(_) => Vec::new(),
+        };
+
+        // Load float embeddings (f16/f32 archives)
+        let precision = self.manifest.embedding_precision;
+        let float_embeddings = match precision.path().filter(|_| precision.is_float()).map(|path| archive.by_name(path)) {
+            Some(Ok(mut file)) => {
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+                crate::deserialize_float_embeddings(&data, precision)?
+            }
+            _ => Vec::new(),
         };
 
         let dimensions = self.manifest.embedding_dim
             .ok_or_else(|| CxpError::Embedding("No embedding dimension in manifest".to_string()))?;
 
-        tracing::info!("Loaded {} embeddings", binary_embeddings.len());
+        tracing::info!("Loaded {} embeddings", binary_embeddings.len());
+
+        Ok(crate::EmbeddingStore::new(
+            binary_embeddings,
+            int8_embeddings,
+            dimensions,
+        )
+        .with_float(float_embeddings))
+    }
+
+    /// Which vector indexes the archive contains
+    pub fn available_indexes(&self) -> Result<AvailableIndexes> {
+        if !self.has_embeddings() {
+            return Ok(AvailableIndexes::default());
+        }
+        let archive = self.archive()?;
+        Ok(AvailableIndexes {
+            text: archive.index_for_name(self.manifest.index_backend.path()).is_some(),
+            unified: archive.index_for_name("embeddings/unified.index").is_some(),
+        })
+    }
+
+    /// Load whichever vector indexes the archive contains
+    ///
+    /// Loads the text index, the UnifiedIndex or both, so callers do not need
+    /// to know how the archive was built. `search_semantic()` and
+    /// `search_multimodal()` fall back to the other index when theirs is
+    /// missing. Indexes this build has no feature for are skipped; returns the
+    /// indexes that were loaded.
+    #[cfg(feature = "search")]
+    pub fn load_any_index(&self) -> Result<AvailableIndexes> {
+        let available = self.available_indexes()?;
+        if available.is_empty() {
+            return Err(CxpError::Embedding(
+                "This CXP file does not contain a vector index".to_string()
+            ));
+        }
+
+        let loaded = AvailableIndexes {
+            text: available.text && cfg!(feature = "embeddings"),
+            unified: available.unified && cfg!(feature = "multimodal"),
+        };
+        if loaded.is_empty() {
+            return Err(CxpError::Embedding(format!(
+                "No enabled feature can load this archive's index ({:?})",
+                available
+            )));
+        }
 
-        Ok(crate::EmbeddingStore::new(
-            binary_embeddings,
-            int8_embeddings,
-            dimensions,
-        ))
+        #[cfg(feature = "embeddings")]
+        if loaded.text {
+            self.load_embeddings()?;
+        }
+        #[cfg(feature = "multimodal")]
+        if loaded.unified {
+            self.load_unified_index()?;
+        }
+        tracing::info!("Loaded indexes: {:?}", loaded);
+        Ok(loaded)
     }
 
     /// Load embeddings and search index into memory
@@ -1119,74 +4550,104 @@ impl CxpReader {
     /// This must be called before using semantic search functions.
     /// The embeddings and index are cached for subsequent searches.
     #[cfg(all(feature = "embeddings", feature = "search"))]
-    pub fn load_embeddings(&mut self) -> Result<()> {
+    pub fn load_embeddings(&self) -> Result<()> {
+        self.load_embeddings_with(LoadOptions::default())
+    }
+
+    /// Load embeddings and search index, keeping only as much in RAM as `options` allow
+    ///
+    /// Binary vectors can be memory-mapped from the archive file and int8
+    /// (or float) rescoring vectors read from disk for the candidates of each
+    /// search, so archives larger than RAM stay searchable. Search results
+    /// are the same as with `load_embeddings()`.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn load_embeddings_with(&self, options: LoadOptions) -> Result<()> {
+        self.cancellation.check("load embeddings")?;
         if !self.has_embeddings() {
             return Err(CxpError::Embedding(
                 "This CXP file does not contain embeddings".to_string()
             ));
         }
 
-        if self.search_index.is_some() {
+        if self.text_search.get().is_some() {
             return Ok(());  // Already loaded
         }
 
         tracing::info!("Loading embeddings from CXP file...");
 
-        let file = File::open(&self.archive_path)?;
-        let mut archive = ZipArchive::new(file)?;
+        let mut archive = self.archive()?;
 
-        // Load binary embeddings
-        let binary_embeddings = {
-            let mut file = archive.by_name("embeddings/binary.bin")?;
-            let mut data = Vec::new();
-            file.read_to_end(&mut data)?;
-            deserialize_binary_embeddings(&data)?
-        };
+        let rows = EmbeddingRows::load(&self.backend, &mut archive, self.manifest.embedding_precision, options)?;
+        tracing::info!(
+            "Loaded {} embeddings ({} bytes in memory{}{})",
+            rows.len(),
+            rows.memory_bytes(),
+            if rows.is_mapped() { ", binary mapped" } else { "" },
+            if rows.is_streamed() { ", rescoring on demand" } else { "" }
+        );
 
-        // Load int8 embeddings
-        let int8_embeddings = {
-            let mut file = archive.by_name("embeddings/int8.bin")?;
-            let mut data = Vec::new();
-            file.read_to_end(&mut data)?;
-            deserialize_int8_embeddings(&data)?
+        // Load embedding ID -> chunk hash mapping (absent in older archives)
+        let embedding_chunks: Option<Vec<String>> = match archive.by_name("embeddings/chunk_ids.msgpack") {
+            Ok(mut file) => {
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+                Some(rmp_serde::from_slice(&data)?)
+            }
+            Err(_) => None,
         };
 
-        tracing::info!("Loaded {} embeddings", binary_embeddings.len());
-
-        self.embeddings = Some(QuantizedEmbeddings {
-            binary: binary_embeddings,
-            int8: int8_embeddings,
-        });
+        // Cross-check rows, dimensions and the mapping against the chunk directory
+        let dimensions = self.manifest.embedding_dim
+            .ok_or_else(|| CxpError::Embedding("No embedding dimension in manifest".to_string()))?;
+        rows.validate(embedding_chunks.as_deref(), dimensions, self.manifest.int8_embeddings)?;
+        match embedding_chunks {
+            Some(ref chunk_ids) => validate_chunk_mapping(chunk_ids, |hash| self.stored_chunks.contains(&archive, hash))?,
+            None => tracing::warn!("No embedding chunk mapping; rows are assumed to follow chunk order"),
+        }
 
-        // Load HNSW index
-        let file = File::open(&self.archive_path)?;
-        let mut archive = ZipArchive::new(file)?;
+        // Load near-duplicate aliases (absent unless built with embedding dedup)
+        let aliases: BTreeMap<String, String> = match archive.by_name(EMBEDDING_ALIASES_PATH) {
+            Ok(mut file) => {
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+                rmp_serde::from_slice(&data)?
+            }
+            Err(_) => BTreeMap::new(),
+        };
+        validate_embedding_aliases(&aliases, embedding_chunks.as_deref().unwrap_or_default())?;
+        let mut embedding_aliases: HashMap<String, Vec<String>> = HashMap::new();
+        for (alias, representative) in aliases {
+            embedding_aliases.entry(representative).or_default().push(alias);
+        }
 
-        let mut index_file = archive.by_name("embeddings/index.hnsw")?;
+        // Load the search index
+        let backend = self.manifest.index_backend;
+        let mut index_file = archive.by_name(backend.path())?;
         let mut index_data = Vec::new();
         index_file.read_to_end(&mut index_data)?;
 
-        // Save to temp file (USearch limitation)
-        let temp_dir = std::env::temp_dir();
-        let temp_index_path = temp_dir.join(format!("cxp_index_{}.hnsw", uuid::Uuid::new_v4()));
-
-        let mut temp_file = File::create(&temp_index_path)?;
-        temp_file.write_all(&index_data)?;
-        drop(temp_file);
-
-        // Load index
-        let dimensions = self.manifest.embedding_dim
-            .ok_or_else(|| CxpError::Embedding("No embedding dimension in manifest".to_string()))?;
-
-        let config = HnswConfig::binary(dimensions);
-        let index = HnswIndex::load(&temp_index_path, config)?;
+        let params = self.manifest.index_params.unwrap_or_default();
+        let index = VectorIndex::from_bytes(&index_data, dimensions, backend, &params)?;
 
-        // Clean up temp file
-        std::fs::remove_file(&temp_index_path)?;
+        tracing::info!("Loaded {} index with {} vectors", backend, index.len());
 
-        tracing::info!("Loaded HNSW index with {} vectors", index.len());
+        // Tombstoned rows (see `Manifest::embedding_tombstones`) are not indexed
+        let indexed_rows = rows.len().saturating_sub(self.manifest.embedding_tombstones);
+        if index.len() != indexed_rows {
+            return Err(CxpError::Index(format!(
+                "Search index has {} vectors for {} embeddings",
+                index.len(),
+                indexed_rows
+            )));
+        }
 
-        self.search_index = Some(index);
+        // A concurrent load may have finished first; both loaded the same data
+        let _ = self.text_search.set(TextSearch {
+            index,
+            rows,
+            chunks: embedding_chunks,
+            aliases: embedding_aliases,
+        });
 
         Ok(())
     }
@@ -1196,21 +4657,21 @@ impl CxpReader {
     /// This must be called before using multimodal search functions.
     /// The index is cached for subsequent searches.
     #[cfg(all(feature = "multimodal", feature = "search"))]
-    pub fn load_unified_index(&mut self) -> Result<()> {
+    pub fn load_unified_index(&self) -> Result<()> {
+        self.cancellation.check("load unified index")?;
         if !self.has_embeddings() {
             return Err(CxpError::Embedding(
                 "This CXP file does not contain embeddings".to_string()
             ));
         }
 
-        if self.unified_index.is_some() {
+        if self.unified_index.get().is_some() {
             return Ok(());  // Already loaded
         }
 
         tracing::info!("Loading UnifiedIndex from CXP file...");
 
-        let file = File::open(&self.archive_path)?;
-        let mut archive = ZipArchive::new(file)?;
+        let mut archive = self.archive()?;
 
         // Check if unified index exists (multimodal)
         let has_unified = archive.by_name("embeddings/unified.index").is_ok();
@@ -1221,52 +4682,29 @@ impl CxpReader {
             ));
         }
 
-        // Re-open archive for reading
-        let file = File::open(&self.archive_path)?;
-        let mut archive = ZipArchive::new(file)?;
-
         // Load index file
         let mut index_file = archive.by_name("embeddings/unified.index")?;
         let mut index_data = Vec::new();
         index_file.read_to_end(&mut index_data)?;
+        drop(index_file);
 
         // Load metadata file
-        let file = File::open(&self.archive_path)?;
-        let mut archive = ZipArchive::new(file)?;
-
         let mut meta_file = archive.by_name("embeddings/unified.meta")?;
         let mut meta_data = Vec::new();
         meta_file.read_to_end(&mut meta_data)?;
 
-        // Save to temp files (USearch limitation)
-        let temp_dir = std::env::temp_dir();
-        let temp_base_path = temp_dir.join(format!("cxp_unified_{}", uuid::Uuid::new_v4()));
-        let temp_index_path = temp_base_path.with_extension("index");
-        let temp_meta_path = temp_base_path.with_extension("meta");
-
-        let mut temp_index_file = File::create(&temp_index_path)?;
-        temp_index_file.write_all(&index_data)?;
-        drop(temp_index_file);
-
-        let mut temp_meta_file = File::create(&temp_meta_path)?;
-        temp_meta_file.write_all(&meta_data)?;
-        drop(temp_meta_file);
-
         // Load index
         let _dimensions = self.manifest.embedding_dim
             .ok_or_else(|| CxpError::Embedding("No embedding dimension in manifest".to_string()))?;
 
-        let config = HnswConfig::multimodal_float32();
-        let unified_index = UnifiedIndex::load(&temp_base_path, config)?;
-
-        // Clean up temp files
-        std::fs::remove_file(&temp_index_path)?;
-        std::fs::remove_file(&temp_meta_path)?;
+        let config = HnswConfig::multimodal_float32().with_params(&self.manifest.index_params.unwrap_or_default());
+        let unified_index = UnifiedIndex::from_bytes(&index_data, &meta_data, config)?;
 
         tracing::info!("Loaded UnifiedIndex with {} vectors ({} text, {} images)",
             unified_index.len(), unified_index.text_count(), unified_index.image_count());
 
-        self.unified_index = Some(unified_index);
+        // A concurrent load may have finished first; both loaded the same data
+        let _ = self.unified_index.set(unified_index);
 
         Ok(())
     }
@@ -1288,38 +4726,40 @@ impl CxpReader {
         query_embedding: &[f32],
         top_k: usize,
     ) -> Result<Vec<SearchResult>> {
-        let index = self.search_index.as_ref()
-            .ok_or_else(|| CxpError::Search(
-                "Embeddings not loaded. Call load_embeddings() first.".to_string()
-            ))?;
+        self.cancellation.check("search")?;
 
-        let embeddings = self.embeddings.as_ref()
-            .ok_or_else(|| CxpError::Search(
-                "Embeddings not loaded. Call load_embeddings() first.".to_string()
-            ))?;
+        // Archives with only a UnifiedIndex are searched through it
+        #[cfg(feature = "multimodal")]
+        if let (None, Some(unified)) = (self.text_search.get(), self.unified_index.get()) {
+            return Ok(unified
+                .search(query_embedding, top_k)?
+                .into_iter()
+                .map(|r| SearchResult {
+                    id: r.id,
+                    distance: r.similarity(),
+                })
+                .collect());
+        }
+
+        let TextSearch { index, rows: embeddings, .. } = self.loaded_text_search()?;
 
         // Convert query to binary for fast initial search
-        let query_binary = BinaryEmbedding::from_float(query_embedding);
+        let query = EmbeddingQuery::new(query_embedding);
 
-        // Search with HNSW (binary)
-        let candidates = index.search_binary_embedding(&query_binary, top_k * 2)?;
+        // Search the index (binary)
+        let candidates = index.search_binary_embedding(query.binary(), top_k * 2)?;
 
-        // Rescore with Int8 for better accuracy
-        let query_int8 = Int8Embedding::from_float(query_embedding);
+        // Rescore with float or Int8 vectors for better accuracy (binary
+        // similarity for rows without either)
+        let ids: Vec<usize> = candidates.iter().map(|result| result.id as usize).collect();
+        let scores = embeddings.scores(&ids, &query)?;
 
         let mut rescored: Vec<_> = candidates
             .iter()
-            .map(|result| {
-                let chunk_id = result.id as usize;
-                let score = if chunk_id < embeddings.int8.len() {
-                    embeddings.int8[chunk_id].dot_product(&query_int8)
-                } else {
-                    0.0
-                };
-                SearchResult {
-                    id: result.id,
-                    distance: -score,  // Negate for sorting (higher is better)
-                }
+            .zip(scores)
+            .map(|(result, score)| SearchResult {
+                id: result.id,
+                distance: -score,  // Negate for sorting (higher is better)
             })
             .collect();
 
@@ -1337,6 +4777,278 @@ impl CxpReader {
             .collect())
     }
 
+    /// Exact semantic search: score every embedding against the query
+    ///
+    /// Scans all rows linearly with the same scores `search_semantic()`
+    /// rescores with (float, then int8, then binary similarity), so the
+    /// result is what the HNSW search would return if it missed nothing.
+    /// Slower than `search_semantic()` on large archives; use it for small
+    /// archives or to measure recall. You must call `load_embeddings()` first.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn search_exact(&self, query_embedding: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        self.cancellation.check("search")?;
+
+        let TextSearch { rows: embeddings, chunks, .. } = self.loaded_text_search()?;
+        if let Some(dimensions) = self.manifest.embedding_dim.filter(|&d| d != query_embedding.len()) {
+            return Err(CxpError::IndexDimensionMismatch {
+                expected: dimensions,
+                got: query_embedding.len(),
+            });
+        }
+
+        let query = EmbeddingQuery::new(query_embedding);
+        let chunk_ids = chunks.as_deref().unwrap_or_default();
+        let ids: Vec<usize> = (0..embeddings.len())
+            .filter(|&id| chunk_ids.get(id).is_none_or(|hash| hash.as_str() != EMBEDDING_TOMBSTONE))
+            .collect();
+        let scores = embeddings.scores(&ids, &query)?;
+        let mut scored: Vec<SearchResult> = ids
+            .into_iter()
+            .zip(scores)
+            .map(|(id, distance)| SearchResult { id: id as u64, distance })
+            .collect();
+
+        // Highest score first, ties by ID so results are deterministic
+        let by_score = |a: &SearchResult, b: &SearchResult| {
+            b.distance.partial_cmp(&a.distance).unwrap_or(std::cmp::Ordering::Equal).then(a.id.cmp(&b.id))
+        };
+        if top_k < scored.len() {
+            scored.select_nth_unstable_by(top_k, by_score);
+            scored.truncate(top_k);
+        }
+        scored.sort_by(by_score);
+        Ok(scored)
+    }
+
+    /// Load the embedding model used to encode text queries
+    ///
+    /// Required by `search_multi()`. The model should be the one the archive
+    /// was built with (see `manifest.embedding_model`).
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn load_query_model<P: AsRef<Path>>(
+        &self,
+        model_path: P,
+        model: EmbeddingModel,
+    ) -> Result<()> {
+        tracing::info!("Loading query model: {}", model.name());
+        let engine = EmbeddingEngine::load(model_path, model)?;
+        *self.query_engine.lock() = Some(engine);
+        Ok(())
+    }
+
+    /// Text search state, or an error if `load_embeddings()` was not called
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    fn loaded_text_search(&self) -> Result<&TextSearch> {
+        self.text_search.get()
+            .ok_or_else(|| CxpError::Search(
+                "Embeddings not loaded. Call load_embeddings() first.".to_string()
+            ))
+    }
+
+    /// Get the hash of the chunk behind an embedding ID
+    ///
+    /// Returns `None` for archives built before the mapping was stored,
+    /// for rows tombstoned by an incremental update, or if
+    /// `load_embeddings()` has not been called.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn embedding_chunk_hash(&self, id: u64) -> Option<&str> {
+        self.text_search
+            .get()
+            .and_then(|search| search.chunks.as_ref())
+            .and_then(|chunks| chunks.get(id as usize))
+            .map(|hash| hash.as_str())
+            .filter(|&hash| hash != EMBEDDING_TOMBSTONE)
+    }
+
+    /// Get the hashes of all chunks behind an embedding ID (embedded chunk first)
+    ///
+    /// Archives built with embedding dedup share one embedding between
+    /// near-duplicate chunks; otherwise this is just `embedding_chunk_hash()`.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn embedding_chunk_hashes(&self, id: u64) -> Vec<&str> {
+        let Some(hash) = self.embedding_chunk_hash(id) else {
+            return Vec::new();
+        };
+        let aliases = self.text_search
+            .get()
+            .and_then(|search| search.aliases.get(hash))
+            .into_iter()
+            .flatten();
+        std::iter::once(hash).chain(aliases.map(String::as_str)).collect()
+    }
+
+    /// Dequantized embedding of every embedded chunk, by chunk hash
+    ///
+    /// Float rows are returned as stored and int8 rows are scaled back to
+    /// floats; rows stored only in binary form become vectors of +1/-1. Near-duplicate chunks get the vector of the
+    /// chunk standing in for them. You must call `load_embeddings()` first.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn chunk_embedding_vectors(&self) -> Result<HashMap<String, Vec<f32>>> {
+        let embeddings = &self.loaded_text_search()?.rows;
+
+        let mut vectors = HashMap::new();
+        embeddings.for_each_vector(|id, vector| {
+            for hash in self.embedding_chunk_hashes(id as u64) {
+                vectors.insert(hash.to_string(), vector.clone());
+            }
+        })?;
+        Ok(vectors)
+    }
+
+    /// Dequantized embedding of every live row, by embedding ID
+    ///
+    /// Rows tombstoned by an incremental update are left out. You must call
+    /// `load_embeddings()` first.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub(crate) fn embedding_vectors(&self) -> Result<Vec<(u64, Vec<f32>)>> {
+        let search = self.loaded_text_search()?;
+        let tombstoned = |id: usize| {
+            search.chunks.as_ref().and_then(|chunks| chunks.get(id)).is_some_and(|hash| hash == EMBEDDING_TOMBSTONE)
+        };
+
+        let mut vectors = Vec::new();
+        search.rows.for_each_vector(|id, vector| {
+            if !tombstoned(id) {
+                vectors.push((id as u64, vector));
+            }
+        })?;
+        Ok(vectors)
+    }
+
+    /// Search with several query variants and fuse the results
+    ///
+    /// Embeds every query with the model loaded via `load_query_model()`,
+    /// runs `search_semantic()` for each, and merges the candidate lists with
+    /// Reciprocal Rank Fusion. You must call `load_embeddings()` first.
+    ///
+    /// # Arguments
+    /// * `queries` - Query variants (paraphrases, expansions)
+    /// * `top_k` - Number of results to return
+    /// * `fusion` - RRF constant and deduplication granularity
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn search_multi(
+        &self,
+        queries: &[&str],
+        top_k: usize,
+        fusion: Fusion,
+    ) -> Result<Vec<FusedResult>> {
+        // Only encoding needs the model; searches run without the lock
+        let query_embeddings = {
+            let mut engine = self.query_engine.lock();
+            let engine = engine.as_mut()
+                .ok_or_else(|| CxpError::Search(
+                    "Query model not loaded. Call load_query_model() first.".to_string()
+                ))?;
+
+            self.cancellation.check("search")?;
+            engine.embed_queries(queries)?
+        };
+
+        self.search_multi_embeddings(&query_embeddings, top_k, fusion)
+    }
+
+    /// Expand a query and search all of its variants
+    ///
+    /// Runs `expansion` (synonyms, HyDE, ...) on the query before embedding
+    /// and fuses the variants like `search_multi()`. Requires
+    /// `load_query_model()` and `load_embeddings()`.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn search_expanded(
+        &self,
+        query: &str,
+        expansion: &dyn crate::expansion::QueryExpansion,
+        top_k: usize,
+        fusion: Fusion,
+    ) -> Result<Vec<FusedResult>> {
+        let variants = expansion.expand(query)?;
+        tracing::debug!("Expanded query into {} variants", variants.len());
+        let queries: Vec<&str> = variants.iter().map(String::as_str).collect();
+        self.search_multi(&queries, top_k, fusion)
+    }
+
+    /// Fuse the results of several pre-computed query embeddings
+    ///
+    /// Same as `search_multi()` for callers that embed queries themselves.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn search_multi_embeddings(
+        &self,
+        query_embeddings: &[Vec<f32>],
+        top_k: usize,
+        fusion: Fusion,
+    ) -> Result<Vec<FusedResult>> {
+        // Over-fetch per query so fusion and deduplication have room to work
+        let per_query = top_k.saturating_mul(2);
+        let deadline = Deadline::after(self.search_options.timeout);
+
+        let mut rankings = Vec::with_capacity(query_embeddings.len());
+        for query in query_embeddings {
+            // After a timeout, fuse the variants searched so far
+            if deadline.expired() {
+                if rankings.is_empty() {
+                    return Err(CxpError::Timeout("search".to_string()));
+                }
+                tracing::warn!(
+                    "Search timed out after {} of {} query variants",
+                    rankings.len(),
+                    query_embeddings.len()
+                );
+                break;
+            }
+            let results: Vec<SearchResult> = self.search_semantic(query, per_query)?;
+            rankings.push(results.into_iter().map(|r| r.id).collect());
+        }
+
+        let mut fused = reciprocal_rank_fusion(&rankings, fusion.rrf_k);
+
+        // Resolve chunk hashes and the (alphabetically first) file containing each chunk
+        let chunk_files = self.chunk_files()?;
+        for result in &mut fused {
+            let hashes = self.embedding_chunk_hashes(result.id);
+            if let Some((hash, aliases)) = hashes.split_first() {
+                result.file_path = hashes.iter().filter_map(|h| chunk_files.get(h)).min().map(|f| f.to_string());
+                result.chunk_hash = Some(hash.to_string());
+                result.aliases = aliases.iter().map(|h| h.to_string()).collect();
+            }
+        }
+
+        if fusion.dedup == DedupBy::File {
+            let mut seen_files = std::collections::HashSet::new();
+            fused.retain(|r| match &r.file_path {
+                Some(path) => seen_files.insert(path.clone()),
+                None => true,
+            });
+        }
+
+        fused.truncate(top_k);
+
+        if let Some(ref usage) = self.usage {
+            let paths: Vec<&str> = fused.iter().filter_map(|r| r.file_path.as_deref()).collect();
+            usage.record_query(&paths);
+        }
+
+        Ok(fused)
+    }
+
+    /// Alphabetically first file containing each chunk
+    ///
+    /// In lazy mode this loads every shard.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    fn chunk_files(&self) -> Result<HashMap<&str, &str>> {
+        let mut chunk_files: HashMap<&str, &str> = HashMap::new();
+        for path in self.file_paths() {
+            let Some(entry) = self.file_entry(path)? else {
+                continue;
+            };
+            for chunk in &entry.chunks {
+                let file = chunk_files.entry(chunk.hash.as_str()).or_insert(path);
+                if path < *file {
+                    *file = path;
+                }
+            }
+        }
+        Ok(chunk_files)
+    }
+
     /// Perform multimodal semantic search with type filtering
     ///
     /// Searches across both text and images using the UnifiedIndex.
@@ -1356,7 +5068,15 @@ impl CxpReader {
         top_k: usize,
         result_type: &str,
     ) -> Result<Vec<crate::SearchResultWithType>> {
-        let index = self.unified_index.as_ref()
+        self.cancellation.check("search")?;
+
+        // Text-only archives are searched through the text index
+        #[cfg(feature = "embeddings")]
+        if self.unified_index.get().is_none() && self.text_search.get().is_some() {
+            return self.search_text_index_typed(query_embedding, top_k, result_type);
+        }
+
+        let index = self.unified_index.get()
             .ok_or_else(|| CxpError::Search(
                 "UnifiedIndex not loaded. Call load_unified_index() first.".to_string()
             ))?;
@@ -1371,6 +5091,56 @@ impl CxpReader {
         Ok(results)
     }
 
+    /// Search with an image file as the query
+    ///
+    /// Loads the UnifiedIndex if needed, embeds the image with `engine` and
+    /// runs `search_multimodal()`. Use `result_type` "image" for
+    /// image-to-image search.
+    #[cfg(all(feature = "multimodal", feature = "search"))]
+    pub fn search_with_image<P: AsRef<Path>>(
+        &self,
+        image_path: P,
+        engine: &mut MultimodalEngine,
+        top_k: usize,
+        result_type: &str,
+    ) -> Result<Vec<crate::SearchResultWithType>> {
+        self.load_unified_index()?;
+        let query_embedding = engine.embed_image(image_path.as_ref())?;
+        self.search_multimodal(&query_embedding, top_k, result_type)
+    }
+
+    /// `search_multimodal()` over the text index (no image entries)
+    #[cfg(all(feature = "embeddings", feature = "multimodal", feature = "search"))]
+    fn search_text_index_typed(
+        &self,
+        query_embedding: &[f32],
+        top_k: usize,
+        result_type: &str,
+    ) -> Result<Vec<crate::SearchResultWithType>> {
+        if result_type.eq_ignore_ascii_case("image") {
+            return Ok(Vec::new());
+        }
+
+        let results = self.search_semantic(query_embedding, top_k)?;
+        let chunk_files = self.chunk_files()?;
+        Ok(results
+            .into_iter()
+            .map(|r| {
+                let file_path = self
+                    .embedding_chunk_hash(r.id)
+                    .and_then(|hash| chunk_files.get(hash).copied())
+                    .unwrap_or_default()
+                    .to_string();
+                crate::SearchResultWithType {
+                    id: r.id,
+                    // search_semantic() scores are similarities
+                    distance: 1.0 - r.distance,
+                    entry_type: crate::EntryType::Text { chunk_id: r.id, file_path },
+                }
+            })
+            .collect())
+    }
+
     /// Search for images using a text query
     ///
     /// Convenience method for text-to-image search.
@@ -1381,7 +5151,8 @@ impl CxpReader {
         query_embedding: &[f32],
         top_k: usize,
     ) -> Result<Vec<crate::SearchResultWithType>> {
-        let index = self.unified_index.as_ref()
+        self.cancellation.check("search")?;
+        let index = self.unified_index.get()
             .ok_or_else(|| CxpError::Search(
                 "UnifiedIndex not loaded. Call load_unified_index() first.".to_string()
             ))?;
@@ -1399,7 +5170,8 @@ impl CxpReader {
         query_embedding: &[f32],
         top_k: usize,
     ) -> Result<Vec<crate::SearchResultWithType>> {
-        let index = self.unified_index.as_ref()
+        self.cancellation.check("search")?;
+        let index = self.unified_index.get()
             .ok_or_else(|| CxpError::Search(
                 "UnifiedIndex not loaded. Call load_unified_index() first.".to_string()
             ))?;
@@ -1412,24 +5184,19 @@ impl CxpReader {
     /// This is useful for retrieving the actual content of chunks found by semantic search.
     #[cfg(all(feature = "embeddings", feature = "search"))]
     pub fn get_chunk_text(&self, chunk_id: u64) -> Result<String> {
-        let file = File::open(&self.archive_path)?;
-        let mut archive = ZipArchive::new(file)?;
-
-        // We need to find the chunk by iterating through the chunk store
-        // For now, we'll use the chunk hash format
-        let chunk_name = format!("chunks/{:016x}.zst", chunk_id);
-
-        let mut chunk_file = archive.by_name(&chunk_name)
-            .or_else(|_| {
-                // Try alternative naming if the first format doesn't work
-                // This is a fallback - in practice you'd maintain a chunk ID -> hash mapping
-                Err(CxpError::FileNotFound(format!("Chunk {} not found", chunk_id)))
-            })?;
+        let mut archive = self.archive()?;
+
+        // Resolve the embedding ID to its chunk hash; older archives without
+        // the mapping fall back to the legacy hex naming
+        let hash = match self.embedding_chunk_hash(chunk_id) {
+            Some(hash) => hash.to_string(),
+            None => format!("{:016x}", chunk_id),
+        };
 
-        let mut compressed = Vec::new();
-        chunk_file.read_to_end(&mut compressed)?;
+        let compressed = self.stored_chunks.read(&mut archive, &hash)
+            .map_err(|_| CxpError::FileNotFound(format!("Chunk {} not found", chunk_id)))?;
 
-        let decompressed = decompress(&compressed)?;
+        let decompressed = self.codec.decompress(&compressed)?;
 
         String::from_utf8(decompressed)
             .map_err(|e| CxpError::Serialization(format!("Invalid UTF-8 in chunk: {}", e)))
@@ -1465,6 +5232,157 @@ impl CxpReader {
         self.extension_manager.read_data(namespace, key)
     }
 
+    /// Build telemetry recorded by the builder (None for archives built without it)
+    pub fn build_info(&self) -> Result<Option<BuildInfo>> {
+        match self.extension_manager.read_data(BUILD_INFO_NAMESPACE, BUILD_INFO_KEY) {
+            Ok(data) => BuildInfo::from_msgpack(&data).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// File and directory summaries stored with `CxpBuilder::with_summarizer()` (None if absent)
+    pub fn summaries(&self) -> Result<Option<Summaries>> {
+        match self.extension_manager.read_data(SUMMARIES_NAMESPACE, SUMMARIES_KEY) {
+            Ok(data) => Summaries::from_msgpack(&data).map(Some),
+            Err(CxpError::FileNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Files and directories whose summaries match the words of `query`
+    ///
+    /// High-level entry points to read before (or instead of) chunk search.
+    /// Archives without summaries return no hits.
+    pub fn search_summaries(&self, query: &str, top_k: usize) -> Result<Vec<crate::SummaryHit>> {
+        Ok(self.summaries()?.map(|s| s.search(query, top_k)).unwrap_or_default())
+    }
+
+    /// Symbol index stored with `CxpBuilder::with_symbols()` (None if absent)
+    ///
+    /// Read from the archive once and kept for later lookups.
+    pub fn symbols(&self) -> Result<Option<&SymbolIndex>> {
+        if let Some(symbols) = self.symbols.get() {
+            return Ok(symbols.as_ref());
+        }
+        let symbols = SymbolIndex::read_from_archive(&mut *self.archive()?)?;
+        Ok(self.symbols.get_or_init(|| symbols).as_ref())
+    }
+
+    /// Where `name` is defined (empty if the archive has no symbol index)
+    pub fn find_symbol(&self, name: &str) -> Result<&[Symbol]> {
+        Ok(self.symbols()?.map(|symbols| symbols.find(name)).unwrap_or_default())
+    }
+
+    /// Knowledge graph stored with `CxpBuilder::with_graph_plugin()` (None if absent)
+    ///
+    /// Expand search hits with `graph.around_files(&[hit_path], 1).files()`.
+    pub fn graph(&self) -> Result<Option<KnowledgeGraph>> {
+        match self.extension_manager.read_data(GRAPH_NAMESPACE, GRAPH_KEY) {
+            Ok(data) => KnowledgeGraph::from_msgpack(&data).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Cluster map stored with `ClusterMap::write_to()` / `cxp cluster` (None if absent)
+    pub fn clusters(&self) -> Result<Option<ClusterMap>> {
+        match self.extension_manager.read_data(CLUSTERS_NAMESPACE, CLUSTERS_KEY) {
+            Ok(data) => ClusterMap::from_msgpack(&data).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Cluster the chunk embeddings by topic (see [`crate::clusters`])
+    ///
+    /// The result is not stored; pass it to `ClusterMap::write_to()` to keep
+    /// it with the archive. You must call `load_embeddings()` first.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn cluster(&self, options: &ClusterOptions) -> Result<ClusterMap> {
+        let vectors = self.chunk_embedding_vectors()?;
+        let mut sharded = FileMap::default();
+        let file_map = if self.shard_index.is_none() {
+            &self.file_map
+        } else {
+            for i in 0..self.shards.len() {
+                sharded.files.extend(self.load_shard(i)?.files.clone());
+            }
+            &sharded
+        };
+        ClusterMap::build(&vectors, file_map, options, |hash| {
+            self.read_chunk(hash).ok().map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        })
+    }
+
+    /// Semantic search restricted to the chunks of one cluster
+    ///
+    /// Scores every embedding of the cluster exactly, like `search_exact()`.
+    /// Requires a stored cluster map; you must call `load_embeddings()` first.
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    pub fn search_cluster(&self, query_embedding: &[f32], cluster: usize, top_k: usize) -> Result<Vec<SearchResult>> {
+        self.cancellation.check("search")?;
+
+        let clusters = self.clusters()?.ok_or_else(|| {
+            CxpError::Search("Archive has no clusters. Run `cxp cluster` first.".to_string())
+        })?;
+        if cluster >= clusters.clusters.len() {
+            return Err(CxpError::Search(format!(
+                "Cluster {} does not exist (archive has {})",
+                cluster,
+                clusters.clusters.len()
+            )));
+        }
+
+        let TextSearch { rows: embeddings, chunks, .. } = self.loaded_text_search()?;
+        let chunk_ids = chunks.as_deref().unwrap_or_default();
+        let ids: Vec<usize> = chunk_ids
+            .iter()
+            .enumerate()
+            .filter(|(_, hash)| clusters.assignments.get(hash.as_str()) == Some(&cluster))
+            .map(|(id, _)| id)
+            .collect();
+        let scores = embeddings.scores(&ids, &EmbeddingQuery::new(query_embedding))?;
+        let mut scored: Vec<SearchResult> = ids
+            .into_iter()
+            .zip(scores)
+            .map(|(id, distance)| SearchResult { id: id as u64, distance })
+            .collect();
+        scored.sort_by(|a, b| b.distance.total_cmp(&a.distance).then(a.id.cmp(&b.id)));
+        scored.truncate(top_k);
+        Ok(scored)
+    }
+
+    /// Commit history stored with `CxpBuilder::with_git_history()` (None if absent)
+    #[cfg(feature = "git")]
+    pub fn git_history(&self) -> Result<Option<GitHistory>> {
+        match self.extension_manager.read_data(GIT_HISTORY_NAMESPACE, GIT_HISTORY_KEY) {
+            Ok(data) => GitHistory::from_msgpack(&data).map(Some),
+            Err(CxpError::FileNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Search the commit messages of the git history
+    ///
+    /// Semantic when the history has message embeddings and a query model is
+    /// loaded (`load_query_model()`), by keywords otherwise. Archives without
+    /// history return no hits.
+    #[cfg(feature = "git")]
+    pub fn search_history(&self, query: &str, top_k: usize) -> Result<Vec<crate::git_history::CommitHit>> {
+        let Some(history) = self.git_history()? else {
+            return Ok(Vec::new());
+        };
+
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if let Some(engine) = self.query_engine.lock().as_mut() {
+            let same_model = history.embedding_model.as_deref() == Some(engine.model().name());
+            if history.has_embeddings() && same_model {
+                let query_embedding = engine.embed_query(query)?;
+                return history.search_semantic(&query_embedding, top_k);
+            }
+        }
+
+        Ok(history.search(query, top_k))
+    }
+
     /// Get extension manifest for a specific namespace
     ///
     /// Returns the extension's metadata including version and description
@@ -1484,10 +5402,72 @@ impl CxpReader {
     }
 }
 
+/// Check that the path of an in-memory file is relative and stays inside the archive
+fn validate_memory_path(path: &str) -> Result<()> {
+    let valid = !path.is_empty()
+        && !path.contains('\\')
+        && Path::new(path).components().all(|c| matches!(c, std::path::Component::Normal(_)));
+    if valid {
+        Ok(())
+    } else {
+        Err(CxpError::InvalidFormat(format!("Invalid file path '{}'", path)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    #[test]
+    fn test_embedding_invariants() {
+        let floats = vec![vec![0.5f32, -0.25, 0.75, -1.0]; 3];
+        let embeddings = QuantizedEmbeddings::from_floats(&floats);
+        let ids: Vec<String> = ["a", "b", "c"].iter().map(|c| c.repeat(64)).collect();
+
+        assert!(validate_embeddings(&embeddings, Some(&ids), 4, Int8Storage::All).is_ok());
+        assert!(validate_embeddings(&embeddings, Some(&ids[..2]), 4, Int8Storage::All).is_err());
+        assert!(validate_embeddings(&embeddings, None, 8, Int8Storage::All).is_err());
+
+        let mut short = embeddings.clone();
+        short.int8.pop();
+        assert!(validate_embeddings(&short, None, 4, Int8Storage::All).is_err());
+        assert!(validate_embeddings(&short, None, 4, Int8Storage::HotOnly).is_ok());
+        assert!(validate_embeddings(&short, None, 4, Int8Storage::None).is_err());
+        short.int8.clear();
+        assert!(validate_embeddings(&short, None, 4, Int8Storage::None).is_ok());
+
+        let mut float = QuantizedEmbeddings::with_precision(&floats, EmbeddingPrecision::F16);
+        assert_eq!(float.float, floats); // exactly representable in f16
+        float.float.truncate(1);
+        assert!(validate_embeddings(&float, None, 4, Int8Storage::All).is_ok());
+        float.float[0].pop();
+        assert!(validate_embeddings(&float, None, 4, Int8Storage::All).is_err());
+
+        assert!(validate_chunk_mapping(&ids, |_| true).is_ok());
+        assert!(validate_chunk_mapping(&ids, |hash| !hash.starts_with('b')).is_err());
+        let duplicated = vec![ids[0].clone(), ids[0].clone()];
+        assert!(validate_chunk_mapping(&duplicated, |_| true).is_err());
+
+        let d = "d".repeat(64);
+        let aliases: BTreeMap<String, String> = [(d.clone(), ids[0].clone())].into_iter().collect();
+        assert!(validate_embedding_aliases(&aliases, &ids).is_ok());
+        let embedded_alias: BTreeMap<String, String> = [(ids[1].clone(), ids[0].clone())].into_iter().collect();
+        assert!(validate_embedding_aliases(&embedded_alias, &ids).is_err());
+        let dangling: BTreeMap<String, String> = [(d, "e".repeat(64))].into_iter().collect();
+        assert!(validate_embedding_aliases(&dangling, &ids).is_err());
+
+        // Tombstoned rows are skipped by the checks and dropped by compaction
+        let mut tombstoned = ids.clone();
+        tombstoned[1] = EMBEDDING_TOMBSTONE.to_string();
+        assert!(validate_chunk_mapping(&tombstoned, |hash| !hash.is_empty()).is_ok());
+        let mut compacted = embeddings.clone();
+        compacted.int8.truncate(2);
+        assert_eq!(compact_embedding_rows(&mut compacted, &mut tombstoned), 1);
+        assert_eq!(tombstoned, vec![ids[0].clone(), ids[2].clone()]);
+        assert_eq!((compacted.binary.len(), compacted.int8.len()), (2, 1));
+    }
+
     #[test]
     fn test_file_entry_serialization() {
         let entry = FileEntry {
@@ -1496,11 +5476,83 @@ mod tests {
             size: 1000,
             chunks: vec![],
             is_image: false,
+            modified: None,
+            provenance: None,
+            keywords: Vec::new(),
         };
 
         let data = rmp_serde::to_vec(&entry).unwrap();
         let restored: FileEntry = rmp_serde::from_slice(&data).unwrap();
 
         assert_eq!(restored.path, entry.path);
+
+        // Readers skip fields appended after the ones they know
+        let appended = rmp_serde::to_vec(&(
+            "b.rs", "rs", 7u64, Vec::<ChunkRef>::new(), false, None::<String>, None::<Provenance>, Vec::<String>::new(), "newer",
+        )).unwrap();
+        let newer: FileEntry = rmp_serde::from_slice(&appended).unwrap();
+        assert_eq!((newer.path.as_str(), newer.size), ("b.rs", 7));
+
+        // Self-describing formats keep field names
+        let json = serde_json::to_string(&entry).unwrap();
+        let from_json: FileEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!((from_json.path, from_json.size), (entry.path.clone(), 1000));
+
+        // Positional entries of older archives are still read
+        let positional = rmp_serde::to_vec(&("a.txt", "txt", 5u64, Vec::<ChunkRef>::new(), false)).unwrap();
+        let legacy: FileEntry = rmp_serde::from_slice(&positional).unwrap();
+        assert_eq!((legacy.path.as_str(), legacy.size, legacy.modified), ("a.txt", 5, None));
+    }
+
+    #[test]
+    fn test_add_in_memory_files() {
+        let source = tempfile::TempDir::new().unwrap();
+        std::fs::write(source.path().join("notes.md"), "on disk\n").unwrap();
+        std::fs::write(source.path().join("main.rs"), "fn main() {}\n").unwrap();
+        let output = source.path().join("out.cxp");
+
+        let mut builder = CxpBuilder::new(source.path());
+        builder.scan().unwrap();
+        builder
+            .add_file_str("notes.md", "generated\n").unwrap()
+            .add_file_bytes("api/response.json", b"{\"ok\": true}".to_vec()).unwrap();
+        assert!(builder.add_file_str("../outside.txt", "").is_err());
+        assert!(builder.add_file_str("/abs.txt", "").is_err());
+        assert!(builder.add_file_str("", "").is_err());
+        builder.process().unwrap().build(&output).unwrap();
+
+        let reader = CxpReader::open(&output).unwrap();
+        let mut paths = reader.file_paths();
+        paths.sort();
+        assert_eq!(paths, vec!["api/response.json", "main.rs", "notes.md"]);
+        assert_eq!(reader.read_file("notes.md").unwrap(), b"generated\n");
+        assert_eq!(reader.manifest().stats.total_files, 3);
+    }
+
+    #[test]
+    fn test_add_same_path_twice() {
+        let source = tempfile::TempDir::new().unwrap();
+        let output = source.path().join("out.cxp");
+
+        let mut builder = CxpBuilder::new(source.path());
+        builder.scan().unwrap();
+        builder
+            .add_file_str("notes.md", "first draft\n").unwrap()
+            .add_file_str("other.md", "other\n").unwrap()
+            .add_file_str("notes.md", "final\n").unwrap();
+        builder.process().unwrap().build(&output).unwrap();
+
+        let reader = CxpReader::open(&output).unwrap();
+        let mut paths = reader.file_paths();
+        paths.sort();
+        assert_eq!(paths, vec!["notes.md", "other.md"]);
+        assert_eq!(reader.read_file("notes.md").unwrap(), b"final\n");
+        assert!(reader.file_entry("notes.md").unwrap().unwrap().modified.is_none());
+
+        let manifest = reader.manifest();
+        assert_eq!(manifest.stats.total_files, 2);
+        assert_eq!(manifest.stats.unique_chunks, 2);
+        assert_eq!(manifest.file_types["md"].count, 2);
+        assert_eq!(manifest.file_types["md"].total_bytes, 12);
     }
 }
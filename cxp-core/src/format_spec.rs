@@ -0,0 +1,603 @@
+//! Format Specification and Conformance Checks
+//!
+//! Describes the on-disk layout of a CXP archive as versioned data, and checks
+//! archives against it. Alternative readers (TypeScript, Python, ...) can run
+//! `cxp conformance` on their own output, or validate themselves against the
+//! golden fixtures in `cxp-core/tests/golden/`.
+//!
+//! A CXP file is a ZIP archive with every entry stored uncompressed (chunks
+//! are compressed individually, as zstd or LZ4 frames told apart by their
+//! magic number):
+//!
+//! ```text
+//! manifest.msgpack              # Manifest (required)
+//! file_map/index.msgpack        # ShardIndex over file_map/NNNNN.msgpack shards
+//! file_map.msgpack              # Unsharded FileMap (archives before sharding)
+//! chunks/pack-NNN.bin          # zstd- (or LZ4-) compressed chunks back to back
+//! chunks/packs.msgpack          # PackIndex: pack, offset and length of every chunk
+//! chunks/<sha256[..16]>.zst     # One compressed chunk per entry (archives before packs)
+//! compression/dict.zstd         # zstd dictionary the chunks were compressed with
+//! filters/{paths,chunks}.bloom  # Bloom filters over paths and chunk hashes
+//! embeddings/...                # Quantized embeddings and search indices
+//! global_index.msgpack          # GlobalIndex over the children's files
+//! children/<id>.cxp             # Embedded child archives
+//! extensions/<ns>/...           # Extension manifest and data
+//! annotations.msgpack           # User tags and notes per file
+//! snapshots/index.msgpack       # SnapshotIndex over snapshots/NNNNN.msgpack file maps
+//! toc.msgpack                   # Table of contents (written last)
+//! ```
+//!
+//! All `.msgpack` entries are MessagePack-encoded structs of this crate with
+//! fields in declaration order, except the manifest, which is a map keyed by
+//! field name so newer fields survive older readers. File entries only grow
+//! at the end; readers skip trailing fields they don't know.
+//!
+//! Archives with more than 65535 entries, entries of 4 GB or more, or entries
+//! starting past 4 GB use the Zip64 extensions; readers must support them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use zip::{CompressionMethod, ZipArchive};
+
+use crate::annotations::ANNOTATIONS_PATH;
+use crate::symbols::SYMBOLS_PATH;
+use crate::snapshots::{SnapshotIndex, SNAPSHOT_INDEX_PATH};
+use crate::bloom::{BloomFilter, CHUNK_FILTER_PATH, PATH_FILTER_PATH};
+use crate::chunker::compute_hash;
+use crate::compress::DICTIONARY_PATH;
+use crate::format::{read_chunk_codec, read_file_map, FileMap};
+use crate::manifest::Manifest;
+use crate::map_shards::SHARD_INDEX_PATH;
+use crate::packs::{PackIndex, PACK_INDEX_PATH};
+use crate::recursive::CxpStorage;
+use crate::toc::{Toc, TOC_PATH};
+use crate::{CxpError, Result};
+
+/// Version of the format described by this module
+pub const SPEC_VERSION: &str = crate::VERSION;
+
+/// One kind of entry in a CXP archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EntrySpec {
+    /// Entry path, or prefix when it ends with `/`
+    pub pattern: &'static str,
+    /// Whether every archive must contain it
+    pub required: bool,
+    /// Format version that introduced the entry
+    pub since: &'static str,
+    /// What the entry contains
+    pub description: &'static str,
+}
+
+impl EntrySpec {
+    /// Whether `path` is an entry of this kind
+    pub fn matches(&self, path: &str) -> bool {
+        if self.pattern.ends_with('/') {
+            path.starts_with(self.pattern)
+        } else {
+            path == self.pattern
+        }
+    }
+}
+
+/// Entries defined by the current format version
+pub const ENTRIES: &[EntrySpec] = &[
+    EntrySpec { pattern: "manifest.msgpack", required: true, since: "1.0.0", description: "Manifest: version, stats, file types, children" },
+    EntrySpec { pattern: SHARD_INDEX_PATH, required: false, since: "1.1.0", description: "ShardIndex listing the file map shards" },
+    EntrySpec { pattern: "file_map/", required: false, since: "1.1.0", description: "FileMap shards (file_map/NNNNN.msgpack)" },
+    EntrySpec { pattern: "file_map.msgpack", required: false, since: "1.0.0", description: "Unsharded FileMap (older archives)" },
+    EntrySpec { pattern: PACK_INDEX_PATH, required: false, since: "1.1.0", description: "PackIndex: pack, offset and length of every packed chunk" },
+    EntrySpec { pattern: "chunks/", required: false, since: "1.0.0", description: "One zstd chunk per entry named by the first 16 hex chars of its SHA-256, or chunk packs (chunks/pack-NNN.bin, since 1.1.0)" },
+    EntrySpec { pattern: DICTIONARY_PATH, required: false, since: "1.1.0", description: "Trained zstd dictionary shared by all chunks" },
+    EntrySpec { pattern: PATH_FILTER_PATH, required: false, since: "1.1.0", description: "Bloom filter over file paths" },
+    EntrySpec { pattern: CHUNK_FILTER_PATH, required: false, since: "1.1.0", description: "Bloom filter over chunk hashes" },
+    EntrySpec { pattern: "embeddings/", required: false, since: "1.0.0", description: "Quantized embeddings, chunk ids and search indices" },
+    EntrySpec { pattern: "global_index.msgpack", required: false, since: "1.0.0", description: "GlobalIndex over the children's files" },
+    EntrySpec { pattern: "children/", required: false, since: "1.0.0", description: "Embedded child archives (children/<id>.cxp)" },
+    EntrySpec { pattern: "extensions/", required: false, since: "1.0.0", description: "Extension manifests and data (extensions/<namespace>/<key>)" },
+    EntrySpec { pattern: ANNOTATIONS_PATH, required: false, since: "1.1.0", description: "User tags and notes per file path" },
+    EntrySpec { pattern: "snapshots/", required: false, since: "1.1.0", description: "SnapshotIndex and the FileMap of every snapshot (snapshots/NNNNN.msgpack)" },
+    EntrySpec { pattern: SYMBOLS_PATH, required: false, since: "1.1.0", description: "SymbolIndex: where functions and types are defined (file and line)" },
+    EntrySpec { pattern: TOC_PATH, required: false, since: "1.1.0", description: "Table of contents of all other entries, written last" },
+];
+
+/// Spec of the entry kind `path` belongs to
+pub fn entry_spec(path: &str) -> Option<&'static EntrySpec> {
+    // Exact paths take precedence over prefixes (file_map/index.msgpack vs file_map/)
+    ENTRIES.iter()
+        .find(|spec| !spec.pattern.ends_with('/') && spec.matches(path))
+        .or_else(|| ENTRIES.iter().find(|spec| spec.matches(path)))
+}
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    /// The archive satisfies the check
+    Pass,
+    /// Allowed, but readers may not understand it
+    Warn,
+    /// The archive violates the spec
+    Fail,
+}
+
+/// Result of one conformance check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceCheck {
+    /// Short check name
+    pub name: String,
+    /// Outcome
+    pub status: CheckStatus,
+    /// Explanation (counts on success, the problem otherwise)
+    pub detail: String,
+}
+
+/// Result of checking an archive against the spec
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceReport {
+    /// Spec version the archive was checked against
+    pub spec_version: String,
+    /// Format version recorded in the archive's manifest
+    pub archive_version: Option<String>,
+    /// Individual checks in the order they ran
+    pub checks: Vec<ConformanceCheck>,
+}
+
+impl ConformanceReport {
+    /// Whether no check failed
+    pub fn is_conformant(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Fail)
+    }
+
+    /// Checks that failed
+    pub fn failures(&self) -> Vec<&ConformanceCheck> {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Fail).collect()
+    }
+
+    fn push(&mut self, name: &str, status: CheckStatus, detail: impl Into<String>) {
+        self.checks.push(ConformanceCheck {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    fn pass(&mut self, name: &str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Pass, detail);
+    }
+
+    fn fail(&mut self, name: &str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Fail, detail);
+    }
+}
+
+/// Check a CXP file against the spec
+pub fn check_file<P: AsRef<Path>>(path: P) -> Result<ConformanceReport> {
+    check_archive(std::fs::File::open(path.as_ref())?)
+}
+
+/// Check an archive against the spec
+///
+/// Only an unreadable ZIP is an error; every spec violation is reported as a
+/// failed check.
+pub fn check_archive<R: Read + Seek>(reader: R) -> Result<ConformanceReport> {
+    let mut archive = ZipArchive::new(reader)
+        .map_err(|e| CxpError::InvalidFormat(format!("Not a ZIP archive: {}", e)))?;
+    let mut report = ConformanceReport {
+        spec_version: SPEC_VERSION.to_string(),
+        archive_version: None,
+        checks: Vec::new(),
+    };
+
+    // Entry layout
+    let mut sizes: BTreeMap<String, u64> = BTreeMap::new();
+    let mut compressed = Vec::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        if entry.compression() != CompressionMethod::Stored {
+            compressed.push(entry.name().to_string());
+        }
+        sizes.insert(entry.name().to_string(), entry.size());
+    }
+
+    if compressed.is_empty() {
+        report.pass("stored entries", format!("{} entries stored uncompressed", sizes.len()));
+    } else {
+        report.fail("stored entries", format!("ZIP-compressed entries: {}", compressed.join(", ")));
+    }
+
+    let unknown: Vec<&str> = sizes.keys()
+        .map(String::as_str)
+        .filter(|name| entry_spec(name).is_none())
+        .collect();
+    if unknown.is_empty() {
+        report.pass("known entries", "all entries are defined by the spec");
+    } else {
+        report.push("known entries", CheckStatus::Warn, format!("entries not in the spec: {}", unknown.join(", ")));
+    }
+
+    // Manifest
+    let manifest = match read_entry(&mut archive, "manifest.msgpack").and_then(|data| Manifest::from_msgpack(&data)) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            report.fail("manifest", e.to_string());
+            return Ok(report);
+        }
+    };
+    report.archive_version = Some(manifest.version.clone());
+    if major(&manifest.version) == major(SPEC_VERSION) {
+        report.pass("manifest", format!("version {}", manifest.version));
+    } else {
+        report.fail("manifest", format!("version {} is incompatible with spec {}", manifest.version, SPEC_VERSION));
+    }
+
+    // File map
+    let has_shards = sizes.contains_key(SHARD_INDEX_PATH);
+    let file_map = if has_shards || sizes.contains_key("file_map.msgpack") {
+        match read_file_map(&mut archive) {
+            Ok(file_map) => {
+                let layout = if has_shards { "sharded" } else { "unsharded" };
+                report.pass("file map", format!("{} files ({})", file_map.files.len(), layout));
+                file_map
+            }
+            Err(e) => {
+                report.fail("file map", e.to_string());
+                return Ok(report);
+            }
+        }
+    } else if manifest.stats.total_files == 0 {
+        report.pass("file map", "no files");
+        FileMap::default()
+    } else {
+        report.fail("file map", "no file_map/index.msgpack or file_map.msgpack");
+        return Ok(report);
+    };
+
+    // Stored chunks, packed or one per entry
+    let packs = match PackIndex::read_from_archive(&mut archive) {
+        Ok(packs) => packs,
+        Err(e) => {
+            report.fail("chunks", format!("{}: {}", PACK_INDEX_PATH, e));
+            return Ok(report);
+        }
+    };
+    let stored = packs.stored_sizes(&mut archive)?;
+
+    check_chunks(&mut archive, &packs, &stored, &file_map, &mut report);
+    let snapshot_chunks = check_snapshots(&mut archive, &sizes, &stored, &file_map, &mut report);
+    check_stats(&manifest, &file_map, &stored, snapshot_chunks, &mut report);
+    check_filters(&mut archive, &sizes, &file_map, &mut report);
+    check_children(&manifest, &sizes, &mut report);
+
+    if manifest.extensions.iter().any(|e| e == "embeddings") {
+        if sizes.keys().any(|name| name.starts_with("embeddings/")) {
+            report.pass("embeddings", "embedding entries present");
+        } else {
+            report.fail("embeddings", "manifest lists embeddings but no embeddings/ entries exist");
+        }
+    }
+
+    check_toc(&mut archive, &sizes, &packs, &mut report);
+
+    Ok(report)
+}
+
+/// Every referenced chunk exists, decompresses, hashes to its name and rebuilds its file
+fn check_chunks<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    packs: &PackIndex,
+    stored: &BTreeMap<String, u64>,
+    file_map: &FileMap,
+    report: &mut ConformanceReport,
+) {
+    let mut problems = Vec::new();
+    let mut verified: BTreeMap<String, usize> = BTreeMap::new();
+    let codec = match read_chunk_codec(archive) {
+        Ok(codec) => codec,
+        Err(e) => {
+            report.fail("chunks", format!("{}: {}", DICTIONARY_PATH, e));
+            return;
+        }
+    };
+
+    for (path, entry) in &file_map.files {
+        let mut expected_offset = 0;
+        for chunk in &entry.chunks {
+            if chunk.offset != expected_offset {
+                problems.push(format!("{}: chunk at offset {} leaves a gap", path, chunk.offset));
+            }
+            expected_offset = chunk.offset + chunk.length;
+
+            if let Some(length) = verified.get(&chunk.hash) {
+                if *length != chunk.length {
+                    problems.push(format!("{}: chunk {} has length {} (expected {})", path, &chunk.hash[..16.min(chunk.hash.len())], chunk.length, length));
+                }
+                continue;
+            }
+
+            let id = &chunk.hash[..16.min(chunk.hash.len())];
+            let name = format!("chunk {}", id);
+            if !stored.contains_key(id) {
+                problems.push(format!("{}: missing {}", path, name));
+                continue;
+            }
+            match packs.read_stored(archive, &chunk.hash).and_then(|data| codec.decompress(&data)) {
+                Ok(content) if compute_hash(&content) != chunk.hash => {
+                    problems.push(format!("{}: SHA-256 mismatch", name));
+                }
+                Ok(content) if content.len() != chunk.length => {
+                    problems.push(format!("{}: {} bytes (expected {})", name, content.len(), chunk.length));
+                }
+                Ok(content) => {
+                    verified.insert(chunk.hash.clone(), content.len());
+                }
+                Err(e) => problems.push(format!("{}: {}", name, e)),
+            }
+        }
+
+        if !entry.is_image && expected_offset as u64 != entry.size {
+            problems.push(format!("{}: chunks cover {} of {} bytes", path, expected_offset, entry.size));
+        }
+    }
+
+    if problems.is_empty() {
+        report.pass("chunks", format!("{} unique chunks verified", verified.len()));
+    } else {
+        report.fail("chunks", summarize(&problems));
+    }
+}
+
+/// Manifest statistics agree with the file map and stored chunks
+///
+/// Chunks only snapshots reference are not counted in `unique_chunks`.
+fn check_stats(
+    manifest: &Manifest,
+    file_map: &FileMap,
+    stored: &BTreeMap<String, u64>,
+    snapshot_chunks: usize,
+    report: &mut ConformanceReport,
+) {
+    let chunk_entries = stored.len();
+    let mut problems = Vec::new();
+
+    if manifest.stats.total_files != file_map.files.len() {
+        problems.push(format!("total_files {} but {} files in the file map", manifest.stats.total_files, file_map.files.len()));
+    }
+    if manifest.stats.unique_chunks + snapshot_chunks != chunk_entries {
+        problems.push(format!(
+            "unique_chunks {} and {} snapshot chunks but {} stored chunks",
+            manifest.stats.unique_chunks, snapshot_chunks, chunk_entries
+        ));
+    }
+
+    if problems.is_empty() {
+        report.pass("stats", format!("{} files, {} chunks", file_map.files.len(), chunk_entries));
+    } else {
+        report.fail("stats", problems.join("; "));
+    }
+}
+
+/// Every snapshot's file map reads and its chunks exist
+///
+/// Returns the number of chunks only snapshots reference.
+fn check_snapshots<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    sizes: &BTreeMap<String, u64>,
+    stored: &BTreeMap<String, u64>,
+    file_map: &FileMap,
+    report: &mut ConformanceReport,
+) -> usize {
+    if !sizes.contains_key(SNAPSHOT_INDEX_PATH) {
+        return 0;
+    }
+    let index = match SnapshotIndex::read_from_archive(archive) {
+        Ok(index) => index,
+        Err(e) => {
+            report.fail("snapshots", e.to_string());
+            return 0;
+        }
+    };
+
+    let chunk_id = |hash: &str| hash[..16.min(hash.len())].to_string();
+    let head: BTreeSet<String> = file_map.files.values()
+        .flat_map(|entry| entry.chunks.iter().map(|c| chunk_id(&c.hash)))
+        .collect();
+    let mut only_in_snapshots = BTreeSet::new();
+    let mut problems = Vec::new();
+    for info in &index.snapshots {
+        let snapshot = match index.read_file_map(archive, &info.label) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                problems.push(format!("{}: {}", info.label, e));
+                continue;
+            }
+        };
+        for (path, entry) in &snapshot.files {
+            for chunk in &entry.chunks {
+                let id = chunk_id(&chunk.hash);
+                if !stored.contains_key(&id) {
+                    problems.push(format!("{}: {}: missing chunk {}", info.label, path, id));
+                } else if !head.contains(&id) {
+                    only_in_snapshots.insert(id);
+                }
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        report.pass("snapshots", format!("{} snapshots, {} chunks only in snapshots", index.snapshots.len(), only_in_snapshots.len()));
+    } else {
+        report.fail("snapshots", summarize(&problems));
+    }
+    only_in_snapshots.len()
+}
+
+/// Bloom filters, if present, contain every path and chunk hash
+fn check_filters<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    sizes: &BTreeMap<String, u64>,
+    file_map: &FileMap,
+    report: &mut ConformanceReport,
+) {
+    for (name, filter_path) in [("path filter", PATH_FILTER_PATH), ("chunk filter", CHUNK_FILTER_PATH)] {
+        if !sizes.contains_key(filter_path) {
+            continue;
+        }
+        let filter = match read_entry(archive, filter_path).and_then(|data| BloomFilter::from_bytes(&data)) {
+            Ok(filter) => filter,
+            Err(e) => {
+                report.fail(name, e.to_string());
+                continue;
+            }
+        };
+
+        let missing = if filter_path == PATH_FILTER_PATH {
+            file_map.files.keys().filter(|path| !filter.contains(path.as_bytes())).count()
+        } else {
+            let hashes: BTreeSet<&str> = file_map.files.values()
+                .flat_map(|entry| entry.chunks.iter().map(|c| c.hash.as_str()))
+                .collect();
+            hashes.into_iter().filter(|hash| !filter.contains(hash.as_bytes())).count()
+        };
+
+        if missing == 0 {
+            report.pass(name, "no false negatives");
+        } else {
+            report.fail(name, format!("{} items missing from {}", missing, filter_path));
+        }
+    }
+}
+
+/// Embedded children listed in the manifest are stored in the archive
+fn check_children(manifest: &Manifest, sizes: &BTreeMap<String, u64>, report: &mut ConformanceReport) {
+    if !manifest.has_children() {
+        return;
+    }
+
+    let missing: Vec<String> = manifest.children.iter()
+        .filter_map(|child| match &child.storage {
+            CxpStorage::Embedded { path_in_zip } if !sizes.contains_key(path_in_zip) => {
+                Some(path_in_zip.clone())
+            }
+            _ => None,
+        })
+        .collect();
+
+    if missing.is_empty() {
+        report.pass("children", format!("{} children", manifest.children.len()));
+    } else {
+        report.fail("children", format!("missing embedded children: {}", missing.join(", ")));
+    }
+}
+
+/// The table of contents, if present, describes exactly the other entries
+fn check_toc<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    sizes: &BTreeMap<String, u64>,
+    packs: &PackIndex,
+    report: &mut ConformanceReport,
+) {
+    if !sizes.contains_key(TOC_PATH) {
+        report.push("toc", CheckStatus::Warn, "no toc.msgpack (readers must scan entries)");
+        return;
+    }
+
+    let toc = match read_entry(archive, TOC_PATH).and_then(|data| Toc::from_msgpack(&data)) {
+        Ok(toc) => toc,
+        Err(e) => {
+            report.fail("toc", e.to_string());
+            return;
+        }
+    };
+
+    let mut expected = Toc::new();
+    for (name, size) in sizes.iter().filter(|(name, _)| name.as_str() != TOC_PATH) {
+        // Packs count their chunks, the pack index nothing
+        let entries = match PackIndex::pack_number(name) {
+            Some(pack) => packs.chunks_in(pack),
+            None if name == PACK_INDEX_PATH => 0,
+            None => 1,
+        };
+        expected.record_entries(name, *size, entries);
+    }
+
+    let mut problems = Vec::new();
+    if toc.sections != expected.sections {
+        problems.push("section sizes do not match the archive entries".to_string());
+    }
+    let names = |toc: &Toc| -> BTreeSet<String> {
+        toc.extensions.iter()
+            .flat_map(|(ns, entries)| entries.iter().map(move |e| format!("{}/{}", ns, e.name)))
+            .chain(toc.indices.iter().map(|e| e.name.clone()))
+            .collect()
+    };
+    if names(&toc) != names(&expected) {
+        problems.push("extension or index entries do not match the archive".to_string());
+    }
+    let toc_last = archive.by_index_raw(archive.len() - 1).map(|e| e.name() == TOC_PATH).unwrap_or(false);
+    if !toc_last {
+        problems.push("toc.msgpack is not the last entry".to_string());
+    }
+
+    if problems.is_empty() {
+        report.pass("toc", format!("{} sections", toc.sections.len()));
+    } else {
+        report.fail("toc", problems.join("; "));
+    }
+}
+
+/// Read a whole ZIP entry
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = archive.by_name(name)
+        .map_err(|e| CxpError::InvalidFormat(format!("No {} found: {}", name, e)))?;
+    let mut data = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Major component of a semantic version
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or(version)
+}
+
+/// First few problems plus a count of the rest
+fn summarize(problems: &[String]) -> String {
+    const SHOWN: usize = 3;
+    let mut summary = problems.iter().take(SHOWN).cloned().collect::<Vec<_>>().join("; ");
+    if problems.len() > SHOWN {
+        summary.push_str(&format!(" (+{} more)", problems.len() - SHOWN));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_spec_lookup() {
+        assert_eq!(entry_spec("manifest.msgpack").unwrap().pattern, "manifest.msgpack");
+        assert_eq!(entry_spec("file_map/index.msgpack").unwrap().pattern, SHARD_INDEX_PATH);
+        assert_eq!(entry_spec("file_map/00000.msgpack").unwrap().pattern, "file_map/");
+        assert_eq!(entry_spec("extensions/notes/data.msgpack").unwrap().pattern, "extensions/");
+        assert!(entry_spec("random.bin").is_none());
+        assert_eq!(ENTRIES.iter().filter(|e| e.required).count(), 1);
+    }
+
+    #[test]
+    fn test_entry_versions() {
+        use crate::manifest::parse_version;
+        use crate::packs::PACKS_READER_VERSION;
+
+        let current = parse_version(SPEC_VERSION).unwrap();
+        for entry in ENTRIES {
+            assert!(parse_version(entry.since).is_some_and(|since| since <= current), "{}", entry.pattern);
+        }
+        assert_eq!(entry_spec("manifest.msgpack").unwrap().since, "1.0.0");
+        assert_eq!(entry_spec(PACK_INDEX_PATH).unwrap().since, PACKS_READER_VERSION);
+        assert_eq!(entry_spec(TOC_PATH).unwrap().since, "1.1.0");
+    }
+}
@@ -0,0 +1,163 @@
+//! Multi-Query Result Fusion
+//!
+//! Agents often issue several paraphrases of the same question. This module
+//! merges the ranked candidate lists of those query variants into a single
+//! ranking using Reciprocal Rank Fusion (RRF):
+//!
+//! ```text
+//! score(d) = Σ 1 / (k + rank_q(d))     for every query q that returned d
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Default RRF constant (the value proposed in the original RRF paper)
+pub const DEFAULT_RRF_K: f32 = 60.0;
+
+/// Granularity used to deduplicate fused results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DedupBy {
+    /// One result per chunk
+    #[default]
+    Chunk,
+    /// One result per file (the best-scoring chunk wins)
+    File,
+}
+
+/// Configuration for fusing the results of several query variants
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Fusion {
+    /// RRF constant - higher values flatten the influence of top ranks
+    pub rrf_k: f32,
+    /// How results are deduplicated after fusion
+    pub dedup: DedupBy,
+}
+
+impl Default for Fusion {
+    fn default() -> Self {
+        Self {
+            rrf_k: DEFAULT_RRF_K,
+            dedup: DedupBy::Chunk,
+        }
+    }
+}
+
+impl Fusion {
+    /// Reciprocal Rank Fusion with the default constant
+    pub fn rrf() -> Self {
+        Self::default()
+    }
+
+    /// Set the RRF constant
+    pub fn with_k(mut self, rrf_k: f32) -> Self {
+        self.rrf_k = rrf_k;
+        self
+    }
+
+    /// Set the deduplication granularity
+    pub fn dedup_by(mut self, dedup: DedupBy) -> Self {
+        self.dedup = dedup;
+        self
+    }
+}
+
+/// A single result after fusing several rankings
+#[derive(Debug, Clone, PartialEq)]
+pub struct FusedResult {
+    /// Candidate ID (embedding / chunk index)
+    pub id: u64,
+    /// Fused RRF score (higher is better)
+    pub score: f32,
+    /// Number of query variants that returned this candidate
+    pub hits: usize,
+    /// Chunk hash, if the ID could be resolved
+    pub chunk_hash: Option<String>,
+    /// File containing the chunk, if the ID could be resolved
+    pub file_path: Option<String>,
+    /// Near-duplicate chunks sharing this chunk's embedding
+    pub aliases: Vec<String>,
+}
+
+/// Fuse several ranked ID lists with Reciprocal Rank Fusion
+///
+/// Each ranking is ordered best-first. IDs repeated within one ranking only
+/// count at their best position. Results are sorted by score (highest first),
+/// ties are broken by ID so the output is deterministic.
+pub fn reciprocal_rank_fusion(rankings: &[Vec<u64>], rrf_k: f32) -> Vec<FusedResult> {
+    let mut scores: HashMap<u64, (f32, usize)> = HashMap::new();
+
+    for ranking in rankings {
+        let mut seen = std::collections::HashSet::new();
+        for (rank, &id) in ranking.iter().enumerate() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let entry = scores.entry(id).or_insert((0.0, 0));
+            entry.0 += 1.0 / (rrf_k + rank as f32 + 1.0);
+            entry.1 += 1;
+        }
+    }
+
+    let mut fused: Vec<FusedResult> = scores
+        .into_iter()
+        .map(|(id, (score, hits))| FusedResult {
+            id,
+            score,
+            hits,
+            chunk_hash: None,
+            file_path: None,
+            aliases: Vec::new(),
+        })
+        .collect();
+
+    fused.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.id.cmp(&b.id))
+    });
+
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rrf_rewards_agreement() {
+        let rankings = vec![vec![1, 2, 3], vec![2, 1, 4], vec![2, 5]];
+        let fused = reciprocal_rank_fusion(&rankings, DEFAULT_RRF_K);
+
+        assert_eq!(fused[0].id, 2);
+        assert_eq!(fused[0].hits, 3);
+        assert_eq!(fused[1].id, 1);
+        assert_eq!(fused.len(), 5);
+    }
+
+    #[test]
+    fn test_rrf_ignores_duplicates_within_ranking() {
+        let fused = reciprocal_rank_fusion(&[vec![7, 7, 7]], DEFAULT_RRF_K);
+
+        assert_eq!(fused.len(), 1);
+        assert_eq!(fused[0].hits, 1);
+        assert!((fused[0].score - 1.0 / 61.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rrf_deterministic_ties() {
+        let fused = reciprocal_rank_fusion(&[vec![9], vec![3]], DEFAULT_RRF_K);
+
+        assert_eq!(fused[0].id, 3);
+        assert_eq!(fused[1].id, 9);
+    }
+
+    #[test]
+    fn test_fusion_config() {
+        let fusion = Fusion::rrf().with_k(10.0).dedup_by(DedupBy::File);
+
+        assert_eq!(fusion.rrf_k, 10.0);
+        assert_eq!(fusion.dedup, DedupBy::File);
+        assert_eq!(Fusion::default().dedup, DedupBy::Chunk);
+    }
+}
@@ -0,0 +1,208 @@
+//! Garbage Collection
+//!
+//! Incremental updates and dropped snapshots leave chunks behind that
+//! no file map references any more. `CxpFile::gc()` rewrites an archive in
+//! place without them:
+//!
+//! - with `keep_last`, the oldest snapshots beyond the newest N are dropped
+//!   together with their file maps
+//! - chunks referenced neither by the head nor by a remaining snapshot are
+//!   removed, and the kept ones are repacked (see [`crate::packs`])
+//!
+//! Everything is copied as stored, so a collection is cheap compared to
+//! `CxpOptimizer`, which also reorders and recompresses the chunks.
+
+use crate::format::{read_file_map, ArchiveWriter};
+use crate::journal::journal_path;
+use crate::manifest::Manifest;
+use crate::packs::PackIndex;
+use crate::snapshots::{SnapshotIndex, SNAPSHOT_INDEX_PATH};
+use crate::temp::replace_file;
+use crate::toc::TOC_PATH;
+use crate::{CxpError, Result};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Options for [`collect_garbage`]
+#[derive(Debug, Clone, Default)]
+pub struct GcOptions {
+    /// Number of newest snapshots to keep (None keeps all of them)
+    pub keep_last: Option<usize>,
+}
+
+impl GcOptions {
+    /// Keep only the newest `count` snapshots
+    pub fn with_keep_last(mut self, count: usize) -> Self {
+        self.keep_last = Some(count);
+        self
+    }
+}
+
+/// Summary of a garbage collection
+#[derive(Debug, Clone, Default)]
+pub struct GcStats {
+    /// Labels of the dropped snapshots, oldest first
+    pub snapshots_dropped: Vec<String>,
+    /// Snapshots left
+    pub snapshots_kept: usize,
+    /// Chunks removed
+    pub chunks_removed: usize,
+    /// Chunks left
+    pub chunks_kept: usize,
+    /// Stored bytes of the removed chunks and snapshot file maps
+    pub bytes_reclaimed: u64,
+    /// Archive size before, in bytes
+    pub bytes_before: u64,
+    /// Archive size after, in bytes
+    pub bytes_after: u64,
+}
+
+/// Remove unreferenced chunks (and old snapshots) from the archive at `path`
+///
+/// The archive is rewritten next to `path` and moved over it; it is left
+/// untouched when there is nothing to collect.
+pub fn collect_garbage<P: AsRef<Path>>(path: P, options: &GcOptions) -> Result<GcStats> {
+    let path = path.as_ref();
+    if journal_path(path).exists() {
+        return Err(CxpError::InvalidFormat(format!(
+            "{:?} has journaled updates; compact them first (see crate::journal::compact)",
+            path
+        )));
+    }
+    let mut stats = GcStats {
+        bytes_before: std::fs::metadata(path)?.len(),
+        ..GcStats::default()
+    };
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+
+    let mut snapshots = SnapshotIndex::read_from_archive(&mut archive)?;
+    let keep = options.keep_last.unwrap_or(usize::MAX).min(snapshots.snapshots.len());
+    let dropped: Vec<_> = snapshots.snapshots.drain(..snapshots.snapshots.len() - keep).collect();
+    stats.snapshots_dropped = dropped.iter().map(|s| s.label.clone()).collect();
+    stats.snapshots_kept = snapshots.snapshots.len();
+    let dropped_maps: HashSet<String> = dropped.iter().map(|s| s.path()).collect();
+
+    // Ids of every chunk a live generation references
+    let mut live: HashSet<String> = read_file_map(&mut archive)?
+        .files
+        .values()
+        .flat_map(|entry| entry.chunks.iter().map(|c| chunk_id(&c.hash)))
+        .collect();
+    live.extend(snapshots.referenced_chunks(&mut archive)?.iter().map(|hash| chunk_id(hash)));
+
+    let packs = PackIndex::read_from_archive(&mut archive)?;
+    let mut kept = Vec::new();
+    for (id, size) in packs.stored_sizes(&mut archive)? {
+        if live.contains(&id) {
+            kept.push(id);
+        } else {
+            stats.bytes_reclaimed += size;
+            stats.chunks_removed += 1;
+        }
+    }
+    stats.chunks_kept = kept.len();
+    for name in &dropped_maps {
+        if let Some(i) = archive.index_for_name(name) {
+            stats.bytes_reclaimed += archive.by_index_raw(i)?.compressed_size();
+        }
+    }
+
+    if stats.chunks_removed == 0 && dropped.is_empty() {
+        tracing::info!("Nothing to collect in {:?}", path);
+        stats.bytes_after = stats.bytes_before;
+        return Ok(stats);
+    }
+
+    // Everything but the chunks is copied; the kept chunks are repacked
+    replace_file(path, |temp_path| {
+        let mut writer = ArchiveWriter::create(temp_path)?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let name = entry.name().to_string();
+            if name.starts_with("chunks/") || dropped_maps.contains(&name) || name == SNAPSHOT_INDEX_PATH || name == TOC_PATH {
+                continue;
+            }
+            let mut data = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut data)?;
+            if name == "manifest.msgpack" {
+                // The kept chunks are packed even if the archive was not
+                writer.write_manifest(&Manifest::from_msgpack(&data)?)?;
+            } else {
+                writer.write(&name, &data)?;
+            }
+        }
+        for id in &kept {
+            writer.write_chunk(id, &packs.read_stored(&mut archive, id)?)?;
+        }
+        if !snapshots.is_empty() {
+            writer.write(SNAPSHOT_INDEX_PATH, &snapshots.to_msgpack()?)?;
+        }
+        writer.finish()?;
+        Ok(())
+    })?;
+    stats.bytes_after = std::fs::metadata(path)?.len();
+    tracing::info!(
+        "Collected {:?}: {} chunks and {} snapshots removed, {} bytes reclaimed",
+        path,
+        stats.chunks_removed,
+        stats.snapshots_dropped.len(),
+        stats.bytes_reclaimed
+    );
+    Ok(stats)
+}
+
+fn chunk_id(hash: &str) -> String {
+    crate::chunker::chunk_id(hash).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format_spec::check_file;
+    use crate::{CxpBuilder, CxpFile, CxpReader};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_gc_drops_old_snapshots_and_their_chunks() {
+        let source = TempDir::new().unwrap();
+        let notes = source.path().join("notes.txt");
+        std::fs::write(source.path().join("main.rs"), "fn main() {}\n").unwrap();
+
+        let out = TempDir::new().unwrap();
+        let path = out.path().join("history.cxp");
+        let mut builder = CxpBuilder::new(source.path());
+        std::fs::write(&notes, "first draft\n").unwrap();
+        builder.scan().unwrap().process().unwrap().snapshot("v1").unwrap();
+        for (label, text) in [("v2", "second draft\n"), ("v3", "third draft\n")] {
+            std::fs::write(&notes, text).unwrap();
+            builder.update_files(std::slice::from_ref(&notes)).unwrap().snapshot(label).unwrap();
+        }
+        std::fs::write(&notes, "final\n").unwrap();
+        builder.update_files(std::slice::from_ref(&notes)).unwrap().build(&path).unwrap();
+
+        // Every chunk is still referenced
+        let stats = CxpFile::gc(&path, &GcOptions::default()).unwrap();
+        assert_eq!((stats.chunks_removed, stats.bytes_reclaimed), (0, 0));
+        assert_eq!(stats.bytes_after, stats.bytes_before);
+
+        let stats = CxpFile::gc(&path, &GcOptions::default().with_keep_last(1)).unwrap();
+        assert_eq!(stats.snapshots_dropped, vec!["v1", "v2"]);
+        assert_eq!((stats.snapshots_kept, stats.chunks_removed, stats.chunks_kept), (1, 2, 3));
+        assert!(stats.bytes_reclaimed > 0);
+        assert!(stats.bytes_after < stats.bytes_before);
+
+        let reader = CxpReader::open(&path).unwrap();
+        assert_eq!(reader.read_file("notes.txt").unwrap(), b"final\n");
+        assert_eq!(reader.snapshots().len(), 1);
+        assert_eq!(reader.open_snapshot("v3").unwrap().read_file("notes.txt").unwrap(), b"third draft\n");
+        assert!(reader.open_snapshot("v1").is_err());
+        assert!(check_file(&path).unwrap().failures().is_empty());
+
+        let stats = CxpFile::gc(&path, &GcOptions::default().with_keep_last(0)).unwrap();
+        assert_eq!((stats.chunks_removed, stats.chunks_kept), (1, 2));
+        assert!(CxpReader::open(&path).unwrap().snapshots().is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+//! Git Integration
+//!
+//! Builds archives from a repository's history instead of its working tree:
+//!
+//! - `CxpBuilder::from_git(repo, "v1.2.0")` packs the full tree at a revision
+//! - `CxpBuilder::from_git(repo, "main..feature")` packs only the files touched
+//!   by the commits in the range, with their content at the end of the range
+//!   ("context for this PR" packs); `main...feature` starts at the merge base
+//!
+//! With `with_provenance(true)`, every entry records its path within the
+//! repository and the commit it was taken from: the newest commit of the
+//! range that touched it, or the revision itself for full trees. Only text
+//! files are packed; files deleted within a range are skipped.
+
+use crate::format::{CxpBuilder, PendingFile};
+use crate::provenance::{detect_license, Provenance};
+use crate::{is_text_file, Result};
+use chrono::{DateTime, Utc};
+use git2::{ObjectType, Oid, Repository, Sort, TreeWalkMode, TreeWalkResult};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Git file mode of symbolic links (not packed)
+const SYMLINK_MODE: i32 = 0o120000;
+
+/// What to pack from a repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitSelection {
+    /// Every file in the tree at a revision
+    Tree(String),
+    /// Files touched by commits reachable from `to` but not from `from`
+    Range {
+        /// Start of the range (excluded)
+        from: String,
+        /// End of the range (content is taken from here)
+        to: String,
+        /// Start at the merge base of `from` and `to` (`from...to`)
+        merge_base: bool,
+    },
+}
+
+impl GitSelection {
+    /// Parse `rev`, `from..to` or `from...to` (an empty side means `HEAD`)
+    pub fn parse(spec: &str) -> Self {
+        let side = |rev: &str| if rev.trim().is_empty() { "HEAD".to_string() } else { rev.trim().to_string() };
+        if let Some((from, to)) = spec.split_once("...") {
+            Self::Range { from: side(from), to: side(to), merge_base: true }
+        } else if let Some((from, to)) = spec.split_once("..") {
+            Self::Range { from: side(from), to: side(to), merge_base: false }
+        } else {
+            Self::Tree(side(spec))
+        }
+    }
+}
+
+/// A text file read from the object database
+struct GitFile {
+    path: String,
+    content: Vec<u8>,
+    commit: Oid,
+    time: i64,
+}
+
+impl CxpBuilder {
+    /// Builder over a git revision (`v1.0`, `HEAD~3`) or range (`main..feature`)
+    ///
+    /// `repo_path` may be any directory inside the repository. Content is read
+    /// from git objects, not the working tree. Call `process()` and `build()`
+    /// as usual (`scan()` is a no-op); options set on the builder still apply
+    /// since content is only chunked in `process()`. The revision and its
+    /// commit are recorded as `git.rev` / `git.commit` manifest metadata.
+    pub fn from_git<P: AsRef<Path>>(repo_path: P, rev: &str) -> Result<Self> {
+        let repo = Repository::discover(repo_path.as_ref())?;
+        let root = repo.workdir().unwrap_or_else(|| repo.path()).to_path_buf();
+        let root = root.canonicalize().unwrap_or(root);
+
+        let selection = GitSelection::parse(rev);
+        let (commit, files) = match &selection {
+            GitSelection::Tree(rev) => tree_files(&repo, rev)?,
+            GitSelection::Range { from, to, merge_base } => range_files(&repo, from, to, *merge_base)?,
+        };
+        tracing::info!("Read {} files from git ({} at {})", files.len(), rev, commit);
+
+        let mut builder = CxpBuilder::new(&root);
+        builder
+            .skip_scan()
+            .with_metadata("git.rev", rev)
+            .with_metadata("git.commit", commit.to_string());
+        for file in files {
+            builder.add_pending(PendingFile {
+                // Dropped in `process()` unless provenance is enabled
+                provenance: Some(Provenance {
+                    origin: file.path.clone(),
+                    git_commit: Some(file.commit.to_string()),
+                    license: detect_license(&file.content),
+                }),
+                modified: DateTime::<Utc>::from_timestamp(file.time, 0),
+                path: file.path,
+                content: file.content,
+            });
+        }
+        Ok(builder)
+    }
+}
+
+/// All text files in the tree at `rev`
+fn tree_files(repo: &Repository, rev: &str) -> Result<(Oid, Vec<GitFile>)> {
+    let commit = repo.revparse_single(rev)?.peel_to_commit()?;
+    let tree = commit.tree()?;
+    let time = commit.time().seconds();
+
+    let mut blobs = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(ObjectType::Blob) && entry.filemode() != SYMLINK_MODE {
+            if let Some(name) = entry.name() {
+                let path = format!("{}{}", root, name);
+                if has_text_extension(&path) {
+                    blobs.push((path, entry.id()));
+                }
+            }
+        }
+        TreeWalkResult::Ok
+    })?;
+
+    let files = blobs
+        .into_iter()
+        .map(|(path, id)| {
+            Ok(GitFile {
+                content: repo.find_blob(id)?.content().to_vec(),
+                path,
+                commit: commit.id(),
+                time,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok((commit.id(), files))
+}
+
+/// Text files touched by the commits in `from..to`, with their content at `to`
+fn range_files(repo: &Repository, from: &str, to: &str, merge_base: bool) -> Result<(Oid, Vec<GitFile>)> {
+    let to = repo.revparse_single(to)?.peel_to_commit()?;
+    let mut from = repo.revparse_single(from)?.peel_to_commit()?.id();
+    if merge_base {
+        from = repo.merge_base(from, to.id())?;
+    }
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    walk.push(to.id())?;
+    walk.hide(from)?;
+
+    // Newest commit touching each path
+    let mut touched: BTreeMap<String, (Oid, i64)> = BTreeMap::new();
+    for id in walk {
+        let commit = repo.find_commit(id?)?;
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+        for delta in diff.deltas() {
+            if let Some(path) = delta.new_file().path().and_then(|p| p.to_str()) {
+                touched
+                    .entry(path.to_string())
+                    .or_insert((commit.id(), commit.time().seconds()));
+            }
+        }
+    }
+
+    let tree = to.tree()?;
+    let mut files = Vec::new();
+    for (path, (commit, time)) in touched {
+        if !has_text_extension(&path) {
+            continue;
+        }
+        // Deleted by the end of the range
+        let Ok(entry) = tree.get_path(Path::new(&path)) else {
+            continue;
+        };
+        if entry.kind() != Some(ObjectType::Blob) || entry.filemode() == SYMLINK_MODE {
+            continue;
+        }
+        let content = repo.find_blob(entry.id())?.content().to_vec();
+        files.push(GitFile { path, content, commit, time });
+    }
+    Ok((to.id(), files))
+}
+
+fn has_text_extension(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(is_text_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CxpReader;
+    use git2::Signature;
+    use tempfile::TempDir;
+
+    /// Write `files` (None deletes) and commit them
+    fn commit(repo: &Repository, files: &[(&str, Option<&str>)], message: &str) -> Oid {
+        let root = repo.workdir().unwrap();
+        let mut index = repo.index().unwrap();
+        for (path, content) in files {
+            match content {
+                Some(content) => {
+                    std::fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+                    std::fs::write(root.join(path), content).unwrap();
+                    index.add_path(Path::new(path)).unwrap();
+                }
+                None => {
+                    std::fs::remove_file(root.join(path)).unwrap();
+                    index.remove_path(Path::new(path)).unwrap();
+                }
+            }
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<_> = repo.head().ok().map(|h| h.peel_to_commit().unwrap()).into_iter().collect();
+        let parents: Vec<_> = parents.iter().collect();
+        repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents).unwrap()
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(GitSelection::parse("v1.0"), GitSelection::Tree("v1.0".to_string()));
+        assert_eq!(
+            GitSelection::parse("HEAD~5..HEAD"),
+            GitSelection::Range { from: "HEAD~5".to_string(), to: "HEAD".to_string(), merge_base: false }
+        );
+        assert_eq!(
+            GitSelection::parse("main..."),
+            GitSelection::Range { from: "main".to_string(), to: "HEAD".to_string(), merge_base: true }
+        );
+    }
+
+    #[test]
+    fn test_build_from_git() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let first = commit(&repo, &[("src/lib.rs", Some("pub fn v1() {}\n")), ("README.md", Some("# Readme\n"))], "init");
+        let second = commit(&repo, &[("src/lib.rs", Some("pub fn v2() {}\n")), ("notes.md", Some("draft\n"))], "change");
+        let third = commit(&repo, &[("notes.md", None), ("src/new.py", Some("print(1)\n"))], "more");
+
+        // Uncommitted changes are ignored
+        std::fs::write(dir.path().join("src/lib.rs"), "pub fn dirty() {}\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+
+        // Full tree at a revision
+        let tree_path = output.path().join("tree.cxp");
+        let mut builder = CxpBuilder::from_git(dir.path().join("src"), "HEAD~1").unwrap();
+        builder.scan().unwrap().process().unwrap().build(&tree_path).unwrap();
+        let reader = CxpReader::open(&tree_path).unwrap();
+        let mut paths = reader.file_paths();
+        paths.sort();
+        assert_eq!(paths, vec!["README.md", "notes.md", "src/lib.rs"]);
+        assert_eq!(reader.read_file("src/lib.rs").unwrap(), b"pub fn v2() {}\n");
+        assert_eq!(reader.manifest().metadata["git.commit"], second.to_string());
+        // Provenance is opt-in like for working trees
+        assert!(reader.file_entry("src/lib.rs").unwrap().unwrap().provenance.is_none());
+
+        // Only files touched in a range, with the newest commit touching them
+        let range_path = output.path().join("range.cxp");
+        let mut builder = CxpBuilder::from_git(dir.path(), &format!("{}..HEAD", first)).unwrap();
+        builder.with_provenance(true).process().unwrap().build(&range_path).unwrap();
+        let reader = CxpReader::open(&range_path).unwrap();
+        let mut paths = reader.file_paths();
+        paths.sort();
+        assert_eq!(paths, vec!["src/lib.rs", "src/new.py"]);
+        let commit_of = |path: &str| {
+            let entry = reader.file_entry(path).unwrap().unwrap();
+            entry.provenance.as_ref().unwrap().git_commit.clone().unwrap()
+        };
+        assert_eq!(commit_of("src/lib.rs"), second.to_string());
+        assert_eq!(commit_of("src/new.py"), third.to_string());
+        let entry = reader.file_entry("src/new.py").unwrap().unwrap();
+        assert_eq!(entry.provenance.as_ref().unwrap().origin, "src/new.py");
+        assert_eq!(reader.manifest().metadata["git.rev"], format!("{}..HEAD", first));
+
+        assert!(CxpBuilder::from_git(dir.path(), "no-such-branch").is_err());
+    }
+}
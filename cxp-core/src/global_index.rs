@@ -2,14 +2,25 @@
 //!
 //! Provides fast cross-CXP search without loading all CXPs into memory.
 //! Uses a lightweight index with keywords and optional embedding hashes.
+//!
+//! The index is persisted in the root archive at [`GLOBAL_INDEX_PATH`].
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::Path;
 use chrono::{DateTime, Utc};
 
+use crate::format::{ArchiveWriter, FileMap};
 use crate::recursive::FileTier;
+use crate::packs::PackIndex;
+use crate::temp::replace_file;
+use crate::toc::TOC_PATH;
 use crate::Result;
 
+/// Path of the global index inside the root archive
+pub const GLOBAL_INDEX_PATH: &str = "global_index.msgpack";
+
 /// Global index spanning all CXPs in the hierarchy
 ///
 /// This index is stored in the master CXP and provides
@@ -20,7 +31,7 @@ pub struct GlobalIndex {
     pub entries: Vec<GlobalIndexEntry>,
 
     /// CXP path -> index range for fast filtering
-    pub cxp_ranges: HashMap<String, IndexRange>,
+    pub cxp_ranges: BTreeMap<String, IndexRange>,
 
     /// Keyword -> entry indices for keyword search
     #[serde(skip)]
@@ -243,6 +254,55 @@ impl GlobalIndex {
         self.stats.cxp_count = self.cxp_ranges.len();
     }
 
+    /// Index every file of a CXP's file map under `cxp_path`
+    pub fn add_file_map(&mut self, cxp_path: Vec<String>, tier: FileTier, file_map: &FileMap) {
+        let cxp_id = cxp_path.join("/");
+        let entries = file_map.files.values()
+            .map(|file| {
+                let mut entry = GlobalIndexEntry::new(&cxp_id, cxp_path.clone(), &file.path, &file.extension);
+                entry.file_size = file.size;
+                entry.tier = tier;
+                entry.keywords = file.keywords.clone();
+                if let Some(modified) = file.modified {
+                    entry.modified_at = modified;
+                }
+                entry
+            })
+            .collect();
+        self.add_from_cxp(&cxp_id, cxp_path, entries);
+        self.stats.total_files = self.stats.total_entries;
+        self.stats.updated_at = Some(Utc::now());
+    }
+
+    /// Set the tier of every entry of a CXP, returning how many entries changed
+    pub fn set_tier(&mut self, cxp_path: &[String], tier: FileTier) -> usize {
+        let Some(range) = self.cxp_ranges.get(&cxp_path.join("/")) else {
+            return 0;
+        };
+        let mut changed = 0;
+        for entry in self.entries.iter_mut().take(range.end).skip(range.start) {
+            if !entry.cxp_id.is_empty() && entry.tier != tier {
+                entry.tier = tier;
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Remove the entries of a CXP and of every CXP nested below it
+    pub fn remove_cxp_tree(&mut self, cxp_path: &[String]) {
+        let key = cxp_path.join("/");
+        let prefix = format!("{}/", key);
+        let nested: Vec<String> = self.cxp_ranges.keys()
+            .filter(|k| **k == key || k.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for path in nested {
+            let path: Vec<String> = path.split('/').map(str::to_string).collect();
+            self.remove_cxp(&path);
+        }
+    }
+
     /// Remove all entries for a specific CXP
     pub fn remove_cxp(&mut self, cxp_path: &[String]) {
         let key = cxp_path.join("/");
@@ -405,6 +465,51 @@ impl GlobalIndex {
         Ok(index)
     }
 
+    /// Read the index stored in a root archive (`None` if it has none)
+    pub fn read_from<P: AsRef<Path>>(archive_path: P) -> Result<Option<Self>> {
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(archive_path.as_ref())?)?;
+        Self::read_from_archive(&mut archive)
+    }
+
+    /// Read the index from an open archive (`None` if it has none)
+    pub fn read_from_archive<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>) -> Result<Option<Self>> {
+        let mut entry = match archive.by_name(GLOBAL_INDEX_PATH) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        Self::from_msgpack(&data).map(Some)
+    }
+
+    /// Store the index in a root archive, replacing any previous index
+    ///
+    /// The archive is rewritten next to the original and moved into place
+    /// once complete.
+    pub fn write_to<P: AsRef<Path>>(&self, archive_path: P) -> Result<()> {
+        let archive_path = archive_path.as_ref();
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(archive_path)?)?;
+        let packs = PackIndex::read_from_archive(&mut archive)?;
+
+        replace_file(archive_path, |temp_path| {
+            let mut writer = ArchiveWriter::create(temp_path)?;
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i)?;
+                let name = entry.name().to_string();
+                if name == GLOBAL_INDEX_PATH || name == TOC_PATH {
+                    continue;
+                }
+                let mut data = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut data)?;
+                writer.copy(&name, &data, &packs)?;
+            }
+            writer.write(GLOBAL_INDEX_PATH, &self.to_msgpack()?)?;
+            writer.finish()?;
+            Ok(())
+        })
+    }
+
     /// Estimate memory size
     pub fn memory_size(&self) -> usize {
         let entries_size = self.entries.len() * std::mem::size_of::<GlobalIndexEntry>();
@@ -0,0 +1,645 @@
+//! Knowledge Graph Extension
+//!
+//! Nodes (files, functions, chunks, conversations) and typed edges between
+//! them, written at build time by [`GraphPlugin`]s
+//! (`CxpBuilder::with_graph_plugin`). Around a search hit, the graph answers
+//! "what else belongs to this?": the modules a file imports, the files that
+//! call its functions, the conversations that mention it.
+//!
+//! | Plugin | Nodes | Edges |
+//! |--------|-------|-------|
+//! | [`ImportGraph`] | - | file `imports` file (Rust `mod`/`use crate::`, Python, JS/TS, C `#include "..."`) |
+//! | [`SymbolGraph`] | functions | file `defines` function, file `references` function |
+//!
+//! The built-in plugins read source lines, no parser needed; plugins backed
+//! by a real parser (tree-sitter, LSP) implement the same trait. Apps add
+//! their own nodes directly, e.g. conversations that `mention` files.
+//!
+//! Structure:
+//! ```text
+//! extensions/graph/
+//! ├── manifest.msgpack     # extension metadata
+//! └── graph.msgpack        # KnowledgeGraph
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::extensions::Extension;
+use crate::symbols::{self, strip_visibility, SymbolKind};
+use crate::{CxpError, Result};
+
+/// Extension namespace of the knowledge graph
+pub const GRAPH_NAMESPACE: &str = "graph";
+
+/// Data key of the knowledge graph within its namespace
+pub const GRAPH_KEY: &str = "graph.msgpack";
+
+/// Version of the knowledge graph layout
+pub const GRAPH_VERSION: &str = "1.0.0";
+
+/// Shortest function name that `SymbolGraph` links references to
+const MIN_SYMBOL_LEN: usize = 4;
+
+/// Function names defined in more files than this are too generic to link
+const MAX_SYMBOL_DEFINITIONS: usize = 3;
+
+/// Extensions tried for extension-less JS/TS imports
+const SCRIPT_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+/// What a node stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    File,
+    Function,
+    Chunk,
+    Conversation,
+}
+
+/// How two nodes relate (edges point from the first to the second)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// A file imports another file
+    Imports,
+    /// A file defines a function
+    Defines,
+    /// A file uses a function defined elsewhere
+    References,
+    /// A conversation (or note) mentions a file, function or chunk
+    Mentions,
+    /// A file contains a chunk
+    Contains,
+}
+
+/// A graph node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    /// Unique id (`file:<path>`, `fn:<path>#<name>`, `chunk:<hash>`, `conversation:<id>`)
+    pub id: String,
+    pub kind: NodeKind,
+    /// Display name (path, function name, conversation title)
+    pub label: String,
+    /// Archive file the node belongs to (files, functions, chunks)
+    pub file: Option<String>,
+    /// 1-based line of a function definition
+    pub line: Option<usize>,
+}
+
+impl Node {
+    /// Node of an archived file
+    pub fn file(path: &str) -> Self {
+        Self { id: Self::file_id(path), kind: NodeKind::File, label: path.to_string(), file: Some(path.to_string()), line: None }
+    }
+
+    /// Node of a function defined in `path` at `line`
+    pub fn function(path: &str, name: &str, line: usize) -> Self {
+        Self {
+            id: Self::function_id(path, name),
+            kind: NodeKind::Function,
+            label: name.to_string(),
+            file: Some(path.to_string()),
+            line: Some(line),
+        }
+    }
+
+    /// Node of a chunk of `path`
+    pub fn chunk(hash: &str, path: &str) -> Self {
+        Self { id: format!("chunk:{}", hash), kind: NodeKind::Chunk, label: hash.to_string(), file: Some(path.to_string()), line: None }
+    }
+
+    /// Node of a conversation
+    pub fn conversation(id: &str, title: &str) -> Self {
+        Self { id: format!("conversation:{}", id), kind: NodeKind::Conversation, label: title.to_string(), file: None, line: None }
+    }
+
+    /// Id of the node of an archived file
+    pub fn file_id(path: &str) -> String {
+        format!("file:{}", path)
+    }
+
+    /// Id of the node of a function
+    pub fn function_id(path: &str, name: &str) -> String {
+        format!("fn:{}#{}", path, name)
+    }
+}
+
+/// A typed edge between two node ids
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+/// A node reached from the seeds of a neighborhood query
+#[derive(Debug, Clone, PartialEq)]
+pub struct Neighbor {
+    pub node: Node,
+    /// Edges walked from the nearest seed (0 for the seeds themselves)
+    pub hops: usize,
+}
+
+/// Nodes and edges around a set of seed nodes
+#[derive(Debug, Clone, Default)]
+pub struct Neighborhood {
+    /// Reached nodes, nearest first
+    pub nodes: Vec<Neighbor>,
+    /// Edges between reached nodes
+    pub edges: Vec<Edge>,
+}
+
+impl Neighborhood {
+    /// Paths of the reached files other than the seeds, nearest first
+    ///
+    /// Functions and chunks count for the file they belong to, so expanding
+    /// a search hit yields the files worth reading next.
+    pub fn files(&self) -> Vec<&str> {
+        let seeds: HashSet<&str> = self.nodes.iter()
+            .filter(|n| n.hops == 0)
+            .filter_map(|n| n.node.file.as_deref())
+            .collect();
+        let mut seen = HashSet::new();
+        self.nodes.iter()
+            .filter_map(|n| n.node.file.as_deref())
+            .filter(|path| !seeds.contains(path) && seen.insert(*path))
+            .collect()
+    }
+}
+
+/// Nodes and typed edges of an archive
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgeGraph {
+    nodes: BTreeMap<String, Node>,
+    edges: BTreeSet<Edge>,
+}
+
+impl KnowledgeGraph {
+    /// Empty graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a node, replacing a node with the same id
+    pub fn add_node(&mut self, node: Node) {
+        self.nodes.insert(node.id.clone(), node);
+    }
+
+    /// Add an edge between two node ids (duplicates are ignored)
+    ///
+    /// Edges to ids without a node are dropped when the graph is stored.
+    pub fn add_edge(&mut self, from: impl Into<String>, to: impl Into<String>, kind: EdgeKind) {
+        let (from, to) = (from.into(), to.into());
+        if from != to {
+            self.edges.insert(Edge { from, to, kind });
+        }
+    }
+
+    /// Node by id
+    pub fn node(&self, id: &str) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    /// All nodes, ordered by id
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.values()
+    }
+
+    /// All edges
+    pub fn edges(&self) -> impl Iterator<Item = &Edge> {
+        self.edges.iter()
+    }
+
+    /// Whether the graph has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Number of nodes
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of edges
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Edges touching a node, in either direction
+    pub fn edges_of<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Edge> + 'a {
+        self.edges.iter().filter(move |e| e.from == id || e.to == id)
+    }
+
+    /// Nodes within `hops` edges of the seed ids, following edges both ways
+    pub fn neighborhood(&self, seeds: &[&str], hops: usize) -> Neighborhood {
+        self.neighborhood_via(seeds, hops, &[])
+    }
+
+    /// Like `neighborhood()`, following only edges of the given kinds (all if empty)
+    pub fn neighborhood_via(&self, seeds: &[&str], hops: usize, kinds: &[EdgeKind]) -> Neighborhood {
+        let mut adjacent: HashMap<&str, Vec<&str>> = HashMap::new();
+        for edge in self.edges.iter().filter(|e| kinds.is_empty() || kinds.contains(&e.kind)) {
+            adjacent.entry(&edge.from).or_default().push(&edge.to);
+            adjacent.entry(&edge.to).or_default().push(&edge.from);
+        }
+
+        let mut distance: HashMap<&str, usize> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for seed in seeds {
+            if let Some((id, _)) = self.nodes.get_key_value(*seed) {
+                if distance.insert(id.as_str(), 0).is_none() {
+                    queue.push_back(id.as_str());
+                }
+            }
+        }
+        let mut order = Vec::new();
+        while let Some(id) = queue.pop_front() {
+            let hop = distance[id];
+            order.push((id, hop));
+            if hop == hops {
+                continue;
+            }
+            for &next in adjacent.get(id).into_iter().flatten() {
+                if self.nodes.contains_key(next) && !distance.contains_key(next) {
+                    distance.insert(next, hop + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let edges = self.edges.iter()
+            .filter(|e| kinds.is_empty() || kinds.contains(&e.kind))
+            .filter(|e| distance.contains_key(e.from.as_str()) && distance.contains_key(e.to.as_str()))
+            .cloned()
+            .collect();
+        let nodes = order.into_iter()
+            .map(|(id, hops)| Neighbor { node: self.nodes[id].clone(), hops })
+            .collect();
+        Neighborhood { nodes, edges }
+    }
+
+    /// Neighborhood of archived files (e.g. the paths of search hits)
+    pub fn around_files(&self, paths: &[&str], hops: usize) -> Neighborhood {
+        let ids: Vec<String> = paths.iter().map(|path| Node::file_id(path)).collect();
+        let seeds: Vec<&str> = ids.iter().map(String::as_str).collect();
+        self.neighborhood(&seeds, hops)
+    }
+
+    /// Drop edges whose ends are not nodes
+    pub fn prune(&mut self) {
+        let nodes = &self.nodes;
+        self.edges.retain(|e| nodes.contains_key(&e.from) && nodes.contains_key(&e.to));
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+}
+
+/// Adds nodes and edges for the archived text files
+///
+/// Runs once at build time, after every file has a [`NodeKind::File`] node.
+pub trait GraphPlugin: Send + Sync {
+    /// Short name used in logs
+    fn name(&self) -> &str;
+
+    /// Populate the graph from (path, content) of the archived text files
+    fn populate(&self, files: &[(&str, &str)], graph: &mut KnowledgeGraph) -> Result<()>;
+}
+
+/// File-to-file import edges
+///
+/// Only imports that resolve to an archived file become edges; packages
+/// from outside the archive are skipped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportGraph;
+
+impl GraphPlugin for ImportGraph {
+    fn name(&self) -> &str {
+        "imports"
+    }
+
+    fn populate(&self, files: &[(&str, &str)], graph: &mut KnowledgeGraph) -> Result<()> {
+        let paths: HashSet<&str> = files.iter().map(|(path, _)| *path).collect();
+        for (path, content) in files {
+            for target in imports(path, content, &paths) {
+                graph.add_edge(Node::file_id(path), Node::file_id(&target), EdgeKind::Imports);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Function definitions and the files referencing them
+///
+/// A file references a function when it uses its name and does not define
+/// a function of that name itself. Short or widely defined names (`new`,
+/// `main`) are not linked.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SymbolGraph;
+
+impl GraphPlugin for SymbolGraph {
+    fn name(&self) -> &str {
+        "symbols"
+    }
+
+    fn populate(&self, files: &[(&str, &str)], graph: &mut KnowledgeGraph) -> Result<()> {
+        let mut defined: HashMap<String, Vec<&str>> = HashMap::new();
+        for (path, content) in files {
+            let functions = symbols::definitions(path, content).into_iter().filter(|(_, _, kind)| *kind == SymbolKind::Function);
+            for (line, name, _) in functions {
+                graph.add_node(Node::function(path, &name, line));
+                graph.add_edge(Node::file_id(path), Node::function_id(path, &name), EdgeKind::Defines);
+                defined.entry(name).or_default().push(path);
+            }
+        }
+        defined.retain(|name, paths| name.len() >= MIN_SYMBOL_LEN && paths.len() <= MAX_SYMBOL_DEFINITIONS);
+
+        for (path, content) in files {
+            let words: HashSet<&str> = content
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .collect();
+            for word in words {
+                let Some(defined_in) = defined.get(word) else {
+                    continue;
+                };
+                if defined_in.contains(path) {
+                    continue;
+                }
+                for target in defined_in {
+                    graph.add_edge(Node::file_id(path), Node::function_id(target, word), EdgeKind::References);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Extension marker used to register the graph namespace
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GraphExtension;
+
+impl Extension for GraphExtension {
+    fn namespace(&self) -> &str {
+        GRAPH_NAMESPACE
+    }
+
+    fn version(&self) -> &str {
+        GRAPH_VERSION
+    }
+}
+
+/// Archived files imported by a file
+fn imports(path: &str, content: &str, paths: &HashSet<&str>) -> BTreeSet<String> {
+    let extension = path.rsplit_once('.').map_or("", |(_, ext)| ext);
+    let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    let mut found = BTreeSet::new();
+    for line in content.lines().map(str::trim) {
+        let candidates = match extension {
+            "rs" => rust_import(path, line, paths),
+            "py" => python_import(dir, line, paths),
+            "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => script_import(dir, line, paths),
+            "c" | "h" | "cc" | "cpp" | "hpp" => include(dir, line, paths),
+            _ => None,
+        };
+        if let Some(target) = candidates {
+            if target != path {
+                found.insert(target);
+            }
+        }
+    }
+    found
+}
+
+/// Target of a Rust `mod name;` or `use crate::/super::/self::` line
+fn rust_import(path: &str, line: &str, paths: &HashSet<&str>) -> Option<String> {
+    let line = strip_visibility(line);
+    if let Some(name) = line.strip_prefix("mod ").and_then(|rest| rest.strip_suffix(';')) {
+        let dir = rust_module_dir(path);
+        return first_existing(paths, [join(&dir, &format!("{}.rs", name)), join(&dir, &format!("{}/mod.rs", name))]);
+    }
+
+    let used = line.strip_prefix("use ")?;
+    let mut segments: Vec<&str> = used
+        .split("::")
+        .map(|s| s.trim_end_matches(';'))
+        .take_while(|s| !s.starts_with('{') && !s.contains(' ') && *s != "*")
+        .collect();
+    let mut dir = match segments.first().copied()? {
+        "crate" => rust_crate_root(path, paths)?,
+        "self" => rust_module_dir(path),
+        "super" => parent_dir(&rust_module_dir(path)),
+        _ => return None,
+    };
+    segments.remove(0);
+    while segments.first() == Some(&"super") {
+        dir = parent_dir(&dir);
+        segments.remove(0);
+    }
+
+    // Deepest module file that exists (the last segments may be items)
+    let mut target = None;
+    for segment in segments {
+        dir = join(&dir, segment);
+        match first_existing(paths, [format!("{}.rs", dir), join(&dir, "mod.rs")]) {
+            Some(file) => target = Some(file),
+            None => break,
+        }
+    }
+    target
+}
+
+/// Directory holding the submodules of a Rust file
+fn rust_module_dir(path: &str) -> String {
+    let (dir, file) = path.rsplit_once('/').unwrap_or(("", path));
+    match file {
+        "mod.rs" | "lib.rs" | "main.rs" => dir.to_string(),
+        _ => join(dir, file.trim_end_matches(".rs")),
+    }
+}
+
+/// Directory of the nearest `lib.rs` / `main.rs` above a Rust file
+fn rust_crate_root(path: &str, paths: &HashSet<&str>) -> Option<String> {
+    let mut dir = path.rsplit_once('/').map_or(String::new(), |(dir, _)| dir.to_string());
+    loop {
+        if paths.contains(join(&dir, "lib.rs").as_str()) || paths.contains(join(&dir, "main.rs").as_str()) {
+            return Some(dir);
+        }
+        if dir.is_empty() {
+            return None;
+        }
+        dir = parent_dir(&dir);
+    }
+}
+
+/// Target of a Python `import a.b` or `from .a import b` line
+fn python_import(dir: &str, line: &str, paths: &HashSet<&str>) -> Option<String> {
+    let module = if let Some(rest) = line.strip_prefix("from ") {
+        rest.split_whitespace().next()?
+    } else {
+        line.strip_prefix("import ")?.split([',', ' ']).next()?
+    };
+
+    let dots = module.chars().take_while(|&c| c == '.').count();
+    let relative = module[dots..].replace('.', "/");
+    if dots > 0 {
+        let mut base = dir.to_string();
+        for _ in 1..dots {
+            base = parent_dir(&base);
+        }
+        let base = join(&base, &relative);
+        return first_existing(paths, [format!("{}.py", base), join(&base, "__init__.py")]);
+    }
+
+    // Absolute modules resolve against any package root in the archive
+    let suffixes = [format!("{}.py", relative), format!("{}/__init__.py", relative)];
+    suffixes.iter().find_map(|suffix| unique_suffix_match(paths, suffix))
+}
+
+/// Target of a relative JS/TS `import`, `export ... from` or `require()`
+fn script_import(dir: &str, line: &str, paths: &HashSet<&str>) -> Option<String> {
+    if !(line.starts_with("import") || line.starts_with("export") || line.contains("require(")) {
+        return None;
+    }
+    let specifier = quoted(line)?;
+    if !specifier.starts_with('.') {
+        return None;
+    }
+
+    let base = normalize(&join(dir, specifier))?;
+    let mut candidates = vec![base.clone()];
+    for ext in SCRIPT_EXTENSIONS {
+        candidates.push(format!("{}.{}", base, ext));
+    }
+    for ext in SCRIPT_EXTENSIONS {
+        candidates.push(join(&base, &format!("index.{}", ext)));
+    }
+    first_existing(paths, candidates)
+}
+
+/// Target of a C/C++ `#include "header"`
+fn include(dir: &str, line: &str, paths: &HashSet<&str>) -> Option<String> {
+    let header = line.strip_prefix("#include")?.trim().strip_prefix('"')?.split('"').next()?;
+    normalize(&join(dir, header))
+        .filter(|path| paths.contains(path.as_str()))
+        .or_else(|| unique_suffix_match(paths, header))
+}
+
+/// Contents of the first '...' or "..." string of a line
+fn quoted(line: &str) -> Option<&str> {
+    let start = line.find(['\'', '"'])?;
+    let quote = line[start..].chars().next()?;
+    let rest = &line[start + 1..];
+    rest.find(quote).map(|end| &rest[..end])
+}
+
+/// The only archived path ending in `/<suffix>` (or equal to it)
+fn unique_suffix_match(paths: &HashSet<&str>, suffix: &str) -> Option<String> {
+    let slash_suffix = format!("/{}", suffix);
+    let mut matches = paths.iter().filter(|p| **p == suffix || p.ends_with(&slash_suffix));
+    let first = matches.next()?;
+    matches.next().is_none().then(|| first.to_string())
+}
+
+/// First candidate that is an archived path
+fn first_existing(paths: &HashSet<&str>, candidates: impl IntoIterator<Item = String>) -> Option<String> {
+    candidates.into_iter().find(|c| paths.contains(c.as_str()))
+}
+
+/// `dir/name` (just `name` at the archive root)
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() { name.to_string() } else { format!("{}/{}", dir, name) }
+}
+
+/// Parent of a directory (`""` at the archive root)
+fn parent_dir(dir: &str) -> String {
+    dir.rsplit_once('/').map_or(String::new(), |(parent, _)| parent.to_string())
+}
+
+/// Resolve `.` and `..` components (None if the path leaves the archive)
+fn normalize(path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            _ => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_graph() -> KnowledgeGraph {
+        let files = [
+            ("src/lib.rs", "pub mod parser;\nmod cache;\n"),
+            ("src/parser.rs", "use crate::cache::Cache;\n\npub fn parse_header(input: &str) {}\n"),
+            ("src/cache.rs", "pub struct Cache;\n\npub(crate) fn evict_entries() {}\n"),
+            ("src/main.rs", "fn main() { cxp::parser::parse_header(\"\"); }\n"),
+            ("web/app.ts", "import { api } from './lib/api';\nconst x = require(\"../vendor/x\");\n"),
+            ("web/lib/api.ts", "export async function fetchAll() {}\n"),
+            ("tools/run.py", "from .util import helper\nimport os\n"),
+            ("tools/util.py", "def helper():\n    pass\n"),
+        ];
+        let mut graph = KnowledgeGraph::new();
+        for (path, _) in &files {
+            graph.add_node(Node::file(path));
+        }
+        ImportGraph.populate(&files, &mut graph).unwrap();
+        SymbolGraph.populate(&files, &mut graph).unwrap();
+        graph.prune();
+        graph
+    }
+
+    #[test]
+    fn test_plugins_link_imports_and_symbols() {
+        let graph = sample_graph();
+        let has = |from: &str, to: &str, kind| graph.edges().any(|e| e.from == from && e.to == to && e.kind == kind);
+
+        assert!(has("file:src/lib.rs", "file:src/parser.rs", EdgeKind::Imports));
+        assert!(has("file:src/lib.rs", "file:src/cache.rs", EdgeKind::Imports));
+        assert!(has("file:src/parser.rs", "file:src/cache.rs", EdgeKind::Imports));
+        assert!(has("file:web/app.ts", "file:web/lib/api.ts", EdgeKind::Imports));
+        assert!(has("file:tools/run.py", "file:tools/util.py", EdgeKind::Imports));
+        assert!(has("file:src/main.rs", "fn:src/parser.rs#parse_header", EdgeKind::References));
+        assert!(has("file:src/cache.rs", "fn:src/cache.rs#evict_entries", EdgeKind::Defines));
+        assert_eq!(graph.node("fn:web/lib/api.ts#fetchAll").unwrap().line, Some(1));
+        assert!(graph.node("fn:src/main.rs#main").is_some());
+
+        let restored = KnowledgeGraph::from_msgpack(&graph.to_msgpack().unwrap()).unwrap();
+        assert_eq!(restored, graph);
+    }
+
+    #[test]
+    fn test_neighborhood_expands_around_files() {
+        let graph = sample_graph();
+
+        let around = graph.around_files(&["src/main.rs"], 1);
+        assert_eq!(around.nodes[0].node.id, "file:src/main.rs");
+        assert_eq!(around.files(), vec!["src/parser.rs"]);
+        let wider = graph.around_files(&["src/main.rs"], 3);
+        let mut files = wider.files();
+        files.sort();
+        assert_eq!(files, vec!["src/cache.rs", "src/lib.rs", "src/parser.rs"]);
+
+        let imports = graph.neighborhood_via(&["file:src/parser.rs"], 1, &[EdgeKind::Imports]);
+        let mut files = imports.files();
+        files.sort();
+        assert_eq!(files, vec!["src/cache.rs", "src/lib.rs"]);
+        assert!(imports.edges.iter().all(|e| e.kind == EdgeKind::Imports));
+        assert!(graph.around_files(&["missing.rs"], 3).nodes.is_empty());
+    }
+}
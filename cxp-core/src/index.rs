@@ -8,6 +8,7 @@
 //! - Persistent index with save/load
 //! - Support for binary and float32 embeddings
 //! - Integration with CXP embedding types
+//! - IVF-Flat alternative (`IvfFlatIndex`) for archives too large for an HNSW graph in RAM
 
 #[cfg(feature = "search")]
 use crate::{CxpError, Result};
@@ -15,6 +16,9 @@ use crate::{CxpError, Result};
 #[cfg(all(feature = "search", feature = "embeddings"))]
 use crate::{BinaryEmbedding, Int8Embedding};
 
+#[cfg(feature = "search")]
+use crate::{IndexBackend, IndexParams};
+
 #[cfg(feature = "search")]
 use std::path::Path;
 
@@ -123,6 +127,14 @@ impl HnswConfig {
         }
     }
 
+    /// Use the graph parameters of `params` (M, ef_construction, ef_search)
+    pub fn with_params(mut self, params: &IndexParams) -> Self {
+        self.connectivity = params.m;
+        self.expansion_add = params.ef_construction;
+        self.expansion_search = params.ef_search;
+        self
+    }
+
     /// Create config for 512-dimensional multimodal embeddings with float32 cosine similarity
     #[cfg(feature = "multimodal")]
     pub fn multimodal_float32() -> Self {
@@ -177,11 +189,10 @@ impl HnswIndex {
     /// Add a float32 vector to the index
     pub fn add_f32(&mut self, id: u64, vector: &[f32]) -> Result<()> {
         if vector.len() != self.config.dimensions {
-            return Err(CxpError::Search(format!(
-                "Vector dimension mismatch: expected {}, got {}",
-                self.config.dimensions,
-                vector.len()
-            )));
+            return Err(CxpError::IndexDimensionMismatch {
+                expected: self.config.dimensions,
+                got: vector.len(),
+            });
         }
 
         self.index
@@ -261,11 +272,10 @@ impl HnswIndex {
     /// Search for k nearest neighbors of a float32 vector
     pub fn search_f32(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
         if query.len() != self.config.dimensions {
-            return Err(CxpError::Search(format!(
-                "Query dimension mismatch: expected {}, got {}",
-                self.config.dimensions,
-                query.len()
-            )));
+            return Err(CxpError::IndexDimensionMismatch {
+                expected: self.config.dimensions,
+                got: query.len(),
+            });
         }
 
         let results = self
@@ -342,6 +352,15 @@ impl HnswIndex {
         Ok(())
     }
 
+    /// Serialize the index to bytes (same format as [`save`](Self::save))
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buffer = vec![0u8; self.index.serialized_length()];
+        self.index
+            .save_to_buffer(&mut buffer)
+            .map_err(|e| CxpError::Search(format!("Failed to save index: {}", e)))?;
+        Ok(buffer)
+    }
+
     /// Load index from disk
     pub fn load<P: AsRef<Path>>(path: P, config: HnswConfig) -> Result<Self> {
         let path_str = path
@@ -349,6 +368,28 @@ impl HnswIndex {
             .to_str()
             .ok_or_else(|| CxpError::Search("Invalid path".to_string()))?;
 
+        let loaded = Self::empty_for_load(config)?;
+        loaded
+            .index
+            .load(path_str)
+            .map_err(|e| CxpError::Search(format!("Failed to load index: {}", e)))?;
+        Ok(loaded)
+    }
+
+    /// Load an index serialized by [`to_bytes`](Self::to_bytes) or [`save`](Self::save)
+    ///
+    /// Needs no filesystem, unlike [`load`](Self::load).
+    pub fn from_bytes(data: &[u8], config: HnswConfig) -> Result<Self> {
+        let loaded = Self::empty_for_load(config)?;
+        loaded
+            .index
+            .load_from_buffer(data)
+            .map_err(|e| CxpError::Search(format!("Failed to load index: {}", e)))?;
+        Ok(loaded)
+    }
+
+    /// Empty index with the options a serialized index is loaded into
+    fn empty_for_load(config: HnswConfig) -> Result<Self> {
         let scalar_kind = match config.metric {
             DistanceMetric::Hamming => ScalarKind::B1,
             _ => ScalarKind::F32,
@@ -365,10 +406,6 @@ impl HnswIndex {
         })
         .map_err(|e| CxpError::Search(format!("Failed to create index: {}", e)))?;
 
-        index
-            .load(path_str)
-            .map_err(|e| CxpError::Search(format!("Failed to load index: {}", e)))?;
-
         Ok(Self {
             index,
             config,
@@ -429,6 +466,409 @@ impl HnswIndex {
     }
 }
 
+/// Magic bytes at the start of a serialized IVF-Flat index
+#[cfg(feature = "search")]
+const IVF_MAGIC: &[u8; 4] = b"CIVF";
+
+/// Training vectors sampled per centroid (caps k-means cost on large archives)
+#[cfg(feature = "search")]
+const IVF_TRAINING_PER_LIST: usize = 64;
+
+/// Maximum k-means rounds while training centroids
+#[cfg(feature = "search")]
+const IVF_TRAINING_ROUNDS: usize = 10;
+
+/// Inverted-file index over binary vectors (IVF-Flat)
+///
+/// Vectors are filed under the nearest of `nlist` centroids, trained with
+/// binary k-means (bitwise majority per cluster). A query scans the lists of
+/// its `nprobe` nearest centroids with exact Hamming distances. There is no
+/// graph, so memory is the vectors plus 8 bytes per id - a fraction of HNSW,
+/// for somewhat slower queries and recall that depends on `nprobe`.
+#[cfg(feature = "search")]
+pub struct IvfFlatIndex {
+    /// Bytes per vector
+    dimensions: usize,
+    /// Lists scanned per query
+    nprobe: usize,
+    /// Cluster centroids, `dimensions` bytes each
+    centroids: Vec<Vec<u8>>,
+    /// Vectors filed under each centroid
+    lists: Vec<IvfList>,
+}
+
+/// Ids and packed vectors of one IVF cluster
+#[cfg(feature = "search")]
+#[derive(Default)]
+struct IvfList {
+    ids: Vec<u64>,
+    codes: Vec<u8>,
+}
+
+#[cfg(feature = "search")]
+impl IvfFlatIndex {
+    /// Train `nlist` centroids on `training` vectors of `dimensions` bits
+    ///
+    /// `nlist` (and `nprobe` with it) is capped at the number of training
+    /// vectors. The index is empty; vectors are added with `add_binary()`.
+    pub fn train(dimensions: usize, nlist: usize, nprobe: usize, training: &[&[u8]]) -> Result<Self> {
+        use rayon::prelude::*;
+
+        let dimensions = dimensions.div_ceil(8);
+        if dimensions == 0 {
+            return Err(CxpError::Index("IVF-Flat index needs at least one dimension".to_string()));
+        }
+        if let Some(bad) = training.iter().find(|v| v.len() != dimensions) {
+            return Err(CxpError::IndexDimensionMismatch { expected: dimensions, got: bad.len() });
+        }
+
+        let step = training.len().div_ceil(nlist.max(1) * IVF_TRAINING_PER_LIST).max(1);
+        let sample: Vec<&[u8]> = training.iter().step_by(step).copied().collect();
+        let nlist = nlist.min(sample.len());
+
+        // Seed with evenly spaced samples, then refine by bitwise majority
+        let mut centroids: Vec<Vec<u8>> = (0..nlist).map(|i| sample[i * sample.len() / nlist].to_vec()).collect();
+        let mut assignment = vec![usize::MAX; sample.len()];
+        for _ in 0..IVF_TRAINING_ROUNDS {
+            let next: Vec<usize> = sample.par_iter().map(|v| nearest(&centroids, v)).collect();
+            if next == assignment {
+                break;
+            }
+            assignment = next;
+
+            let mut ones = vec![vec![0u32; dimensions * 8]; nlist];
+            let mut sizes = vec![0u32; nlist];
+            for (vector, &list) in sample.iter().zip(&assignment) {
+                sizes[list] += 1;
+                for (bit, count) in ones[list].iter_mut().enumerate() {
+                    *count += u32::from((vector[bit / 8] >> (bit % 8)) & 1);
+                }
+            }
+            for ((centroid, ones), size) in centroids.iter_mut().zip(&ones).zip(sizes) {
+                // Empty clusters keep their previous centroid
+                if size == 0 {
+                    continue;
+                }
+                centroid.iter_mut().for_each(|byte| *byte = 0);
+                for (bit, &count) in ones.iter().enumerate() {
+                    if count * 2 > size {
+                        centroid[bit / 8] |= 1 << (bit % 8);
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            dimensions,
+            nprobe: nprobe.clamp(1, nlist.max(1)),
+            lists: (0..nlist).map(|_| IvfList::default()).collect(),
+            centroids,
+        })
+    }
+
+    /// Add a binary vector (as bytes) to the list of its nearest centroid
+    pub fn add_binary(&mut self, id: u64, bits: &[u8]) -> Result<()> {
+        self.check_dimensions(bits)?;
+        if self.centroids.is_empty() {
+            return Err(CxpError::Index("IVF-Flat index was trained without vectors".to_string()));
+        }
+        let list = &mut self.lists[nearest(&self.centroids, bits)];
+        list.ids.push(id);
+        list.codes.extend_from_slice(bits);
+        Ok(())
+    }
+
+    /// Remove a vector by ID (no-op if it is not in the index)
+    pub fn remove(&mut self, id: u64) -> Result<()> {
+        for list in &mut self.lists {
+            if let Some(pos) = list.ids.iter().position(|&other| other == id) {
+                list.ids.swap_remove(pos);
+                let last = list.ids.len() * self.dimensions;
+                let start = pos * self.dimensions;
+                if start != last {
+                    list.codes.copy_within(last..last + self.dimensions, start);
+                }
+                list.codes.truncate(last);
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Search for the k nearest neighbors of a binary vector (distance: differing bits)
+    pub fn search_binary(&self, bits: &[u8], k: usize) -> Result<Vec<SearchResult>> {
+        self.check_dimensions(bits)?;
+
+        let mut probes: Vec<(u32, usize)> = self
+            .centroids
+            .iter()
+            .enumerate()
+            .map(|(list, centroid)| (crate::simd::hamming(bits, centroid), list))
+            .collect();
+        probes.sort_unstable();
+
+        let mut results: Vec<(u32, u64)> = Vec::new();
+        for &(_, list) in probes.iter().take(self.nprobe) {
+            let list = &self.lists[list];
+            results.extend(
+                list.codes
+                    .chunks_exact(self.dimensions)
+                    .zip(&list.ids)
+                    .map(|(code, &id)| (crate::simd::hamming(bits, code), id)),
+            );
+        }
+        if results.len() > k {
+            results.select_nth_unstable(k);
+            results.truncate(k);
+        }
+        results.sort_unstable();
+
+        Ok(results
+            .into_iter()
+            .map(|(distance, id)| SearchResult { id, distance: distance as f32 })
+            .collect())
+    }
+
+    /// Serialize the index to bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(16 + self.centroids.len() * self.dimensions + self.len() * (8 + self.dimensions));
+        data.extend_from_slice(IVF_MAGIC);
+        for value in [self.dimensions, self.nprobe, self.centroids.len()] {
+            data.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        for centroid in &self.centroids {
+            data.extend_from_slice(centroid);
+        }
+        for list in &self.lists {
+            data.extend_from_slice(&(list.ids.len() as u32).to_le_bytes());
+            for id in &list.ids {
+                data.extend_from_slice(&id.to_le_bytes());
+            }
+            data.extend_from_slice(&list.codes);
+        }
+        data
+    }
+
+    /// Load an index serialized by [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut data = data
+            .strip_prefix(IVF_MAGIC.as_slice())
+            .ok_or_else(|| CxpError::Index("Invalid IVF-Flat index: bad magic".to_string()))?;
+        let dimensions = take_u32(&mut data)?;
+        let nprobe = take_u32(&mut data)?;
+        let nlist = take_u32(&mut data)?;
+        if dimensions == 0 {
+            return Err(CxpError::Index("Invalid IVF-Flat index: zero dimensions".to_string()));
+        }
+
+        // Sizes come from the file: check them against the bytes left before allocating
+        let invalid = |what: &str| CxpError::InvalidFormat(format!("Invalid IVF-Flat index: {}", what));
+        if nlist.checked_mul(dimensions).is_none_or(|len| len > data.len()) {
+            return Err(invalid("more centroids than data"));
+        }
+        let centroids = (0..nlist)
+            .map(|_| take(&mut data, dimensions).map(<[u8]>::to_vec))
+            .collect::<Result<Vec<_>>>()?;
+        let mut lists = Vec::with_capacity(nlist.min(data.len() / 4));
+        for _ in 0..nlist {
+            let count = take_u32(&mut data)?;
+            let ids_len = count.checked_mul(8).ok_or_else(|| invalid("list size overflows"))?;
+            let ids = take(&mut data, ids_len)?
+                .chunks_exact(8)
+                .map(|id| u64::from_le_bytes(id.try_into().expect("8 bytes")))
+                .collect();
+            let codes_len = count.checked_mul(dimensions).ok_or_else(|| invalid("list size overflows"))?;
+            let codes = take(&mut data, codes_len)?.to_vec();
+            lists.push(IvfList { ids, codes });
+        }
+        if !data.is_empty() {
+            return Err(CxpError::Index("Invalid IVF-Flat index: trailing data".to_string()));
+        }
+
+        Ok(Self {
+            dimensions,
+            nprobe: nprobe.clamp(1, nlist.max(1)),
+            centroids,
+            lists,
+        })
+    }
+
+    /// Number of vectors in the index
+    pub fn len(&self) -> usize {
+        self.lists.iter().map(|list| list.ids.len()).sum()
+    }
+
+    /// Check if the index is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of clusters (nlist)
+    pub fn nlist(&self) -> usize {
+        self.centroids.len()
+    }
+
+    /// Lists scanned per query (nprobe)
+    pub fn nprobe(&self) -> usize {
+        self.nprobe
+    }
+
+    /// Set the lists scanned per query (capped at nlist)
+    /// Higher values = better recall, slower search
+    pub fn set_nprobe(&mut self, nprobe: usize) {
+        self.nprobe = nprobe.clamp(1, self.nlist().max(1));
+    }
+
+    fn check_dimensions(&self, bits: &[u8]) -> Result<()> {
+        if bits.len() != self.dimensions {
+            return Err(CxpError::IndexDimensionMismatch { expected: self.dimensions, got: bits.len() });
+        }
+        Ok(())
+    }
+}
+
+/// Split `len` bytes off the front of a serialized IVF-Flat index
+#[cfg(feature = "search")]
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if data.len() < len {
+        return Err(CxpError::Index("Invalid IVF-Flat index: truncated".to_string()));
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Ok(head)
+}
+
+/// Read a little-endian u32 off the front of a serialized IVF-Flat index
+#[cfg(feature = "search")]
+fn take_u32(data: &mut &[u8]) -> Result<usize> {
+    Ok(u32::from_le_bytes(take(data, 4)?.try_into().expect("4 bytes")) as usize)
+}
+
+/// Position of the centroid closest to `vector` (lowest position on ties)
+#[cfg(feature = "search")]
+fn nearest(centroids: &[Vec<u8>], vector: &[u8]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .min_by_key(|(list, centroid)| (crate::simd::hamming(vector, centroid), *list))
+        .map_or(0, |(list, _)| list)
+}
+
+/// Index over binary text embeddings, with either backend
+///
+/// Archives record the backend in `Manifest::index_backend` and store the
+/// index at `IndexBackend::path()`.
+#[cfg(feature = "search")]
+pub enum VectorIndex {
+    /// HNSW graph
+    Hnsw(HnswIndex),
+    /// Inverted file with flat lists
+    IvfFlat(IvfFlatIndex),
+}
+
+#[cfg(feature = "search")]
+impl VectorIndex {
+    /// Empty index for `dimensions`-bit binary embeddings
+    ///
+    /// IVF-Flat centroids are trained on `training`; HNSW ignores it.
+    #[cfg(feature = "embeddings")]
+    pub fn binary(
+        dimensions: usize,
+        backend: IndexBackend,
+        params: &IndexParams,
+        training: &[BinaryEmbedding],
+    ) -> Result<Self> {
+        match backend {
+            IndexBackend::Hnsw => Ok(Self::Hnsw(HnswIndex::new(HnswConfig::binary(dimensions).with_params(params))?)),
+            IndexBackend::IvfFlat { nlist, nprobe } => {
+                let training: Vec<&[u8]> = training.iter().map(|e| e.bits.as_slice()).collect();
+                Ok(Self::IvfFlat(IvfFlatIndex::train(dimensions, nlist, nprobe, &training)?))
+            }
+        }
+    }
+
+    /// Index over `embeddings`, each added with its position as ID
+    #[cfg(feature = "embeddings")]
+    pub fn from_embeddings(
+        dimensions: usize,
+        backend: IndexBackend,
+        params: &IndexParams,
+        embeddings: &[BinaryEmbedding],
+    ) -> Result<Self> {
+        let mut index = Self::binary(dimensions, backend, params, embeddings)?;
+        for (id, embedding) in embeddings.iter().enumerate() {
+            index.add_binary_embedding(id as u64, embedding)?;
+        }
+        Ok(index)
+    }
+
+    /// Load an index over `dimensions`-bit binary embeddings serialized by [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(data: &[u8], dimensions: usize, backend: IndexBackend, params: &IndexParams) -> Result<Self> {
+        match backend {
+            IndexBackend::Hnsw => Ok(Self::Hnsw(HnswIndex::from_bytes(
+                data,
+                HnswConfig::binary(dimensions).with_params(params),
+            )?)),
+            IndexBackend::IvfFlat { .. } => Ok(Self::IvfFlat(IvfFlatIndex::from_bytes(data)?)),
+        }
+    }
+
+    /// Serialize the index to bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::Hnsw(index) => index.to_bytes(),
+            Self::IvfFlat(index) => Ok(index.to_bytes()),
+        }
+    }
+
+    /// Backend of this index (IVF-Flat with the trained nlist)
+    pub fn backend(&self) -> IndexBackend {
+        match self {
+            Self::Hnsw(_) => IndexBackend::Hnsw,
+            Self::IvfFlat(index) => IndexBackend::IvfFlat { nlist: index.nlist(), nprobe: index.nprobe() },
+        }
+    }
+
+    /// Add a BinaryEmbedding to the index
+    #[cfg(feature = "embeddings")]
+    pub fn add_binary_embedding(&mut self, id: u64, embedding: &BinaryEmbedding) -> Result<()> {
+        match self {
+            Self::Hnsw(index) => index.add_binary_embedding(id, embedding),
+            Self::IvfFlat(index) => index.add_binary(id, &embedding.bits),
+        }
+    }
+
+    /// Search using a BinaryEmbedding
+    #[cfg(feature = "embeddings")]
+    pub fn search_binary_embedding(&self, embedding: &BinaryEmbedding, k: usize) -> Result<Vec<SearchResult>> {
+        match self {
+            Self::Hnsw(index) => index.search_binary_embedding(embedding, k),
+            Self::IvfFlat(index) => index.search_binary(&embedding.bits, k),
+        }
+    }
+
+    /// Remove a vector by ID
+    pub fn remove(&mut self, id: u64) -> Result<()> {
+        match self {
+            Self::Hnsw(index) => index.remove(id),
+            Self::IvfFlat(index) => index.remove(id),
+        }
+    }
+
+    /// Get the number of vectors in the index
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Hnsw(index) => index.len(),
+            Self::IvfFlat(index) => index.len(),
+        }
+    }
+
+    /// Check if the index is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 /// Search result containing ID and distance
 #[derive(Debug, Clone, PartialEq)]
 pub struct SearchResult {
@@ -593,6 +1033,60 @@ mod tests {
 
         // Try to add vector with wrong dimensions
         let result = index.add_f32(1, &[1.0, 0.0, 0.0]);
-        assert!(result.is_err());
+        assert!(matches!(result, Err(CxpError::IndexDimensionMismatch { expected: 4, got: 3 })));
+    }
+
+    #[test]
+    fn test_ivf_flat() {
+        // Two clusters of 16-bit vectors around 0x0000 and 0xFFFF
+        let vectors: Vec<[u8; 2]> = (0..40u8)
+            .map(|i| if i % 2 == 0 { [1 << (i % 8), 0] } else { [!(1 << (i % 8)), 0xFF] })
+            .collect();
+        let training: Vec<&[u8]> = vectors.iter().map(|v| v.as_slice()).collect();
+        let mut index = IvfFlatIndex::train(16, 2, 1, &training).unwrap();
+        for (id, vector) in vectors.iter().enumerate() {
+            index.add_binary(id as u64, vector).unwrap();
+        }
+        assert_eq!((index.len(), index.nlist(), index.nprobe()), (40, 2, 1));
+
+        // One probe finds the exact match in the right cluster
+        let results = index.search_binary(&[!(1 << 3), 0xFF], 3).unwrap();
+        assert_eq!(results[0].id, 3);
+        assert_eq!(results[0].distance, 0.0);
+        assert!(results.iter().all(|r| r.id % 2 == 1));
+
+        let mut restored = IvfFlatIndex::from_bytes(&index.to_bytes()).unwrap();
+        assert_eq!(restored.len(), 40);
+        restored.remove(3).unwrap();
+        assert_eq!(restored.len(), 39);
+        assert_ne!(restored.search_binary(&[!(1 << 3), 0xFF], 1).unwrap()[0].id, 3);
+        assert_eq!(restored.search_binary(&[1 << 4, 0], 1).unwrap()[0].id, 4);
+        assert!(IvfFlatIndex::from_bytes(&index.to_bytes()[..20]).is_err());
+
+        // Oversized counts are rejected without allocating for them
+        let mut huge = IVF_MAGIC.to_vec();
+        for value in [2u32, 1, u32::MAX] {
+            huge.extend_from_slice(&value.to_le_bytes());
+        }
+        assert!(matches!(IvfFlatIndex::from_bytes(&huge), Err(CxpError::InvalidFormat(_))));
+        let mut huge_list = IVF_MAGIC.to_vec();
+        for value in [2u32, 1, 1] {
+            huge_list.extend_from_slice(&value.to_le_bytes());
+        }
+        huge_list.extend_from_slice(&[0, 0]);
+        huge_list.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(IvfFlatIndex::from_bytes(&huge_list).is_err());
+        assert!(matches!(index.add_binary(99, &[0]), Err(CxpError::IndexDimensionMismatch { expected: 2, got: 1 })));
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let mut index = HnswIndex::new(HnswConfig::binary(8)).unwrap();
+        index.add_binary(1, &[0b10101010]).unwrap();
+        index.add_binary(2, &[0b11110000]).unwrap();
+
+        let restored = HnswIndex::from_bytes(&index.to_bytes().unwrap(), HnswConfig::binary(8)).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored.search_binary(&[0b11110000], 1).unwrap()[0].id, 2);
     }
 }
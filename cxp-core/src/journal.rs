@@ -0,0 +1,350 @@
+//! Update Journal
+//!
+//! Rewriting a whole archive for every small change is slow, and a crash
+//! halfway through leaves a corrupt file. In journal mode
+//! (`CxpBuilder::append_journal()`) incremental updates are appended to
+//! `<file>.cxp.journal` next to the archive instead. Each record holds the
+//! changed file entries, the removed paths and the new chunks, framed with
+//! lengths and a checksum.
+//!
+//! `CxpReader::open()` replays the complete records on top of the archive. A
+//! record cut short by a crash fails its checks and is rolled back: readers
+//! skip it without touching the file, and the next [`append_record`]
+//! truncates the journal to the last complete record. Records written against
+//! an earlier version of the archive (see [`JournalRecord::base`]) are
+//! ignored.
+//!
+//! [`compact`] folds the journal into the archive; a full `build()` of the
+//! same archive deletes it. Journaled chunks get embeddings and other indices
+//! with the next full build.
+//!
+//! Frame layout:
+//! ```text
+//! "CXPJ" | header length (u32 LE) | data length (u64 LE) | checksum (8 bytes) | header | data
+//! ```
+//! The header is a MessagePack [`JournalRecord`], the data the stored
+//! (compressed) chunks back to back. The checksum is the start of the SHA-256
+//! of header and data.
+
+use crate::format::{FileEntry, FileMap};
+use crate::manifest::Manifest;
+use crate::optimize::{CxpOptimizer, OptimizeStats};
+use crate::temp::replace_file;
+use crate::{CxpError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Marks the start of a record
+const MAGIC: &[u8; 4] = b"CXPJ";
+
+/// Magic, lengths and checksum in front of each record
+const FRAME_HEADER_LEN: usize = 4 + 4 + 8 + 8;
+
+/// One journaled update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalRecord {
+    /// Version of the archive the record applies to (its manifest's `updated_at`)
+    pub base: String,
+    /// Position of the record in the journal, from 0
+    pub sequence: u64,
+    /// When the record was written
+    pub created_at: DateTime<Utc>,
+    /// New and changed files
+    pub files: Vec<FileEntry>,
+    /// Removed paths
+    pub removed: Vec<String>,
+    /// Hash and stored size of each chunk in the record's data, in order
+    pub chunks: Vec<(String, u64)>,
+}
+
+/// The complete records of an archive's journal, merged
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+    /// Records replayed
+    pub records: usize,
+    /// Bytes of an incomplete record that were skipped
+    pub rolled_back: u64,
+    /// Changed files by path (None: removed), the latest record wins
+    pub files: BTreeMap<String, Option<FileEntry>>,
+    /// Stored chunks by hash
+    pub chunks: HashMap<String, Vec<u8>>,
+}
+
+impl Journal {
+    /// Replay the journal of the archive at `archive_path`
+    ///
+    /// `base` identifies the archive as opened (see [`journal_base`]). An
+    /// incomplete last record is skipped; the file is left as it is.
+    pub fn open(archive_path: &Path, base: &str) -> Result<Self> {
+        let path = journal_path(archive_path);
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut journal = Self::default();
+        let mut offset = 0;
+        while let Some((record, chunk_data, len)) = parse_frame(&data[offset..]) {
+            offset += len;
+            if record.base != base {
+                tracing::warn!("Ignoring journal {:?}: it was written for an earlier version of the archive", path);
+                return Ok(Self::default());
+            }
+            journal.push(record, chunk_data);
+        }
+
+        if offset < data.len() {
+            journal.rolled_back = (data.len() - offset) as u64;
+            tracing::warn!(
+                "Skipping an incomplete journal record ({} bytes) in {:?}",
+                journal.rolled_back,
+                path
+            );
+        }
+        Ok(journal)
+    }
+
+    /// Whether no complete record was found
+    pub fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+
+    /// Apply the journaled changes to the archive's file map
+    pub fn apply(&self, file_map: &mut FileMap) {
+        for (path, entry) in &self.files {
+            match entry {
+                Some(entry) => file_map.files.insert(path.clone(), entry.clone()),
+                None => file_map.files.remove(path),
+            };
+        }
+    }
+
+    fn push(&mut self, record: JournalRecord, chunk_data: &[u8]) {
+        let mut offset = 0;
+        for (hash, len) in record.chunks {
+            let len = len as usize;
+            self.chunks.insert(hash, chunk_data[offset..offset + len].to_vec());
+            offset += len;
+        }
+        for path in record.removed {
+            self.files.insert(path, None);
+        }
+        for entry in record.files {
+            self.files.insert(entry.path.clone(), Some(entry));
+        }
+        self.records += 1;
+    }
+}
+
+/// Journal file of the archive at `archive_path`
+pub fn journal_path(archive_path: &Path) -> PathBuf {
+    let mut name = archive_path.as_os_str().to_os_string();
+    name.push(".journal");
+    PathBuf::from(name)
+}
+
+/// Version of an archive that journal records refer to
+pub fn journal_base(manifest: &Manifest) -> String {
+    manifest.updated_at.to_rfc3339()
+}
+
+/// Append a record and its stored chunks (in `record.chunks` order) and sync it to disk
+///
+/// An incomplete record left at the end by a crash is cut off first, so the
+/// new record directly follows the last complete one.
+pub fn append_record(archive_path: &Path, record: &JournalRecord, chunks: &[Vec<u8>]) -> Result<()> {
+    if record.chunks.len() != chunks.len()
+        || record.chunks.iter().zip(chunks).any(|((_, len), data)| *len != data.len() as u64)
+    {
+        return Err(CxpError::InvalidFormat("Journal record does not match its chunk data".to_string()));
+    }
+    let header = rmp_serde::to_vec(record)?;
+    let data = chunks.concat();
+
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + header.len() + data.len());
+    frame.extend_from_slice(MAGIC);
+    frame.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    frame.extend_from_slice(&checksum(&header, &data));
+    frame.extend_from_slice(&header);
+    frame.extend_from_slice(&data);
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(journal_path(archive_path))?;
+    let mut existing = Vec::new();
+    file.read_to_end(&mut existing)?;
+    let complete = complete_len(&existing);
+    if complete < existing.len() {
+        tracing::warn!("Truncating an incomplete journal record ({} bytes)", existing.len() - complete);
+        file.set_len(complete as u64)?;
+    }
+
+    // One write per record, so a crash can only leave the last one incomplete
+    file.seek(SeekFrom::Start(complete as u64))?;
+    file.write_all(&frame)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Delete the journal of an archive (if it has one)
+pub fn remove(archive_path: &Path) -> Result<()> {
+    match std::fs::remove_file(journal_path(archive_path)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Fold the journal into the archive at `path` and delete it
+///
+/// The archive is rewritten by `optimizer` next to `path` and moved over it,
+/// so a crash leaves either the old archive and its journal or the new one.
+pub fn compact<P: AsRef<Path>>(path: P, optimizer: &CxpOptimizer) -> Result<OptimizeStats> {
+    let path = path.as_ref();
+    let stats = replace_file(path, |temp_path| optimizer.optimize(path, temp_path))?;
+    remove(path)?;
+    Ok(stats)
+}
+
+fn checksum(header: &[u8], data: &[u8]) -> [u8; 8] {
+    let digest = Sha256::new().chain_update(header).chain_update(data).finalize();
+    let mut checksum = [0u8; 8];
+    checksum.copy_from_slice(&digest[..8]);
+    checksum
+}
+
+/// Length of the complete records at the start of `data`
+fn complete_len(data: &[u8]) -> usize {
+    let mut offset = 0;
+    while let Some((_, _, len)) = parse_frame(&data[offset..]) {
+        offset += len;
+    }
+    offset
+}
+
+/// Parse the record at the start of `data`: (record, chunk data, frame length)
+///
+/// None if the frame is incomplete or damaged.
+fn parse_frame(data: &[u8]) -> Option<(JournalRecord, &[u8], usize)> {
+    if data.len() < FRAME_HEADER_LEN || &data[..4] != MAGIC {
+        return None;
+    }
+    let header_len = u32::from_le_bytes(data[4..8].try_into().ok()?) as usize;
+    let data_len = usize::try_from(u64::from_le_bytes(data[8..16].try_into().ok()?)).ok()?;
+    let end = FRAME_HEADER_LEN.checked_add(header_len)?.checked_add(data_len)?;
+    if data.len() < end {
+        return None;
+    }
+    let header = &data[FRAME_HEADER_LEN..FRAME_HEADER_LEN + header_len];
+    let chunk_data = &data[FRAME_HEADER_LEN + header_len..end];
+    if checksum(header, chunk_data) != data[16..24] {
+        return None;
+    }
+    let record: JournalRecord = rmp_serde::from_slice(header).ok()?;
+    if record.chunks.iter().map(|(_, len)| *len).sum::<u64>() != data_len as u64 {
+        return None;
+    }
+    Some((record, chunk_data, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format_spec::check_file;
+    use crate::{CxpBuilder, CxpReader};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_journaled_updates_replay_and_roll_back() {
+        let source = TempDir::new().unwrap();
+        std::fs::write(source.path().join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(source.path().join("old.txt"), "to be removed\n").unwrap();
+        let out = TempDir::new().unwrap();
+        let path = out.path().join("live.cxp");
+
+        let mut builder = CxpBuilder::new(source.path());
+        builder.with_atomic_write(true).scan().unwrap().process().unwrap().build(&path).unwrap();
+        assert!(builder.append_journal(out.path().join("missing.cxp")).is_err());
+
+        std::fs::write(source.path().join("main.rs"), "fn main() { run(); }\n").unwrap();
+        std::fs::remove_file(source.path().join("old.txt")).unwrap();
+        let changed = [source.path().join("main.rs"), source.path().join("old.txt")];
+        builder.update_files(&changed).unwrap().append_journal(&path).unwrap();
+
+        // A record cut short by a crash is skipped by readers, which leave the
+        // file alone, and cut off by the next append
+        let journal = journal_path(&path);
+        let mut file = OpenOptions::new().append(true).open(&journal).unwrap();
+        file.write_all(b"CXPJ\x40\x00\x00\x00partial").unwrap();
+        drop(file);
+        let torn = std::fs::metadata(&journal).unwrap().len();
+        let reader = CxpReader::open(&path).unwrap();
+        assert_eq!(reader.journal_records(), 1);
+        assert_eq!(std::fs::metadata(&journal).unwrap().len(), torn);
+
+        std::fs::write(source.path().join("new.txt"), "added later\n").unwrap();
+        builder.update_files(&[source.path().join("new.txt")]).unwrap().append_journal(&path).unwrap();
+
+        let reader = CxpReader::open(&path).unwrap();
+        assert_eq!(reader.journal_records(), 2);
+        assert_eq!(reader.file_paths(), vec!["main.rs", "new.txt"]);
+        assert_eq!(reader.read_file("main.rs").unwrap(), b"fn main() { run(); }\n");
+        assert_eq!(reader.manifest().stats.total_files, 2);
+
+        // Compaction folds the journal into the archive
+        let stale = std::fs::read(&journal).unwrap();
+        let stats = compact(&path, &CxpOptimizer::new()).unwrap();
+        assert_eq!(stats.chunks, 2);
+        assert!(!journal.exists());
+        let reader = CxpReader::open(&path).unwrap();
+        assert_eq!(reader.journal_records(), 0);
+        assert_eq!(reader.read_file("new.txt").unwrap(), b"added later\n");
+        assert!(reader.file_entry("old.txt").unwrap().is_none());
+        assert!(check_file(&path).unwrap().failures().is_empty());
+
+        // A journal written against the previous version is ignored, and a
+        // full build drops it
+        std::fs::write(&journal, &stale).unwrap();
+        let reader = CxpReader::open(&path).unwrap();
+        assert_eq!(reader.journal_records(), 0);
+        assert_eq!(reader.file_paths(), vec!["main.rs", "new.txt"]);
+        builder.build(&path).unwrap();
+        assert!(!journal.exists());
+    }
+
+    #[test]
+    fn test_fresh_builder_journals_onto_archive_on_disk() {
+        let source = TempDir::new().unwrap();
+        std::fs::write(source.path().join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(source.path().join("lib.rs"), "pub fn lib() {}\n").unwrap();
+        let out = TempDir::new().unwrap();
+        let path = out.path().join("live.cxp");
+        CxpBuilder::new(source.path()).scan().unwrap().process().unwrap().build(&path).unwrap();
+
+        // Each update comes from a new builder, as in separate `cxp build --journal` runs
+        for (i, body) in ["fn main() { a(); }\n", "fn main() { b(); }\n"].into_iter().enumerate() {
+            std::fs::write(source.path().join("main.rs"), body).unwrap();
+            let mut builder = CxpBuilder::new(source.path());
+            builder.scan().unwrap().process().unwrap().append_journal(&path).unwrap();
+            assert_eq!(builder.journal_records(), i as u64 + 1);
+        }
+
+        let manifest = CxpReader::open(&path).unwrap().manifest().clone();
+        let journal = Journal::open(&path, &journal_base(&manifest)).unwrap();
+        assert_eq!(journal.records, 2);
+        // Only the changed file is journaled
+        assert_eq!(journal.files.keys().collect::<Vec<_>>(), vec!["main.rs"]);
+        let reader = CxpReader::open(&path).unwrap();
+        assert_eq!(reader.read_file("main.rs").unwrap(), b"fn main() { b(); }\n");
+        assert_eq!(reader.read_file("lib.rs").unwrap(), b"pub fn lib() {}\n");
+    }
+}
@@ -0,0 +1,221 @@
+//! Keyword Extraction
+//!
+//! TF-IDF over the text of an archive, run at build time
+//! (`CxpBuilder::with_keywords`). Every text file gets its most characteristic
+//! terms (`FileEntry::keywords`, copied into the `GlobalIndex`), and the
+//! archive gets topics (`Manifest::topics`) aggregated from the keywords of
+//! all its files.
+//!
+//! Identifiers are split into words (`parseChunkHeader` and
+//! `parse_chunk_header` both yield `parse`, `chunk`, `header`), and English
+//! stopwords and common language keywords are dropped.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Default number of keywords per file (the `GlobalIndex` keeps at most 20)
+pub const DEFAULT_MAX_KEYWORDS: usize = 20;
+
+/// Default number of topics per archive
+pub const DEFAULT_MAX_TOPICS: usize = 10;
+
+/// Shortest word that can become a keyword
+const MIN_WORD_LEN: usize = 3;
+
+/// Longest word that can become a keyword (longer ones are hashes, base64, ...)
+const MAX_WORD_LEN: usize = 32;
+
+/// Words that are never keywords: English stopwords and language keywords
+const STOPWORDS: &[&str] = &[
+    // English
+    "about", "after", "all", "also", "and", "any", "are", "because", "been", "before", "being", "between",
+    "both", "but", "can", "could", "does", "done", "each", "else", "every", "for", "from", "had", "has",
+    "have", "here", "how", "into", "its", "just", "like", "may", "more", "most", "much", "must", "not",
+    "now", "one", "only", "other", "our", "out", "over", "same", "see", "should", "since", "some", "such",
+    "than", "that", "the", "their", "them", "then", "there", "these", "they", "this", "those", "through",
+    "too", "two", "under", "until", "use", "used", "uses", "using", "very", "was", "way", "were", "what",
+    "when", "where", "which", "while", "who", "why", "will", "with", "without", "would", "you", "your",
+    // Language keywords and ubiquitous identifiers
+    "async", "await", "bool", "break", "case", "catch", "char", "class", "const", "continue", "crate",
+    "def", "default", "derive", "dyn", "elif", "enum", "err", "export", "extern", "false", "final",
+    "func", "function", "impl", "import", "int", "let", "loop", "match", "mod", "mut", "new", "nil",
+    "none", "null", "pass", "private", "protected", "pub", "public", "ref", "return", "self", "static",
+    "str", "string", "struct", "super", "switch", "throw", "trait", "true", "try", "type", "undefined",
+    "unwrap", "usize", "var", "vec", "void", "where", "yield",
+];
+
+/// Picks keywords of files and topics of an archive
+#[derive(Debug, Clone)]
+pub struct KeywordExtractor {
+    max_keywords: usize,
+    max_topics: usize,
+}
+
+impl Default for KeywordExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeywordExtractor {
+    /// Extractor with the default limits
+    pub fn new() -> Self {
+        Self {
+            max_keywords: DEFAULT_MAX_KEYWORDS,
+            max_topics: DEFAULT_MAX_TOPICS,
+        }
+    }
+
+    /// Keep at most `max` keywords per file
+    pub fn with_max_keywords(mut self, max: usize) -> Self {
+        self.max_keywords = max;
+        self
+    }
+
+    /// Keep at most `max` topics per archive
+    pub fn with_max_topics(mut self, max: usize) -> Self {
+        self.max_topics = max;
+        self
+    }
+
+    /// Keywords of every (path, content) file, best first
+    ///
+    /// Terms score by TF-IDF across the given files, so a word that appears
+    /// in every file says little about any of them. Files without a
+    /// qualifying word are left out.
+    pub fn extract<'a>(&self, files: impl IntoIterator<Item = (&'a str, &'a str)>) -> BTreeMap<String, Vec<String>> {
+        let counts: Vec<(&str, HashMap<String, usize>)> = files
+            .into_iter()
+            .map(|(path, content)| (path, term_counts(content)))
+            .filter(|(_, counts)| !counts.is_empty())
+            .collect();
+
+        let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+        for (_, terms) in &counts {
+            for term in terms.keys() {
+                *document_frequency.entry(term.as_str()).or_default() += 1;
+            }
+        }
+
+        let documents = counts.len() as f64;
+        counts
+            .iter()
+            .map(|(path, terms)| {
+                let mut scored: Vec<(&str, f64)> = terms
+                    .iter()
+                    .map(|(term, &count)| {
+                        let df = document_frequency[term.as_str()] as f64;
+                        let idf = ((documents + 1.0) / (df + 1.0)).ln() + 1.0;
+                        (term.as_str(), (1.0 + (count as f64).ln()) * idf)
+                    })
+                    .collect();
+                scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+                let keywords = scored
+                    .into_iter()
+                    .take(self.max_keywords)
+                    .map(|(term, _)| term.to_string())
+                    .collect();
+                (path.to_string(), keywords)
+            })
+            .collect()
+    }
+
+    /// Topics of an archive from the keyword lists of its files, best first
+    ///
+    /// A keyword counts more the higher it ranks in a file and the more
+    /// files share it.
+    pub fn topics<'a>(&self, keywords: impl IntoIterator<Item = &'a [String]>) -> Vec<String> {
+        let mut scores: HashMap<&str, f64> = HashMap::new();
+        for list in keywords {
+            for (rank, keyword) in list.iter().enumerate() {
+                *scores.entry(keyword.as_str()).or_default() += 1.0 / (rank + 1) as f64;
+            }
+        }
+
+        let mut scored: Vec<(&str, f64)> = scores.into_iter().collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        scored
+            .into_iter()
+            .take(self.max_topics)
+            .map(|(term, _)| term.to_string())
+            .collect()
+    }
+}
+
+/// Occurrences of every candidate term of a text
+fn term_counts(content: &str) -> HashMap<String, usize> {
+    let stopwords: HashSet<&str> = STOPWORDS.iter().copied().collect();
+    let mut counts = HashMap::new();
+    for token in content.split(|c: char| !c.is_alphanumeric()) {
+        for word in split_identifier(token) {
+            let word = word.to_lowercase();
+            let len = word.chars().count();
+            if (MIN_WORD_LEN..=MAX_WORD_LEN).contains(&len)
+                && word.chars().any(|c| c.is_alphabetic())
+                && !word.chars().any(|c| c.is_ascii_digit())
+                && !stopwords.contains(word.as_str())
+            {
+                *counts.entry(word).or_default() += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Words of a camelCase / PascalCase token (`HTTPServer` -> `HTTP`, `Server`)
+fn split_identifier(token: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = token.char_indices().collect();
+    let mut words = Vec::new();
+    let mut start = 0;
+    for i in 1..chars.len() {
+        let (offset, c) = chars[i];
+        let prev = chars[i - 1].1;
+        let next_lower = chars.get(i + 1).is_some_and(|(_, n)| n.is_lowercase());
+        if c.is_uppercase() && (prev.is_lowercase() || (prev.is_uppercase() && next_lower)) {
+            words.push(&token[start..offset]);
+            start = offset;
+        }
+    }
+    if start < token.len() {
+        words.push(&token[start..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_prefers_distinctive_terms() {
+        let files = [
+            ("src/cache.rs", "/// Cache eviction\nfn evict_entry(cache: &mut LruCache) { cache.evict(); cache.evict(); }"),
+            ("src/parser.rs", "/// Parser for chunk headers\nfn parseChunkHeader(input: &str) -> Header { parse(input) }"),
+            ("src/empty.rs", "fn main() {}"),
+        ];
+        let keywords = KeywordExtractor::new().extract(files);
+
+        assert_eq!(keywords["src/cache.rs"][0], "cache");
+        assert!(keywords["src/cache.rs"].contains(&"evict".to_string()));
+        assert!(keywords["src/parser.rs"].contains(&"chunk".to_string()));
+        assert!(keywords["src/parser.rs"].contains(&"header".to_string()));
+        assert!(!keywords["src/parser.rs"].contains(&"str".to_string()));
+        assert_eq!(keywords["src/empty.rs"], vec!["main"]);
+
+        let limited = KeywordExtractor::new().with_max_keywords(2).extract(files);
+        assert!(limited.values().all(|k| k.len() <= 2));
+    }
+
+    #[test]
+    fn test_topics_favor_shared_top_keywords() {
+        let lists = [
+            vec!["cache".to_string(), "evict".to_string()],
+            vec!["cache".to_string(), "parser".to_string()],
+            vec!["parser".to_string(), "lexer".to_string(), "cache".to_string()],
+        ];
+        let extractor = KeywordExtractor::new().with_max_topics(2);
+        let topics = extractor.topics(lists.iter().map(|l| l.as_slice()));
+
+        assert_eq!(topics, vec!["cache", "parser"]);
+        assert_eq!(split_identifier("HTTPServerConfig"), vec!["HTTP", "Server", "Config"]);
+    }
+}
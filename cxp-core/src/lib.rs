@@ -15,12 +15,58 @@ pub mod manifest;
 pub mod error;
 pub mod extensions;
 pub mod token;
+pub mod fusion;
+pub mod simd;
+pub mod context;
+pub mod stats;
+pub mod toc;
+pub mod map_shards;
+pub mod bloom;
+pub mod delta;
+pub mod merge;
+pub mod optimize;
+pub mod split;
+pub mod temp;
+pub mod cancel;
+pub mod checkpoint;
+pub mod build_info;
+pub mod quick;
+pub mod access_log;
+pub mod usage;
+pub mod annotations;
+pub mod extract;
+pub mod export;
+pub mod filter;
+pub mod progress;
+pub mod records;
+pub mod provenance;
+pub mod format_spec;
+pub mod lint;
+pub mod publish;
+pub mod federated;
+pub mod backend;
+pub mod priority;
+pub mod expansion;
+pub mod summaries;
+pub mod keywords;
+pub mod graph;
+pub mod symbols;
+pub mod dupes;
+pub mod clusters;
+pub mod snapshots;
+pub mod gc;
+pub mod journal;
+pub mod packs;
+pub mod tree;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
 // Recursive CXP support (always available)
 pub mod recursive;
 pub mod global_index;
 pub mod manager;
 pub mod recursive_builder;
+pub mod tier_scheduler;
 
 #[cfg(feature = "contextai")]
 pub mod contextai;
@@ -31,6 +77,9 @@ pub mod embeddings;
 #[cfg(feature = "embeddings-wasm")]
 pub mod embeddings_tract;
 
+#[cfg(any(feature = "embeddings", feature = "multimodal"))]
+pub mod device;
+
 #[cfg(feature = "multimodal")]
 pub mod multimodal;
 
@@ -40,34 +89,132 @@ pub mod index;
 #[cfg(all(feature = "search", feature = "multimodal"))]
 pub mod unified_index;
 
+#[cfg(all(feature = "embeddings", feature = "search"))]
+pub mod recall;
+
+#[cfg(all(feature = "embeddings", feature = "search"))]
+pub mod embedding_rows;
+
 #[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
 pub mod semantic;
 
 #[cfg(feature = "scanner")]
 pub mod scanner;
 
-pub use error::{CxpError, Result};
-pub use manifest::Manifest;
-pub use format::{CxpFile, CxpBuilder, CxpReader};
+#[cfg(feature = "watch")]
+pub mod watch;
+
+#[cfg(feature = "tokio")]
+pub mod async_reader;
+
+#[cfg(feature = "cloud")]
+pub mod cloud;
+
+#[cfg(feature = "redact")]
+pub mod redact;
+
+#[cfg(feature = "redact")]
+pub mod pii;
+
+#[cfg(feature = "git")]
+pub mod git;
+
+#[cfg(feature = "git")]
+pub mod git_history;
+
+pub use error::{CxpError, ErrorCode, Result, ResultExt};
+pub use manifest::{Manifest, Int8Storage, EmbeddingPrecision, IndexBackend, IndexParams, RedactionReport, Redaction};
+pub use compress::Codec;
+pub use chunker::{Chunker, ChunkingAlgorithm, GearChunker, BuzhashChunker, FixedChunker};
+pub use format::{CxpFile, CxpBuilder, CxpReader, FileStream, ChunkInfo, AvailableIndexes};
 pub use extensions::{Extension, ExtensionManager, ExtensionManifest};
-pub use token::{estimate_tokens, calculate_savings, TokenSavings, CostSavings, format_bytes, format_tokens};
+pub use token::{estimate_tokens, calculate_savings, TokenSavings, CostSavings, Tokenizer, format_bytes, format_tokens};
+pub use fusion::{Fusion, DedupBy, FusedResult, reciprocal_rank_fusion};
+pub use context::{ContextAssembler, ContextHit, ContextSource, AssembledContext};
+pub use toc::{Toc, TocSection, TocEntry};
+pub use map_shards::{ShardIndex, ShardInfo};
+pub use bloom::BloomFilter;
+pub use delta::CxpDelta;
+pub use merge::{CxpMerger, ConflictPolicy, MergeStats};
+pub use optimize::{CxpOptimizer, OptimizeStats};
+pub use split::{CxpSplitter, SplitMode, SplitStats};
+pub use temp::{TempGuard, TempPolicy};
+pub use cancel::CancellationToken;
+pub use checkpoint::Checkpoint;
+pub use build_info::{BuildInfo, BuildInfoExtension};
+pub use access_log::{AccessLog, AccessRecord, RecentFile};
+pub use usage::{UsageMetrics, UsageRecorder, UsageExtension, FileUsage};
+pub use annotations::{Annotations, FileAnnotation};
+pub use extract::{ExtractOptions, ExtractStats, OverwritePolicy};
+pub use export::{ExportFormat, ExportGranularity, ExportOptions, ExportRecord, ExportStats};
+pub use records::{ImportOptions, JsonlRecords, Record};
+pub use filter::{PathPattern, ScanFilter};
+pub use progress::{NoProgress, ProgressReporter};
+pub use provenance::Provenance;
+pub use format_spec::{ConformanceReport, ConformanceCheck, CheckStatus};
+pub use lint::{LintPolicy, LintReport, LintViolation};
+pub use publish::{publish, verify_bundle, PublishOptions, PublishedBundle};
+pub use federated::{FederatedSearch, FederatedHit, FederatedResults};
+pub use expansion::{QueryExpansion, NoExpansion, SynonymExpansion, HydeExpansion, ExpansionKind};
+pub use summaries::{Summarizer, HeuristicSummarizer, CommandSummarizer, Summaries, SummaryHit, SummaryKind};
+pub use keywords::KeywordExtractor;
+pub use graph::{KnowledgeGraph, GraphPlugin, ImportGraph, SymbolGraph, Node, NodeKind, Edge, EdgeKind, Neighborhood};
+pub use symbols::{Symbol, SymbolIndex, SymbolKind, SymbolParser, LineSymbolParser};
+pub use dupes::{DupeGroup, DupeFile};
+pub use clusters::{Cluster, ClusterMap, ClusterOptions, ClustersExtension};
+pub use snapshots::{SnapshotIndex, SnapshotInfo};
+pub use gc::{collect_garbage, GcOptions, GcStats};
+pub use journal::{Journal, JournalRecord};
+pub use packs::{PackIndex, PackedChunk};
+pub use tree::{DirTree, TreeFile};
+pub use backend::{ArchiveBackend, FileBackend, MemoryBackend, SharedReaderBackend};
+pub use stats::{
+    ArchiveStatistics, ExtensionStats, DirectoryStats, FileStats, EmbeddingStats, HistogramBucket,
+    DuplicateOptions, DuplicateReport, DuplicateCluster, DuplicateFile, SharedPair,
+};
 
 // Recursive CXP exports
-pub use recursive::{CxpRef, CxpStorage, CxpRefMeta, FileTier, ChildrenMap};
+pub use recursive::{CxpRef, CxpStorage, CxpRefMeta, FileTier, ChildrenMap, TierChange};
 pub use global_index::{GlobalIndex, GlobalIndexEntry, GlobalIndexStats};
-pub use manager::{CxpManager, CxpManagerConfig, SearchHit, MemoryStats};
+pub use manager::{CxpManager, CxpManagerConfig, SearchHit, SearchOptions, MemoryStats};
 pub use recursive_builder::{RecursiveBuilder, RecursiveBuildConfig, ProposedStructure, DirStats, ProjectPattern};
+pub use tier_scheduler::{TierScheduler, TierSchedulerHandle};
 
 #[cfg(feature = "contextai")]
 pub use contextai::ContextAIExtension;
 
+#[cfg(feature = "tokenizer")]
+pub use token::TokenCounter;
+
+#[cfg(feature = "watch")]
+pub use watch::{WatchService, WatchConfig, WatchUpdate, WatchHandle};
+
+#[cfg(feature = "tokio")]
+pub use async_reader::AsyncCxpReader;
+#[cfg(feature = "cloud")]
+pub use cloud::{CxpStorageBackend, ObjectStoreBackend, TransferStats};
+
+#[cfg(feature = "redact")]
+pub use redact::{Redactor, Scrubber};
+#[cfg(feature = "redact")]
+pub use pii::RegexScrubber;
+
+#[cfg(feature = "git")]
+pub use git::GitSelection;
+#[cfg(feature = "git")]
+pub use git_history::{CommitHit, CommitInfo, FileHistory, GitHistory, GitHistoryExtension};
+
 // Export common embedding types from either feature
 #[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
-pub use embeddings::{EmbeddingModel, BinaryEmbedding, Int8Embedding, QuantizedEmbeddings};
+pub use embeddings::{EmbeddingModel, ModelSpec, EmbeddingOptions, BinaryEmbedding, Int8Embedding, QuantizedEmbeddings, Pooling, pool_token_embeddings, pool_windows, DEFAULT_WINDOW_OVERLAP, BatchSizer, is_out_of_memory, DEFAULT_BATCH_SIZE, MAX_BATCH_SIZE};
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
+pub use embeddings::{PROBE_TEXTS, EmbeddingDeviation, compare_embeddings};
 
 // Export native engine (ort-based)
 #[cfg(feature = "embeddings")]
-pub use embeddings::EmbeddingEngine;
+pub use embeddings::{EmbeddingEngine, model_revision};
+#[cfg(any(feature = "embeddings", feature = "multimodal"))]
+pub use device::Device;
 
 // Export WASM engine (tract-based)
 #[cfg(feature = "embeddings-wasm")]
@@ -79,7 +226,15 @@ pub use multimodal::{MultimodalEngine, SIGLIP2_DIMENSIONS, cosine_similarity, co
 
 // Export search types
 #[cfg(feature = "search")]
-pub use index::{HnswIndex, HnswConfig, DistanceMetric, SearchResult};
+pub use index::{HnswIndex, HnswConfig, IvfFlatIndex, VectorIndex, DistanceMetric, SearchResult};
+
+// Export recall evaluation
+#[cfg(all(feature = "embeddings", feature = "search"))]
+pub use recall::{RecallQuery, RecallReport, recall_at_k};
+
+// Export embedding load options
+#[cfg(all(feature = "embeddings", feature = "search"))]
+pub use embedding_rows::LoadOptions;
 
 // Export unified index types
 #[cfg(all(feature = "search", feature = "multimodal"))]
@@ -89,14 +244,17 @@ pub use unified_index::{UnifiedIndex, EntryType, SearchResultWithType};
 #[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
 pub use semantic::{
     EmbeddingStore,
+    StoreSearchResult,
     serialize_binary_embeddings,
     deserialize_binary_embeddings,
     serialize_int8_embeddings,
     deserialize_int8_embeddings,
+    serialize_float_embeddings,
+    deserialize_float_embeddings,
 };
 
 /// CXP Format Version
-pub const VERSION: &str = "1.0.0";
+pub const VERSION: &str = "1.1.0";
 
 /// Default chunk size boundaries (in bytes)
 pub const MIN_CHUNK_SIZE: u32 = 2 * 1024;      // 2 KB
@@ -215,6 +373,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_chunk_size_constants() {
         assert!(MIN_CHUNK_SIZE < AVG_CHUNK_SIZE);
         assert!(AVG_CHUNK_SIZE < MAX_CHUNK_SIZE);
@@ -0,0 +1,224 @@
+//! Archive Linting
+//!
+//! Checks an archive against organizational rules, e.g. as a CI gate before
+//! archives are published. The policy is plain data (`serde`), so it can be
+//! kept in TOML or JSON next to the project:
+//!
+//! ```toml
+//! max_archive_bytes = 104857600
+//! required_extensions = ["secrets"]
+//! required_metadata = ["owner", "project"]
+//! allowed_embedding_models = ["all-MiniLM-L6-v2"]
+//! forbidden_file_extensions = ["env", "pem", "key"]
+//! ```
+//!
+//! Empty lists and missing limits disable the corresponding rule.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::format::CxpReader;
+use crate::Result;
+
+/// Rules an archive must satisfy
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintPolicy {
+    /// Maximum size of the archive file in bytes
+    pub max_archive_bytes: Option<u64>,
+    /// Extension namespaces that must be present (e.g. a secrets scan)
+    pub required_extensions: Vec<String>,
+    /// Manifest metadata keys that must be set
+    pub required_metadata: Vec<String>,
+    /// Embedding models archives may use (empty: any)
+    pub allowed_embedding_models: Vec<String>,
+    /// File extensions that must not be archived (without the dot)
+    pub forbidden_file_extensions: Vec<String>,
+}
+
+/// A broken rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LintViolation {
+    /// Policy field of the rule
+    pub rule: String,
+    /// What is wrong
+    pub message: String,
+}
+
+/// Result of linting an archive
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LintReport {
+    /// Number of rules checked
+    pub rules_checked: usize,
+    /// Violations in rule order
+    pub violations: Vec<LintViolation>,
+}
+
+impl LintReport {
+    /// Whether no rule was violated
+    pub fn is_clean(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    fn violation(&mut self, rule: &str, message: String) {
+        self.violations.push(LintViolation {
+            rule: rule.to_string(),
+            message,
+        });
+    }
+}
+
+impl LintPolicy {
+    /// Create a policy with no rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the archive size
+    pub fn with_max_archive_bytes(mut self, bytes: u64) -> Self {
+        self.max_archive_bytes = Some(bytes);
+        self
+    }
+
+    /// Require an extension namespace
+    pub fn with_required_extension(mut self, namespace: impl Into<String>) -> Self {
+        self.required_extensions.push(namespace.into());
+        self
+    }
+
+    /// Require a manifest metadata key
+    pub fn with_required_metadata(mut self, key: impl Into<String>) -> Self {
+        self.required_metadata.push(key.into());
+        self
+    }
+
+    /// Allow an embedding model
+    pub fn with_allowed_embedding_model(mut self, model: impl Into<String>) -> Self {
+        self.allowed_embedding_models.push(model.into());
+        self
+    }
+
+    /// Forbid a file extension
+    pub fn with_forbidden_file_extension(mut self, extension: impl Into<String>) -> Self {
+        self.forbidden_file_extensions.push(extension.into());
+        self
+    }
+
+    /// Lint the archive at `path`
+    pub fn check_file<P: AsRef<Path>>(&self, path: P) -> Result<LintReport> {
+        let path = path.as_ref();
+        let reader = CxpReader::open(path)?.with_access_tracking(false);
+        self.check(&reader, std::fs::metadata(path)?.len())
+    }
+
+    /// Lint an open archive whose file is `archive_bytes` large
+    pub fn check(&self, reader: &CxpReader, archive_bytes: u64) -> Result<LintReport> {
+        let manifest = reader.manifest();
+        let mut report = LintReport::default();
+
+        if let Some(max) = self.max_archive_bytes {
+            report.rules_checked += 1;
+            if archive_bytes > max {
+                report.violation(
+                    "max_archive_bytes",
+                    format!("archive is {} bytes, limit is {}", archive_bytes, max),
+                );
+            }
+        }
+
+        if !self.required_extensions.is_empty() {
+            report.rules_checked += 1;
+            for namespace in &self.required_extensions {
+                if !manifest.extensions.contains(namespace) {
+                    report.violation("required_extensions", format!("extension '{}' is missing", namespace));
+                }
+            }
+        }
+
+        if !self.required_metadata.is_empty() {
+            report.rules_checked += 1;
+            for key in &self.required_metadata {
+                if manifest.metadata.get(key).is_none_or(|value| value.trim().is_empty()) {
+                    report.violation("required_metadata", format!("metadata key '{}' is not set", key));
+                }
+            }
+        }
+
+        if !self.allowed_embedding_models.is_empty() {
+            report.rules_checked += 1;
+            if let Some(model) = &manifest.embedding_model {
+                if !self.allowed_embedding_models.contains(model) {
+                    report.violation(
+                        "allowed_embedding_models",
+                        format!("embedding model '{}' is not allowed", model),
+                    );
+                }
+            }
+        }
+
+        if !self.forbidden_file_extensions.is_empty() {
+            report.rules_checked += 1;
+            let forbidden: Vec<String> = self.forbidden_file_extensions
+                .iter()
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect();
+            for path in reader.file_paths() {
+                let extension = Path::new(path)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(str::to_lowercase);
+                if extension.is_some_and(|ext| forbidden.contains(&ext)) {
+                    report.violation("forbidden_file_extensions", format!("'{}' has a forbidden extension", path));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CxpBuilder;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lint_policy() {
+        let source = TempDir::new().unwrap();
+        std::fs::write(source.path().join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(source.path().join("settings.json"), "{}\n").unwrap();
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("lint.cxp");
+
+        let mut builder = CxpBuilder::new(source.path());
+        builder.with_metadata("owner", "platform-team");
+        builder.scan().unwrap().process().unwrap();
+        builder.build(&cxp_path).unwrap();
+
+        assert!(LintPolicy::new().check_file(&cxp_path).unwrap().is_clean());
+
+        let passing = LintPolicy::new()
+            .with_max_archive_bytes(10 * 1024 * 1024)
+            .with_required_metadata("owner")
+            .with_allowed_embedding_model("all-MiniLM-L6-v2")
+            .with_forbidden_file_extension("pem");
+        let report = passing.check_file(&cxp_path).unwrap();
+        assert!(report.is_clean(), "{:?}", report.violations);
+        assert_eq!(report.rules_checked, 4);
+
+        let failing = LintPolicy::new()
+            .with_max_archive_bytes(16)
+            .with_required_extension("secrets")
+            .with_required_metadata("project")
+            .with_forbidden_file_extension(".JSON");
+        let report = failing.check_file(&cxp_path).unwrap();
+        let rules: Vec<&str> = report.violations.iter().map(|v| v.rule.as_str()).collect();
+        assert_eq!(
+            rules,
+            vec!["max_archive_bytes", "required_extensions", "required_metadata", "forbidden_file_extensions"]
+        );
+        assert!(report.violations[3].message.contains("settings.json"));
+    }
+}
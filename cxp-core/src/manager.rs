@@ -1,14 +1,20 @@
 //! CXP Manager with LRU Cache
 //!
 //! Manages recursive CXP hierarchies with lazy loading and memory management.
-//! Hot CXPs stay in memory, Warm/Cold are loaded on demand.
+//! Hot CXPs stay in memory, Warm/Cold are loaded on demand. When the memory
+//! or entry budget is exceeded, Cold CXPs are evicted before Warm ones (least
+//! recently used first); Hot and pinned CXPs are never evicted.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read, Seek};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use chrono::Utc;
 
-use crate::recursive::{CxpRef, CxpStorage, ChildrenMap, FileTier};
+use crate::access_log::{AccessLog, AccessRecord};
+use crate::cancel::Deadline;
+use crate::recursive::{CxpRef, CxpStorage, ChildrenMap, FileTier, TierChange};
 use crate::global_index::{GlobalIndex, GlobalIndexEntry};
 use crate::format::CxpFile;
 use crate::Result;
@@ -28,6 +34,9 @@ pub struct CxpManagerConfig {
 
     /// Preload all Hot CXPs on startup
     pub preload_hot: bool,
+
+    /// Record `get()` accesses in `<storage_root>/master.access_log.msgpack`
+    pub track_access: bool,
 }
 
 impl Default for CxpManagerConfig {
@@ -37,6 +46,7 @@ impl Default for CxpManagerConfig {
             max_cached_cxps: 50,
             storage_root: PathBuf::from("~/.contextai/"),
             preload_hot: true,
+            track_access: true,
         }
     }
 }
@@ -82,6 +92,9 @@ pub struct CxpManager {
 
     /// LRU order (most recent at end)
     lru_order: Arc<RwLock<Vec<String>>>,
+
+    /// CXPs pinned in the cache by `pin()`
+    pinned: Arc<RwLock<HashSet<String>>>,
 }
 
 impl CxpManager {
@@ -94,19 +107,82 @@ impl CxpManager {
             root_children: Arc::new(RwLock::new(ChildrenMap::new())),
             current_memory: Arc::new(RwLock::new(0)),
             lru_order: Arc::new(RwLock::new(Vec::new())),
+            pinned: Arc::new(RwLock::new(HashSet::new())),
         }
     }
 
+    /// Path of the master CXP (`<storage_root>/master.cxp`)
+    fn master_path(&self) -> PathBuf {
+        self.config.storage_root.join("master.cxp")
+    }
+
     /// Initialize the manager, loading root CXP references
     pub fn init(&self) -> Result<()> {
-        let master_path = self.config.storage_root.join("master.cxp");
+        let master_path = self.master_path();
 
         if master_path.exists() {
             // Load master CXP to get children references
             self.load_master_refs(&master_path)?;
+            if self.config.track_access {
+                self.apply_access_log()?;
+            }
+
+            if let Some(index) = GlobalIndex::read_from(&master_path)? {
+                *self.global_index.write()
+                    .map_err(|_| CxpError::io("Lock poisoned".to_string()))? = index;
+            }
 
             if self.config.preload_hot {
-                self.preload_hot_cxps()?;
+                self.preload_hot()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Access log of the master CXP
+    pub fn access_log(&self) -> AccessLog {
+        AccessLog::for_archive(self.master_path())
+    }
+
+    /// Restore `last_accessed` of root children from the access log and recalculate their tiers
+    fn apply_access_log(&self) -> Result<()> {
+        let last_accessed = self.access_log().read()?
+            .into_iter()
+            .filter_map(|record| record.cxp_path.first().cloned().map(|id| (id, record.accessed_at)))
+            .fold(HashMap::new(), |mut latest: HashMap<String, chrono::DateTime<Utc>>, (id, at)| {
+                let entry = latest.entry(id).or_insert(at);
+                *entry = (*entry).max(at);
+                latest
+            });
+
+        let mut children = self.root_children.write()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+        for (id, at) in last_accessed {
+            if let Some(cxp_ref) = children.get_mut(&id) {
+                if cxp_ref.last_accessed.is_none_or(|current| current < at) {
+                    cxp_ref.last_accessed = Some(at);
+                    cxp_ref.recalculate_tier();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Touch the root CXP of `cxp_id` and append the access to the log
+    fn record_access(&self, cxp_id: &str) -> Result<()> {
+        let cxp_path: Vec<String> = cxp_id.split('/').map(str::to_string).collect();
+        self.touch(&cxp_path[0])?;
+
+        if self.config.track_access && self.config.storage_root.is_dir() {
+            let access = AccessRecord {
+                cxp_path,
+                file_path: None,
+                accessed_at: Utc::now(),
+            };
+            if let Err(e) = self.access_log().append(&access) {
+                tracing::warn!("Could not record access to CXP '{}': {}", cxp_id, e);
             }
         }
 
@@ -115,6 +191,15 @@ impl CxpManager {
 
     /// Load master CXP references
     fn load_master_refs(&self, master_path: &Path) -> Result<()> {
+        let mut children = self.root_children.write()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+
+        // Children listed in the master manifest (embedded or external)
+        let manifest = read_zip_entry(std::fs::File::open(master_path)?, "manifest.msgpack")?;
+        for cxp_ref in crate::Manifest::from_msgpack(&manifest)?.children.iter() {
+            children.add(cxp_ref.clone());
+        }
+
         // Read the master CXP's children directory
         let children_dir = master_path.with_extension("").join("children");
 
@@ -122,18 +207,15 @@ impl CxpManager {
             return Ok(());
         }
 
-        let mut children = self.root_children.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
-
         for entry in std::fs::read_dir(&children_dir)
-            .map_err(|e| CxpError::Io(e.to_string()))?
+            .map_err(|e| CxpError::io(e.to_string()))?
         {
-            let entry = entry.map_err(|e| CxpError::Io(e.to_string()))?;
+            let entry = entry.map_err(|e| CxpError::io(e.to_string()))?;
             let path = entry.path();
 
             if path.extension().map(|e| e == "cxpref").unwrap_or(false) {
                 let data = std::fs::read(&path)
-                    .map_err(|e| CxpError::Io(e.to_string()))?;
+                    .map_err(|e| CxpError::io(e.to_string()))?;
                 let cxp_ref = CxpRef::from_msgpack(&data)?;
                 children.add(cxp_ref);
             }
@@ -142,22 +224,59 @@ impl CxpManager {
         Ok(())
     }
 
-    /// Preload all Hot tier CXPs into memory
-    fn preload_hot_cxps(&self) -> Result<()> {
+    /// Load all Hot root CXPs into the cache, returning how many were loaded
+    ///
+    /// Called by `init()` when `preload_hot` is set. CXPs that fail to load
+    /// are skipped with a warning.
+    pub fn preload_hot(&self) -> Result<usize> {
         let children = self.root_children.read()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
-        let hot_refs: Vec<_> = children.hot().iter()
-            .map(|r| (*r).clone())
+        let hot_ids: Vec<String> = children.iter()
+            .filter(|r| r.tier.should_preload())
+            .map(|r| r.id.clone())
             .collect();
 
         drop(children); // Release lock before loading
 
-        for cxp_ref in hot_refs {
-            let _ = self.load_cxp(&cxp_ref.id); // Ignore errors during preload
+        let mut loaded = 0;
+        for cxp_id in hot_ids {
+            match self.get_or_load(&cxp_id) {
+                Ok(Some(_)) => loaded += 1,
+                Ok(None) => tracing::warn!("Hot CXP '{}' not found during preload", cxp_id),
+                Err(e) => tracing::warn!("Failed to preload Hot CXP '{}': {}", cxp_id, e),
+            }
         }
 
-        Ok(())
+        Ok(loaded)
+    }
+
+    /// Keep a CXP in the cache regardless of its tier, loading it if needed
+    ///
+    /// Returns false if no CXP with this id exists.
+    pub fn pin(&self, cxp_id: &str) -> Result<bool> {
+        if self.get_or_load(cxp_id)?.is_none() {
+            return Ok(false);
+        }
+
+        self.pinned.write()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?
+            .insert(cxp_id.to_string());
+        Ok(true)
+    }
+
+    /// Make a pinned CXP evictable again (returns false if it was not pinned)
+    pub fn unpin(&self, cxp_id: &str) -> Result<bool> {
+        Ok(self.pinned.write()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?
+            .remove(cxp_id))
+    }
+
+    /// Whether a CXP is pinned in the cache
+    pub fn is_pinned(&self, cxp_id: &str) -> Result<bool> {
+        Ok(self.pinned.read()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?
+            .contains(cxp_id))
     }
 
     /// Get a CXP by path (e.g., ["home", "projects", "contextai"])
@@ -167,27 +286,33 @@ impl CxpManager {
         }
 
         let cxp_id = path.join("/");
+        let cxp = self.get_or_load(&cxp_id)?;
+        if cxp.is_some() {
+            self.record_access(&cxp_id)?;
+        }
+        Ok(cxp)
+    }
 
-        // Check cache first
-        if let Some(cxp) = self.get_from_cache(&cxp_id)? {
+    /// Get a CXP by id from the cache, loading it on a miss
+    fn get_or_load(&self, cxp_id: &str) -> Result<Option<CxpFile>> {
+        if let Some(cxp) = self.get_from_cache(cxp_id)? {
             return Ok(Some(cxp));
         }
 
-        // Load from disk
-        self.load_cxp(&cxp_id)
+        self.load_cxp(cxp_id)
     }
 
     /// Get from cache, updating LRU order
     fn get_from_cache(&self, cxp_id: &str) -> Result<Option<CxpFile>> {
         let mut cache = self.cache.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         if let Some(entry) = cache.get_mut(cxp_id) {
             entry.last_accessed = Utc::now();
 
             // Update LRU order
             let mut lru = self.lru_order.write()
-                .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
             lru.retain(|id| id != cxp_id);
             lru.push(cxp_id.to_string());
 
@@ -197,40 +322,22 @@ impl CxpManager {
         Ok(None)
     }
 
-    /// Load a CXP from disk and cache it
+    /// Load a CXP from disk (or its parent archive) and cache it
     fn load_cxp(&self, cxp_id: &str) -> Result<Option<CxpFile>> {
-        // Find the CxpRef for this ID
-        let cxp_ref = self.find_ref(cxp_id)?;
-
-        let cxp_ref = match cxp_ref {
-            Some(r) => r,
+        // Find the CxpRef for this ID and where its archive lives
+        let (cxp_ref, location) = match self.find_ref(cxp_id)? {
+            Some(found) => found,
             None => return Ok(None),
         };
 
-        // Get the actual path
-        let cxp_path = match &cxp_ref.storage {
-            CxpStorage::External { path } => path.clone(),
-            CxpStorage::Embedded { path_in_zip } => {
-                // TODO: Extract from parent ZIP
-                return Err(CxpError::Io(format!(
-                    "Embedded CXP loading not yet implemented: {}",
-                    path_in_zip
-                )));
-            }
-            CxpStorage::Remote { url, .. } => {
-                return Err(CxpError::Io(format!(
-                    "Remote CXP loading not yet implemented: {}",
-                    url
-                )));
+        if let Location::File(path) = &location {
+            if !path.exists() {
+                return Ok(None);
             }
-        };
-
-        if !cxp_path.exists() {
-            return Ok(None);
         }
 
         // Load the CXP file
-        let cxp = CxpFile::open(&cxp_path)?;
+        let cxp = location.open()?;
         let memory_size = cxp.estimate_memory_size();
 
         // Evict if necessary
@@ -238,7 +345,7 @@ impl CxpManager {
 
         // Add to cache
         let mut cache = self.cache.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         cache.insert(cxp_id.to_string(), CacheEntry {
             cxp: cxp.clone(),
@@ -249,92 +356,117 @@ impl CxpManager {
 
         // Update LRU order
         let mut lru = self.lru_order.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
         lru.push(cxp_id.to_string());
 
         // Update memory counter
         let mut memory = self.current_memory.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
         *memory += memory_size;
 
         Ok(Some(cxp))
     }
 
-    /// Find a CxpRef by ID (recursive search)
-    fn find_ref(&self, cxp_id: &str) -> Result<Option<CxpRef>> {
+    /// Find a CxpRef by ID and resolve where its archive is stored (recursive search)
+    ///
+    /// Nested ids (`"projects/web"`) walk the children of each CXP along the
+    /// path; embedded children are read from inside their parent archive.
+    fn find_ref(&self, cxp_id: &str) -> Result<Option<(CxpRef, Location)>> {
+        let master = Location::File(self.master_path());
         let children = self.root_children.read()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         // First check root level
         if let Some(cxp_ref) = children.get(cxp_id) {
-            return Ok(Some(cxp_ref.clone()));
+            let location = Location::of(cxp_ref, master)?;
+            return Ok(Some((cxp_ref.clone(), location)));
         }
 
         // Check if it's a nested path
-        let parts: Vec<&str> = cxp_id.split('/').collect();
-        if parts.len() > 1 {
-            // TODO: Recursively search in loaded child CXPs
-            // For now, just check root level
+        let mut parts = cxp_id.split('/');
+        let Some(mut cxp_ref) = parts.next().and_then(|root| children.get(root)).cloned() else {
+            return Ok(None);
+        };
+        drop(children);
+
+        let mut location = Location::of(&cxp_ref, master)?;
+        for part in parts {
+            let parent = location.open()?;
+            cxp_ref = match parent.manifest.children.get(part) {
+                Some(child) => child.clone(),
+                None => return Ok(None),
+            };
+            location = Location::of(&cxp_ref, location)?;
         }
 
-        Ok(None)
+        Ok(Some((cxp_ref, location)))
     }
 
-    /// Ensure enough memory is available, evicting if necessary
+    /// Ensure there is room for one more CXP of `needed` bytes, evicting if necessary
     fn ensure_memory_available(&self, needed: usize) -> Result<()> {
         let current = *self.current_memory.read()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
-
-        if current + needed <= self.config.max_memory_bytes {
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+        let cached = self.cache.read()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?
+            .len();
+
+        let bytes_over = (current + needed).saturating_sub(self.config.max_memory_bytes);
+        let entries_over = (cached + 1).saturating_sub(self.config.max_cached_cxps);
+        if bytes_over == 0 && entries_over == 0 {
             return Ok(());
         }
 
-        // Need to evict
-        let to_evict = (current + needed).saturating_sub(self.config.max_memory_bytes);
-        self.evict_lru(to_evict)?;
-
-        Ok(())
+        self.evict_lru(bytes_over, entries_over)
     }
 
-    /// Evict least recently used CXPs to free memory
-    fn evict_lru(&self, bytes_needed: usize) -> Result<()> {
+    /// Evict cached CXPs until `bytes_needed` bytes and `entries_needed` entries are freed
+    ///
+    /// Cold CXPs go before Warm ones, least recently used first within a tier.
+    /// Hot and pinned CXPs are never evicted, so the budget may stay exceeded.
+    fn evict_lru(&self, bytes_needed: usize, entries_needed: usize) -> Result<()> {
         let mut freed = 0usize;
         let mut to_remove = Vec::new();
 
         {
             let cache = self.cache.read()
-                .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
             let lru = self.lru_order.read()
-                .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
-
-            // Iterate from least recently used (front of list)
-            for cxp_id in lru.iter() {
-                if freed >= bytes_needed {
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+            let pinned = self.pinned.read()
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+
+            // Candidates in eviction order: highest tier priority, then least recently used
+            let mut candidates: Vec<(usize, &String, &CacheEntry)> = lru.iter()
+                .enumerate()
+                .filter_map(|(position, cxp_id)| cache.get(cxp_id).map(|entry| (position, cxp_id, entry)))
+                .filter(|(_, cxp_id, entry)| entry.tier != FileTier::Hot && !pinned.contains(*cxp_id))
+                .collect();
+            candidates.sort_by_key(|(position, _, entry)| (std::cmp::Reverse(entry.tier.eviction_priority()), *position));
+
+            for (_, cxp_id, entry) in candidates {
+                if freed >= bytes_needed && to_remove.len() >= entries_needed {
                     break;
                 }
 
-                if let Some(entry) = cache.get(cxp_id) {
-                    // Never evict Hot CXPs
-                    if entry.tier == FileTier::Hot {
-                        continue;
-                    }
-
-                    // Evict Cold first, then Warm
-                    to_remove.push((cxp_id.clone(), entry.memory_size));
-                    freed += entry.memory_size;
-                }
+                to_remove.push((cxp_id.clone(), entry.memory_size));
+                freed += entry.memory_size;
             }
         }
 
+        if freed < bytes_needed || to_remove.len() < entries_needed {
+            tracing::warn!("Cache budget exceeded: only Hot or pinned CXPs left to evict");
+        }
+
         // Actually remove
         let mut cache = self.cache.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
         let mut lru = self.lru_order.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
         let mut memory = self.current_memory.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         for (cxp_id, size) in to_remove {
+            tracing::debug!("Evicting CXP '{}' ({} bytes)", cxp_id, size);
             cache.remove(&cxp_id);
             lru.retain(|id| id != &cxp_id);
             *memory = memory.saturating_sub(size);
@@ -343,27 +475,117 @@ impl CxpManager {
         Ok(())
     }
 
-    /// Search across all CXPs using the global index
-    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
-        let index = self.global_index.read()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+    /// Search across the CXP hierarchy with default options
+    ///
+    /// See [`search_with`](Self::search_with).
+    pub fn search(&self, query: &str, top_k: usize) -> Result<Vec<SearchHit>> {
+        self.search_with(query, top_k, &SearchOptions::default())
+    }
 
-        let results = index.search(query, limit);
+    /// Search the global index and fan the query out across child CXPs
+    ///
+    /// Hot and Warm children (and their nested children) are loaded through
+    /// the LRU cache, so the memory budget and eviction rules apply; Cold
+    /// children are only searched if they are already cached or
+    /// `options.load_cold` is set. Hits from the global index and from loaded
+    /// children are merged per file, keeping the best score, and ranked.
+    ///
+    /// Once `options.timeout` has passed no further children are searched and
+    /// the hits collected so far are returned.
+    pub fn search_with(&self, query: &str, top_k: usize, options: &SearchOptions) -> Result<Vec<SearchHit>> {
+        let deadline = Deadline::after(options.timeout);
+        let mut hits: HashMap<(Vec<String>, String), SearchHit> = HashMap::new();
+        let mut add_hit = |hit: SearchHit| {
+            let key = (hit.cxp_path.clone(), hit.file_path.clone());
+            match hits.get(&key) {
+                Some(existing) if existing.score >= hit.score => {}
+                _ => {
+                    hits.insert(key, hit);
+                }
+            }
+        };
 
-        Ok(results.into_iter().map(|r| SearchHit {
-            cxp_path: r.entry.cxp_path.clone(),
-            file_path: r.entry.file_path.clone(),
-            file_name: r.entry.file_name.clone(),
-            score: r.score,
-            preview: r.entry.preview.clone(),
-            tier: r.entry.tier,
-        }).collect())
+        {
+            let index = self.global_index.read()
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+            for r in index.search(query, top_k) {
+                add_hit(SearchHit {
+                    cxp_path: r.entry.cxp_path.clone(),
+                    file_path: r.entry.file_path.clone(),
+                    file_name: r.entry.file_name.clone(),
+                    score: r.score,
+                    preview: r.entry.preview.clone(),
+                    tier: r.entry.tier,
+                });
+            }
+        }
+
+        // Walk the hierarchy, loading children the tiers allow
+        let mut pending: Vec<(Vec<String>, CxpRef)> = self.root_children()?
+            .into_iter()
+            .map(|cxp_ref| (vec![cxp_ref.id.clone()], cxp_ref))
+            .collect();
+
+        while let Some((cxp_path, cxp_ref)) = pending.pop() {
+            if deadline.expired() {
+                tracing::warn!("Search timed out; skipped {} CXPs", pending.len() + 1);
+                break;
+            }
+
+            let cxp_id = cxp_path.join("/");
+            let cxp = match self.get_from_cache(&cxp_id)? {
+                Some(cxp) => cxp,
+                None if cxp_ref.tier == FileTier::Cold && !options.load_cold => continue,
+                None => match self.load_cxp(&cxp_id) {
+                    Ok(Some(cxp)) => cxp,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        tracing::warn!("Skipping CXP {} during search: {}", cxp_id, e);
+                        continue;
+                    }
+                },
+            };
+
+            for (path, file_entry) in &cxp.file_map.files {
+                let mut entry = GlobalIndexEntry::new(&cxp_id, cxp_path.clone(), path, &file_entry.extension);
+                entry.tier = cxp_ref.tier;
+                entry.keywords = file_entry.keywords.clone();
+                let score = entry.matches(query);
+                if score > 0.0 {
+                    add_hit(SearchHit {
+                        cxp_path: cxp_path.clone(),
+                        file_path: entry.file_path,
+                        file_name: entry.file_name,
+                        score,
+                        preview: None,
+                        tier: cxp_ref.tier,
+                    });
+                }
+            }
+
+            for child in cxp.manifest.children.iter() {
+                let mut child_path = cxp_path.clone();
+                child_path.push(child.id.clone());
+                pending.push((child_path, child.clone()));
+            }
+        }
+
+        let mut results: Vec<SearchHit> = hits.into_values().collect();
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.full_path().cmp(&b.full_path()))
+        });
+        results.truncate(top_k);
+
+        Ok(results)
     }
 
     /// Search by file type
     pub fn search_by_type(&self, file_type: &str, limit: usize) -> Result<Vec<SearchHit>> {
         let index = self.global_index.read()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         let results = index.search_by_type(file_type, limit);
 
@@ -390,13 +612,13 @@ impl CxpManager {
                     &file_entry.extension,
                 );
                 entry.file_size = file_entry.size;
-                // Keywords would be extracted during CXP creation
+                entry.keywords = file_entry.keywords.clone();
                 entry
             })
             .collect();
 
         let mut index = self.global_index.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         index.add_from_cxp(&cxp_id, cxp_path.to_vec(), entries);
 
@@ -406,22 +628,77 @@ impl CxpManager {
     /// Update tier for a CXP reference
     pub fn update_tier(&self, cxp_id: &str) -> Result<()> {
         let mut children = self.root_children.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         if let Some(cxp_ref) = children.get_mut(cxp_id) {
             cxp_ref.recalculate_tier();
+
+            // Keep the cached tier in sync so eviction sees the new tier
+            if let Some(entry) = self.cache.write()
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?
+                .get_mut(cxp_id)
+            {
+                entry.tier = cxp_ref.tier;
+            }
         }
 
         Ok(())
     }
 
+    /// Recalculate the tiers of all root children, returning the ones that moved
+    ///
+    /// Cached entries and global index entries follow the new tier. Children
+    /// promoted to Hot are loaded when `preload_hot` is set; the updated index
+    /// is saved to the master CXP if anything changed.
+    pub fn recalculate_tiers(&self) -> Result<Vec<TierChange>> {
+        let changes = self.root_children.write()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?
+            .recalculate_tiers();
+
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+
+        {
+            let mut cache = self.cache.write()
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+            let mut index = self.global_index.write()
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+
+            for change in &changes {
+                if let Some(entry) = cache.get_mut(&change.id) {
+                    entry.tier = change.to;
+                }
+                index.set_tier(std::slice::from_ref(&change.id), change.to);
+            }
+        }
+
+        if self.config.preload_hot {
+            for change in changes.iter().filter(|c| c.to.should_preload()) {
+                if let Err(e) = self.get_or_load(&change.id) {
+                    tracing::warn!("Failed to load promoted CXP '{}': {}", change.id, e);
+                }
+            }
+        }
+
+        self.save_index()?;
+        Ok(changes)
+    }
+
     /// Mark a CXP as accessed (updates tier calculation)
     pub fn touch(&self, cxp_id: &str) -> Result<()> {
         let mut children = self.root_children.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         if let Some(cxp_ref) = children.get_mut(cxp_id) {
             cxp_ref.touch();
+
+            if let Some(entry) = self.cache.write()
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?
+                .get_mut(cxp_id)
+            {
+                entry.tier = cxp_ref.tier;
+            }
         }
 
         Ok(())
@@ -430,9 +707,9 @@ impl CxpManager {
     /// Get current memory usage
     pub fn memory_usage(&self) -> Result<MemoryStats> {
         let current = *self.current_memory.read()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
         let cache = self.cache.read()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         let mut hot_count = 0;
         let mut warm_count = 0;
@@ -459,38 +736,135 @@ impl CxpManager {
     /// Get all root children
     pub fn root_children(&self) -> Result<Vec<CxpRef>> {
         let children = self.root_children.read()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         Ok(children.iter().cloned().collect())
     }
 
-    /// Add a root child
+    /// Add a root child and index its files (and those of its children)
     pub fn add_root_child(&self, cxp_ref: CxpRef) -> Result<()> {
-        let mut children = self.root_children.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+        {
+            let mut index = self.global_index.write()
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+            let cxp_path = vec![cxp_ref.id.clone()];
+            index.remove_cxp_tree(&cxp_path);
+            index.compact();
+            if let Err(e) = index_tree(&mut index, cxp_path, &cxp_ref, Location::File(self.master_path())) {
+                tracing::warn!("Could not index CXP {}: {}", cxp_ref.id, e);
+            }
+        }
 
+        let mut children = self.root_children.write()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
         children.add(cxp_ref);
-        Ok(())
+        drop(children);
+
+        self.save_index()
     }
 
-    /// Remove a root child
+    /// Remove a root child and its entries from the global index
     pub fn remove_root_child(&self, cxp_id: &str) -> Result<Option<CxpRef>> {
         let mut children = self.root_children.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+        let removed = children.remove(cxp_id);
+        drop(children);
+
+        if removed.is_some() {
+            let mut index = self.global_index.write()
+                .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+            index.remove_cxp_tree(&[cxp_id.to_string()]);
+            index.compact();
+            drop(index);
+            self.save_index()?;
+        }
 
-        Ok(children.remove(cxp_id))
+        Ok(removed)
+    }
+
+    /// Rebuild the global index from all root children and save it
+    ///
+    /// Returns the number of indexed files.
+    pub fn reindex(&self) -> Result<usize> {
+        let mut index = GlobalIndex::new();
+        for cxp_ref in self.root_children()? {
+            index_tree(&mut index, vec![cxp_ref.id.clone()], &cxp_ref, Location::File(self.master_path()))?;
+        }
+        let files = index.entries.len();
+
+        *self.global_index.write()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))? = index;
+        self.save_index()?;
+
+        Ok(files)
+    }
+
+    /// Rebuild the global index of a root archive from its children and store it there
+    pub fn reindex_archive<P: AsRef<Path>>(path: P) -> Result<GlobalIndex> {
+        let path = path.as_ref();
+        let manifest = read_zip_entry(std::fs::File::open(path)?, "manifest.msgpack")?;
+        let manifest = crate::Manifest::from_msgpack(&manifest)?;
+
+        let mut index = GlobalIndex::new();
+        for cxp_ref in manifest.children.iter() {
+            index_tree(&mut index, vec![cxp_ref.id.clone()], cxp_ref, Location::File(path.to_path_buf()))?;
+        }
+        index.write_to(path)?;
+
+        Ok(index)
+    }
+
+    /// Snapshot of the global index
+    pub fn global_index(&self) -> Result<GlobalIndex> {
+        let index = self.global_index.read()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+        Ok(index.clone())
+    }
+
+    /// Store the global index in master.cxp (no-op without a master archive)
+    pub fn save_index(&self) -> Result<()> {
+        let master_path = self.master_path();
+        if !master_path.exists() {
+            return Ok(());
+        }
+
+        let index = self.global_index.read()
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+        index.write_to(&master_path)
     }
 
     /// Compact the global index (remove deleted entries)
     pub fn compact_index(&self) -> Result<()> {
         let mut index = self.global_index.write()
-            .map_err(|_| CxpError::Io("Lock poisoned".to_string()))?;
+            .map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
 
         index.compact();
         Ok(())
     }
 }
 
+/// Options for [`CxpManager::search_with`] and [`CxpReader`](crate::CxpReader) searches
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Load Cold children that are not cached yet (default: only search cached Cold CXPs)
+    pub load_cold: bool,
+    /// Stop searching further CXPs or query variants after this long and return partial results
+    pub timeout: Option<Duration>,
+}
+
+impl SearchOptions {
+    /// Load Cold children on demand
+    pub fn with_load_cold(mut self, load_cold: bool) -> Self {
+        self.load_cold = load_cold;
+        self
+    }
+
+    /// Set the search timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
 /// Search result with context
 #[derive(Debug, Clone)]
 pub struct SearchHit {
@@ -520,6 +894,78 @@ impl SearchHit {
     }
 }
 
+/// Where a CXP archive is stored
+#[derive(Debug, Clone)]
+enum Location {
+    /// A file on disk
+    File(PathBuf),
+    /// An entry inside another CXP archive
+    Embedded {
+        parent: Box<Location>,
+        path_in_zip: String,
+    },
+}
+
+impl Location {
+    /// Location of a referenced CXP whose parent archive is at `parent`
+    fn of(cxp_ref: &CxpRef, parent: Location) -> Result<Self> {
+        match &cxp_ref.storage {
+            CxpStorage::External { path } => Ok(Location::File(path.clone())),
+            CxpStorage::Embedded { path_in_zip } => Ok(Location::Embedded {
+                parent: Box::new(parent),
+                path_in_zip: path_in_zip.clone(),
+            }),
+            CxpStorage::Remote { url, .. } => Err(CxpError::io(format!(
+                "Remote CXP loading not yet implemented: {}",
+                url
+            ))),
+        }
+    }
+
+    /// Open the archive
+    fn open(&self) -> Result<CxpFile> {
+        match self {
+            Location::File(path) => CxpFile::open(path),
+            Location::Embedded { .. } => CxpFile::from_reader(Cursor::new(self.read()?)),
+        }
+    }
+
+    /// Read the whole archive
+    fn read(&self) -> Result<Vec<u8>> {
+        match self {
+            Location::File(path) => Ok(std::fs::read(path)?),
+            Location::Embedded { parent, path_in_zip } => match parent.as_ref() {
+                Location::File(path) => read_zip_entry(std::fs::File::open(path)?, path_in_zip),
+                embedded => read_zip_entry(Cursor::new(embedded.read()?), path_in_zip),
+            },
+        }
+    }
+}
+
+/// Index a CXP and, recursively, the children listed in its manifest
+fn index_tree(index: &mut GlobalIndex, cxp_path: Vec<String>, cxp_ref: &CxpRef, parent: Location) -> Result<()> {
+    let location = Location::of(cxp_ref, parent)?;
+    let cxp = location.open()?;
+    index.add_file_map(cxp_path.clone(), cxp_ref.tier, &cxp.file_map);
+
+    for child in cxp.manifest.children.iter() {
+        let mut child_path = cxp_path.clone();
+        child_path.push(child.id.clone());
+        index_tree(index, child_path, child, location.clone())?;
+    }
+
+    Ok(())
+}
+
+fn read_zip_entry<R: Read + Seek>(reader: R, name: &str) -> Result<Vec<u8>> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut entry = archive.by_name(name)
+        .map_err(|e| CxpError::InvalidFormat(format!("No {} found: {}", name, e)))?;
+    let mut data = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
+
 /// Memory usage statistics
 #[derive(Debug, Clone)]
 pub struct MemoryStats {
@@ -578,7 +1024,6 @@ fn format_bytes(bytes: usize) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::TempDir;
 
     #[test]
     fn test_manager_creation() {
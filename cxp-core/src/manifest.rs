@@ -1,11 +1,17 @@
 //! CXP Manifest - The "roadmap" for AI to understand the file
 //!
 //! Contains metadata, statistics, and structure information.
+//!
+//! Manifests are written as MessagePack maps keyed by field name, so readers
+//! tolerate fields added by newer writers. Such fields are kept in
+//! [`Manifest::unknown_fields`] and written back unchanged. Older archives
+//! store the manifest as a positional array and are still read.
 
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Serializer, Deserialize};
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use crate::format::FileMap;
 use crate::recursive::{ChildrenMap, FileTier};
 
 /// CXP Manifest - stored as manifest.msgpack in the CXP file
@@ -24,7 +30,7 @@ pub struct Manifest {
     pub stats: ManifestStats,
 
     /// File type breakdown
-    pub file_types: HashMap<String, FileTypeInfo>,
+    pub file_types: BTreeMap<String, FileTypeInfo>,
 
     /// Top topics/categories detected
     pub topics: Vec<String>,
@@ -41,7 +47,7 @@ pub struct Manifest {
     pub extensions: Vec<String>,
 
     /// Custom metadata
-    pub metadata: HashMap<String, String>,
+    pub metadata: BTreeMap<String, String>,
 
     // === Recursive CXP Support ===
 
@@ -68,6 +74,362 @@ pub struct Manifest {
     /// Last access time (for tier calculation)
     #[serde(default)]
     pub last_accessed: Option<DateTime<Utc>>,
+
+    /// Chunking algorithm of the build (`gear`, `buzhash`, `fixed:<size>`; None if unknown)
+    #[serde(default)]
+    pub chunker: Option<String>,
+
+    /// Chunk codec of the build (`zstd:<level>` or `lz4`; None if unknown, e.g. older zstd archives)
+    #[serde(default)]
+    pub compression: Option<String>,
+
+    /// Which embeddings have an int8 vector next to their binary one
+    #[serde(default)]
+    pub int8_embeddings: Int8Storage,
+
+    /// Oldest reader (`crate::VERSION`) allowed to open this archive
+    #[serde(default)]
+    pub min_reader_version: Option<String>,
+
+    /// Application-defined metadata (any JSON-compatible value)
+    #[serde(default, serialize_with = "serialize_sorted")]
+    pub custom_metadata: HashMap<String, serde_json::Value>,
+
+    /// Secrets and PII replaced with placeholders during the build (None if redaction did not run)
+    #[serde(default)]
+    pub redactions: Option<RedactionReport>,
+
+    /// Hugging Face ID of the embedding model (e.g. `BAAI/bge-small-en-v1.5`)
+    #[serde(default)]
+    pub embedding_model_id: Option<String>,
+
+    /// Revision of the embedding model weights (see `embeddings::model_revision`)
+    #[serde(default)]
+    pub embedding_model_revision: Option<String>,
+
+    /// Format of the stored rescoring vectors
+    #[serde(default)]
+    pub embedding_precision: EmbeddingPrecision,
+
+    /// HNSW parameters the search index was built with (None: `IndexParams::default()`)
+    #[serde(default)]
+    pub index_params: Option<IndexParams>,
+
+    /// Kind of vector index stored for text embeddings
+    #[serde(default)]
+    pub index_backend: IndexBackend,
+
+    /// Embedding rows of chunks removed by incremental updates (reclaimed by `cxp optimize`)
+    ///
+    /// Archives with tombstones require
+    /// [`EMBEDDING_TOMBSTONES_READER_VERSION`](crate::format::EMBEDDING_TOMBSTONES_READER_VERSION).
+    #[serde(default)]
+    pub embedding_tombstones: usize,
+
+    /// Fields written by a newer version of the format, preserved on rewrite
+    #[serde(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Names of the serialized manifest fields (anything else is an unknown field)
+const KNOWN_FIELDS: &[&str] = &[
+    "version",
+    "created_at",
+    "updated_at",
+    "stats",
+    "file_types",
+    "topics",
+    "embedding_model",
+    "embedding_dim",
+    "extensions",
+    "metadata",
+    "children",
+    "parent_path",
+    "tier",
+    "categories",
+    "keywords",
+    "last_accessed",
+    "chunker",
+    "compression",
+    "int8_embeddings",
+    "min_reader_version",
+    "custom_metadata",
+    "redactions",
+    "embedding_model_id",
+    "embedding_model_revision",
+    "embedding_precision",
+    "index_params",
+    "index_backend",
+    "embedding_tombstones",
+];
+
+/// Manifest plus its unknown fields, serialized as one map
+#[derive(Serialize)]
+struct NamedManifest<'a> {
+    #[serde(flatten)]
+    manifest: &'a Manifest,
+    #[serde(flatten)]
+    unknown_fields: &'a BTreeMap<String, serde_json::Value>,
+}
+
+/// Serialize a `HashMap` in key order so manifests are deterministic
+fn serialize_sorted<S: Serializer>(
+    map: &HashMap<String, serde_json::Value>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// Which embeddings store an int8 vector for rescoring
+///
+/// Binary vectors are always stored; search rescores candidates without an
+/// int8 vector by their binary similarity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Int8Storage {
+    /// Every embedding (binary + int8)
+    #[default]
+    All,
+    /// Only embeddings of chunks in hot-tier files (stored first)
+    HotOnly,
+    /// No int8 vectors (binary only, about half the size)
+    None,
+}
+
+impl std::str::FromStr for Int8Storage {
+    type Err = crate::CxpError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "all" => Ok(Self::All),
+            "hot" | "hot-only" => Ok(Self::HotOnly),
+            "none" | "binary" => Ok(Self::None),
+            _ => Err(crate::CxpError::InvalidFormat(format!(
+                "Unknown int8 storage '{}' (expected all, hot or none)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Format of the vectors search rescores binary candidates with
+///
+/// Binary vectors are always stored for the HNSW index. `Int8Storage` still
+/// selects which rows keep a rescoring vector.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EmbeddingPrecision {
+    /// No rescoring vectors (same as `Int8Storage::None`)
+    Binary,
+    /// Int8 vectors with a per-row scale (4x smaller than float32)
+    #[default]
+    Int8,
+    /// Half-precision floats (2x smaller than float32)
+    F16,
+    /// Unquantized float32 vectors
+    F32,
+}
+
+impl EmbeddingPrecision {
+    /// Archive entry holding the rescoring vectors (None for binary only)
+    pub fn path(&self) -> Option<&'static str> {
+        match self {
+            Self::Binary => None,
+            Self::Int8 => Some("embeddings/int8.bin"),
+            Self::F16 => Some("embeddings/f16.bin"),
+            Self::F32 => Some("embeddings/f32.bin"),
+        }
+    }
+
+    /// Whether rescoring vectors are stored as floats
+    pub fn is_float(&self) -> bool {
+        matches!(self, Self::F16 | Self::F32)
+    }
+}
+
+impl std::str::FromStr for EmbeddingPrecision {
+    type Err = crate::CxpError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "binary" => Ok(Self::Binary),
+            "int8" => Ok(Self::Int8),
+            "f16" | "float16" | "half" => Ok(Self::F16),
+            "f32" | "float32" | "float" => Ok(Self::F32),
+            _ => Err(crate::CxpError::InvalidFormat(format!(
+                "Unknown embedding precision '{}' (expected binary, int8, f16 or f32)",
+                s
+            ))),
+        }
+    }
+}
+
+/// HNSW graph parameters of the search index
+///
+/// Higher values improve recall at the cost of memory (`m`), build time
+/// (`ef_construction`) or query time (`ef_search`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexParams {
+    /// Connections per node (M)
+    pub m: usize,
+    /// Candidate list size while inserting (ef_construction)
+    pub ef_construction: usize,
+    /// Candidate list size while searching (ef_search)
+    pub ef_search: usize,
+}
+
+impl Default for IndexParams {
+    fn default() -> Self {
+        Self { m: 16, ef_construction: 128, ef_search: 64 }
+    }
+}
+
+impl IndexParams {
+    /// Set the connections per node (M)
+    pub fn with_m(mut self, m: usize) -> Self {
+        self.m = m;
+        self
+    }
+
+    /// Set the candidate list size while inserting
+    pub fn with_ef_construction(mut self, ef_construction: usize) -> Self {
+        self.ef_construction = ef_construction;
+        self
+    }
+
+    /// Set the candidate list size while searching
+    pub fn with_ef_search(mut self, ef_search: usize) -> Self {
+        self.ef_search = ef_search;
+        self
+    }
+
+    /// Check that the graph can be built with these parameters
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.m < 2 || self.ef_construction == 0 || self.ef_search == 0 {
+            return Err(crate::CxpError::Index(format!(
+                "Invalid HNSW parameters (M {}, ef_construction {}, ef_search {}): M must be at least 2 and ef values at least 1",
+                self.m, self.ef_construction, self.ef_search
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Vector index used for semantic search over text embeddings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexBackend {
+    /// HNSW graph (fastest queries, highest memory use; see `IndexParams`)
+    #[default]
+    Hnsw,
+    /// Inverted file over `nlist` binary k-means clusters, scanning the
+    /// `nprobe` nearest clusters per query (no graph, much less RAM)
+    IvfFlat { nlist: usize, nprobe: usize },
+}
+
+impl IndexBackend {
+    /// IVF-Flat with 1024 clusters and 16 probed per query
+    pub fn ivf_flat() -> Self {
+        Self::IvfFlat { nlist: 1024, nprobe: 16 }
+    }
+
+    /// Archive entry holding the index
+    pub fn path(&self) -> &'static str {
+        match self {
+            Self::Hnsw => "embeddings/index.hnsw",
+            Self::IvfFlat { .. } => "embeddings/index.ivf",
+        }
+    }
+
+    /// Check that the index can be built with these parameters
+    pub fn validate(&self) -> crate::Result<()> {
+        match *self {
+            Self::IvfFlat { nlist, nprobe } if nlist == 0 || nprobe == 0 || nprobe > nlist => {
+                Err(crate::CxpError::Index(format!(
+                    "Invalid IVF-Flat parameters (nlist {}, nprobe {}): both must be at least 1 and nprobe at most nlist",
+                    nlist, nprobe
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::str::FromStr for IndexBackend {
+    type Err = crate::CxpError;
+
+    /// Parse `hnsw` or `ivf-flat` (default nlist and nprobe)
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "hnsw" => Ok(Self::Hnsw),
+            "ivf-flat" | "ivfflat" | "ivf" => Ok(Self::ivf_flat()),
+            _ => Err(crate::CxpError::InvalidFormat(format!(
+                "Unknown index backend '{}' (expected hnsw or ivf-flat)",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for IndexBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Hnsw => write!(f, "HNSW"),
+            Self::IvfFlat { nlist, nprobe } => write!(f, "IVF-Flat (nlist {}, nprobe {})", nlist, nprobe),
+        }
+    }
+}
+
+/// Secrets and personal data found and replaced while building
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RedactionReport {
+    /// Number of redacted values
+    pub total: usize,
+    /// Redactions per kind (e.g. `aws-access-key`, `private-key`, `email`)
+    pub by_kind: BTreeMap<String, usize>,
+    /// Redactions per file path
+    pub files: BTreeMap<String, Vec<Redaction>>,
+}
+
+/// One redacted value (the value itself is never recorded)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redaction {
+    /// Kind of secret
+    pub kind: String,
+    /// 1-based line the value started on
+    pub line: usize,
+}
+
+impl RedactionReport {
+    /// Replace the redactions recorded for `path`
+    pub fn set_file(&mut self, path: &str, redactions: Vec<Redaction>) {
+        if redactions.is_empty() {
+            self.files.remove(path);
+        } else {
+            self.files.insert(path.to_string(), redactions);
+        }
+        self.recount();
+    }
+
+    /// Report restricted to the files of `file_map`
+    pub fn for_files(&self, file_map: &FileMap) -> Self {
+        let mut report = Self {
+            files: self
+                .files
+                .iter()
+                .filter(|(path, _)| file_map.files.contains_key(*path))
+                .map(|(path, redactions)| (path.clone(), redactions.clone()))
+                .collect(),
+            ..Default::default()
+        };
+        report.recount();
+        report
+    }
+
+    fn recount(&mut self) {
+        self.by_kind.clear();
+        for redaction in self.files.values().flatten() {
+            *self.by_kind.entry(redaction.kind.clone()).or_insert(0) += 1;
+        }
+        self.total = self.by_kind.values().sum();
+    }
 }
 
 /// Statistics about the CXP contents
@@ -106,6 +468,21 @@ pub struct FileTypeInfo {
 
     /// Total bytes for this file type
     pub total_bytes: u64,
+
+    /// Bytes of the unique chunks of this type before compression
+    #[serde(default)]
+    pub chunk_bytes: u64,
+
+    /// Bytes of those chunks after compression
+    #[serde(default)]
+    pub compressed_bytes: u64,
+}
+
+impl FileTypeInfo {
+    /// Compressed / uncompressed chunk bytes (None if not recorded)
+    pub fn compression_ratio(&self) -> Option<f64> {
+        (self.chunk_bytes > 0).then(|| self.compressed_bytes as f64 / self.chunk_bytes as f64)
+    }
 }
 
 impl Manifest {
@@ -117,12 +494,12 @@ impl Manifest {
             created_at: now,
             updated_at: now,
             stats: ManifestStats::default(),
-            file_types: HashMap::new(),
+            file_types: BTreeMap::new(),
             topics: Vec::new(),
             embedding_model: None,
             embedding_dim: None,
             extensions: Vec::new(),
-            metadata: HashMap::new(),
+            metadata: BTreeMap::new(),
             // Recursive CXP defaults
             children: ChildrenMap::new(),
             parent_path: None,
@@ -130,6 +507,19 @@ impl Manifest {
             categories: Vec::new(),
             keywords: Vec::new(),
             last_accessed: None,
+            chunker: None,
+            compression: None,
+            int8_embeddings: Int8Storage::All,
+            min_reader_version: None,
+            custom_metadata: HashMap::new(),
+            redactions: None,
+            embedding_model_id: None,
+            embedding_model_revision: None,
+            embedding_precision: EmbeddingPrecision::Int8,
+            index_params: None,
+            index_backend: IndexBackend::Hnsw,
+            embedding_tombstones: 0,
+            unknown_fields: BTreeMap::new(),
         }
     }
 
@@ -195,6 +585,8 @@ impl Manifest {
                 description: get_file_type_description(ext),
                 sample_files: Vec::new(),
                 total_bytes: 0,
+                chunk_bytes: 0,
+                compressed_bytes: 0,
             }
         });
 
@@ -207,14 +599,91 @@ impl Manifest {
         }
     }
 
-    /// Serialize to MessagePack
+    /// Record compressed vs. uncompressed chunk bytes per file type
+    ///
+    /// Each unique chunk counts once for every file type referencing it;
+    /// chunks without a known compressed size are skipped.
+    pub fn record_chunk_compression<F>(&mut self, file_map: &FileMap, compressed_size: F)
+    where
+        F: Fn(&str) -> Option<u64>,
+    {
+        let mut seen: HashSet<(String, &str)> = HashSet::new();
+        for info in self.file_types.values_mut() {
+            info.chunk_bytes = 0;
+            info.compressed_bytes = 0;
+        }
+
+        for entry in file_map.files.values() {
+            let ext = entry.extension.to_lowercase();
+            for chunk in &entry.chunks {
+                let Some(compressed) = compressed_size(&chunk.hash) else {
+                    continue;
+                };
+                if !seen.insert((ext.clone(), chunk.hash.as_str())) {
+                    continue;
+                }
+                if let Some(info) = self.file_types.get_mut(&ext) {
+                    info.chunk_bytes += chunk.length as u64;
+                    info.compressed_bytes += compressed;
+                }
+            }
+        }
+    }
+
+    /// Fail with an upgrade message if this reader is older than `min_reader_version`
+    pub fn check_reader_version(&self) -> crate::Result<()> {
+        let Some(ref required) = self.min_reader_version else {
+            return Ok(());
+        };
+        let parsed = parse_version(required).ok_or_else(|| {
+            crate::CxpError::Manifest(format!("Invalid min_reader_version '{}'", required))
+        })?;
+        if parse_version(crate::VERSION).is_some_and(|current| current < parsed) {
+            return Err(crate::CxpError::UnsupportedFormatVersion {
+                required: required.clone(),
+                current: crate::VERSION.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Raise `min_reader_version` to at least `version` (kept if already newer)
+    pub fn require_reader_version(&mut self, version: &str) {
+        let current = self.min_reader_version.as_deref().and_then(parse_version);
+        if current.is_none_or(|current| Some(current) < parse_version(version)) {
+            self.min_reader_version = Some(version.trim().to_string());
+        }
+    }
+
+    /// Set an application-defined metadata value
+    pub fn set_custom_metadata(&mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) {
+        self.custom_metadata.insert(key.into(), value.into());
+    }
+
+    /// Serialize to MessagePack (a map keyed by field name, unknown fields included)
     pub fn to_msgpack(&self) -> crate::Result<Vec<u8>> {
-        rmp_serde::to_vec(self).map_err(|e| crate::CxpError::Serialization(e.to_string()))
+        let named = NamedManifest {
+            manifest: self,
+            unknown_fields: &self.unknown_fields,
+        };
+        rmp_serde::to_vec_named(&named).map_err(|e| crate::CxpError::Serialization(e.to_string()))
     }
 
     /// Deserialize from MessagePack
+    ///
+    /// Accepts both the named and the older positional encoding; fields this
+    /// version does not know are kept in `unknown_fields`.
     pub fn from_msgpack(data: &[u8]) -> crate::Result<Self> {
-        rmp_serde::from_slice(data).map_err(|e| crate::CxpError::Serialization(e.to_string()))
+        let mut manifest: Self = rmp_serde::from_slice(data)
+            .map_err(|e| crate::CxpError::Serialization(e.to_string()))?;
+
+        if let Ok(serde_json::Value::Object(fields)) = rmp_serde::from_slice::<serde_json::Value>(data) {
+            manifest.unknown_fields = fields
+                .into_iter()
+                .filter(|(name, _)| !KNOWN_FIELDS.contains(&name.as_str()))
+                .collect();
+        }
+        Ok(manifest)
     }
 
     /// Serialize to JSON (for debugging/human reading)
@@ -229,6 +698,18 @@ impl Default for Manifest {
     }
 }
 
+/// Parse a `major.minor.patch` version (missing parts count as 0)
+pub(crate) fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim().trim_start_matches('v').splitn(3, '.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+    // Ignore pre-release / build suffixes ("1.2.0-rc.1")
+    let patch = parts
+        .next()
+        .map_or(Some(0), |p| p.split(['-', '+']).next().and_then(|p| p.parse().ok()))?;
+    Some((major, minor, patch))
+}
+
 /// Get human-readable description for a file extension
 fn get_file_type_description(ext: &str) -> String {
     match ext.to_lowercase().as_str() {
@@ -299,6 +780,89 @@ mod tests {
         assert!(manifest.file_types.is_empty());
     }
 
+    #[test]
+    fn test_min_reader_version() {
+        assert_eq!(parse_version("1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_version("v2"), Some((2, 0, 0)));
+        assert_eq!(parse_version("1.4.0-rc.1"), Some((1, 4, 0)));
+        assert_eq!(parse_version("one"), None);
+
+        let mut manifest = Manifest::new();
+        assert!(manifest.check_reader_version().is_ok());
+        manifest.min_reader_version = Some(crate::VERSION.to_string());
+        assert!(manifest.check_reader_version().is_ok());
+        manifest.min_reader_version = Some("999.0.0".to_string());
+        let err = manifest.check_reader_version().unwrap_err();
+        assert!(matches!(err, crate::CxpError::UnsupportedFormatVersion { .. }));
+        assert!(err.to_string().contains("999.0.0"));
+        manifest.min_reader_version = Some("latest".to_string());
+        assert!(manifest.check_reader_version().is_err());
+
+        manifest.min_reader_version = None;
+        manifest.require_reader_version("1.1.0");
+        assert_eq!(manifest.min_reader_version.as_deref(), Some("1.1.0"));
+        manifest.require_reader_version("1.0.0");
+        assert_eq!(manifest.min_reader_version.as_deref(), Some("1.1.0"));
+        manifest.require_reader_version("1.2.0");
+        assert_eq!(manifest.min_reader_version.as_deref(), Some("1.2.0"));
+    }
+
+    #[test]
+    fn test_int8_storage() {
+        assert_eq!(Manifest::new().int8_embeddings, Int8Storage::All);
+        assert_eq!("hot".parse::<Int8Storage>().unwrap(), Int8Storage::HotOnly);
+        assert_eq!("NONE".parse::<Int8Storage>().unwrap(), Int8Storage::None);
+        assert!("some".parse::<Int8Storage>().is_err());
+
+        let mut manifest = Manifest::new();
+        manifest.int8_embeddings = Int8Storage::HotOnly;
+        let restored = Manifest::from_msgpack(&manifest.to_msgpack().unwrap()).unwrap();
+        assert_eq!(restored.int8_embeddings, Int8Storage::HotOnly);
+    }
+
+    #[test]
+    fn test_index_params() {
+        assert_eq!(Manifest::new().index_params, None);
+        let params = IndexParams::default().with_m(32).with_ef_construction(200);
+        assert!(params.validate().is_ok());
+        assert!(params.with_m(1).validate().is_err());
+        assert!(params.with_ef_search(0).validate().is_err());
+
+        let mut manifest = Manifest::new();
+        manifest.index_params = Some(params);
+        let restored = Manifest::from_msgpack(&manifest.to_msgpack().unwrap()).unwrap();
+        assert_eq!(restored.index_params, Some(IndexParams { m: 32, ef_construction: 200, ef_search: 64 }));
+    }
+
+    #[test]
+    fn test_index_backend() {
+        assert_eq!(Manifest::new().index_backend, IndexBackend::Hnsw);
+        assert_eq!("ivf-flat".parse::<IndexBackend>().unwrap(), IndexBackend::ivf_flat());
+        assert!("flat".parse::<IndexBackend>().is_err());
+        assert!(IndexBackend::IvfFlat { nlist: 4, nprobe: 8 }.validate().is_err());
+        assert_eq!(IndexBackend::ivf_flat().path(), "embeddings/index.ivf");
+
+        let mut manifest = Manifest::new();
+        manifest.index_backend = IndexBackend::IvfFlat { nlist: 64, nprobe: 4 };
+        let restored = Manifest::from_msgpack(&manifest.to_msgpack().unwrap()).unwrap();
+        assert_eq!(restored.index_backend, manifest.index_backend);
+    }
+
+    #[test]
+    fn test_embedding_precision() {
+        assert_eq!(Manifest::new().embedding_precision, EmbeddingPrecision::Int8);
+        assert_eq!("F32".parse::<EmbeddingPrecision>().unwrap(), EmbeddingPrecision::F32);
+        assert_eq!("half".parse::<EmbeddingPrecision>().unwrap(), EmbeddingPrecision::F16);
+        assert!("f64".parse::<EmbeddingPrecision>().is_err());
+        assert_eq!(EmbeddingPrecision::Binary.path(), None);
+        assert_eq!(EmbeddingPrecision::F16.path(), Some("embeddings/f16.bin"));
+
+        let mut manifest = Manifest::new();
+        manifest.embedding_precision = EmbeddingPrecision::F16;
+        let restored = Manifest::from_msgpack(&manifest.to_msgpack().unwrap()).unwrap();
+        assert_eq!(restored.embedding_precision, EmbeddingPrecision::F16);
+    }
+
     #[test]
     fn test_manifest_serialization() {
         let mut manifest = Manifest::new();
@@ -316,4 +880,108 @@ mod tests {
         assert_eq!(restored.file_types.len(), 2);
         assert_eq!(restored.file_types.get("rs").unwrap().count, 2);
     }
+
+    #[test]
+    fn test_unknown_fields_round_trip() {
+        let mut manifest = Manifest::new();
+        manifest.set_custom_metadata("owner", "platform-team");
+        manifest.set_custom_metadata("review", serde_json::json!({ "required": true, "approvers": 2 }));
+
+        // A newer writer added fields this version does not know
+        let mut fields = match serde_json::to_value(&manifest).unwrap() {
+            serde_json::Value::Object(fields) => fields,
+            _ => unreachable!(),
+        };
+        fields.insert("retention_days".to_string(), serde_json::json!(30));
+        fields.insert("signing".to_string(), serde_json::json!({ "alg": "ed25519" }));
+        let newer = rmp_serde::to_vec_named(&fields).unwrap();
+
+        let restored = Manifest::from_msgpack(&newer).unwrap();
+        assert_eq!(restored.custom_metadata["owner"], "platform-team");
+        assert_eq!(restored.custom_metadata["review"]["approvers"], 2);
+        assert_eq!(restored.unknown_fields.len(), 2);
+        assert_eq!(restored.unknown_fields["retention_days"], 30);
+
+        // Rewriting keeps them
+        let rewritten = Manifest::from_msgpack(&restored.to_msgpack().unwrap()).unwrap();
+        assert_eq!(rewritten.unknown_fields, restored.unknown_fields);
+        assert_eq!(rewritten.custom_metadata, restored.custom_metadata);
+
+        // Older positional manifests are still read
+        let positional = rmp_serde::to_vec(&Manifest::new()).unwrap();
+        let legacy = Manifest::from_msgpack(&positional).unwrap();
+        assert!(legacy.unknown_fields.is_empty());
+        assert!(legacy.custom_metadata.is_empty());
+
+        // Every serialized field is known
+        let serialized = match serde_json::to_value(Manifest::new()).unwrap() {
+            serde_json::Value::Object(fields) => fields,
+            _ => unreachable!(),
+        };
+        assert!(serialized.keys().all(|name| KNOWN_FIELDS.contains(&name.as_str())));
+        assert_eq!(serialized.len(), KNOWN_FIELDS.len());
+    }
+
+    #[test]
+    fn test_chunk_compression_per_type() {
+        use crate::chunker::ChunkRef;
+        use crate::format::FileEntry;
+
+        let chunk = |hash: &str, length: usize| ChunkRef {
+            hash: hash.to_string(),
+            offset: 0,
+            length,
+        };
+        let entry = |path: &str, ext: &str, chunks: Vec<ChunkRef>| FileEntry {
+            path: path.to_string(),
+            extension: ext.to_string(),
+            size: chunks.iter().map(|c| c.length as u64).sum(),
+            chunks,
+            is_image: false,
+            modified: None,
+            provenance: None,
+            keywords: Vec::new(),
+        };
+
+        let mut file_map = FileMap::default();
+        // "shared" appears in both .rs files but counts once for the type
+        for e in [
+            entry("a.rs", "rs", vec![chunk("a", 1000), chunk("shared", 1000)]),
+            entry("b.rs", "rs", vec![chunk("shared", 1000)]),
+            entry("c.png", "png", vec![chunk("c", 1000), chunk("unknown", 10)]),
+        ] {
+            file_map.files.insert(e.path.clone(), e);
+        }
+
+        let mut manifest = Manifest::new();
+        for e in file_map.files.values() {
+            manifest.add_file_type(&e.extension, &e.path, e.size);
+        }
+        let sizes: BTreeMap<&str, u64> = [("a", 200), ("shared", 100), ("c", 990)].into();
+        manifest.record_chunk_compression(&file_map, |hash| sizes.get(hash).copied());
+
+        let rs = &manifest.file_types["rs"];
+        assert_eq!((rs.chunk_bytes, rs.compressed_bytes), (2000, 300));
+        assert_eq!(rs.compression_ratio(), Some(0.15));
+        let png = &manifest.file_types["png"];
+        assert_eq!((png.chunk_bytes, png.compressed_bytes), (1000, 990));
+
+        // Entries written before the fields existed decode with zeros
+        #[derive(Serialize)]
+        struct OldFileTypeInfo {
+            count: usize,
+            description: String,
+            sample_files: Vec<String>,
+            total_bytes: u64,
+        }
+        let old = OldFileTypeInfo {
+            count: 1,
+            description: "Rust".to_string(),
+            sample_files: vec!["src/main.rs".to_string()],
+            total_bytes: 1000,
+        };
+        let restored: FileTypeInfo = rmp_serde::from_slice(&rmp_serde::to_vec(&old).unwrap()).unwrap();
+        assert_eq!(restored.total_bytes, 1000);
+        assert_eq!(restored.compression_ratio(), None);
+    }
 }
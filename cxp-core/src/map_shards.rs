@@ -0,0 +1,154 @@
+//! Sharded File Map
+//!
+//! Archives store the file map as sorted shards so readers can load only the
+//! part of the tree they need (older archives use a single `file_map.msgpack`):
+//!
+//! ```text
+//! file_map/
+//! ├── index.msgpack        # Shard descriptors (first/last path per shard)
+//! ├── 00000.msgpack        # Files sorted by path, DEFAULT_SHARD_SIZE per shard
+//! └── ...
+//! ```
+
+use crate::format::FileMap;
+use crate::{CxpError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Path of the shard index inside the archive
+pub const SHARD_INDEX_PATH: &str = "file_map/index.msgpack";
+
+/// Default number of files per shard
+pub const DEFAULT_SHARD_SIZE: usize = 4096;
+
+/// A serialized shard: `(archive path, MessagePack bytes)`
+pub type SerializedShard = (String, Vec<u8>);
+
+/// Descriptor of a single file map shard
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardInfo {
+    /// Archive path of the shard
+    pub path: String,
+    /// First file path in the shard
+    pub first: String,
+    /// Last file path in the shard
+    pub last: String,
+    /// Number of files in the shard
+    pub files: usize,
+}
+
+/// Index of all file map shards (sorted by path range)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardIndex {
+    /// Shards in path order
+    pub shards: Vec<ShardInfo>,
+}
+
+impl ShardIndex {
+    /// Split a file map into shards of at most `shard_size` files
+    ///
+    /// Returns the index and the serialized shards as `(archive path, bytes)`.
+    pub fn build(file_map: &FileMap, shard_size: usize) -> Result<(Self, Vec<SerializedShard>)> {
+        let shard_size = shard_size.max(1);
+        let entries: Vec<_> = file_map.files.iter().collect();
+
+        let mut index = ShardIndex::default();
+        let mut shards = Vec::new();
+
+        for (i, group) in entries.chunks(shard_size).enumerate() {
+            let path = format!("file_map/{:05}.msgpack", i);
+            let shard = FileMap {
+                files: group.iter().map(|(k, v)| ((*k).clone(), (*v).clone())).collect(),
+            };
+
+            index.shards.push(ShardInfo {
+                path: path.clone(),
+                first: group[0].0.clone(),
+                last: group[group.len() - 1].0.clone(),
+                files: group.len(),
+            });
+            shards.push((path, rmp_serde::to_vec(&shard)?));
+        }
+
+        Ok((index, shards))
+    }
+
+    /// Shard that would contain `path`
+    pub fn shard_for_path(&self, path: &str) -> Option<usize> {
+        self.shards
+            .iter()
+            .position(|s| s.first.as_str() <= path && path <= s.last.as_str())
+    }
+
+    /// Shards that may contain paths starting with `prefix`
+    pub fn shards_for_prefix(&self, prefix: &str) -> Vec<usize> {
+        self.shards
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| {
+                s.last.as_str() >= prefix
+                    && (s.first.as_str() < prefix || s.first.starts_with(prefix))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Total number of files across all shards
+    pub fn total_files(&self) -> usize {
+        self.shards.iter().map(|s| s.files).sum()
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::FileEntry;
+
+    fn file_map(paths: &[&str]) -> FileMap {
+        let mut map = FileMap::default();
+        for path in paths {
+            map.files.insert(
+                path.to_string(),
+                FileEntry {
+                    path: path.to_string(),
+                    extension: "rs".to_string(),
+                    size: 0,
+                    chunks: Vec::new(),
+                    is_image: false,
+                    modified: None,
+                    provenance: None,
+                    keywords: Vec::new(),
+                },
+            );
+        }
+        map
+    }
+
+    #[test]
+    fn test_shard_prefix_lookup() {
+        let map = file_map(&["a.rs", "docs/x.md", "src/a.rs", "src/b.rs", "src/c.rs", "tests/t.rs"]);
+        let (index, shards) = ShardIndex::build(&map, 2).unwrap();
+
+        assert_eq!(shards.len(), 3);
+        assert_eq!(index.total_files(), 6);
+        assert_eq!(index.shards[1].first, "src/a.rs");
+
+        assert_eq!(index.shards_for_prefix("src/"), vec![1, 2]);
+        assert_eq!(index.shards_for_prefix("tests/"), vec![2]);
+        assert_eq!(index.shards_for_prefix("zzz/"), Vec::<usize>::new());
+        assert_eq!(index.shards_for_prefix("").len(), 3);
+
+        assert_eq!(index.shard_for_path("docs/x.md"), Some(0));
+        assert_eq!(index.shard_for_path("src/c.rs"), Some(2));
+        assert_eq!(index.shard_for_path("missing.rs"), None);
+    }
+}
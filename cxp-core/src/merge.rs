@@ -0,0 +1,574 @@
+//! Merging CXP Archives
+//!
+//! `CxpMerger` unions the file maps of several archives into one. Chunks are
+//! deduplicated across inputs and copied as stored (no recompression), the
+//! file map, bloom filters and TOC are rebuilt, and extension data is carried
+//! over. Inputs compressed with different trained dictionaries are the
+//! exception: their chunks are recompressed without a dictionary. Embeddings are merged by chunk hash and the HNSW index is rebuilt
+//! when every input was embedded with the same model (requires the
+//! `embeddings` and `search` features). Chunks pruned as near-duplicate
+//! embeddings keep their alias when their representative is merged too.
+
+use crate::annotations::{Annotations, ANNOTATIONS_PATH};
+use crate::symbols::{Symbol, SymbolIndex, SYMBOLS_PATH};
+use crate::build_info::BUILD_INFO_NAMESPACE;
+use crate::compress::{ChunkCodec, DICTIONARY_PATH};
+use crate::format::{read_chunk_codec, read_file_map, ArchiveWriter, FileEntry, FileMap};
+use crate::manifest::{parse_version, Manifest, RedactionReport};
+use crate::map_shards::DEFAULT_SHARD_SIZE;
+use crate::packs::PackIndex;
+use crate::{CxpError, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::format::EMBEDDING_ALIASES_PATH;
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::{
+    deserialize_binary_embeddings, deserialize_float_embeddings, deserialize_int8_embeddings,
+    serialize_binary_embeddings, serialize_int8_embeddings, BinaryEmbedding, EmbeddingPrecision, IndexBackend,
+    IndexParams, Int8Embedding, Int8Storage, VectorIndex,
+};
+
+/// What to do when two archives contain the same file path
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the entry from the first archive that has the path
+    #[default]
+    KeepFirst,
+    /// Keep the entry from the last archive that has the path
+    KeepLast,
+    /// Abort the merge
+    Fail,
+    /// Place every archive's files under a directory named after the archive
+    Prefix,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = CxpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "first" | "keep-first" => Ok(Self::KeepFirst),
+            "last" | "keep-last" => Ok(Self::KeepLast),
+            "fail" | "error" => Ok(Self::Fail),
+            "prefix" => Ok(Self::Prefix),
+            _ => Err(CxpError::InvalidFormat(format!(
+                "Unknown conflict policy '{}' (expected first, last, fail or prefix)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Summary of a merge
+#[derive(Debug, Clone, Default)]
+pub struct MergeStats {
+    /// Number of input archives
+    pub archives: usize,
+    /// Files in the merged archive
+    pub files: usize,
+    /// Paths present in more than one input
+    pub conflicts: usize,
+    /// Unique chunks in the merged archive
+    pub unique_chunks: usize,
+    /// Chunks present in more than one input (stored once)
+    pub shared_chunks: usize,
+    /// Whether embeddings and the search index were merged
+    pub embeddings: bool,
+}
+
+/// Merges several CXP archives into one
+#[derive(Debug, Clone)]
+pub struct CxpMerger {
+    policy: ConflictPolicy,
+    shard_size: usize,
+}
+
+impl Default for CxpMerger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An opened input archive
+struct Input {
+    path: PathBuf,
+    archive: ZipArchive<File>,
+    manifest: Manifest,
+    file_map: FileMap,
+    codec: ChunkCodec,
+    packs: PackIndex,
+    annotations: Annotations,
+    symbols: Option<SymbolIndex>,
+}
+
+impl CxpMerger {
+    /// Create a merger with the default conflict policy (keep first)
+    pub fn new() -> Self {
+        Self {
+            policy: ConflictPolicy::default(),
+            shard_size: DEFAULT_SHARD_SIZE,
+        }
+    }
+
+    /// Set the conflict policy
+    pub fn with_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the number of files per file map shard
+    pub fn with_shard_size(mut self, shard_size: usize) -> Self {
+        self.shard_size = shard_size.max(1);
+        self
+    }
+
+    /// Formerly set where the rebuilt search index was staged; has no effect
+    #[deprecated(since = "1.1.0", note = "indexes are serialized in memory; no temp files are written")]
+    pub fn with_temp_policy(self, _policy: crate::temp::TempPolicy) -> Self {
+        self
+    }
+
+    /// Merge `inputs` (in order) into a new archive at `output`
+    pub fn merge<P: AsRef<Path>, Q: AsRef<Path>>(&self, inputs: &[P], output: Q) -> Result<MergeStats> {
+        if inputs.is_empty() {
+            return Err(CxpError::InvalidFormat("No archives to merge".to_string()));
+        }
+
+        let mut archives = Vec::with_capacity(inputs.len());
+        for path in inputs {
+            let path = path.as_ref();
+            let mut archive = ZipArchive::new(File::open(path)?)?;
+            let manifest = Manifest::from_msgpack(&read_entry(&mut archive, "manifest.msgpack")?)?;
+            manifest.check_reader_version()?;
+            let file_map = read_file_map(&mut archive)?;
+            let codec = read_chunk_codec(&mut archive)?;
+            let packs = PackIndex::read_from_archive(&mut archive)?;
+            let annotations = Annotations::read_from_archive(&mut archive)?;
+            let symbols = SymbolIndex::read_from_archive(&mut archive)?;
+            archives.push(Input {
+                path: path.to_path_buf(),
+                archive,
+                manifest,
+                file_map,
+                codec,
+                packs,
+                annotations,
+                symbols,
+            });
+        }
+
+        let mut stats = MergeStats {
+            archives: archives.len(),
+            ..Default::default()
+        };
+
+        // Union of the file maps (annotations, redactions and symbols follow the file that is kept)
+        let mut file_map = FileMap::default();
+        let mut annotations = Annotations::new();
+        let mut redactions: Option<RedactionReport> = None;
+        let mut origins: HashMap<String, usize> = HashMap::new();
+        let prefixes: Vec<Option<String>> = archives
+            .iter()
+            .map(|input| match self.policy {
+                ConflictPolicy::Prefix => Some(archive_name(&input.path)),
+                _ => None,
+            })
+            .collect();
+        for (i, input) in archives.iter().enumerate() {
+            let prefix = &prefixes[i];
+
+            for entry in input.file_map.files.values() {
+                let path = match prefix {
+                    Some(ref prefix) => format!("{}/{}", prefix, entry.path),
+                    None => entry.path.clone(),
+                };
+
+                if file_map.files.contains_key(&path) {
+                    stats.conflicts += 1;
+                    match self.policy {
+                        ConflictPolicy::KeepFirst => continue,
+                        ConflictPolicy::KeepLast => {}
+                        ConflictPolicy::Fail | ConflictPolicy::Prefix => {
+                            return Err(CxpError::InvalidFormat(format!(
+                                "{} exists in more than one archive ({})",
+                                path,
+                                input.path.display()
+                            )));
+                        }
+                    }
+                }
+
+                match input.annotations.get(&entry.path) {
+                    Some(annotation) => annotations.files.insert(path.clone(), annotation.clone()),
+                    None => annotations.files.remove(&path),
+                };
+                if input.manifest.redactions.is_some() || redactions.is_some() {
+                    let found = input
+                        .manifest
+                        .redactions
+                        .as_ref()
+                        .and_then(|report| report.files.get(&entry.path))
+                        .cloned()
+                        .unwrap_or_default();
+                    redactions.get_or_insert_with(RedactionReport::default).set_file(&path, found);
+                }
+                let entry = FileEntry {
+                    path: path.clone(),
+                    ..entry.clone()
+                };
+                origins.insert(path.clone(), i);
+                file_map.files.insert(path, entry);
+            }
+        }
+
+        let symbols = archives.iter().any(|input| input.symbols.is_some()).then(|| {
+            let kept = archives.iter().enumerate().flat_map(|(i, input)| {
+                let prefix = &prefixes[i];
+                let origins = &origins;
+                input.symbols.iter().flat_map(|index| index.symbols()).filter_map(move |symbol| {
+                    let path = match prefix {
+                        Some(prefix) => format!("{}/{}", prefix, symbol.path),
+                        None => symbol.path.clone(),
+                    };
+                    (origins.get(&path) == Some(&i)).then(|| Symbol { path, ..symbol.clone() })
+                })
+            });
+            SymbolIndex::from_symbols(kept)
+        });
+
+        // First input holding each chunk, and how many inputs share it
+        let mut chunk_sources: HashMap<String, usize> = HashMap::new();
+        let mut chunk_inputs: HashMap<String, usize> = HashMap::new();
+        for (i, input) in archives.iter().enumerate() {
+            let hashes: BTreeSet<&str> = input
+                .file_map
+                .files
+                .values()
+                .flat_map(|e| e.chunks.iter().map(|c| c.hash.as_str()))
+                .collect();
+            for hash in hashes {
+                chunk_sources.entry(hash.to_string()).or_insert(i);
+                *chunk_inputs.entry(hash.to_string()).or_default() += 1;
+            }
+        }
+
+        let mut hashes: BTreeMap<&str, usize> = BTreeMap::new();
+        for entry in file_map.files.values() {
+            for chunk in &entry.chunks {
+                hashes.entry(chunk.hash.as_str()).or_insert(chunk.length);
+            }
+        }
+        stats.files = file_map.files.len();
+        stats.unique_chunks = hashes.len();
+        stats.shared_chunks = hashes.keys().filter(|h| chunk_inputs[**h] > 1).count();
+
+        // Manifest for the combined archive
+        let mut manifest = Manifest::new();
+        manifest.redactions = redactions;
+        let total_bytes: u64 = file_map.files.values().map(|e| e.size).sum();
+        let unique_bytes: u64 = hashes.values().map(|&len| len as u64).sum();
+        for entry in file_map.files.values() {
+            manifest.add_file_type(&entry.extension, &entry.path, entry.size);
+        }
+        manifest.stats.total_files = file_map.files.len();
+        manifest.stats.unique_chunks = hashes.len();
+        manifest.stats.original_size_bytes = total_bytes;
+        manifest.stats.dedup_savings_percent = if total_bytes > 0 {
+            total_bytes.saturating_sub(unique_bytes) as f64 / total_bytes as f64 * 100.0
+        } else {
+            0.0
+        };
+        if archives.iter().all(|input| input.manifest.compression == archives[0].manifest.compression) {
+            manifest.compression = archives[0].manifest.compression.clone();
+        }
+        if archives.iter().all(|input| input.manifest.chunker == archives[0].manifest.chunker) {
+            manifest.chunker = archives[0].manifest.chunker.clone();
+        }
+        // The merged archive needs a reader that can open every input
+        manifest.min_reader_version = archives
+            .iter()
+            .filter_map(|input| input.manifest.min_reader_version.clone())
+            .max_by_key(|version| parse_version(version));
+        for input in &archives {
+            for extension in &input.manifest.extensions {
+                if extension != "embeddings"
+                    && extension != BUILD_INFO_NAMESPACE
+                    && !manifest.extensions.contains(extension)
+                {
+                    manifest.extensions.push(extension.clone());
+                }
+            }
+            for (key, value) in &input.manifest.metadata {
+                manifest.metadata.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+            for (key, value) in &input.manifest.custom_metadata {
+                manifest.custom_metadata.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        let embeddings = merge_embeddings(&mut archives, &hashes)?;
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if let Some(ref merged) = embeddings {
+            manifest.embedding_model = Some(merged.model.clone());
+            manifest.embedding_dim = Some(merged.dimensions);
+            manifest.int8_embeddings = if merged.int8.is_empty() { Int8Storage::None } else { Int8Storage::All };
+            manifest.embedding_precision =
+                if merged.int8.is_empty() { EmbeddingPrecision::Binary } else { EmbeddingPrecision::Int8 };
+            manifest.index_params = Some(merged.params);
+            manifest.index_backend = merged.backend;
+            manifest.extensions.push("embeddings".to_string());
+        }
+
+        #[cfg(not(all(feature = "embeddings", feature = "search")))]
+        if archives.iter().any(|input| input.manifest.embedding_model.is_some()) {
+            tracing::warn!("Embeddings are not merged (requires the embeddings and search features)");
+        }
+
+        // Chunks are copied as-is when every input shares the same dictionary
+        // (or none); otherwise chunks of dictionary inputs are recompressed
+        let shared_dictionary = archives[1..]
+            .iter()
+            .all(|input| input.codec.dictionary() == archives[0].codec.dictionary());
+        let dictionary = if shared_dictionary {
+            archives[0].codec.dictionary().map(<[u8]>::to_vec)
+        } else {
+            tracing::info!("Inputs use different compression dictionaries, recompressing their chunks");
+            None
+        };
+        let plain = ChunkCodec::default();
+        let mut recompressed: HashMap<&str, Vec<u8>> = HashMap::new();
+        let mut compressed_sizes: HashMap<&str, u64> = HashMap::new();
+        for hash in hashes.keys() {
+            let input = &mut archives[chunk_sources[*hash]];
+            if !shared_dictionary && input.codec.dictionary().is_some() {
+                let data = input.codec.decompress(&input.packs.read_stored(&mut input.archive, hash)?)?;
+                let data = plain.compress(&data)?;
+                compressed_sizes.insert(hash, data.len() as u64);
+                recompressed.insert(hash, data);
+            } else if let Some(chunk) = input.packs.get(hash) {
+                compressed_sizes.insert(hash, chunk.length);
+            } else if let Ok(entry) = input.archive.by_name(&PackIndex::legacy_path(hash)) {
+                compressed_sizes.insert(hash, entry.compressed_size());
+            }
+        }
+        manifest.record_chunk_compression(&file_map, |hash| compressed_sizes.get(hash).copied());
+
+        // Write the combined archive
+        let mut writer = ArchiveWriter::create(output.as_ref())?;
+        writer.write_manifest(&manifest)?;
+        writer.write_file_map(&file_map, self.shard_size)?;
+        if let Some(ref dictionary) = dictionary {
+            writer.write(DICTIONARY_PATH, dictionary)?;
+        }
+
+        for hash in hashes.keys() {
+            match recompressed.remove(hash) {
+                Some(data) => writer.write_chunk(hash, &data)?,
+                None => {
+                    let input = &mut archives[chunk_sources[*hash]];
+                    let data = input.packs.read_stored(&mut input.archive, hash)?;
+                    writer.write_chunk(hash, &data)?;
+                }
+            }
+        }
+        writer.write_filters(&file_map)?;
+        if !annotations.is_empty() {
+            writer.write(ANNOTATIONS_PATH, &annotations.to_msgpack()?)?;
+        }
+        if let Some(ref symbols) = symbols {
+            writer.write(SYMBOLS_PATH, &symbols.to_msgpack()?)?;
+        }
+
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        if let Some(merged) = embeddings {
+            writer.write("embeddings/binary.bin", &serialize_binary_embeddings(&merged.binary)?)?;
+            if !merged.int8.is_empty() {
+                writer.write("embeddings/int8.bin", &serialize_int8_embeddings(&merged.int8)?)?;
+            }
+            writer.write("embeddings/chunk_ids.msgpack", &rmp_serde::to_vec(&merged.chunk_ids)?)?;
+            if !merged.aliases.is_empty() {
+                writer.write(EMBEDDING_ALIASES_PATH, &rmp_serde::to_vec(&merged.aliases)?)?;
+            }
+            writer.write(merged.backend.path(), &merged.index)?;
+            stats.embeddings = true;
+        }
+
+        // Extension data (first archive wins on conflicting entries); build
+        // telemetry describes the inputs' builds, not the merge, so it is dropped
+        let build_info_prefix = format!("extensions/{}/", BUILD_INFO_NAMESPACE);
+        let mut written: BTreeSet<String> = BTreeSet::new();
+        for input in &mut archives {
+            let names: Vec<String> = input
+                .archive
+                .file_names()
+                .filter(|name| name.starts_with("extensions/") && !name.starts_with(&build_info_prefix))
+                .map(str::to_string)
+                .collect();
+            for name in names {
+                if !written.insert(name.clone()) {
+                    tracing::warn!("Skipping duplicate extension entry {} from {:?}", name, input.path);
+                    continue;
+                }
+                let data = read_entry(&mut input.archive, &name)?;
+                writer.write(&name, &data)?;
+            }
+        }
+
+        writer.finish()?;
+
+        tracing::info!(
+            "Merged {} archives: {} files, {} unique chunks ({} shared), {} conflicts",
+            stats.archives,
+            stats.files,
+            stats.unique_chunks,
+            stats.shared_chunks,
+            stats.conflicts
+        );
+
+        Ok(stats)
+    }
+}
+
+/// Directory name used for an archive under [`ConflictPolicy::Prefix`]
+fn archive_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "archive".to_string())
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| CxpError::InvalidFormat(format!("No {} found: {}", name, e)))?;
+    let mut data = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Embeddings combined from all inputs, in merged chunk order
+#[cfg(all(feature = "embeddings", feature = "search"))]
+struct MergedEmbeddings {
+    model: String,
+    dimensions: usize,
+    /// HNSW parameters of the first input
+    params: IndexParams,
+    /// Index backend of the first input
+    backend: IndexBackend,
+    binary: Vec<BinaryEmbedding>,
+    int8: Vec<Int8Embedding>,
+    chunk_ids: Vec<String>,
+    /// Near-duplicate chunk -> embedded chunk
+    aliases: BTreeMap<String, String>,
+    /// Serialized HNSW index
+    index: Vec<u8>,
+}
+
+/// Merge embedding rows by chunk hash (None if any input lacks compatible embeddings)
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn merge_embeddings(
+    archives: &mut [Input],
+    hashes: &BTreeMap<&str, usize>,
+) -> Result<Option<MergedEmbeddings>> {
+    let model = archives[0].manifest.embedding_model.clone();
+    let dimensions = archives[0].manifest.embedding_dim;
+    let (Some(model), Some(dimensions)) = (model, dimensions) else {
+        return Ok(None);
+    };
+    if archives.iter().any(|a| {
+        a.manifest.embedding_model.as_deref() != Some(model.as_str()) || a.manifest.embedding_dim != Some(dimensions)
+    }) {
+        tracing::warn!("Inputs use different embedding models; embeddings are not merged");
+        return Ok(None);
+    }
+
+    // Int8 vectors are optional per row (hot-only or binary-only inputs)
+    let mut rows: HashMap<String, (BinaryEmbedding, Option<Int8Embedding>)> = HashMap::new();
+    let mut aliases: HashMap<String, String> = HashMap::new();
+    for input in archives.iter_mut() {
+        let Ok(chunk_ids) = read_entry(&mut input.archive, "embeddings/chunk_ids.msgpack") else {
+            tracing::warn!("{:?} has no embedding chunk mapping; embeddings are not merged", input.path);
+            return Ok(None);
+        };
+        let chunk_ids: Vec<String> = rmp_serde::from_slice(&chunk_ids)?;
+        let binary = deserialize_binary_embeddings(&read_entry(&mut input.archive, "embeddings/binary.bin")?)?;
+        // Float vectors (f16/f32 inputs) are merged as int8
+        let precision = input.manifest.embedding_precision;
+        let int8 = match precision.path().filter(|path| input.archive.index_for_name(path).is_some()) {
+            Some(path) if precision.is_float() => deserialize_float_embeddings(&read_entry(&mut input.archive, path)?, precision)?
+                .iter()
+                .map(|float| Int8Embedding::from_float(float))
+                .collect(),
+            _ => match input.archive.index_for_name("embeddings/int8.bin") {
+                Some(_) => deserialize_int8_embeddings(&read_entry(&mut input.archive, "embeddings/int8.bin")?)?,
+                None => Vec::new(),
+            },
+        };
+        let int8 = int8.into_iter().map(Some).chain(std::iter::repeat_with(|| None));
+        for (hash, row) in chunk_ids.into_iter().zip(binary.into_iter().zip(int8)) {
+            rows.entry(hash).or_insert(row);
+        }
+        if input.archive.index_for_name(EMBEDDING_ALIASES_PATH).is_some() {
+            let input_aliases: BTreeMap<String, String> =
+                rmp_serde::from_slice(&read_entry(&mut input.archive, EMBEDDING_ALIASES_PATH)?)?;
+            for (alias, representative) in input_aliases {
+                aliases.entry(alias).or_insert(representative);
+            }
+        }
+    }
+
+    // Chunks pruned as near-duplicates stay aliases of their (embedded) representative
+    let aliases: BTreeMap<String, String> = aliases
+        .into_iter()
+        .filter(|(alias, representative)| {
+            !rows.contains_key(alias) && rows.contains_key(representative) && hashes.contains_key(representative.as_str())
+        })
+        .collect();
+
+    let mut merged = MergedEmbeddings {
+        model,
+        dimensions,
+        params: archives[0].manifest.index_params.unwrap_or_default(),
+        backend: archives[0].manifest.index_backend,
+        binary: Vec::with_capacity(hashes.len()),
+        int8: Vec::with_capacity(hashes.len()),
+        chunk_ids: Vec::with_capacity(hashes.len()),
+        aliases: BTreeMap::new(),
+        index: Vec::new(),
+    };
+    for hash in hashes.keys() {
+        if aliases.contains_key(*hash) {
+            continue;
+        }
+        let Some((binary, int8)) = rows.remove(*hash) else {
+            tracing::warn!("Chunk {} has no embedding; embeddings are not merged", hash);
+            return Ok(None);
+        };
+        merged.binary.push(binary);
+        merged.int8.extend(int8);
+        merged.chunk_ids.push(hash.to_string());
+    }
+
+    // Merged rows are in hash order, so partial int8 coverage cannot be kept
+    if merged.int8.len() != merged.binary.len() {
+        tracing::info!(
+            "Only {} of {} embeddings have int8 vectors; the merged archive stores binary only",
+            merged.int8.len(),
+            merged.binary.len()
+        );
+        merged.int8.clear();
+    }
+
+    merged.aliases = aliases;
+
+    let index = VectorIndex::from_embeddings(dimensions, merged.backend, &merged.params, &merged.binary)?;
+    merged.backend = index.backend();
+    merged.index = index.to_bytes()?;
+
+    Ok(Some(merged))
+}
@@ -11,6 +11,7 @@
 //! - Text embeddings via SigLIP 2 text encoder
 //! - Binary and Int8 quantization support
 
+use crate::device::{session_builder, Device};
 use crate::{CxpError, Result};
 
 use ndarray::{Array3, Array4, Array2};
@@ -64,6 +65,8 @@ pub struct MultimodalEngine {
     tokenizer: tokenizers::Tokenizer,
     /// Maximum sequence length for text
     max_length: usize,
+    /// Device the encoders run on
+    device: Device,
 }
 
 #[cfg(feature = "multimodal")]
@@ -78,21 +81,25 @@ impl MultimodalEngine {
     ///   tokenizer.json
     /// ```
     pub fn load<P: AsRef<Path>>(model_dir: P) -> Result<Self> {
+        Self::load_with(model_dir, Device::Cpu)
+    }
+
+    /// Load SigLIP 2 to run on `device`, falling back to the CPU if the
+    /// device is not available
+    pub fn load_with<P: AsRef<Path>>(model_dir: P, device: Device) -> Result<Self> {
         let model_dir = model_dir.as_ref();
 
         // Load image encoder
         let image_path = model_dir.join("image_encoder.onnx");
-        let image_session = Session::builder()?
-            .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
-            .with_intra_threads(num_cpus::get())?
+        let (builder, device) = session_builder(device)?;
+        let image_session = builder
             .commit_from_file(&image_path)
             .map_err(|e| CxpError::Embedding(format!("Failed to load image encoder: {}", e)))?;
 
-        // Load text encoder
+        // Load text encoder (on the device the image encoder got)
         let text_path = model_dir.join("text_encoder.onnx");
-        let text_session = Session::builder()?
-            .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
-            .with_intra_threads(num_cpus::get())?
+        let (builder, _) = session_builder(device)?;
+        let text_session = builder
             .commit_from_file(&text_path)
             .map_err(|e| CxpError::Embedding(format!("Failed to load text encoder: {}", e)))?;
 
@@ -106,9 +113,15 @@ impl MultimodalEngine {
             text_session,
             tokenizer,
             max_length: 64, // SigLIP 2 typically uses shorter sequences
+            device,
         })
     }
 
+    /// Device the encoders run on (`Device::Cpu` after a fallback)
+    pub fn device(&self) -> Device {
+        self.device
+    }
+
     /// Embed a single image from file path
     ///
     /// Returns a 512-dimensional embedding vector.
@@ -305,7 +318,7 @@ fn normalize_embeddings(embeddings: Array2<f32>) -> Result<Array2<f32>> {
 /// For normalized vectors, this is simply the dot product.
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     assert_eq!(a.len(), b.len(), "Embeddings must have same dimension");
-    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+    crate::simd::dot_f32(a, b)
 }
 
 /// Compute cosine distance (1 - similarity)
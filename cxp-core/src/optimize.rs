@@ -0,0 +1,443 @@
+//! Optimizing CXP Archives
+//!
+//! `CxpOptimizer` repacks an archive after many incremental updates, delta
+//! applies or merges:
+//!
+//! - journaled updates (see [`crate::journal`]) are folded into the archive
+//! - chunk entries no file references any more, in the head or a snapshot,
+//!   are dropped
+//! - chunks are rewritten in file order, so a file's chunks sit next to each
+//!   other in the archive
+//! - zstd archives get a dictionary trained on the current chunks; the
+//!   recompressed chunks are kept only if they are smaller than the stored ones
+//! - the file map shards, bloom filters and TOC are rebuilt
+//! - tombstoned embedding rows (see `Manifest::embedding_tombstones`) and rows
+//!   of dropped chunks are removed and the search index is rebuilt from the
+//!   rest, without re-embedding anything (requires the `embeddings` and
+//!   `search` features)
+//!
+//! Every other entry is copied as stored.
+
+use crate::chunker::chunk_id;
+use crate::compress::{ChunkCodec, Codec, DICTIONARY_PATH};
+use crate::format::{compress_with_trained_dictionary, read_chunk_codec, read_file_map, ArchiveWriter};
+use crate::journal::{journal_base, Journal};
+use crate::manifest::Manifest;
+use crate::snapshots::SnapshotIndex;
+use crate::map_shards::DEFAULT_SHARD_SIZE;
+use crate::packs::PackIndex;
+use crate::toc::TOC_PATH;
+use crate::{CxpError, Result};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use zip::ZipArchive;
+
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::format::{compact_embedding_rows, retire_embedding_rows, EMBEDDING_ALIASES_PATH};
+#[cfg(all(feature = "embeddings", feature = "search"))]
+use crate::{
+    deserialize_binary_embeddings, deserialize_float_embeddings, deserialize_int8_embeddings,
+    serialize_binary_embeddings, serialize_float_embeddings, serialize_int8_embeddings, EmbeddingPrecision,
+    QuantizedEmbeddings, VectorIndex,
+};
+
+/// Summary of an optimization
+#[derive(Debug, Clone, Default)]
+pub struct OptimizeStats {
+    /// Archive size before, in bytes
+    pub bytes_before: u64,
+    /// Archive size after, in bytes
+    pub bytes_after: u64,
+    /// Chunks written
+    pub chunks: usize,
+    /// Chunk entries no file referenced (dropped)
+    pub orphaned_chunks: usize,
+    /// Stored chunk bytes before (dictionary included)
+    pub chunk_bytes_before: u64,
+    /// Stored chunk bytes after (dictionary included)
+    pub chunk_bytes_after: u64,
+    /// Whether the chunks were recompressed
+    pub recompressed: bool,
+    /// Size of the dictionary the archive ends up with
+    pub dictionary_bytes: Option<usize>,
+    /// Embedding rows dropped (tombstoned or of dropped chunks)
+    pub embeddings_dropped: usize,
+    /// Embedding rows left
+    pub embeddings_kept: usize,
+    /// Whether the search index was rebuilt
+    pub index_rebuilt: bool,
+    /// Wall-clock time of the rewrite
+    pub elapsed: Duration,
+}
+
+impl OptimizeStats {
+    /// Bytes saved (negative if the archive grew)
+    pub fn bytes_saved(&self) -> i64 {
+        self.bytes_before as i64 - self.bytes_after as i64
+    }
+}
+
+/// Repacks an archive without the leftovers of incremental updates
+#[derive(Debug, Clone)]
+pub struct CxpOptimizer {
+    retrain_dictionary: bool,
+    shard_size: usize,
+}
+
+impl Default for CxpOptimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CxpOptimizer {
+    /// Create an optimizer that retrains the dictionary
+    pub fn new() -> Self {
+        Self {
+            retrain_dictionary: true,
+            shard_size: DEFAULT_SHARD_SIZE,
+        }
+    }
+
+    /// Whether to try a freshly trained dictionary (otherwise chunks are copied as stored)
+    pub fn with_retrain_dictionary(mut self, retrain: bool) -> Self {
+        self.retrain_dictionary = retrain;
+        self
+    }
+
+    /// Set the number of files per file map shard
+    pub fn with_shard_size(mut self, shard_size: usize) -> Self {
+        self.shard_size = shard_size.max(1);
+        self
+    }
+
+    /// Formerly set where the rebuilt search index was staged; has no effect
+    #[deprecated(since = "1.1.0", note = "indexes are serialized in memory; no temp files are written")]
+    pub fn with_temp_policy(self, _policy: crate::temp::TempPolicy) -> Self {
+        self
+    }
+
+    /// Optimize `input` into a new archive at `output` (must be a different path)
+    pub fn optimize<P: AsRef<Path>, Q: AsRef<Path>>(&self, input: P, output: Q) -> Result<OptimizeStats> {
+        let started = Instant::now();
+        let input = input.as_ref();
+        let output = output.as_ref();
+        let mut stats = OptimizeStats {
+            bytes_before: std::fs::metadata(input)?.len(),
+            ..OptimizeStats::default()
+        };
+
+        let mut archive = ZipArchive::new(File::open(input)?)?;
+        let mut manifest = Manifest::from_msgpack(&read_entry(&mut archive, "manifest.msgpack")?)?;
+        let mut file_map = read_file_map(&mut archive)?;
+        let codec = read_chunk_codec(&mut archive)?;
+        let packs = PackIndex::read_from_archive(&mut archive)?;
+
+        let journal = Journal::open(input, &journal_base(&manifest))?;
+        if !journal.is_empty() {
+            journal.apply(&mut file_map);
+            manifest.file_types.clear();
+            for entry in file_map.files.values() {
+                manifest.add_file_type(&entry.extension, &entry.path, entry.size);
+            }
+            manifest.stats.total_files = file_map.files.len();
+            manifest.touch();
+            tracing::info!("Folding {} journal records into the archive", journal.records);
+        }
+
+        // Referenced chunks in file order (files by path), then those only snapshots reference
+        let snapshot_hashes = SnapshotIndex::read_from_archive(&mut archive)?.referenced_chunks(&mut archive)?;
+        let mut seen = HashSet::new();
+        let mut hashes: Vec<&str> = file_map
+            .files
+            .values()
+            .flat_map(|entry| entry.chunks.iter().map(|c| c.hash.as_str()))
+            .filter(|hash| seen.insert(*hash))
+            .collect();
+        let head_chunks = hashes.len();
+        hashes.extend(snapshot_hashes.iter().map(String::as_str).filter(|hash| seen.insert(*hash)));
+        let ids: HashSet<&str> = hashes.iter().map(|hash| chunk_id(hash)).collect();
+        stats.orphaned_chunks = packs
+            .stored_sizes(&mut archive)?
+            .keys()
+            .filter(|id| !ids.contains(id.as_str()))
+            .count();
+        stats.chunks = hashes.len();
+
+        let mut stored = Vec::with_capacity(hashes.len());
+        for hash in &hashes {
+            match journal.chunks.get(*hash) {
+                Some(data) => stored.push(data.clone()),
+                None => stored.push(packs.read_stored(&mut archive, hash)?),
+            }
+        }
+        let old_dictionary = codec.dictionary().map_or(0, <[u8]>::len);
+        stats.chunk_bytes_before = stored.iter().map(|data| data.len() as u64).sum::<u64>() + old_dictionary as u64;
+
+        let mut dictionary = codec.dictionary().map(<[u8]>::to_vec);
+        if self.retrain_dictionary {
+            if let Some((new_codec, recompressed)) = self.recompress(&manifest, &codec, &stored)? {
+                let new_dictionary = new_codec.dictionary().map(<[u8]>::to_vec);
+                let new_bytes = recompressed.iter().map(Vec::len).sum::<usize>() + new_dictionary.as_ref().map_or(0, Vec::len);
+                if (new_bytes as u64) < stats.chunk_bytes_before {
+                    stored = recompressed;
+                    dictionary = new_dictionary;
+                    stats.recompressed = true;
+                } else {
+                    tracing::info!("Recompressed chunks are not smaller, keeping them as stored");
+                }
+            }
+        }
+        stats.dictionary_bytes = dictionary.as_ref().map(Vec::len);
+        stats.chunk_bytes_after =
+            stored.iter().map(|data| data.len() as u64).sum::<u64>() + stats.dictionary_bytes.unwrap_or(0) as u64;
+
+        // Entries replaced by the optimizer (an empty one is dropped)
+        #[cfg(all(feature = "embeddings", feature = "search"))]
+        let replaced = if manifest.embedding_dim.is_some()
+            && archive.index_for_name("embeddings/chunk_ids.msgpack").is_some()
+        {
+            let referenced: HashSet<&str> = hashes[..head_chunks].iter().copied().collect();
+            self.compact_embeddings(&mut archive, &mut manifest, &referenced, &mut stats)?
+        } else {
+            BTreeMap::new()
+        };
+        #[cfg(not(all(feature = "embeddings", feature = "search")))]
+        let replaced: BTreeMap<String, Vec<u8>> = {
+            if manifest.embedding_tombstones > 0 {
+                tracing::warn!(
+                    "{} tombstoned embedding rows kept (compacting needs the embeddings and search features)",
+                    manifest.embedding_tombstones
+                );
+            }
+            BTreeMap::new()
+        };
+
+        let compressed_sizes: HashMap<&str, u64> =
+            hashes.iter().zip(&stored).map(|(hash, data)| (*hash, data.len() as u64)).collect();
+        manifest.stats.unique_chunks = head_chunks;
+        manifest.record_chunk_compression(&file_map, |hash| compressed_sizes.get(hash).copied());
+
+        // Write the repacked archive in the builder's order
+        let mut writer = ArchiveWriter::create(output)?;
+        writer.write_manifest(&manifest)?;
+        writer.write_file_map(&file_map, self.shard_size)?;
+        if let Some(ref dictionary) = dictionary {
+            writer.write(DICTIONARY_PATH, dictionary)?;
+        }
+        for (hash, data) in hashes.iter().zip(&stored) {
+            writer.write_chunk(hash, data)?;
+        }
+        writer.write_filters(&file_map)?;
+        for (name, data) in &replaced {
+            if !data.is_empty() {
+                writer.write(name, data)?;
+            }
+        }
+
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let name = entry.name().to_string();
+            if is_rebuilt(&name) || replaced.contains_key(&name) {
+                continue;
+            }
+            let mut data = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut data)?;
+            writer.write(&name, &data)?;
+        }
+        writer.finish()?;
+
+        stats.bytes_after = std::fs::metadata(output)?.len();
+        stats.elapsed = started.elapsed();
+        tracing::info!(
+            "Optimized {:?}: {} chunks ({} orphaned dropped), {} -> {} bytes in {:.2}s",
+            input,
+            stats.chunks,
+            stats.orphaned_chunks,
+            stats.bytes_before,
+            stats.bytes_after,
+            stats.elapsed.as_secs_f64()
+        );
+        Ok(stats)
+    }
+
+    /// Recompress the chunks with a freshly trained dictionary, or plain if it does not pay off
+    ///
+    /// None for codecs without dictionary support.
+    fn recompress(
+        &self,
+        manifest: &Manifest,
+        codec: &ChunkCodec,
+        stored: &[Vec<u8>],
+    ) -> Result<Option<(ChunkCodec, Vec<Vec<u8>>)>> {
+        let target: Codec = match manifest.compression.as_deref() {
+            Some(name) => name.parse()?,
+            None => Codec::default(),
+        };
+        let Some(level) = target.zstd_level() else {
+            tracing::info!("{} does not support dictionaries, chunks are copied as stored", target.name());
+            return Ok(None);
+        };
+
+        let data: Vec<Vec<u8>> = stored.par_iter().map(|data| codec.decompress(data)).collect::<Result<_>>()?;
+        let plain_codec = ChunkCodec::new(target);
+        let plain: Vec<Vec<u8>> = data.par_iter().map(|data| plain_codec.compress(data)).collect::<Result<_>>()?;
+        let slices: Vec<&[u8]> = data.iter().map(Vec::as_slice).collect();
+        Ok(Some(compress_with_trained_dictionary(&slices, &plain, level)?.unwrap_or((plain_codec, plain))))
+    }
+
+    /// Drop tombstoned rows and rows of unreferenced chunks, then rebuild the search index
+    ///
+    /// Returns the rewritten entries; an empty entry drops it (rescoring
+    /// vectors and aliases vanish with their last row).
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    fn compact_embeddings(
+        &self,
+        archive: &mut ZipArchive<File>,
+        manifest: &mut Manifest,
+        referenced: &HashSet<&str>,
+        stats: &mut OptimizeStats,
+    ) -> Result<BTreeMap<String, Vec<u8>>> {
+        let dimensions = manifest
+            .embedding_dim
+            .ok_or_else(|| CxpError::Embedding("No embedding dimension in manifest".to_string()))?;
+        let precision = manifest.embedding_precision;
+
+        let mut chunk_ids: Vec<String> =
+            rmp_serde::from_slice(&read_entry(archive, "embeddings/chunk_ids.msgpack")?)?;
+        let mut aliases: BTreeMap<String, String> = match archive.index_for_name(EMBEDDING_ALIASES_PATH) {
+            Some(_) => rmp_serde::from_slice(&read_entry(archive, EMBEDDING_ALIASES_PATH)?)?,
+            None => BTreeMap::new(),
+        };
+        let mut embeddings = QuantizedEmbeddings {
+            binary: deserialize_binary_embeddings(&read_entry(archive, "embeddings/binary.bin")?)?,
+            int8: Vec::new(),
+            float: Vec::new(),
+        };
+        match precision.path().filter(|path| archive.index_for_name(path).is_some()) {
+            Some(path) if precision.is_float() => {
+                embeddings.float = deserialize_float_embeddings(&read_entry(archive, path)?, precision)?;
+            }
+            Some(path) => embeddings.int8 = deserialize_int8_embeddings(&read_entry(archive, path)?)?,
+            None => {}
+        }
+        if chunk_ids.len() != embeddings.binary.len() {
+            return Err(CxpError::Embedding(format!(
+                "{} embedding rows for {} chunk IDs",
+                embeddings.binary.len(),
+                chunk_ids.len()
+            )));
+        }
+
+        retire_embedding_rows(&mut chunk_ids, &mut aliases, |hash| referenced.contains(hash));
+        stats.embeddings_dropped = compact_embedding_rows(&mut embeddings, &mut chunk_ids);
+        stats.embeddings_kept = chunk_ids.len();
+
+        let params = manifest.index_params.unwrap_or_default();
+        let index = VectorIndex::from_embeddings(dimensions, manifest.index_backend, &params, &embeddings.binary)?;
+        let index_data = index.to_bytes()?;
+        stats.index_rebuilt = true;
+
+        let mut replaced = BTreeMap::new();
+        replaced.insert("embeddings/binary.bin".to_string(), serialize_binary_embeddings(&embeddings.binary)?);
+        replaced.insert("embeddings/chunk_ids.msgpack".to_string(), rmp_serde::to_vec(&chunk_ids)?);
+        let aliases_data = if aliases.is_empty() { Vec::new() } else { rmp_serde::to_vec(&aliases)? };
+        replaced.insert(EMBEDDING_ALIASES_PATH.to_string(), aliases_data);
+        if let Some(path) = precision.path() {
+            let data = match precision {
+                EmbeddingPrecision::Int8 if !embeddings.int8.is_empty() => serialize_int8_embeddings(&embeddings.int8)?,
+                _ if !embeddings.float.is_empty() => serialize_float_embeddings(&embeddings.float, precision)?,
+                _ => Vec::new(),
+            };
+            replaced.insert(path.to_string(), data);
+        }
+        manifest.index_backend = index.backend();
+        replaced.insert(index.backend().path().to_string(), index_data);
+        manifest.embedding_tombstones = 0;
+
+        tracing::info!(
+            "Dropped {} embedding rows, rebuilt {} index with {} vectors",
+            stats.embeddings_dropped,
+            index.backend(),
+            index.len()
+        );
+        Ok(replaced)
+    }
+}
+
+/// Entries the optimizer always writes itself
+fn is_rebuilt(name: &str) -> bool {
+    name == "manifest.msgpack"
+        || name == "file_map.msgpack"
+        || name == TOC_PATH
+        || name == DICTIONARY_PATH
+        || name.starts_with("file_map/")
+        || name.starts_with("chunks/")
+        || name.starts_with("filters/")
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| CxpError::InvalidFormat(format!("No {} found: {}", name, e)))?;
+    let mut data = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CxpBuilder, CxpReader};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_optimize_drops_orphans() {
+        let source = TempDir::new().unwrap();
+        for i in 0..40 {
+            let body = format!("pub fn handler_{i}(request: &Request) -> Response {{\n    Response::ok({i})\n}}\n");
+            std::fs::write(source.path().join(format!("handler_{i}.rs")), body.repeat(20)).unwrap();
+        }
+
+        let out = TempDir::new().unwrap();
+        let input = out.path().join("input.cxp");
+        let patched = out.path().join("patched.cxp");
+        let output = out.path().join("output.cxp");
+        let mut builder = CxpBuilder::new(source.path());
+        builder.scan().unwrap().process().unwrap().build(&input).unwrap();
+
+        // A chunk entry nothing references, as left behind by a removed file
+        let orphan = PackIndex::legacy_path(&"f".repeat(64));
+        let mut archive = ZipArchive::new(File::open(&input).unwrap()).unwrap();
+        let mut writer = ArchiveWriter::create(&patched).unwrap();
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).unwrap();
+            let name = entry.name().to_string();
+            if name == TOC_PATH {
+                continue;
+            }
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).unwrap();
+            writer.write(&name, &data).unwrap();
+        }
+        writer.write(&orphan, &crate::compress::compress(&[7u8; 4096]).unwrap()).unwrap();
+        writer.finish().unwrap();
+
+        let stats = CxpOptimizer::new().optimize(&patched, &output).unwrap();
+        assert_eq!(stats.orphaned_chunks, 1);
+        assert_eq!(stats.chunks, 40);
+        assert!(stats.chunk_bytes_after <= stats.chunk_bytes_before);
+        assert!(stats.bytes_saved() > 0);
+
+        let archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        assert!(archive.index_for_name(&orphan).is_none());
+        let reader = CxpReader::open(&output).unwrap();
+        assert_eq!(reader.manifest().stats.unique_chunks, 40);
+        let expected = std::fs::read(source.path().join("handler_7.rs")).unwrap();
+        assert_eq!(reader.read_file("handler_7.rs").unwrap(), expected);
+    }
+}
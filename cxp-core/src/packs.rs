@@ -0,0 +1,358 @@
+//! Chunk Packs
+//!
+//! One ZIP entry per chunk means hundreds of thousands of central directory
+//! records for a large tree, all of which are parsed on every open. New
+//! archives store their chunks back to back in a few large pack entries
+//! instead, plus an offset table locating each chunk:
+//!
+//! ```text
+//! chunks/
+//! ├── packs.msgpack        # PackIndex: chunk id -> (pack, offset, length)
+//! ├── pack-000.bin         # Stored (compressed) chunks, up to DEFAULT_PACK_SIZE each
+//! └── pack-001.bin
+//! ```
+//!
+//! Packs are stored uncompressed like every other entry, so a chunk is read
+//! with a seek into its pack. Archives with one `chunks/<id>.zst` entry per
+//! chunk stay readable; `CxpBuilder::with_packed_chunks(false)` still writes
+//! them for readers that predate packs. Packed archives set the manifest's
+//! `min_reader_version` to at least [`PACKS_READER_VERSION`], so those readers
+//! refuse them instead of reporting every chunk missing.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom};
+
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+use crate::chunker::chunk_id;
+use crate::{CxpError, Result};
+
+/// Entry holding the offset table of the packs
+pub const PACK_INDEX_PATH: &str = "chunks/packs.msgpack";
+
+/// Oldest reader that understands chunk packs (required by every packed archive)
+pub const PACKS_READER_VERSION: &str = "1.1.0";
+
+/// Size at which a pack is closed and the next one started (64 MiB)
+pub const DEFAULT_PACK_SIZE: u64 = 64 << 20;
+
+/// Where a chunk is stored inside the packs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackedChunk {
+    /// Pack number (see [`PackIndex::pack_path`])
+    pub pack: u32,
+    /// Offset of the stored chunk in the pack
+    pub offset: u64,
+    /// Stored (compressed) length
+    pub length: u64,
+}
+
+/// Offset table of an archive's chunk packs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PackIndex {
+    /// Number of pack entries
+    pub packs: u32,
+    /// Location of each chunk by id (the first 16 hex chars of its SHA-256)
+    pub chunks: BTreeMap<String, PackedChunk>,
+}
+
+impl PackIndex {
+    /// Entry name of pack `pack`
+    pub fn pack_path(pack: u32) -> String {
+        format!("chunks/pack-{:03}.bin", pack)
+    }
+
+    /// Whether the archive has no packed chunks
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Number of packed chunks
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Pack number of a pack entry name (None for any other entry)
+    pub fn pack_number(name: &str) -> Option<u32> {
+        name.strip_prefix("chunks/pack-")?.strip_suffix(".bin")?.parse().ok()
+    }
+
+    /// Number of chunks stored in pack `pack`
+    pub fn chunks_in(&self, pack: u32) -> usize {
+        self.chunks.values().filter(|chunk| chunk.pack == pack).count()
+    }
+
+    /// Location of the chunk with the given hash
+    pub fn get(&self, hash: &str) -> Option<&PackedChunk> {
+        self.chunks.get(chunk_id(hash))
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Read the offset table of an archive (empty if its chunks are not packed)
+    pub fn read_from_archive<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Self> {
+        let mut entry = match archive.by_name(PACK_INDEX_PATH) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        Self::from_msgpack(&data)
+    }
+
+    /// Entry name of a chunk stored on its own (archives before packs)
+    pub fn legacy_path(hash: &str) -> String {
+        format!("chunks/{}.zst", chunk_id(hash))
+    }
+
+    /// Stored bytes of a chunk, packed or in its own entry
+    pub fn read_stored<R: Read + Seek>(&self, archive: &mut ZipArchive<R>, hash: &str) -> Result<Vec<u8>> {
+        if let Some(data) = self.read_chunk(archive, hash)? {
+            return Ok(data);
+        }
+        let mut entry = match archive.by_name(&Self::legacy_path(hash)) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => return Err(CxpError::ChunkMissing { hash: hash.to_string() }),
+            Err(e) => return Err(e.into()),
+        };
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Whether a chunk is stored, packed or in its own entry
+    pub fn contains<R: Read + Seek>(&self, archive: &ZipArchive<R>, hash: &str) -> bool {
+        self.get(hash).is_some() || archive.index_for_name(&Self::legacy_path(hash)).is_some()
+    }
+
+    /// Stored size of every chunk in the archive by id, packed or in its own entry
+    pub fn stored_sizes<R: Read + Seek>(&self, archive: &mut ZipArchive<R>) -> Result<BTreeMap<String, u64>> {
+        let mut sizes: BTreeMap<String, u64> =
+            self.chunks.iter().map(|(id, chunk)| (id.clone(), chunk.length)).collect();
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i)?;
+            if let Some(id) = entry.name().strip_prefix("chunks/").and_then(|n| n.strip_suffix(".zst")) {
+                sizes.insert(id.to_string(), entry.compressed_size());
+            }
+        }
+        Ok(sizes)
+    }
+
+    /// Stored bytes of a packed chunk (None if the chunk is not in a pack)
+    pub fn read_chunk<R: Read + Seek>(&self, archive: &mut ZipArchive<R>, hash: &str) -> Result<Option<Vec<u8>>> {
+        let Some(location) = self.get(hash) else {
+            return Ok(None);
+        };
+        let name = Self::pack_path(location.pack);
+        let index = archive
+            .index_for_name(&name)
+            .ok_or_else(|| CxpError::InvalidFormat(format!("No {} found", name)))?;
+        // Check the offset table against the pack before allocating
+        let size = archive.by_index_raw(index)?.size();
+        if location.offset.checked_add(location.length).is_none_or(|end| end > size) {
+            return Err(CxpError::InvalidFormat(format!(
+                "Chunk {} at {}+{} is past the end of {} ({} bytes)",
+                hash, location.offset, location.length, name, size
+            )));
+        }
+        let mut pack = archive.by_index_seek(index)?;
+        let mut data = vec![0u8; location.length as usize];
+        pack.seek(SeekFrom::Start(location.offset))?;
+        pack.read_exact(&mut data)?;
+        Ok(Some(data))
+    }
+}
+
+/// A closed pack, ready to be written as one entry
+pub(crate) struct Pack {
+    /// Entry name
+    pub(crate) path: String,
+    /// Stored chunks, back to back
+    pub(crate) data: Vec<u8>,
+    /// Number of chunks in `data`
+    pub(crate) chunks: usize,
+}
+
+/// Collects stored chunks into packs while an archive is written
+pub(crate) struct PackWriter {
+    pack_size: u64,
+    current: Vec<u8>,
+    current_chunks: usize,
+    index: PackIndex,
+}
+
+impl PackWriter {
+    /// Start packing with packs of about `pack_size` bytes
+    pub(crate) fn new(pack_size: u64) -> Self {
+        Self {
+            pack_size: pack_size.max(1),
+            current: Vec::new(),
+            current_chunks: 0,
+            index: PackIndex::default(),
+        }
+    }
+
+    /// Add a stored chunk; returns the previous pack once it is full
+    ///
+    /// A chunk already packed is skipped.
+    pub(crate) fn push(&mut self, hash: &str, data: &[u8]) -> Option<Pack> {
+        if self.index.get(hash).is_some() {
+            return None;
+        }
+        let full = if !self.current.is_empty() && (self.current.len() + data.len()) as u64 > self.pack_size {
+            self.close()
+        } else {
+            None
+        };
+        self.index.chunks.insert(
+            chunk_id(hash).to_string(),
+            PackedChunk {
+                pack: self.index.packs,
+                offset: self.current.len() as u64,
+                length: data.len() as u64,
+            },
+        );
+        self.current.extend_from_slice(data);
+        self.current_chunks += 1;
+        full
+    }
+
+    /// The last pack (if any chunk went into it) and the offset table
+    pub(crate) fn finish(mut self) -> (Option<Pack>, PackIndex) {
+        let last = self.close();
+        (last, self.index)
+    }
+
+    fn close(&mut self) -> Option<Pack> {
+        if self.current.is_empty() {
+            return None;
+        }
+        let path = PackIndex::pack_path(self.index.packs);
+        self.index.packs += 1;
+        Some(Pack {
+            path,
+            data: std::mem::take(&mut self.current),
+            chunks: std::mem::take(&mut self.current_chunks),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    #[test]
+    fn test_chunks_are_packed_and_read_back() {
+        let chunks: Vec<(String, Vec<u8>)> = (0..5u8)
+            .map(|i| (format!("{:02x}{}", i, "ab".repeat(31)), vec![i; 40]))
+            .collect();
+
+        let mut writer = PackWriter::new(100);
+        let mut packs = Vec::new();
+        for (hash, data) in &chunks {
+            packs.extend(writer.push(hash, data));
+        }
+        assert!(writer.push(&chunks[0].0, &chunks[0].1).is_none());
+        let (last, index) = writer.finish();
+        packs.extend(last);
+
+        // Two chunks fit into a pack of 100 bytes
+        assert_eq!(packs.iter().map(|pack| (pack.path.as_str(), pack.chunks)).collect::<Vec<_>>(), vec![
+            ("chunks/pack-000.bin", 2),
+            ("chunks/pack-001.bin", 2),
+            ("chunks/pack-002.bin", 1)
+        ]);
+        assert_eq!((index.packs, index.len()), (3, 5));
+        assert_eq!(index.get(&chunks[3].0), Some(&PackedChunk { pack: 1, offset: 40, length: 40 }));
+        assert_eq!((index.chunks_in(0), index.chunks_in(2), index.chunks_in(3)), (2, 1, 0));
+        assert_eq!(PackIndex::pack_number("chunks/pack-002.bin"), Some(2));
+        assert_eq!(PackIndex::pack_number(PACK_INDEX_PATH), None);
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::<()>::default().compression_method(CompressionMethod::Stored);
+        for pack in &packs {
+            zip.start_file(pack.path.as_str(), options).unwrap();
+            zip.write_all(&pack.data).unwrap();
+        }
+        zip.start_file(PACK_INDEX_PATH, options).unwrap();
+        zip.write_all(&index.to_msgpack().unwrap()).unwrap();
+        let mut archive = ZipArchive::new(zip.finish().unwrap()).unwrap();
+
+        let index = PackIndex::read_from_archive(&mut archive).unwrap();
+        for (hash, data) in &chunks {
+            assert_eq!(index.read_chunk(&mut archive, hash).unwrap().as_ref(), Some(data));
+        }
+        assert_eq!(index.read_chunk(&mut archive, &"f".repeat(64)).unwrap(), None);
+        let missing = index.read_stored(&mut archive, &"f".repeat(64));
+        assert!(matches!(missing, Err(CxpError::ChunkMissing { hash }) if hash == "f".repeat(64)));
+
+        // A multi-byte character across the chunk id boundary is a miss, not a panic
+        let odd = format!("{}é{}", "0".repeat(15), "f".repeat(47));
+        assert_eq!(index.get(&odd), None);
+        assert!(matches!(index.read_stored(&mut archive, &odd), Err(CxpError::ChunkMissing { .. })));
+        assert_eq!(PackIndex::legacy_path("é"), "chunks/é.zst");
+
+        // An offset table pointing past its pack is rejected before allocating
+        let mut corrupt = index.clone();
+        corrupt.chunks.values_mut().next().unwrap().length = u64::MAX;
+        assert!(matches!(corrupt.read_chunk(&mut archive, &chunks[0].0), Err(CxpError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_packed_and_legacy_archives_read_alike() {
+        use crate::format_spec::check_file;
+        use crate::{CxpBuilder, CxpReader};
+        use std::fs::File;
+        use tempfile::TempDir;
+
+        let source = TempDir::new().unwrap();
+        for i in 0..20 {
+            let body = format!("pub fn handler_{i}() -> u32 {{\n    {i}\n}}\n");
+            std::fs::write(source.path().join(format!("handler_{i}.rs")), body.repeat(50)).unwrap();
+        }
+
+        let out = TempDir::new().unwrap();
+        let packed = out.path().join("packed.cxp");
+        let legacy = out.path().join("legacy.cxp");
+        CxpBuilder::new(source.path()).scan().unwrap().process().unwrap().build(&packed).unwrap();
+        CxpBuilder::new(source.path())
+            .with_packed_chunks(false)
+            .scan()
+            .unwrap()
+            .process()
+            .unwrap()
+            .build(&legacy)
+            .unwrap();
+
+        let packed_archive = ZipArchive::new(File::open(&packed).unwrap()).unwrap();
+        assert!(packed_archive.index_for_name(PACK_INDEX_PATH).is_some());
+        assert!(packed_archive.file_names().all(|name| !name.ends_with(".zst")));
+        let legacy_archive = ZipArchive::new(File::open(&legacy).unwrap()).unwrap();
+        assert!(legacy_archive.index_for_name(PACK_INDEX_PATH).is_none());
+        assert!(packed_archive.len() < legacy_archive.len());
+
+        let (packed, legacy) = (CxpReader::open(&packed).unwrap(), CxpReader::open(&legacy).unwrap());
+        assert_eq!(packed.manifest().min_reader_version.as_deref(), Some(PACKS_READER_VERSION));
+        assert_eq!(legacy.manifest().min_reader_version, None);
+        let expected = std::fs::read(source.path().join("handler_7.rs")).unwrap();
+        assert_eq!(packed.read_file("handler_7.rs").unwrap(), expected);
+        assert_eq!(legacy.read_file("handler_7.rs").unwrap(), expected);
+        assert_eq!(packed.chunks().unwrap().count(), legacy.chunks().unwrap().count());
+        for path in [out.path().join("packed.cxp"), out.path().join("legacy.cxp")] {
+            assert!(check_file(&path).unwrap().failures().is_empty());
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! PII Scrubbing
+//!
+//! Regex-based [`Scrubber`]s for personal data (email addresses, phone
+//! numbers, IBANs and known names), for archives that are shared with
+//! third-party LLM providers. Each detector has its own allowlist so
+//! addresses such as `security@yourcompany.com` can stay readable:
+//!
+//! ```ignore
+//! let email = RegexScrubber::email().with_allowed("security@acme.dev");
+//! builder
+//!     .with_scrubber(email)
+//!     .with_scrubber(RegexScrubber::iban())
+//!     .with_scrubber(RegexScrubber::names(["Jane Doe"])?);
+//! ```
+
+use crate::redact::Scrubber;
+use crate::{CxpError, Result};
+use regex::bytes::Regex;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Built-in detector names accepted by [`RegexScrubber::preset`]
+pub const PII_PRESETS: &[&str] = &["email", "phone", "iban"];
+
+const EMAIL_PATTERN: &str = r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b";
+const PHONE_PATTERN: &str =
+    r"(?:\+\d{1,3}[ -]?(?:\(\d{1,4}\)[ -]?)?|\(0\d{1,4}\)[ -]?|\b0\d{2,4}[ -])\d{2,12}(?:[ -]\d{2,12}){0,3}\b";
+const IBAN_PATTERN: &str = r"\b[A-Z]{2}[0-9]{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,4})?\b";
+
+/// Scrubber matching a regular expression, with an allowlist
+#[derive(Debug, Clone)]
+pub struct RegexScrubber {
+    kind: String,
+    regex: Regex,
+    /// Values kept as-is (compared case-insensitively)
+    allowed: HashSet<String>,
+    /// Patterns of values kept as-is
+    allowed_patterns: Vec<Regex>,
+    /// Extra check a match must pass (e.g. a checksum)
+    validator: Option<fn(&[u8]) -> bool>,
+}
+
+impl RegexScrubber {
+    /// Scrubber replacing matches of `pattern` as `kind`
+    pub fn new(kind: &str, pattern: &str) -> Result<Self> {
+        Ok(Self {
+            kind: kind.to_string(),
+            regex: compile(pattern)?,
+            allowed: HashSet::new(),
+            allowed_patterns: Vec::new(),
+            validator: None,
+        })
+    }
+
+    /// Email addresses (addresses at `example.com/.org/.net` are allowed)
+    pub fn email() -> Self {
+        let mut scrubber = Self::new("email", EMAIL_PATTERN).expect("built-in PII pattern");
+        scrubber
+            .allowed_patterns
+            .push(compile(r"(?i)@example\.(?:com|org|net)$").expect("built-in PII pattern"));
+        scrubber
+    }
+
+    /// International (`+49 30 1234567`) and national (`030 1234567`) phone numbers
+    pub fn phone() -> Self {
+        Self::new("phone", PHONE_PATTERN)
+            .expect("built-in PII pattern")
+            .with_validator(|value| (8..=15).contains(&value.iter().filter(|b| b.is_ascii_digit()).count()))
+    }
+
+    /// IBANs with a valid mod-97 checksum
+    pub fn iban() -> Self {
+        Self::new("iban", IBAN_PATTERN)
+            .expect("built-in PII pattern")
+            .with_validator(is_valid_iban)
+    }
+
+    /// Known names (whole words, case-sensitive)
+    pub fn names<I, S>(names: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let alternatives: Vec<String> = names
+            .into_iter()
+            .map(|name| name.as_ref().trim().to_string())
+            .filter(|name| !name.is_empty())
+            .map(|name| regex::escape(&name))
+            .collect();
+        if alternatives.is_empty() {
+            return Err(CxpError::InvalidFormat("No names to scrub".to_string()));
+        }
+        Self::new("name", &format!(r"\b(?:{})\b", alternatives.join("|")))
+    }
+
+    /// Built-in detector by name (`email`, `phone` or `iban`)
+    pub fn preset(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "email" => Ok(Self::email()),
+            "phone" => Ok(Self::phone()),
+            "iban" => Ok(Self::iban()),
+            other => Err(CxpError::InvalidFormat(format!(
+                "Unknown PII detector '{}' (expected one of: {})",
+                other,
+                PII_PRESETS.join(", ")
+            ))),
+        }
+    }
+
+    /// Kind recorded for matches
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Keep this exact value (case-insensitive)
+    pub fn with_allowed(mut self, value: &str) -> Self {
+        self.allowed.insert(value.trim().to_lowercase());
+        self
+    }
+
+    /// Keep values matching `pattern`
+    pub fn with_allowed_pattern(mut self, pattern: &str) -> Result<Self> {
+        self.allowed_patterns.push(compile(pattern)?);
+        Ok(self)
+    }
+
+    /// Only replace matches for which `validator` returns true
+    pub fn with_validator(mut self, validator: fn(&[u8]) -> bool) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    fn is_allowed(&self, value: &[u8]) -> bool {
+        (!self.allowed.is_empty() && self.allowed.contains(&String::from_utf8_lossy(value).to_lowercase()))
+            || self.allowed_patterns.iter().any(|pattern| pattern.is_match(value))
+    }
+}
+
+impl Scrubber for RegexScrubber {
+    fn find(&self, content: &[u8]) -> Vec<(Range<usize>, &str)> {
+        self.regex
+            .find_iter(content)
+            .filter(|m| self.validator.is_none_or(|validate| validate(m.as_bytes())))
+            .filter(|m| !self.is_allowed(m.as_bytes()))
+            .map(|m| (m.range(), self.kind.as_str()))
+            .collect()
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| CxpError::InvalidFormat(format!("Invalid scrub pattern '{}': {}", pattern, e)))
+}
+
+/// ISO 13616 checksum: move the first four characters to the end, map letters to 10..35, mod 97 == 1
+fn is_valid_iban(value: &[u8]) -> bool {
+    let compact: Vec<u8> = value.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !(15..=34).contains(&compact.len()) {
+        return false;
+    }
+    let mut remainder: u32 = 0;
+    for &b in compact[4..].iter().chain(&compact[..4]) {
+        let digit = match b {
+            b'0'..=b'9' => (b - b'0') as u32,
+            b'A'..=b'Z' => (b - b'A') as u32 + 10,
+            _ => return false,
+        };
+        remainder = if digit >= 10 { (remainder * 100 + digit) % 97 } else { (remainder * 10 + digit) % 97 };
+    }
+    remainder == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::scrub;
+
+    #[test]
+    fn test_pii_presets() {
+        let content = b"Contact: jane.doe@acme.dev, ops@example.com\n\
+Call +49 30 1234567 or (030) 7654321\n\
+Pay to DE89 3704 0044 0532 0130 00, not DE00 3704 1044 1532 1130 10\n\
+version 1.2.3, date 2024-01-15, id 12345678\n";
+        let email = RegexScrubber::email();
+        let phone = RegexScrubber::phone();
+        let iban = RegexScrubber::iban();
+        let (output, redactions) = scrub(content, &[&email, &phone, &iban]).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("Contact: [REDACTED:email], ops@example.com"));
+        assert!(output.contains("Call [REDACTED:phone] or [REDACTED:phone]"));
+        assert!(output.contains("Pay to [REDACTED:iban], not DE00"));
+        assert!(output.contains("version 1.2.3, date 2024-01-15, id 12345678"));
+
+        let kinds: Vec<(&str, usize)> = redactions.iter().map(|r| (r.kind.as_str(), r.line)).collect();
+        assert_eq!(kinds, vec![("email", 1), ("phone", 2), ("phone", 2), ("iban", 3)]);
+    }
+
+    #[test]
+    fn test_allowlists_and_names() {
+        let content = b"Author: Jane Doe <jane@acme.dev>\nReviewer: security@acme.dev\nJane Doesnt\n";
+        let email = RegexScrubber::email()
+            .with_allowed("SECURITY@acme.dev")
+            .with_allowed_pattern(r"^noreply@")
+            .unwrap();
+        let names = RegexScrubber::names(["Jane Doe", ""]).unwrap();
+        let (output, _) = scrub(content, &[&email, &names]).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Author: [REDACTED:name] <[REDACTED:email]>\nReviewer: security@acme.dev\nJane Doesnt\n"
+        );
+        assert!(RegexScrubber::names(Vec::<String>::new()).is_err());
+        assert!(RegexScrubber::preset("Phone").is_ok());
+        assert!(RegexScrubber::preset("ssn").is_err());
+    }
+}
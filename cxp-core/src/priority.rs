@@ -0,0 +1,212 @@
+//! Background Priority
+//!
+//! Lets always-on work (watch mode rebuilds, embedding, compression) run at
+//! idle CPU and I/O priority so it does not compete with interactive use, and
+//! tells whether the machine is running on battery.
+//!
+//! | Platform | CPU | I/O |
+//! |----------|-----|-----|
+//! | Linux | `nice` 19 | `ionice` idle class |
+//! | macOS | background QoS (`PRIO_DARWIN_BG`) | throttled with the QoS |
+//! | Other | not supported | not supported |
+//!
+//! Priorities apply to the calling thread. Threads it spawns afterwards
+//! inherit them on Linux and macOS, so lower the priority before creating
+//! worker pools or embedding sessions.
+
+use std::time::{Duration, Instant};
+
+use crate::{CxpError, Result};
+
+/// Lowest CPU priority (`nice` value) on Unix
+#[cfg(target_os = "linux")]
+const IDLE_NICE: libc::c_int = 19;
+
+/// `ioprio_set` target: a single thread/process
+#[cfg(target_os = "linux")]
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+
+/// `ioprio_set` idle scheduling class
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+
+/// Shift of the class in an I/O priority value
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+/// Run the calling thread at idle CPU and I/O priority
+///
+/// Returns an error on platforms without support or when the OS refuses;
+/// callers usually log it and continue at normal priority.
+pub fn lower_current_thread() -> Result<()> {
+    imp::lower_current_thread()
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::*;
+
+    pub fn lower_current_thread() -> Result<()> {
+        // SAFETY: plain syscalls on the calling thread's id
+        unsafe {
+            let tid = libc::gettid();
+            if libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, IDLE_NICE) != 0 {
+                return Err(os_error("setpriority"));
+            }
+            let ioprio = IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT;
+            if libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, tid, ioprio) != 0 {
+                return Err(os_error("ioprio_set"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use super::*;
+
+    pub fn lower_current_thread() -> Result<()> {
+        // SAFETY: PRIO_DARWIN_THREAD with id 0 targets the calling thread
+        let rc = unsafe { libc::setpriority(libc::PRIO_DARWIN_THREAD, 0, libc::PRIO_DARWIN_BG) };
+        if rc != 0 {
+            return Err(os_error("setpriority"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod imp {
+    use super::*;
+
+    pub fn lower_current_thread() -> Result<()> {
+        Err(CxpError::io("Background priority is not supported on this platform".to_string()))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn os_error(call: &str) -> CxpError {
+    CxpError::io(format!("{} failed: {}", call, std::io::Error::last_os_error()))
+}
+
+/// Whether the machine is running on battery (None if unknown)
+///
+/// Linux reads `/sys/class/power_supply`, macOS asks `pmset`. Desktops
+/// without a battery report `Some(false)`.
+pub fn on_battery() -> Option<bool> {
+    #[cfg(target_os = "linux")]
+    {
+        on_battery_sysfs(std::path::Path::new("/sys/class/power_supply"))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        Some(text.contains("'Battery Power'"))
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// Power state from a sysfs `power_supply` directory
+#[cfg(target_os = "linux")]
+fn on_battery_sysfs(dir: &std::path::Path) -> Option<bool> {
+    let read = |path: std::path::PathBuf| std::fs::read_to_string(path).ok().map(|s| s.trim().to_string());
+
+    let mut discharging = false;
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let supply = entry.path();
+        match read(supply.join("type")).as_deref() {
+            Some("Mains") | Some("USB") if read(supply.join("online")).as_deref() == Some("1") => {
+                return Some(false);
+            }
+            Some("Battery") => {
+                discharging |= read(supply.join("status")).as_deref() == Some("Discharging");
+            }
+            _ => {}
+        }
+    }
+    Some(discharging)
+}
+
+/// Caches [`on_battery`] for an interval (it may spawn a process on macOS)
+#[derive(Debug)]
+pub struct BatteryMonitor {
+    interval: Duration,
+    last: Option<(Instant, bool)>,
+}
+
+impl BatteryMonitor {
+    /// Default time between power checks
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Monitor checking at most once per `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, last: None }
+    }
+
+    /// Whether the machine is on battery (unknown counts as mains power)
+    pub fn on_battery(&mut self) -> bool {
+        match self.last {
+            Some((checked, state)) if checked.elapsed() < self.interval => state,
+            _ => {
+                let state = on_battery().unwrap_or(false);
+                self.last = Some((Instant::now(), state));
+                state
+            }
+        }
+    }
+}
+
+impl Default for BatteryMonitor {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_lower_current_thread() {
+        // Lowering is always allowed; run on a scratch thread to leave the test thread alone
+        std::thread::spawn(|| {
+            lower_current_thread().unwrap();
+            // SAFETY: reads the calling thread's nice value
+            let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t) };
+            assert_eq!(nice, IDLE_NICE);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_on_battery_sysfs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let supply = |name: &str, files: &[(&str, &str)]| {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(&path).unwrap();
+            for (file, content) in files {
+                std::fs::write(path.join(file), format!("{}\n", content)).unwrap();
+            }
+        };
+
+        // Desktop without supplies
+        assert_eq!(on_battery_sysfs(dir.path()), Some(false));
+
+        supply("BAT0", &[("type", "Battery"), ("status", "Discharging")]);
+        supply("AC", &[("type", "Mains"), ("online", "0")]);
+        assert_eq!(on_battery_sysfs(dir.path()), Some(true));
+
+        supply("AC", &[("type", "Mains"), ("online", "1")]);
+        assert_eq!(on_battery_sysfs(dir.path()), Some(false));
+
+        assert_eq!(on_battery_sysfs(&dir.path().join("missing")), None);
+    }
+}
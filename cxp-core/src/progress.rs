@@ -0,0 +1,90 @@
+//! Build Progress
+//!
+//! `CxpBuilder::with_progress` reports each build phase to a host - progress
+//! bars in the CLI, a progress UI in a GUI. Every method has a no-op default,
+//! so reporters only implement the events they display. Events are sent from
+//! the thread running the builder.
+//!
+//! # Example
+//! ```ignore
+//! struct Log;
+//!
+//! impl ProgressReporter for Log {
+//!     fn written(&self, done: usize, total: usize) {
+//!         println!("{}/{} chunks written", done, total);
+//!     }
+//! }
+//!
+//! let mut builder = CxpBuilder::new("./project");
+//! builder.with_progress(Arc::new(Log));
+//! ```
+
+/// Receives progress events from `CxpBuilder`
+pub trait ProgressReporter: Send + Sync {
+    /// `scan()` found `files` files to process
+    fn scanned(&self, _files: usize) {}
+
+    /// `process()` chunked `path`, the `done`th of `total` files
+    fn chunked(&self, _path: &str, _done: usize, _total: usize) {}
+
+    /// Embeddings exist for `done` of `total` chunks (and images)
+    fn embedded(&self, _done: usize, _total: usize) {}
+
+    /// `build()` wrote `done` of `total` chunks
+    fn written(&self, _done: usize, _total: usize) {}
+}
+
+/// Reporter that ignores every event (the default)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CxpBuilder;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<(&'static str, usize, usize)>>,
+    }
+
+    impl ProgressReporter for Recorder {
+        fn scanned(&self, files: usize) {
+            self.events.lock().unwrap().push(("scanned", files, files));
+        }
+
+        fn chunked(&self, _path: &str, done: usize, total: usize) {
+            self.events.lock().unwrap().push(("chunked", done, total));
+        }
+
+        fn written(&self, done: usize, total: usize) {
+            self.events.lock().unwrap().push(("written", done, total));
+        }
+    }
+
+    #[test]
+    fn test_progress_events() {
+        let source = TempDir::new().unwrap();
+        std::fs::write(source.path().join("a.txt"), "alpha\n").unwrap();
+        std::fs::write(source.path().join("b.txt"), "beta\n").unwrap();
+        let output = TempDir::new().unwrap();
+
+        let recorder = Arc::new(Recorder::default());
+        let mut builder = CxpBuilder::new(source.path());
+        builder.with_progress(recorder.clone());
+        builder.add_file_str("generated.md", "# Generated\n").unwrap();
+        builder.scan().unwrap().process().unwrap().build(output.path().join("out.cxp")).unwrap();
+
+        let events = recorder.events.lock().unwrap();
+        assert_eq!(events[0], ("scanned", 2, 2));
+        let chunked: Vec<_> = events.iter().filter(|e| e.0 == "chunked").map(|e| (e.1, e.2)).collect();
+        assert_eq!(chunked, vec![(1, 3), (2, 3), (3, 3)]);
+        let written: Vec<_> = events.iter().filter(|e| e.0 == "written").collect();
+        assert_eq!(written.len(), 3);
+        assert_eq!(written.last().unwrap(), &&("written", 3, 3));
+    }
+}
@@ -0,0 +1,245 @@
+//! File Provenance
+//!
+//! Where each archived file came from, recorded in its [`FileEntry`] so
+//! organizations can audit what source went into a context pack:
+//!
+//! - the absolute origin path at build time (the path within the repository
+//!   for archives built with `CxpBuilder::from_git`)
+//! - the commit checked out in the surrounding git repository (read from
+//!   `.git` directly, no git binary needed)
+//! - the license, from an `SPDX-License-Identifier:` tag or a well-known
+//!   license header
+//!
+//! The modification time is already part of [`FileEntry::modified`].
+
+use crate::format::FileEntry;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Bytes at the start of a file searched for a license header
+const LICENSE_SCAN_BYTES: usize = 4096;
+
+/// SPDX tag marker
+const SPDX_TAG: &str = "SPDX-License-Identifier:";
+
+/// Well-known license header phrases (normalized: lowercase, words only) and their SPDX IDs
+const LICENSE_HEADERS: &[(&str, &str)] = &[
+    ("licensed under the apache license version 2 0", "Apache-2.0"),
+    ("permission is hereby granted free of charge to any person obtaining a copy", "MIT"),
+    ("subject to the terms of the mozilla public license v 2 0", "MPL-2.0"),
+    ("neither the name of the copyright holder nor the names of its contributors", "BSD-3-Clause"),
+    ("permission to use copy modify and or distribute this software for any purpose", "ISC"),
+    ("gnu affero general public license", "AGPL-3.0"),
+    ("gnu lesser general public license", "LGPL"),
+    ("gnu general public license", "GPL"),
+];
+
+/// Where a file came from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Absolute source path at build time (repository-relative for `from_git`)
+    pub origin: String,
+    /// Commit checked out in the source repository (None outside git)
+    #[serde(default)]
+    pub git_commit: Option<String>,
+    /// SPDX license expression detected in the file header
+    #[serde(default)]
+    pub license: Option<String>,
+}
+
+impl Provenance {
+    /// Provenance of a file read from `path`
+    pub fn for_file(path: &Path, content: &[u8]) -> Self {
+        Self {
+            origin: path
+                .canonicalize()
+                .unwrap_or_else(|_| path.to_path_buf())
+                .to_string_lossy()
+                .to_string(),
+            git_commit: None,
+            license: detect_license(content),
+        }
+    }
+}
+
+/// License of a file, from its SPDX tag or a well-known header
+pub fn detect_license(content: &[u8]) -> Option<String> {
+    let head = String::from_utf8_lossy(&content[..content.len().min(LICENSE_SCAN_BYTES)]);
+
+    for (position, _) in head.match_indices(SPDX_TAG) {
+        let expression = head[position + SPDX_TAG.len()..]
+            .lines()
+            .next()
+            .unwrap_or("")
+            .trim()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->")
+            .trim();
+        // Only license IDs, operators and parentheses (skips prose mentioning the tag)
+        let valid = expression
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || " .-+():".contains(c));
+        if !expression.is_empty() && valid {
+            return Some(expression.to_string());
+        }
+    }
+
+    // Comment markers and line breaks vary, so compare words only
+    let words = head
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    LICENSE_HEADERS
+        .iter()
+        .find(|(phrase, _)| words.contains(phrase))
+        .map(|(_, id)| id.to_string())
+}
+
+/// Commit checked out in the git repository containing `dir`
+///
+/// Handles branches, detached HEADs, packed refs and worktrees/submodules
+/// (`.git` files pointing to the real git directory).
+pub fn git_head(dir: &Path) -> Option<String> {
+    let dir = dir.canonicalize().ok()?;
+    for ancestor in dir.ancestors() {
+        let dot_git = ancestor.join(".git");
+        if dot_git.is_dir() {
+            return resolve_head(&dot_git);
+        }
+        if dot_git.is_file() {
+            let pointer = fs::read_to_string(&dot_git).ok()?;
+            let git_dir = ancestor.join(pointer.trim().strip_prefix("gitdir:")?.trim());
+            return resolve_head(&git_dir);
+        }
+    }
+    None
+}
+
+/// License of an entry ("unknown" if none was detected)
+pub fn license_of(entry: &FileEntry) -> &str {
+    entry
+        .provenance
+        .as_ref()
+        .and_then(|p| p.license.as_deref())
+        .unwrap_or("unknown")
+}
+
+fn resolve_head(git_dir: &Path) -> Option<String> {
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    let Some(reference) = head.strip_prefix("ref:") else {
+        return is_commit_hash(head).then(|| head.to_string());
+    };
+    let reference = reference.trim();
+
+    // Worktrees keep shared refs in the common directory
+    let common_dir = fs::read_to_string(git_dir.join("commondir"))
+        .map(|dir| git_dir.join(dir.trim()))
+        .unwrap_or_else(|_| git_dir.to_path_buf());
+    for dir in [git_dir, common_dir.as_path()] {
+        if let Ok(hash) = fs::read_to_string(dir.join(reference)) {
+            let hash = hash.trim();
+            if is_commit_hash(hash) {
+                return Some(hash.to_string());
+            }
+        }
+    }
+
+    let packed = fs::read_to_string(common_dir.join("packed-refs")).ok()?;
+    packed
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+        .find_map(|line| {
+            let (hash, name) = line.split_once(' ')?;
+            (name.trim() == reference && is_commit_hash(hash)).then(|| hash.to_string())
+        })
+}
+
+fn is_commit_hash(value: &str) -> bool {
+    (value.len() == 40 || value.len() == 64) && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    #[test]
+    fn test_detect_license() {
+        assert_eq!(
+            detect_license(b"// SPDX-License-Identifier: MIT OR Apache-2.0\nfn main() {}\n").as_deref(),
+            Some("MIT OR Apache-2.0")
+        );
+        assert_eq!(
+            detect_license(b"/* SPDX-License-Identifier: GPL-2.0-only */\n").as_deref(),
+            Some("GPL-2.0-only")
+        );
+        assert_eq!(detect_license(b"//! The `SPDX-License-Identifier:` tag\n").as_deref(), None);
+        let apache = b"# Copyright 2024 Acme\n#\n# Licensed under the Apache License,\n# Version 2.0 (the \"License\");\n";
+        assert_eq!(detect_license(apache).as_deref(), Some("Apache-2.0"));
+        assert_eq!(detect_license(b"fn main() {}\n"), None);
+    }
+
+    #[test]
+    fn test_git_head() {
+        let repo = TempDir::new().unwrap();
+        let git_dir = repo.path().join(".git");
+        std::fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+        std::fs::create_dir_all(repo.path().join("src/nested")).unwrap();
+
+        // Loose branch ref
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        std::fs::write(git_dir.join("refs/heads/main"), format!("{}\n", COMMIT)).unwrap();
+        assert_eq!(git_head(&repo.path().join("src/nested")).as_deref(), Some(COMMIT));
+
+        // Packed ref
+        std::fs::remove_file(git_dir.join("refs/heads/main")).unwrap();
+        std::fs::write(
+            git_dir.join("packed-refs"),
+            format!("# pack-refs with: peeled fully-peeled sorted\n{} refs/heads/main\n", COMMIT),
+        )
+        .unwrap();
+        assert_eq!(git_head(repo.path()).as_deref(), Some(COMMIT));
+
+        // Detached HEAD
+        std::fs::write(git_dir.join("HEAD"), format!("{}\n", COMMIT)).unwrap();
+        assert_eq!(git_head(repo.path()).as_deref(), Some(COMMIT));
+
+        let outside = TempDir::new().unwrap();
+        assert_eq!(git_head(outside.path()), None);
+    }
+
+    #[test]
+    fn test_build_records_provenance() {
+        use crate::{CxpBuilder, CxpReader};
+
+        let source = TempDir::new().unwrap();
+        std::fs::create_dir_all(source.path().join(".git")).unwrap();
+        std::fs::write(source.path().join(".git/HEAD"), format!("{}\n", COMMIT)).unwrap();
+        std::fs::write(source.path().join("lib.rs"), "// SPDX-License-Identifier: MIT\npub fn f() {}\n").unwrap();
+        std::fs::write(source.path().join("notes.md"), "# Notes\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("provenance.cxp");
+        let mut builder = CxpBuilder::new(source.path());
+        builder.with_provenance(true);
+        builder.scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+
+        let reader = CxpReader::open(&cxp_path).unwrap();
+        let entry = reader.file_entry("lib.rs").unwrap().unwrap();
+        let provenance = entry.provenance.as_ref().unwrap();
+        assert_eq!(provenance.license.as_deref(), Some("MIT"));
+        assert_eq!(provenance.git_commit.as_deref(), Some(COMMIT));
+        assert!(Path::new(&provenance.origin).is_absolute());
+        assert!(provenance.origin.ends_with("lib.rs"));
+        assert!(entry.modified.is_some());
+
+        let notes = reader.file_entry("notes.md").unwrap().unwrap();
+        assert_eq!(license_of(notes), "unknown");
+    }
+}
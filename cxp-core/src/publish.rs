@@ -0,0 +1,356 @@
+//! Publishing Context Bundles
+//!
+//! Packages an archive for handing it to another team or a vendor. A bundle
+//! is a directory with:
+//!
+//! ```text
+//! <name>.cxp          # The archive (copied as is)
+//! README.md           # Stats, topics, build provenance and usage snippets
+//! SHA256SUMS          # `sha256sum -c` compatible checksums of both files
+//! SHA256SUMS.sig      # HMAC-SHA256 of SHA256SUMS (only with a signing key)
+//! ```
+//!
+//! The signature uses a key shared between publisher and recipients; anyone
+//! holding it can check that the checksums were not swapped along with the
+//! files ([`verify_bundle`]).
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::format::CxpReader;
+use crate::token::format_bytes;
+use crate::{CxpError, Result};
+
+/// Name of the generated README in a bundle
+pub const README_NAME: &str = "README.md";
+
+/// Name of the checksum file in a bundle
+pub const CHECKSUMS_NAME: &str = "SHA256SUMS";
+
+/// Name of the checksum signature in a bundle
+pub const SIGNATURE_NAME: &str = "SHA256SUMS.sig";
+
+/// File types listed in the README
+const README_FILE_TYPES: usize = 10;
+
+/// Options for [`publish`]
+#[derive(Debug, Clone, Default)]
+pub struct PublishOptions {
+    /// README title (default: the archive's file stem)
+    pub title: Option<String>,
+    /// Key for signing the checksums (no signature without one)
+    pub signing_key: Option<Vec<u8>>,
+}
+
+impl PublishOptions {
+    /// Set the README title
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sign the checksums with a shared key
+    pub fn with_signing_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.signing_key = Some(key.into());
+        self
+    }
+}
+
+/// Files written by [`publish`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishedBundle {
+    /// Copied archive
+    pub archive: PathBuf,
+    /// Generated README
+    pub readme: PathBuf,
+    /// Checksum file
+    pub checksums: PathBuf,
+    /// Signature file (None if unsigned)
+    pub signature: Option<PathBuf>,
+    /// SHA-256 of the archive (hex)
+    pub archive_sha256: String,
+}
+
+/// Write a bundle for `archive` into `out_dir` (created if missing)
+pub fn publish(archive: &Path, out_dir: &Path, options: &PublishOptions) -> Result<PublishedBundle> {
+    let reader = CxpReader::open(archive)?;
+    let file_name = archive
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| CxpError::io(format!("Invalid archive path {}", archive.display())))?;
+
+    std::fs::create_dir_all(out_dir)?;
+    let bundled_archive = out_dir.join(file_name);
+    let same_file = matches!(
+        (archive.canonicalize(), bundled_archive.canonicalize()),
+        (Ok(a), Ok(b)) if a == b
+    );
+    if !same_file {
+        std::fs::copy(archive, &bundled_archive)?;
+    }
+    let archive_sha256 = sha256_file(&bundled_archive)?;
+
+    let title = options.title.clone().unwrap_or_else(|| {
+        archive.file_stem().map_or(file_name.to_string(), |stem| stem.to_string_lossy().into_owned())
+    });
+    let readme = render_readme(&reader, &title, file_name, &archive_sha256)?;
+    let readme_path = out_dir.join(README_NAME);
+    std::fs::write(&readme_path, &readme)?;
+
+    let checksums = format!(
+        "{}  {}\n{}  {}\n",
+        archive_sha256,
+        file_name,
+        hex::encode(Sha256::digest(readme.as_bytes())),
+        README_NAME
+    );
+    let checksums_path = out_dir.join(CHECKSUMS_NAME);
+    std::fs::write(&checksums_path, &checksums)?;
+
+    let signature = match options.signing_key {
+        Some(ref key) => {
+            let path = out_dir.join(SIGNATURE_NAME);
+            std::fs::write(&path, format!("{}\n", sign(key, checksums.as_bytes())?))?;
+            Some(path)
+        }
+        None => None,
+    };
+
+    tracing::info!("Published {} to {}", file_name, out_dir.display());
+
+    Ok(PublishedBundle {
+        archive: bundled_archive,
+        readme: readme_path,
+        checksums: checksums_path,
+        signature,
+        archive_sha256,
+    })
+}
+
+/// Check a bundle's checksums and, with a key, its signature
+///
+/// Fails if a listed file is missing or changed, or if `signing_key` is given
+/// and the signature is missing or does not match.
+pub fn verify_bundle(dir: &Path, signing_key: Option<&[u8]>) -> Result<()> {
+    let checksums = std::fs::read_to_string(dir.join(CHECKSUMS_NAME))?;
+
+    for line in checksums.lines().filter(|line| !line.trim().is_empty()) {
+        let (expected, name) = line
+            .split_once("  ")
+            .ok_or_else(|| CxpError::InvalidFormat(format!("Malformed checksum line '{}'", line)))?;
+        if name.contains('/') || name.contains('\\') || name.contains("..") {
+            return Err(CxpError::InvalidFormat(format!("Checksum entry '{}' leaves the bundle", name)));
+        }
+        let actual = sha256_file(&dir.join(name))?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(CxpError::InvalidFormat(format!("Checksum mismatch for {}", name)));
+        }
+    }
+
+    if let Some(key) = signing_key {
+        let signature = std::fs::read_to_string(dir.join(SIGNATURE_NAME))
+            .map_err(|e| CxpError::InvalidFormat(format!("Bundle is not signed: {}", e)))?;
+        let signature = hex::decode(signature.trim())
+            .map_err(|e| CxpError::InvalidFormat(format!("Malformed signature: {}", e)))?;
+        hmac_sha256(key)?
+            .chain_update(checksums.as_bytes())
+            .verify_slice(&signature)
+            .map_err(|_| CxpError::InvalidFormat("Signature does not match".to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Generate the bundle README for an open archive
+pub fn render_readme(reader: &CxpReader, title: &str, file_name: &str, archive_sha256: &str) -> Result<String> {
+    let manifest = reader.manifest();
+    let stats = reader.statistics()?;
+    let mut out = String::new();
+
+    // Writing to a String cannot fail
+    let _ = writeln!(out, "# {}\n", title);
+    let _ = writeln!(out, "Context bundle `{}`, packaged with cxp {}.\n", file_name, env!("CARGO_PKG_VERSION"));
+
+    let _ = writeln!(out, "## Contents\n");
+    let _ = writeln!(out, "| | |\n|---|---|");
+    let _ = writeln!(out, "| Files | {} |", stats.total_files);
+    let _ = writeln!(out, "| Original size | {} |", format_bytes(stats.original_bytes));
+    let _ = writeln!(out, "| Archive size | {} |", format_bytes(stats.archive_bytes));
+    let _ = writeln!(out, "| Unique chunks | {} |", stats.unique_chunks);
+    let _ = writeln!(out, "| Dedup savings | {:.1}% |", stats.dedup_ratio() * 100.0);
+    match (&manifest.embedding_model, manifest.embedding_dim) {
+        (Some(model), Some(dim)) => {
+            let _ = writeln!(out, "| Embeddings | {} ({} dims) |", model, dim);
+        }
+        (Some(model), None) => {
+            let _ = writeln!(out, "| Embeddings | {} |", model);
+        }
+        _ => {
+            let _ = writeln!(out, "| Embeddings | none |");
+        }
+    }
+    if !manifest.children.is_empty() {
+        let _ = writeln!(out, "| Child archives | {} |", manifest.children.len());
+    }
+
+    let mut file_types: Vec<_> = manifest.file_types.iter().collect();
+    file_types.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+    if !file_types.is_empty() {
+        let _ = writeln!(out, "\n### File types\n");
+        let _ = writeln!(out, "| Extension | Files | Size | Type |\n|---|---|---|---|");
+        for (extension, info) in file_types.iter().take(README_FILE_TYPES) {
+            let _ = writeln!(
+                out,
+                "| `{}` | {} | {} | {} |",
+                extension,
+                info.count,
+                format_bytes(info.total_bytes),
+                info.description
+            );
+        }
+    }
+
+    if !manifest.topics.is_empty() || !manifest.keywords.is_empty() {
+        let _ = writeln!(out, "\n### Topics\n");
+        if !manifest.topics.is_empty() {
+            let _ = writeln!(out, "{}\n", manifest.topics.join(", "));
+        }
+        if !manifest.keywords.is_empty() {
+            let _ = writeln!(out, "Keywords: {}\n", manifest.keywords.join(", "));
+        }
+    }
+
+    let _ = writeln!(out, "\n## Provenance\n");
+    let _ = writeln!(out, "- Format version: {}", manifest.version);
+    if let Some(ref version) = manifest.min_reader_version {
+        let _ = writeln!(out, "- Requires cxp reader: {} or newer", version);
+    }
+    let _ = writeln!(out, "- Created: {}", manifest.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    let _ = writeln!(out, "- Updated: {}", manifest.updated_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    for (key, value) in &manifest.metadata {
+        let _ = writeln!(out, "- {}: {}", key, value);
+    }
+    if let Some(info) = reader.build_info()? {
+        let _ = writeln!(out, "- Built with: cxp-core {} on {}/{}", info.builder_version, info.host_os, info.host_arch);
+        if !info.features.is_empty() {
+            let _ = writeln!(out, "- Features: {}", info.features.join(", "));
+        }
+        if !info.parameters.is_empty() {
+            let _ = writeln!(out, "- Build parameters:");
+            for (key, value) in &info.parameters {
+                let _ = writeln!(out, "  - {}: {}", key, value);
+            }
+        }
+    }
+    let _ = writeln!(out, "- SHA-256: `{}`", archive_sha256);
+
+    let example = reader.file_paths().into_iter().next().unwrap_or("path/to/file");
+    let _ = writeln!(out, "\n## Usage\n");
+    let _ = writeln!(out, "Verify and inspect the bundle:\n");
+    let _ = writeln!(out, "```bash\nsha256sum -c {}", CHECKSUMS_NAME);
+    let _ = writeln!(out, "cxp info {}", file_name);
+    let _ = writeln!(out, "cxp list {}", file_name);
+    let _ = writeln!(out, "cxp query {} \"<search terms>\"", file_name);
+    let _ = writeln!(out, "cxp extract {} {}", file_name, example);
+    if manifest.embedding_model.is_some() {
+        let _ = writeln!(out, "cxp search {} \"<question>\" --model <model-dir>", file_name);
+    }
+    let _ = writeln!(out, "```\n");
+    let _ = writeln!(out, "Read it from Rust:\n");
+    let _ = writeln!(out, "```rust");
+    let _ = writeln!(out, "let reader = cxp_core::CxpReader::open(\"{}\")?;", file_name);
+    let _ = writeln!(out, "let content = reader.read_file(\"{}\")?;", example);
+    let _ = writeln!(out, "```");
+
+    Ok(out)
+}
+
+/// Hex SHA-256 of a file's content
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hex HMAC-SHA256 of `data`
+fn sign(key: &[u8], data: &[u8]) -> Result<String> {
+    Ok(hex::encode(hmac_sha256(key)?.chain_update(data).finalize().into_bytes()))
+}
+
+fn hmac_sha256(key: &[u8]) -> Result<Hmac<Sha256>> {
+    if key.is_empty() {
+        return Err(CxpError::InvalidFormat("Signing key is empty".to_string()));
+    }
+    Hmac::<Sha256>::new_from_slice(key).map_err(|e| CxpError::InvalidFormat(format!("Invalid signing key: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CxpBuilder;
+    use tempfile::TempDir;
+
+    fn build_archive(dir: &TempDir) -> PathBuf {
+        let source = dir.path().join("src");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(source.join("notes.md"), "# Notes\n").unwrap();
+
+        let archive = dir.path().join("team.cxp");
+        CxpBuilder::new(&source)
+            .with_metadata("owner", "platform")
+            .scan()
+            .unwrap()
+            .process()
+            .unwrap()
+            .build(&archive)
+            .unwrap();
+        archive
+    }
+
+    #[test]
+    fn test_publish_and_verify() {
+        let dir = TempDir::new().unwrap();
+        let archive = build_archive(&dir);
+        let out = dir.path().join("bundle");
+
+        let options = PublishOptions::default().with_title("Team Context").with_signing_key("secret");
+        let bundle = publish(&archive, &out, &options).unwrap();
+        assert_eq!(std::fs::read(&bundle.archive).unwrap(), std::fs::read(&archive).unwrap());
+
+        let readme = std::fs::read_to_string(&bundle.readme).unwrap();
+        assert!(readme.starts_with("# Team Context"));
+        assert!(readme.contains("| Files | 2 |"));
+        assert!(readme.contains("- owner: platform"));
+        assert!(readme.contains(&bundle.archive_sha256));
+        assert!(readme.contains("cxp extract team.cxp main.rs"));
+
+        let checksums = std::fs::read_to_string(&bundle.checksums).unwrap();
+        assert!(checksums.contains("  team.cxp\n"));
+        assert!(checksums.contains("  README.md\n"));
+
+        verify_bundle(&out, Some(b"secret")).unwrap();
+        assert!(verify_bundle(&out, Some(b"other")).is_err());
+
+        // Tampering with a file breaks the checksums
+        std::fs::write(&bundle.readme, "changed").unwrap();
+        assert!(verify_bundle(&out, None).is_err());
+    }
+
+    #[test]
+    fn test_publish_unsigned_in_place() {
+        let dir = TempDir::new().unwrap();
+        let archive = build_archive(&dir);
+
+        let bundle = publish(&archive, dir.path(), &PublishOptions::default()).unwrap();
+        assert_eq!(bundle.archive, archive);
+        assert!(bundle.signature.is_none());
+        verify_bundle(dir.path(), None).unwrap();
+        assert!(verify_bundle(dir.path(), Some(b"secret")).is_err());
+    }
+}
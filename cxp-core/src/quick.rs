@@ -0,0 +1,302 @@
+//! Quick Start Facade
+//!
+//! One-call helpers for applications that just want to archive a directory and
+//! query it, without wiring up the builder, embedding engines and indices by
+//! hand.
+//!
+//! - [`build`] scans, chunks, optionally embeds and writes an archive
+//! - [`search`] runs semantic search when the archive has embeddings and the
+//!   model can be found, and keyword search otherwise
+//!
+//! The embedding model directory is taken from [`Options::model_dir`] or the
+//! `CXP_MODEL_DIR` environment variable. At query time the model is resolved
+//! from the archive's manifest.
+//!
+//! # Example
+//! ```ignore
+//! use cxp_core::quick;
+//!
+//! let manifest = quick::build("./project", "project.cxp", quick::Options::default())?;
+//! println!("{} files archived", manifest.stats.total_files);
+//!
+//! for hit in quick::search("project.cxp", "error handling", 5)? {
+//!     println!("{} ({:.2})\n{}", hit.file_path, hit.score, hit.text);
+//! }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::cancel::CancellationToken;
+use crate::context::{ContextAssembler, ContextHit};
+use crate::format::{CxpBuilder, CxpReader};
+use crate::manifest::Manifest;
+use crate::map_shards::DEFAULT_SHARD_SIZE;
+use crate::Result;
+
+#[cfg(feature = "embeddings")]
+use crate::EmbeddingModel;
+
+/// Environment variable consulted when no model directory is configured
+pub const MODEL_DIR_ENV: &str = "CXP_MODEL_DIR";
+
+/// Settings for [`build`] and [`search_with`]
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Generate embeddings / use semantic search when a model is available (default: true)
+    pub embeddings: bool,
+    /// Embedding model directory (falls back to `CXP_MODEL_DIR`)
+    pub model_dir: Option<PathBuf>,
+    /// Embedding model used when building
+    #[cfg(feature = "embeddings")]
+    pub model: EmbeddingModel,
+    /// Include images (requires the multimodal feature)
+    pub images: bool,
+    /// Files per file map shard
+    pub shard_size: usize,
+    /// Record build telemetry in the archive
+    pub build_info: bool,
+    /// Token that aborts [`build`] with `CxpError::Cancelled`
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            embeddings: true,
+            model_dir: None,
+            #[cfg(feature = "embeddings")]
+            model: EmbeddingModel::MiniLM,
+            images: false,
+            shard_size: DEFAULT_SHARD_SIZE,
+            build_info: true,
+            cancellation: None,
+        }
+    }
+}
+
+impl Options {
+    /// Create options with the defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable embeddings
+    pub fn with_embeddings(mut self, embeddings: bool) -> Self {
+        self.embeddings = embeddings;
+        self
+    }
+
+    /// Set the embedding model directory
+    pub fn with_model_dir<P: Into<PathBuf>>(mut self, model_dir: P) -> Self {
+        self.model_dir = Some(model_dir.into());
+        self
+    }
+
+    /// Set the embedding model used when building
+    #[cfg(feature = "embeddings")]
+    pub fn with_model(mut self, model: EmbeddingModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Include images in the archive
+    pub fn with_images(mut self, images: bool) -> Self {
+        self.images = images;
+        self
+    }
+
+    /// Set the number of files per file map shard
+    pub fn with_shard_size(mut self, shard_size: usize) -> Self {
+        self.shard_size = shard_size;
+        self
+    }
+
+    /// Enable or disable build telemetry
+    pub fn with_build_info(mut self, build_info: bool) -> Self {
+        self.build_info = build_info;
+        self
+    }
+
+    /// Abort [`build`] once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Model directory to use, if embeddings are enabled and one exists
+    pub fn resolve_model_dir(&self) -> Option<PathBuf> {
+        if !self.embeddings {
+            return None;
+        }
+        self.model_dir
+            .clone()
+            .or_else(|| std::env::var_os(MODEL_DIR_ENV).map(PathBuf::from))
+            .filter(|dir| dir.is_dir())
+    }
+}
+
+/// A search result with the matching chunk's text
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hit {
+    /// File path inside the archive
+    pub file_path: String,
+    /// Chunk index within the file
+    pub chunk_index: usize,
+    /// Relevance score (higher is better)
+    pub score: f32,
+    /// Text of the matching chunk
+    pub text: String,
+}
+
+impl Hit {
+    /// Read the chunk text behind a context hit
+    fn read(reader: &CxpReader, hit: ContextHit) -> Result<Self> {
+        let data = reader.read_file_chunks(&hit.file_path, hit.chunk_index..hit.chunk_index + 1)?;
+        Ok(Self {
+            text: String::from_utf8_lossy(&data).into_owned(),
+            file_path: hit.file_path,
+            chunk_index: hit.chunk_index,
+            score: hit.score,
+        })
+    }
+}
+
+/// Archive `dir` into `out` and return the written manifest
+///
+/// Embeddings are generated when the "embeddings" and "search" features are
+/// enabled and a model directory resolves; otherwise the archive is built
+/// without them.
+pub fn build<P: AsRef<Path>, Q: AsRef<Path>>(dir: P, out: Q, options: Options) -> Result<Manifest> {
+    let mut builder = CxpBuilder::new(dir);
+    builder
+        .with_shard_size(options.shard_size)
+        .with_build_info(options.build_info);
+    if let Some(token) = &options.cancellation {
+        builder.with_cancellation(token.clone());
+    }
+
+    if options.images {
+        #[cfg(feature = "multimodal")]
+        builder.with_images();
+        #[cfg(not(feature = "multimodal"))]
+        tracing::warn!("Images requested but the multimodal feature is disabled");
+    }
+
+    builder.scan()?;
+
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    if let Some(model_dir) = options.resolve_model_dir() {
+        builder.with_embeddings(model_dir, options.model)?;
+    }
+
+    builder.process()?;
+    builder.build(out)?;
+
+    Ok(builder.manifest().clone())
+}
+
+/// Search an archive with the default options
+///
+/// See [`search_with`].
+pub fn search<P: AsRef<Path>>(path: P, query: &str, top_k: usize) -> Result<Vec<Hit>> {
+    search_with(path, query, top_k, &Options::default())
+}
+
+/// Search an archive, semantically if possible and by keywords otherwise
+///
+/// Semantic search is used when the archive has embeddings, their model is
+/// known and a model directory resolves from `options`.
+pub fn search_with<P: AsRef<Path>>(path: P, query: &str, top_k: usize, options: &Options) -> Result<Vec<Hit>> {
+    let reader = CxpReader::open(path)?;
+    search_reader(&reader, query, top_k, options)
+}
+
+/// Search an already opened archive
+///
+/// Embeddings are loaded into `reader` on the first semantic search and reused
+/// afterwards.
+pub fn search_reader(reader: &CxpReader, query: &str, top_k: usize, options: &Options) -> Result<Vec<Hit>> {
+    #[cfg(all(feature = "embeddings", feature = "search"))]
+    if reader.has_embeddings() {
+        if let Some(model_dir) = options.resolve_model_dir() {
+            return semantic_search(reader, &model_dir, query, top_k);
+        }
+        tracing::debug!("No embedding model directory found, using keyword search");
+    }
+    #[cfg(not(all(feature = "embeddings", feature = "search")))]
+    let _ = options;
+
+    let hits = ContextAssembler::new(reader, u64::MAX).keyword_hits(query, top_k)?;
+    hits.into_iter().map(|hit| Hit::read(reader, hit)).collect()
+}
+
+/// Embed the query with the archive's model and search its HNSW index
+#[cfg(all(feature = "embeddings", feature = "search"))]
+fn semantic_search(reader: &CxpReader, model_dir: &Path, query: &str, top_k: usize) -> Result<Vec<Hit>> {
+    use crate::{CxpError, EmbeddingEngine};
+
+    let model_name = reader.manifest().embedding_model.clone().unwrap_or_default();
+    let model = EmbeddingModel::from_name(&model_name)
+        .ok_or_else(|| CxpError::Embedding(format!("Unknown embedding model '{}'", model_name)))?;
+
+    reader.load_embeddings()?;
+    let query_embedding = EmbeddingEngine::load(model_dir, model)?.embed_query(query)?;
+    let results = reader.search_semantic(&query_embedding, top_k)?;
+
+    let assembler = ContextAssembler::new(reader, u64::MAX);
+    let hits: Vec<ContextHit> = results
+        .iter()
+        .filter_map(|r| {
+            reader
+                .embedding_chunk_hash(r.id)
+                .and_then(|hash| assembler.locate_chunk(hash, r.distance))
+        })
+        .collect();
+
+    hits.into_iter().map(|hit| Hit::read(reader, hit)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_quick_build_and_search() {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("auth.rs"), "fn login() { check_password(); }\n").unwrap();
+        fs::write(source.path().join("notes.md"), "# Notes\nNothing here.\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("quick.cxp");
+        let options = Options::new().with_embeddings(false).with_build_info(false);
+
+        let manifest = build(source.path(), &cxp_path, options.clone()).unwrap();
+        assert_eq!(manifest.stats.total_files, 2);
+
+        let hits = search_with(&cxp_path, "password", 5, &options).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].file_path, "auth.rs");
+        assert!(hits[0].text.contains("check_password"));
+
+        assert!(search_with(&cxp_path, "nonexistent", 5, &options).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_quick_build_cancelled() {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("a.txt"), "alpha\n").unwrap();
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("cancelled.cxp");
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let options = Options::new().with_embeddings(false).with_cancellation(token);
+        let err = build(source.path(), &cxp_path, options).unwrap_err();
+        assert!(matches!(err, crate::CxpError::Cancelled(_)));
+        assert!(!cxp_path.exists());
+    }
+}
@@ -0,0 +1,163 @@
+//! Recall Evaluation
+//!
+//! Semantic search ranks binary HNSW candidates, which can miss true nearest
+//! neighbours. `CxpReader::evaluate_recall` runs each query through both
+//! `search_semantic()` and the linear `search_exact()` and reports recall@k:
+//! the share of the exact top k that the HNSW search also returned.
+//!
+//! # Example
+//! ```ignore
+//! let reader = CxpReader::open("project.cxp")?;
+//! reader.load_embeddings()?;
+//! let queries = reader.sample_query_embeddings(100)?;
+//! let report = reader.evaluate_recall(&queries, 10)?;
+//! println!("recall@10 = {:.3}", report.mean_recall);
+//! ```
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use crate::{CxpReader, Result, SearchResult};
+
+/// Score slack under which two results count as tied
+const TIE_EPSILON: f32 = 1e-6;
+
+/// Query embedding for `CxpReader::evaluate_recall`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecallQuery {
+    /// Query embedding
+    pub embedding: Vec<f32>,
+    /// Embedding ID left out of both result lists (the query's own row when
+    /// it was sampled from the archive)
+    pub exclude: Option<u64>,
+}
+
+impl From<Vec<f32>> for RecallQuery {
+    fn from(embedding: Vec<f32>) -> Self {
+        Self { embedding, exclude: None }
+    }
+}
+
+/// Recall of HNSW search against exact search over a set of queries
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecallReport {
+    /// Number of queries evaluated
+    pub queries: usize,
+    /// Results compared per query
+    pub k: usize,
+    /// Mean recall@k over all queries (0.0 to 1.0)
+    pub mean_recall: f32,
+    /// Lowest recall@k of a single query
+    pub min_recall: f32,
+    /// Total time spent in `search_semantic()`
+    pub hnsw_time: Duration,
+    /// Total time spent in `search_exact()`
+    pub exact_time: Duration,
+}
+
+/// Share of the exact top `k` found in the approximate top `k`
+///
+/// Results scoring at least as high as the `k`th exact result count as hits,
+/// so ties broken differently by the two searches are not misses. Both lists
+/// must be sorted by score (highest first, in `distance`).
+pub fn recall_at_k(approx: &[SearchResult], exact: &[SearchResult], k: usize) -> f32 {
+    let exact = &exact[..k.min(exact.len())];
+    let Some(threshold) = exact.last().map(|r| r.distance - TIE_EPSILON) else {
+        return 1.0;
+    };
+    let ids: HashSet<u64> = exact.iter().map(|r| r.id).collect();
+    let hits = approx
+        .iter()
+        .take(k)
+        .filter(|r| ids.contains(&r.id) || r.distance >= threshold)
+        .count();
+    hits.min(exact.len()) as f32 / exact.len() as f32
+}
+
+/// Top `k` results without the excluded ID
+fn without(results: Vec<SearchResult>, exclude: Option<u64>, k: usize) -> Vec<SearchResult> {
+    results.into_iter().filter(|r| Some(r.id) != exclude).take(k).collect()
+}
+
+impl CxpReader {
+    /// Compare `search_semantic()` with `search_exact()` for every query
+    ///
+    /// A query's `exclude` ID is dropped from both searches, so a stored
+    /// embedding used as query is not its own ground truth. You must call
+    /// `load_embeddings()` first.
+    pub fn evaluate_recall(&self, queries: &[RecallQuery], k: usize) -> Result<RecallReport> {
+        let mut report = RecallReport {
+            queries: queries.len(),
+            k,
+            mean_recall: 1.0,
+            min_recall: 1.0,
+            hnsw_time: Duration::ZERO,
+            exact_time: Duration::ZERO,
+        };
+        let mut total = 0.0;
+        for query in queries {
+            let fetch = k + usize::from(query.exclude.is_some());
+
+            let started = Instant::now();
+            let approx = without(self.search_semantic(&query.embedding, fetch)?, query.exclude, k);
+            report.hnsw_time += started.elapsed();
+
+            let started = Instant::now();
+            let exact = without(self.search_exact(&query.embedding, fetch)?, query.exclude, k);
+            report.exact_time += started.elapsed();
+
+            let recall = recall_at_k(&approx, &exact, k);
+            report.min_recall = report.min_recall.min(recall);
+            total += recall;
+        }
+        if !queries.is_empty() {
+            report.mean_recall = total / queries.len() as f32;
+        }
+        Ok(report)
+    }
+
+    /// Up to `count` stored embeddings, evenly spread over the archive, to use as queries
+    ///
+    /// Lets recall be measured without the embedding model. Each query
+    /// excludes its own embedding ID. You must call `load_embeddings()` first.
+    pub fn sample_query_embeddings(&self, count: usize) -> Result<Vec<RecallQuery>> {
+        let vectors = self.embedding_vectors()?;
+        let step = vectors.len().div_ceil(count.max(1)).max(1);
+        Ok(vectors
+            .into_iter()
+            .step_by(step)
+            .take(count)
+            .map(|(id, embedding)| RecallQuery { embedding, exclude: Some(id) })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(scored: &[(u64, f32)]) -> Vec<SearchResult> {
+        scored.iter().map(|&(id, distance)| SearchResult { id, distance }).collect()
+    }
+
+    #[test]
+    fn test_recall_at_k() {
+        let exact = results(&[(1, 0.9), (2, 0.8), (3, 0.7), (4, 0.6)]);
+
+        assert_eq!(recall_at_k(&exact, &exact, 3), 1.0);
+        assert_eq!(recall_at_k(&results(&[(1, 0.9), (4, 0.6), (5, 0.5)]), &exact, 3), 1.0 / 3.0);
+        assert_eq!(recall_at_k(&[], &exact, 2), 0.0);
+        assert_eq!(recall_at_k(&[], &[], 10), 1.0);
+
+        // A different result with the same score as the kth exact one is a hit
+        assert_eq!(recall_at_k(&results(&[(1, 0.9), (9, 0.8)]), &exact, 2), 1.0);
+    }
+
+    #[test]
+    fn test_excluded_id_is_not_ground_truth() {
+        let fetched = results(&[(7, 1.0), (1, 0.9), (2, 0.8)]);
+
+        assert_eq!(without(fetched.clone(), Some(7), 2), results(&[(1, 0.9), (2, 0.8)]));
+        assert_eq!(without(fetched, None, 2), results(&[(7, 1.0), (1, 0.9)]));
+    }
+}
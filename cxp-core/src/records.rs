@@ -0,0 +1,311 @@
+//! Record Import
+//!
+//! Builds archives from text records instead of a directory tree, so
+//! scraped or synthetic corpora can be packaged as CXP:
+//!
+//! ```ignore
+//! let records = JsonlRecords::new(BufReader::new(File::open("data.jsonl")?), ImportOptions::new());
+//! let mut builder = CxpBuilder::new(".");
+//! builder.add_records(records.collect::<Result<Vec<_>>>()?)?;
+//! builder.process()?.build("corpus.cxp")?;
+//! ```
+//!
+//! Each record becomes one file (`records/<id>.txt` by default). Its
+//! metadata is stored as notes in the archive's annotations, with non-string
+//! values JSON-encoded. JSON Lines input follows the Hugging Face datasets
+//! convention: one object per line with a `text` field. IDs that map to the
+//! same file name (`a/b` and `a_b`) get a `-2`, `-3`, ... suffix.
+
+use crate::{CxpError, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::io::BufRead;
+
+/// One text record
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// Path inside the archive (relative, `/`-separated)
+    pub path: String,
+    /// Content
+    pub text: String,
+    /// Arbitrary metadata (stored as annotation notes)
+    pub metadata: BTreeMap<String, Value>,
+}
+
+impl Record {
+    /// Record stored at `path`
+    pub fn new(path: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            text: text.into(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// Attach a metadata value
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Record from a JSON object (see [`ImportOptions`] for the field mapping)
+    ///
+    /// `index` names the record when it has no ID field.
+    pub fn from_json(index: usize, value: Value, options: &ImportOptions) -> Result<Self> {
+        let Value::Object(mut fields) = value else {
+            return Err(CxpError::InvalidFormat(format!("Record {} is not a JSON object", index)));
+        };
+        let text = match fields.remove(&options.text_field) {
+            Some(Value::String(text)) => text,
+            _ => {
+                return Err(CxpError::InvalidFormat(format!(
+                    "Record {} has no '{}' string field",
+                    index, options.text_field
+                )))
+            }
+        };
+        let id = match options.id_field.as_ref().and_then(|field| fields.get(field)) {
+            Some(Value::String(id)) => id.clone(),
+            Some(Value::Number(id)) => id.to_string(),
+            Some(_) => {
+                return Err(CxpError::InvalidFormat(format!(
+                    "Record {} has a non-scalar ID",
+                    index
+                )))
+            }
+            None => format!("{:08}", index),
+        };
+
+        let mut path = options.prefix.trim_matches('/').to_string();
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(&sanitize(&id));
+        if !options.extension.is_empty() {
+            path.push('.');
+            path.push_str(options.extension.trim_start_matches('.'));
+        }
+
+        Ok(Self {
+            path,
+            text,
+            metadata: fields.into_iter().collect(),
+        })
+    }
+
+    /// Metadata as annotation notes (strings as-is, other values as JSON)
+    pub fn notes(&self) -> impl Iterator<Item = (&str, String)> {
+        self.metadata.iter().map(|(key, value)| {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (key.as_str(), value)
+        })
+    }
+}
+
+/// How JSON objects map to records
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Field holding the text (default: `text`)
+    pub text_field: String,
+    /// Field naming the record (default: none, records are numbered)
+    pub id_field: Option<String>,
+    /// Directory records are stored under (default: `records`)
+    pub prefix: String,
+    /// File extension of records (default: `txt`)
+    pub extension: String,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            text_field: "text".to_string(),
+            id_field: None,
+            prefix: "records".to_string(),
+            extension: "txt".to_string(),
+        }
+    }
+}
+
+impl ImportOptions {
+    /// Default field mapping (`text`, numbered `records/*.txt`)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the text from `field`
+    pub fn with_text_field(mut self, field: impl Into<String>) -> Self {
+        self.text_field = field.into();
+        self
+    }
+
+    /// Name records after `field`
+    pub fn with_id_field(mut self, field: impl Into<String>) -> Self {
+        self.id_field = Some(field.into());
+        self
+    }
+
+    /// Store records under `prefix`
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Give records this file extension
+    pub fn with_extension(mut self, extension: impl Into<String>) -> Self {
+        self.extension = extension.into();
+        self
+    }
+}
+
+/// Records read from JSON Lines (blank lines are skipped)
+pub struct JsonlRecords<R> {
+    lines: std::io::Lines<R>,
+    options: ImportOptions,
+    line: usize,
+    index: usize,
+    /// Paths handed out so far
+    paths: HashSet<String>,
+}
+
+impl<R: BufRead> JsonlRecords<R> {
+    /// Read records from `reader`
+    pub fn new(reader: R, options: ImportOptions) -> Self {
+        Self {
+            lines: reader.lines(),
+            options,
+            line: 0,
+            index: 0,
+            paths: HashSet::new(),
+        }
+    }
+
+    /// `path`, or `path` with the first free `-<n>` suffix if an earlier record took it
+    fn unique_path(&mut self, path: String) -> String {
+        let extension = self.options.extension.trim_start_matches('.');
+        let stem = match path.strip_suffix(extension).and_then(|stem| stem.strip_suffix('.')) {
+            Some(stem) if !extension.is_empty() => stem,
+            _ => path.as_str(),
+        };
+        let unique = (1..)
+            .map(|n| match n {
+                1 => path.clone(),
+                n if stem.len() < path.len() => format!("{}-{}.{}", stem, n, extension),
+                n => format!("{}-{}", stem, n),
+            })
+            .find(|candidate| !self.paths.contains(candidate))
+            .expect("unbounded suffixes");
+        self.paths.insert(unique.clone());
+        unique
+    }
+}
+
+impl<R: BufRead> Iterator for JsonlRecords<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            self.line += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value: Value = match serde_json::from_str(&line) {
+                Ok(value) => value,
+                Err(e) => {
+                    return Some(Err(CxpError::Serialization(format!("Line {}: {}", self.line, e))));
+                }
+            };
+            let record = Record::from_json(self.index, value, &self.options).map(|mut record| {
+                record.path = self.unique_path(record.path);
+                record
+            });
+            self.index += 1;
+            return Some(record);
+        }
+    }
+}
+
+/// Record ID usable as a file name
+fn sanitize(id: &str) -> String {
+    let name: String = id
+        .chars()
+        .map(|c| if c.is_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+        .collect();
+    match name.trim_matches('.') {
+        "" => "_".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CxpBuilder, CxpReader};
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_jsonl_records() {
+        let data = "{\"text\": \"first\", \"source\": \"web\", \"score\": 0.5}\n\n{\"id\": \"a/b c\", \"text\": \"second\"}\n";
+        let options = ImportOptions::new().with_id_field("id");
+        let records: Vec<Record> = JsonlRecords::new(data.as_bytes(), options).collect::<Result<_>>().unwrap();
+
+        assert_eq!(records[0].path, "records/00000000.txt");
+        assert_eq!(records[0].metadata["score"], json!(0.5));
+        assert_eq!(records[1].path, "records/a_b_c.txt");
+        assert_eq!(records[1].metadata["id"], json!("a/b c"));
+
+        let bad = JsonlRecords::new("{\"body\": 1}\nnot json\n".as_bytes(), ImportOptions::new());
+        let errors: Vec<String> = bad.map(|r| r.unwrap_err().to_string()).collect();
+        assert!(errors[0].contains("'text'"));
+        assert!(errors[1].contains("Line 2"));
+
+        assert_eq!(sanitize(".."), "_");
+    }
+
+    #[test]
+    fn test_colliding_ids_get_suffixes() {
+        let data = "{\"id\": \"a/b\", \"text\": \"1\"}\n{\"id\": \"a_b\", \"text\": \"2\"}\n{\"id\": \"a b\", \"text\": \"3\"}\n{\"id\": \"a_b-2\", \"text\": \"4\"}\n";
+        let options = ImportOptions::new().with_id_field("id");
+        let paths: Vec<String> = JsonlRecords::new(data.as_bytes(), options)
+            .map(|record| record.unwrap().path)
+            .collect();
+        assert_eq!(paths, vec!["records/a_b.txt", "records/a_b-2.txt", "records/a_b-3.txt", "records/a_b-2-2.txt"]);
+
+        let options = ImportOptions::new().with_id_field("id").with_extension("");
+        let paths: Vec<String> = JsonlRecords::new(data.as_bytes(), options)
+            .map(|record| record.unwrap().path)
+            .collect();
+        assert_eq!(paths, vec!["records/a_b", "records/a_b-2", "records/a_b-3", "records/a_b-2-2"]);
+    }
+
+    #[test]
+    fn test_build_from_records() {
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("records.cxp");
+
+        let mut builder = CxpBuilder::new(output.path().join("unused"));
+        builder
+            .add_records(vec![
+                Record::new("qa/1.txt", "What is CXP?").with_metadata("split", "train").with_metadata("votes", 3),
+                Record::new("qa/2.txt", "A context archive format."),
+            ])
+            .unwrap();
+        builder.process().unwrap().build(&cxp_path).unwrap();
+
+        let reader = CxpReader::open(&cxp_path).unwrap();
+        assert_eq!(reader.read_file("qa/1.txt").unwrap(), b"What is CXP?");
+        assert_eq!(reader.read_file("qa/2.txt").unwrap(), b"A context archive format.");
+        let notes = &reader.annotations().get("qa/1.txt").unwrap().notes;
+        assert_eq!(notes["split"], "train");
+        assert_eq!(notes["votes"], "3");
+
+        assert!(CxpBuilder::new(".").add_records([Record::new("../x.txt", "")]).is_err());
+    }
+}
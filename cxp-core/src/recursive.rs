@@ -16,7 +16,7 @@
 
 use serde::{Deserialize, Serialize};
 use serde_repr::{Serialize_repr, Deserialize_repr};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use chrono::{DateTime, Utc};
 
 use crate::Result;
@@ -167,7 +167,7 @@ pub enum CxpStorage {
 
 impl CxpStorage {
     /// Create external storage with path resolution
-    pub fn external_at(base_dir: &PathBuf, name: &str) -> Self {
+    pub fn external_at(base_dir: &Path, name: &str) -> Self {
         let path = base_dir.join(format!("{}.cxp", name));
         CxpStorage::External { path }
     }
@@ -313,6 +313,18 @@ pub enum FileTier {
 }
 
 impl FileTier {
+    /// Tier of a file by its modification time (unknown times count as warm)
+    pub fn from_modified(modified: Option<DateTime<Utc>>) -> Self {
+        let Some(modified) = modified else {
+            return FileTier::Warm;
+        };
+        match (Utc::now() - modified).num_days() {
+            i64::MIN..=7 => FileTier::Hot,
+            8..=30 => FileTier::Warm,
+            _ => FileTier::Cold,
+        }
+    }
+
     /// Get display name
     pub fn name(&self) -> &'static str {
         match self {
@@ -346,11 +358,22 @@ impl FileTier {
     }
 }
 
+/// A child whose tier changed during recalculation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TierChange {
+    /// Child ID
+    pub id: String,
+    /// Tier before recalculation
+    pub from: FileTier,
+    /// Tier after recalculation
+    pub to: FileTier,
+}
+
 /// Collection of child references in a CXP
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChildrenMap {
     /// Map of child ID to reference
-    pub children: std::collections::HashMap<String, CxpRef>,
+    pub children: std::collections::BTreeMap<String, CxpRef>,
 
     /// Ordered list of child IDs (for consistent iteration)
     pub order: Vec<String>,
@@ -412,6 +435,21 @@ impl ChildrenMap {
         self.by_tier(FileTier::Hot)
     }
 
+    /// Recalculate the tier of every child, returning the children that moved
+    pub fn recalculate_tiers(&mut self) -> Vec<TierChange> {
+        let mut changes = Vec::new();
+        for id in &self.order {
+            if let Some(child) = self.children.get_mut(id) {
+                let from = child.tier;
+                child.recalculate_tier();
+                if child.tier != from {
+                    changes.push(TierChange { id: id.clone(), from, to: child.tier });
+                }
+            }
+        }
+        changes
+    }
+
     /// Serialize to MessagePack
     pub fn to_msgpack(&self) -> Result<Vec<u8>> {
         rmp_serde::to_vec(self)
@@ -459,6 +497,34 @@ mod tests {
         assert_eq!(child.calculate_tier(), FileTier::Cold);
     }
 
+    #[test]
+    fn test_tier_from_modified() {
+        assert_eq!(FileTier::from_modified(Some(Utc::now())), FileTier::Hot);
+        assert_eq!(FileTier::from_modified(Some(Utc::now() - chrono::Duration::days(10))), FileTier::Warm);
+        assert_eq!(FileTier::from_modified(Some(Utc::now() - chrono::Duration::days(60))), FileTier::Cold);
+        assert_eq!(FileTier::from_modified(None), FileTier::Warm);
+    }
+
+    #[test]
+    fn test_recalculate_tiers() {
+        let mut children = ChildrenMap::new();
+
+        let mut stale = CxpRef::external("stale", "Stale", PathBuf::from("/stale.cxp"));
+        stale.tier = FileTier::Hot;
+        stale.meta.updated_at = Utc::now() - chrono::Duration::days(90);
+        let mut fresh = CxpRef::external("fresh", "Fresh", PathBuf::from("/fresh.cxp"));
+        fresh.meta.updated_at = Utc::now();
+        fresh.last_accessed = Some(Utc::now());
+        fresh.tier = FileTier::Hot;
+        children.add(stale);
+        children.add(fresh);
+
+        let changes = children.recalculate_tiers();
+        assert_eq!(changes, vec![TierChange { id: "stale".to_string(), from: FileTier::Hot, to: FileTier::Cold }]);
+        assert_eq!(children.get("stale").unwrap().tier, FileTier::Cold);
+        assert!(children.recalculate_tiers().is_empty());
+    }
+
     #[test]
     fn test_children_map() {
         let mut children = ChildrenMap::new();
@@ -6,12 +6,14 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use chrono::Utc;
-use walkdir::WalkDir;
 
 use crate::recursive::{CxpRef, CxpStorage, CxpRefMeta, FileTier, ChildrenMap};
-use crate::global_index::{GlobalIndex, GlobalIndexEntry};
-use crate::format::CxpBuilder;
+use crate::global_index::{GlobalIndex, GLOBAL_INDEX_PATH};
+use crate::format::{ArchiveWriter, CxpBuilder, CxpFile, FileMap};
+use crate::map_shards::DEFAULT_SHARD_SIZE;
+use crate::cancel::CancellationToken;
 use crate::manifest::Manifest;
+use crate::keywords::KeywordExtractor;
 use crate::{Result, CxpError};
 
 /// Configuration for recursive CXP building
@@ -105,6 +107,10 @@ pub struct RecursiveBuilder {
     global_index: GlobalIndex,
     /// Built CXP paths
     built_cxps: Vec<PathBuf>,
+    /// References to the top-level CXPs (children of the master)
+    root_refs: Vec<CxpRef>,
+    /// Checked per directory entry and passed on to every `CxpBuilder`
+    cancellation: CancellationToken,
 }
 
 impl RecursiveBuilder {
@@ -114,9 +120,17 @@ impl RecursiveBuilder {
             config,
             global_index: GlobalIndex::new(),
             built_cxps: Vec::new(),
+            root_refs: Vec::new(),
+            cancellation: CancellationToken::default(),
         }
     }
 
+    /// Abort analysis and building once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
     /// Analyze a directory and propose a structure
     pub fn analyze(&self, root: &Path) -> Result<ProposedStructure> {
         self.analyze_dir(root, 0)
@@ -135,9 +149,10 @@ impl RecursiveBuilder {
 
         // Scan directory
         for entry in std::fs::read_dir(path)
-            .map_err(|e| CxpError::Io(e.to_string()))?
+            .map_err(|e| CxpError::io(e.to_string()))?
         {
-            let entry = entry.map_err(|e| CxpError::Io(e.to_string()))?;
+            self.cancellation.check("analyze")?;
+            let entry = entry.map_err(|e| CxpError::io(e.to_string()))?;
             let entry_path = entry.path();
             let entry_name = entry_path.file_name()
                 .and_then(|n| n.to_str())
@@ -166,7 +181,7 @@ impl RecursiveBuilder {
                 }
             } else if entry_path.is_file() {
                 let metadata = entry_path.metadata()
-                    .map_err(|e| CxpError::Io(e.to_string()))?;
+                    .map_err(|e| CxpError::io(e.to_string()))?;
 
                 stats.file_count += 1;
                 stats.total_size += metadata.len();
@@ -246,24 +261,25 @@ impl RecursiveBuilder {
 
     /// Build CXPs from a proposed structure
     pub fn build(&mut self, root: &Path, structure: &ProposedStructure, parent_path: Vec<String>) -> Result<CxpRef> {
+        self.cancellation.check("build")?;
         let cxp_name = &structure.name;
         let cxp_path = self.config.output_dir.join(parent_path.join("/")).join(format!("{}.cxp", cxp_name));
 
         // Ensure parent directory exists
         if let Some(parent) = cxp_path.parent() {
             std::fs::create_dir_all(parent)
-                .map_err(|e| CxpError::Io(e.to_string()))?;
+                .map_err(|e| CxpError::io(e.to_string()))?;
         }
 
         // Build the CXP using the standard builder
         let mut builder = CxpBuilder::new(root);
-        builder.scan()?;
-
-        // Add files from this level
-        for file in &structure.direct_files {
-            // The standard builder's scan() already includes files
-            // Here we could add custom processing
+        builder.with_cancellation(self.cancellation.clone());
+        if self.config.extract_keywords {
+            builder.with_keywords(KeywordExtractor::new());
         }
+        builder.scan()?.process()?;
+
+        // Files from this level are already included by the standard builder's scan()
 
         // Process children
         let mut children_map = ChildrenMap::new();
@@ -300,7 +316,7 @@ impl RecursiveBuilder {
                 updated_at: Utc::now(),
                 category: None,
                 file_types: structure.stats.file_types.keys().cloned().collect(),
-                keywords: Vec::new(),
+                keywords: builder.manifest().topics.clone(),
                 has_embeddings: false,
             },
             last_accessed: None,
@@ -309,39 +325,36 @@ impl RecursiveBuilder {
         };
 
         // Add to global index
-        self.add_to_index(&cxp_ref, &structure)?;
+        self.add_to_index(&cxp_ref, structure, current_path)?;
+
+        if parent_path.is_empty() {
+            self.root_refs.push(cxp_ref.clone());
+        }
 
         Ok(cxp_ref)
     }
 
-    /// Add CXP entries to the global index
-    fn add_to_index(&mut self, cxp_ref: &CxpRef, structure: &ProposedStructure) -> Result<()> {
-        let cxp_path = match &cxp_ref.storage {
-            CxpStorage::External { path } => path.to_string_lossy().to_string(),
-            _ => cxp_ref.id.clone(),
+    /// Add the files of a built CXP to the global index
+    ///
+    /// Files below child directories that became their own CXP are indexed
+    /// with the child only.
+    fn add_to_index(&mut self, cxp_ref: &CxpRef, structure: &ProposedStructure, cxp_path: Vec<String>) -> Result<()> {
+        let CxpStorage::External { path } = &cxp_ref.storage else {
+            return Ok(());
         };
-
-        for file in &structure.direct_files {
-            let file_name = file.file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("")
-                .to_string();
-
-            let file_type = file.extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or("")
-                .to_lowercase();
-
-            let entry = GlobalIndexEntry::new(
-                &cxp_ref.id,
-                vec![cxp_ref.name.clone()],
-                file.to_string_lossy(),
-                &file_type,
-            );
-
-            self.global_index.add(entry);
+        let child_dirs: Vec<String> = structure.children.iter()
+            .filter(|child| child.should_be_cxp)
+            .map(|child| format!("{}/", child.name))
+            .collect();
+
+        let mut file_map = FileMap::default();
+        for (file_path, entry) in CxpFile::open(path)?.file_map.files {
+            if !child_dirs.iter().any(|dir| file_path.starts_with(dir.as_str())) {
+                file_map.files.insert(file_path, entry);
+            }
         }
 
+        self.global_index.add_file_map(cxp_path, cxp_ref.tier, &file_map);
         Ok(())
     }
 
@@ -355,44 +368,27 @@ impl RecursiveBuilder {
         &self.built_cxps
     }
 
-    /// Build a master CXP that references all others
+    /// Build a master CXP that references all top-level CXPs
+    ///
+    /// The master has no files of its own; it lists the built CXPs as
+    /// children and stores the global index at `global_index.msgpack`.
     pub fn build_master(&self, name: &str) -> Result<PathBuf> {
         let master_path = self.config.output_dir.join(format!("{}.cxp", name));
 
         // Create manifest with children
         let mut manifest = Manifest::new();
         manifest.tier = FileTier::Hot; // Master is always hot
+        for cxp_ref in &self.root_refs {
+            manifest.add_child(cxp_ref.clone());
+        }
 
-        // Add all built CXPs as children
-        // In a real implementation, we'd load each CXP's metadata
-
-        // Serialize manifest
-        let manifest_data = manifest.to_msgpack()?;
-
-        // Create ZIP file
-        let file = std::fs::File::create(&master_path)
-            .map_err(|e| CxpError::Io(e.to_string()))?;
-        let mut zip = zip::ZipWriter::new(file);
-
-        let options = zip::write::SimpleFileOptions::default()
-            .compression_method(zip::CompressionMethod::Zstd);
-
-        zip.start_file("manifest.msgpack", options)
-            .map_err(|e| CxpError::Io(e.to_string()))?;
-
-        use std::io::Write;
-        zip.write_all(&manifest_data)
-            .map_err(|e| CxpError::Io(e.to_string()))?;
-
-        // Write global index
-        let index_data = self.global_index.to_msgpack()?;
-        zip.start_file("global_index.msgpack", options)
-            .map_err(|e| CxpError::Io(e.to_string()))?;
-        zip.write_all(&index_data)
-            .map_err(|e| CxpError::Io(e.to_string()))?;
-
-        zip.finish()
-            .map_err(|e| CxpError::Io(e.to_string()))?;
+        let file_map = FileMap::default();
+        let mut writer = ArchiveWriter::create(&master_path)?;
+        writer.write("manifest.msgpack", &manifest.to_msgpack()?)?;
+        writer.write_file_map(&file_map, DEFAULT_SHARD_SIZE)?;
+        writer.write_filters(&file_map)?;
+        writer.write(GLOBAL_INDEX_PATH, &self.global_index.to_msgpack()?)?;
+        writer.finish()?;
 
         Ok(master_path)
     }
@@ -461,8 +457,10 @@ mod tests {
         let builder = RecursiveBuilder::new(RecursiveBuildConfig::default());
 
         // Recent modification -> Hot
-        let mut stats = DirStats::default();
-        stats.newest_modified = Some(std::time::SystemTime::now());
+        let stats = DirStats {
+            newest_modified: Some(std::time::SystemTime::now()),
+            ..Default::default()
+        };
         assert_eq!(builder.calculate_tier_from_stats(&stats), FileTier::Hot);
 
         // No modification info -> Warm (default)
@@ -127,10 +127,10 @@ impl Default for TierThresholds {
 impl TierThresholds {
     /// Create new thresholds with validation
     pub fn new(hot_min: f64, warm_min: f64) -> Result<Self, String> {
-        if hot_min < 0.0 || hot_min > 1.0 {
+        if !(0.0..=1.0).contains(&hot_min) {
             return Err("hot_min must be between 0.0 and 1.0".to_string());
         }
-        if warm_min < 0.0 || warm_min > 1.0 {
+        if !(0.0..=1.0).contains(&warm_min) {
             return Err("warm_min must be between 0.0 and 1.0".to_string());
         }
         if hot_min <= warm_min {
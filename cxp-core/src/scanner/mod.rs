@@ -13,6 +13,7 @@ mod ignore;
 mod relevance;
 mod tier;
 mod config;
+mod plan;
 
 pub use profile::{UserProfile, SpecialDetector, DetectedApp};
 pub use profile_detector::{ProfileDetector, ProfileSuggestion, QuickScanner, QuickScanResult};
@@ -21,3 +22,4 @@ pub use ignore::{IgnoreConfig, ALWAYS_IGNORE, DEFAULT_IGNORE};
 pub use relevance::{RelevanceScorer, FileMetadata};
 pub use tier::{Tier, TierManager};
 pub use config::ScanConfig;
+pub use plan::{PlannedFile, ScanPlan};
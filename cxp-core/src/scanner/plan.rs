@@ -0,0 +1,227 @@
+//! Scan plans: the files a profile-aware scan selected, with their tiers
+//!
+//! A `ScanPlan` is what `cxp smart-scan` reports and what
+//! `CxpBuilder::with_scan_plan` packs, so relevance scoring directly decides
+//! which files end up in an archive.
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::Result;
+use walkdir::WalkDir;
+use crate::recursive::FileTier;
+use super::ignore::IgnoreConfig;
+use super::profile::UserProfile;
+use super::relevance::{FileMetadata, RelevanceScorer};
+use super::tier::{Tier, TierManager};
+
+/// A file selected by a scan
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    /// Absolute or scan-root-joined path
+    pub path: PathBuf,
+    /// Relevance score (0.0 - 1.0)
+    pub score: f64,
+    /// Tier derived from the score
+    pub tier: Tier,
+    /// File size in bytes
+    pub size: u64,
+}
+
+/// Files selected for a profile, grouped into tiers
+#[derive(Debug, Clone)]
+pub struct ScanPlan {
+    /// Profile used for filtering and scoring
+    pub profile: UserProfile,
+    /// Selected files, in walk order
+    pub files: Vec<PlannedFile>,
+    /// Files looked at
+    pub scanned: usize,
+    /// Files skipped by ignore rules, extension or size
+    pub ignored: usize,
+}
+
+impl ScanPlan {
+    /// Scan `paths` with the ignore rules, extensions and size limit of `profile`
+    pub fn scan<P: AsRef<Path>>(paths: &[P], profile: UserProfile) -> Result<Self> {
+        let scan_config = profile.default_config();
+        let ignore_config = IgnoreConfig::default();
+        let scorer = RelevanceScorer::new(profile);
+        let mut plan = Self { profile, files: Vec::new(), scanned: 0, ignored: 0 };
+
+        for base in paths {
+            let base = base.as_ref();
+            for entry in WalkDir::new(base).follow_links(false).into_iter().filter_map(|e| e.ok()) {
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                plan.scanned += 1;
+
+                // Ignore rules see the path below the scan root
+                let path = entry.path();
+                let relative = path.strip_prefix(base).unwrap_or(path);
+                let relative_str = relative.to_string_lossy().replace('\\', "/");
+                if ignore_config.should_ignore(&relative_str)? {
+                    plan.ignored += 1;
+                    continue;
+                }
+
+                let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+                let allowed = match &extension {
+                    Some(ext) => {
+                        scan_config.file_extensions.is_empty()
+                            || scan_config.file_extensions.iter().any(|e| e.to_lowercase() == *ext)
+                    }
+                    None => false,
+                };
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                if !allowed || size > scan_config.max_file_size {
+                    plan.ignored += 1;
+                    continue;
+                }
+
+                let score = match FileMetadata::from_path(path) {
+                    Ok(mut metadata) => {
+                        metadata.path = relative_str;
+                        metadata.path_depth = relative.components().count();
+                        scorer.score_file(&metadata)
+                    }
+                    Err(_) => 0.5,
+                };
+                plan.files.push(PlannedFile {
+                    path: path.to_path_buf(),
+                    score,
+                    tier: Tier::from_score(score),
+                    size,
+                });
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Keep only files in `tiers` (all files if empty)
+    pub fn with_tiers(mut self, tiers: &[Tier]) -> Self {
+        if !tiers.is_empty() {
+            self.files.retain(|file| tiers.contains(&file.tier));
+        }
+        self
+    }
+
+    /// Files in `tier`
+    pub fn files_in(&self, tier: Tier) -> impl Iterator<Item = &PlannedFile> {
+        self.files.iter().filter(move |file| file.tier == tier)
+    }
+
+    /// Tier of the file at `path`, if the plan selected it
+    pub fn tier_of(&self, path: &Path) -> Option<Tier> {
+        self.files.iter().find(|file| file.path == path).map(|file| file.tier)
+    }
+
+    /// Total bytes of the files in `tier`
+    pub fn size_of(&self, tier: Tier) -> u64 {
+        self.files_in(tier).map(|file| file.size).sum()
+    }
+
+    /// Tier manager holding the selected files
+    pub fn tier_manager(&self) -> TierManager {
+        let mut manager = TierManager::new();
+        for file in &self.files {
+            manager.add_file_with_score(file.path.to_string_lossy().to_string(), file.score);
+        }
+        manager
+    }
+}
+
+impl FromStr for Tier {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "hot" => Ok(Tier::Hot),
+            "warm" => Ok(Tier::Warm),
+            "cold" => Ok(Tier::Cold),
+            _ => Err(anyhow::anyhow!("Unknown tier: {}. Valid options: hot, warm, cold", s)),
+        }
+    }
+}
+
+impl From<Tier> for FileTier {
+    fn from(tier: Tier) -> Self {
+        match tier {
+            Tier::Hot => FileTier::Hot,
+            Tier::Warm => FileTier::Warm,
+            Tier::Cold => FileTier::Cold,
+        }
+    }
+}
+
+impl FromStr for UserProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "developer" | "dev" => Ok(UserProfile::Developer),
+            "photographer" | "photo" => Ok(UserProfile::Photographer),
+            "designer" | "design" => Ok(UserProfile::Designer),
+            "writer" | "write" => Ok(UserProfile::Writer),
+            "student" => Ok(UserProfile::Student),
+            "business" | "biz" => Ok(UserProfile::Business),
+            "custom" => Ok(UserProfile::Custom),
+            _ => Err(anyhow::anyhow!(
+                "Unknown profile: {}. Valid options: developer, photographer, designer, writer, student, business, custom",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CxpBuilder, CxpReader, CxpSplitter, SplitMode};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_plan() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.path().join("README.md"), "# Readme\n").unwrap();
+        std::fs::write(dir.path().join("node_modules/pkg/index.js"), "x\n").unwrap();
+        std::fs::write(dir.path().join("photo.cr2"), "raw").unwrap();
+
+        let plan = ScanPlan::scan(&[dir.path()], UserProfile::Developer).unwrap();
+        assert_eq!(plan.scanned, 4);
+        assert_eq!(plan.files.len(), 2);
+        let main = dir.path().join("src/main.rs");
+        let tier = plan.tier_of(&main).unwrap();
+        assert_eq!(plan.clone().with_tiers(&[tier]).files_in(tier).count(), plan.files_in(tier).count());
+        assert!(plan.tier_of(&dir.path().join("photo.cr2")).is_none());
+
+        // Builds pack the planned files, tagged with their tier
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("plan.cxp");
+        let mut builder = CxpBuilder::new(dir.path());
+        builder.with_scan_plan(plan.clone().with_tiers(&[tier]));
+        builder.scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+        let reader = CxpReader::open(&cxp_path).unwrap();
+        assert!(reader.file_paths().contains(&"src/main.rs"));
+        assert!(!reader.file_paths().contains(&"node_modules/pkg/index.js"));
+        let tag = format!("tier:{}", tier.name().to_lowercase());
+        assert!(reader.annotations().get("src/main.rs").unwrap().tags.contains(&tag));
+        assert_eq!(reader.manifest().tier, FileTier::from(tier));
+        assert_eq!(reader.manifest().metadata["scan.profile"], "developer");
+
+        // Splitting by tier follows the tags
+        let stats = CxpSplitter::new(SplitMode::ByTier)
+            .split(&cxp_path, output.path().join("parent.cxp"))
+            .unwrap();
+        assert_eq!(stats.children.len(), 1);
+        assert_eq!(stats.children[0].id, tier.name().to_lowercase());
+
+        assert_eq!("HOT".parse::<Tier>().unwrap(), Tier::Hot);
+        assert!("lukewarm".parse::<Tier>().is_err());
+        assert_eq!("dev".parse::<UserProfile>().unwrap(), UserProfile::Developer);
+    }
+}
@@ -3,9 +3,10 @@ use serde::{Deserialize, Serialize};
 use super::config::ScanConfig;
 
 /// User profiles for intelligent scan configuration
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum UserProfile {
     /// Developer - Code, Configs, Documentation
+    #[default]
     Developer,
     /// Photographer - RAW, JPEG, Lightroom, Catalogs
     Photographer,
@@ -21,12 +22,6 @@ pub enum UserProfile {
     Custom,
 }
 
-impl Default for UserProfile {
-    fn default() -> Self {
-        UserProfile::Developer
-    }
-}
-
 impl UserProfile {
     /// Returns the default ScanConfig for this profile
     pub fn default_config(&self) -> ScanConfig {
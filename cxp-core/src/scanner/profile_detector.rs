@@ -72,9 +72,9 @@ impl ProfileDetector {
                 },
                 "pdf" => {
                     // PDF is universal - small weight for all
-                    *scores.entry(UserProfile::Writer).or_insert(0) += (*count as i32) * 1;
-                    *scores.entry(UserProfile::Business).or_insert(0) += (*count as i32) * 1;
-                    *scores.entry(UserProfile::Student).or_insert(0) += (*count as i32) * 1;
+                    *scores.entry(UserProfile::Writer).or_insert(0) += *count as i32;
+                    *scores.entry(UserProfile::Business).or_insert(0) += *count as i32;
+                    *scores.entry(UserProfile::Student).or_insert(0) += *count as i32;
                 },
                 _ => {},
             }
@@ -101,9 +101,9 @@ impl ProfileDetector {
 
         // === STEP 3: Calculate confidence ===
         let mut sorted: Vec<_> = scores.into_iter().collect();
-        sorted.sort_by(|a, b| b.1.cmp(&a.1));
+        sorted.sort_by_key(|entry| std::cmp::Reverse(entry.1));
 
-        let (primary, primary_score) = sorted.get(0)
+        let (primary, primary_score) = sorted.first()
             .map(|(p, s)| (*p, *s))
             .unwrap_or((UserProfile::Custom, 0));
 
@@ -117,7 +117,7 @@ impl ProfileDetector {
             1.0
         } else {
             let ratio = primary_score as f32 / secondary_score as f32;
-            ((ratio - 1.0).min(1.0).max(0.0) * 0.5 + 0.5).min(1.0)
+            ((ratio - 1.0).clamp(0.0, 1.0) * 0.5 + 0.5).min(1.0)
         };
 
         ProfileSuggestion {
@@ -531,10 +531,8 @@ mod tests {
         for name in skip_names {
             let path = temp.join(name);
             if path.exists() {
-                for entry in WalkDir::new(&path).max_depth(0) {
-                    if let Ok(entry) = entry {
-                        assert!(QuickScanner::should_skip(&entry));
-                    }
+                for entry in WalkDir::new(&path).max_depth(0).into_iter().flatten() {
+                    assert!(QuickScanner::should_skip(&entry));
                 }
             }
         }
@@ -551,7 +549,6 @@ mod tests {
         }
     }
 
-    #[test]
     #[test]
     fn test_confidence_calculation() {
         // Test case 1: Photographer vs Developer (Photographer wins)
@@ -1,19 +1,34 @@
 //! Semantic Embeddings Storage
 //!
-//! This module provides serialization and deserialization for binary and Int8 embeddings
-//! that are stored inside CXP archives.
+//! This module provides serialization and deserialization for binary, Int8 and
+//! float (f16/f32) embeddings that are stored inside CXP archives.
 //!
 //! Features:
-//! - Binary and Int8 embedding storage
+//! - Binary, Int8 and float embedding storage
 //! - Compact serialization format
 //! - Integration with HNSW index
+//! - Linear-scan search for builds without USearch (e.g. embeddings-wasm)
 
 #[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
-use crate::{BinaryEmbedding, Int8Embedding, CxpError, Result};
+use crate::{BinaryEmbedding, EmbeddingPrecision, Int8Embedding, CxpError, Result};
 
 #[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
 use serde::{Deserialize, Serialize};
 
+/// Number of binary candidates kept per requested result before Int8 rescoring
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
+pub const RESCORE_CANDIDATE_FACTOR: usize = 4;
+
+/// A single result of a linear scan over an `EmbeddingStore`
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoreSearchResult {
+    /// Index of the embedding in the store (same order as the chunks at build time)
+    pub id: usize,
+    /// Int8 similarity score (higher is better)
+    pub score: f32,
+}
+
 /// Container for storing embeddings in a CXP archive
 #[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +37,9 @@ pub struct EmbeddingStore {
     pub binary: Vec<BinaryEmbedding>,
     /// Int8 embeddings (for rescoring)
     pub int8: Vec<Int8Embedding>,
+    /// Float embeddings (for rescoring archives built with f16/f32 precision)
+    #[serde(default)]
+    pub float: Vec<Vec<f32>>,
     /// Original embedding dimensions
     pub dimensions: usize,
 }
@@ -33,10 +51,17 @@ impl EmbeddingStore {
         Self {
             binary,
             int8,
+            float: Vec::new(),
             dimensions,
         }
     }
 
+    /// Rescore with these float vectors instead of the Int8 ones
+    pub fn with_float(mut self, float: Vec<Vec<f32>>) -> Self {
+        self.float = float;
+        self
+    }
+
     /// Create from float embeddings
     pub fn from_floats(embeddings: &[Vec<f32>]) -> Self {
         let dimensions = embeddings.first().map(|e| e.len()).unwrap_or(0);
@@ -46,6 +71,7 @@ impl EmbeddingStore {
         Self {
             binary,
             int8,
+            float: Vec::new(),
             dimensions,
         }
     }
@@ -64,7 +90,8 @@ impl EmbeddingStore {
     pub fn size_bytes(&self) -> usize {
         let binary_size: usize = self.binary.iter().map(|e| e.size_bytes()).sum();
         let int8_size: usize = self.int8.iter().map(|e| e.size_bytes()).sum();
-        binary_size + int8_size + 8 // +8 for dimensions
+        let float_size: usize = self.float.iter().map(|e| e.len() * 4).sum();
+        binary_size + int8_size + float_size + 8 // +8 for dimensions
     }
 
     /// Get a binary embedding by index
@@ -76,6 +103,69 @@ impl EmbeddingStore {
     pub fn get_int8(&self, index: usize) -> Option<&Int8Embedding> {
         self.int8.get(index)
     }
+
+    /// Search the store without an HNSW index
+    ///
+    /// Scans all binary embeddings by Hamming distance, keeps the best
+    /// `k * RESCORE_CANDIDATE_FACTOR` candidates and rescores them with float
+    /// or Int8 dot products. Intended for modest archives and for builds that cannot
+    /// load USearch (e.g. embeddings-wasm).
+    ///
+    /// # Returns
+    /// Up to `k` results sorted by score (highest first)
+    pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<StoreSearchResult>> {
+        if k == 0 || self.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if query.len() != self.dimensions {
+            return Err(CxpError::Search(format!(
+                "Query has {} dimensions, store expects {}",
+                query.len(),
+                self.dimensions
+            )));
+        }
+
+        // Binary pre-filter: rank everything by Hamming distance
+        let query_binary = BinaryEmbedding::from_float(query);
+        let mut candidates: Vec<(usize, u32)> = self
+            .binary
+            .iter()
+            .enumerate()
+            .map(|(id, emb)| (id, emb.hamming_distance(&query_binary)))
+            .collect();
+
+        let candidate_count = k.saturating_mul(RESCORE_CANDIDATE_FACTOR).min(candidates.len());
+        if candidate_count < candidates.len() {
+            candidates.select_nth_unstable_by_key(candidate_count, |&(id, dist)| (dist, id));
+            candidates.truncate(candidate_count);
+        }
+
+        // Rescore with float or Int8 vectors for better accuracy
+        let query_int8 = Int8Embedding::from_float(query);
+        let mut results: Vec<StoreSearchResult> = candidates
+            .into_iter()
+            .map(|(id, dist)| {
+                let score = match (self.float.get(id), self.int8.get(id)) {
+                    (Some(emb), _) => crate::simd::dot_f32(emb, query),
+                    (None, Some(emb)) => emb.dot_product(&query_int8),
+                    // Fall back to the binary similarity if no rescoring vector is stored
+                    (None, None) => 1.0 - 2.0 * dist as f32 / self.dimensions.max(1) as f32,
+                };
+                StoreSearchResult { id, score }
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.id.cmp(&b.id))
+        });
+        results.truncate(k);
+
+        Ok(results)
+    }
 }
 
 /// Serialize binary embeddings to a compact binary format
@@ -93,7 +183,7 @@ pub fn serialize_binary_embeddings(embeddings: &[BinaryEmbedding]) -> Result<Vec
 
     let count = embeddings.len() as u32;
     let dimensions = embeddings[0].dimensions as u32;
-    let bytes_per_embedding = (dimensions + 7) / 8;
+    let bytes_per_embedding = dimensions.div_ceil(8);
 
     let mut data = Vec::with_capacity(8 + embeddings.len() * bytes_per_embedding as usize);
 
@@ -129,7 +219,7 @@ pub fn deserialize_binary_embeddings(data: &[u8]) -> Result<Vec<BinaryEmbedding>
 
     let count = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
     let dimensions = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
-    let bytes_per_embedding = (dimensions + 7) / 8;
+    let bytes_per_embedding = dimensions.div_ceil(8);
 
     let expected_size = 8 + count * bytes_per_embedding;
     if data.len() != expected_size {
@@ -247,6 +337,94 @@ pub fn deserialize_int8_embeddings(data: &[u8]) -> Result<Vec<Int8Embedding>> {
     Ok(embeddings)
 }
 
+/// Serialize float embeddings as f16 or f32 (`precision` must be `F16` or `F32`)
+///
+/// Format:
+/// - u32: number of embeddings
+/// - u32: dimensions
+/// - For each embedding:
+///   - f16/f32 * dimensions: values (little endian)
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
+pub fn serialize_float_embeddings(embeddings: &[Vec<f32>], precision: EmbeddingPrecision) -> Result<Vec<u8>> {
+    let width = float_width(precision)?;
+    if embeddings.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let count = embeddings.len() as u32;
+    let dimensions = embeddings[0].len() as u32;
+
+    let mut data = Vec::with_capacity(8 + embeddings.len() * dimensions as usize * width);
+
+    // Header
+    data.extend_from_slice(&count.to_le_bytes());
+    data.extend_from_slice(&dimensions.to_le_bytes());
+
+    // Embeddings
+    for emb in embeddings {
+        if emb.len() as u32 != dimensions {
+            return Err(CxpError::Serialization(
+                "All float embeddings must have same dimensions".to_string()
+            ));
+        }
+        for &val in emb {
+            match precision {
+                EmbeddingPrecision::F16 => data.extend_from_slice(&half::f16::from_f32(val).to_le_bytes()),
+                _ => data.extend_from_slice(&val.to_le_bytes()),
+            }
+        }
+    }
+
+    Ok(data)
+}
+
+/// Deserialize float embeddings written by `serialize_float_embeddings`
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
+pub fn deserialize_float_embeddings(data: &[u8], precision: EmbeddingPrecision) -> Result<Vec<Vec<f32>>> {
+    let width = float_width(precision)?;
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if data.len() < 8 {
+        return Err(CxpError::Serialization(
+            "Invalid float embeddings data: too short".to_string()
+        ));
+    }
+
+    let count = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    let dimensions = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+
+    let expected_size = 8 + count * dimensions * width;
+    if data.len() != expected_size {
+        return Err(CxpError::Serialization(format!(
+            "Invalid float embeddings data: expected {} bytes, got {}",
+            expected_size,
+            data.len()
+        )));
+    }
+
+    let values: Vec<f32> = data[8..]
+        .chunks_exact(width)
+        .map(|b| match precision {
+            EmbeddingPrecision::F16 => half::f16::from_le_bytes([b[0], b[1]]).to_f32(),
+            _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        })
+        .collect();
+
+    Ok(values.chunks(dimensions.max(1)).take(count).map(<[f32]>::to_vec).collect())
+}
+
+/// Bytes per value of a float precision
+#[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
+fn float_width(precision: EmbeddingPrecision) -> Result<usize> {
+    match precision {
+        EmbeddingPrecision::F16 => Ok(2),
+        EmbeddingPrecision::F32 => Ok(4),
+        _ => Err(CxpError::Serialization(format!("{:?} embeddings are not stored as floats", precision))),
+    }
+}
+
 #[cfg(test)]
 #[cfg(any(feature = "embeddings", feature = "embeddings-wasm", feature = "multimodal"))]
 mod tests {
@@ -310,6 +488,24 @@ mod tests {
         assert!((deserialized[1].scale - int8[1].scale).abs() < 0.001);
     }
 
+    #[test]
+    fn test_float_serialization_roundtrip() {
+        let embeddings = vec![vec![0.25, -0.5, 0.125, 1.0], vec![0.1, 0.2, 0.3, 0.4]];
+
+        let f32_data = serialize_float_embeddings(&embeddings, EmbeddingPrecision::F32).unwrap();
+        assert_eq!(f32_data.len(), 8 + 2 * 4 * 4);
+        assert_eq!(deserialize_float_embeddings(&f32_data, EmbeddingPrecision::F32).unwrap(), embeddings);
+
+        let f16_data = serialize_float_embeddings(&embeddings, EmbeddingPrecision::F16).unwrap();
+        assert_eq!(f16_data.len(), 8 + 2 * 4 * 2);
+        let restored = deserialize_float_embeddings(&f16_data, EmbeddingPrecision::F16).unwrap();
+        assert_eq!(restored[0], embeddings[0]); // exactly representable
+        assert!(restored[1].iter().zip(&embeddings[1]).all(|(a, b)| (a - b).abs() < 1e-3));
+
+        assert!(deserialize_float_embeddings(&f32_data, EmbeddingPrecision::F16).is_err());
+        assert!(serialize_float_embeddings(&embeddings, EmbeddingPrecision::Int8).is_err());
+    }
+
     #[test]
     fn test_empty_embeddings() {
         let empty_binary: Vec<BinaryEmbedding> = Vec::new();
@@ -338,6 +534,34 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_store_search_finds_nearest() {
+        let embeddings = vec![
+            vec![1.0, 0.9, -1.0, -0.8, 0.5, -0.5, 1.0, -1.0],
+            vec![-1.0, -0.9, 1.0, 0.8, -0.5, 0.5, -1.0, 1.0],
+            vec![0.9, 1.0, -0.7, -1.0, 0.4, -0.6, 0.8, -0.9],
+        ];
+        let store = EmbeddingStore::from_floats(&embeddings);
+
+        let results = store.search(&embeddings[1], 2).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, 1);
+        assert!(results[0].score >= results[1].score);
+    }
+
+    #[test]
+    fn test_store_search_edge_cases() {
+        let store = EmbeddingStore::from_floats(&[vec![1.0, -1.0, 1.0, -1.0]]);
+
+        assert!(store.search(&[1.0, -1.0, 1.0, -1.0], 0).unwrap().is_empty());
+        assert_eq!(store.search(&[1.0, -1.0, 1.0, -1.0], 10).unwrap().len(), 1);
+        assert!(store.search(&[1.0, -1.0], 1).is_err());
+
+        let empty = EmbeddingStore::new(Vec::new(), Vec::new(), 4);
+        assert!(empty.search(&[1.0, -1.0, 1.0, -1.0], 5).unwrap().is_empty());
+    }
+
     #[test]
     fn test_store_size_calculation() {
         let embeddings = vec![
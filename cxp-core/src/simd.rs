@@ -0,0 +1,355 @@
+//! SIMD Distance Kernels
+//!
+//! Hamming distance, int8 dot product and f32 dot product / cosine used by
+//! binary search, rescoring and exact search. Each kernel checks the CPU at
+//! runtime and runs the widest implementation available - AVX2 (with POPCNT
+//! and FMA) on x86_64, NEON on aarch64 - falling back to the portable loops
+//! in [`scalar`]. All implementations agree on integer results; float results
+//! differ only by summation order.
+//!
+//! `cargo test --release --test distance_benchmark -- --nocapture` compares
+//! the kernels with the scalar loops.
+
+/// Instruction set the kernels run with on this CPU (`avx2`, `popcnt`, `neon` or `scalar`)
+pub fn active_kernels() -> &'static str {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if has_avx2() {
+            return "avx2";
+        }
+        if is_x86_feature_detected!("popcnt") {
+            return "popcnt";
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        return "neon";
+    }
+    "scalar"
+}
+
+/// Number of differing bits between two packed bit vectors
+pub fn hamming(a: &[u8], b: &[u8]) -> u32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("popcnt") {
+            // The lookup-table kernel only pays off for longer vectors
+            if a.len().min(b.len()) >= 64 && has_avx2() {
+                // SAFETY: AVX2 and POPCNT support were checked at runtime
+                return unsafe { x86::hamming_avx2(a, b) };
+            }
+            // SAFETY: POPCNT support was checked at runtime
+            return unsafe { x86::hamming_popcnt(a, b) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: NEON support was checked at runtime
+        return unsafe { neon::hamming(a, b) };
+    }
+    scalar::hamming(a, b)
+}
+
+/// Dot product of two int8 vectors
+pub fn dot_i8(a: &[i8], b: &[i8]) -> i32 {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2() {
+        // SAFETY: AVX2 support was checked at runtime
+        return unsafe { x86::dot_i8_avx2(a, b) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: NEON support was checked at runtime
+        return unsafe { neon::dot_i8(a, b) };
+    }
+    scalar::dot_i8(a, b)
+}
+
+/// Dot product of two f32 vectors (cosine similarity for normalized vectors)
+pub fn dot_f32(a: &[f32], b: &[f32]) -> f32 {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2_fma() {
+        // SAFETY: AVX2 and FMA support were checked at runtime
+        return unsafe { x86::dot_f32_avx2(a, b) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: NEON support was checked at runtime
+        return unsafe { neon::dot_f32(a, b) };
+    }
+    scalar::dot_f32(a, b)
+}
+
+/// Cosine similarity of two f32 vectors (0.0 if either is all zeros)
+pub fn cosine_f32(a: &[f32], b: &[f32]) -> f32 {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2_fma() {
+        // SAFETY: AVX2 and FMA support were checked at runtime
+        let (dot, aa, bb) = unsafe { x86::dot_norms_f32_avx2(a, b) };
+        return cosine_from(dot, aa, bb);
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: NEON support was checked at runtime
+        let (dot, aa, bb) = unsafe { neon::dot_norms_f32(a, b) };
+        return cosine_from(dot, aa, bb);
+    }
+    scalar::cosine_f32(a, b)
+}
+
+fn cosine_from(dot: f32, aa: f32, bb: f32) -> f32 {
+    let norm = aa.sqrt() * bb.sqrt();
+    if norm == 0.0 {
+        0.0
+    } else {
+        dot / norm
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn has_avx2() -> bool {
+    is_x86_feature_detected!("avx2")
+}
+
+#[cfg(target_arch = "x86_64")]
+fn has_avx2_fma() -> bool {
+    is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma")
+}
+
+/// Portable kernels (the fallback, and the baseline for benchmarks)
+pub mod scalar {
+    /// Number of differing bits between two packed bit vectors
+    pub fn hamming(a: &[u8], b: &[u8]) -> u32 {
+        a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum()
+    }
+
+    /// Dot product of two int8 vectors
+    pub fn dot_i8(a: &[i8], b: &[i8]) -> i32 {
+        a.iter().zip(b).map(|(&a, &b)| a as i32 * b as i32).sum()
+    }
+
+    /// Dot product of two f32 vectors
+    pub fn dot_f32(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    /// Cosine similarity of two f32 vectors (0.0 if either is all zeros)
+    pub fn cosine_f32(a: &[f32], b: &[f32]) -> f32 {
+        let aa = dot_f32(a, a);
+        let bb = dot_f32(b, b);
+        super::cosine_from(dot_f32(a, b), aa, bb)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::scalar;
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2,popcnt")]
+    pub unsafe fn hamming_avx2(a: &[u8], b: &[u8]) -> u32 {
+        let n = a.len().min(b.len());
+        // Bits set per nibble value
+        let lut = _mm256_setr_epi8(
+            0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4, 0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4,
+        );
+        let low_nibbles = _mm256_set1_epi8(0x0f);
+        let mut acc = _mm256_setzero_si256();
+        let mut i = 0;
+        while i + 32 <= n {
+            let x = _mm256_xor_si256(
+                _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i),
+                _mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i),
+            );
+            let lo = _mm256_shuffle_epi8(lut, _mm256_and_si256(x, low_nibbles));
+            let hi = _mm256_shuffle_epi8(lut, _mm256_and_si256(_mm256_srli_epi16(x, 4), low_nibbles));
+            acc = _mm256_add_epi64(acc, _mm256_sad_epu8(_mm256_add_epi8(lo, hi), _mm256_setzero_si256()));
+            i += 32;
+        }
+        let mut lanes = [0u64; 4];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, acc);
+        lanes.iter().sum::<u64>() as u32 + hamming_popcnt(&a[i..n], &b[i..n])
+    }
+
+    #[target_feature(enable = "popcnt")]
+    pub unsafe fn hamming_popcnt(a: &[u8], b: &[u8]) -> u32 {
+        let n = a.len().min(b.len());
+        let words = n / 8;
+        let mut total = 0;
+        for w in 0..words {
+            let x = (a.as_ptr().add(w * 8) as *const u64).read_unaligned();
+            let y = (b.as_ptr().add(w * 8) as *const u64).read_unaligned();
+            total += (x ^ y).count_ones();
+        }
+        total + scalar::hamming(&a[words * 8..n], &b[words * 8..n])
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn dot_i8_avx2(a: &[i8], b: &[i8]) -> i32 {
+        let n = a.len().min(b.len());
+        let mut acc = _mm256_setzero_si256();
+        let mut i = 0;
+        while i + 16 <= n {
+            // Widen to i16 and multiply-add pairs into i32 lanes
+            let x = _mm256_cvtepi8_epi16(_mm_loadu_si128(a.as_ptr().add(i) as *const __m128i));
+            let y = _mm256_cvtepi8_epi16(_mm_loadu_si128(b.as_ptr().add(i) as *const __m128i));
+            acc = _mm256_add_epi32(acc, _mm256_madd_epi16(x, y));
+            i += 16;
+        }
+        let mut lanes = [0i32; 8];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, acc);
+        lanes.iter().sum::<i32>() + scalar::dot_i8(&a[i..n], &b[i..n])
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn dot_f32_avx2(a: &[f32], b: &[f32]) -> f32 {
+        let n = a.len().min(b.len());
+        let mut acc0 = _mm256_setzero_ps();
+        let mut acc1 = _mm256_setzero_ps();
+        let mut i = 0;
+        while i + 16 <= n {
+            acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(a.as_ptr().add(i)), _mm256_loadu_ps(b.as_ptr().add(i)), acc0);
+            acc1 = _mm256_fmadd_ps(
+                _mm256_loadu_ps(a.as_ptr().add(i + 8)),
+                _mm256_loadu_ps(b.as_ptr().add(i + 8)),
+                acc1,
+            );
+            i += 16;
+        }
+        while i + 8 <= n {
+            acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(a.as_ptr().add(i)), _mm256_loadu_ps(b.as_ptr().add(i)), acc0);
+            i += 8;
+        }
+        sum_ps(_mm256_add_ps(acc0, acc1)) + scalar::dot_f32(&a[i..n], &b[i..n])
+    }
+
+    /// Dot product and both squared norms in one pass
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn dot_norms_f32_avx2(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        let n = a.len().min(b.len());
+        let mut dot = _mm256_setzero_ps();
+        let mut aa = _mm256_setzero_ps();
+        let mut bb = _mm256_setzero_ps();
+        let mut i = 0;
+        while i + 8 <= n {
+            let x = _mm256_loadu_ps(a.as_ptr().add(i));
+            let y = _mm256_loadu_ps(b.as_ptr().add(i));
+            dot = _mm256_fmadd_ps(x, y, dot);
+            aa = _mm256_fmadd_ps(x, x, aa);
+            bb = _mm256_fmadd_ps(y, y, bb);
+            i += 8;
+        }
+        let (a, b) = (&a[i..n], &b[i..n]);
+        (
+            sum_ps(dot) + scalar::dot_f32(a, b),
+            sum_ps(aa) + scalar::dot_f32(a, a),
+            sum_ps(bb) + scalar::dot_f32(b, b),
+        )
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn sum_ps(v: __m256) -> f32 {
+        let mut lanes = [0f32; 8];
+        _mm256_storeu_ps(lanes.as_mut_ptr(), v);
+        lanes.iter().sum()
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::scalar;
+    use std::arch::aarch64::*;
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn hamming(a: &[u8], b: &[u8]) -> u32 {
+        let n = a.len().min(b.len());
+        let mut total = 0u32;
+        let mut i = 0;
+        while i + 16 <= n {
+            let x = veorq_u8(vld1q_u8(a.as_ptr().add(i)), vld1q_u8(b.as_ptr().add(i)));
+            total += vaddvq_u8(vcntq_u8(x)) as u32;
+            i += 16;
+        }
+        total + scalar::hamming(&a[i..n], &b[i..n])
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn dot_i8(a: &[i8], b: &[i8]) -> i32 {
+        let n = a.len().min(b.len());
+        let mut acc = vdupq_n_s32(0);
+        let mut i = 0;
+        while i + 8 <= n {
+            let products = vmull_s8(vld1_s8(a.as_ptr().add(i)), vld1_s8(b.as_ptr().add(i)));
+            acc = vpadalq_s16(acc, products);
+            i += 8;
+        }
+        vaddvq_s32(acc) + scalar::dot_i8(&a[i..n], &b[i..n])
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn dot_f32(a: &[f32], b: &[f32]) -> f32 {
+        let n = a.len().min(b.len());
+        let mut acc = vdupq_n_f32(0.0);
+        let mut i = 0;
+        while i + 4 <= n {
+            acc = vfmaq_f32(acc, vld1q_f32(a.as_ptr().add(i)), vld1q_f32(b.as_ptr().add(i)));
+            i += 4;
+        }
+        vaddvq_f32(acc) + scalar::dot_f32(&a[i..n], &b[i..n])
+    }
+
+    /// Dot product and both squared norms in one pass
+    #[target_feature(enable = "neon")]
+    pub unsafe fn dot_norms_f32(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        let n = a.len().min(b.len());
+        let mut dot = vdupq_n_f32(0.0);
+        let mut aa = vdupq_n_f32(0.0);
+        let mut bb = vdupq_n_f32(0.0);
+        let mut i = 0;
+        while i + 4 <= n {
+            let x = vld1q_f32(a.as_ptr().add(i));
+            let y = vld1q_f32(b.as_ptr().add(i));
+            dot = vfmaq_f32(dot, x, y);
+            aa = vfmaq_f32(aa, x, x);
+            bb = vfmaq_f32(bb, y, y);
+            i += 4;
+        }
+        let (a, b) = (&a[i..n], &b[i..n]);
+        (
+            vaddvq_f32(dot) + scalar::dot_f32(a, b),
+            vaddvq_f32(aa) + scalar::dot_f32(a, a),
+            vaddvq_f32(bb) + scalar::dot_f32(b, b),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kernels_match_scalar() {
+        // Lengths around every block size, including tails
+        for len in [0, 1, 7, 8, 15, 16, 31, 33, 48, 63, 64, 100, 384, 769] {
+            let bytes_a: Vec<u8> = (0..len).map(|i| (i * 37 + 11) as u8).collect();
+            let bytes_b: Vec<u8> = (0..len).map(|i| (i * 91 + 3) as u8).collect();
+            assert_eq!(hamming(&bytes_a, &bytes_b), scalar::hamming(&bytes_a, &bytes_b), "hamming, len {}", len);
+
+            let int8_a: Vec<i8> = bytes_a.iter().map(|&b| b as i8).collect();
+            let int8_b: Vec<i8> = bytes_b.iter().map(|&b| b as i8).collect();
+            assert_eq!(dot_i8(&int8_a, &int8_b), scalar::dot_i8(&int8_a, &int8_b), "dot_i8, len {}", len);
+
+            let float_a: Vec<f32> = int8_a.iter().map(|&v| v as f32 / 128.0).collect();
+            let float_b: Vec<f32> = int8_b.iter().map(|&v| v as f32 / 128.0).collect();
+            let tolerance = 1e-4 * (len as f32 + 1.0);
+            assert!((dot_f32(&float_a, &float_b) - scalar::dot_f32(&float_a, &float_b)).abs() < tolerance);
+            assert!((cosine_f32(&float_a, &float_b) - scalar::cosine_f32(&float_a, &float_b)).abs() < 1e-4);
+        }
+
+        assert_eq!(hamming(&[0xff; 80], &[0x00; 80]), 640);
+        assert_eq!(dot_i8(&[-128; 40], &[-128; 40]), 40 * 16384);
+        assert_eq!(cosine_f32(&[0.0; 16], &[1.0; 16]), 0.0);
+        assert!((cosine_f32(&[2.0; 20], &[3.0; 20]) - 1.0).abs() < 1e-6);
+        assert!(!active_kernels().is_empty());
+    }
+}
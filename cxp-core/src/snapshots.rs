@@ -0,0 +1,202 @@
+//! Snapshots
+//!
+//! Several versions of the same tree in one archive. `CxpBuilder::snapshot()`
+//! records the builder's current file map as a labeled generation; files
+//! updated afterwards get new chunks while every generation keeps pointing at
+//! the chunks it was taken with. Chunks are stored once, so a generation costs
+//! little more than its file map and the chunks that changed since the last
+//! one. `CxpReader::open_snapshot()` reads any generation like an archive of
+//! its own. A fresh builder picks up the generations of an earlier build with
+//! `CxpBuilder::with_snapshots_from()`, so history survives rebuilds.
+//!
+//! The regular file map is the head of the history; search indices, symbols
+//! and extensions describe the head only.
+//!
+//! Structure:
+//! ```text
+//! snapshots/
+//! ├── index.msgpack        # SnapshotIndex: generations in the order they were taken
+//! ├── 00000.msgpack        # FileMap of generation 0
+//! └── 00001.msgpack
+//! ```
+
+use std::collections::BTreeSet;
+use std::io::Read;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::format::FileMap;
+use crate::{CxpError, Result};
+
+/// Entry listing the snapshots of an archive
+pub const SNAPSHOT_INDEX_PATH: &str = "snapshots/index.msgpack";
+
+/// A labeled generation of the file map
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    /// Label given to `CxpBuilder::snapshot()`
+    pub label: String,
+    /// Generation number, unique within the archive (names the file map entry)
+    pub generation: u32,
+    /// When the snapshot was taken
+    pub created_at: DateTime<Utc>,
+    /// Number of files
+    pub files: usize,
+    /// Total size of the files
+    pub size: u64,
+}
+
+impl SnapshotInfo {
+    /// Entry holding the generation's file map
+    pub fn path(&self) -> String {
+        format!("snapshots/{:05}.msgpack", self.generation)
+    }
+}
+
+/// Generations of an archive, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotIndex {
+    /// Snapshots in the order they were taken
+    pub snapshots: Vec<SnapshotInfo>,
+}
+
+impl SnapshotIndex {
+    /// Empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the archive has no snapshots
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Snapshot with the given label
+    pub fn get(&self, label: &str) -> Option<&SnapshotInfo> {
+        self.snapshots.iter().find(|s| s.label == label)
+    }
+
+    /// Labels, oldest first
+    pub fn labels(&self) -> Vec<&str> {
+        self.snapshots.iter().map(|s| s.label.as_str()).collect()
+    }
+
+    /// Record a new generation of `file_map` under `label`
+    pub fn push(&mut self, label: &str, file_map: &FileMap) -> Result<&SnapshotInfo> {
+        if label.trim().is_empty() {
+            return Err(CxpError::InvalidFormat("Snapshot label must not be empty".to_string()));
+        }
+        if self.get(label).is_some() {
+            return Err(CxpError::InvalidFormat(format!("Snapshot '{}' already exists", label)));
+        }
+        let generation = self.snapshots.last().map_or(0, |s| s.generation + 1);
+        self.snapshots.push(SnapshotInfo {
+            label: label.to_string(),
+            generation,
+            created_at: Utc::now(),
+            files: file_map.files.len(),
+            size: file_map.files.values().map(|e| e.size).sum(),
+        });
+        Ok(self.snapshots.last().expect("just pushed"))
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Read the index of an archive (empty if it has no snapshots)
+    pub fn read_from_archive<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>) -> Result<Self> {
+        match read_entry(archive, SNAPSHOT_INDEX_PATH)? {
+            Some(data) => Self::from_msgpack(&data),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Read the file map of one generation
+    pub fn read_file_map<R: Read + std::io::Seek>(
+        &self,
+        archive: &mut zip::ZipArchive<R>,
+        label: &str,
+    ) -> Result<FileMap> {
+        let info = self
+            .get(label)
+            .ok_or_else(|| CxpError::FileNotFound(format!("Snapshot '{}' not found", label)))?;
+        let data = read_entry(archive, &info.path())?
+            .ok_or_else(|| CxpError::InvalidFormat(format!("Snapshot '{}' has no file map", label)))?;
+        rmp_serde::from_slice(&data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Hashes of the chunks any generation references
+    pub fn referenced_chunks<R: Read + std::io::Seek>(
+        &self,
+        archive: &mut zip::ZipArchive<R>,
+    ) -> Result<BTreeSet<String>> {
+        let mut hashes = BTreeSet::new();
+        for info in &self.snapshots {
+            let file_map = self.read_file_map(archive, &info.label)?;
+            hashes.extend(file_map.files.into_values().flat_map(|e| e.chunks).map(|c| c.hash));
+        }
+        Ok(hashes)
+    }
+}
+
+/// Serialize a generation's file map
+pub(crate) fn file_map_to_msgpack(file_map: &FileMap) -> Result<Vec<u8>> {
+    rmp_serde::to_vec(file_map).map_err(|e| CxpError::Serialization(e.to_string()))
+}
+
+fn read_entry<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>, name: &str) -> Result<Option<Vec<u8>>> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut data = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut data)?;
+    Ok(Some(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::FileEntry;
+
+    #[test]
+    fn test_generations_are_numbered_and_labels_unique() {
+        let mut file_map = FileMap::default();
+        file_map.files.insert(
+            "a.txt".to_string(),
+            FileEntry {
+                path: "a.txt".to_string(),
+                extension: "txt".to_string(),
+                size: 5,
+                chunks: Vec::new(),
+                is_image: false,
+                modified: None,
+                provenance: None,
+                keywords: Vec::new(),
+            },
+        );
+
+        let mut index = SnapshotIndex::new();
+        assert_eq!(index.push("v1", &FileMap::default()).unwrap().generation, 0);
+        let v2 = index.push("v2", &file_map).unwrap();
+        assert_eq!((v2.generation, v2.files, v2.size), (1, 1, 5));
+        assert_eq!(v2.path(), "snapshots/00001.msgpack");
+        assert!(index.push("v1", &file_map).is_err());
+        assert!(index.push(" ", &file_map).is_err());
+
+        // Numbers are never reused, even after older generations are dropped
+        index.snapshots.remove(0);
+        assert_eq!(index.push("v3", &file_map).unwrap().generation, 2);
+        assert_eq!(index.labels(), vec!["v2", "v3"]);
+        assert_eq!(SnapshotIndex::from_msgpack(&index.to_msgpack().unwrap()).unwrap(), index);
+    }
+}
@@ -0,0 +1,318 @@
+//! Splitting CXP Archives
+//!
+//! `CxpSplitter` is the inverse of [`CxpMerger`](crate::CxpMerger): it breaks
+//! one archive into child CXPs - one per top-level directory or one per tier -
+//! and writes a parent pack that embeds them under `children/` and lists them
+//! as [`CxpRef`]s in its manifest.
+//!
+//! Children keep the original file paths, so merging them back reproduces the
+//! input. Chunks are copied as stored (no recompression); embeddings are not
+//! carried over and have to be regenerated per child.
+//!
+//! ```text
+//! big.cxp  --by-dir-->  parent.cxp
+//!                       ├── manifest.msgpack      # children: src, docs, ...
+//!                       ├── file_map/ chunks/     # files at the archive root
+//!                       └── children/
+//!                           ├── src.cxp
+//!                           └── docs.cxp
+//! ```
+
+use crate::annotations::{Annotations, ANNOTATIONS_PATH};
+use crate::symbols::{SymbolIndex, SYMBOLS_PATH};
+use crate::compress::DICTIONARY_PATH;
+use crate::format::{read_file_map, ArchiveWriter, FileMap};
+use crate::manifest::Manifest;
+use crate::map_shards::DEFAULT_SHARD_SIZE;
+use crate::packs::PackIndex;
+use crate::recursive::{CxpRef, CxpRefMeta, FileTier};
+use crate::{CxpError, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// How files are grouped into children
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SplitMode {
+    /// One child per top-level directory; root-level files stay in the parent
+    #[default]
+    ByDir,
+    /// One child per tier (hot, warm, cold): the file's `tier:` tag from a
+    /// scan plan, or its modification time
+    ByTier,
+}
+
+/// Summary of a split
+#[derive(Debug, Clone, Default)]
+pub struct SplitStats {
+    /// References to the embedded children, in archive order
+    pub children: Vec<CxpRef>,
+    /// Files kept in the parent itself
+    pub root_files: usize,
+    /// Total files across parent and children
+    pub files: usize,
+}
+
+/// Splits a CXP archive into a parent pack with embedded children
+#[derive(Debug, Clone)]
+pub struct CxpSplitter {
+    mode: SplitMode,
+    shard_size: usize,
+}
+
+impl Default for CxpSplitter {
+    fn default() -> Self {
+        Self::new(SplitMode::default())
+    }
+}
+
+impl CxpSplitter {
+    /// Create a splitter for the given mode
+    pub fn new(mode: SplitMode) -> Self {
+        Self {
+            mode,
+            shard_size: DEFAULT_SHARD_SIZE,
+        }
+    }
+
+    /// Set the number of files per file map shard
+    pub fn with_shard_size(mut self, shard_size: usize) -> Self {
+        self.shard_size = shard_size.max(1);
+        self
+    }
+
+    /// Split `input` and write the parent pack to `output`
+    pub fn split<P: AsRef<Path>, Q: AsRef<Path>>(&self, input: P, output: Q) -> Result<SplitStats> {
+        let output = output.as_ref();
+        let mut archive = ZipArchive::new(File::open(input.as_ref())?)?;
+        let source_manifest = Manifest::from_msgpack(&read_entry(&mut archive, "manifest.msgpack")?)?;
+        source_manifest.check_reader_version()?;
+        let file_map = read_file_map(&mut archive)?;
+        let packs = PackIndex::read_from_archive(&mut archive)?;
+
+        if source_manifest.embedding_model.is_some() {
+            tracing::warn!("Embeddings are not carried over when splitting; regenerate them per child");
+        }
+
+        // Group files by child id (None = stays in the parent)
+        let annotations = Annotations::read_from_archive(&mut archive)?;
+        let mut root = FileMap::default();
+        let mut groups: BTreeMap<String, FileMap> = BTreeMap::new();
+        for (path, entry) in &file_map.files {
+            let group = match self.mode {
+                SplitMode::ByDir => path.split_once('/').map(|(dir, _)| dir.to_string()),
+                SplitMode::ByTier => {
+                    let tier = tagged_tier(&annotations, path).unwrap_or_else(|| FileTier::from_modified(entry.modified));
+                    Some(tier_id(tier).to_string())
+                }
+            };
+            match group {
+                Some(id) => groups.entry(id).or_default().files.insert(path.clone(), entry.clone()),
+                None => root.files.insert(path.clone(), entry.clone()),
+            };
+        }
+
+        if groups.is_empty() {
+            return Err(CxpError::InvalidFormat(
+                "Nothing to split: the archive has no top-level directories".to_string(),
+            ));
+        }
+
+        let parent_name = output
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "parent".to_string());
+
+        let mut stats = SplitStats {
+            root_files: root.files.len(),
+            files: file_map.files.len(),
+            ..Default::default()
+        };
+
+        // Build every child in memory
+        let mut children = Vec::with_capacity(groups.len());
+        for (id, group) in &groups {
+            let mut manifest = manifest_for(group, &mut archive, &packs);
+            manifest.compression = source_manifest.compression.clone();
+            manifest.chunker = source_manifest.chunker.clone();
+            manifest.min_reader_version = source_manifest.min_reader_version.clone();
+            manifest.redactions = source_manifest.redactions.as_ref().map(|r| r.for_files(group));
+            manifest.parent_path = Some(vec![parent_name.clone()]);
+            manifest.tier = match self.mode {
+                SplitMode::ByDir => FileTier::from_modified(group.files.values().filter_map(|e| e.modified).max()),
+                SplitMode::ByTier => tier_from_id(id),
+            };
+
+            let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+            self.write_contents(&mut writer, &mut archive, &packs, &manifest, group)?;
+            let data = writer.finish()?.into_inner();
+
+            let path_in_zip = format!("children/{}.cxp", id);
+            let mut child_ref = CxpRef::embedded(id.clone(), id.clone(), path_in_zip.clone());
+            child_ref.meta = CxpRefMeta::from_manifest(&manifest);
+            child_ref.meta.size_bytes = data.len() as u64;
+            child_ref.tier = manifest.tier;
+
+            stats.children.push(child_ref);
+            children.push((path_in_zip, data));
+        }
+
+        // Parent: root files, children and the source's extension data
+        let mut manifest = manifest_for(&root, &mut archive, &packs);
+        manifest.created_at = source_manifest.created_at;
+        manifest.compression = source_manifest.compression.clone();
+        manifest.chunker = source_manifest.chunker.clone();
+        manifest.min_reader_version = source_manifest.min_reader_version.clone();
+        manifest.metadata = source_manifest.metadata.clone();
+        manifest.custom_metadata = source_manifest.custom_metadata.clone();
+        manifest.redactions = source_manifest.redactions.as_ref().map(|r| r.for_files(&root));
+        manifest.extensions = source_manifest
+            .extensions
+            .iter()
+            .filter(|e| *e != "embeddings")
+            .cloned()
+            .collect();
+        for child_ref in &stats.children {
+            manifest.add_child(child_ref.clone());
+        }
+
+        let mut writer = ArchiveWriter::create(output)?;
+        self.write_contents(&mut writer, &mut archive, &packs, &manifest, &root)?;
+        for (path_in_zip, data) in &children {
+            writer.write(path_in_zip, data)?;
+        }
+
+        let extension_entries: Vec<String> = archive
+            .file_names()
+            .filter(|name| name.starts_with("extensions/"))
+            .map(str::to_string)
+            .collect();
+        for name in extension_entries {
+            let data = read_entry(&mut archive, &name)?;
+            writer.write(&name, &data)?;
+        }
+        writer.finish()?;
+
+        tracing::info!(
+            "Split {} files into {} children ({} kept in parent)",
+            stats.files,
+            stats.children.len(),
+            stats.root_files
+        );
+
+        Ok(stats)
+    }
+
+    /// Write manifest, file map, chunks and filters for one archive
+    fn write_contents<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut ArchiveWriter<W>,
+        source: &mut ZipArchive<File>,
+        packs: &PackIndex,
+        manifest: &Manifest,
+        file_map: &FileMap,
+    ) -> Result<()> {
+        writer.write_manifest(manifest)?;
+        writer.write_file_map(file_map, self.shard_size)?;
+        // Chunks are copied as stored, so they keep the source's dictionary
+        if source.index_for_name(DICTIONARY_PATH).is_some() {
+            writer.write(DICTIONARY_PATH, &read_entry(source, DICTIONARY_PATH)?)?;
+        }
+        for hash in chunk_hashes(file_map).keys() {
+            let data = packs.read_stored(source, hash)?;
+            writer.write_chunk(hash, &data)?;
+        }
+        let annotations = Annotations::read_from_archive(source)?.for_files(file_map);
+        if !annotations.is_empty() {
+            writer.write(ANNOTATIONS_PATH, &annotations.to_msgpack()?)?;
+        }
+        if let Some(symbols) = SymbolIndex::read_from_archive(source)? {
+            writer.write(SYMBOLS_PATH, &symbols.for_files(file_map).to_msgpack()?)?;
+        }
+        writer.write_filters(file_map)
+    }
+}
+
+/// Manifest with file types and stats for a subset of files
+///
+/// Chunks are copied from `source` as-is, so their stored sizes carry over.
+fn manifest_for(file_map: &FileMap, source: &mut ZipArchive<File>, packs: &PackIndex) -> Manifest {
+    let mut manifest = Manifest::new();
+    let hashes = chunk_hashes(file_map);
+    let total_bytes: u64 = file_map.files.values().map(|e| e.size).sum();
+    let unique_bytes: u64 = hashes.values().map(|&len| len as u64).sum();
+
+    for entry in file_map.files.values() {
+        manifest.add_file_type(&entry.extension, &entry.path, entry.size);
+    }
+    manifest.stats.total_files = file_map.files.len();
+    manifest.stats.unique_chunks = hashes.len();
+    manifest.stats.original_size_bytes = total_bytes;
+    manifest.stats.dedup_savings_percent = if total_bytes > 0 {
+        total_bytes.saturating_sub(unique_bytes) as f64 / total_bytes as f64 * 100.0
+    } else {
+        0.0
+    };
+
+    let compressed_sizes: HashMap<&str, u64> = hashes
+        .keys()
+        .filter_map(|hash| {
+            let size = match packs.get(hash) {
+                Some(chunk) => chunk.length,
+                None => source.by_name(&PackIndex::legacy_path(hash)).ok()?.compressed_size(),
+            };
+            Some((*hash, size))
+        })
+        .collect();
+    manifest.record_chunk_compression(file_map, |hash| compressed_sizes.get(hash).copied());
+    manifest
+}
+
+/// Unique chunk hashes of a file map with their uncompressed length
+fn chunk_hashes(file_map: &FileMap) -> BTreeMap<&str, usize> {
+    let mut hashes = BTreeMap::new();
+    for entry in file_map.files.values() {
+        for chunk in &entry.chunks {
+            hashes.entry(chunk.hash.as_str()).or_insert(chunk.length);
+        }
+    }
+    hashes
+}
+
+/// Tier recorded in a file's `tier:<hot|warm|cold>` tag
+fn tagged_tier(annotations: &Annotations, path: &str) -> Option<FileTier> {
+    annotations
+        .get(path)?
+        .tags
+        .iter()
+        .find_map(|tag| tag.strip_prefix("tier:"))
+        .map(tier_from_id)
+}
+
+/// Child id used for a tier
+fn tier_id(tier: FileTier) -> &'static str {
+    match tier {
+        FileTier::Hot => "hot",
+        FileTier::Warm => "warm",
+        FileTier::Cold => "cold",
+    }
+}
+
+fn tier_from_id(id: &str) -> FileTier {
+    match id {
+        "hot" => FileTier::Hot,
+        "cold" => FileTier::Cold,
+        _ => FileTier::Warm,
+    }
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| CxpError::InvalidFormat(format!("No {} found: {}", name, e)))?;
+    let mut data = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
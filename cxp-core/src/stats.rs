@@ -0,0 +1,606 @@
+//! Archive Statistics
+//!
+//! Structured breakdown of where the bytes of a CXP archive go: chunk sizes,
+//! deduplication and compression per file type, per-directory totals, the
+//! largest files and the storage used by embeddings.
+//!
+//! Shared chunks are attributed to the first file (in path order) that
+//! references them, so every unique chunk is counted exactly once.
+//!
+//! [`duplicate_report`] answers the opposite question: which files share
+//! chunks with which (vendored copies, duplicated configs), grouped into
+//! clusters with per-file shared-content percentages, plus groups of
+//! identical and near-identical files to prune (see [`crate::dupes`]).
+
+use crate::dupes::DupeGroup;
+use crate::format::FileMap;
+use crate::backend::{open_zip, ArchiveBackend};
+use crate::packs::PackIndex;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Number of files listed in [`ArchiveStatistics::largest_files`]
+pub const TOP_FILES: usize = 20;
+
+/// Upper bounds (inclusive) of the chunk size histogram buckets
+pub const CHUNK_SIZE_BUCKETS: [usize; 8] = [1024, 2048, 3072, 4096, 5120, 6144, 7168, 8192];
+
+/// Structured statistics report for a CXP archive
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArchiveStatistics {
+    /// Number of files
+    pub total_files: usize,
+    /// Total size of all files before chunking
+    pub original_bytes: u64,
+    /// Total chunk references across all files
+    pub chunk_refs: usize,
+    /// Number of unique chunks stored
+    pub unique_chunks: usize,
+    /// Uncompressed size of the unique chunks
+    pub unique_chunk_bytes: u64,
+    /// Compressed size of the unique chunks
+    pub compressed_chunk_bytes: u64,
+    /// Size of the archive on disk
+    pub archive_bytes: u64,
+    /// Unique chunk count per size bucket
+    pub chunk_size_histogram: Vec<HistogramBucket>,
+    /// Breakdown per file extension (largest first)
+    pub extensions: Vec<ExtensionStats>,
+    /// Breakdown per top-level directory (largest first)
+    pub directories: Vec<DirectoryStats>,
+    /// Largest files (at most [`TOP_FILES`])
+    pub largest_files: Vec<FileStats>,
+    /// Storage used by embeddings and search indices
+    pub embeddings: EmbeddingStats,
+}
+
+impl ArchiveStatistics {
+    /// Share of chunk references served by an already stored chunk
+    pub fn dedup_ratio(&self) -> f64 {
+        ratio(self.chunk_refs.saturating_sub(self.unique_chunks) as f64, self.chunk_refs as f64)
+    }
+
+    /// Compressed size relative to the uncompressed unique chunks
+    pub fn compression_ratio(&self) -> f64 {
+        ratio(self.compressed_chunk_bytes as f64, self.unique_chunk_bytes as f64)
+    }
+}
+
+/// One bucket of the chunk size histogram
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    /// Inclusive upper bound in bytes (`None` for the overflow bucket)
+    pub max_bytes: Option<usize>,
+    /// Number of unique chunks in this bucket
+    pub count: usize,
+}
+
+/// Statistics for one file extension
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtensionStats {
+    /// File extension (empty for files without one)
+    pub extension: String,
+    /// Number of files
+    pub files: usize,
+    /// Total original size
+    pub original_bytes: u64,
+    /// Chunk references of these files
+    pub chunk_refs: usize,
+    /// References that hit an already stored chunk
+    pub dedup_hits: usize,
+    /// Uncompressed size of the chunks attributed to this extension
+    pub unique_chunk_bytes: u64,
+    /// Compressed size of the chunks attributed to this extension
+    pub compressed_bytes: u64,
+}
+
+impl ExtensionStats {
+    /// Share of chunk references that were deduplicated
+    pub fn dedup_ratio(&self) -> f64 {
+        ratio(self.dedup_hits as f64, self.chunk_refs as f64)
+    }
+
+    /// Compressed size relative to the uncompressed chunks
+    pub fn compression_ratio(&self) -> f64 {
+        ratio(self.compressed_bytes as f64, self.unique_chunk_bytes as f64)
+    }
+}
+
+/// Statistics for one top-level directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirectoryStats {
+    /// Top-level directory (`.` for files at the archive root)
+    pub directory: String,
+    /// Number of files
+    pub files: usize,
+    /// Total original size
+    pub original_bytes: u64,
+    /// Compressed size of the chunks attributed to this directory
+    pub compressed_bytes: u64,
+}
+
+/// Size information for a single file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileStats {
+    /// File path
+    pub path: String,
+    /// Original size
+    pub size: u64,
+    /// Number of chunks
+    pub chunks: usize,
+}
+
+/// Storage used by embeddings and search indices
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddingStats {
+    /// Binary embeddings (`embeddings/binary.bin`)
+    pub binary_bytes: u64,
+    /// Int8 embeddings (`embeddings/int8.bin`)
+    pub int8_bytes: u64,
+    /// Float embeddings (`embeddings/f16.bin` or `embeddings/f32.bin`)
+    #[serde(default)]
+    pub float_bytes: u64,
+    /// HNSW / unified search indices
+    pub index_bytes: u64,
+    /// Other embedding files (ID mappings, index metadata)
+    pub other_bytes: u64,
+}
+
+impl EmbeddingStats {
+    /// Total embedding storage
+    pub fn total_bytes(&self) -> u64 {
+        self.binary_bytes + self.int8_bytes + self.float_bytes + self.index_bytes + self.other_bytes
+    }
+}
+
+/// Options for [`duplicate_report`]
+#[derive(Debug, Clone)]
+pub struct DuplicateOptions {
+    /// Minimum share of the smaller file two files must have in common to be clustered
+    pub min_shared_fraction: f64,
+    /// Chunks referenced by more files than this are treated as boilerplate
+    /// and do not link files into clusters
+    pub max_chunk_fanout: usize,
+    /// Minimum Jaccard similarity of two files' chunk sets to be near-duplicates
+    pub similarity_threshold: f64,
+    /// Files smaller than this (bytes) are left out of near-duplicate groups
+    pub min_size: u64,
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        Self {
+            min_shared_fraction: 0.5,
+            max_chunk_fanout: 64,
+            similarity_threshold: 0.8,
+            min_size: 1,
+        }
+    }
+}
+
+impl DuplicateOptions {
+    /// Set the minimum shared fraction (0.0 - 1.0)
+    pub fn with_min_shared_fraction(mut self, fraction: f64) -> Self {
+        self.min_shared_fraction = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the boilerplate fan-out limit
+    pub fn with_max_chunk_fanout(mut self, fanout: usize) -> Self {
+        self.max_chunk_fanout = fanout.max(2);
+        self
+    }
+
+    /// Set the near-duplicate similarity threshold (0.0 - 1.0)
+    pub fn with_similarity_threshold(mut self, threshold: f64) -> Self {
+        self.similarity_threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Leave files smaller than `bytes` out of near-duplicate groups
+    pub fn with_min_size(mut self, bytes: u64) -> Self {
+        self.min_size = bytes.max(1);
+        self
+    }
+}
+
+/// Files that share chunks with each other
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    /// Clusters of files sharing content (most shared bytes first)
+    pub clusters: Vec<DuplicateCluster>,
+    /// Number of files in any cluster
+    pub files_with_duplicates: usize,
+    /// Bytes saved by sharing chunks between the clustered files
+    pub duplicate_bytes: u64,
+    /// Identical and near-identical files, most redundant bytes first
+    #[serde(default)]
+    pub groups: Vec<DupeGroup>,
+    /// Bytes of all group members except the kept file of each group
+    #[serde(default)]
+    pub redundant_bytes: u64,
+}
+
+/// A group of files connected by shared chunks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateCluster {
+    /// Files of the cluster (path order)
+    pub files: Vec<DuplicateFile>,
+    /// File pairs that share enough content to be linked (most shared first)
+    pub pairs: Vec<SharedPair>,
+    /// Uncompressed size of the chunks referenced by more than one file of the cluster
+    pub shared_bytes: u64,
+    /// Bytes saved by storing those chunks once
+    pub saved_bytes: u64,
+}
+
+/// A file's share of content duplicated elsewhere in its cluster
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateFile {
+    /// File path
+    pub path: String,
+    /// Original size
+    pub size: u64,
+    /// Bytes of this file found in other files of the cluster
+    pub shared_bytes: u64,
+    /// `shared_bytes` as a percentage of `size`
+    pub shared_percent: f64,
+}
+
+/// Content shared by two files
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SharedPair {
+    /// First file (path order)
+    pub a: String,
+    /// Second file
+    pub b: String,
+    /// Uncompressed size of the chunks both files reference
+    pub shared_bytes: u64,
+    /// `shared_bytes` as a percentage of the smaller file
+    pub shared_percent: f64,
+}
+
+/// Group the files of `file_map` into clusters of files sharing chunks
+pub fn duplicate_report(file_map: &FileMap, options: &DuplicateOptions) -> DuplicateReport {
+    let paths: Vec<&String> = file_map.files.keys().collect();
+
+    // Distinct chunks of each file and the files referencing each chunk
+    let mut chunk_files: HashMap<&str, (u64, Vec<usize>)> = HashMap::new();
+    for (index, entry) in file_map.files.values().enumerate() {
+        let mut own = HashSet::new();
+        for chunk in &entry.chunks {
+            if own.insert(chunk.hash.as_str()) {
+                chunk_files
+                    .entry(chunk.hash.as_str())
+                    .or_insert_with(|| (chunk.length as u64, Vec::new()))
+                    .1
+                    .push(index);
+            }
+        }
+    }
+
+    // Shared bytes per file pair, skipping boilerplate chunks
+    let mut pair_bytes: HashMap<(usize, usize), u64> = HashMap::new();
+    for (length, files) in chunk_files.values() {
+        if files.len() < 2 || files.len() > options.max_chunk_fanout {
+            continue;
+        }
+        for (i, &a) in files.iter().enumerate() {
+            for &b in &files[i + 1..] {
+                *pair_bytes.entry((a, b)).or_insert(0) += length;
+            }
+        }
+    }
+
+    let sizes: Vec<u64> = file_map.files.values().map(|e| e.size).collect();
+    let mut parent: Vec<usize> = (0..paths.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut links = Vec::new();
+    for (&(a, b), &shared) in &pair_bytes {
+        let smaller = sizes[a].min(sizes[b]);
+        let fraction = ratio(shared as f64, smaller as f64).min(1.0);
+        if smaller == 0 || fraction < options.min_shared_fraction {
+            continue;
+        }
+        let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+        if ra != rb {
+            parent[ra.max(rb)] = ra.min(rb);
+        }
+        links.push((a, b, shared, fraction));
+    }
+
+    let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for &(a, b, _, _) in &links {
+        for file in [a, b] {
+            let root = find(&mut parent, file);
+            members.entry(root).or_default().push(file);
+        }
+    }
+
+    let mut report = DuplicateReport::default();
+    // File index -> (cluster, position within the cluster)
+    let mut cluster_of: HashMap<usize, (usize, usize)> = HashMap::new();
+    for mut files in members.into_values() {
+        files.sort_unstable();
+        files.dedup();
+        for (slot, &file) in files.iter().enumerate() {
+            cluster_of.insert(file, (report.clusters.len(), slot));
+        }
+        report.files_with_duplicates += files.len();
+        report.clusters.push(DuplicateCluster {
+            files: files
+                .iter()
+                .map(|&f| DuplicateFile {
+                    path: paths[f].clone(),
+                    size: sizes[f],
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        });
+    }
+
+    // Chunks referenced by several files of the same cluster
+    for (length, files) in chunk_files.values() {
+        let mut per_cluster: HashMap<usize, Vec<usize>> = HashMap::new();
+        for file in files {
+            if let Some(&(cluster, slot)) = cluster_of.get(file) {
+                per_cluster.entry(cluster).or_default().push(slot);
+            }
+        }
+        for (cluster, sharing) in per_cluster {
+            if sharing.len() < 2 {
+                continue;
+            }
+            let cluster = &mut report.clusters[cluster];
+            cluster.shared_bytes += length;
+            cluster.saved_bytes += length * (sharing.len() as u64 - 1);
+            for slot in sharing {
+                cluster.files[slot].shared_bytes += length;
+            }
+        }
+    }
+
+    for (a, b, shared, fraction) in links {
+        report.clusters[cluster_of[&a].0].pairs.push(SharedPair {
+            a: paths[a].clone(),
+            b: paths[b].clone(),
+            shared_bytes: shared,
+            shared_percent: fraction * 100.0,
+        });
+    }
+
+    for cluster in &mut report.clusters {
+        for file in &mut cluster.files {
+            file.shared_bytes = file.shared_bytes.min(file.size);
+            file.shared_percent = ratio(file.shared_bytes as f64, file.size as f64) * 100.0;
+        }
+        cluster
+            .pairs
+            .sort_by(|x, y| y.shared_bytes.cmp(&x.shared_bytes).then_with(|| (&x.a, &x.b).cmp(&(&y.a, &y.b))));
+        report.duplicate_bytes += cluster.saved_bytes;
+    }
+    report.clusters.sort_by(|x, y| {
+        y.saved_bytes
+            .cmp(&x.saved_bytes)
+            .then_with(|| x.files[0].path.cmp(&y.files[0].path))
+    });
+
+    report.groups = crate::dupes::near_duplicates(file_map, options);
+    report.redundant_bytes = report.groups.iter().map(|g| g.redundant_bytes).sum();
+    report
+}
+
+fn ratio(part: f64, total: f64) -> f64 {
+    if total > 0.0 {
+        part / total
+    } else {
+        0.0
+    }
+}
+
+/// Collect statistics for the archive at `archive_path` described by `file_map`
+pub(crate) fn collect(file_map: &FileMap, backend: &dyn ArchiveBackend) -> Result<ArchiveStatistics> {
+    let archive_bytes = backend.size()?;
+    let mut archive = open_zip(backend)?;
+
+    // Stored sizes of chunks (packed or not) and embedding files
+    let chunk_sizes: HashMap<String, u64> =
+        PackIndex::read_from_archive(&mut archive)?.stored_sizes(&mut archive)?.into_iter().collect();
+    let mut embeddings = EmbeddingStats::default();
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        let name = entry.name();
+        let size = entry.compressed_size();
+
+        if let Some(file) = name.strip_prefix("embeddings/") {
+            match file {
+                "binary.bin" => embeddings.binary_bytes += size,
+                "int8.bin" => embeddings.int8_bytes += size,
+                "f16.bin" | "f32.bin" => embeddings.float_bytes += size,
+                "index.hnsw" | "index.ivf" | "unified.index" => embeddings.index_bytes += size,
+                _ => embeddings.other_bytes += size,
+            }
+        }
+    }
+
+    let mut stats = ArchiveStatistics {
+        total_files: file_map.files.len(),
+        archive_bytes,
+        embeddings,
+        chunk_size_histogram: CHUNK_SIZE_BUCKETS
+            .iter()
+            .map(|&max| HistogramBucket { max_bytes: Some(max), count: 0 })
+            .chain(std::iter::once(HistogramBucket { max_bytes: None, count: 0 }))
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut extensions: BTreeMap<String, ExtensionStats> = BTreeMap::new();
+    let mut directories: BTreeMap<String, DirectoryStats> = BTreeMap::new();
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut files = Vec::with_capacity(file_map.files.len());
+
+    // BTreeMap iteration is path-ordered, which makes chunk attribution deterministic
+    for (path, entry) in &file_map.files {
+        let directory = match path.split_once('/') {
+            Some((top, _)) => top.to_string(),
+            None => ".".to_string(),
+        };
+
+        let ext = extensions.entry(entry.extension.clone()).or_insert_with(|| ExtensionStats {
+            extension: entry.extension.clone(),
+            ..Default::default()
+        });
+        let dir = directories.entry(directory.clone()).or_insert_with(|| DirectoryStats {
+            directory,
+            ..Default::default()
+        });
+
+        ext.files += 1;
+        ext.original_bytes += entry.size;
+        ext.chunk_refs += entry.chunks.len();
+        dir.files += 1;
+        dir.original_bytes += entry.size;
+        stats.original_bytes += entry.size;
+        stats.chunk_refs += entry.chunks.len();
+
+        for chunk in &entry.chunks {
+            if !seen.insert(chunk.hash.as_str()) {
+                ext.dedup_hits += 1;
+                continue;
+            }
+
+            let id = crate::chunker::chunk_id(&chunk.hash);
+            let compressed = chunk_sizes.get(id).copied().unwrap_or(0);
+
+            ext.unique_chunk_bytes += chunk.length as u64;
+            ext.compressed_bytes += compressed;
+            dir.compressed_bytes += compressed;
+            stats.unique_chunks += 1;
+            stats.unique_chunk_bytes += chunk.length as u64;
+            stats.compressed_chunk_bytes += compressed;
+
+            let bucket = CHUNK_SIZE_BUCKETS
+                .iter()
+                .position(|&max| chunk.length <= max)
+                .unwrap_or(CHUNK_SIZE_BUCKETS.len());
+            stats.chunk_size_histogram[bucket].count += 1;
+        }
+
+        files.push(FileStats {
+            path: path.clone(),
+            size: entry.size,
+            chunks: entry.chunks.len(),
+        });
+    }
+
+    files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    files.truncate(TOP_FILES);
+    stats.largest_files = files;
+
+    stats.extensions = extensions.into_values().collect();
+    stats.extensions.sort_by_key(|e| std::cmp::Reverse(e.original_bytes));
+    stats.directories = directories.into_values().collect();
+    stats.directories.sort_by_key(|d| std::cmp::Reverse(d.original_bytes));
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CxpBuilder;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_statistics() {
+        let source = TempDir::new().unwrap();
+        let shared = "shared line of text\n".repeat(200);
+        fs::create_dir_all(source.path().join("src")).unwrap();
+        fs::write(source.path().join("src/a.rs"), &shared).unwrap();
+        fs::write(source.path().join("src/b.rs"), &shared).unwrap();
+        fs::write(source.path().join("README.md"), "# Readme\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("stats.cxp");
+        CxpBuilder::new(source.path()).scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+
+        let reader = crate::CxpReader::open(&cxp_path).unwrap();
+        let stats = reader.statistics().unwrap();
+
+        assert_eq!(stats.total_files, 3);
+        assert_eq!(stats.original_bytes, 2 * shared.len() as u64 + 9);
+        assert!(stats.compressed_chunk_bytes > 0);
+        assert!(stats.dedup_ratio() > 0.0);
+
+        let rs = stats.extensions.iter().find(|e| e.extension == "rs").unwrap();
+        assert_eq!(rs.files, 2);
+        assert_eq!(rs.dedup_hits, rs.chunk_refs / 2);
+        assert!((rs.dedup_ratio() - 0.5).abs() < 1e-9);
+
+        let histogram_total: usize = stats.chunk_size_histogram.iter().map(|b| b.count).sum();
+        assert_eq!(histogram_total, stats.unique_chunks);
+
+        assert_eq!(stats.directories[0].directory, "src");
+        assert_eq!(stats.directories[1].directory, ".");
+        assert_eq!(stats.largest_files.last().unwrap().path, "README.md");
+        assert_eq!(stats.embeddings.total_bytes(), 0);
+    }
+
+    #[test]
+    fn test_duplicate_report() {
+        let source = TempDir::new().unwrap();
+        let config = (0..400).map(|i| format!("setting_{} = {}\n", i, i * 7)).collect::<String>();
+        let vendored = (0..400).map(|i| format!("fn helper_{}() -> u32 {{ {} }}\n", i, i)).collect::<String>();
+        fs::create_dir_all(source.path().join("app")).unwrap();
+        fs::create_dir_all(source.path().join("vendor/lib")).unwrap();
+        fs::write(source.path().join("app/config.toml"), &config).unwrap();
+        fs::write(source.path().join("deploy.toml"), &config).unwrap();
+        fs::write(source.path().join("app/lib.rs"), &vendored).unwrap();
+        fs::write(source.path().join("vendor/lib/lib.rs"), &vendored).unwrap();
+        fs::write(source.path().join("README.md"), "# Unique readme\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("dups.cxp");
+        CxpBuilder::new(source.path()).scan().unwrap().process().unwrap().build(&cxp_path).unwrap();
+
+        let reader = crate::CxpReader::open(&cxp_path).unwrap();
+        let report = reader.duplicate_report().unwrap();
+
+        assert_eq!(report.clusters.len(), 2);
+        assert_eq!(report.files_with_duplicates, 4);
+        assert_eq!(report.duplicate_bytes, (config.len() + vendored.len()) as u64);
+
+        let paths: Vec<Vec<&str>> = report
+            .clusters
+            .iter()
+            .map(|c| c.files.iter().map(|f| f.path.as_str()).collect())
+            .collect();
+        assert!(paths.contains(&vec!["app/config.toml", "deploy.toml"]));
+        assert!(paths.contains(&vec!["app/lib.rs", "vendor/lib/lib.rs"]));
+
+        for cluster in &report.clusters {
+            assert_eq!(cluster.pairs.len(), 1);
+            assert!((cluster.pairs[0].shared_percent - 100.0).abs() < 1e-9);
+            assert!(cluster.files.iter().all(|f| (f.shared_percent - 100.0).abs() < 1e-9));
+        }
+
+        // Exact copies still cluster at the strictest threshold
+        let strict = super::DuplicateOptions::default().with_min_shared_fraction(1.0);
+        assert_eq!(reader.duplicate_report_with(&strict).unwrap().clusters.len(), 2);
+
+        // The same copies form identical groups, keeping one file each
+        assert_eq!(report.groups.len(), 2);
+        assert!(report.groups.iter().all(|g| g.identical && g.files.len() == 2));
+        assert_eq!(report.redundant_bytes, report.duplicate_bytes);
+    }
+}
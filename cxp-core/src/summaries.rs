@@ -0,0 +1,534 @@
+//! Summaries Extension
+//!
+//! Short summaries of files and directories, written at build time by a
+//! [`Summarizer`] (`CxpBuilder::with_summarizer`). Summaries are high-level
+//! entry points: searching them finds the module that handles something
+//! before a single chunk is read.
+//!
+//! | Summarizer | Summary |
+//! |------------|---------|
+//! | [`HeuristicSummarizer`] | leading doc comment (or first lines) of a file; entries and child summaries of a directory |
+//! | [`CommandSummarizer`] | stdout of an external command (any LLM CLI that reads a prompt on stdin) |
+//!
+//! Structure:
+//! ```text
+//! extensions/summaries/
+//! ├── manifest.msgpack     # extension metadata
+//! └── summaries.msgpack    # Summaries
+//! ```
+//!
+//! Directories are summarized bottom-up, so a directory's summarizer sees
+//! the summaries of its files and subdirectories. Directory paths have no
+//! trailing slash; the archive root is `""`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::extensions::Extension;
+use crate::{CxpError, Result};
+
+/// Extension namespace of the summaries
+pub const SUMMARIES_NAMESPACE: &str = "summaries";
+
+/// Data key of the summaries within their namespace
+pub const SUMMARIES_KEY: &str = "summaries.msgpack";
+
+/// Version of the summaries layout
+pub const SUMMARIES_VERSION: &str = "1.0.0";
+
+/// Default maximum length of a summary (characters)
+pub const DEFAULT_SUMMARY_CHARS: usize = 240;
+
+/// File content sent to external summarizers (bytes)
+const MAX_PROMPT_CONTENT: usize = 16 * 1024;
+
+/// Directory entries named in a heuristic directory summary
+const MAX_LISTED_ENTRIES: usize = 8;
+
+/// Writes the summaries of an archive's files and directories
+pub trait Summarizer: Send + Sync {
+    /// Summary of a text file (`None` leaves it unsummarized)
+    fn summarize_file(&self, path: &str, content: &str) -> Result<Option<String>>;
+
+    /// Summary of a directory from its direct entries
+    ///
+    /// `entries` holds (name, summary) of files and subdirectories (the
+    /// latter with a trailing `/`), sorted by name; the summary is empty for
+    /// entries without one.
+    fn summarize_directory(&self, path: &str, entries: &[(&str, &str)]) -> Result<Option<String>> {
+        let _ = path;
+        Ok(Some(directory_overview(entries, DEFAULT_SUMMARY_CHARS)))
+    }
+}
+
+/// Summaries from the files themselves (no LLM needed)
+///
+/// A file is summarized by its leading comment (module docs, docstring,
+/// license headers aside) or, without one, by its first lines.
+#[derive(Debug, Clone, Copy)]
+pub struct HeuristicSummarizer {
+    max_chars: usize,
+}
+
+impl Default for HeuristicSummarizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeuristicSummarizer {
+    /// Summarizer with the default summary length
+    pub fn new() -> Self {
+        Self { max_chars: DEFAULT_SUMMARY_CHARS }
+    }
+
+    /// Limit summaries to `max_chars` characters
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars.max(1);
+        self
+    }
+}
+
+impl Summarizer for HeuristicSummarizer {
+    fn summarize_file(&self, path: &str, content: &str) -> Result<Option<String>> {
+        let prose = path.ends_with(".md") || path.ends_with(".txt");
+        let summary = match prose {
+            true => first_lines(content, self.max_chars),
+            false => leading_comment(content).unwrap_or_else(|| first_lines(content, self.max_chars)),
+        };
+        Ok((!summary.is_empty()).then(|| truncate(&summary, self.max_chars)))
+    }
+
+    fn summarize_directory(&self, _path: &str, entries: &[(&str, &str)]) -> Result<Option<String>> {
+        Ok(Some(directory_overview(entries, self.max_chars)))
+    }
+}
+
+/// Summaries from an external command
+///
+/// A prompt with the file content (or directory listing) is written to the
+/// command's stdin and its stdout is used as the summary, e.g.
+/// `["ollama", "run", "llama3"]`. Runs once per file and directory.
+#[derive(Debug, Clone)]
+pub struct CommandSummarizer {
+    command: Vec<String>,
+    max_chars: usize,
+}
+
+impl CommandSummarizer {
+    /// Summarizer running `command` (program followed by its arguments)
+    pub fn new<I, S>(command: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let command: Vec<String> = command.into_iter().map(Into::into).collect();
+        if command.is_empty() {
+            return Err(CxpError::InvalidFormat("Summarizer command is empty".to_string()));
+        }
+        Ok(Self { command, max_chars: DEFAULT_SUMMARY_CHARS })
+    }
+
+    /// Cut summaries to `max_chars` characters
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars.max(1);
+        self
+    }
+
+    /// Prompt sent for a file
+    pub fn file_prompt(path: &str, content: &str) -> String {
+        let mut end = content.len().min(MAX_PROMPT_CONTENT);
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!(
+            "Summarize what the file below does in one or two sentences. \
+             Reply with the summary only.\n\nFile: {}\n\n{}\n",
+            path,
+            &content[..end]
+        )
+    }
+
+    /// Prompt sent for a directory
+    pub fn directory_prompt(path: &str, entries: &[(&str, &str)]) -> String {
+        let listing: String = entries
+            .iter()
+            .map(|(name, summary)| format!("- {}: {}\n", name, summary))
+            .collect();
+        format!(
+            "Summarize what the directory below contains in one or two sentences. \
+             Reply with the summary only.\n\nDirectory: {}/\n\n{}",
+            path, listing
+        )
+    }
+
+    /// Run the command on a prompt and clean up its reply
+    fn run(&self, prompt: &str) -> Result<Option<String>> {
+        let mut child = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| CxpError::io(format!("Failed to run summarizer '{}': {}", self.command[0], e)))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(prompt.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(CxpError::io(format!(
+                "Summarizer '{}' failed ({}): {}",
+                self.command[0],
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let reply = collapse_whitespace(&String::from_utf8_lossy(&output.stdout));
+        Ok((!reply.is_empty()).then(|| truncate(&reply, self.max_chars)))
+    }
+}
+
+impl Summarizer for CommandSummarizer {
+    fn summarize_file(&self, path: &str, content: &str) -> Result<Option<String>> {
+        self.run(&Self::file_prompt(path, content))
+    }
+
+    fn summarize_directory(&self, path: &str, entries: &[(&str, &str)]) -> Result<Option<String>> {
+        self.run(&Self::directory_prompt(path, entries))
+    }
+}
+
+/// Which kind of path a summary describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryKind {
+    /// A file
+    File,
+    /// A directory
+    Directory,
+}
+
+/// A summary matching a search
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummaryHit {
+    /// File or directory path
+    pub path: String,
+    /// Whether `path` is a file or a directory
+    pub kind: SummaryKind,
+    /// The matching summary
+    pub summary: String,
+    /// Relevance (higher is better)
+    pub score: f32,
+}
+
+/// Summaries of an archive's files and directories
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summaries {
+    /// Summaries by file path
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+    /// Summaries by directory path (`""` for the root)
+    #[serde(default)]
+    pub directories: BTreeMap<String, String>,
+}
+
+impl Summaries {
+    /// Create empty summaries
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Summarize `files` (path, text content) and every directory above them
+    pub fn build<'a, I>(files: I, summarizer: &dyn Summarizer) -> Result<Self>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut summaries = Self::new();
+        let mut directories = BTreeSet::from([String::new()]);
+        for (path, content) in files {
+            if let Some(summary) = summarizer.summarize_file(path, content)? {
+                summaries.files.insert(path.to_string(), summary);
+            }
+            let mut dir = parent(path);
+            while let Some(current) = dir {
+                directories.insert(current.to_string());
+                dir = parent(current);
+            }
+        }
+
+        // Deepest first, so subdirectory summaries exist when their parent is summarized
+        let mut directories: Vec<String> = directories.into_iter().collect();
+        directories.sort_by_key(|dir| std::cmp::Reverse(depth(dir)));
+        for dir in directories {
+            let entries = summaries.entries(&dir);
+            let entries: Vec<(&str, &str)> = entries.iter().map(|(n, s)| (n.as_str(), s.as_str())).collect();
+            if let Some(summary) = summarizer.summarize_directory(&dir, &entries)? {
+                summaries.directories.insert(dir, summary);
+            }
+        }
+
+        tracing::info!(
+            "Summarized {} files and {} directories",
+            summaries.files.len(),
+            summaries.directories.len()
+        );
+        Ok(summaries)
+    }
+
+    /// Whether nothing is summarized
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.directories.is_empty()
+    }
+
+    /// Summary of a file
+    pub fn file(&self, path: &str) -> Option<&str> {
+        self.files.get(path).map(String::as_str)
+    }
+
+    /// Summary of a directory (a trailing slash is ignored)
+    pub fn directory(&self, path: &str) -> Option<&str> {
+        self.directories.get(path.trim_end_matches('/')).map(String::as_str)
+    }
+
+    /// Summaries whose text or path matches the words of `query`
+    ///
+    /// Summaries matching more distinct words rank first, then those with
+    /// more occurrences (matches in the path count double); directories come
+    /// before files on ties, as broader entry points.
+    pub fn search(&self, query: &str, top_k: usize) -> Vec<SummaryHit> {
+        let terms: BTreeSet<String> = words(query).collect();
+        if terms.is_empty() || top_k == 0 {
+            return Vec::new();
+        }
+
+        let candidates = self
+            .directories
+            .iter()
+            .map(|(path, summary)| (path, summary, SummaryKind::Directory))
+            .chain(self.files.iter().map(|(path, summary)| (path, summary, SummaryKind::File)));
+        let mut hits: Vec<(usize, usize, SummaryHit)> = candidates
+            .filter_map(|(path, summary, kind)| {
+                let path_words: Vec<String> = words(path).collect();
+                let summary_words: Vec<String> = words(summary).collect();
+                let count = |words: &[String], term: &str| words.iter().filter(|w| w.starts_with(term)).count();
+                // A match in the path names the entry itself, so it weighs double
+                let occurrences = |term: &String| 2 * count(&path_words, term) + count(&summary_words, term);
+                let distinct = terms.iter().filter(|t| occurrences(t) > 0).count();
+                let total: usize = terms.iter().map(occurrences).sum();
+                (distinct > 0).then(|| {
+                    let hit = SummaryHit {
+                        path: path.clone(),
+                        kind,
+                        summary: summary.clone(),
+                        score: distinct as f32 / terms.len() as f32 + total as f32 / 100.0,
+                    };
+                    (distinct, total, hit)
+                })
+            })
+            .collect();
+        // Stable sort keeps directories (chained first) ahead on ties
+        hits.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+        hits.into_iter().take(top_k).map(|(_, _, hit)| hit).collect()
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Direct entries of a directory with their summaries, sorted by name
+    fn entries(&self, dir: &str) -> Vec<(String, String)> {
+        let files = self.files.iter().map(|(path, summary)| (path, summary, ""));
+        let dirs = self.directories.iter().filter(|(path, _)| !path.is_empty()).map(|(path, summary)| (path, summary, "/"));
+        let mut entries: Vec<(String, String)> = files
+            .chain(dirs)
+            .filter(|(path, _, _)| parent(path).unwrap_or("") == dir)
+            .map(|(path, summary, suffix)| {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                (format!("{}{}", name, suffix), summary.clone())
+            })
+            .collect();
+        entries.sort();
+        entries
+    }
+}
+
+/// Extension marker used to register the summaries namespace
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SummariesExtension;
+
+impl Extension for SummariesExtension {
+    fn namespace(&self) -> &str {
+        SUMMARIES_NAMESPACE
+    }
+
+    fn version(&self) -> &str {
+        SUMMARIES_VERSION
+    }
+}
+
+/// Directory containing `path` (`None` for the root itself)
+fn parent(path: &str) -> Option<&str> {
+    if path.is_empty() {
+        return None;
+    }
+    Some(path.rsplit_once('/').map_or("", |(dir, _)| dir))
+}
+
+/// Number of path components
+fn depth(path: &str) -> usize {
+    if path.is_empty() { 0 } else { path.matches('/').count() + 1 }
+}
+
+/// Lowercase alphanumeric words of a text
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Text of the first comment block (skipping shebangs, attributes and blank lines)
+fn leading_comment(content: &str) -> Option<String> {
+    const MARKERS: &[&str] = &["//!", "///", "//", "/**", "/*", "*/", "*", "#", "--", "\"\"\"", "'''"];
+
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty() || line.starts_with("#!") || line.starts_with("#["))
+        .peekable();
+    let is_comment = |line: &str| MARKERS.iter().any(|m| line.starts_with(m)) && !line.starts_with("#include");
+    if !lines.peek().is_some_and(|line| is_comment(line)) {
+        return None;
+    }
+
+    let mut text = Vec::new();
+    for line in lines.take_while(|line| is_comment(line)) {
+        let mut rest = line;
+        while let Some(marker) = MARKERS.iter().find(|m| rest.starts_with(*m)) {
+            rest = rest[marker.len()..].trim();
+        }
+        let rest = rest.trim_end_matches("*/").trim_end_matches("\"\"\"").trim_end_matches("'''").trim();
+        // License headers are not what the file does
+        if rest.starts_with("Copyright") || rest.starts_with("SPDX-") {
+            continue;
+        }
+        if !rest.is_empty() {
+            text.push(rest);
+        }
+    }
+    let text = text.join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// First non-empty lines (heading markers removed) up to `max_chars`
+fn first_lines(content: &str, max_chars: usize) -> String {
+    let mut text = String::new();
+    for line in content.lines().map(|l| l.trim().trim_start_matches('#').trim()).filter(|l| !l.is_empty()) {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(line);
+        if text.chars().count() >= max_chars {
+            break;
+        }
+    }
+    text
+}
+
+/// "3 files, 1 directory: a.rs, b.rs, ..." followed by the first entry summary
+fn directory_overview(entries: &[(&str, &str)], max_chars: usize) -> String {
+    let dirs = entries.iter().filter(|(name, _)| name.ends_with('/')).count();
+    let files = entries.len() - dirs;
+    let count = |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+    let mut names: Vec<&str> = entries.iter().take(MAX_LISTED_ENTRIES).map(|(name, _)| *name).collect();
+    if entries.len() > MAX_LISTED_ENTRIES {
+        names.push("...");
+    }
+
+    let mut overview = format!(
+        "{}, {}: {}",
+        count(files, "file", "files"),
+        count(dirs, "directory", "directories"),
+        names.join(", ")
+    );
+    if let Some((name, summary)) = entries.iter().find(|(_, summary)| !summary.is_empty()) {
+        overview.push_str(&format!(". {}: {}", name, summary));
+    }
+    truncate(&overview, max_chars)
+}
+
+/// Whitespace runs (including newlines) replaced by single spaces
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cut `text` to `max_chars` characters, marking the cut with "..."
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = collapse_whitespace(text);
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let kept: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+    format!("{}...", kept.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heuristic_file_summaries() {
+        let summarizer = HeuristicSummarizer::new();
+        let rust = "#![allow(dead_code)]\n//! Retry logic for HTTP calls\n//!\n//! Backs off exponentially.\n\nfn retry() {}\n";
+        assert_eq!(
+            summarizer.summarize_file("src/retry.rs", rust).unwrap().as_deref(),
+            Some("Retry logic for HTTP calls Backs off exponentially.")
+        );
+
+        let python = "#!/usr/bin/env python\n# Copyright 2024 Example\n# Loads the config file\nimport os\n";
+        assert_eq!(summarizer.summarize_file("load.py", python).unwrap().as_deref(), Some("Loads the config file"));
+
+        let markdown = "# Setup\n\nInstall the tools first.\n";
+        assert_eq!(summarizer.summarize_file("README.md", markdown).unwrap().as_deref(), Some("Setup Install the tools first."));
+
+        let short = HeuristicSummarizer::new().with_max_chars(10);
+        assert_eq!(short.summarize_file("a.rs", "fn main() { run_everything(); }").unwrap().as_deref(), Some("fn main..."));
+        assert_eq!(summarizer.summarize_file("empty.rs", "\n\n").unwrap(), None);
+    }
+
+    #[test]
+    fn test_build_and_search() {
+        let files = [
+            ("src/net/retry.rs", "//! Retry logic for HTTP calls\n"),
+            ("src/net/client.rs", "//! HTTP client with connection pooling\n"),
+            ("src/main.rs", "fn main() {}\n"),
+        ];
+        let summaries = Summaries::build(files, &HeuristicSummarizer::new()).unwrap();
+
+        assert_eq!(summaries.file("src/net/retry.rs"), Some("Retry logic for HTTP calls"));
+        assert_eq!(
+            summaries.directory("src/net/"),
+            Some("2 files, 0 directories: client.rs, retry.rs. client.rs: HTTP client with connection pooling")
+        );
+        assert!(summaries.directory("src").unwrap().starts_with("1 file, 1 directory: main.rs, net/"));
+        assert!(summaries.directory("").is_some());
+
+        let hits = summaries.search("http retry", 10);
+        assert_eq!(hits[0].path, "src/net/retry.rs");
+        assert_eq!(hits[0].kind, SummaryKind::File);
+        assert!(hits.iter().any(|h| h.path == "src/net" && h.kind == SummaryKind::Directory));
+        assert!(summaries.search("database", 10).is_empty());
+
+        let restored = Summaries::from_msgpack(&summaries.to_msgpack().unwrap()).unwrap();
+        assert_eq!(restored, summaries);
+    }
+}
@@ -0,0 +1,337 @@
+//! Symbol Index
+//!
+//! A ctags-like table of the functions, types and constants defined in the
+//! archived source files, stored at [`SYMBOLS_PATH`] when building with
+//! `CxpBuilder::with_symbols`. It answers "where is `ChunkStore` defined?"
+//! with a file and line, without loading embeddings or reading chunks.
+//!
+//! Symbols come from a [`SymbolParser`]. The built-in [`LineSymbolParser`]
+//! recognizes definitions by their leading keywords (Rust, Python, JS/TS,
+//! Go, Java/Kotlin/C#, C/C++, Ruby); parsers backed by tree-sitter or an
+//! LSP implement the same trait.
+
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+
+use crate::format::FileMap;
+use crate::{CxpError, Result};
+
+/// Path of the symbol index inside the archive
+pub const SYMBOLS_PATH: &str = "symbols.msgpack";
+
+/// What a symbol defines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    Function,
+    Struct,
+    Enum,
+    Trait,
+    Interface,
+    Class,
+    Type,
+    Constant,
+    Module,
+    Macro,
+}
+
+impl std::fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Function => "function",
+            Self::Struct => "struct",
+            Self::Enum => "enum",
+            Self::Trait => "trait",
+            Self::Interface => "interface",
+            Self::Class => "class",
+            Self::Type => "type",
+            Self::Constant => "constant",
+            Self::Module => "module",
+            Self::Macro => "macro",
+        };
+        f.pad(name)
+    }
+}
+
+/// A named definition in an archived file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Archive path of the defining file
+    pub path: String,
+    /// 1-based line of the definition
+    pub line: usize,
+}
+
+/// Finds the symbol definitions of a file
+pub trait SymbolParser: Send + Sync {
+    /// Definitions in `content` of the file at `path` (empty for unknown languages)
+    fn parse(&self, path: &str, content: &str) -> Vec<Symbol>;
+}
+
+/// Definitions recognized by their leading keywords, one per line
+///
+/// Fast and dependency-free, but blind to definitions that do not start a
+/// line (e.g. C functions or Java methods).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineSymbolParser;
+
+impl SymbolParser for LineSymbolParser {
+    fn parse(&self, path: &str, content: &str) -> Vec<Symbol> {
+        definitions(path, content)
+            .into_iter()
+            .map(|(line, name, kind)| Symbol { name, kind, path: path.to_string(), line })
+            .collect()
+    }
+}
+
+/// Symbols of an archive, sorted by name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SymbolIndex {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolIndex {
+    /// Index the (path, content) of text files with `parser`
+    pub fn build<'a>(files: impl IntoIterator<Item = (&'a str, &'a str)>, parser: &dyn SymbolParser) -> Self {
+        Self::from_symbols(files.into_iter().flat_map(|(path, content)| parser.parse(path, content)))
+    }
+
+    /// Index already parsed symbols
+    pub fn from_symbols(symbols: impl IntoIterator<Item = Symbol>) -> Self {
+        let mut symbols: Vec<Symbol> = symbols.into_iter().collect();
+        symbols.sort_by(|a, b| (&a.name, &a.path, a.line).cmp(&(&b.name, &b.path, b.line)));
+        symbols.dedup();
+        Self { symbols }
+    }
+
+    /// Whether no symbol is indexed
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Number of indexed symbols
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// All symbols, sorted by name
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Definitions of exactly `name`
+    pub fn find(&self, name: &str) -> &[Symbol] {
+        let start = self.symbols.partition_point(|s| s.name.as_str() < name);
+        let end = start + self.symbols[start..].partition_point(|s| s.name == name);
+        &self.symbols[start..end]
+    }
+
+    /// Symbols whose name contains `query` (case-insensitive), exact and prefix matches first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<&Symbol> {
+        let query = query.to_lowercase();
+        let mut hits: Vec<(u8, &Symbol)> = self.symbols
+            .iter()
+            .filter_map(|symbol| {
+                let name = symbol.name.to_lowercase();
+                let rank = if name == query {
+                    0
+                } else if name.starts_with(&query) {
+                    1
+                } else if name.contains(&query) {
+                    2
+                } else {
+                    return None;
+                };
+                Some((rank, symbol))
+            })
+            .collect();
+        hits.sort_by_key(|(rank, symbol)| (*rank, symbol.name.len()));
+        hits.into_iter().take(limit).map(|(_, symbol)| symbol).collect()
+    }
+
+    /// Symbols defined in one file, in line order
+    pub fn in_file(&self, path: &str) -> Vec<&Symbol> {
+        let mut symbols: Vec<&Symbol> = self.symbols.iter().filter(|s| s.path == path).collect();
+        symbols.sort_by_key(|s| s.line);
+        symbols
+    }
+
+    /// Only the symbols of files in `file_map`
+    pub fn for_files(&self, file_map: &FileMap) -> Self {
+        Self {
+            symbols: self.symbols.iter().filter(|s| file_map.files.contains_key(&s.path)).cloned().collect(),
+        }
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Read the symbol index from an archive (`None` if it has none)
+    pub fn read_from_archive<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>) -> Result<Option<Self>> {
+        let mut entry = match archive.by_name(SYMBOLS_PATH) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        Self::from_msgpack(&data).map(Some)
+    }
+}
+
+/// Modifiers skipped before a definition keyword, per language
+const RUST_MODIFIERS: &[&str] = &["async ", "unsafe ", "extern \"C\" ", "default "];
+const SCRIPT_MODIFIERS: &[&str] = &["export ", "default ", "declare ", "async ", "abstract "];
+const JVM_MODIFIERS: &[&str] = &[
+    "public ", "private ", "protected ", "internal ", "static ", "final ", "abstract ", "sealed ", "open ",
+    "data ", "partial ", "override ", "suspend ", "inline ",
+];
+
+/// Definitions of a file as (line, name, kind)
+pub(crate) fn definitions(path: &str, content: &str) -> Vec<(usize, String, SymbolKind)> {
+    use SymbolKind::*;
+
+    let extension = path.rsplit_once('.').map_or("", |(_, ext)| ext);
+    let (modifiers, keywords): (&[&str], &[(&str, SymbolKind)]) = match extension {
+        "rs" => (RUST_MODIFIERS, &[
+            ("const fn ", Function), ("fn ", Function), ("struct ", Struct), ("enum ", Enum), ("union ", Struct),
+            ("trait ", Trait), ("type ", Type), ("const ", Constant), ("static ", Constant), ("mod ", Module),
+            ("macro_rules! ", Macro),
+        ]),
+        "py" | "pyi" => (&["async "], &[("def ", Function), ("class ", Class)]),
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => (SCRIPT_MODIFIERS, &[
+            ("function* ", Function), ("function ", Function), ("class ", Class), ("interface ", Interface),
+            ("type ", Type), ("enum ", Enum), ("const enum ", Enum), ("namespace ", Module),
+        ]),
+        "go" => (&[], &[("func ", Function), ("type ", Type)]),
+        "java" | "kt" | "kts" | "cs" | "scala" => (JVM_MODIFIERS, &[
+            ("class ", Class), ("interface ", Interface), ("enum class ", Enum), ("enum ", Enum), ("record ", Class),
+            ("object ", Class), ("trait ", Trait), ("fun ", Function), ("def ", Function),
+        ]),
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" => (&["typedef "], &[
+            ("struct ", Struct), ("class ", Class), ("enum class ", Enum), ("enum ", Enum), ("union ", Struct),
+            ("namespace ", Module), ("#define ", Macro),
+        ]),
+        "rb" => (&[], &[("def self.", Function), ("def ", Function), ("class ", Class), ("module ", Module)]),
+        _ => return Vec::new(),
+    };
+
+    let c_family = matches!(extension, "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh");
+    let mut found = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let mut line = strip_visibility(line.trim());
+        let definition = loop {
+            if let Some((rest, kind)) = keywords.iter().find_map(|(k, kind)| line.strip_prefix(k).map(|rest| (rest, *kind))) {
+                break Some((rest, kind));
+            }
+            match modifiers.iter().find_map(|m| line.strip_prefix(m)) {
+                Some(rest) => line = rest,
+                None => break None,
+            }
+        };
+        let Some((rest, mut kind)) = definition else {
+            continue;
+        };
+        // C forward declarations (`struct foo;`) define nothing
+        if c_family && line.ends_with(';') && kind != Macro {
+            continue;
+        }
+
+        // Go methods: func (r *Receiver) Name(
+        let rest = match rest.strip_prefix('(') {
+            Some(receiver) if extension == "go" => receiver.split_once(')').map_or("", |(_, name)| name.trim_start()),
+            _ => rest,
+        };
+        let name: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$').collect();
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            continue;
+        }
+        if extension == "go" && kind == Type {
+            let body = rest[name.len()..].trim_start();
+            if body.starts_with("struct") {
+                kind = Struct;
+            } else if body.starts_with("interface") {
+                kind = Interface;
+            }
+        }
+        found.push((index + 1, name, kind));
+    }
+    found
+}
+
+/// A line without a leading Rust visibility (`pub`, `pub(crate)`, ...)
+pub(crate) fn strip_visibility(line: &str) -> &str {
+    let Some(rest) = line.strip_prefix("pub") else {
+        return line;
+    };
+    let rest = match rest.strip_prefix('(') {
+        Some(scoped) => scoped.split_once(')').map_or(rest, |(_, after)| after),
+        None => rest,
+    };
+    rest.strip_prefix(' ').unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_parser_finds_definitions() {
+        let rust = "pub struct ChunkStore {\n}\n\nimpl ChunkStore {\n    pub(crate) async fn insert(&self) {}\n    const fn empty() {}\n}\n\npub const MAX_CHUNKS: usize = 8;\nmacro_rules! chunk {\n";
+        let kinds: Vec<(usize, String, SymbolKind)> = definitions("src/store.rs", rust);
+        assert_eq!(kinds, vec![
+            (1, "ChunkStore".to_string(), SymbolKind::Struct),
+            (5, "insert".to_string(), SymbolKind::Function),
+            (6, "empty".to_string(), SymbolKind::Function),
+            (9, "MAX_CHUNKS".to_string(), SymbolKind::Constant),
+            (10, "chunk".to_string(), SymbolKind::Macro),
+        ]);
+
+        let names = |path, content| definitions(path, content).into_iter().map(|(_, name, kind)| (name, kind)).collect::<Vec<_>>();
+        assert_eq!(names("app.ts", "export default class App {}\nexport async function load() {}\nexport interface Props {}\n"), vec![
+            ("App".to_string(), SymbolKind::Class),
+            ("load".to_string(), SymbolKind::Function),
+            ("Props".to_string(), SymbolKind::Interface),
+        ]);
+        assert_eq!(names("srv.go", "type Server struct {\nfunc (s *Server) Serve() error {\n"), vec![
+            ("Server".to_string(), SymbolKind::Struct),
+            ("Serve".to_string(), SymbolKind::Function),
+        ]);
+        assert_eq!(names("api.h", "struct forward;\ntypedef struct config {\n#define LIMIT 4\n"), vec![
+            ("config".to_string(), SymbolKind::Struct),
+            ("LIMIT".to_string(), SymbolKind::Macro),
+        ]);
+        assert!(names("notes.md", "fn not_code() {}").is_empty());
+    }
+
+    #[test]
+    fn test_index_find_and_search() {
+        let files = [
+            ("src/store.rs", "pub struct ChunkStore;\nfn store_chunk() {}\n"),
+            ("py/store.py", "class ChunkStore:\n    def put(self):\n"),
+        ];
+        let index = SymbolIndex::build(files, &LineSymbolParser);
+
+        let found = index.find("ChunkStore");
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].path.as_str(), found[0].kind), ("py/store.py", SymbolKind::Class));
+        assert_eq!((found[1].path.as_str(), found[1].line), ("src/store.rs", 1));
+        assert!(index.find("Chunk").is_empty());
+
+        let hits = index.search("chunk", 10);
+        assert_eq!(hits.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["ChunkStore", "ChunkStore", "store_chunk"]);
+        assert_eq!(index.in_file("py/store.py").iter().map(|s| s.line).collect::<Vec<_>>(), vec![1, 2]);
+
+        let restored = SymbolIndex::from_msgpack(&index.to_msgpack().unwrap()).unwrap();
+        assert_eq!(restored, index);
+    }
+}
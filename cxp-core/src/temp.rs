@@ -0,0 +1,206 @@
+//! Temporary Files
+//!
+//! Embedded children opened from an archive on disk go through short-lived
+//! temp files (search indexes are serialized in memory). `TempPolicy` decides
+//! where they live and `TempGuard` removes them when dropped - on success, on
+//! early `?` returns and while unwinding from a panic.
+//!
+//! Files rewritten in place (archives, sidecars) go through [`replace_file`],
+//! which writes a unique sibling and renames it over the original.
+
+use crate::{CxpError, Result};
+use std::path::{Path, PathBuf};
+
+/// RAM-backed directory used by [`TempPolicy::InMemory`] when available
+const SHM_DIR: &str = "/dev/shm";
+
+/// Where temporary files are created
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TempPolicy {
+    /// The system temp directory (`std::env::temp_dir()`)
+    #[default]
+    System,
+    /// A custom directory (created if missing)
+    Dir(PathBuf),
+    /// A RAM-backed filesystem when the platform has one, else the system temp directory
+    InMemory,
+}
+
+impl TempPolicy {
+    /// Policy from CLI-style options (`--temp-dir`, `--temp-in-memory`)
+    pub fn from_options(dir: Option<PathBuf>, in_memory: bool) -> Self {
+        match dir {
+            Some(dir) => Self::Dir(dir),
+            None if in_memory => Self::InMemory,
+            None => Self::System,
+        }
+    }
+
+    /// Directory temp files are created in
+    pub fn dir(&self) -> PathBuf {
+        match self {
+            Self::System => std::env::temp_dir(),
+            Self::Dir(dir) => dir.clone(),
+            Self::InMemory => {
+                let shm = Path::new(SHM_DIR);
+                if shm.is_dir() {
+                    shm.to_path_buf()
+                } else {
+                    std::env::temp_dir()
+                }
+            }
+        }
+    }
+
+    /// Reserve a unique temp path (`<dir>/<prefix>_<uuid>`) that is removed on drop
+    ///
+    /// Nothing is created on disk except the directory itself.
+    pub fn guard(&self, prefix: &str) -> Result<TempGuard> {
+        let dir = self.dir();
+        std::fs::create_dir_all(&dir)?;
+        let base = dir.join(format!("{}_{}", prefix, uuid::Uuid::new_v4()));
+        Ok(TempGuard {
+            paths: vec![base.clone()],
+            base,
+        })
+    }
+}
+
+/// Removes its temp files when dropped
+#[derive(Debug)]
+pub struct TempGuard {
+    base: PathBuf,
+    paths: Vec<PathBuf>,
+}
+
+impl TempGuard {
+    /// The reserved base path
+    pub fn path(&self) -> &Path {
+        &self.base
+    }
+
+    /// Base path with an extension, also removed on drop
+    pub fn with_extension(&mut self, extension: &str) -> PathBuf {
+        let path = self.base.with_extension(extension);
+        if !self.paths.contains(&path) {
+            self.paths.push(path.clone());
+        }
+        path
+    }
+}
+
+impl Drop for TempGuard {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            match std::fs::remove_file(path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => tracing::warn!("Failed to remove temp file {:?}: {}", path, e),
+            }
+        }
+    }
+}
+
+/// Replace `path` with the file `write` creates at the temp path it is given
+///
+/// The temp file is a unique sibling of `path` (`.<name>_<uuid>`, so
+/// concurrent writers never share it and the rename stays on one
+/// filesystem). It is synced and moved over `path` once `write` succeeds,
+/// and removed if `write` fails or panics.
+pub fn replace_file<T>(path: &Path, write: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let guard = TempPolicy::Dir(dir.to_path_buf()).guard(&replacement_prefix(path))?;
+
+    let value = write(guard.path())?;
+    std::fs::OpenOptions::new().write(true).open(guard.path())?.sync_all()?;
+    std::fs::rename(guard.path(), path).map_err(|e| CxpError::io(format!("Failed to replace {:?}: {}", path, e)))?;
+    Ok(value)
+}
+
+/// Whether `candidate` is a temp file of [`replace_file`] for `path`
+pub fn is_replacement_of(path: &Path, candidate: &Path) -> bool {
+    candidate.parent() == path.parent()
+        && candidate
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(&format!("{}_", replacement_prefix(path))))
+}
+
+fn replacement_prefix(path: &Path) -> String {
+    format!(".{}", path.file_name().unwrap_or_default().to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_guard_removes_files() {
+        let dir = TempDir::new().unwrap();
+        let policy = TempPolicy::Dir(dir.path().join("nested"));
+
+        let (base, meta) = {
+            let mut guard = policy.guard("cxp_test").unwrap();
+            let meta = guard.with_extension("meta");
+            std::fs::write(guard.path(), b"index").unwrap();
+            std::fs::write(&meta, b"meta").unwrap();
+            assert!(guard.path().starts_with(dir.path().join("nested")));
+            (guard.path().to_path_buf(), meta)
+        };
+
+        assert!(!base.exists());
+        assert!(!meta.exists());
+    }
+
+    #[test]
+    fn test_guard_cleans_up_on_panic() {
+        let dir = TempDir::new().unwrap();
+        let policy = TempPolicy::Dir(dir.path().to_path_buf());
+
+        let result = std::panic::catch_unwind(|| {
+            let guard = policy.guard("cxp_panic").unwrap();
+            std::fs::write(guard.path(), b"partial").unwrap();
+            panic!("save failed");
+        });
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, b"old").unwrap();
+
+        let len = replace_file(&path, |temp| {
+            assert!(is_replacement_of(&path, temp));
+            std::fs::write(temp, b"new")?;
+            Ok(3)
+        })
+        .unwrap();
+        assert_eq!(len, 3);
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+
+        // A failed write leaves the original and no temp file behind
+        let result: Result<()> = replace_file(&path, |temp| {
+            std::fs::write(temp, b"partial")?;
+            Err(CxpError::InvalidFormat("failed".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert!(!is_replacement_of(&path, &dir.path().join("data.bin.tmp")));
+    }
+
+    #[test]
+    fn test_policy_from_options() {
+        assert_eq!(TempPolicy::from_options(None, false), TempPolicy::System);
+        assert_eq!(TempPolicy::from_options(None, true), TempPolicy::InMemory);
+        assert_eq!(
+            TempPolicy::from_options(Some(PathBuf::from("/tmp/cxp")), true),
+            TempPolicy::Dir(PathBuf::from("/tmp/cxp"))
+        );
+        assert!(TempPolicy::InMemory.dir().is_dir());
+    }
+}
@@ -0,0 +1,117 @@
+//! Background Tier Recalculation
+//!
+//! `TierScheduler` periodically recalculates the tiers of a `CxpManager`'s
+//! root children from their `last_accessed` / `updated_at` timestamps, so CXPs
+//! move between Hot, Warm and Cold without calling `calculate_tier` by hand.
+//! Every change is reported to a callback, e.g. to update a UI.
+//!
+//! # Example
+//! ```ignore
+//! let manager = Arc::new(CxpManager::new(CxpManagerConfig::default()));
+//! manager.init()?;
+//! let handle = TierScheduler::new(manager.clone())
+//!     .with_interval(Duration::from_secs(600))
+//!     .spawn(|change| println!("{}: {} -> {}", change.id, change.from.name(), change.to.name()));
+//! // ...
+//! let scheduler = handle.stop()?;
+//! ```
+
+use crate::manager::CxpManager;
+use crate::recursive::TierChange;
+use crate::{CxpError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Default time between recalculations
+pub const DEFAULT_TIER_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the scheduler loop checks for stop requests
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Recalculates the tiers of a manager's CXPs on an interval
+pub struct TierScheduler {
+    manager: Arc<CxpManager>,
+    interval: Duration,
+}
+
+impl TierScheduler {
+    /// Create a scheduler for `manager` with the default interval
+    pub fn new(manager: Arc<CxpManager>) -> Self {
+        Self {
+            manager,
+            interval: DEFAULT_TIER_INTERVAL,
+        }
+    }
+
+    /// Set the time between recalculations
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Time between recalculations
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Recalculate tiers once, returning the CXPs that moved
+    pub fn run_once(&self) -> Result<Vec<TierChange>> {
+        let changes = self.manager.recalculate_tiers()?;
+        for change in &changes {
+            tracing::info!(
+                "CXP '{}' moved from {} to {}",
+                change.id,
+                change.from.name(),
+                change.to.name()
+            );
+        }
+        Ok(changes)
+    }
+
+    /// Recalculate immediately and then every interval on a background thread
+    ///
+    /// `on_change` is called for every tier change. Failed runs are logged and
+    /// retried on the next interval.
+    pub fn spawn<F>(self, mut on_change: F) -> TierSchedulerHandle
+    where
+        F: FnMut(&TierChange) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+
+        let thread = std::thread::spawn(move || {
+            while !stop_flag.load(Ordering::Relaxed) {
+                match self.run_once() {
+                    Ok(changes) => changes.iter().for_each(&mut on_change),
+                    Err(e) => tracing::warn!("Tier recalculation failed: {}", e),
+                }
+
+                let next_run = Instant::now() + self.interval;
+                while Instant::now() < next_run && !stop_flag.load(Ordering::Relaxed) {
+                    std::thread::sleep(POLL_INTERVAL.min(self.interval));
+                }
+            }
+            self
+        });
+
+        TierSchedulerHandle { stop, thread }
+    }
+}
+
+/// Handle to a background tier scheduler thread
+pub struct TierSchedulerHandle {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<TierScheduler>,
+}
+
+impl TierSchedulerHandle {
+    /// Stop the scheduler and return it
+    pub fn stop(self) -> Result<TierScheduler> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread
+            .join()
+            .map_err(|_| CxpError::io("Tier scheduler thread panicked".to_string()))
+    }
+}
@@ -0,0 +1,149 @@
+//! Archive Table of Contents
+//!
+//! `toc.msgpack` is written as the last entry of a CXP archive and lists every
+//! section, extension entry and index file together with its size. Readers use
+//! it to locate extension data by name instead of scanning all ZIP entries
+//! (which can be hundreds of thousands of chunks).
+//!
+//! Archives written before the TOC existed are still readable; the reader
+//! falls back to scanning the entries.
+
+use crate::{CxpError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Path of the table of contents inside the archive
+pub const TOC_PATH: &str = "toc.msgpack";
+
+/// Current TOC version
+pub const TOC_VERSION: u32 = 1;
+
+/// Table of contents of a CXP archive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Toc {
+    /// TOC format version
+    pub version: u32,
+    /// Top-level sections (`manifest.msgpack`, `chunks`, `embeddings`, ...)
+    pub sections: BTreeMap<String, TocSection>,
+    /// Extension namespace -> entries (names relative to `extensions/<namespace>/`)
+    pub extensions: BTreeMap<String, Vec<TocEntry>>,
+    /// Embedding and search index files (full archive paths)
+    pub indices: Vec<TocEntry>,
+}
+
+/// Size summary of one top-level section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TocSection {
+    /// Number of ZIP entries (stored chunks for the packed `chunks` section)
+    pub entries: usize,
+    /// Total stored bytes
+    pub bytes: u64,
+}
+
+/// A single named entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TocEntry {
+    /// Entry name
+    pub name: String,
+    /// Stored bytes
+    pub bytes: u64,
+}
+
+impl Default for Toc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Toc {
+    /// Create an empty table of contents
+    pub fn new() -> Self {
+        Self {
+            version: TOC_VERSION,
+            sections: BTreeMap::new(),
+            extensions: BTreeMap::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    /// Record a written ZIP entry
+    pub fn record(&mut self, path: &str, bytes: u64) {
+        self.record_entries(path, bytes, 1);
+    }
+
+    /// Record a written ZIP entry that counts as `entries` section entries
+    ///
+    /// A chunk pack counts once per chunk it holds and the pack index not at
+    /// all, so the `chunks` section counts stored chunks whether or not the
+    /// archive packs them.
+    pub fn record_entries(&mut self, path: &str, bytes: u64, entries: usize) {
+        let section_name = path.split('/').next().unwrap_or(path);
+        let section = self.sections.entry(section_name.to_string()).or_default();
+        section.entries += entries;
+        section.bytes += bytes;
+
+        if let Some(rest) = path.strip_prefix("extensions/") {
+            if let Some((namespace, name)) = rest.split_once('/') {
+                self.extensions
+                    .entry(namespace.to_string())
+                    .or_default()
+                    .push(TocEntry { name: name.to_string(), bytes });
+            }
+        } else if path.starts_with("embeddings/") {
+            self.indices.push(TocEntry { name: path.to_string(), bytes });
+        }
+    }
+
+    /// Size of a section (zero if absent)
+    pub fn section(&self, name: &str) -> TocSection {
+        self.sections.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Total stored bytes across all sections
+    pub fn total_bytes(&self) -> u64 {
+        self.sections.values().map(|s| s.bytes).sum()
+    }
+
+    /// Serialize to MessagePack
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize from MessagePack
+    pub fn from_msgpack(data: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(data).map_err(|e| CxpError::Serialization(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toc_record() {
+        let mut toc = Toc::new();
+        toc.record("manifest.msgpack", 100);
+        toc.record("chunks/aaaa.zst", 10);
+        toc.record("chunks/bbbb.zst", 20);
+        toc.record("embeddings/binary.bin", 48);
+        toc.record("extensions/contextai/manifest.msgpack", 5);
+        toc.record("extensions/contextai/notes/a.msgpack", 7);
+
+        assert_eq!(toc.section("chunks"), TocSection { entries: 2, bytes: 30 });
+        assert_eq!(toc.section("missing"), TocSection::default());
+        assert_eq!(toc.total_bytes(), 190);
+
+        let mut packed = Toc::new();
+        packed.record_entries("chunks/pack-000.bin", 30, 2);
+        packed.record_entries("chunks/packs.msgpack", 12, 0);
+        assert_eq!(packed.section("chunks"), TocSection { entries: 2, bytes: 42 });
+        assert_eq!(toc.indices[0].name, "embeddings/binary.bin");
+
+        let contextai = &toc.extensions["contextai"];
+        assert_eq!(contextai.len(), 2);
+        assert_eq!(contextai[1].name, "notes/a.msgpack");
+
+        let decoded = Toc::from_msgpack(&toc.to_msgpack().unwrap()).unwrap();
+        assert_eq!(decoded, toc);
+    }
+}
@@ -8,6 +8,11 @@
 //! typical for English text and code. This aligns with OpenAI's tokenizer
 //! estimates for GPT models.
 //!
+//! # Exact Counting
+//! With the `tokenizer` feature, [`TokenCounter`] counts tokens with a real
+//! vocabulary (cl100k, o200k or Llama) loaded from a HuggingFace
+//! `tokenizer.json` export.
+//!
 //! # Example
 //! ```
 //! use cxp_core::token::{estimate_tokens, calculate_savings};
@@ -19,7 +24,10 @@
 //! println!("Token savings: {}%", savings.savings_percent);
 //! ```
 
+use crate::{CxpError, Result};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
 /// Characters per token - conservative estimate
 /// This is based on typical tokenization for GPT models
@@ -89,7 +97,7 @@ impl TokenSavings {
         // Calculate savings
         let savings_per_query = original_total_cost - cxp_total_cost;
         let savings_percent =
-            (savings_per_query / original_total_cost * 100.0).max(0.0).min(100.0);
+            (savings_per_query / original_total_cost * 100.0).clamp(0.0, 100.0);
 
         CostSavings {
             original_cost: original_total_cost,
@@ -136,9 +144,7 @@ pub fn calculate_savings(original_size: u64, cxp_size: u64) -> TokenSavings {
 
     let savings_tokens = original_tokens.saturating_sub(cxp_tokens);
     let savings_percent = if original_tokens > 0 {
-        (savings_tokens as f64 / original_tokens as f64 * 100.0)
-            .max(0.0)
-            .min(100.0)
+        (savings_tokens as f64 / original_tokens as f64 * 100.0).clamp(0.0, 100.0)
     } else {
         0.0
     };
@@ -151,6 +157,169 @@ pub fn calculate_savings(original_size: u64, cxp_size: u64) -> TokenSavings {
     }
 }
 
+/// Tokenizer vocabularies supported for exact token counting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Tokenizer {
+    /// OpenAI cl100k_base (GPT-4, GPT-3.5)
+    #[default]
+    Cl100k,
+    /// OpenAI o200k_base (GPT-4o)
+    O200k,
+    /// Llama (SentencePiece / Llama 3 BPE)
+    Llama,
+}
+
+impl Tokenizer {
+    /// All supported tokenizers
+    pub const ALL: [Tokenizer; 3] = [Tokenizer::Cl100k, Tokenizer::O200k, Tokenizer::Llama];
+
+    /// Short name used on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tokenizer::Cl100k => "cl100k",
+            Tokenizer::O200k => "o200k",
+            Tokenizer::Llama => "llama",
+        }
+    }
+
+    /// File name looked up by [`TokenCounter::from_dir`]
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Tokenizer::Cl100k => "cl100k_base.json",
+            Tokenizer::O200k => "o200k_base.json",
+            Tokenizer::Llama => "llama.json",
+        }
+    }
+}
+
+impl fmt::Display for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Tokenizer {
+    type Err = CxpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "cl100k" | "cl100k_base" => Ok(Tokenizer::Cl100k),
+            "o200k" | "o200k_base" => Ok(Tokenizer::O200k),
+            "llama" => Ok(Tokenizer::Llama),
+            other => Err(CxpError::Tokenizer(format!(
+                "Unknown tokenizer '{}'. Use: cl100k, o200k, llama",
+                other
+            ))),
+        }
+    }
+}
+
+/// Exact token counter backed by HuggingFace tokenizers
+#[cfg(feature = "tokenizer")]
+#[derive(Default)]
+pub struct TokenCounter {
+    vocabularies: std::collections::HashMap<Tokenizer, tokenizers::Tokenizer>,
+}
+
+#[cfg(feature = "tokenizer")]
+impl TokenCounter {
+    /// Create a counter without any loaded vocabulary
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every known vocabulary file present in `dir` (see [`Tokenizer::file_name`])
+    pub fn from_dir<P: AsRef<std::path::Path>>(dir: P) -> Result<Self> {
+        let mut counter = Self::new();
+        for tokenizer in Tokenizer::ALL {
+            let path = dir.as_ref().join(tokenizer.file_name());
+            if path.exists() {
+                counter.load(tokenizer, &path)?;
+            }
+        }
+        Ok(counter)
+    }
+
+    /// Load the vocabulary for `tokenizer` from a `tokenizer.json` file
+    pub fn load<P: AsRef<std::path::Path>>(&mut self, tokenizer: Tokenizer, path: P) -> Result<&mut Self> {
+        let vocabulary = tokenizers::Tokenizer::from_file(path.as_ref()).map_err(|e| {
+            CxpError::Tokenizer(format!(
+                "Failed to load {} from {}: {}",
+                tokenizer,
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        self.vocabularies.insert(tokenizer, vocabulary);
+        Ok(self)
+    }
+
+    /// Check whether the vocabulary for `tokenizer` is loaded
+    pub fn has(&self, tokenizer: Tokenizer) -> bool {
+        self.vocabularies.contains_key(&tokenizer)
+    }
+
+    /// Count the tokens of `text` (without special tokens)
+    pub fn count(&self, text: &str, tokenizer: Tokenizer) -> Result<u64> {
+        let vocabulary = self.vocabularies.get(&tokenizer).ok_or_else(|| {
+            CxpError::Tokenizer(format!("Vocabulary for {} is not loaded", tokenizer))
+        })?;
+
+        let encoding = vocabulary
+            .encode(text, false)
+            .map_err(|e| CxpError::Tokenizer(e.to_string()))?;
+
+        Ok(encoding.len() as u64)
+    }
+
+    /// Tokenizer-accurate savings of an archive
+    ///
+    /// Original tokens cover every text file in full; CXP tokens cover each
+    /// unique chunk once. Binary (non UTF-8) files are skipped.
+    pub fn token_savings(&self, reader: &crate::CxpReader, tokenizer: Tokenizer) -> Result<TokenSavings> {
+        let mut paths = reader.file_paths();
+        paths.sort_unstable();
+
+        let mut original_tokens = 0u64;
+        let mut cxp_tokens = 0u64;
+        let mut seen = std::collections::HashSet::new();
+
+        for path in paths {
+            let content = reader.read_file(path)?;
+            let Ok(text) = std::str::from_utf8(&content) else {
+                continue;
+            };
+            original_tokens += self.count(text, tokenizer)?;
+
+            let Some(entry) = reader.file_entry(path)? else {
+                continue;
+            };
+            for chunk in &entry.chunks {
+                if seen.insert(chunk.hash.as_str()) {
+                    let offset = chunk.offset.min(content.len());
+                    let end = (chunk.offset + chunk.length).min(content.len());
+                    let chunk_text = String::from_utf8_lossy(&content[offset..end]);
+                    cxp_tokens += self.count(&chunk_text, tokenizer)?;
+                }
+            }
+        }
+
+        let savings_tokens = original_tokens.saturating_sub(cxp_tokens);
+        let savings_percent = if original_tokens > 0 {
+            (savings_tokens as f64 / original_tokens as f64 * 100.0).clamp(0.0, 100.0)
+        } else {
+            0.0
+        };
+
+        Ok(TokenSavings {
+            original_tokens,
+            cxp_tokens,
+            savings_percent,
+            savings_tokens,
+        })
+    }
+}
+
 /// Format bytes as human-readable size
 pub fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -258,6 +427,46 @@ mod tests {
         assert_eq!(format_bytes(10_485_760), "10.00 MB");
     }
 
+    #[test]
+    fn test_tokenizer_parse() {
+        assert_eq!("cl100k".parse::<Tokenizer>().unwrap(), Tokenizer::Cl100k);
+        assert_eq!("O200K_BASE".parse::<Tokenizer>().unwrap(), Tokenizer::O200k);
+        assert_eq!("llama".parse::<Tokenizer>().unwrap(), Tokenizer::Llama);
+        assert!("gpt2".parse::<Tokenizer>().is_err());
+
+        for tokenizer in Tokenizer::ALL {
+            assert_eq!(tokenizer.name().parse::<Tokenizer>().unwrap(), tokenizer);
+        }
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[test]
+    fn test_token_counter() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let vocabulary = r#"{
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": [],
+            "normalizer": null,
+            "pre_tokenizer": {"type": "Whitespace"},
+            "post_processor": null,
+            "decoder": null,
+            "model": {"type": "WordLevel", "vocab": {"hello": 0, "world": 1, "[UNK]": 2}, "unk_token": "[UNK]"}
+        }"#;
+        std::fs::write(temp_dir.path().join(Tokenizer::Cl100k.file_name()), vocabulary).unwrap();
+
+        let counter = TokenCounter::from_dir(temp_dir.path()).unwrap();
+        assert!(counter.has(Tokenizer::Cl100k));
+        assert!(!counter.has(Tokenizer::Llama));
+
+        assert_eq!(counter.count("hello world hello", Tokenizer::Cl100k).unwrap(), 3);
+        assert_eq!(counter.count("", Tokenizer::Cl100k).unwrap(), 0);
+        assert!(counter.count("hello", Tokenizer::Llama).is_err());
+    }
+
     #[test]
     fn test_format_tokens() {
         assert_eq!(format_tokens(500), "500");
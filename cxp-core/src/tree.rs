@@ -0,0 +1,147 @@
+//! Directory Tree
+//!
+//! The file map is a flat list of paths. [`DirTree`] folds it into the
+//! directory hierarchy with rollups per directory (files and bytes in the
+//! directory and everything below it), for `cxp tree` and
+//! `CxpReader::tree()`.
+
+use crate::format::FileMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A file directly inside a [`DirTree`] directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeFile {
+    /// File name (last path component)
+    pub name: String,
+    /// Original size in bytes
+    pub size: u64,
+}
+
+/// A directory of the archive with its subdirectories and files
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirTree {
+    /// Directory name ("" for the archive root)
+    pub name: String,
+    /// Path from the archive root ("" for the root)
+    pub path: String,
+    /// Subdirectories, by name
+    pub dirs: Vec<DirTree>,
+    /// Files directly in this directory, by name
+    pub files: Vec<TreeFile>,
+    /// Files in this directory and below
+    pub file_count: usize,
+    /// Original bytes of those files
+    pub total_size: u64,
+}
+
+impl DirTree {
+    /// Build the tree of all files in a file map
+    pub fn from_file_map(file_map: &FileMap) -> Self {
+        let mut root = Builder::default();
+        for (path, entry) in &file_map.files {
+            let mut dir = &mut root;
+            let mut components = path.split('/').peekable();
+            while let Some(component) = components.next() {
+                dir.file_count += 1;
+                dir.total_size += entry.size;
+                if components.peek().is_none() {
+                    dir.files.insert(component.to_string(), entry.size);
+                } else {
+                    dir = dir.dirs.entry(component.to_string()).or_default();
+                }
+            }
+        }
+        root.finish(String::new(), String::new())
+    }
+
+    /// The directory at `path` ("" or "/" for the root)
+    pub fn find(&self, path: &str) -> Option<&DirTree> {
+        path.split('/')
+            .filter(|component| !component.is_empty())
+            .try_fold(self, |dir, component| dir.dirs.iter().find(|d| d.name == component))
+    }
+
+    /// Number of directories below this one
+    pub fn dir_count(&self) -> usize {
+        self.dirs.iter().map(|dir| 1 + dir.dir_count()).sum()
+    }
+}
+
+/// Directory while files are added (maps keep names sorted)
+#[derive(Default)]
+struct Builder {
+    dirs: BTreeMap<String, Builder>,
+    files: BTreeMap<String, u64>,
+    file_count: usize,
+    total_size: u64,
+}
+
+impl Builder {
+    fn finish(self, name: String, path: String) -> DirTree {
+        let dirs = self
+            .dirs
+            .into_iter()
+            .map(|(name, dir)| {
+                let child = if path.is_empty() { name.clone() } else { format!("{}/{}", path, name) };
+                dir.finish(name, child)
+            })
+            .collect();
+        DirTree {
+            name,
+            path,
+            dirs,
+            files: self.files.into_iter().map(|(name, size)| TreeFile { name, size }).collect(),
+            file_count: self.file_count,
+            total_size: self.total_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::FileEntry;
+
+    fn file(path: &str, size: u64) -> FileEntry {
+        FileEntry {
+            path: path.to_string(),
+            extension: path.rsplit('.').next().unwrap_or_default().to_string(),
+            size,
+            chunks: Vec::new(),
+            is_image: false,
+            modified: None,
+            provenance: None,
+            keywords: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_tree_rolls_up_counts_and_sizes() {
+        let mut file_map = FileMap::default();
+        for entry in [
+            file("README.md", 100),
+            file("src/main.rs", 200),
+            file("src/lib.rs", 300),
+            file("src/util/io.rs", 400),
+            file("docs/guide.md", 50),
+        ] {
+            file_map.files.insert(entry.path.clone(), entry);
+        }
+
+        let tree = DirTree::from_file_map(&file_map);
+        assert_eq!((tree.file_count, tree.total_size), (5, 1050));
+        assert_eq!(tree.dir_count(), 3);
+        assert_eq!(tree.dirs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["docs", "src"]);
+        assert_eq!(tree.files, vec![TreeFile { name: "README.md".to_string(), size: 100 }]);
+
+        let src = tree.find("src").unwrap();
+        assert_eq!((src.file_count, src.total_size), (3, 900));
+        assert_eq!(src.files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["lib.rs", "main.rs"]);
+
+        let util = tree.find("/src/util/").unwrap();
+        assert_eq!((util.path.as_str(), util.file_count, util.total_size), ("src/util", 1, 400));
+        assert!(tree.find("src/missing").is_none());
+        assert_eq!(tree.find("").unwrap(), &tree);
+    }
+}
@@ -19,7 +19,7 @@ use crate::{CxpError, Result};
 use crate::index::{HnswIndex, HnswConfig, SearchResult};
 
 #[cfg(all(feature = "search", feature = "multimodal"))]
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 #[cfg(all(feature = "search", feature = "multimodal"))]
 use std::path::Path;
@@ -121,7 +121,7 @@ pub struct UnifiedIndex {
     /// Underlying HNSW index
     hnsw: HnswIndex,
     /// Metadata for each entry
-    metadata: HashMap<u64, EntryType>,
+    metadata: BTreeMap<u64, EntryType>,
 }
 
 #[cfg(all(feature = "search", feature = "multimodal"))]
@@ -133,7 +133,7 @@ impl UnifiedIndex {
     /// - `HnswConfig::multimodal_binary()` for best compression (64 bytes)
     pub fn new(config: HnswConfig) -> Result<Self> {
         let hnsw = HnswIndex::new(config)?;
-        let metadata = HashMap::new();
+        let metadata = BTreeMap::new();
 
         Ok(Self { hnsw, metadata })
     }
@@ -362,7 +362,26 @@ impl UnifiedIndex {
         let meta_path = base_path.with_extension("meta");
         let meta_json = std::fs::read_to_string(&meta_path)
             .map_err(|e| CxpError::Search(format!("Failed to read metadata: {}", e)))?;
-        let metadata: HashMap<u64, EntryType> = serde_json::from_str(&meta_json)
+        let metadata: BTreeMap<u64, EntryType> = serde_json::from_str(&meta_json)
+            .map_err(|e| CxpError::Search(format!("Failed to deserialize metadata: {}", e)))?;
+
+        Ok(Self { hnsw, metadata })
+    }
+
+    /// Serialize index and metadata to the bytes of the `.index` and `.meta` files
+    ///
+    /// Needs no filesystem, unlike [`save`](Self::save).
+    pub fn to_bytes(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        let index_data = self.hnsw.to_bytes()?;
+        let meta_data = serde_json::to_vec(&self.metadata)
+            .map_err(|e| CxpError::Search(format!("Failed to serialize metadata: {}", e)))?;
+        Ok((index_data, meta_data))
+    }
+
+    /// Load index and metadata from the bytes of the `.index` and `.meta` files
+    pub fn from_bytes(index_data: &[u8], meta_data: &[u8], config: HnswConfig) -> Result<Self> {
+        let hnsw = HnswIndex::from_bytes(index_data, config)?;
+        let metadata: BTreeMap<u64, EntryType> = serde_json::from_slice(meta_data)
             .map_err(|e| CxpError::Search(format!("Failed to deserialize metadata: {}", e)))?;
 
         Ok(Self { hnsw, metadata })
@@ -577,6 +596,24 @@ mod tests {
         assert!(similarity > 0.99, "Similarity was {}", similarity);
     }
 
+    #[test]
+    fn test_bytes_roundtrip() {
+        let config = HnswConfig::multimodal_float32();
+        let mut index = UnifiedIndex::new(config.clone()).unwrap();
+        index.add_text(1, &create_test_embedding(1.0), 42, "doc.txt").unwrap();
+        index.add_image(2, &create_test_embedding(2.0), "photo.jpg").unwrap();
+
+        let (index_data, meta_data) = index.to_bytes().unwrap();
+        let loaded = UnifiedIndex::from_bytes(&index_data, &meta_data, config).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.text_count(), 1);
+        assert_eq!(loaded.image_count(), 1);
+        let results = loaded.search(&create_test_embedding(2.0), 1).unwrap();
+        assert_eq!(results[0].id, 2);
+        assert!(results[0].entry_type.is_image());
+    }
+
     #[test]
     fn test_entry_type_helpers() {
         let text = EntryType::Text {
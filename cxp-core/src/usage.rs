@@ -0,0 +1,321 @@
+//! Usage Metrics
+//!
+//! Opt-in, anonymous usage counters for archive consumers. Reader hosts
+//! record how many queries they ran, how many returned results and which
+//! files were returned or read. The counters are kept in a sidecar next to
+//! the archive and are never transmitted anywhere:
+//!
+//! ```text
+//! project.cxp
+//! project.usage.msgpack   # concatenated MessagePack UsageMetrics
+//! ```
+//!
+//! Like the access log, the sidecar is append-only: every flush appends the
+//! counters it buffered, and reading adds all of them up. Hosts sharing a
+//! sidecar never lose each other's counters; `compact()` folds the records
+//! into one.
+//!
+//! Only aggregates are stored (no query text, no per-event timestamps), so
+//! maintainers can see which content is actually used when curating the
+//! next build. The metrics can be attached to a build as the `usage`
+//! extension.
+
+use crate::access_log::{append_record, read_records, rewrite_records};
+use crate::extensions::Extension;
+use crate::{CxpError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Extension of the usage sidecar (replaces the archive's `.cxp`)
+pub const USAGE_EXTENSION: &str = "usage.msgpack";
+
+/// Extension namespace of embedded usage metrics
+pub const USAGE_NAMESPACE: &str = "usage";
+
+/// Data key of the metrics inside the `usage` extension
+pub const USAGE_KEY: &str = "metrics.msgpack";
+
+/// Version of the usage metrics format
+pub const USAGE_VERSION: &str = "1.0.0";
+
+/// Aggregate usage counters of an archive
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageMetrics {
+    /// First recorded activity
+    pub since: Option<DateTime<Utc>>,
+    /// Latest recorded activity
+    pub until: Option<DateTime<Utc>>,
+    /// Number of queries
+    pub queries: u64,
+    /// Queries that returned at least one result
+    pub queries_with_results: u64,
+    /// How often each file was returned as a query result
+    pub result_counts: BTreeMap<String, u64>,
+    /// How often each file was read
+    pub read_counts: BTreeMap<String, u64>,
+}
+
+/// Usage of a single file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileUsage {
+    /// File path
+    pub path: String,
+    /// Times returned as a query result
+    pub results: u64,
+    /// Times read
+    pub reads: u64,
+}
+
+impl UsageMetrics {
+    /// Share of queries that returned at least one result
+    pub fn hit_rate(&self) -> f64 {
+        if self.queries > 0 {
+            self.queries_with_results as f64 / self.queries as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Whether nothing has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.queries == 0 && self.read_counts.is_empty()
+    }
+
+    /// Record a query and the files it returned
+    pub fn record_query<S: AsRef<str>>(&mut self, result_paths: &[S]) {
+        self.touch();
+        self.queries += 1;
+        if !result_paths.is_empty() {
+            self.queries_with_results += 1;
+        }
+        for path in result_paths {
+            *self.result_counts.entry(path.as_ref().to_string()).or_insert(0) += 1;
+        }
+    }
+
+    /// Record a read of `path`
+    pub fn record_read(&mut self, path: &str) {
+        self.touch();
+        *self.read_counts.entry(path.to_string()).or_insert(0) += 1;
+    }
+
+    /// Add the counters of `other`
+    pub fn merge(&mut self, other: &UsageMetrics) {
+        self.since = match (self.since, other.since) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.until = self.until.max(other.until);
+        self.queries += other.queries;
+        self.queries_with_results += other.queries_with_results;
+        for (path, count) in &other.result_counts {
+            *self.result_counts.entry(path.clone()).or_insert(0) += count;
+        }
+        for (path, count) in &other.read_counts {
+            *self.read_counts.entry(path.clone()).or_insert(0) += count;
+        }
+    }
+
+    /// Most used files (results + reads), most used first
+    pub fn top_files(&self, limit: usize) -> Vec<FileUsage> {
+        let mut files: HashMap<&str, FileUsage> = HashMap::new();
+        for path in self.result_counts.keys().chain(self.read_counts.keys()) {
+            files.entry(path).or_insert_with(|| FileUsage {
+                path: path.clone(),
+                results: self.result_counts.get(path).copied().unwrap_or(0),
+                reads: self.read_counts.get(path).copied().unwrap_or(0),
+            });
+        }
+
+        let mut files: Vec<FileUsage> = files.into_values().collect();
+        files.sort_by(|a, b| {
+            (b.results + b.reads)
+                .cmp(&(a.results + a.reads))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        files.truncate(limit);
+        files
+    }
+
+    /// Serialize for the `usage` extension
+    pub fn to_extension_data(&self) -> Result<HashMap<String, Vec<u8>>> {
+        let data = rmp_serde::to_vec(self).map_err(|e| CxpError::Serialization(e.to_string()))?;
+        Ok(HashMap::from([(USAGE_KEY.to_string(), data)]))
+    }
+
+    fn touch(&mut self) {
+        let now = Utc::now();
+        self.since.get_or_insert(now);
+        self.until = Some(now);
+    }
+}
+
+/// Extension marker used to embed usage metrics into a build
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UsageExtension;
+
+impl Extension for UsageExtension {
+    fn namespace(&self) -> &str {
+        USAGE_NAMESPACE
+    }
+
+    fn version(&self) -> &str {
+        USAGE_VERSION
+    }
+}
+
+/// Records usage into an archive's sidecar
+///
+/// Counters are buffered in memory and appended to the sidecar on `flush()`
+/// and when the recorder is dropped, so several hosts can share a sidecar.
+#[derive(Debug)]
+pub struct UsageRecorder {
+    /// Sidecar file
+    path: PathBuf,
+    /// Counters not yet written to the sidecar
+    pending: Mutex<UsageMetrics>,
+}
+
+impl UsageRecorder {
+    /// Recorder writing next to `archive` (`<name>.usage.msgpack`)
+    pub fn for_archive<P: AsRef<Path>>(archive: P) -> Self {
+        Self::at(archive.as_ref().with_extension(USAGE_EXTENSION))
+    }
+
+    /// Recorder writing to an explicit path
+    pub fn at<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            pending: Mutex::new(UsageMetrics::default()),
+        }
+    }
+
+    /// Sidecar file path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record a query and the files it returned
+    pub fn record_query<S: AsRef<str>>(&self, result_paths: &[S]) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.record_query(result_paths);
+        }
+    }
+
+    /// Record a read of `path`
+    pub fn record_read(&self, path: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.record_read(path);
+        }
+    }
+
+    /// Add the buffered counters to the sidecar
+    pub fn flush(&self) -> Result<()> {
+        let mut pending = self.pending.lock().map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        append_record(&self.path, &*pending)?;
+        *pending = UsageMetrics::default();
+        Ok(())
+    }
+
+    /// Fold the sidecar into a single record
+    pub fn compact(&self) -> Result<()> {
+        let metrics = self.load()?;
+        if metrics.is_empty() {
+            return Ok(());
+        }
+        rewrite_records(&self.path, &[metrics])
+    }
+
+    /// Recorded metrics, including counters not flushed yet
+    pub fn metrics(&self) -> Result<UsageMetrics> {
+        let mut metrics = self.load()?;
+        let pending = self.pending.lock().map_err(|_| CxpError::io("Lock poisoned".to_string()))?;
+        metrics.merge(&pending);
+        Ok(metrics)
+    }
+
+    /// Delete the sidecar and drop buffered counters
+    pub fn reset(&self) -> Result<()> {
+        *self.pending.lock().map_err(|_| CxpError::io("Lock poisoned".to_string()))? = UsageMetrics::default();
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sum of the metrics stored in the sidecar (empty if it does not exist)
+    fn load(&self) -> Result<UsageMetrics> {
+        let mut metrics = UsageMetrics::default();
+        for record in read_records::<UsageMetrics>(&self.path)? {
+            metrics.merge(&record);
+        }
+        Ok(metrics)
+    }
+}
+
+impl Drop for UsageRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::warn!("Could not write usage metrics to {:?}: {}", self.path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_usage_recorder() {
+        let dir = TempDir::new().unwrap();
+        let archive = dir.path().join("project.cxp");
+        let recorder = UsageRecorder::for_archive(&archive);
+        assert_eq!(recorder.path(), dir.path().join("project.usage.msgpack"));
+
+        recorder.record_query(&["src/main.rs", "README.md"]);
+        recorder.record_query::<&str>(&[]);
+        recorder.record_read("src/main.rs");
+        assert!(!recorder.path().exists());
+        recorder.flush().unwrap();
+
+        // A second host adds to the same sidecar
+        {
+            let other = UsageRecorder::for_archive(&archive);
+            other.record_query(&["src/main.rs"]);
+        }
+
+        let metrics = recorder.metrics().unwrap();
+        assert_eq!(read_records::<UsageMetrics>(recorder.path()).unwrap().len(), 2);
+        assert_eq!(metrics.queries, 3);
+        assert_eq!(metrics.queries_with_results, 2);
+        assert!((metrics.hit_rate() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(metrics.result_counts["src/main.rs"], 2);
+        assert!(metrics.since <= metrics.until);
+
+        let top = metrics.top_files(1);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].path, "src/main.rs");
+        assert_eq!((top[0].results, top[0].reads), (2, 1));
+
+        recorder.compact().unwrap();
+        assert_eq!(read_records::<UsageMetrics>(recorder.path()).unwrap().len(), 1);
+        assert_eq!(recorder.metrics().unwrap(), metrics);
+
+        let data = metrics.to_extension_data().unwrap();
+        let restored: UsageMetrics = rmp_serde::from_slice(&data[USAGE_KEY]).unwrap();
+        assert_eq!(restored, metrics);
+
+        recorder.reset().unwrap();
+        assert!(!recorder.path().exists());
+        assert!(recorder.metrics().unwrap().is_empty());
+    }
+}
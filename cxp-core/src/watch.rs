@@ -0,0 +1,499 @@
+//! Watch Mode - incremental rebuilds on file change
+//!
+//! `WatchService` keeps a `CxpBuilder` in memory, watches the source tree with
+//! `notify` and rewrites the archive after a debounce window whenever files
+//! change. Only changed files are re-chunked and, when embeddings are enabled,
+//! only new chunks are embedded.
+//!
+//! For always-on use, [`WatchConfig::with_low_priority`] runs rebuilds at idle
+//! CPU/IO priority and [`WatchConfig::with_pause_on_battery`] holds them back
+//! while the machine is on battery (see [`crate::priority`]).
+//!
+//! Archives are replaced atomically. With [`WatchConfig::with_journal`]
+//! updates are appended to the archive's journal instead (see
+//! [`crate::journal`]) and the archive is only rewritten every few records;
+//! a service started on an existing archive journals what changed while it
+//! was not running.
+//!
+//! # Example
+//! ```ignore
+//! let service = WatchService::new(CxpBuilder::new("./project"), "project.cxp")
+//!     .with_config(WatchConfig::new().with_debounce(Duration::from_millis(250)));
+//! let handle = service.spawn(|update| println!("{} files", update.total_files))?;
+//! // ...
+//! let service = handle.stop()?;
+//! ```
+
+use crate::journal::journal_path;
+use crate::priority::{self, BatteryMonitor};
+use crate::temp::is_replacement_of;
+use crate::{CxpBuilder, CxpError, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Default debounce window
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// How often the watch loop checks for stop requests
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Watch configuration
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    /// Quiet period after the last event before rebuilding
+    pub debounce: Duration,
+    /// Run rebuilds (chunking, compression, embedding) at idle CPU/IO priority
+    pub low_priority: bool,
+    /// Hold back rebuilds while the machine is on battery
+    pub pause_on_battery: bool,
+    /// Journal updates, rewriting the archive after this many records (None: always rewrite)
+    pub journal: Option<u64>,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            debounce: DEFAULT_DEBOUNCE,
+            low_priority: false,
+            pause_on_battery: false,
+            journal: None,
+        }
+    }
+}
+
+impl WatchConfig {
+    /// Create a config with the default debounce window
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the debounce window
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Run rebuilds at idle CPU/IO priority
+    pub fn with_low_priority(mut self, enabled: bool) -> Self {
+        self.low_priority = enabled;
+        self
+    }
+
+    /// Pause rebuilds while on battery; changes are applied once back on mains power
+    pub fn with_pause_on_battery(mut self, enabled: bool) -> Self {
+        self.pause_on_battery = enabled;
+        self
+    }
+
+    /// Append updates to the archive's journal, rewriting it after `max_records` records
+    pub fn with_journal(mut self, max_records: u64) -> Self {
+        self.journal = Some(max_records.max(1));
+        self
+    }
+}
+
+/// Result of a (re)build
+#[derive(Debug, Clone)]
+pub struct WatchUpdate {
+    /// Number of changed paths applied (0 for the initial build)
+    pub changed_paths: usize,
+    /// Files in the archive after the update
+    pub total_files: usize,
+    /// Unique chunks in the archive after the update
+    pub unique_chunks: usize,
+    /// Time spent updating and writing the archive
+    pub duration: Duration,
+}
+
+/// Keeps a CXP archive in sync with its source directory
+pub struct WatchService {
+    builder: CxpBuilder,
+    output_path: PathBuf,
+    config: WatchConfig,
+    initialized: bool,
+}
+
+impl WatchService {
+    /// Create a service for a configured builder (e.g. with embeddings enabled)
+    pub fn new<P: AsRef<Path>>(builder: CxpBuilder, output_path: P) -> Self {
+        Self {
+            builder,
+            output_path: output_path.as_ref().to_path_buf(),
+            config: WatchConfig::default(),
+            initialized: false,
+        }
+    }
+
+    /// Set the watch configuration
+    pub fn with_config(mut self, config: WatchConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Archive being written
+    pub fn output_path(&self) -> &Path {
+        &self.output_path
+    }
+
+    /// Scan, process and write the full archive
+    ///
+    /// In journal mode an existing archive is not rewritten; the changes since
+    /// it was written are appended to its journal.
+    pub fn build(&mut self) -> Result<WatchUpdate> {
+        let start = Instant::now();
+        self.builder.scan()?.process()?;
+        match self.config.journal {
+            Some(max_records) if self.output_path.exists() => self.journal(max_records)?,
+            _ => self.write()?,
+        }
+        self.initialized = true;
+        Ok(self.update(0, start))
+    }
+
+    /// Apply changed paths and rewrite (or journal) the archive
+    ///
+    /// Paths that no longer exist are removed from the archive.
+    pub fn apply(&mut self, paths: &[PathBuf]) -> Result<WatchUpdate> {
+        if !self.initialized {
+            return self.build();
+        }
+
+        let start = Instant::now();
+        self.builder.update_files(paths)?;
+        match self.config.journal {
+            Some(max_records) => self.journal(max_records)?,
+            None => self.write()?,
+        }
+        Ok(self.update(paths.len(), start))
+    }
+
+    /// Watch the source tree until `stop` is set, rebuilding after each debounce window
+    ///
+    /// Performs the initial build first if it has not happened yet. Failed
+    /// updates are logged and the service keeps watching.
+    pub fn run<F>(&mut self, stop: &AtomicBool, mut on_update: F) -> Result<()>
+    where
+        F: FnMut(&WatchUpdate),
+    {
+        let pool = self.config.low_priority.then(background_pool).flatten();
+        let apply = |service: &mut Self, paths: &[PathBuf]| match pool {
+            Some(ref pool) => pool.install(|| service.apply(paths)),
+            None => service.apply(paths),
+        };
+
+        if !self.initialized {
+            let update = apply(self, &[])?;
+            on_update(&update);
+        }
+
+        let source_dir = self.builder.source_dir().to_path_buf();
+        let root = std::path::absolute(&source_dir)?;
+        let output = std::path::absolute(&self.output_path)?;
+        let journal = journal_path(&output);
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher: RecommendedWatcher =
+            notify::recommended_watcher(tx).map_err(|e| CxpError::Watch(e.to_string()))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| CxpError::Watch(e.to_string()))?;
+
+        tracing::info!("Watching {:?} (debounce {:?})", root, self.config.debounce);
+
+        let mut pending: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut last_event = Instant::now();
+        let mut battery = BatteryMonitor::default();
+        let mut paused = false;
+
+        while !stop.load(Ordering::Relaxed) {
+            match rx.recv_timeout(POLL_INTERVAL.min(self.config.debounce)) {
+                Ok(Ok(event)) => {
+                    if matches!(event.kind, EventKind::Access(_)) {
+                        continue;
+                    }
+                    for path in event.paths {
+                        // Our own writes must not trigger another rebuild
+                        if path == output || path == journal || is_replacement_of(&output, &path) {
+                            continue;
+                        }
+                        if let Ok(relative) = path.strip_prefix(&root) {
+                            pending.insert(source_dir.join(relative));
+                            last_event = Instant::now();
+                        }
+                    }
+                }
+                Ok(Err(e)) => tracing::warn!("Watch error: {}", e),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            if !pending.is_empty() && last_event.elapsed() >= self.config.debounce {
+                if self.config.pause_on_battery && battery.on_battery() {
+                    if !paused {
+                        tracing::info!("On battery power, pausing rebuilds ({} pending paths)", pending.len());
+                        paused = true;
+                    }
+                    continue;
+                }
+                if paused {
+                    tracing::info!("Back on mains power, resuming rebuilds");
+                    paused = false;
+                }
+
+                let paths: Vec<PathBuf> = std::mem::take(&mut pending).into_iter().collect();
+                match apply(self, &paths) {
+                    Ok(update) => on_update(&update),
+                    Err(e) => tracing::warn!("Incremental rebuild failed: {}", e),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run the watch loop on a background thread
+    pub fn spawn<F>(mut self, on_update: F) -> Result<WatchHandle>
+    where
+        F: FnMut(&WatchUpdate) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        let thread = std::thread::Builder::new()
+            .name("cxp-watch".to_string())
+            .spawn(move || {
+                self.run(&thread_stop, on_update)?;
+                Ok(self)
+            })?;
+
+        Ok(WatchHandle { stop, thread })
+    }
+
+    /// Write the archive to a temporary file and move it into place
+    fn write(&mut self) -> Result<()> {
+        self.builder.with_atomic_write(true).build(&self.output_path)
+    }
+
+    /// Append to the journal, rewriting the archive once it holds `max_records` records
+    fn journal(&mut self, max_records: u64) -> Result<()> {
+        self.builder.append_journal(&self.output_path)?;
+        if self.builder.journal_records() >= max_records {
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    fn update(&self, changed_paths: usize, start: Instant) -> WatchUpdate {
+        let stats = &self.builder.manifest().stats;
+        WatchUpdate {
+            changed_paths,
+            total_files: stats.total_files,
+            unique_chunks: stats.unique_chunks,
+            duration: start.elapsed(),
+        }
+    }
+}
+
+/// Handle to a background watch thread
+pub struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<Result<WatchService>>,
+}
+
+impl WatchHandle {
+    /// Stop watching and return the service
+    pub fn stop(self) -> Result<WatchService> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread
+            .join()
+            .map_err(|_| CxpError::Watch("Watch thread panicked".to_string()))?
+    }
+}
+
+/// Worker pool for rebuilds at idle priority; also lowers the calling thread
+///
+/// Returns None (rebuilds run at normal priority on the global pool) when the
+/// platform does not support it.
+fn background_pool() -> Option<rayon::ThreadPool> {
+    if let Err(e) = priority::lower_current_thread() {
+        tracing::warn!("Rebuilding at normal priority: {}", e);
+        return None;
+    }
+
+    rayon::ThreadPoolBuilder::new()
+        .thread_name(|i| format!("cxp-watch-{}", i))
+        .start_handler(|_| {
+            if let Err(e) = priority::lower_current_thread() {
+                tracing::debug!("Could not lower worker priority: {}", e);
+            }
+        })
+        .build()
+        .inspect_err(|e| tracing::warn!("Rebuilding on the global pool: {}", e))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CxpReader;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_watch_apply() {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(source.path().join("b.rs"), "fn b() {}\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("watch.cxp");
+        let mut service = WatchService::new(CxpBuilder::new(source.path()), &cxp_path);
+
+        let update = service.build().unwrap();
+        assert_eq!(update.total_files, 2);
+
+        fs::write(source.path().join("a.rs"), "fn a_changed() {}\n").unwrap();
+        fs::remove_file(source.path().join("b.rs")).unwrap();
+        fs::write(source.path().join("c.md"), "# New\n").unwrap();
+
+        let changed = ["a.rs", "b.rs", "c.md"].map(|p| source.path().join(p));
+        let update = service.apply(&changed).unwrap();
+        assert_eq!(update.changed_paths, 3);
+        assert_eq!(update.total_files, 2);
+        assert_eq!(update.unique_chunks, 2);
+
+        let reader = CxpReader::open(&cxp_path).unwrap();
+        assert_eq!(reader.read_file("a.rs").unwrap(), b"fn a_changed() {}\n");
+        assert!(!reader.contains_file("b.rs").unwrap());
+        assert_eq!(reader.read_file("c.md").unwrap(), b"# New\n");
+        let leftovers = fs::read_dir(output.path()).unwrap().filter(|e| is_replacement_of(&cxp_path, &e.as_ref().unwrap().path()));
+        assert_eq!(leftovers.count(), 0);
+    }
+
+    #[test]
+    fn test_watch_apply_keeps_filtered_files_out() {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("a.rs"), "fn a() {}\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("watch.cxp");
+        let mut builder = CxpBuilder::new(source.path());
+        builder.with_scan_filter(
+            crate::ScanFilter::new()
+                .with_exclude(["node_modules/**"])
+                .with_max_file_size(64)
+                .with_skip_hidden(true),
+        );
+        let mut service = WatchService::new(builder, &cxp_path);
+        assert_eq!(service.build().unwrap().total_files, 1);
+
+        fs::create_dir_all(source.path().join("node_modules/dep")).unwrap();
+        fs::write(source.path().join("node_modules/dep/index.js"), "module.exports = 1;\n").unwrap();
+        fs::create_dir_all(source.path().join(".cache")).unwrap();
+        fs::write(source.path().join(".cache/notes.md"), "# Cached\n").unwrap();
+        fs::write(source.path().join("big.txt"), "x".repeat(100)).unwrap();
+        fs::write(source.path().join("b.rs"), "fn b() {}\n").unwrap();
+
+        let changed = ["node_modules", "node_modules/dep/index.js", ".cache/notes.md", "big.txt", "b.rs"]
+            .map(|p| source.path().join(p));
+        assert_eq!(service.apply(&changed).unwrap().total_files, 2);
+
+        let reader = CxpReader::open(&cxp_path).unwrap();
+        let mut paths = reader.file_paths();
+        paths.sort_unstable();
+        assert_eq!(paths, vec!["a.rs", "b.rs"]);
+    }
+
+    #[test]
+    fn test_watch_journal() {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("a.rs"), "fn a() {}\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("watch.cxp");
+        let mut service = WatchService::new(CxpBuilder::new(source.path()), &cxp_path)
+            .with_config(WatchConfig::new().with_journal(3));
+        service.build().unwrap();
+        let built = fs::read(&cxp_path).unwrap();
+
+        for (i, body) in ["fn a1() {}\n", "fn a2() {}\n"].iter().enumerate() {
+            fs::write(source.path().join("a.rs"), body).unwrap();
+            service.apply(&[source.path().join("a.rs")]).unwrap();
+            assert_eq!(fs::read(&cxp_path).unwrap(), built);
+            assert_eq!(CxpReader::open(&cxp_path).unwrap().journal_records(), i + 1);
+        }
+
+        // The third journaled update rewrites the archive
+        fs::write(source.path().join("a.rs"), "fn a3() {}\n").unwrap();
+        service.apply(&[source.path().join("a.rs")]).unwrap();
+        assert!(!journal_path(&cxp_path).exists());
+        assert_eq!(CxpReader::open(&cxp_path).unwrap().read_file("a.rs").unwrap(), b"fn a3() {}\n");
+
+        // A restarted service journals what changed while it was not running
+        fs::write(source.path().join("a.rs"), "fn a4() {}\n").unwrap();
+        let built = fs::read(&cxp_path).unwrap();
+        let mut service = WatchService::new(CxpBuilder::new(source.path()), &cxp_path)
+            .with_config(WatchConfig::new().with_journal(3));
+        service.build().unwrap();
+        assert_eq!(fs::read(&cxp_path).unwrap(), built);
+        let reader = CxpReader::open(&cxp_path).unwrap();
+        assert_eq!(reader.journal_records(), 1);
+        assert_eq!(reader.read_file("a.rs").unwrap(), b"fn a4() {}\n");
+    }
+
+    #[test]
+    fn test_watch_spawn_rebuilds_on_change() {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("a.rs"), "fn a() {}\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("watch.cxp");
+        let service = WatchService::new(CxpBuilder::new(source.path()), &cxp_path)
+            .with_config(WatchConfig::new().with_debounce(Duration::from_millis(50)));
+
+        let (tx, rx) = mpsc::channel();
+        let handle = service.spawn(move |update| tx.send(update.clone()).unwrap()).unwrap();
+
+        let initial = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(initial.total_files, 1);
+
+        // Give the watcher a moment to register before changing files
+        std::thread::sleep(Duration::from_millis(200));
+        fs::write(source.path().join("b.rs"), "fn b() {}\n").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut total_files = initial.total_files;
+        while total_files < 2 && Instant::now() < deadline {
+            if let Ok(update) = rx.recv_timeout(Duration::from_millis(500)) {
+                total_files = update.total_files;
+            }
+        }
+        assert_eq!(total_files, 2);
+
+        let service = handle.stop().unwrap();
+        let reader = CxpReader::open(service.output_path()).unwrap();
+        assert_eq!(reader.read_file("b.rs").unwrap(), b"fn b() {}\n");
+    }
+
+    #[test]
+    fn test_watch_low_priority() {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("a.rs"), "fn a() {}\n").unwrap();
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("watch.cxp");
+        let config = WatchConfig::new().with_low_priority(true).with_pause_on_battery(true);
+        let service = WatchService::new(CxpBuilder::new(source.path()), &cxp_path).with_config(config);
+
+        let (tx, rx) = mpsc::channel();
+        let handle = service.spawn(move |update| tx.send(update.clone()).unwrap()).unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)).unwrap().total_files, 1);
+        assert!(handle.stop().unwrap().output_path().exists());
+    }
+}
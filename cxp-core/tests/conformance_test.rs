@@ -0,0 +1,218 @@
+//! Format conformance tests and golden fixtures
+//!
+//! `tests/golden/*.cxp` are reference archives written by this crate. They are
+//! committed so alternative implementations can test their readers against
+//! them (and their writers with `cxp conformance`). Each fixture must pass the
+//! conformance checker and read back the inputs below.
+//!
+//! Regenerate the fixtures after an intentional format change with
+//! `CXP_UPDATE_GOLDEN=1 cargo test --test conformance_test`.
+
+use cxp_core::format_spec::{check_archive, check_file, CheckStatus};
+use cxp_core::{CxpBuilder, CxpReader, Extension, Result};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Files archived in every fixture
+const FILES: &[(&str, &str)] = &[
+    ("README.md", "# Golden\n\nReference archive for CXP readers.\n"),
+    ("src/main.rs", "fn main() {\n    println!(\"Hello, world!\");\n}\n"),
+    ("src/lib.rs", "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n"),
+    ("src/copy.rs", "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n"),
+    ("data/config.json", "{\"name\": \"golden\", \"value\": 42}\n"),
+];
+
+/// Files archived in the child of the `children` fixture
+const CHILD_FILES: &[(&str, &str)] = &[("notes.txt", "Child archive notes.\n")];
+
+/// Extension stored in the `extension` fixture
+#[derive(Clone)]
+struct GoldenExtension;
+
+impl Extension for GoldenExtension {
+    fn namespace(&self) -> &str {
+        "golden"
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.cxp", name))
+}
+
+fn write_files(dir: &Path, files: &[(&str, &str)]) -> Result<()> {
+    for (path, content) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+/// Build an archive of `files` into `out`, letting `configure` adjust the builder
+fn build_archive(files: &[(&str, &str)], out: &Path, configure: impl FnOnce(&mut CxpBuilder) -> Result<()>) -> Result<()> {
+    let source = TempDir::new()?;
+    write_files(source.path(), files)?;
+
+    let mut builder = CxpBuilder::new(source.path());
+    builder.with_build_info(false);
+    builder.scan()?;
+    configure(&mut builder)?;
+    builder.process()?;
+    builder.build(out)
+}
+
+/// Build the named fixture
+fn build_fixture(name: &str, out: &Path) -> Result<()> {
+    match name {
+        "basic" => build_archive(FILES, out, |_| Ok(())),
+        "sharded" => build_archive(FILES, out, |builder| {
+            builder.with_shard_size(2);
+            Ok(())
+        }),
+        "children" => {
+            let temp = TempDir::new()?;
+            let child = temp.path().join("child.cxp");
+            build_archive(CHILD_FILES, &child, |_| Ok(()))?;
+            build_archive(FILES, out, |builder| {
+                builder.embed_child("docs", &child)?;
+                Ok(())
+            })
+        }
+        "extension" => build_archive(FILES, out, |builder| {
+            let data = HashMap::from([("data.bin".to_string(), b"golden extension data".to_vec())]);
+            builder.add_extension(&GoldenExtension, data)?;
+            Ok(())
+        }),
+        _ => unreachable!("unknown fixture {}", name),
+    }
+}
+
+fn assert_conformant(path: &Path) {
+    let report = check_file(path).unwrap();
+    assert!(report.is_conformant(), "{:?} failed: {:?}", path, report.failures());
+    assert!(report.checks.iter().all(|c| c.status == CheckStatus::Pass), "{:?}: {:?}", path, report.checks);
+}
+
+fn assert_contents(reader: &CxpReader, files: &[(&str, &str)]) {
+    assert_eq!(reader.file_paths().len(), files.len());
+    for (path, content) in files {
+        assert_eq!(reader.read_file(path).unwrap(), content.as_bytes(), "{}", path);
+    }
+}
+
+#[test]
+fn test_golden_fixtures() -> Result<()> {
+    let update = std::env::var_os("CXP_UPDATE_GOLDEN").is_some();
+
+    for name in ["basic", "sharded", "children", "extension"] {
+        let path = golden_path(name);
+        if update {
+            fs::create_dir_all(path.parent().unwrap())?;
+            build_fixture(name, &path)?;
+        }
+
+        assert_conformant(&path);
+
+        let reader = CxpReader::open(&path)?;
+        // Fixtures stay readable across minor format versions
+        let major = |version: &str| version.split('.').next().unwrap_or_default().to_string();
+        assert_eq!(major(&reader.manifest().version), major(cxp_core::format_spec::SPEC_VERSION));
+        assert_contents(&reader, FILES);
+
+        match name {
+            "children" => assert_contents(&reader.open_child("docs")?, CHILD_FILES),
+            "extension" => {
+                assert_eq!(reader.read_extension("golden", "data.bin")?, b"golden extension data");
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_fresh_archives_conform() -> Result<()> {
+    let temp = TempDir::new()?;
+    for name in ["basic", "sharded", "children", "extension"] {
+        let path = temp.path().join(format!("{}.cxp", name));
+        build_fixture(name, &path)?;
+        assert_conformant(&path);
+    }
+    Ok(())
+}
+
+/// Copy an archive, letting `rewrite` replace or drop entries
+fn rewrite_archive(data: &[u8], rewrite: impl Fn(&str, Vec<u8>) -> Option<(Vec<u8>, CompressionMethod)>) -> Vec<u8> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).unwrap();
+        let name = entry.name().to_string();
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content).unwrap();
+        if let Some((content, method)) = rewrite(&name, content) {
+            writer.start_file(name, FileOptions::<()>::default().compression_method(method)).unwrap();
+            writer.write_all(&content).unwrap();
+        }
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn test_conformance_detects_violations() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.path().join("basic.cxp");
+    build_fixture("basic", &path)?;
+    let data = fs::read(&path)?;
+
+    let failed = |data: Vec<u8>| -> Vec<String> {
+        let report = check_archive(Cursor::new(data)).unwrap();
+        report.failures().iter().map(|c| c.name.clone()).collect()
+    };
+
+    // ZIP-level compression is not allowed
+    let deflated = rewrite_archive(&data, |name, content| {
+        let method = if name == "manifest.msgpack" { CompressionMethod::Deflated } else { CompressionMethod::Stored };
+        Some((content, method))
+    });
+    assert_eq!(failed(deflated), vec!["stored entries"]);
+
+    // A missing chunk breaks files, stats and the TOC
+    let dropped = Cell::new(false);
+    let missing_chunk = rewrite_archive(&data, |name, content| {
+        if name.starts_with("chunks/") && !dropped.replace(true) {
+            return None;
+        }
+        Some((content, CompressionMethod::Stored))
+    });
+    let failures = failed(missing_chunk);
+    assert!(failures.contains(&"chunks".to_string()), "{:?}", failures);
+
+    // Unknown entries are only a warning
+    let extra = {
+        let mut writer = ZipWriter::new_append(Cursor::new(data.clone())).unwrap();
+        writer.start_file("vendor.bin", FileOptions::<()>::default().compression_method(CompressionMethod::Stored)).unwrap();
+        writer.write_all(b"vendor data").unwrap();
+        writer.finish().unwrap().into_inner()
+    };
+    let report = check_archive(Cursor::new(extra)).unwrap();
+    let known = report.checks.iter().find(|c| c.name == "known entries").unwrap();
+    assert_eq!(known.status, CheckStatus::Warn);
+    assert!(known.detail.contains("vendor.bin"));
+
+    // Not a CXP archive at all
+    assert!(check_archive(Cursor::new(b"not a zip".to_vec())).is_err());
+
+    Ok(())
+}
@@ -4,6 +4,7 @@
 //! in CXP files using the extensions mechanism.
 
 #![cfg(feature = "contextai")]
+#![allow(clippy::bool_assert_comparison)]
 
 use cxp_core::contextai::{
     ContextAIExtension, Conversation, ChatMessage, UserHabit, AppSettings, WatchedFolder, DictionaryEntry,
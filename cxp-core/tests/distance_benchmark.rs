@@ -0,0 +1,101 @@
+//! Distance Kernel Benchmark
+//!
+//! Times the SIMD distance kernels (`cxp_core::simd`) against the scalar
+//! loops they replace, at the vector sizes CXP stores: binary embeddings of
+//! 384 and 768 bits, int8 and f32 vectors of 384 and 768 dimensions.
+//!
+//! # Output
+//! Run with `cargo test --release --test distance_benchmark -- --nocapture`
+//! for meaningful timings; debug builds only check that both agree.
+
+use cxp_core::simd::{self, scalar};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Vectors scanned per measurement (like an exact search over that many rows)
+const ROWS: usize = 2_000;
+
+/// Measurements per kernel; the fastest one counts
+const ROUNDS: usize = 5;
+
+/// Fastest of `ROUNDS` runs of `f` over every row
+fn time_per_row<T>(rows: &[T], mut f: impl FnMut(&T) -> f64) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let started = Instant::now();
+        let mut sink = 0.0;
+        for row in rows {
+            sink += f(row);
+        }
+        black_box(sink);
+        best = best.min(started.elapsed());
+    }
+    best / rows.len() as u32
+}
+
+fn report(kernel: &str, dims: usize, scalar: Duration, simd: Duration) {
+    println!(
+        "  {:<10} {:>5}  {:>9.1} ns  {:>9.1} ns  {:>6.2}x",
+        kernel,
+        dims,
+        scalar.as_nanos() as f64,
+        simd.as_nanos() as f64,
+        scalar.as_secs_f64() / simd.as_secs_f64().max(1e-12)
+    );
+}
+
+/// Deterministic pseudo-random bytes
+fn bytes(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn benchmark_distance_kernels() {
+    println!();
+    println!("Distance kernels ({} on this CPU), per vector:", simd::active_kernels());
+    println!("  {:<10} {:>5}  {:>12}  {:>12}  {:>7}", "KERNEL", "DIMS", "SCALAR", "SIMD", "SPEEDUP");
+
+    for dims in [384, 768] {
+        // Hamming over packed bits
+        let query = bytes(0, dims / 8);
+        let rows: Vec<Vec<u8>> = (1..=ROWS as u64).map(|seed| bytes(seed, dims / 8)).collect();
+        for row in &rows {
+            assert_eq!(simd::hamming(&query, row), scalar::hamming(&query, row));
+        }
+        let scalar_time = time_per_row(&rows, |row| scalar::hamming(black_box(&query), row) as f64);
+        let simd_time = time_per_row(&rows, |row| simd::hamming(black_box(&query), row) as f64);
+        report("hamming", dims, scalar_time, simd_time);
+
+        // Int8 dot product
+        let query: Vec<i8> = bytes(0, dims).into_iter().map(|b| b as i8).collect();
+        let rows: Vec<Vec<i8>> = (1..=ROWS as u64)
+            .map(|seed| bytes(seed, dims).into_iter().map(|b| b as i8).collect())
+            .collect();
+        for row in &rows {
+            assert_eq!(simd::dot_i8(&query, row), scalar::dot_i8(&query, row));
+        }
+        let scalar_time = time_per_row(&rows, |row| scalar::dot_i8(black_box(&query), row) as f64);
+        let simd_time = time_per_row(&rows, |row| simd::dot_i8(black_box(&query), row) as f64);
+        report("int8 dot", dims, scalar_time, simd_time);
+
+        // F32 dot product and cosine
+        let query: Vec<f32> = query.iter().map(|&v| v as f32 / 128.0).collect();
+        let rows: Vec<Vec<f32>> = rows.iter().map(|row| row.iter().map(|&v| v as f32 / 128.0).collect()).collect();
+        for row in &rows {
+            assert!((simd::dot_f32(&query, row) - scalar::dot_f32(&query, row)).abs() < 1e-2);
+            assert!((simd::cosine_f32(&query, row) - scalar::cosine_f32(&query, row)).abs() < 1e-4);
+        }
+        let scalar_time = time_per_row(&rows, |row| scalar::dot_f32(black_box(&query), row) as f64);
+        let simd_time = time_per_row(&rows, |row| simd::dot_f32(black_box(&query), row) as f64);
+        report("f32 dot", dims, scalar_time, simd_time);
+        let scalar_time = time_per_row(&rows, |row| scalar::cosine_f32(black_box(&query), row) as f64);
+        let simd_time = time_per_row(&rows, |row| simd::cosine_f32(black_box(&query), row) as f64);
+        report("f32 cosine", dims, scalar_time, simd_time);
+    }
+}
@@ -0,0 +1,75 @@
+//! Conformance tests between the ort and tract embedding engines
+//!
+//! Both engines must produce identical vectors for the same input. These tests
+//! need a real model: set `CXP_TEST_MODEL_DIR` to a directory containing
+//! `model.onnx` and `tokenizer.json` (all-MiniLM-L6-v2) and run with
+//! `cargo test --features embeddings,embeddings-wasm --test engine_conformance_test`.
+
+#[cfg(all(feature = "embeddings", feature = "embeddings-wasm"))]
+mod conformance {
+    use cxp_core::{EmbeddingEngine, EmbeddingModel, Pooling, TractEmbeddingEngine};
+
+    const PROBES: &[&str] = &[
+        "fn main() { println!(\"Hello, world!\"); }",
+        "The quick brown fox jumps over the lazy dog.",
+        "a",
+        "Ein deutscher Satz mit Umlauten: äöü ß",
+    ];
+
+    fn model_dir() -> Option<std::path::PathBuf> {
+        std::env::var_os("CXP_TEST_MODEL_DIR").map(Into::into)
+    }
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let na: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let nb: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+        dot / (na * nb)
+    }
+
+    fn assert_engines_agree(pooling: Pooling, max_length: usize) {
+        let Some(dir) = model_dir() else {
+            eprintln!("CXP_TEST_MODEL_DIR not set, skipping");
+            return;
+        };
+
+        let mut ort = EmbeddingEngine::load(&dir, EmbeddingModel::MiniLM)
+            .unwrap()
+            .with_pooling(pooling)
+            .with_max_length(max_length);
+        let tract = TractEmbeddingEngine::load(&dir, EmbeddingModel::MiniLM)
+            .unwrap()
+            .with_pooling(pooling)
+            .with_max_length(max_length);
+
+        let ort_vectors = ort.embed_batch(PROBES).unwrap();
+        let tract_vectors = tract.embed_batch(PROBES).unwrap();
+
+        assert_eq!(ort_vectors.len(), tract_vectors.len());
+        for (a, b) in ort_vectors.iter().zip(&tract_vectors) {
+            assert_eq!(a.len(), b.len());
+            assert!(cosine(a, b) > 0.9999, "engines diverge: cosine {}", cosine(a, b));
+        }
+
+        // Batched and single embeddings must match as well (padding must not leak)
+        for (probe, batched) in PROBES.iter().zip(&tract_vectors) {
+            let single = tract.embed(probe).unwrap();
+            assert!(cosine(&single, batched) > 0.9999);
+        }
+    }
+
+    #[test]
+    fn test_mean_pooling_parity() {
+        assert_engines_agree(Pooling::Mean, 512);
+    }
+
+    #[test]
+    fn test_cls_pooling_parity() {
+        assert_engines_agree(Pooling::Cls, 512);
+    }
+
+    #[test]
+    fn test_truncation_parity() {
+        assert_engines_agree(Pooling::Mean, 8);
+    }
+}
@@ -5,12 +5,11 @@
 use cxp_core::{CxpBuilder, CxpReader, CxpError, Result};
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
 use tempfile::TempDir;
 
 /// Helper to create a test directory with sample files
 fn create_test_directory() -> Result<TempDir> {
-    let temp_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let temp_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
 
     // Create some test files
     let files = vec![
@@ -64,7 +63,7 @@ fn test_cxp_builder_process() -> Result<()> {
 #[test]
 fn test_cxp_builder_build() -> Result<()> {
     let test_dir = create_test_directory()?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("test.cxp");
 
     let mut builder = CxpBuilder::new(test_dir.path());
@@ -81,7 +80,7 @@ fn test_cxp_builder_build() -> Result<()> {
 fn test_cxp_reader_open() -> Result<()> {
     // Build a CXP file first
     let test_dir = create_test_directory()?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("test.cxp");
 
     let mut builder = CxpBuilder::new(test_dir.path());
@@ -100,7 +99,7 @@ fn test_cxp_reader_open() -> Result<()> {
 #[test]
 fn test_cxp_reader_list_files() -> Result<()> {
     let test_dir = create_test_directory()?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("test.cxp");
 
     let mut builder = CxpBuilder::new(test_dir.path());
@@ -122,7 +121,7 @@ fn test_cxp_reader_list_files() -> Result<()> {
 #[test]
 fn test_cxp_reader_read_file() -> Result<()> {
     let test_dir = create_test_directory()?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("test.cxp");
 
     let mut builder = CxpBuilder::new(test_dir.path());
@@ -143,7 +142,7 @@ fn test_cxp_reader_read_file() -> Result<()> {
 #[test]
 fn test_cxp_reader_extract_and_verify() -> Result<()> {
     let test_dir = create_test_directory()?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("test.cxp");
 
     // Create original content map
@@ -183,7 +182,7 @@ fn test_cxp_reader_extract_and_verify() -> Result<()> {
 #[test]
 fn test_chunking_consistency() -> Result<()> {
     let test_dir = create_test_directory()?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("test.cxp");
 
     let mut builder = CxpBuilder::new(test_dir.path());
@@ -199,7 +198,7 @@ fn test_chunking_consistency() -> Result<()> {
 #[test]
 fn test_deduplication() -> Result<()> {
     // Create a directory with duplicate content
-    let temp_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let temp_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
 
     // Create multiple files with the same content
     let duplicate_content = "This is repeated content that should be deduplicated.\n".repeat(10);
@@ -210,7 +209,7 @@ fn test_deduplication() -> Result<()> {
         file.write_all(duplicate_content.as_bytes())?;
     }
 
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("dedup_test.cxp");
 
     let mut builder = CxpBuilder::new(temp_dir.path());
@@ -229,7 +228,7 @@ fn test_deduplication() -> Result<()> {
 #[test]
 fn test_compression_effectiveness() -> Result<()> {
     let test_dir = create_test_directory()?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("test.cxp");
 
     let mut builder = CxpBuilder::new(test_dir.path());
@@ -252,7 +251,7 @@ fn test_compression_effectiveness() -> Result<()> {
 #[test]
 fn test_file_not_found_error() -> Result<()> {
     let test_dir = create_test_directory()?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("test.cxp");
 
     let mut builder = CxpBuilder::new(test_dir.path());
@@ -276,8 +275,8 @@ fn test_file_not_found_error() -> Result<()> {
 
 #[test]
 fn test_empty_directory() -> Result<()> {
-    let temp_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let temp_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("empty.cxp");
 
     let mut builder = CxpBuilder::new(temp_dir.path());
@@ -292,7 +291,7 @@ fn test_empty_directory() -> Result<()> {
 
 #[test]
 fn test_large_file_chunking() -> Result<()> {
-    let temp_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let temp_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
 
     // Create a large file (>100KB)
     let large_content = "A".repeat(100_000);
@@ -300,7 +299,7 @@ fn test_large_file_chunking() -> Result<()> {
     let mut file = File::create(&file_path)?;
     file.write_all(large_content.as_bytes())?;
 
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("large_test.cxp");
 
     let mut builder = CxpBuilder::new(temp_dir.path());
@@ -326,7 +325,7 @@ fn test_large_file_chunking() -> Result<()> {
 #[test]
 fn test_manifest_file_types() -> Result<()> {
     let test_dir = create_test_directory()?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("test.cxp");
 
     let mut builder = CxpBuilder::new(test_dir.path());
@@ -353,11 +352,49 @@ fn test_manifest_file_types() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_manifest_compression_per_type() -> Result<()> {
+    let temp_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+
+    // Repetitive source code vs. incompressible noise (xorshift bytes)
+    fs::write(temp_dir.path().join("main.rs"), "fn main() { println!(\"hi\"); }\n".repeat(500))?;
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let noise: Vec<u8> = (0..32 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    fs::write(temp_dir.path().join("noise.txt"), &noise)?;
+
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let output_path = output_dir.path().join("compression.cxp");
+    let mut builder = CxpBuilder::new(temp_dir.path());
+    builder.scan()?.process()?.build(&output_path)?;
+
+    let reader = CxpReader::open(&output_path)?;
+    let manifest = reader.manifest();
+    let rs = &manifest.file_types["rs"];
+    let txt = &manifest.file_types["txt"];
+
+    // Every stored chunk is accounted for under its file type
+    let chunks: Vec<_> = reader.chunks()?.collect();
+    assert_eq!(rs.chunk_bytes + txt.chunk_bytes, chunks.iter().map(|c| c.uncompressed_size).sum::<u64>());
+    assert_eq!(rs.compressed_bytes + txt.compressed_bytes, chunks.iter().map(|c| c.compressed_size).sum::<u64>());
+
+    assert!(rs.compression_ratio().unwrap() < 0.1);
+    assert!(txt.compression_ratio().unwrap() > 0.9);
+
+    Ok(())
+}
+
 #[test]
 fn test_complete_workflow() -> Result<()> {
     // This test verifies the complete workflow from start to finish
     let test_dir = create_test_directory()?;
-    let output_dir = TempDir::new().map_err(|e| CxpError::Io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("complete_workflow.cxp");
 
     // Step 1: Create CXP file
@@ -393,3 +430,1221 @@ fn test_complete_workflow() -> Result<()> {
 
     Ok(())
 }
+
+/// Read every ZIP entry name (in archive order) and the raw file map shard bytes
+fn archive_layout(path: &std::path::Path) -> (Vec<String>, Vec<u8>) {
+    use std::io::Read;
+
+    let mut archive = zip::ZipArchive::new(File::open(path).unwrap()).unwrap();
+    let names = (0..archive.len())
+        .map(|i| archive.by_index(i).unwrap().name().to_string())
+        .collect();
+
+    let mut file_map = Vec::new();
+    archive
+        .by_name("file_map/00000.msgpack")
+        .unwrap()
+        .read_to_end(&mut file_map)
+        .unwrap();
+
+    (names, file_map)
+}
+
+#[test]
+fn test_deterministic_serialization() -> Result<()> {
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+
+    let first = output_dir.path().join("first.cxp");
+    let second = output_dir.path().join("second.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&first)?;
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&second)?;
+
+    assert_eq!(archive_layout(&first), archive_layout(&second));
+
+    // Paths come back in sorted order
+    let reader = CxpReader::open(&first)?;
+    let paths = reader.file_paths();
+    let mut sorted = paths.clone();
+    sorted.sort();
+    assert_eq!(paths, sorted);
+
+    Ok(())
+}
+
+#[derive(Clone)]
+struct NotesExtension;
+
+impl cxp_core::Extension for NotesExtension {
+    fn namespace(&self) -> &str {
+        "notes"
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+}
+
+#[test]
+fn test_table_of_contents() -> Result<()> {
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("toc.cxp");
+
+    let data = std::collections::HashMap::from([
+        ("a.msgpack".to_string(), vec![1u8, 2, 3]),
+        ("nested/b.msgpack".to_string(), vec![4u8]),
+    ]);
+
+    let mut builder = CxpBuilder::new(test_dir.path());
+    builder.scan()?.process()?;
+    builder.add_extension(&NotesExtension, data)?;
+    builder.build(&cxp_path)?;
+
+    let reader = CxpReader::open(&cxp_path)?;
+    let toc = reader.toc().expect("archive should have a table of contents");
+
+    // Packs count once per chunk they hold, so this holds for packed archives too
+    assert_eq!(toc.section("chunks").entries, reader.manifest().stats.unique_chunks);
+    assert_eq!(toc.section("manifest.msgpack").entries, 1);
+    assert_eq!(toc.extensions["notes"].len(), 3); // manifest + 2 data files
+
+    // Extension data is loaded through the TOC
+    assert_eq!(reader.read_extension("notes", "a.msgpack")?, vec![1, 2, 3]);
+    assert_eq!(reader.read_extension("notes", "nested/b.msgpack")?, vec![4]);
+    assert!(reader.get_extension_manifest("notes").is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_lazy_file_map_prefix() -> Result<()> {
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("lazy.cxp");
+
+    CxpBuilder::new(test_dir.path())
+        .with_shard_size(2)
+        .scan()?
+        .process()?
+        .build(&cxp_path)?;
+
+    let eager = CxpReader::open(&cxp_path)?;
+    let mut lazy = CxpReader::open_lazy(&cxp_path)?;
+    assert!(!eager.is_lazy());
+    assert!(lazy.is_lazy());
+    assert!(lazy.file_map.files.is_empty());
+
+    let src = lazy.list_prefix("src/")?;
+    assert_eq!(src, vec!["src/lib.rs", "src/main.rs", "src/utils.rs"]);
+    assert_eq!(src, eager.list_prefix("src/")?);
+    assert!(lazy.list_prefix("nothing/")?.is_empty());
+
+    assert_eq!(lazy.read_file("src/main.rs")?, eager.read_file("src/main.rs")?);
+    assert!(lazy.file_entry("missing.rs")?.is_none());
+    assert_eq!(lazy.file_paths(), eager.file_paths());
+
+    lazy.load_file_map()?;
+    assert!(!lazy.is_lazy());
+    assert_eq!(lazy.file_map.files.len(), 6);
+
+    Ok(())
+}
+
+#[test]
+fn test_bloom_filter_lookups() -> Result<()> {
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("bloom.cxp");
+
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&cxp_path)?;
+
+    let reader = CxpReader::open_lazy(&cxp_path)?;
+    assert!(reader.contains_file("src/main.rs")?);
+    assert!(reader.might_contain_file("README.md"));
+    assert!(!reader.contains_file("src/missing.rs")?);
+
+    let entry = reader.file_entry("src/main.rs")?.unwrap();
+    let hash = entry.chunks[0].hash.clone();
+    assert!(reader.contains_chunk(&hash)?);
+    assert!(!reader.contains_chunk(&"0".repeat(64))?);
+    assert!(!reader.contains_chunk("abc")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_incremental_update_files() -> Result<()> {
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("incremental.cxp");
+
+    let mut builder = CxpBuilder::new(test_dir.path());
+    builder.scan()?.process()?.build(&cxp_path)?;
+
+    // Modify one file, delete the src directory, add a new file
+    fs::write(test_dir.path().join("README.md"), "# Updated\n")?;
+    fs::remove_dir_all(test_dir.path().join("src"))?;
+    fs::write(test_dir.path().join("notes.txt"), "new notes\n")?;
+
+    builder.update_files(&[
+        test_dir.path().join("README.md"),
+        test_dir.path().join("src"),
+        test_dir.path().join("notes.txt"),
+    ])?;
+    builder.build(&cxp_path)?;
+
+    let reader = CxpReader::open(&cxp_path)?;
+    assert_eq!(reader.file_paths(), vec!["README.md", "config.toml", "data.json", "notes.txt"]);
+    assert_eq!(reader.read_file("README.md")?, b"# Updated\n");
+    assert_eq!(reader.manifest().stats.total_files, 4);
+    assert_eq!(reader.manifest().stats.unique_chunks, 4);
+    assert!(!reader.manifest().file_types.contains_key("rs"));
+
+    // The result matches a fresh build of the same tree
+    let fresh_path = output_dir.path().join("fresh.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&fresh_path)?;
+    let fresh = CxpReader::open(&fresh_path)?;
+    assert_eq!(reader.file_map.files.len(), fresh.file_map.files.len());
+    for (path, entry) in &fresh.file_map.files {
+        let hashes = |chunks: &[cxp_core::chunker::ChunkRef]| chunks.iter().map(|c| c.hash.clone()).collect::<Vec<_>>();
+        assert_eq!(hashes(&reader.file_map.files[path].chunks), hashes(&entry.chunks));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_open_file_stream() -> Result<()> {
+    use std::io::Read;
+
+    let temp_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let content: String = (0..20_000).map(|i| format!("line {}\n", i)).collect();
+    fs::write(temp_dir.path().join("big.txt"), &content)?;
+
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("stream.cxp");
+    CxpBuilder::new(temp_dir.path()).scan()?.process()?.build(&cxp_path)?;
+
+    let reader = CxpReader::open_lazy(&cxp_path)?;
+    assert!(reader.file_entry("big.txt")?.unwrap().chunks.len() > 1);
+
+    let mut stream = reader.open_file_stream("big.txt")?;
+    assert_eq!(stream.size(), content.len() as u64);
+
+    // Small reads cross chunk boundaries
+    let mut streamed = Vec::new();
+    let mut buf = [0u8; 1000];
+    loop {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        streamed.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(streamed, content.as_bytes());
+
+    assert!(matches!(reader.open_file_stream("missing.txt"), Err(CxpError::FileNotFound(_))));
+
+    // Byte ranges (within one chunk, across chunks, clamped at the end)
+    let len = content.len() as u64;
+    for range in [0..10, 5000..60_000, len - 100..len + 100, len..len + 5] {
+        let mut stream = reader.open_file_range("big.txt", range.clone())?;
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data)?;
+
+        let end = range.end.min(len) as usize;
+        let start = range.start as usize;
+        assert_eq!(data, &content.as_bytes()[start..end], "range {:?}", range);
+        assert_eq!(stream.remaining(), 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_delta_roundtrip() -> Result<()> {
+    use cxp_core::CxpDelta;
+
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let old_path = output_dir.path().join("old.cxp");
+    let new_path = output_dir.path().join("new.cxp");
+    let patch_path = output_dir.path().join("patch.cxpd");
+
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&old_path)?;
+
+    fs::write(test_dir.path().join("README.md"), "# Changed readme\n")?;
+    fs::remove_file(test_dir.path().join("data.json"))?;
+    fs::write(test_dir.path().join("src/new.rs"), "pub fn new() {}\n")?;
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&new_path)?;
+
+    let delta = CxpDelta::create(&old_path, &new_path, &patch_path)?;
+    assert_eq!(delta.upserted_files.len(), 2);
+    assert_eq!(delta.removed_files, vec!["data.json"]);
+    assert_eq!(delta.new_chunks.len(), 2);
+    assert!(fs::metadata(&patch_path)?.len() < fs::metadata(&new_path)?.len());
+
+    // Apply in place
+    CxpDelta::apply(&old_path, &patch_path, &old_path)?;
+
+    let patched = CxpReader::open(&old_path)?;
+    let expected = CxpReader::open(&new_path)?;
+    assert_eq!(patched.file_paths(), expected.file_paths());
+    for path in expected.file_paths() {
+        assert_eq!(patched.read_file(path)?, expected.read_file(path)?);
+    }
+    assert!(!patched.contains_file("data.json")?);
+    assert_eq!(patched.manifest().stats.total_files, 6);
+
+    // The patch no longer matches the (already patched) base
+    assert!(CxpDelta::apply(&old_path, &patch_path, output_dir.path().join("again.cxp")).is_err());
+
+    // Identical archives produce an empty delta
+    let empty = CxpDelta::create(&new_path, &new_path, output_dir.path().join("empty.cxpd"))?;
+    assert!(empty.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_archives() -> Result<()> {
+    use cxp_core::{ConflictPolicy, CxpMerger};
+
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let shared = "shared helper code\n".repeat(100);
+
+    // Two "repos" sharing one file's content and one conflicting path
+    let repo_a = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    fs::write(repo_a.path().join("common.rs"), &shared)?;
+    fs::write(repo_a.path().join("README.md"), "# Repo A\n")?;
+    let repo_b = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    fs::write(repo_b.path().join("util.rs"), &shared)?;
+    fs::write(repo_b.path().join("README.md"), "# Repo B\n")?;
+
+    let a_path = output_dir.path().join("a.cxp");
+    let b_path = output_dir.path().join("b.cxp");
+    CxpBuilder::new(repo_a.path()).scan()?.process()?.build(&a_path)?;
+    CxpBuilder::new(repo_b.path()).scan()?.process()?.build(&b_path)?;
+
+    let merged_path = output_dir.path().join("merged.cxp");
+    let stats = CxpMerger::new().merge(&[&a_path, &b_path], &merged_path)?;
+    assert_eq!(stats.files, 3);
+    assert_eq!(stats.conflicts, 1);
+    assert_eq!(stats.shared_chunks, 1);
+
+    let merged = CxpReader::open(&merged_path)?;
+    assert_eq!(merged.file_paths(), vec!["README.md", "common.rs", "util.rs"]);
+    assert_eq!(merged.read_file("README.md")?, b"# Repo A\n");
+    assert_eq!(merged.read_file("util.rs")?, shared.as_bytes());
+    assert_eq!(merged.manifest().stats.unique_chunks, 2);
+    assert!(merged.manifest().file_types["rs"].compressed_bytes > 0);
+    assert!(merged.toc().is_some());
+
+    CxpMerger::new()
+        .with_policy(ConflictPolicy::KeepLast)
+        .merge(&[&a_path, &b_path], &merged_path)?;
+    assert_eq!(CxpReader::open(&merged_path)?.read_file("README.md")?, b"# Repo B\n");
+
+    let stats = CxpMerger::new()
+        .with_policy(ConflictPolicy::Prefix)
+        .merge(&[&a_path, &b_path], &merged_path)?;
+    assert_eq!(stats.files, 4);
+    assert_eq!(CxpReader::open(&merged_path)?.read_file("b/README.md")?, b"# Repo B\n");
+
+    assert!(CxpMerger::new()
+        .with_policy(ConflictPolicy::Fail)
+        .merge(&[&a_path, &b_path], &merged_path)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_enumerate_chunks() -> Result<()> {
+    let temp_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    fs::write(temp_dir.path().join("a.txt"), "same content\n")?;
+    fs::write(temp_dir.path().join("b.txt"), "same content\n")?;
+    fs::write(temp_dir.path().join("c.txt"), "other content\n")?;
+
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("chunks.cxp");
+    CxpBuilder::new(temp_dir.path()).scan()?.process()?.build(&cxp_path)?;
+
+    for reader in [CxpReader::open(&cxp_path)?, CxpReader::open_lazy(&cxp_path)?] {
+        let chunks: Vec<_> = reader.chunks()?.collect();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.windows(2).all(|w| w[0].hash < w[1].hash));
+
+        let mut ref_counts: Vec<usize> = chunks.iter().map(|c| c.ref_count).collect();
+        ref_counts.sort();
+        assert_eq!(ref_counts, vec![1, 2]);
+
+        let shared = chunks.iter().find(|c| c.ref_count == 2).unwrap();
+        assert_eq!(shared.uncompressed_size, 13);
+        assert!(shared.compressed_size > 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reader_from_bytes() -> Result<()> {
+    use cxp_core::{CxpSplitter, SplitMode};
+    use std::io::Cursor;
+
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let output_path = output_dir.path().join("memory.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&output_path)?;
+    let data = fs::read(&output_path)?;
+
+    let from_file = CxpReader::open(&output_path)?;
+    let reader = CxpReader::from_bytes(data.clone())?.with_access_tracking(true);
+    assert_eq!(reader.file_paths(), from_file.file_paths());
+    assert_eq!(reader.read_file("src/main.rs")?, from_file.read_file("src/main.rs")?);
+    assert!(reader.toc().is_some());
+    assert!(reader.access_log().is_none(), "in-memory readers have no sidecar");
+    assert_eq!(reader.statistics()?.archive_bytes, data.len() as u64);
+
+    let mut stream = reader.open_file_stream("README.md")?;
+    let mut content = Vec::new();
+    std::io::Read::read_to_end(&mut stream, &mut content)?;
+    assert_eq!(content, fs::read(test_dir.path().join("README.md"))?);
+
+    // Any Read + Seek works, regardless of its position
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(7);
+    assert_eq!(CxpReader::from_reader(cursor)?.file_paths().len(), 6);
+
+    // Embedded children of an in-memory archive stay in memory
+    let parent_path = output_dir.path().join("parent.cxp");
+    CxpSplitter::new(SplitMode::ByDir).split(&output_path, &parent_path)?;
+    let parent = CxpReader::from_bytes(fs::read(&parent_path)?)?;
+    let child = parent.open_child("src")?;
+    assert_eq!(child.read_file("src/lib.rs")?, fs::read(test_dir.path().join("src/lib.rs"))?);
+
+    Ok(())
+}
+
+#[test]
+fn test_split_archive() -> Result<()> {
+    use cxp_core::{CxpMerger, CxpSplitter, FileTier, SplitMode};
+    use std::io::Read;
+
+    let temp_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    fs::create_dir_all(temp_dir.path().join("src"))?;
+    fs::create_dir_all(temp_dir.path().join("docs"))?;
+    fs::write(temp_dir.path().join("README.md"), "# Project\n")?;
+    fs::write(temp_dir.path().join("src/main.rs"), "fn main() {}\n")?;
+    fs::write(temp_dir.path().join("src/lib.rs"), "pub fn lib() {}\n")?;
+    fs::write(temp_dir.path().join("docs/guide.md"), "# Guide\n")?;
+
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let big_path = output_dir.path().join("big.cxp");
+    CxpBuilder::new(temp_dir.path()).scan()?.process()?.build(&big_path)?;
+
+    let parent_path = output_dir.path().join("parent.cxp");
+    let stats = CxpSplitter::new(SplitMode::ByDir).split(&big_path, &parent_path)?;
+    assert_eq!(stats.files, 4);
+    assert_eq!(stats.root_files, 1);
+    assert_eq!(stats.children.len(), 2);
+
+    let parent = CxpReader::open(&parent_path)?;
+    assert_eq!(parent.file_paths(), vec!["README.md"]);
+    assert!(parent.manifest().file_types["md"].compressed_bytes > 0);
+    let children = &parent.manifest().children;
+    assert_eq!(children.len(), 2);
+    let src = children.get("src").unwrap();
+    assert!(src.is_embedded());
+    assert_eq!(src.meta.total_files, 2);
+    assert_eq!(src.tier, FileTier::Hot);
+
+    // Extract the embedded children and merge everything back together
+    let mut archive = zip::ZipArchive::new(File::open(&parent_path)?)?;
+    let mut inputs = vec![parent_path.clone()];
+    for child in children.iter() {
+        let cxp_core::CxpStorage::Embedded { path_in_zip } = &child.storage else {
+            panic!("child {} is not embedded", child.id);
+        };
+        let mut data = Vec::new();
+        archive.by_name(path_in_zip)?.read_to_end(&mut data)?;
+        let child_path = output_dir.path().join(format!("{}.cxp", child.id));
+        fs::write(&child_path, data)?;
+        inputs.push(child_path);
+    }
+
+    let docs = CxpReader::open(output_dir.path().join("docs.cxp"))?;
+    assert_eq!(docs.read_file("docs/guide.md")?, b"# Guide\n");
+    assert_eq!(docs.manifest().parent_path, Some(vec!["parent".to_string()]));
+
+    let merged_path = output_dir.path().join("merged.cxp");
+    CxpMerger::new().merge(&inputs, &merged_path)?;
+    let merged = CxpReader::open(&merged_path)?;
+    assert_eq!(merged.file_paths(), CxpReader::open(&big_path)?.file_paths());
+    assert_eq!(merged.read_file("src/lib.rs")?, b"pub fn lib() {}\n");
+
+    // Freshly written files all land in the hot tier
+    let stats = CxpSplitter::new(SplitMode::ByTier).split(&big_path, &parent_path)?;
+    assert_eq!(stats.root_files, 0);
+    assert_eq!(stats.children.len(), 1);
+    assert_eq!(stats.children[0].id, "hot");
+    assert_eq!(stats.children[0].meta.total_files, 4);
+
+    Ok(())
+}
+
+#[test]
+fn test_embedded_children() -> Result<()> {
+    let temp_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+
+    let leaf_dir = temp_dir.path().join("leaf");
+    fs::create_dir_all(&leaf_dir)?;
+    fs::write(leaf_dir.join("leaf.txt"), "leaf content\n")?;
+    let leaf_path = output_dir.path().join("leaf.cxp");
+    CxpBuilder::new(&leaf_dir).scan()?.process()?.build(&leaf_path)?;
+
+    let child_dir = temp_dir.path().join("child");
+    fs::create_dir_all(&child_dir)?;
+    fs::write(child_dir.join("child.txt"), "child content\n")?;
+    let child_path = output_dir.path().join("child.cxp");
+    CxpBuilder::new(&child_dir)
+        .scan()?
+        .process()?
+        .embed_child("leaf", &leaf_path)?
+        .build(&child_path)?;
+
+    let parent_dir = temp_dir.path().join("parent");
+    fs::create_dir_all(&parent_dir)?;
+    fs::write(parent_dir.join("parent.txt"), "parent content\n")?;
+    let parent_path = output_dir.path().join("parent.cxp");
+    let mut builder = CxpBuilder::new(&parent_dir);
+    builder.scan()?.process()?;
+    builder.embed_child("child", &child_path)?;
+    assert!(builder.embed_child("bad/id", &child_path).is_err());
+    builder.build(&parent_path)?;
+
+    let parent = CxpReader::open(&parent_path)?;
+    assert_eq!(parent.file_paths(), vec!["parent.txt"]);
+    let child_ref = parent.child("child").unwrap();
+    assert!(child_ref.is_embedded());
+    assert_eq!(child_ref.meta.total_files, 1);
+    assert_eq!(child_ref.meta.child_count, 1);
+    assert_eq!(parent.toc().unwrap().section("children").entries, 1);
+    assert_eq!(parent.read_child("child")?, fs::read(&child_path)?);
+
+    // Embedded children open from a temp copy that is removed with the reader
+    let temp_root = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let parent = parent.with_temp_policy(cxp_core::TempPolicy::Dir(temp_root.path().to_path_buf()));
+    let child = parent.open_child("child")?;
+    assert_eq!(child.read_file("child.txt")?, b"child content\n");
+    let leaf = child.open_child("leaf")?;
+    assert_eq!(leaf.read_file("leaf.txt")?, b"leaf content\n");
+    assert_eq!(fs::read_dir(temp_root.path())?.count(), 2);
+    drop(leaf);
+    drop(child);
+    assert_eq!(fs::read_dir(temp_root.path())?.count(), 0);
+    assert!(parent.open_child("missing").is_err());
+
+    // Recursive extraction
+    let extract_dir = output_dir.path().join("extracted");
+    let written = parent.extract_children(&extract_dir)?;
+    assert_eq!(written, vec![extract_dir.join("child.cxp"), extract_dir.join("child/leaf.cxp")]);
+    assert_eq!(CxpReader::open(&written[1])?.read_file("leaf.txt")?, b"leaf content\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_cancelled_build() -> Result<()> {
+    let temp_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let output_path = output_dir.path().join("cancelled.cxp");
+
+    let token = cxp_core::CancellationToken::new();
+    let mut builder = CxpBuilder::new(temp_dir.path());
+    builder.with_cancellation(token.clone());
+    builder.scan()?.process()?;
+
+    token.cancel();
+    assert!(matches!(builder.process(), Err(CxpError::Cancelled(_))));
+    assert!(matches!(builder.scan(), Err(CxpError::Cancelled(_))));
+    assert!(matches!(builder.build(&output_path), Err(CxpError::Cancelled(_))));
+    assert!(!output_path.exists());
+
+    let config = cxp_core::RecursiveBuildConfig::default();
+    let recursive = cxp_core::RecursiveBuilder::new(config).with_cancellation(token);
+    assert!(matches!(recursive.analyze(temp_dir.path()), Err(CxpError::Cancelled(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_build_info_recorded() -> Result<()> {
+    let temp_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let output_path = output_dir.path().join("info.cxp");
+
+    let mut builder = CxpBuilder::new(temp_dir.path());
+    builder.with_shard_size(7).scan()?.process()?;
+    builder.build(&output_path)?;
+
+    let reader = CxpReader::open(&output_path)?;
+    assert!(reader.manifest().extensions.contains(&"build_info".to_string()));
+
+    let info = reader.build_info()?.expect("build info should be recorded");
+    assert_eq!(info.builder_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(info.host_os, std::env::consts::OS);
+    for phase in ["scan", "process", "write"] {
+        assert!(info.phases_ms.contains_key(phase), "missing phase {}", phase);
+    }
+    assert_eq!(info.parameters["shard_size"], "7");
+
+    // Disabled telemetry leaves no trace in the archive
+    let plain_path = output_dir.path().join("plain.cxp");
+    let mut builder = CxpBuilder::new(temp_dir.path());
+    builder.with_build_info(false).scan()?.process()?;
+    builder.build(&plain_path)?;
+
+    let reader = CxpReader::open(&plain_path)?;
+    assert!(reader.build_info()?.is_none());
+    assert!(!reader.manifest().extensions.contains(&"build_info".to_string()));
+
+    Ok(())
+}
+
+#[test]
+fn test_reader_access_tracking() -> Result<()> {
+    let temp_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let output_path = output_dir.path().join("tracked.cxp");
+
+    let mut builder = CxpBuilder::new(temp_dir.path());
+    builder.scan()?.process()?;
+    builder.build(&output_path)?;
+
+    let path = CxpReader::open(&output_path)?.file_paths()[0].to_string();
+
+    // Untracked readers leave no sidecar behind
+    CxpReader::open(&output_path)?.read_file(&path)?;
+    let log = cxp_core::AccessLog::for_archive(&output_path);
+    assert!(!log.path().exists());
+
+    let reader = CxpReader::open(&output_path)?.with_access_tracking(true);
+    reader.read_file(&path)?;
+    reader.read_file(&path)?;
+    assert_eq!(reader.access_log(), Some(&log));
+
+    // A later process sees the reads
+    let recent = log.recent_files(5)?;
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].file_path, path);
+    assert_eq!(log.read()?.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_available_indexes_without_embeddings() -> Result<()> {
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let output_path = output_dir.path().join("plain.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&output_path)?;
+
+    let reader = CxpReader::open(&output_path)?;
+    let indexes = reader.available_indexes()?;
+    assert!(indexes.is_empty());
+    assert_eq!(indexes, cxp_core::AvailableIndexes::default());
+
+    #[cfg(feature = "search")]
+    assert!(reader.load_any_index().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_trained_dictionary() -> Result<()> {
+    use cxp_core::compress::DICTIONARY_PATH;
+    use cxp_core::{CxpDelta, CxpMerger, CxpSplitter, SplitMode};
+
+    // Many small, similar files: the case a shared dictionary is for
+    let temp_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    fs::create_dir_all(temp_dir.path().join("records"))?;
+    for i in 0..300 {
+        let record = format!(
+            "{{\"id\": {}, \"kind\": \"customer\", \"status\": \"active\", \"region\": \"eu-west-{}\", \"tags\": [\"priority\", \"newsletter\"], \"score\": {}}}\n",
+            i,
+            i % 3,
+            i * 7 % 100
+        );
+        fs::write(temp_dir.path().join(format!("records/{:03}.json", i)), record)?;
+    }
+    fs::write(temp_dir.path().join("README.md"), "# Records\n")?;
+
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let plain_path = output_dir.path().join("plain.cxp");
+    let dict_path = output_dir.path().join("dict.cxp");
+    CxpBuilder::new(temp_dir.path()).scan()?.process()?.build(&plain_path)?;
+    CxpBuilder::new(temp_dir.path()).scan()?.process()?.with_trained_dictionary().build(&dict_path)?;
+
+    let plain = CxpReader::open(&plain_path)?;
+    let reader = CxpReader::open(&dict_path)?;
+    assert!(plain.compression_dictionary().is_none());
+    assert!(reader.compression_dictionary().is_some());
+    assert!(zip::ZipArchive::new(File::open(&dict_path)?)?.index_for_name(DICTIONARY_PATH).is_some());
+    assert!(fs::metadata(&dict_path)?.len() < fs::metadata(&plain_path)?.len());
+    for path in plain.file_paths() {
+        assert_eq!(reader.read_file(path)?, plain.read_file(path)?);
+    }
+    assert!(cxp_core::format_spec::check_file(&dict_path)?.is_conformant());
+
+    // Merging with a dictionary-less archive recompresses without the dictionary
+    let merged_path = output_dir.path().join("merged.cxp");
+    CxpMerger::new().merge(&[&dict_path, &plain_path], &merged_path)?;
+    let merged = CxpReader::open(&merged_path)?;
+    assert!(merged.compression_dictionary().is_none());
+    assert_eq!(merged.read_file("records/042.json")?, plain.read_file("records/042.json")?);
+    assert!(cxp_core::format_spec::check_file(&merged_path)?.is_conformant());
+
+    // Split children keep the dictionary their chunks were compressed with
+    let parent_path = output_dir.path().join("parent.cxp");
+    CxpSplitter::new(SplitMode::ByDir).split(&dict_path, &parent_path)?;
+    let child = CxpReader::open(&parent_path)?.open_child("records")?;
+    assert_eq!(child.read_file("records/007.json")?, plain.read_file("records/007.json")?);
+
+    // A delta from a plain base ships every chunk, since the dictionary changed
+    let patch_path = output_dir.path().join("patch.cxpd");
+    let delta = CxpDelta::create(&plain_path, &dict_path, &patch_path)?;
+    assert_eq!(delta.new_chunks.len(), reader.manifest().stats.unique_chunks);
+    let patched_path = output_dir.path().join("patched.cxp");
+    CxpDelta::apply(&plain_path, &patch_path, &patched_path)?;
+    assert_eq!(CxpReader::open(&patched_path)?.read_file("README.md")?, b"# Records\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_compression_codec() -> Result<()> {
+    use cxp_core::Codec;
+
+    let test_dir = create_test_directory()?;
+    fs::write(test_dir.path().join("notes.txt"), "compressible line of text\n".repeat(400))?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+
+    let fast_path = output_dir.path().join("fast.cxp");
+    let small_path = output_dir.path().join("small.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.with_compression(Codec::Zstd { level: 1 }).build(&fast_path)?;
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.with_compression("zstd:19".parse()?).build(&small_path)?;
+
+    let fast = CxpReader::open(&fast_path)?;
+    let small = CxpReader::open(&small_path)?;
+    assert_eq!(fast.manifest().compression.as_deref(), Some("zstd:1"));
+    assert_eq!(small.manifest().compression.as_deref(), Some("zstd:19"));
+    for path in fast.file_paths() {
+        assert_eq!(small.read_file(path)?, fast.read_file(path)?);
+    }
+
+    #[cfg(feature = "lz4")]
+    {
+        let lz4_path = output_dir.path().join("lz4.cxp");
+        CxpBuilder::new(test_dir.path()).scan()?.process()?.with_compression(Codec::Lz4).build(&lz4_path)?;
+        let lz4 = CxpReader::open(&lz4_path)?;
+        assert_eq!(lz4.manifest().compression.as_deref(), Some("lz4"));
+        assert_eq!(lz4.read_file("notes.txt")?, fast.read_file("notes.txt")?);
+        assert!(cxp_core::format_spec::check_file(&lz4_path)?.is_conformant());
+
+        // Archives mixing both codecs read chunk by chunk
+        let other_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+        fs::write(other_dir.path().join("other.txt"), "zstd-only content\n".repeat(100))?;
+        let other_path = output_dir.path().join("other.cxp");
+        CxpBuilder::new(other_dir.path()).scan()?.process()?.build(&other_path)?;
+        let merged_path = output_dir.path().join("mixed.cxp");
+        cxp_core::CxpMerger::new().merge(&[&lz4_path, &other_path], &merged_path)?;
+        let merged = CxpReader::open(&merged_path)?;
+        assert!(merged.manifest().compression.is_none());
+        assert_eq!(merged.read_file("notes.txt")?, fast.read_file("notes.txt")?);
+        assert_eq!(merged.read_file("other.txt")?, "zstd-only content\n".repeat(100).as_bytes());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_min_reader_version() -> Result<()> {
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+
+    // Pinned to the current version: readable, and carried through merge
+    let current_path = output_dir.path().join("current.cxp");
+    CxpBuilder::new(test_dir.path())
+        .scan()?
+        .process()?
+        .with_min_reader_version(cxp_core::VERSION)?
+        .build(&current_path)?;
+    let reader = CxpReader::open(&current_path)?;
+    assert_eq!(reader.manifest().min_reader_version.as_deref(), Some(cxp_core::VERSION));
+
+    let merged_path = output_dir.path().join("merged.cxp");
+    cxp_core::CxpMerger::new().merge(&[&current_path], &merged_path)?;
+    assert_eq!(CxpReader::open(&merged_path)?.manifest().min_reader_version.as_deref(), Some(cxp_core::VERSION));
+
+    // Pinned to a future version: refused with an upgrade message
+    let future_path = output_dir.path().join("future.cxp");
+    CxpBuilder::new(test_dir.path())
+        .scan()?
+        .process()?
+        .with_min_reader_version("99.0.0")?
+        .build(&future_path)?;
+    let err = CxpReader::open(&future_path).err().expect("future archive must not open");
+    assert!(matches!(err, CxpError::UnsupportedFormatVersion { .. }));
+    assert!(err.to_string().contains("99.0.0"));
+    assert!(cxp_core::CxpMerger::new().merge(&[&future_path], output_dir.path().join("x.cxp")).is_err());
+
+    assert!(CxpBuilder::new(test_dir.path()).with_min_reader_version("next").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_chunking_algorithms() -> Result<()> {
+    use cxp_core::ChunkingAlgorithm;
+
+    let test_dir = create_test_directory()?;
+    let large: String = (0..4000).map(|i| format!("line {} of a larger file\n", i)).collect();
+    fs::write(test_dir.path().join("large.txt"), &large)?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+
+    let default_path = output_dir.path().join("gear.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&default_path)?;
+    let gear = CxpReader::open(&default_path)?;
+    assert_eq!(gear.manifest().chunker.as_deref(), Some("gear"));
+
+    for algorithm in [ChunkingAlgorithm::Buzhash, ChunkingAlgorithm::Fixed { size: 1024 }] {
+        let path = output_dir.path().join(format!("{}.cxp", algorithm.to_string().replace(':', "_")));
+        CxpBuilder::new(test_dir.path())
+            .with_chunking(algorithm)
+            .scan()?
+            .process()?
+            .build(&path)?;
+        let reader = CxpReader::open(&path)?;
+        assert_eq!(reader.manifest().chunker, Some(algorithm.to_string()));
+        for file in gear.file_paths() {
+            assert_eq!(reader.read_file(file)?, gear.read_file(file)?);
+        }
+        assert!(cxp_core::format_spec::check_file(&path)?.is_conformant());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_summaries_extension() -> Result<()> {
+    use cxp_core::{HeuristicSummarizer, SummaryKind};
+
+    let test_dir = create_test_directory()?;
+    fs::write(test_dir.path().join("src/retry.rs"), "//! Retry failed requests with backoff\n\npub fn retry() {}\n")?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+
+    let plain_path = output_dir.path().join("plain.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&plain_path)?;
+    assert!(CxpReader::open(&plain_path)?.summaries()?.is_none());
+
+    let cxp_path = output_dir.path().join("summaries.cxp");
+    CxpBuilder::new(test_dir.path())
+        .with_summarizer(HeuristicSummarizer::new())
+        .scan()?
+        .process()?
+        .build(&cxp_path)?;
+    let reader = CxpReader::open(&cxp_path)?;
+    let summaries = reader.summaries()?.expect("summaries stored");
+    assert_eq!(summaries.file("src/retry.rs"), Some("Retry failed requests with backoff"));
+    assert_eq!(summaries.file("README.md"), Some("Test Project This is a test project for CXP."));
+    assert!(summaries.directory("src").unwrap().starts_with("4 files, 0 directories"));
+
+    let hits = reader.search_summaries("backoff", 5)?;
+    assert_eq!(hits.len(), 1);
+    assert_eq!((hits[0].path.as_str(), hits[0].kind), ("src/retry.rs", SummaryKind::File));
+    let hits = reader.search_summaries("src", 5)?;
+    assert_eq!((hits[0].path.as_str(), hits[0].kind), ("src", SummaryKind::Directory));
+
+    Ok(())
+}
+
+#[test]
+fn test_symbol_index() -> Result<()> {
+    use cxp_core::merge::CxpMerger;
+    use cxp_core::{LineSymbolParser, SymbolKind};
+
+    let test_dir = create_test_directory()?;
+    fs::write(test_dir.path().join("src/store.rs"), "//! Chunk storage\n\npub struct ChunkStore;\n\nimpl ChunkStore {\n    pub fn insert(&self) {}\n}\n")?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+
+    let plain_path = output_dir.path().join("plain.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&plain_path)?;
+    let plain = CxpReader::open(&plain_path)?;
+    assert!(plain.symbols()?.is_none());
+    assert!(plain.find_symbol("ChunkStore")?.is_empty());
+
+    let cxp_path = output_dir.path().join("symbols.cxp");
+    CxpBuilder::new(test_dir.path())
+        .with_symbols(LineSymbolParser)
+        .scan()?
+        .process()?
+        .build(&cxp_path)?;
+    let reader = CxpReader::open(&cxp_path)?;
+    let found = reader.find_symbol("ChunkStore")?;
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].path.as_str(), found[0].line, found[0].kind), ("src/store.rs", 3, SymbolKind::Struct));
+    assert_eq!(reader.symbols()?.unwrap().in_file("src/store.rs").len(), 2);
+
+    // Merging keeps the symbols of the kept files
+    let merged_path = output_dir.path().join("merged.cxp");
+    CxpMerger::new().merge(&[&cxp_path, &plain_path], &merged_path)?;
+    assert_eq!(CxpReader::open(&merged_path)?.find_symbol("insert")?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_graph_extension() -> Result<()> {
+    use cxp_core::{EdgeKind, ImportGraph, SymbolGraph};
+
+    let test_dir = create_test_directory()?;
+    fs::write(test_dir.path().join("src/lib.rs"), "pub mod retry;\n")?;
+    fs::write(test_dir.path().join("src/retry.rs"), "pub fn retry_with_backoff() {}\n")?;
+    fs::write(test_dir.path().join("src/client.rs"), "fn send() { crate::retry::retry_with_backoff(); }\n")?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+
+    let plain_path = output_dir.path().join("plain.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&plain_path)?;
+    assert!(CxpReader::open(&plain_path)?.graph()?.is_none());
+
+    let cxp_path = output_dir.path().join("graph.cxp");
+    CxpBuilder::new(test_dir.path())
+        .with_graph_plugin(ImportGraph)
+        .with_graph_plugin(SymbolGraph)
+        .scan()?
+        .process()?
+        .build(&cxp_path)?;
+    let reader = CxpReader::open(&cxp_path)?;
+    let graph = reader.graph()?.expect("graph stored");
+    assert!(graph.node("file:README.md").is_some());
+    assert!(graph.edges().any(|e| e.from == "file:src/lib.rs" && e.to == "file:src/retry.rs" && e.kind == EdgeKind::Imports));
+
+    // A hit in the client leads to the retry module it calls
+    assert_eq!(graph.around_files(&["src/client.rs"], 1).files(), vec!["src/retry.rs"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_keywords_fill_manifest_and_global_index() -> Result<()> {
+    use cxp_core::global_index::GlobalIndex;
+    use cxp_core::recursive::FileTier;
+    use cxp_core::{CxpFile, KeywordExtractor};
+
+    let test_dir = create_test_directory()?;
+    fs::write(
+        test_dir.path().join("src/retry.rs"),
+        "//! Retry failed requests with exponential backoff\n\npub fn retry_with_backoff(attempts: u32) { backoff(attempts); }\n",
+    )?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+
+    let plain_path = output_dir.path().join("plain.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&plain_path)?;
+    let plain = CxpReader::open(&plain_path)?;
+    assert!(plain.manifest().topics.is_empty());
+    assert!(plain.file_entry("src/retry.rs")?.unwrap().keywords.is_empty());
+
+    let cxp_path = output_dir.path().join("keywords.cxp");
+    CxpBuilder::new(test_dir.path())
+        .with_keywords(KeywordExtractor::new())
+        .scan()?
+        .process()?
+        .build(&cxp_path)?;
+    let reader = CxpReader::open(&cxp_path)?;
+    assert!(!reader.manifest().topics.is_empty());
+    let keywords = &reader.file_entry("src/retry.rs")?.unwrap().keywords;
+    assert_eq!(keywords[0], "backoff");
+    assert!(keywords.contains(&"retry".to_string()));
+
+    let mut index = GlobalIndex::new();
+    index.add_file_map(vec!["project".to_string()], FileTier::Hot, &CxpFile::open(&cxp_path)?.file_map);
+    let hits = index.search("exponential", 5);
+    assert_eq!(hits[0].entry.file_path, "src/retry.rs");
+
+    Ok(())
+}
+
+#[test]
+fn test_near_duplicates() -> Result<()> {
+    let test_dir = create_test_directory()?;
+    let config = "port: 8080\nlog_level: info\nretries: 3\n";
+    for service in ["billing", "search"] {
+        fs::create_dir_all(test_dir.path().join(service))?;
+        fs::write(test_dir.path().join(service).join("config.yml"), config)?;
+    }
+    let library: String = (0..4000).map(|i| format!("export function helper{i}(x) {{ return x * {i}; }}\n")).collect();
+    let patched = library.replace("helper3999(x) { return x * 3999; }", "helper3999(x) { return x + 1; }");
+    for (vendor, content) in [("vendor/a", &library), ("vendor/b", &library), ("vendor/c", &patched)] {
+        fs::create_dir_all(test_dir.path().join(vendor))?;
+        fs::write(test_dir.path().join(vendor).join("lib.js"), content)?;
+    }
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("dupes.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&cxp_path)?;
+
+    let reader = CxpReader::open(&cxp_path)?;
+    let report = reader.duplicate_report()?;
+    assert_eq!(report.groups.len(), 2);
+
+    let libs = &report.groups[0];
+    assert!(!libs.identical);
+    assert_eq!(libs.files.len(), 3);
+    assert_eq!(libs.files[2].path, "vendor/c/lib.js");
+    assert!(libs.files[2].similarity < 1.0);
+
+    let configs = &report.groups[1];
+    assert!(configs.identical);
+    assert_eq!(configs.files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), vec!["billing/config.yml", "search/config.yml"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_cluster_map_stored_in_archive() -> Result<()> {
+    use cxp_core::format_spec::check_file;
+    use cxp_core::{ClusterMap, ClusterOptions};
+    use std::collections::HashMap;
+
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("clusters.cxp");
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.build(&cxp_path)?;
+
+    // Code files point one way, prose and config the other
+    let reader = CxpReader::open(&cxp_path)?;
+    assert!(reader.clusters()?.is_none());
+    let mut vectors = HashMap::new();
+    for path in reader.file_paths() {
+        let vector = if path.ends_with(".rs") { vec![1.0, 0.1, 0.0] } else { vec![0.0, 0.1, 1.0] };
+        for chunk in &reader.file_entry(path)?.unwrap().chunks {
+            vectors.insert(chunk.hash.clone(), vector.clone());
+        }
+    }
+    let map = ClusterMap::build(&vectors, &reader.file_map, &ClusterOptions::default().with_k(2), |hash| {
+        reader.read_chunk(hash).ok().map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    })?;
+    drop(reader);
+    map.write_to(&cxp_path)?;
+
+    let reader = CxpReader::open(&cxp_path)?;
+    assert!(reader.manifest().extensions.contains(&"clusters".to_string()));
+    let stored = reader.clusters()?.expect("clusters stored");
+    assert_eq!(stored, map);
+    let code = stored.cluster_of(&reader.file_entry("src/main.rs")?.unwrap().chunks[0].hash).unwrap();
+    assert!(code.files.iter().all(|f| f.ends_with(".rs")));
+    assert_eq!(reader.read_file("README.md")?, b"# Test Project\n\nThis is a test project for CXP.\n");
+    assert!(check_file(&cxp_path)?.failures().is_empty());
+
+    // Clustering again replaces the stored map
+    ClusterMap::default().write_to(&cxp_path)?;
+    assert!(CxpReader::open(&cxp_path)?.clusters()?.unwrap().clusters.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshots() -> Result<()> {
+    use cxp_core::format_spec::check_file;
+    use cxp_core::CxpOptimizer;
+
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("history.cxp");
+
+    let mut builder = CxpBuilder::new(test_dir.path());
+    builder.scan()?.process()?.snapshot("v1")?;
+    fs::write(test_dir.path().join("README.md"), "# Updated\n")?;
+    builder.update_files(&[test_dir.path().join("README.md")])?.snapshot("v2")?;
+    assert!(builder.snapshot("v2").is_err());
+    fs::remove_dir_all(test_dir.path().join("src"))?;
+    builder.update_files(&[test_dir.path().join("src")])?.build(&cxp_path)?;
+
+    let reader = CxpReader::open(&cxp_path)?;
+    assert_eq!(reader.snapshots().iter().map(|s| s.label.as_str()).collect::<Vec<_>>(), vec!["v1", "v2"]);
+    assert!(reader.file_entry("src/main.rs")?.is_none());
+    assert_eq!(reader.manifest().stats.total_files, 3);
+
+    let v1 = reader.open_snapshot("v1")?;
+    assert_eq!(v1.snapshot_label(), Some("v1"));
+    assert_eq!(v1.file_paths().len(), 6);
+    assert_eq!(v1.read_file("README.md")?, b"# Test Project\n\nThis is a test project for CXP.\n");
+    assert_eq!(v1.read_file("src/main.rs")?, b"fn main() {\n    println!(\"Hello, world!\");\n}\n");
+    let v2 = reader.open_snapshot("v2")?;
+    assert_eq!(v2.read_file("README.md")?, b"# Updated\n");
+    assert!(v2.file_entry("src/lib.rs")?.is_some());
+    assert!(reader.open_snapshot("v3").is_err());
+
+    // Snapshot chunks survive the conformance checker and the optimizer
+    let report = check_file(&cxp_path)?;
+    assert!(report.failures().is_empty(), "{:?}", report.failures());
+    let optimized_path = output_dir.path().join("optimized.cxp");
+    let stats = CxpOptimizer::new().optimize(&cxp_path, &optimized_path)?;
+    assert_eq!(stats.orphaned_chunks, 0);
+    let optimized = CxpReader::open(&optimized_path)?;
+    assert_eq!(optimized.open_snapshot("v1")?.read_file("src/utils.rs")?, b"pub fn multiply(a: i32, b: i32) -> i32 {\n    a * b\n}\n");
+    assert!(check_file(&optimized_path)?.failures().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshots_survive_rebuilds() -> Result<()> {
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("history.cxp");
+
+    // What `cxp build --snapshot <label>` does, twice over the same output
+    CxpBuilder::new(test_dir.path()).scan()?.process()?.snapshot("v1")?.build(&cxp_path)?;
+    fs::write(test_dir.path().join("README.md"), "# Rebuilt\n")?;
+    fs::remove_file(test_dir.path().join("src/utils.rs"))?;
+    let mut builder = CxpBuilder::new(test_dir.path());
+    builder.with_snapshots_from(&cxp_path)?;
+    assert!(builder.snapshot("v1").is_err());
+    builder.scan()?.process()?.snapshot("v2")?.build(&cxp_path)?;
+
+    let reader = CxpReader::open(&cxp_path)?;
+    assert_eq!(reader.snapshots().iter().map(|s| (s.label.as_str(), s.generation)).collect::<Vec<_>>(), vec![("v1", 0), ("v2", 1)]);
+    let v1 = reader.open_snapshot("v1")?;
+    assert_eq!(v1.read_file("README.md")?, b"# Test Project\n\nThis is a test project for CXP.\n");
+    assert_eq!(v1.read_file("src/utils.rs")?, b"pub fn multiply(a: i32, b: i32) -> i32 {\n    a * b\n}\n");
+    let v2 = reader.open_snapshot("v2")?;
+    assert_eq!(v2.read_file("README.md")?, b"# Rebuilt\n");
+    assert!(v2.file_entry("src/utils.rs")?.is_none());
+    assert!(cxp_core::format_spec::check_file(&cxp_path)?.failures().is_empty());
+
+    // Loading them after taking a snapshot would renumber generations
+    let mut builder = CxpBuilder::new(test_dir.path());
+    builder.scan()?.process()?.snapshot("v3")?;
+    assert!(builder.with_snapshots_from(&cxp_path).is_err());
+
+    Ok(())
+}
+
+/// Build -> append -> tombstone -> compact -> search with a real model
+///
+/// Needs `CXP_TEST_MODEL_DIR` pointing to all-MiniLM-L6-v2 (`model.onnx` and
+/// `tokenizer.json`); skipped otherwise.
+#[cfg(all(feature = "embeddings", feature = "search"))]
+#[test]
+fn test_embedding_tombstones_end_to_end() -> Result<()> {
+    use cxp_core::format::EMBEDDING_TOMBSTONES_READER_VERSION;
+    use cxp_core::{CxpOptimizer, EmbeddingEngine, EmbeddingModel};
+    use std::path::Path;
+
+    let Some(model_dir) = std::env::var_os("CXP_TEST_MODEL_DIR").map(std::path::PathBuf::from) else {
+        eprintln!("CXP_TEST_MODEL_DIR not set, skipping");
+        return Ok(());
+    };
+    let mut engine = EmbeddingEngine::load(&model_dir, EmbeddingModel::MiniLM)?;
+
+    // Chunk hashes of the best hits, failing on hits without a live chunk
+    let mut top_chunks = |path: &Path, query: &str| -> Result<Vec<String>> {
+        let reader = CxpReader::open(path)?;
+        reader.load_embeddings()?;
+        let hits = reader.search_semantic(&engine.embed_query(query)?, 3)?;
+        Ok(hits
+            .iter()
+            .map(|hit| reader.embedding_chunk_hash(hit.id).expect("hit on a tombstoned row").to_string())
+            .collect())
+    };
+    let first_chunk = |path: &Path, file: &str| -> Result<String> {
+        let entry = CxpReader::open(path)?.file_entry(file)?.expect("file in archive");
+        Ok(entry.chunks[0].hash.clone())
+    };
+
+    let source = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    fs::write(source.path().join("retry.rs"), "// Retry failed requests with exponential backoff between attempts\nfn retry() {}\n")?;
+    fs::write(source.path().join("config.rs"), "// Read the TOML configuration file from disk\nfn load_config() {}\n")?;
+    let output = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output.path().join("tombstones.cxp");
+
+    let mut builder = CxpBuilder::new(source.path());
+    builder.with_embeddings(&model_dir, EmbeddingModel::MiniLM)?;
+    builder.scan()?.process()?.build(&cxp_path)?;
+    assert_eq!(CxpReader::open(&cxp_path)?.manifest().embedding_tombstones, 0);
+
+    // Append a file and remove another: the removed file's row is tombstoned
+    fs::write(source.path().join("cache.rs"), "// Evict the least recently used entries from the LRU cache\nfn evict() {}\n")?;
+    fs::remove_file(source.path().join("retry.rs"))?;
+    builder
+        .update_files(&[source.path().join("cache.rs"), source.path().join("retry.rs")])?
+        .build(&cxp_path)?;
+
+    let reader = CxpReader::open(&cxp_path)?;
+    assert_eq!(reader.manifest().embedding_tombstones, 1);
+    assert_eq!(reader.manifest().min_reader_version.as_deref(), Some(EMBEDDING_TOMBSTONES_READER_VERSION));
+    assert_eq!(top_chunks(&cxp_path, "least recently used cache eviction")?[0], first_chunk(&cxp_path, "cache.rs")?);
+    assert_eq!(top_chunks(&cxp_path, "exponential backoff")?.len(), 2);
+
+    // Compaction drops the row and keeps search working
+    let compacted = output.path().join("compacted.cxp");
+    let stats = CxpOptimizer::new().optimize(&cxp_path, &compacted)?;
+    assert_eq!((stats.embeddings_dropped, stats.embeddings_kept), (1, 2));
+    assert_eq!(CxpReader::open(&compacted)?.manifest().embedding_tombstones, 0);
+    assert_eq!(top_chunks(&compacted, "least recently used cache eviction")?[0], first_chunk(&compacted, "cache.rs")?);
+    assert_eq!(top_chunks(&compacted, "TOML configuration")?[0], first_chunk(&compacted, "config.rs")?);
+
+    Ok(())
+}
+
+/// Search results of a lazily opened archive carry their file paths
+///
+/// Needs `CXP_TEST_MODEL_DIR` pointing to all-MiniLM-L6-v2; skipped otherwise.
+#[cfg(all(feature = "embeddings", feature = "search"))]
+#[test]
+fn test_lazy_search_resolves_file_paths() -> Result<()> {
+    use cxp_core::{DedupBy, EmbeddingEngine, EmbeddingModel, Fusion};
+
+    let Some(model_dir) = std::env::var_os("CXP_TEST_MODEL_DIR").map(std::path::PathBuf::from) else {
+        eprintln!("CXP_TEST_MODEL_DIR not set, skipping");
+        return Ok(());
+    };
+    let mut engine = EmbeddingEngine::load(&model_dir, EmbeddingModel::MiniLM)?;
+
+    let test_dir = create_test_directory()?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("lazy_search.cxp");
+    let mut builder = CxpBuilder::new(test_dir.path());
+    builder.with_embeddings(&model_dir, EmbeddingModel::MiniLM)?;
+    builder.scan()?.process()?.build(&cxp_path)?;
+
+    let reader = CxpReader::open_lazy(&cxp_path)?;
+    assert!(reader.is_lazy());
+    reader.load_embeddings()?;
+    let queries = vec![engine.embed_query("multiply two numbers")?, engine.embed_query("test project")?];
+
+    let fused = reader.search_multi_embeddings(&queries, 5, Fusion::rrf().dedup_by(DedupBy::File))?;
+    assert!(!fused.is_empty());
+    assert!(fused.iter().all(|r| r.file_path.is_some()));
+    let mut files: Vec<_> = fused.iter().filter_map(|r| r.file_path.as_deref()).collect();
+    files.sort_unstable();
+    files.dedup();
+    assert_eq!(files.len(), fused.len());
+
+    #[cfg(feature = "multimodal")]
+    {
+        let typed = reader.search_multimodal(&queries[0], 3, "text")?;
+        assert!(!typed.is_empty());
+        for result in typed {
+            let cxp_core::EntryType::Text { file_path, .. } = result.entry_type else {
+                panic!("text index returned a non-text entry");
+            };
+            assert!(!file_path.is_empty());
+        }
+    }
+
+    Ok(())
+}
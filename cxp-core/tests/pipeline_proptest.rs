@@ -0,0 +1,158 @@
+//! Property-based tests for the storage pipeline
+//!
+//! Generates arbitrary byte streams and file trees and checks the invariants
+//! of chunk -> dedup -> compress -> build -> read:
+//!
+//! - chunks tile their input exactly and are named by their SHA-256
+//! - every file reads back byte-identical
+//! - each unique chunk is stored once and its ref count matches the file map
+//! - size and compression metadata agree with the stored chunk entries
+//!
+//! Increase the number of cases with `PROPTEST_CASES=1000`.
+
+use cxp_core::chunker::{chunk_content, compute_hash};
+use cxp_core::compress::{compress, compress_with_level, decompress};
+use cxp_core::dedup::ChunkStore;
+use cxp_core::format_spec::check_file;
+use cxp_core::{CxpBuilder, CxpReader};
+use proptest::collection::{btree_map, vec};
+use proptest::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use tempfile::TempDir;
+
+/// Arbitrary content: random bytes, or a repeated pattern that compresses well
+fn content() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        vec(any::<u8>(), 0..48 * 1024),
+        (vec(any::<u8>(), 1..64), 0usize..1024).prop_map(|(pattern, repeats)| pattern.repeat(repeats)),
+    ]
+}
+
+/// Arbitrary file tree; some files share content so deduplication kicks in
+fn file_tree() -> impl Strategy<Value = BTreeMap<String, Vec<u8>>> {
+    let path = "[a-z]{1,6}(/[a-z]{1,6}){0,2}\\.(txt|rs|md)";
+    (vec(content(), 1..4), btree_map(path, (any::<prop::sample::Index>(), any::<bool>()), 1..12)).prop_map(
+        |(blobs, files)| {
+            files
+                .into_iter()
+                .map(|(path, (blob, suffix))| {
+                    let mut data = blob.get(&blobs).clone();
+                    if suffix {
+                        // Shared prefix, different tail
+                        data.extend_from_slice(path.as_bytes());
+                    }
+                    (path, data)
+                })
+                .collect()
+        },
+    )
+}
+
+proptest! {
+    #[test]
+    fn chunks_tile_input(data in content()) {
+        let chunks = chunk_content(&data);
+
+        let mut offset = 0;
+        let mut rebuilt = Vec::with_capacity(data.len());
+        for chunk in &chunks {
+            prop_assert_eq!(chunk.offset, offset);
+            prop_assert_eq!(chunk.length, chunk.data.len());
+            prop_assert_eq!(&chunk.hash, &compute_hash(&chunk.data));
+            offset += chunk.length;
+            rebuilt.extend_from_slice(&chunk.data);
+        }
+        prop_assert_eq!(rebuilt, data);
+    }
+
+    #[test]
+    fn compression_roundtrips(data in content(), level in 1i32..10) {
+        prop_assert_eq!(decompress(&compress(&data).unwrap()).unwrap(), data.clone());
+        prop_assert_eq!(decompress(&compress_with_level(&data, level).unwrap()).unwrap(), data);
+    }
+
+    #[test]
+    fn chunk_store_counts_are_consistent(blobs in vec(content(), 1..6)) {
+        let mut store = ChunkStore::new();
+        let mut refs = Vec::new();
+        for blob in &blobs {
+            refs.extend(store.add_many(chunk_content(blob)));
+        }
+
+        let stats = store.stats();
+        let distinct: HashMap<&str, usize> = refs.iter().map(|r| (r.hash.as_str(), r.length)).collect();
+        prop_assert_eq!(stats.total_chunks, refs.len());
+        prop_assert_eq!(stats.unique_chunks, distinct.len());
+        prop_assert_eq!(store.len(), distinct.len());
+        prop_assert_eq!(stats.duplicates_found, refs.len() - distinct.len());
+        prop_assert_eq!(stats.total_bytes, blobs.iter().map(Vec::len).sum::<usize>());
+        prop_assert_eq!(stats.deduplicated_bytes, distinct.values().sum::<usize>());
+        for r in &refs {
+            prop_assert!(store.contains(&r.hash));
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn build_then_read_is_identical(tree in file_tree()) {
+        let source = TempDir::new().unwrap();
+        for (path, data) in &tree {
+            let path = source.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, data).unwrap();
+        }
+
+        let output = TempDir::new().unwrap();
+        let cxp_path = output.path().join("prop.cxp");
+        let mut builder = CxpBuilder::new(source.path());
+        builder.with_build_info(false).with_shard_size(4);
+        builder.scan().unwrap().process().unwrap();
+        builder.build(&cxp_path).unwrap();
+
+        // Content
+        let reader = CxpReader::open(&cxp_path).unwrap();
+        prop_assert_eq!(reader.file_paths().len(), tree.len());
+        for (path, data) in &tree {
+            prop_assert_eq!(&reader.read_file(path).unwrap(), data, "{}", path);
+        }
+
+        // Dedup: one stored chunk per distinct hash, ref counts match the inputs
+        let mut expected_refs: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for data in tree.values() {
+            for chunk in chunk_content(data) {
+                expected_refs.entry(chunk.hash).or_insert((0, chunk.length)).0 += 1;
+            }
+        }
+        let chunks: Vec<_> = reader.chunks().unwrap().collect();
+        prop_assert_eq!(chunks.len(), expected_refs.len());
+        for info in &chunks {
+            let (refs, length) = expected_refs[&info.hash];
+            prop_assert_eq!(info.ref_count, refs);
+            prop_assert_eq!(info.uncompressed_size, length as u64);
+            prop_assert_eq!(reader.read_chunk(&info.hash).unwrap().len(), length);
+            prop_assert!(info.compressed_size > 0);
+        }
+
+        // Metadata
+        let manifest = reader.manifest();
+        let original: u64 = tree.values().map(|d| d.len() as u64).sum();
+        prop_assert_eq!(manifest.stats.total_files, tree.len());
+        prop_assert_eq!(manifest.stats.unique_chunks, expected_refs.len());
+        prop_assert_eq!(manifest.stats.original_size_bytes, original);
+        // The archive size is only known after writing, so only the builder has it
+        prop_assert_eq!(builder.manifest().stats.cxp_size_bytes, fs::metadata(&cxp_path).unwrap().len());
+
+        let stats = reader.statistics().unwrap();
+        prop_assert_eq!(stats.original_bytes, original);
+        prop_assert_eq!(stats.chunk_refs, expected_refs.values().map(|(refs, _)| refs).sum::<usize>());
+        prop_assert_eq!(stats.unique_chunk_bytes, chunks.iter().map(|c| c.uncompressed_size).sum::<u64>());
+        prop_assert_eq!(stats.compressed_chunk_bytes, chunks.iter().map(|c| c.compressed_size).sum::<u64>());
+
+        let report = check_file(&cxp_path).unwrap();
+        prop_assert!(report.is_conformant(), "{:?}", report.failures());
+    }
+}
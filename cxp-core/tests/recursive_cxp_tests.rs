@@ -9,16 +9,24 @@
 //! - Large file handling
 //! - Edge cases and error handling
 
+#![allow(
+    dead_code,
+    unused_variables,
+    clippy::ptr_arg,
+    clippy::field_reassign_with_default,
+    clippy::needless_borrows_for_generic_args
+)]
+
 use std::fs;
 use std::path::PathBuf;
 use tempfile::TempDir;
 use chrono::{Utc, Duration};
 
 use cxp_core::{
-    CxpBuilder, CxpFile, Manifest,
-    CxpRef, CxpStorage, CxpRefMeta, FileTier, ChildrenMap,
+    CxpBuilder, Manifest,
+    CxpRef, CxpRefMeta, FileTier, ChildrenMap,
     GlobalIndex, GlobalIndexEntry,
-    CxpManager, CxpManagerConfig,
+    CxpManager, CxpManagerConfig, SearchOptions, TierScheduler,
     RecursiveBuilder, RecursiveBuildConfig, ProjectPattern,
 };
 
@@ -427,6 +435,7 @@ fn test_cxp_manager_memory_stats() {
         max_cached_cxps: 50,
         storage_root: PathBuf::from("/tmp/test"),
         preload_hot: false,
+        track_access: false,
     };
 
     let manager = CxpManager::new(config);
@@ -769,6 +778,7 @@ fn test_full_workflow() {
         max_cached_cxps: 10,
         storage_root: root.clone(),
         preload_hot: false,
+        track_access: false,
     };
 
     let manager = CxpManager::new(config);
@@ -806,3 +816,318 @@ fn test_cxp_builder_with_directory() {
     // The builder should have found the files
     // Note: This doesn't actually build the CXP file, just scans
 }
+
+#[test]
+fn test_cxp_manager_loads_embedded_children() {
+    let source = TempDir::new().unwrap();
+    let storage = TempDir::new().unwrap();
+
+    // web.cxp is embedded in projects.cxp, which is embedded in master.cxp
+    let web_dir = source.path().join("web");
+    fs::create_dir_all(&web_dir).unwrap();
+    fs::write(web_dir.join("index.ts"), "export const app = 1;").unwrap();
+    let web_path = storage.path().join("web.cxp");
+    CxpBuilder::new(&web_dir).scan().unwrap().process().unwrap().build(&web_path).unwrap();
+
+    let projects_dir = source.path().join("projects");
+    fs::create_dir_all(&projects_dir).unwrap();
+    fs::write(projects_dir.join("README.md"), "# Projects").unwrap();
+    let projects_path = storage.path().join("projects.cxp");
+    let mut projects = CxpBuilder::new(&projects_dir);
+    projects.scan().unwrap().process().unwrap();
+    projects.embed_child("web", &web_path).unwrap();
+    projects.build(&projects_path).unwrap();
+
+    let empty_dir = source.path().join("empty");
+    fs::create_dir_all(&empty_dir).unwrap();
+    let mut master = CxpBuilder::new(&empty_dir);
+    master.scan().unwrap().process().unwrap();
+    master.embed_child("projects", &projects_path).unwrap();
+    master.build(storage.path().join("master.cxp")).unwrap();
+
+    // Only the master stays on disk
+    fs::remove_file(&web_path).unwrap();
+    fs::remove_file(&projects_path).unwrap();
+
+    let manager = CxpManager::new(CxpManagerConfig {
+        storage_root: storage.path().to_path_buf(),
+        preload_hot: false,
+        ..CxpManagerConfig::default()
+    });
+    manager.init().unwrap();
+
+    let children = manager.root_children().unwrap();
+    assert_eq!(children.len(), 1);
+    assert!(children[0].is_embedded());
+    assert!(children[0].meta.has_children);
+    assert_eq!(manager.memory_usage().unwrap().cached_cxps, 0);
+
+    let projects = manager.get(&["projects"]).unwrap().unwrap();
+    assert_eq!(projects.list_files(), vec!["README.md"]);
+
+    let web = manager.get(&["projects", "web"]).unwrap().unwrap();
+    assert_eq!(web.list_files(), vec!["index.ts"]);
+    assert_eq!(manager.memory_usage().unwrap().cached_cxps, 2);
+
+    assert!(manager.get(&["projects", "missing"]).unwrap().is_none());
+}
+
+#[test]
+fn test_cxp_manager_recursive_search() {
+    let source = TempDir::new().unwrap();
+    let storage = TempDir::new().unwrap();
+
+    let build = |name: &str, file: &str, content: &str| {
+        let dir = source.path().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(file), content).unwrap();
+        let mut builder = CxpBuilder::new(&dir);
+        builder.scan().unwrap().process().unwrap();
+        builder
+    };
+
+    // master.cxp embeds projects.cxp, which embeds web.cxp
+    let web_path = storage.path().join("web.cxp");
+    build("web", "router.ts", "export const routes = [];").build(&web_path).unwrap();
+    let projects_path = storage.path().join("projects.cxp");
+    let mut projects = build("projects", "README.md", "# Projects");
+    projects.embed_child("web", &web_path).unwrap();
+    projects.build(&projects_path).unwrap();
+    let mut master = build("master", "notes.txt", "master");
+    master.embed_child("projects", &projects_path).unwrap();
+    master.build(storage.path().join("master.cxp")).unwrap();
+
+    // A Cold external child
+    let archive_path = storage.path().join("archive.cxp");
+    build("archive", "router_old.ts", "legacy").build(&archive_path).unwrap();
+
+    let manager = CxpManager::new(CxpManagerConfig {
+        storage_root: storage.path().to_path_buf(),
+        preload_hot: false,
+        ..CxpManagerConfig::default()
+    });
+    manager.init().unwrap();
+    let mut archive_ref = CxpRef::external("archive", "Archive", archive_path);
+    archive_ref.tier = FileTier::Cold;
+    manager.add_root_child(archive_ref).unwrap();
+
+    // A search that times out immediately only returns global index hits
+    let options = SearchOptions::default().with_timeout(std::time::Duration::ZERO);
+    let hits = manager.search_with("router", 10, &options).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].full_path(), "archive/router_old.ts");
+    assert_eq!(manager.memory_usage().unwrap().cached_cxps, 0);
+
+    // The Cold child is found through the global index without being loaded
+    let hits = manager.search("router", 10).unwrap();
+    let paths: Vec<String> = hits.iter().map(|h| h.full_path()).collect();
+    assert_eq!(paths, vec!["projects/web/router.ts", "archive/router_old.ts"]);
+    assert_eq!(hits[0].cxp_path, vec!["projects", "web"]);
+    assert_eq!(hits[1].tier, FileTier::Cold);
+    assert_eq!(manager.memory_usage().unwrap().cached_cxps, 2);
+
+    let hits = manager
+        .search_with("router", 10, &SearchOptions::default().with_load_cold(true))
+        .unwrap();
+    assert_eq!(hits.len(), 2);
+    assert_eq!(manager.memory_usage().unwrap().cached_cxps, 3);
+
+    assert_eq!(manager.search("router", 1).unwrap().len(), 1);
+    assert!(manager.search("nothing-matches", 10).unwrap().is_empty());
+}
+
+#[test]
+fn test_global_index_persisted_in_master() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("workspace");
+    fs::create_dir_all(root.join("app")).unwrap();
+    fs::write(root.join("readme.md"), "# Workspace").unwrap();
+    fs::write(root.join("app/main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("app/lib.rs"), "pub fn lib() {}").unwrap();
+    let output = temp_dir.path().join("output");
+
+    let config = RecursiveBuildConfig {
+        min_size_for_child: u64::MAX,
+        min_files_for_child: 2,
+        output_dir: output.clone(),
+        ..RecursiveBuildConfig::default()
+    };
+    let mut builder = RecursiveBuilder::new(config);
+    let structure = builder.analyze(&root).unwrap();
+    builder.build(&root, &structure, Vec::new()).unwrap();
+    let master_path = builder.build_master("master").unwrap();
+
+    // Child files are indexed with the child CXP only
+    let index = GlobalIndex::read_from(&master_path).unwrap().unwrap();
+    let mut indexed: Vec<String> = index.entries.iter().map(|e| e.display_path()).collect();
+    indexed.sort();
+    assert_eq!(indexed, vec!["workspace/app/lib.rs", "workspace/app/main.rs", "workspace/readme.md"]);
+
+    let manager = CxpManager::new(CxpManagerConfig {
+        storage_root: output.clone(),
+        preload_hot: false,
+        ..CxpManagerConfig::default()
+    });
+    manager.init().unwrap();
+    assert_eq!(manager.global_index().unwrap().entries.len(), 3);
+
+    // Removing a child drops its entries from the persisted index
+    let workspace = manager.remove_root_child("workspace").unwrap().unwrap();
+    assert!(GlobalIndex::read_from(&master_path).unwrap().unwrap().entries.is_empty());
+
+    // Adding it back indexes its archive again
+    manager.add_root_child(workspace).unwrap();
+    let index = GlobalIndex::read_from(&master_path).unwrap().unwrap();
+    assert_eq!(index.entries.len(), 3);
+    assert!(index.entries.iter().all(|e| e.cxp_path == vec!["workspace"]));
+
+    assert_eq!(manager.reindex().unwrap(), 3);
+    let index = CxpManager::reindex_archive(&master_path).unwrap();
+    assert_eq!(index.stats.cxp_count, 1);
+    assert_eq!(GlobalIndex::read_from(&master_path).unwrap().unwrap().entries.len(), 3);
+
+    // The master is still a readable CXP
+    let reader = cxp_core::CxpReader::open(&master_path).unwrap();
+    assert!(reader.child("workspace").is_some());
+    assert_eq!(reader.global_index().unwrap().unwrap().entries.len(), 3);
+}
+
+#[test]
+fn test_cxp_manager_tier_aware_eviction() {
+    let source = TempDir::new().unwrap();
+    let storage = TempDir::new().unwrap();
+
+    let manager = CxpManager::new(CxpManagerConfig {
+        storage_root: storage.path().to_path_buf(),
+        max_cached_cxps: 2,
+        preload_hot: false,
+        ..CxpManagerConfig::default()
+    });
+    manager.init().unwrap();
+
+    // Modification ages keep the tiers stable when get() records an access
+    let tiers = [("hot", FileTier::Hot, 0), ("warm", FileTier::Warm, 20), ("cold", FileTier::Cold, 90), ("cold2", FileTier::Cold, 90)];
+    for (id, tier, age_days) in tiers {
+        let dir = source.path().join(id);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("notes.txt"), id).unwrap();
+        let path = storage.path().join(format!("{}.cxp", id));
+        let mut builder = CxpBuilder::new(&dir);
+        builder.scan().unwrap().process().unwrap();
+        builder.build(&path).unwrap();
+
+        let mut cxp_ref = CxpRef::external(id, id, path);
+        cxp_ref.tier = tier;
+        cxp_ref.meta.updated_at = Utc::now() - Duration::days(age_days);
+        manager.add_root_child(cxp_ref).unwrap();
+    }
+
+    assert_eq!(manager.preload_hot().unwrap(), 1);
+
+    // Loading past the entry budget evicts Warm, never Hot
+    manager.get(&["warm"]).unwrap().unwrap();
+    manager.get(&["cold"]).unwrap().unwrap();
+    let stats = manager.memory_usage().unwrap();
+    assert_eq!((stats.hot_cxps, stats.warm_cxps, stats.cold_cxps), (1, 0, 1));
+
+    // Cold goes before Warm
+    manager.get(&["warm"]).unwrap().unwrap();
+    let stats = manager.memory_usage().unwrap();
+    assert_eq!((stats.hot_cxps, stats.warm_cxps, stats.cold_cxps), (1, 1, 0));
+
+    // Pinned CXPs are kept even if the budget is exceeded
+    assert!(manager.pin("warm").unwrap());
+    assert!(manager.is_pinned("warm").unwrap());
+    manager.get(&["cold"]).unwrap().unwrap();
+    assert_eq!(manager.memory_usage().unwrap().cached_cxps, 3);
+
+    assert!(manager.unpin("warm").unwrap());
+    assert!(!manager.unpin("warm").unwrap());
+    manager.get(&["cold2"]).unwrap().unwrap();
+    let stats = manager.memory_usage().unwrap();
+    assert_eq!((stats.hot_cxps, stats.warm_cxps, stats.cold_cxps), (1, 0, 1));
+
+    assert!(!manager.pin("missing").unwrap());
+}
+
+#[test]
+fn test_tier_scheduler_demotes_stale_cxps() {
+    let source = TempDir::new().unwrap();
+    let storage = TempDir::new().unwrap();
+    fs::write(source.path().join("notes.txt"), "old notes").unwrap();
+    let path = storage.path().join("old.cxp");
+    let mut builder = CxpBuilder::new(source.path());
+    builder.scan().unwrap().process().unwrap();
+    builder.build(&path).unwrap();
+
+    let manager = std::sync::Arc::new(CxpManager::new(CxpManagerConfig {
+        storage_root: storage.path().to_path_buf(),
+        preload_hot: false,
+        ..CxpManagerConfig::default()
+    }));
+    manager.init().unwrap();
+
+    let mut old_ref = CxpRef::external("old", "Old", path);
+    old_ref.tier = FileTier::Hot;
+    old_ref.meta.updated_at = Utc::now() - Duration::days(90);
+    manager.add_root_child(old_ref).unwrap();
+    assert_eq!(manager.preload_hot().unwrap(), 1);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = TierScheduler::new(manager.clone())
+        .with_interval(std::time::Duration::from_millis(50))
+        .spawn(move |change| tx.send(change.clone()).unwrap());
+
+    let change = rx.recv_timeout(std::time::Duration::from_secs(10)).unwrap();
+    assert_eq!(change.id, "old");
+    assert_eq!((change.from, change.to), (FileTier::Hot, FileTier::Cold));
+
+    let scheduler = handle.stop().unwrap();
+    assert!(scheduler.run_once().unwrap().is_empty());
+    assert!(rx.try_recv().is_err());
+
+    assert_eq!(manager.root_children().unwrap()[0].tier, FileTier::Cold);
+    let stats = manager.memory_usage().unwrap();
+    assert_eq!((stats.hot_cxps, stats.cold_cxps), (0, 1));
+    let index = manager.global_index().unwrap();
+    assert_eq!(index.entries_by_tier(FileTier::Cold).len(), 1);
+    assert!(index.entries_by_tier(FileTier::Hot).is_empty());
+}
+
+#[test]
+fn test_cxp_manager_access_persists_across_restarts() {
+    let source = TempDir::new().unwrap();
+    let storage = TempDir::new().unwrap();
+
+    let projects_dir = source.path().join("projects");
+    fs::create_dir_all(&projects_dir).unwrap();
+    fs::write(projects_dir.join("README.md"), "# Projects").unwrap();
+    let projects_path = source.path().join("projects.cxp");
+    let mut projects = CxpBuilder::new(&projects_dir);
+    projects.scan().unwrap().process().unwrap();
+    projects.build(&projects_path).unwrap();
+
+    let mut master = CxpBuilder::new(source.path().join("projects"));
+    master.embed_child("projects", &projects_path).unwrap();
+    master.build(storage.path().join("master.cxp")).unwrap();
+
+    let config = CxpManagerConfig {
+        storage_root: storage.path().to_path_buf(),
+        preload_hot: false,
+        ..CxpManagerConfig::default()
+    };
+
+    let manager = CxpManager::new(config.clone());
+    manager.init().unwrap();
+    assert!(manager.root_children().unwrap()[0].last_accessed.is_none());
+    manager.get(&["projects"]).unwrap().unwrap();
+    assert_eq!(manager.access_log().read().unwrap().len(), 1);
+    drop(manager);
+
+    // A fresh manager restores the access time and the resulting tier
+    let manager = CxpManager::new(config);
+    manager.init().unwrap();
+    let projects_ref = &manager.root_children().unwrap()[0];
+    assert!(projects_ref.last_accessed.is_some());
+    assert_eq!(projects_ref.tier, FileTier::Hot);
+}
@@ -14,9 +14,8 @@
 //! Run with `cargo test --test token_savings_benchmark -- --nocapture`
 //! to see detailed benchmark results with visual charts.
 
-use cxp_core::{CxpBuilder, CxpReader, Result, estimate_tokens, calculate_savings};
-use std::fs::{self, File};
-use std::io::Write;
+use cxp_core::{CxpBuilder, CxpReader, Result, calculate_savings};
+use std::fs;
 use std::path::PathBuf;
 use tempfile::TempDir;
 
@@ -455,8 +454,8 @@ fn print_benchmark_results(
 fn test_scenario_1_small_project() -> Result<()> {
     // Small Project: 10MB, ~50 files
     // Mix of Rust, TypeScript, Python, and Markdown
-    let test_dir = TempDir::new().map_err(|e| cxp_core::CxpError::Io(e.to_string()))?;
-    let output_dir = TempDir::new().map_err(|e| cxp_core::CxpError::Io(e.to_string()))?;
+    let test_dir = TempDir::new().map_err(|e| cxp_core::CxpError::io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| cxp_core::CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("small_project.cxp");
 
     // Create test corpus - varied to reach ~10MB
@@ -506,8 +505,8 @@ fn test_scenario_1_small_project() -> Result<()> {
 fn test_scenario_2_medium_project() -> Result<()> {
     // Medium Project: 100MB, ~500 files
     // Realistic codebase with multiple modules
-    let test_dir = TempDir::new().map_err(|e| cxp_core::CxpError::Io(e.to_string()))?;
-    let output_dir = TempDir::new().map_err(|e| cxp_core::CxpError::Io(e.to_string()))?;
+    let test_dir = TempDir::new().map_err(|e| cxp_core::CxpError::io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| cxp_core::CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("medium_project.cxp");
 
     // Create larger corpus
@@ -551,8 +550,8 @@ fn test_scenario_2_medium_project() -> Result<()> {
 fn test_scenario_3_large_project() -> Result<()> {
     // Large Project: 500MB+, ~2000 files
     // Enterprise scale with significant repetition
-    let test_dir = TempDir::new().map_err(|e| cxp_core::CxpError::Io(e.to_string()))?;
-    let output_dir = TempDir::new().map_err(|e| cxp_core::CxpError::Io(e.to_string()))?;
+    let test_dir = TempDir::new().map_err(|e| cxp_core::CxpError::io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| cxp_core::CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("large_project.cxp");
 
     // Create very large corpus
@@ -596,8 +595,8 @@ fn test_scenario_3_large_project() -> Result<()> {
 fn test_scenario_4_high_deduplication() -> Result<()> {
     // High Deduplication: Files with lots of repetition
     // This should achieve the highest savings (90%+)
-    let test_dir = TempDir::new().map_err(|e| cxp_core::CxpError::Io(e.to_string()))?;
-    let output_dir = TempDir::new().map_err(|e| cxp_core::CxpError::Io(e.to_string()))?;
+    let test_dir = TempDir::new().map_err(|e| cxp_core::CxpError::io(e.to_string()))?;
+    let output_dir = TempDir::new().map_err(|e| cxp_core::CxpError::io(e.to_string()))?;
     let output_path = output_dir.path().join("high_dedup.cxp");
 
     // Create corpus with NO variation (maximum deduplication)
@@ -0,0 +1,129 @@
+//! Zip64 tests: archives past the classic ZIP limits
+//!
+//! The >4 GB test builds an archive from about 4 GB of source data in the
+//! temp directory and is ignored by default:
+//! ```bash
+//! cargo test --release --test zip64_test -- --ignored
+//! ```
+
+use cxp_core::format_spec::check_file;
+use cxp_core::{ChunkingAlgorithm, CxpBuilder, CxpError, CxpReader, Result};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use tempfile::TempDir;
+use zip::ZipArchive;
+
+/// Signature of the Zip64 end of central directory record
+const ZIP64_EOCD_SIGNATURE: &[u8] = b"PK\x06\x06";
+
+/// Whether the archive ends with Zip64 end of central directory records
+fn has_zip64_eocd(path: &Path) -> Result<bool> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(1024)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    Ok(tail.windows(4).any(|w| w == ZIP64_EOCD_SIGNATURE))
+}
+
+/// `count` distinct 64-byte blocks
+fn distinct_blocks(start: usize, count: usize) -> Vec<u8> {
+    (start..start + count)
+        .flat_map(|i| format!("{:063}\n", i).into_bytes())
+        .collect()
+}
+
+#[test]
+fn test_more_than_65535_chunk_entries() -> Result<()> {
+    const FILES: usize = 8;
+    const BLOCKS_PER_FILE: usize = 8_500;
+
+    let source = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    for i in 0..FILES {
+        fs::write(source.path().join(format!("part{}.txt", i)), distinct_blocks(i * BLOCKS_PER_FILE, BLOCKS_PER_FILE))?;
+    }
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("many.cxp");
+
+    // One entry per chunk (packs would hold them all in a single entry)
+    let mut builder = CxpBuilder::new(source.path());
+    builder
+        .with_chunking(ChunkingAlgorithm::Fixed { size: 64 })
+        .with_packed_chunks(false)
+        .scan()?
+        .process()?
+        .build(&cxp_path)?;
+
+    assert!(has_zip64_eocd(&cxp_path)?);
+    let archive = ZipArchive::new(File::open(&cxp_path)?)?;
+    assert!(archive.len() > u16::MAX as usize);
+
+    let reader = CxpReader::open(&cxp_path)?;
+    assert_eq!(reader.manifest().stats.unique_chunks, FILES * BLOCKS_PER_FILE);
+    assert_eq!(reader.chunks()?.count(), FILES * BLOCKS_PER_FILE);
+    assert_eq!(reader.read_file("part7.txt")?, distinct_blocks(7 * BLOCKS_PER_FILE, BLOCKS_PER_FILE));
+    let report = check_file(&cxp_path)?;
+    assert!(report.failures().is_empty(), "{:?}", report.failures());
+
+    Ok(())
+}
+
+/// Write `len` bytes of incompressible pseudo-random data to `path`
+fn write_random(path: &Path, len: u64) -> Result<()> {
+    let mut file = std::io::BufWriter::new(File::create(path)?);
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    for _ in 0..len / 8 {
+        // splitmix64
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut x = state;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        file.write_all(&(x ^ (x >> 31)).to_le_bytes())?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+#[test]
+#[ignore = "writes a 4 GB archive and needs about 16 GB of memory; run with --ignored"]
+fn test_archive_larger_than_4gb() -> Result<()> {
+    const LARGE: u64 = (4 << 30) + (1 << 20);
+    const LARGE_PATH: &str = "large.txt";
+
+    let source = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    fs::write(source.path().join("main.rs"), "fn main() {}\n")?;
+    write_random(&source.path().join(LARGE_PATH), LARGE)?;
+    let output_dir = TempDir::new().map_err(|e| CxpError::io(e.to_string()))?;
+    let cxp_path = output_dir.path().join("large.cxp");
+
+    // The large file is one incompressible chunk, so its pack is written as
+    // a >4 GB Zip64 entry and everything after it starts past 4 GB
+    let mut builder = CxpBuilder::new(source.path());
+    builder.with_chunking(ChunkingAlgorithm::Fixed { size: LARGE as usize });
+    #[cfg(feature = "redact")]
+    builder.with_redaction(false);
+    builder.scan()?.process()?.build(&cxp_path)?;
+
+    assert!(fs::metadata(&cxp_path)?.len() > LARGE);
+    assert!(has_zip64_eocd(&cxp_path)?);
+    let mut archive = ZipArchive::new(File::open(&cxp_path)?)?;
+    let large_entries = (0..archive.len())
+        .filter(|&i| archive.by_index_raw(i).is_ok_and(|entry| entry.size() > u32::MAX as u64))
+        .count();
+    assert_eq!(large_entries, 1);
+
+    let reader = CxpReader::open(&cxp_path)?;
+    assert_eq!(reader.file_paths(), vec![LARGE_PATH, "main.rs"]);
+    assert_eq!(reader.read_file("main.rs")?, b"fn main() {}\n");
+    let large = reader.read_file(LARGE_PATH)?;
+    assert_eq!(large.len() as u64, LARGE);
+    let mut expected = Vec::new();
+    File::open(source.path().join(LARGE_PATH))?.take(1 << 20).read_to_end(&mut expected)?;
+    assert_eq!(&large[..1 << 20], &expected[..]);
+    drop(large);
+    let report = check_file(&cxp_path)?;
+    assert!(report.failures().is_empty(), "{:?}", report.failures());
+
+    Ok(())
+}
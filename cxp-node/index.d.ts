@@ -0,0 +1,73 @@
+/* Type declarations for the cxp-node N-API module (src/lib.rs) */
+
+/** Options for `build()` and `search()` */
+export interface CxpOptions {
+  /** Generate embeddings / use semantic search when a model is available (default: true) */
+  embeddings?: boolean
+  /** Embedding model directory (falls back to `CXP_MODEL_DIR`) */
+  modelDir?: string
+  /** Files per file map shard */
+  shardSize?: number
+  /** Record build telemetry in the archive (default: true) */
+  buildInfo?: boolean
+}
+
+/** Summary of a written archive */
+export interface BuildResult {
+  /** Number of archived files */
+  totalFiles: number
+  /** Number of unique chunks */
+  uniqueChunks: number
+  /** Size of the source files in bytes */
+  originalSizeBytes: number
+  /** Embedding model used, if embeddings were generated */
+  embeddingModel?: string
+}
+
+/** A search result with the matching chunk's text */
+export interface SearchHit {
+  /** File path inside the archive */
+  filePath: string
+  /** Chunk index within the file */
+  chunkIndex: number
+  /** Relevance score (higher is better) */
+  score: number
+  /** Text of the matching chunk */
+  text: string
+}
+
+/** Aborts a running `build()` */
+export class CancellationToken {
+  /** Create a token that is not cancelled */
+  constructor()
+  /** Request cancellation; the build stops at the next file, batch or chunk */
+  cancel(): void
+  /** Whether cancellation was requested */
+  get isCancelled(): boolean
+}
+
+/**
+ * Archive a directory; resolves with a summary of the written archive
+ *
+ * Rejects with a `Cancelled` error if `token` is cancelled first.
+ */
+export function build(
+  dir: string,
+  out: string,
+  options?: CxpOptions | undefined | null,
+  token?: CancellationToken | undefined | null,
+): Promise<BuildResult>
+
+/** Read a file from an archive; resolves with its content */
+export function readFile(archive: string, path: string): Promise<Buffer>
+
+/** Search an archive; resolves with up to `topK` hits (default 10) */
+export function search(
+  archive: string,
+  query: string,
+  topK?: number | undefined | null,
+  options?: CxpOptions | undefined | null,
+): Promise<SearchHit[]>
+
+/** Version of the bindings */
+export function version(): string
@@ -0,0 +1,24 @@
+// Loads the native module built by `napi build --platform` (cxp.<platform>-<arch>[-<abi>].node)
+// or by `napi build` without --platform (cxp.node).
+const { existsSync } = require('fs')
+const { join } = require('path')
+
+function abi() {
+  if (process.platform !== 'linux') return ''
+  const report = process.report && process.report.getReport()
+  const glibc = report && report.header && report.header.glibcVersionRuntime
+  return glibc ? '-gnu' : '-musl'
+}
+
+const candidates = [
+  `cxp.${process.platform}-${process.arch}${abi()}.node`,
+  `cxp.${process.platform}-${process.arch}${process.platform === 'win32' ? '-msvc' : ''}.node`,
+  'cxp.node',
+]
+
+const file = candidates.find((name) => existsSync(join(__dirname, name)))
+if (!file) {
+  throw new Error(`cxp-node: no native module for ${process.platform}-${process.arch}, run \`npm run build\``)
+}
+
+module.exports = require(join(__dirname, file))
@@ -0,0 +1,242 @@
+//! CXP Node.js Bindings
+//!
+//! N-API module for Electron apps and VS Code extensions. Every call runs on
+//! the libuv thread pool and returns a Promise, so the event loop is never
+//! blocked by archive I/O or embedding inference.
+//!
+//! ```js
+//! const cxp = require('cxp-node');
+//!
+//! const stats = await cxp.build('./project', 'project.cxp', { embeddings: false });
+//! const source = await cxp.readFile('project.cxp', 'src/main.rs');
+//! for (const hit of await cxp.search('project.cxp', 'retry logic', 5)) {
+//!   console.log(hit.filePath, hit.score);
+//! }
+//! ```
+//!
+//! Builds can be aborted with a `CancellationToken`; the Promise then rejects
+//! and no partial archive is left behind:
+//!
+//! ```js
+//! const token = new cxp.CancellationToken();
+//! const pending = cxp.build('./project', 'project.cxp', {}, token);
+//! token.cancel();
+//! ```
+//!
+//! Semantic search is used when the archive has embeddings and a model
+//! directory is given (or `CXP_MODEL_DIR` is set); otherwise search falls
+//! back to keywords.
+
+#![deny(clippy::all)]
+
+use napi::bindgen_prelude::{AsyncTask, Buffer};
+use napi::{Env, Error, Result, Task};
+use napi_derive::napi;
+
+use cxp_core::quick::{self, Options};
+use cxp_core::CxpReader;
+
+/// Options for `build()` and `search()`
+#[napi(object)]
+#[derive(Default)]
+pub struct CxpOptions {
+    /// Generate embeddings / use semantic search when a model is available (default: true)
+    pub embeddings: Option<bool>,
+    /// Embedding model directory (falls back to `CXP_MODEL_DIR`)
+    pub model_dir: Option<String>,
+    /// Files per file map shard
+    pub shard_size: Option<u32>,
+    /// Record build telemetry in the archive (default: true)
+    pub build_info: Option<bool>,
+}
+
+impl CxpOptions {
+    fn to_quick(&self) -> Options {
+        let mut options = Options::new();
+        if let Some(embeddings) = self.embeddings {
+            options = options.with_embeddings(embeddings);
+        }
+        if let Some(model_dir) = &self.model_dir {
+            options = options.with_model_dir(model_dir);
+        }
+        if let Some(shard_size) = self.shard_size {
+            options = options.with_shard_size(shard_size as usize);
+        }
+        if let Some(build_info) = self.build_info {
+            options = options.with_build_info(build_info);
+        }
+        options
+    }
+}
+
+/// Summary of a written archive
+#[napi(object)]
+pub struct BuildResult {
+    /// Number of archived files
+    pub total_files: u32,
+    /// Number of unique chunks
+    pub unique_chunks: u32,
+    /// Size of the source files in bytes
+    pub original_size_bytes: f64,
+    /// Embedding model used, if embeddings were generated
+    pub embedding_model: Option<String>,
+}
+
+/// A search result with the matching chunk's text
+#[napi(object)]
+pub struct SearchHit {
+    /// File path inside the archive
+    pub file_path: String,
+    /// Chunk index within the file
+    pub chunk_index: u32,
+    /// Relevance score (higher is better)
+    pub score: f64,
+    /// Text of the matching chunk
+    pub text: String,
+}
+
+fn to_napi(e: cxp_core::CxpError) -> Error {
+    Error::from_reason(e.full_message())
+}
+
+/// JS handle to a [`cxp_core::CancellationToken`] that aborts a running `build()`
+#[napi(js_name = "CancellationToken")]
+#[derive(Default)]
+pub struct JsCancellationToken {
+    inner: cxp_core::CancellationToken,
+}
+
+#[napi]
+impl JsCancellationToken {
+    /// Create a token that is not cancelled
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation; the build stops at the next file, batch or chunk
+    #[napi]
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    /// Whether cancellation was requested
+    #[napi(getter)]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+}
+
+/// Background task behind `build()`
+pub struct BuildTask {
+    dir: String,
+    out: String,
+    options: Options,
+}
+
+impl Task for BuildTask {
+    type Output = cxp_core::Manifest;
+    type JsValue = BuildResult;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        quick::build(&self.dir, &self.out, self.options.clone()).map_err(to_napi)
+    }
+
+    fn resolve(&mut self, _env: Env, manifest: Self::Output) -> Result<Self::JsValue> {
+        Ok(BuildResult {
+            total_files: manifest.stats.total_files as u32,
+            unique_chunks: manifest.stats.unique_chunks as u32,
+            original_size_bytes: manifest.stats.original_size_bytes as f64,
+            embedding_model: manifest.embedding_model,
+        })
+    }
+}
+
+/// Background task behind `readFile()`
+pub struct ReadFileTask {
+    archive: String,
+    path: String,
+}
+
+impl Task for ReadFileTask {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let reader = CxpReader::open(&self.archive).map_err(to_napi)?;
+        reader.read_file(&self.path).map_err(to_napi)
+    }
+
+    fn resolve(&mut self, _env: Env, data: Self::Output) -> Result<Self::JsValue> {
+        Ok(data.into())
+    }
+}
+
+/// Background task behind `search()`
+pub struct SearchTask {
+    archive: String,
+    query: String,
+    top_k: usize,
+    options: Options,
+}
+
+impl Task for SearchTask {
+    type Output = Vec<quick::Hit>;
+    type JsValue = Vec<SearchHit>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        quick::search_with(&self.archive, &self.query, self.top_k, &self.options).map_err(to_napi)
+    }
+
+    fn resolve(&mut self, _env: Env, hits: Self::Output) -> Result<Self::JsValue> {
+        Ok(hits
+            .into_iter()
+            .map(|hit| SearchHit {
+                file_path: hit.file_path,
+                chunk_index: hit.chunk_index as u32,
+                score: hit.score as f64,
+                text: hit.text,
+            })
+            .collect())
+    }
+}
+
+/// Archive a directory; resolves with a summary of the written archive
+///
+/// Rejects with a `Cancelled` error if `token` is cancelled first.
+#[napi(ts_return_type = "Promise<BuildResult>")]
+pub fn build(
+    dir: String,
+    out: String,
+    options: Option<CxpOptions>,
+    token: Option<&JsCancellationToken>,
+) -> AsyncTask<BuildTask> {
+    let mut options = options.unwrap_or_default().to_quick();
+    if let Some(token) = token {
+        options = options.with_cancellation(token.inner.clone());
+    }
+    AsyncTask::new(BuildTask { dir, out, options })
+}
+
+/// Read a file from an archive; resolves with its content
+#[napi(ts_return_type = "Promise<Buffer>")]
+pub fn read_file(archive: String, path: String) -> AsyncTask<ReadFileTask> {
+    AsyncTask::new(ReadFileTask { archive, path })
+}
+
+/// Search an archive; resolves with up to `topK` hits (default 10)
+#[napi(ts_return_type = "Promise<SearchHit[]>")]
+pub fn search(archive: String, query: String, top_k: Option<u32>, options: Option<CxpOptions>) -> AsyncTask<SearchTask> {
+    AsyncTask::new(SearchTask {
+        archive,
+        query,
+        top_k: top_k.unwrap_or(10) as usize,
+        options: options.unwrap_or_default().to_quick(),
+    })
+}
+
+/// Version of the bindings
+#[napi]
+pub fn version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}
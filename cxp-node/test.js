@@ -0,0 +1,36 @@
+// Smoke test: node test.js (after `npm run build:debug`)
+const assert = require('assert')
+const fs = require('fs')
+const os = require('os')
+const path = require('path')
+const cxp = require('.')
+
+;(async () => {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'cxp-node-'))
+  fs.mkdirSync(path.join(dir, 'src'))
+  fs.writeFileSync(path.join(dir, 'src', 'client.rs'), 'fn fetch() { retry(3); }\n')
+  const archive = path.join(dir, 'test.cxp')
+
+  const result = await cxp.build(path.join(dir, 'src'), archive, { embeddings: false, buildInfo: false })
+  assert.strictEqual(result.totalFiles, 1)
+
+  const content = await cxp.readFile(archive, 'client.rs')
+  assert.strictEqual(content.toString(), 'fn fetch() { retry(3); }\n')
+
+  const hits = await cxp.search(archive, 'retry', 5, { embeddings: false })
+  assert.strictEqual(hits[0].filePath, 'client.rs')
+
+  await assert.rejects(cxp.readFile(archive, 'missing.rs'), /missing\.rs/)
+
+  const token = new cxp.CancellationToken()
+  token.cancel()
+  const cancelled = path.join(dir, 'cancelled.cxp')
+  await assert.rejects(cxp.build(path.join(dir, 'src'), cancelled, { embeddings: false }, token), /[Cc]ancelled/)
+  assert.ok(!fs.existsSync(cancelled))
+
+  fs.rmSync(dir, { recursive: true })
+  console.log('ok')
+})().catch((e) => {
+  console.error(e)
+  process.exit(1)
+})